@@ -654,6 +654,19 @@ fn test_detect_static_or_dynamic_property() {
     );
 }
 
+#[test]
+fn test_dynamic_css_property_calc_default() {
+    use azul_css::{CssProperty, LayoutWidth, PixelValue};
+    assert_eq!(
+        determine_static_or_dynamic_css_property(CssPropertyType::Width, "[[ content_width | calc(100% - 48px) ]]"),
+        Ok(CssDeclaration::Dynamic(DynamicCssProperty {
+            property_type: CssPropertyType::Width,
+            dynamic_id: "content_width".to_string(),
+            default: DynamicCssPropertyDefault::Exact(CssProperty::Width(LayoutWidth(PixelValue::calc(-48.0, 0.0, 100.0)))),
+        }))
+    );
+}
+
 #[test]
 fn test_css_parse_1() {
 
@@ -14,9 +14,13 @@ use azul_css::{
     GradientStopPre, RadialGradient, StyleBackgroundColor, StyleBackgroundSize, StyleBackgroundRepeat,
     DirectionCorner, StyleBorder, Direction, CssImageId, LinearGradient,
     BoxShadowPreDisplayItem, BorderStyle, LayoutPadding, StyleBorderSide, BorderRadius, PixelSize,
-    BackgroundType,
+    BackgroundType, StyleOutline, StyleTransform, StyleTransformFunction,
+    StyleTransformOrigin, TransformOriginOffset, StyleZIndex,
+    StyleBorderImage, BorderImageSlice, BorderImageRepeat,
+    LayoutIntrinsicSize, LayoutAspectRatio, GridTrackSize, GridPlacement, LayoutGap,
+    LayoutGridTemplateColumns, LayoutGridTemplateRows, LayoutGridColumn, LayoutGridRow,
 
-    SizeMetric, BoxShadowClipMode, ExtendMode, FontId,
+    SizeMetric, BoxShadowClipMode, ExtendMode, FontId, PT_TO_PX,
 };
 
 /// A parser that can accept a list of items and mappings
@@ -45,6 +49,17 @@ macro_rules! typed_pixel_value_parser {
     )
 }
 
+/// Same as `typed_pixel_value_parser`, but also accepts percentages and `calc()`
+macro_rules! typed_length_value_parser {
+    ($fn:ident, $return:ident) => (
+        pub fn $fn<'a>(input: &'a str)
+        -> Result<$return, PixelParseError<'a>>
+        {
+            parse_length_value(input).and_then(|e| Ok($return(e)))
+        }
+    )
+}
+
 /// Main parsing function, takes a stringified key / value pair and either
 /// returns the parsed value or an error
 ///
@@ -79,13 +94,28 @@ pub fn parse_key_value_pair<'a>(key: CssPropertyType, value: &'a str) -> Result<
         BorderBottom     => Ok(border_parser::parse_bottom(value)?.into()),
         BorderLeft       => Ok(border_parser::parse_left(value)?.into()),
         BorderRight      => Ok(border_parser::parse_right(value)?.into()),
+        BorderImage      => Ok(parse_style_border_image(value)?.into()),
+
+        Outline          => Ok(StyleOutline(parse_css_border(value)?).into()),
+
+        Transform        => Ok(parse_style_transform(value)?.into()),
+        TransformOrigin  => Ok(parse_style_transform_origin(value)?.into()),
 
-        Width            => Ok(parse_layout_width(value)?.into()),
-        Height           => Ok(parse_layout_height(value)?.into()),
+        ZIndex           => Ok(parse_style_z_index(value)?.into()),
+
+        Width            => match parse_layout_intrinsic_size(value) {
+            Ok(intrinsic_width) => Ok(CssProperty::IntrinsicWidth(intrinsic_width)),
+            Err(_) => Ok(parse_layout_width(value)?.into()),
+        },
+        Height           => match parse_layout_intrinsic_size(value) {
+            Ok(intrinsic_height) => Ok(CssProperty::IntrinsicHeight(intrinsic_height)),
+            Err(_) => Ok(parse_layout_height(value)?.into()),
+        },
         MinWidth         => Ok(parse_layout_min_width(value)?.into()),
         MinHeight        => Ok(parse_layout_min_height(value)?.into()),
         MaxWidth         => Ok(parse_layout_max_width(value)?.into()),
         MaxHeight        => Ok(parse_layout_max_height(value)?.into()),
+        AspectRatio      => Ok(parse_layout_aspect_ratio(value)?.into()),
 
         Position         => Ok(parse_layout_position(value)?.into()),
         Top              => Ok(parse_layout_top(value)?.into()),
@@ -121,6 +151,12 @@ pub fn parse_key_value_pair<'a>(key: CssPropertyType, value: &'a str) -> Result<
         AlignItems       => Ok(parse_layout_align_items(value)?.into()),
         AlignContent     => Ok(parse_layout_align_content(value)?.into()),
 
+        GridTemplateColumns => Ok(LayoutGridTemplateColumns(parse_grid_track_list(value)?).into()),
+        GridTemplateRows => Ok(LayoutGridTemplateRows(parse_grid_track_list(value)?).into()),
+        GridColumn       => Ok(LayoutGridColumn(parse_grid_placement(value)?).into()),
+        GridRow          => Ok(LayoutGridRow(parse_grid_placement(value)?).into()),
+        Gap              => Ok(parse_layout_gap(value)?.into()),
+
         Overflow         => {
             let overflow_both_directions = parse_layout_text_overflow(value)?;
             Ok(LayoutOverflow {
@@ -164,6 +200,11 @@ pub enum CssParsingError<'a> {
     MarginParseError(LayoutMarginParseError<'a>),
     FlexShrinkParseError(FlexShrinkParseError<'a>),
     FlexGrowParseError(FlexGrowParseError<'a>),
+    AspectRatioParseError(AspectRatioParseError<'a>),
+    GridParseError(LayoutGridParseError<'a>),
+    CssStyleTransformParseError(CssStyleTransformParseError<'a>),
+    CssStyleTransformOriginParseError(CssStyleTransformOriginParseError<'a>),
+    CssStyleBorderImageParseError(CssStyleBorderImageParseError<'a>),
 }
 
 impl_display!{ CssParsingError<'a>, {
@@ -181,6 +222,11 @@ impl_display!{ CssParsingError<'a>, {
     MarginParseError(e) => format!("{}", e),
     FlexShrinkParseError(e) => format!("{}", e),
     FlexGrowParseError(e) => format!("{}", e),
+    AspectRatioParseError(e) => format!("{}", e),
+    GridParseError(e) => format!("{}", e),
+    CssStyleTransformParseError(e) => format!("Invalid transform: {}", e),
+    CssStyleTransformOriginParseError(e) => format!("Invalid transform-origin: {}", e),
+    CssStyleBorderImageParseError(e) => format!("Invalid border-image: {}", e),
 }}
 
 impl_from!(CssBorderParseError<'a>, CssParsingError::CssBorderParseError);
@@ -196,6 +242,11 @@ impl_from!(LayoutPaddingParseError<'a>, CssParsingError::PaddingParseError);
 impl_from!(LayoutMarginParseError<'a>, CssParsingError::MarginParseError);
 impl_from!(FlexShrinkParseError<'a>, CssParsingError::FlexShrinkParseError);
 impl_from!(FlexGrowParseError<'a>, CssParsingError::FlexGrowParseError);
+impl_from!(AspectRatioParseError<'a>, CssParsingError::AspectRatioParseError);
+impl_from!(LayoutGridParseError<'a>, CssParsingError::GridParseError);
+impl_from!(CssStyleTransformParseError<'a>, CssParsingError::CssStyleTransformParseError);
+impl_from!(CssStyleTransformOriginParseError<'a>, CssParsingError::CssStyleTransformOriginParseError);
+impl_from!(CssStyleBorderImageParseError<'a>, CssParsingError::CssStyleBorderImageParseError);
 
 impl<'a> From<PercentageParseError> for CssParsingError<'a> {
     fn from(e: PercentageParseError) -> Self {
@@ -408,13 +459,17 @@ pub fn parse_style_border_radius<'a>(input: &'a str)
 pub enum PixelParseError<'a> {
     InvalidComponent(&'a str),
     ValueParseErr(ParseFloatError),
+    CalcParseError(CalcParseError<'a>),
 }
 
 impl_display!{ PixelParseError<'a>, {
     InvalidComponent(component) => format!("Invalid component: \"{}\"", component),
     ValueParseErr(e) => format!("Unexpected value: \"{}\"", e),
+    CalcParseError(e) => format!("Invalid calc() expression: {}", e),
 }}
 
+impl_from!(CalcParseError<'a>, PixelParseError::CalcParseError);
+
 /// parse a single value such as "15px"
 pub fn parse_pixel_value<'a>(input: &'a str)
 -> Result<PixelValue, PixelParseError<'a>>
@@ -441,6 +496,242 @@ pub fn parse_pixel_value<'a>(input: &'a str)
     Ok(PixelValue::from_metric(unit, number))
 }
 
+/// Parse a length of a layout property: a pixel value ("15px"), a
+/// percentage of the containing block ("50%") or a `calc()` expression
+pub fn parse_length_value<'a>(input: &'a str)
+-> Result<PixelValue, PixelParseError<'a>>
+{
+    let input = input.trim();
+
+    if input.starts_with("calc(") {
+        return Ok(parse_calc_expression(input)?);
+    }
+
+    if input.ends_with('%') {
+        let number = input[..input.len() - 1].trim().parse::<f32>().map_err(|e| PixelParseError::ValueParseErr(e))?;
+        return Ok(PixelValue::percent(number));
+    }
+
+    parse_pixel_value(input)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CalcParseError<'a> {
+    /// A `(` without a matching `)` or vice versa
+    UnbalancedBraces(&'a str),
+    /// The expression ended where a value was expected
+    UnexpectedEnd,
+    UnexpectedToken(&'a str),
+    /// A value with an unknown unit, i.e. `10vw`
+    InvalidUnit(&'a str),
+    /// Two lengths multiplied with each other, i.e. `10px * 10px`
+    InvalidMultiplication,
+    /// Division by a length or by zero
+    InvalidDivision,
+    /// Number and length added together, i.e. `10px + 2`
+    MixedNumberAndLength,
+    /// The expression evaluates to a plain number instead of a length
+    NotALength,
+}
+
+impl_display!{ CalcParseError<'a>, {
+    UnbalancedBraces(e) => format!("Unbalanced braces: \"{}\"", e),
+    UnexpectedEnd => format!("Unexpected end of expression"),
+    UnexpectedToken(e) => format!("Unexpected token: \"{}\"", e),
+    InvalidUnit(e) => format!("Invalid unit: \"{}\", expected px, pt, em or %", e),
+    InvalidMultiplication => format!("At least one side of a multiplication has to be a number"),
+    InvalidDivision => format!("Can only divide by a number that is not zero"),
+    MixedNumberAndLength => format!("Can't add or subtract a number and a length"),
+    NotALength => format!("Expression has no unit"),
+}}
+
+/// Intermediate value of a `calc()` expression: either a plain number
+/// (stored in `px`) or a length of the form `px + em + percent%`
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct CalcTerm {
+    px: f32,
+    em: f32,
+    percent: f32,
+    is_number: bool,
+}
+
+impl CalcTerm {
+
+    fn scale(self, factor: f32) -> Self {
+        CalcTerm { px: self.px * factor, em: self.em * factor, percent: self.percent * factor, .. self }
+    }
+
+    fn add<'a>(self, other: Self, sign: f32) -> Result<Self, CalcParseError<'a>> {
+        if self.is_number != other.is_number {
+            return Err(CalcParseError::MixedNumberAndLength);
+        }
+        Ok(CalcTerm {
+            px: self.px + sign * other.px,
+            em: self.em + sign * other.em,
+            percent: self.percent + sign * other.percent,
+            .. self
+        })
+    }
+}
+
+/// Splits a `calc()` expression into numbers (including their unit), operators and braces.
+/// A `-` is a sign (part of the number) if it doesn't follow a value or a closing brace,
+/// so both `100% - 48px` and `100%-48px` are accepted.
+fn tokenize_calc_expression<'a>(input: &'a str) -> Result<Vec<&'a str>, CalcParseError<'a>> {
+
+    let mut tokens = Vec::<&'a str>::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((start, ch)) = chars.next() {
+
+        let follows_value = tokens.last().map(|t| *t == ")" || !"+-*/(".contains(*t)).unwrap_or(false);
+
+        let is_value_start = ch.is_ascii_digit() || ch == '.' ||
+            (ch == '-' && !follows_value) ||
+            ch.is_alphabetic();
+
+        if ch.is_whitespace() {
+            continue;
+        } else if is_value_start {
+            // number + unit or a function name (only `calc`, which is followed by a brace)
+            let mut end = start + ch.len_utf8();
+            while let Some((idx, next)) = chars.peek().cloned() {
+                if next.is_alphanumeric() || next == '.' || next == '%' {
+                    end = idx + next.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(&input[start..end]);
+        } else if "+-*/()".contains(ch) {
+            tokens.push(&input[start..start + ch.len_utf8()]);
+        } else {
+            return Err(CalcParseError::UnexpectedToken(&input[start..start + ch.len_utf8()]));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parses a `calc()` expression such as `calc(100% - 48px)` or `calc((100% - 2 * 10px) / 3)`
+///
+/// Supports `+`, `-`, `*` and `/` on px, pt, em and % values and plain numbers, nested
+/// braces and nested `calc()`. Since the result is always a linear combination of the
+/// units, it is normalized to `px + em + percent%` (see `PixelValue::calc`).
+pub fn parse_calc_expression<'a>(input: &'a str)
+-> Result<PixelValue, CalcParseError<'a>>
+{
+    // sum := product (("+" | "-") product)*
+    fn parse_sum<'a>(tokens: &[&'a str], pos: &mut usize) -> Result<CalcTerm, CalcParseError<'a>> {
+        let mut result = parse_product(tokens, pos)?;
+        while let Some(op) = tokens.get(*pos).cloned() {
+            let sign = match op { "+" => 1.0, "-" => -1.0, _ => break };
+            *pos += 1;
+            result = result.add(parse_product(tokens, pos)?, sign)?;
+        }
+        Ok(result)
+    }
+
+    // product := value (("*" | "/") value)*
+    fn parse_product<'a>(tokens: &[&'a str], pos: &mut usize) -> Result<CalcTerm, CalcParseError<'a>> {
+        let mut result = parse_value(tokens, pos)?;
+        while let Some(op) = tokens.get(*pos).cloned() {
+            match op {
+                "*" => {
+                    *pos += 1;
+                    let rhs = parse_value(tokens, pos)?;
+                    result = match (result.is_number, rhs.is_number) {
+                        (true, _) => rhs.scale(result.px),
+                        (false, true) => result.scale(rhs.px),
+                        (false, false) => return Err(CalcParseError::InvalidMultiplication),
+                    };
+                },
+                "/" => {
+                    *pos += 1;
+                    let rhs = parse_value(tokens, pos)?;
+                    if !rhs.is_number || rhs.px == 0.0 {
+                        return Err(CalcParseError::InvalidDivision);
+                    }
+                    result = result.scale(1.0 / rhs.px);
+                },
+                _ => break,
+            }
+        }
+        Ok(result)
+    }
+
+    // value := number [unit] | "(" sum ")" | "calc(" sum ")"
+    fn parse_value<'a>(tokens: &[&'a str], pos: &mut usize) -> Result<CalcTerm, CalcParseError<'a>> {
+
+        let token = tokens.get(*pos).cloned().ok_or(CalcParseError::UnexpectedEnd)?;
+        *pos += 1;
+
+        if token == "(" || (token == "calc" && tokens.get(*pos) == Some(&"(")) {
+            if token == "calc" {
+                *pos += 1;
+            }
+            let inner = parse_sum(tokens, pos)?;
+            return match tokens.get(*pos) {
+                Some(&")") => { *pos += 1; Ok(inner) },
+                Some(other) => Err(CalcParseError::UnexpectedToken(other)),
+                None => Err(CalcParseError::UnexpectedEnd),
+            };
+        }
+
+        let unit_start = token
+            .char_indices()
+            .find(|(idx, ch)| !(ch.is_ascii_digit() || *ch == '.' || (*idx == 0 && *ch == '-')))
+            .map(|(idx, _)| idx)
+            .unwrap_or(token.len());
+
+        let number = token[..unit_start].parse::<f32>().map_err(|_| CalcParseError::UnexpectedToken(token))?;
+        let zero = CalcTerm { px: 0.0, em: 0.0, percent: 0.0, is_number: false };
+
+        match &token[unit_start..] {
+            "" => Ok(CalcTerm { px: number, is_number: true, .. zero }),
+            "px" => Ok(CalcTerm { px: number, .. zero }),
+            "pt" => Ok(CalcTerm { px: number * PT_TO_PX, .. zero }),
+            "em" => Ok(CalcTerm { em: number, .. zero }),
+            "%" => Ok(CalcTerm { percent: number, .. zero }),
+            unit => Err(CalcParseError::InvalidUnit(unit)),
+        }
+    }
+
+    let input = input.trim();
+
+    if !input.starts_with("calc(") || !input.ends_with(')') {
+        return Err(CalcParseError::UnbalancedBraces(input));
+    }
+
+    let mut depth = 0_usize;
+    for ch in input.chars() {
+        match ch {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err(CalcParseError::UnbalancedBraces(input)),
+            ')' => depth -= 1,
+            _ => { },
+        }
+    }
+    if depth != 0 {
+        return Err(CalcParseError::UnbalancedBraces(input));
+    }
+
+    let tokens = tokenize_calc_expression(input)?;
+    let mut pos = 0;
+    let result = parse_value(&tokens, &mut pos)?;
+
+    if let Some(token) = tokens.get(pos) {
+        return Err(CalcParseError::UnexpectedToken(token));
+    }
+
+    if result.is_number {
+        return Err(CalcParseError::NotALength);
+    }
+
+    Ok(PixelValue::calc(result.px, result.em, result.percent))
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PercentageParseError {
     ValueParseErr(ParseFloatError),
@@ -929,8 +1220,8 @@ mod $mod_name {
     parse_x!($struct_name, $error_name, parse_top, top, $parse_fn);
 })}
 
-parse_tblr!(layout_padding_parser, LayoutPadding, LayoutPaddingParseError, parse_pixel_value);
-parse_tblr!(layout_margin_parser, LayoutMargin, LayoutMarginParseError, parse_pixel_value);
+parse_tblr!(layout_padding_parser, LayoutPadding, LayoutPaddingParseError, parse_length_value);
+parse_tblr!(layout_margin_parser, LayoutMargin, LayoutMarginParseError, parse_length_value);
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum LayoutPaddingParseError<'a> {
@@ -947,15 +1238,31 @@ impl_display!{ LayoutPaddingParseError<'a>, {
 
 impl_from!(PixelParseError<'a>, LayoutPaddingParseError::PixelParseError);
 
+/// Splits a value at the whitespace that is not inside of braces, so
+/// that `calc(100% - 48px) 10px` is split into two values
+fn split_whitespace_outside_braces<'a>(input: &'a str) -> impl Iterator<Item = &'a str> {
+    let mut depth = 0_isize;
+    input
+        .split(move |ch: char| {
+            match ch {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => { },
+            }
+            ch.is_whitespace() && depth <= 0
+        })
+        .filter(|value| !value.is_empty())
+}
+
 /// Parse a padding value such as
 ///
-/// "10px 10px"
+/// "10px 10px" or "5% calc(100% - 48px)"
 pub fn parse_layout_padding<'a>(input: &'a str)
 -> Result<LayoutPadding, LayoutPaddingParseError>
 {
-    let mut input_iter = input.split_whitespace();
-    let first = parse_pixel_value(input_iter.next().ok_or(LayoutPaddingParseError::TooFewValues)?)?;
-    let second = parse_pixel_value(match input_iter.next() {
+    let mut input_iter = split_whitespace_outside_braces(input);
+    let first = parse_length_value(input_iter.next().ok_or(LayoutPaddingParseError::TooFewValues)?)?;
+    let second = parse_length_value(match input_iter.next() {
         Some(s) => s,
         None => return Ok(LayoutPadding {
             top: Some(first),
@@ -964,7 +1271,7 @@ pub fn parse_layout_padding<'a>(input: &'a str)
             right: Some(first),
         }),
     })?;
-    let third = parse_pixel_value(match input_iter.next() {
+    let third = parse_length_value(match input_iter.next() {
         Some(s) => s,
         None => return Ok(LayoutPadding {
             top: Some(first),
@@ -973,7 +1280,7 @@ pub fn parse_layout_padding<'a>(input: &'a str)
             right: Some(second),
         }),
     })?;
-    let fourth = parse_pixel_value(match input_iter.next() {
+    let fourth = parse_length_value(match input_iter.next() {
         Some(s) => s,
         None => return Ok(LayoutPadding {
             top: Some(first),
@@ -1735,12 +2042,22 @@ pub struct RectStyle {
     pub line_height: Option<StyleLineHeight>,
     /// `letter-spacing` property (modifies the width and height)
     pub letter_spacing: Option<StyleLetterSpacing>,
+    /// `outline` property (doesn't affect the layout)
+    pub outline: Option<StyleOutline>,
+    /// `border-image` property
+    pub border_image: Option<StyleBorderImage>,
+    /// `transform` property (doesn't affect the layout)
+    pub transform: Option<StyleTransform>,
+    /// `transform-origin` property
+    pub transform_origin: Option<StyleTransformOrigin>,
+    /// `z-index` property (doesn't affect the layout)
+    pub z_index: Option<StyleZIndex>,
 }
 
 typed_pixel_value_parser!(parse_style_letter_spacing, StyleLetterSpacing);
 
 // Layout constraints for a given rectangle, such as "width", "min-width", "height", etc.
-#[derive(Default, Debug, Copy, Clone, PartialEq, Hash)]
+#[derive(Default, Debug, Clone, PartialEq, Hash)]
 pub struct RectLayout {
 
     pub width: Option<LayoutWidth>,
@@ -1749,6 +2066,9 @@ pub struct RectLayout {
     pub min_height: Option<LayoutMinHeight>,
     pub max_width: Option<LayoutMaxWidth>,
     pub max_height: Option<LayoutMaxHeight>,
+    pub intrinsic_width: Option<LayoutIntrinsicSize>,
+    pub intrinsic_height: Option<LayoutIntrinsicSize>,
+    pub aspect_ratio: Option<LayoutAspectRatio>,
 
     pub position: Option<LayoutPosition>,
     pub top: Option<LayoutTop>,
@@ -1766,20 +2086,26 @@ pub struct RectLayout {
     pub justify_content: Option<LayoutJustifyContent>,
     pub align_items: Option<LayoutAlignItems>,
     pub align_content: Option<LayoutAlignContent>,
+
+    pub grid_template_columns: Option<LayoutGridTemplateColumns>,
+    pub grid_template_rows: Option<LayoutGridTemplateRows>,
+    pub grid_column: Option<LayoutGridColumn>,
+    pub grid_row: Option<LayoutGridRow>,
+    pub gap: Option<LayoutGap>,
 }
 
-typed_pixel_value_parser!(parse_layout_width, LayoutWidth);
-typed_pixel_value_parser!(parse_layout_height, LayoutHeight);
+typed_length_value_parser!(parse_layout_width, LayoutWidth);
+typed_length_value_parser!(parse_layout_height, LayoutHeight);
 
-typed_pixel_value_parser!(parse_layout_min_height, LayoutMinHeight);
-typed_pixel_value_parser!(parse_layout_min_width, LayoutMinWidth);
-typed_pixel_value_parser!(parse_layout_max_width, LayoutMaxWidth);
-typed_pixel_value_parser!(parse_layout_max_height, LayoutMaxHeight);
+typed_length_value_parser!(parse_layout_min_height, LayoutMinHeight);
+typed_length_value_parser!(parse_layout_min_width, LayoutMinWidth);
+typed_length_value_parser!(parse_layout_max_width, LayoutMaxWidth);
+typed_length_value_parser!(parse_layout_max_height, LayoutMaxHeight);
 
-typed_pixel_value_parser!(parse_layout_top, LayoutTop);
-typed_pixel_value_parser!(parse_layout_bottom, LayoutBottom);
-typed_pixel_value_parser!(parse_layout_right, LayoutRight);
-typed_pixel_value_parser!(parse_layout_left, LayoutLeft);
+typed_length_value_parser!(parse_layout_top, LayoutTop);
+typed_length_value_parser!(parse_layout_bottom, LayoutBottom);
+typed_length_value_parser!(parse_layout_right, LayoutRight);
+typed_length_value_parser!(parse_layout_left, LayoutLeft);
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum FlexGrowParseError<'a> {
@@ -1797,6 +2123,214 @@ pub fn parse_layout_flex_grow<'a>(input: &'a str) -> Result<LayoutFlexGrow, Flex
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum AspectRatioParseError<'a> {
+    ParseFloat(ParseFloatError, &'a str),
+    NotPositive(&'a str),
+}
+
+impl_display!{AspectRatioParseError<'a>, {
+    ParseFloat(e, orig_str) => format!("aspect-ratio: Could not parse floating-point value: \"{}\" - Error: \"{}\"", orig_str, e),
+    NotPositive(orig_str) => format!("aspect-ratio: Ratio has to be larger than 0: \"{}\"", orig_str),
+}}
+
+/// Parses an `aspect-ratio`, either as `width / height` (i.e. `16 / 9`) or as a single number (`1.5`)
+pub fn parse_layout_aspect_ratio<'a>(input: &'a str) -> Result<LayoutAspectRatio, AspectRatioParseError<'a>> {
+
+    let mut components = input.splitn(2, '/');
+    let width_str = components.next().unwrap_or(input);
+    let height_str = components.next().unwrap_or("1");
+
+    let width = parse_float_value(width_str).map_err(|e| AspectRatioParseError::ParseFloat(e, input))?;
+    let height = parse_float_value(height_str).map_err(|e| AspectRatioParseError::ParseFloat(e, input))?;
+
+    if width.get() <= 0.0 || height.get() <= 0.0 {
+        return Err(AspectRatioParseError::NotPositive(input));
+    }
+
+    Ok(LayoutAspectRatio { width, height })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutGridParseError<'a> {
+    EmptyTemplate(&'a str),
+    UnbalancedBraces(&'a str),
+    InvalidRepeat(&'a str),
+    InvalidFraction(ParseFloatError, &'a str),
+    InvalidLine(&'a str),
+    TooManyValues(&'a str),
+    PixelParseError(PixelParseError<'a>),
+}
+
+impl_display!{LayoutGridParseError<'a>, {
+    EmptyTemplate(orig_str) => format!("Grid template has no tracks: \"{}\"", orig_str),
+    UnbalancedBraces(orig_str) => format!("Unbalanced braces: \"{}\"", orig_str),
+    InvalidRepeat(orig_str) => format!("Invalid repeat(), expected repeat(count, tracks): \"{}\"", orig_str),
+    InvalidFraction(e, orig_str) => format!("Could not parse fraction: \"{}\" - Error: \"{}\"", orig_str, e),
+    InvalidLine(orig_str) => format!("Invalid grid line, expected a number, \"span n\" or \"start / end\": \"{}\"", orig_str),
+    TooManyValues(orig_str) => format!("Too many values: \"{}\"", orig_str),
+    PixelParseError(e) => format!("{}", e),
+}}
+
+impl_from!(PixelParseError<'a>, LayoutGridParseError::PixelParseError);
+
+/// Splits a `grid-template-columns` / `grid-template-rows` value into the individual
+/// tracks, ignoring the whitespace inside of a `repeat()`
+fn split_grid_tracks<'a>(input: &'a str) -> Result<Vec<&'a str>, LayoutGridParseError<'a>> {
+
+    let mut tracks = Vec::new();
+    let mut depth = 0;
+    let mut track_start = None;
+
+    for (idx, ch) in input.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => {
+                if depth == 0 {
+                    return Err(LayoutGridParseError::UnbalancedBraces(input));
+                }
+                depth -= 1;
+            },
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(start) = track_start.take() {
+                    tracks.push(&input[start..idx]);
+                }
+                continue;
+            },
+            _ => { },
+        }
+
+        if track_start.is_none() {
+            track_start = Some(idx);
+        }
+    }
+
+    if depth != 0 {
+        return Err(LayoutGridParseError::UnbalancedBraces(input));
+    }
+
+    if let Some(start) = track_start {
+        tracks.push(&input[start..]);
+    }
+
+    Ok(tracks)
+}
+
+/// Parses a single track of a grid template: `100px`, `1fr`, `auto`, `min-content` or `max-content`
+pub fn parse_grid_track_size<'a>(input: &'a str) -> Result<GridTrackSize, LayoutGridParseError<'a>> {
+    match input {
+        "auto" => Ok(GridTrackSize::Auto),
+        "min-content" => Ok(GridTrackSize::MinContent),
+        "max-content" => Ok(GridTrackSize::MaxContent),
+        _ if input.ends_with("fr") => {
+            let fraction = parse_float_value(&input[..input.len() - 2])
+                .map_err(|e| LayoutGridParseError::InvalidFraction(e, input))?;
+            Ok(GridTrackSize::Fraction(fraction))
+        },
+        _ => Ok(GridTrackSize::Fixed(parse_pixel_value(input)?)),
+    }
+}
+
+/// Parses the tracks of a `grid-template-columns` or `grid-template-rows` attribute,
+/// such as `100px 1fr auto` or `repeat(3, 1fr)`
+pub fn parse_grid_track_list<'a>(input: &'a str) -> Result<Vec<GridTrackSize>, LayoutGridParseError<'a>> {
+
+    let mut tracks = Vec::new();
+
+    for track in split_grid_tracks(input)? {
+        if track.starts_with("repeat(") {
+
+            if !track.ends_with(')') {
+                return Err(LayoutGridParseError::InvalidRepeat(track));
+            }
+
+            let mut arguments = track["repeat(".len()..track.len() - 1].splitn(2, ',');
+            let count = arguments.next().unwrap_or("").trim().parse::<usize>()
+                .map_err(|_| LayoutGridParseError::InvalidRepeat(track))?;
+            let repeated_tracks = parse_grid_track_list(arguments.next().ok_or(LayoutGridParseError::InvalidRepeat(track))?)?;
+
+            if count == 0 {
+                return Err(LayoutGridParseError::InvalidRepeat(track));
+            }
+
+            for _ in 0..count {
+                tracks.extend(repeated_tracks.iter().cloned());
+            }
+        } else {
+            tracks.push(parse_grid_track_size(track)?);
+        }
+    }
+
+    if tracks.is_empty() {
+        return Err(LayoutGridParseError::EmptyTemplate(input));
+    }
+
+    Ok(tracks)
+}
+
+/// Parses a `grid-column` or `grid-row` attribute: `auto`, `2`, `span 2`, `1 / 3` or `2 / span 2`
+pub fn parse_grid_placement<'a>(input: &'a str) -> Result<GridPlacement, LayoutGridParseError<'a>> {
+
+    // Parses "span 2" into Some(2), returns None if the string doesn't start with "span"
+    let parse_span = |s: &str| -> Option<Result<usize, LayoutGridParseError<'a>>> {
+        if s.starts_with("span") {
+            Some(s["span".len()..].trim().parse::<usize>().ok().filter(|span| *span > 0).ok_or(LayoutGridParseError::InvalidLine(input)))
+        } else {
+            None
+        }
+    };
+
+    let parse_line = |s: &str| -> Result<usize, LayoutGridParseError<'a>> {
+        s.parse::<usize>().ok().filter(|line| *line > 0).ok_or(LayoutGridParseError::InvalidLine(input))
+    };
+
+    let mut components = input.splitn(2, '/');
+    let start_str = components.next().unwrap_or(input).trim();
+    let end_str = components.next().map(|s| s.trim());
+
+    let mut placement = match start_str {
+        "auto" => GridPlacement { start: None, span: 1 },
+        s => match parse_span(s) {
+            Some(span) => GridPlacement { start: None, span: span? },
+            None => GridPlacement { start: Some(parse_line(s)?), span: 1 },
+        },
+    };
+
+    match end_str {
+        None | Some("auto") => { },
+        Some(s) => match (parse_span(s), placement.start) {
+            (Some(span), Some(_)) => placement.span = span?,
+            (None, Some(start)) => {
+                let end = parse_line(s)?;
+                if end <= start {
+                    return Err(LayoutGridParseError::InvalidLine(input));
+                }
+                placement.span = end - start;
+            },
+            (_, None) => return Err(LayoutGridParseError::InvalidLine(input)),
+        },
+    }
+
+    Ok(placement)
+}
+
+/// Parses a `gap` attribute: `10px` (same gap between rows and columns) or `10px 20px` (row gap, column gap)
+pub fn parse_layout_gap<'a>(input: &'a str) -> Result<LayoutGap, LayoutGridParseError<'a>> {
+
+    let mut values = input.split_whitespace();
+    let row = parse_pixel_value(values.next().unwrap_or(input))?;
+    let column = match values.next() {
+        Some(column) => parse_pixel_value(column)?,
+        None => row,
+    };
+
+    if values.next().is_some() {
+        return Err(LayoutGridParseError::TooManyValues(input));
+    }
+
+    Ok(LayoutGap { row, column })
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum FlexShrinkParseError<'a> {
     ParseFloat(ParseFloatError, &'a str),
@@ -1930,6 +2464,242 @@ pub fn parse_parentheses<'a>(
     Ok((validated_stopword, &input[(first_open_brace + 1)..last_closing_brace]))
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum CssStyleTransformParseError<'a> {
+    /// The function is not closed by a `)`
+    UnclosedFunction(&'a str),
+    /// Not one of `translate`, `translateX`, `translateY`, `scale`, `scaleX`, `scaleY` or `rotate`
+    InvalidFunction(&'a str),
+    /// The function got too few or too many arguments
+    WrongNumberOfArguments(&'a str),
+    PixelParseError(PixelParseError<'a>),
+    FloatParseError(ParseFloatError, &'a str),
+    /// Angle without a `deg`, `rad`, `grad` or `turn` unit
+    InvalidAngle(&'a str),
+}
+
+impl_display!{ CssStyleTransformParseError<'a>, {
+    UnclosedFunction(e) => format!("Unclosed function: \"{}\"", e),
+    InvalidFunction(e) => format!("Unknown transform function: \"{}\"", e),
+    WrongNumberOfArguments(e) => format!("Wrong number of arguments: \"{}\"", e),
+    PixelParseError(e) => format!("{}", e),
+    FloatParseError(e, orig_str) => format!("Could not parse floating-point value: \"{}\" - Error: \"{}\"", orig_str, e),
+    InvalidAngle(e) => format!("Invalid angle: \"{}\"", e),
+}}
+
+impl_from!(PixelParseError<'a>, CssStyleTransformParseError::PixelParseError);
+
+/// Parses a `transform` attribute, i.e. `none` or `translateY(-8px) rotate(45deg)`
+pub fn parse_style_transform<'a>(input: &'a str)
+-> Result<StyleTransform, CssStyleTransformParseError<'a>>
+{
+    use self::CssStyleTransformParseError::*;
+
+    let input = input.trim();
+    if input == "none" {
+        return Ok(StyleTransform::default());
+    }
+
+    let parse_float = |value: &'a str| parse_float_value(value).map_err(|e| FloatParseError(e, value));
+
+    let mut functions = Vec::new();
+    let mut remaining = input;
+
+    while !remaining.is_empty() {
+        let open_brace = remaining.find('(').ok_or(InvalidFunction(remaining))?;
+        let close_brace = remaining.find(')').ok_or(UnclosedFunction(remaining))?;
+        if close_brace < open_brace {
+            return Err(InvalidFunction(remaining));
+        }
+
+        let function = &remaining[..=close_brace];
+        let name = remaining[..open_brace].trim();
+        let arguments = remaining[(open_brace + 1)..close_brace]
+            .split(',')
+            .map(|argument| argument.trim())
+            .collect::<Vec<_>>();
+
+        let transform_function = match (name, arguments.as_slice()) {
+            ("translate", &[x]) => StyleTransformFunction::Translate(parse_pixel_value(x)?, PixelValue::px(0.0)),
+            ("translate", &[x, y]) => StyleTransformFunction::Translate(parse_pixel_value(x)?, parse_pixel_value(y)?),
+            ("translateX", &[x]) => StyleTransformFunction::Translate(parse_pixel_value(x)?, PixelValue::px(0.0)),
+            ("translateY", &[y]) => StyleTransformFunction::Translate(PixelValue::px(0.0), parse_pixel_value(y)?),
+            ("scale", &[s]) => { let s = parse_float(s)?; StyleTransformFunction::Scale(s, s) },
+            ("scale", &[x, y]) => StyleTransformFunction::Scale(parse_float(x)?, parse_float(y)?),
+            ("scaleX", &[x]) => StyleTransformFunction::Scale(parse_float(x)?, FloatValue::new(1.0)),
+            ("scaleY", &[y]) => StyleTransformFunction::Scale(FloatValue::new(1.0), parse_float(y)?),
+            ("rotate", &[angle]) => StyleTransformFunction::Rotate(parse_angle_degrees(angle)?),
+            ("translate", _) | ("translateX", _) | ("translateY", _) |
+            ("scale", _) | ("scaleX", _) | ("scaleY", _) | ("rotate", _) => return Err(WrongNumberOfArguments(function)),
+            _ => return Err(InvalidFunction(function)),
+        };
+
+        functions.push(transform_function);
+        remaining = remaining[(close_brace + 1)..].trim();
+    }
+
+    Ok(StyleTransform(functions))
+}
+
+/// Parses an angle (`45deg`, `0.5turn`, `3.14rad`, `100grad`) and converts it to degrees
+fn parse_angle_degrees<'a>(input: &'a str)
+-> Result<FloatValue, CssStyleTransformParseError<'a>>
+{
+    use std::f32::consts::PI;
+
+    let (number, degrees_per_unit) =
+        if input.ends_with("deg") { (&input[..input.len() - 3], 1.0) }
+        else if input.ends_with("grad") { (&input[..input.len() - 4], 0.9) }
+        else if input.ends_with("rad") { (&input[..input.len() - 3], 180.0 / PI) }
+        else if input.ends_with("turn") { (&input[..input.len() - 4], 360.0) }
+        else if input == "0" { (input, 0.0) }
+        else { return Err(CssStyleTransformParseError::InvalidAngle(input)); };
+
+    let number = number.trim().parse::<f32>()
+        .map_err(|e| CssStyleTransformParseError::FloatParseError(e, input))?;
+
+    Ok(FloatValue::new(number * degrees_per_unit))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CssStyleTransformOriginParseError<'a> {
+    /// More than two values
+    TooManyValues(&'a str),
+    /// Not a keyword, pixel value or percentage
+    InvalidValue(&'a str),
+}
+
+impl_display!{ CssStyleTransformOriginParseError<'a>, {
+    TooManyValues(e) => format!("Too many values: \"{}\"", e),
+    InvalidValue(e) => format!("Invalid value: \"{}\"", e),
+}}
+
+/// Parses a `transform-origin` attribute, i.e. `center`, `left top`, `10px 50%`
+pub fn parse_style_transform_origin<'a>(input: &'a str)
+-> Result<StyleTransformOrigin, CssStyleTransformOriginParseError<'a>>
+{
+    use self::CssStyleTransformOriginParseError::*;
+
+    let percent = |p: f32| TransformOriginOffset::Percentage(PercentageValue::new(p));
+
+    let parse_offset = |value: &'a str| -> Result<TransformOriginOffset, CssStyleTransformOriginParseError<'a>> {
+        match value {
+            "left" | "top" => Ok(percent(0.0)),
+            "center" => Ok(percent(50.0)),
+            "right" | "bottom" => Ok(percent(100.0)),
+            _ if value.ends_with('%') => value[..(value.len() - 1)].parse::<f32>().map(|p| percent(p)).map_err(|_| InvalidValue(value)),
+            _ => parse_pixel_value(value).map(TransformOriginOffset::Pixels).map_err(|_| InvalidValue(value)),
+        }
+    };
+
+    let values = input.split_whitespace().collect::<Vec<_>>();
+
+    match values.as_slice() {
+        // A single keyword only sets one direction, i.e. `top` = `center top`
+        &["top"] | &["bottom"] => Ok(StyleTransformOrigin { x: percent(50.0), y: parse_offset(values[0])? }),
+        &[x] => Ok(StyleTransformOrigin { x: parse_offset(x)?, y: percent(50.0) }),
+        // Keywords can be in any order, i.e. `top left` = `left top`
+        &[y @ "top", x] | &[y @ "bottom", x] | &[y @ "center", x @ "left"] | &[y @ "center", x @ "right"] => {
+            Ok(StyleTransformOrigin { x: parse_offset(x)?, y: parse_offset(y)? })
+        },
+        &[x, y] => Ok(StyleTransformOrigin { x: parse_offset(x)?, y: parse_offset(y)? }),
+        _ => Err(TooManyValues(input)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CssStyleBorderImageParseError<'a> {
+    /// The source is not `none`, a gradient or an `image()`
+    InvalidSource(CssBackgroundParseError<'a>),
+    /// Slices have to be whole numbers (image pixels)
+    InvalidSlice(&'a str),
+    /// More than four slices
+    TooManySlices(&'a str),
+    /// Not `stretch`, `repeat`, `round` or `space`, or more than two of them
+    InvalidRepeat(&'a str),
+}
+
+impl_display!{ CssStyleBorderImageParseError<'a>, {
+    InvalidSource(e) => format!("{}", e),
+    InvalidSlice(e) => format!("Invalid slice: \"{}\"", e),
+    TooManySlices(e) => format!("Too many slices: \"{}\"", e),
+    InvalidRepeat(e) => format!("Invalid repeat: \"{}\"", e),
+}}
+
+impl_from!(CssBackgroundParseError<'a>, CssStyleBorderImageParseError::InvalidSource);
+
+multi_type_parser!(parse_border_image_repeat, BorderImageRepeat,
+                    ["stretch", Stretch],
+                    ["repeat", Repeat],
+                    ["round", Round],
+                    ["space", Space]);
+
+/// Parses a `border-image` attribute, i.e. `image("frame") 16 8 fill round stretch`
+/// or `linear-gradient(to right, red, blue)`: the source (same as for the `background`),
+/// one to four slices (top, right, bottom, left - like the `padding`), `fill` and
+/// one or two repeat modes (horizontal, vertical). The slices are only used for images.
+pub fn parse_style_border_image<'a>(input: &'a str)
+-> Result<StyleBorderImage, CssStyleBorderImageParseError<'a>>
+{
+    use self::CssStyleBorderImageParseError::*;
+
+    // The source ends at the first whitespace outside of its braces
+    let mut depth = 0;
+    let source_end = input.char_indices().find(|&(_, c)| {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => { },
+        }
+        depth == 0 && c.is_whitespace()
+    }).map(|(idx, _)| idx).unwrap_or(input.len());
+
+    let (source, rest) = input.split_at(source_end);
+    let source = parse_style_background(source)?;
+
+    let mut slices = Vec::new();
+    let mut repeats = Vec::new();
+    let mut fill = false;
+
+    for value in rest.split_whitespace() {
+        match value {
+            "fill" => { fill = true; },
+            _ => match parse_border_image_repeat(value) {
+                Ok(repeat) => repeats.push(repeat),
+                // The slices come before the repeat modes
+                Err(_) if repeats.is_empty() => slices.push(value.parse::<u32>().map_err(|_| InvalidSlice(value))?),
+                Err(_) => return Err(InvalidRepeat(value)),
+            },
+        }
+    }
+
+    let slice = match slices.as_slice() {
+        &[] => BorderImageSlice::default(),
+        &[all] => BorderImageSlice::uniform(all),
+        &[vertical, horizontal] => BorderImageSlice { top: vertical, right: horizontal, bottom: vertical, left: horizontal },
+        &[top, horizontal, bottom] => BorderImageSlice { top, right: horizontal, bottom, left: horizontal },
+        &[top, right, bottom, left] => BorderImageSlice { top, right, bottom, left },
+        _ => return Err(TooManySlices(input)),
+    };
+
+    let (repeat_horizontal, repeat_vertical) = match repeats.as_slice() {
+        &[] => (BorderImageRepeat::Stretch, BorderImageRepeat::Stretch),
+        &[both] => (both, both),
+        &[horizontal, vertical] => (horizontal, vertical),
+        _ => return Err(InvalidRepeat(input)),
+    };
+
+    Ok(StyleBorderImage { source, slice, fill, repeat_horizontal, repeat_vertical })
+}
+
+/// Parses a `z-index` attribute, i.e. `auto`, `2` or `-1`
+pub fn parse_style_z_index<'a>(input: &'a str) -> Result<StyleZIndex, InvalidValueErr<'a>> {
+    match input {
+        "auto" => Ok(StyleZIndex::Auto),
+        _ => input.parse::<i32>().map(StyleZIndex::Integer).map_err(|_| InvalidValueErr(input)),
+    }
+}
+
 multi_type_parser!(parse_style_cursor, StyleCursor,
                     ["alias", Alias],
                     ["all-scroll", AllScroll],
@@ -2010,7 +2780,13 @@ multi_type_parser!(parse_shape, Shape,
 multi_type_parser!(parse_layout_position, LayoutPosition,
                     ["static", Static],
                     ["absolute", Absolute],
-                    ["relative", Relative]);
+                    ["relative", Relative],
+                    ["fixed", Fixed]);
+
+multi_type_parser!(parse_layout_intrinsic_size, LayoutIntrinsicSize,
+                    ["min-content", MinContent],
+                    ["max-content", MaxContent],
+                    ["fit-content", FitContent]);
 
 multi_type_parser!(parse_layout_text_overflow, TextOverflowBehaviourInner,
                     ["auto", Auto],
@@ -2155,6 +2931,209 @@ mod css_tests {
         );
     }
 
+    #[test]
+    fn test_parse_style_transform() {
+        use azul_css::StyleTransformFunction::*;
+        assert_eq!(parse_style_transform("none"), Ok(StyleTransform(Vec::new())));
+        assert_eq!(parse_style_transform("translateY(-8px)"), Ok(StyleTransform(vec![
+            Translate(PixelValue::px(0.0), PixelValue::px(-8.0)),
+        ])));
+        assert_eq!(parse_style_transform("translate(10px, 5px) scale(2) rotate(0.25turn)"), Ok(StyleTransform(vec![
+            Translate(PixelValue::px(10.0), PixelValue::px(5.0)),
+            Scale(FloatValue::new(2.0), FloatValue::new(2.0)),
+            Rotate(FloatValue::new(90.0)),
+        ])));
+        assert_eq!(parse_style_transform("scaleX(0.5)rotate(45deg)"), Ok(StyleTransform(vec![
+            Scale(FloatValue::new(0.5), FloatValue::new(1.0)),
+            Rotate(FloatValue::new(45.0)),
+        ])));
+        assert_eq!(parse_style_transform("skew(10deg)"), Err(CssStyleTransformParseError::InvalidFunction("skew(10deg)")));
+        assert_eq!(parse_style_transform("rotate(45deg, 10deg)"), Err(CssStyleTransformParseError::WrongNumberOfArguments("rotate(45deg, 10deg)")));
+        assert_eq!(parse_style_transform("rotate(45)"), Err(CssStyleTransformParseError::InvalidAngle("45")));
+        assert_eq!(parse_style_transform("rotate(45deg"), Err(CssStyleTransformParseError::UnclosedFunction("rotate(45deg")));
+    }
+
+    #[test]
+    fn test_parse_style_transform_origin() {
+        let percent = |p| TransformOriginOffset::Percentage(PercentageValue::new(p));
+        let px = |p| TransformOriginOffset::Pixels(PixelValue::px(p));
+        assert_eq!(parse_style_transform_origin("center"), Ok(StyleTransformOrigin { x: percent(50.0), y: percent(50.0) }));
+        assert_eq!(parse_style_transform_origin("left top"), Ok(StyleTransformOrigin { x: percent(0.0), y: percent(0.0) }));
+        assert_eq!(parse_style_transform_origin("bottom right"), Ok(StyleTransformOrigin { x: percent(100.0), y: percent(100.0) }));
+        assert_eq!(parse_style_transform_origin("top"), Ok(StyleTransformOrigin { x: percent(50.0), y: percent(0.0) }));
+        assert_eq!(parse_style_transform_origin("10px 25%"), Ok(StyleTransformOrigin { x: px(10.0), y: percent(25.0) }));
+        assert_eq!(parse_style_transform_origin("1px 2px 3px"), Err(CssStyleTransformOriginParseError::TooManyValues("1px 2px 3px")));
+    }
+
+    #[test]
+    fn test_parse_css_outline() {
+        assert_eq!(
+            parse_key_value_pair(CssPropertyType::Outline, "2px dashed blue"),
+            Ok(CssProperty::Outline(StyleOutline(StyleBorderSide {
+                border_width: PixelValue::px(2.0),
+                border_style: BorderStyle::Dashed,
+                border_color: ColorU { r: 0, g: 0, b: 255, a: 255 },
+            })))
+        );
+    }
+
+    #[test]
+    fn test_parse_style_border_image() {
+
+        let gradient = parse_style_background("linear-gradient(to right, red, blue)").unwrap();
+        assert_eq!(parse_style_border_image("linear-gradient(to right, red, blue)"), Ok(StyleBorderImage {
+            source: gradient,
+            slice: BorderImageSlice::default(),
+            fill: false,
+            repeat_horizontal: BorderImageRepeat::Stretch,
+            repeat_vertical: BorderImageRepeat::Stretch,
+        }));
+
+        assert_eq!(parse_style_border_image("image(\"frame\") 16 8 fill round stretch"), Ok(StyleBorderImage {
+            source: StyleBackground::Image(CssImageId(String::from("frame"))),
+            slice: BorderImageSlice { top: 16, right: 8, bottom: 16, left: 8 },
+            fill: true,
+            repeat_horizontal: BorderImageRepeat::Round,
+            repeat_vertical: BorderImageRepeat::Stretch,
+        }));
+
+        assert_eq!(parse_style_border_image("image(\"frame\") 1 2 3 4 space").map(|b| (b.slice, b.repeat_vertical)),
+            Ok((BorderImageSlice { top: 1, right: 2, bottom: 3, left: 4 }, BorderImageRepeat::Space)));
+        assert_eq!(parse_style_border_image("none").map(|b| b.source), Ok(StyleBackground::NoBackground));
+
+        assert_eq!(parse_style_border_image("image(\"frame\") 1.5"), Err(CssStyleBorderImageParseError::InvalidSlice("1.5")));
+        assert_eq!(parse_style_border_image("image(\"frame\") 1 2 3 4 5"), Err(CssStyleBorderImageParseError::TooManySlices("image(\"frame\") 1 2 3 4 5")));
+        assert_eq!(parse_style_border_image("image(\"frame\") round 4"), Err(CssStyleBorderImageParseError::InvalidRepeat("4")));
+        assert!(parse_key_value_pair(CssPropertyType::BorderImage, "image(\"frame\") 8 repeat").is_ok());
+    }
+
+    #[test]
+    fn test_parse_style_z_index() {
+        assert_eq!(parse_style_z_index("auto"), Ok(StyleZIndex::Auto));
+        assert_eq!(parse_style_z_index("3"), Ok(StyleZIndex::Integer(3)));
+        assert_eq!(parse_style_z_index("-1"), Ok(StyleZIndex::Integer(-1)));
+        assert_eq!(parse_style_z_index("1.5"), Err(InvalidValueErr("1.5")));
+        assert_eq!(parse_key_value_pair(CssPropertyType::ZIndex, " 2 "), Ok(CssProperty::ZIndex(StyleZIndex::Integer(2))));
+    }
+
+    #[test]
+    fn test_parse_layout_position() {
+        assert_eq!(parse_layout_position("fixed"), Ok(LayoutPosition::Fixed));
+        assert_eq!(parse_key_value_pair(CssPropertyType::Position, "absolute"), Ok(CssProperty::Position(LayoutPosition::Absolute)));
+    }
+
+    #[test]
+    fn test_parse_layout_intrinsic_size() {
+        assert_eq!(parse_key_value_pair(CssPropertyType::Width, "min-content"), Ok(CssProperty::IntrinsicWidth(LayoutIntrinsicSize::MinContent)));
+        assert_eq!(parse_key_value_pair(CssPropertyType::Height, "fit-content"), Ok(CssProperty::IntrinsicHeight(LayoutIntrinsicSize::FitContent)));
+        assert_eq!(parse_key_value_pair(CssPropertyType::Width, "max-content"), Ok(CssProperty::IntrinsicWidth(LayoutIntrinsicSize::MaxContent)));
+        assert_eq!(parse_key_value_pair(CssPropertyType::Width, "50px"), Ok(CssProperty::Width(LayoutWidth(PixelValue::px(50.0)))));
+        assert_eq!(CssProperty::IntrinsicWidth(LayoutIntrinsicSize::MinContent).get_type(), CssPropertyType::Width);
+    }
+
+    #[test]
+    fn test_parse_layout_aspect_ratio() {
+        assert_eq!(parse_layout_aspect_ratio("16 / 9"), Ok(LayoutAspectRatio { width: FloatValue::new(16.0), height: FloatValue::new(9.0) }));
+        assert_eq!(parse_layout_aspect_ratio("1.5"), Ok(LayoutAspectRatio { width: FloatValue::new(1.5), height: FloatValue::new(1.0) }));
+        assert_eq!(parse_layout_aspect_ratio("0 / 1"), Err(AspectRatioParseError::NotPositive("0 / 1")));
+        assert!(parse_layout_aspect_ratio("16 / abc").is_err());
+    }
+
+    #[test]
+    fn test_parse_grid_track_list() {
+        assert_eq!(parse_grid_track_list("100px 1fr auto"), Ok(vec![
+            GridTrackSize::Fixed(PixelValue::px(100.0)),
+            GridTrackSize::Fraction(FloatValue::new(1.0)),
+            GridTrackSize::Auto,
+        ]));
+        assert_eq!(parse_grid_track_list("min-content repeat(2, 20px 0.5fr)"), Ok(vec![
+            GridTrackSize::MinContent,
+            GridTrackSize::Fixed(PixelValue::px(20.0)),
+            GridTrackSize::Fraction(FloatValue::new(0.5)),
+            GridTrackSize::Fixed(PixelValue::px(20.0)),
+            GridTrackSize::Fraction(FloatValue::new(0.5)),
+        ]));
+        assert_eq!(parse_grid_track_list("repeat(0, 1fr)"), Err(LayoutGridParseError::InvalidRepeat("repeat(0, 1fr)")));
+        assert_eq!(parse_grid_track_list("repeat(2, 1fr"), Err(LayoutGridParseError::UnbalancedBraces("repeat(2, 1fr")));
+        assert_eq!(parse_grid_track_list(""), Err(LayoutGridParseError::EmptyTemplate("")));
+    }
+
+    #[test]
+    fn test_parse_grid_placement() {
+        assert_eq!(parse_grid_placement("auto"), Ok(GridPlacement { start: None, span: 1 }));
+        assert_eq!(parse_grid_placement("2"), Ok(GridPlacement { start: Some(2), span: 1 }));
+        assert_eq!(parse_grid_placement("span 3"), Ok(GridPlacement { start: None, span: 3 }));
+        assert_eq!(parse_grid_placement("1 / 3"), Ok(GridPlacement { start: Some(1), span: 2 }));
+        assert_eq!(parse_grid_placement("2 / span 2"), Ok(GridPlacement { start: Some(2), span: 2 }));
+        assert_eq!(parse_grid_placement("3 / 1"), Err(LayoutGridParseError::InvalidLine("3 / 1")));
+        assert_eq!(parse_grid_placement("0"), Err(LayoutGridParseError::InvalidLine("0")));
+        assert_eq!(parse_grid_placement("span 2 / 4"), Err(LayoutGridParseError::InvalidLine("span 2 / 4")));
+    }
+
+    #[test]
+    fn test_parse_layout_gap() {
+        assert_eq!(parse_layout_gap("10px"), Ok(LayoutGap { row: PixelValue::px(10.0), column: PixelValue::px(10.0) }));
+        assert_eq!(parse_layout_gap("10px 20px"), Ok(LayoutGap { row: PixelValue::px(10.0), column: PixelValue::px(20.0) }));
+        assert_eq!(parse_layout_gap("10px 20px 30px"), Err(LayoutGridParseError::TooManyValues("10px 20px 30px")));
+    }
+
+    #[test]
+    fn test_parse_length_value() {
+        assert_eq!(parse_length_value("10px"), Ok(PixelValue::px(10.0)));
+        assert_eq!(parse_length_value("50%"), Ok(PixelValue::percent(50.0)));
+        assert_eq!(parse_length_value("calc(100% - 48px)"), Ok(PixelValue::calc(-48.0, 0.0, 100.0)));
+        assert_eq!(parse_layout_width("calc(100% - 48px)"), Ok(LayoutWidth(PixelValue::calc(-48.0, 0.0, 100.0))));
+        assert!(parse_length_value("10vw").is_err());
+    }
+
+    #[test]
+    fn test_parse_calc_expression_whitespace() {
+        let expected = Ok(PixelValue::calc(-48.0, 0.0, 100.0));
+        assert_eq!(parse_calc_expression("calc(100% - 48px)"), expected);
+        assert_eq!(parse_calc_expression("calc(100%-48px)"), expected);
+        assert_eq!(parse_calc_expression("calc(  100%   -   48px  )"), expected);
+        assert_eq!(parse_calc_expression("calc(\n100%\t- 48px)"), expected);
+        // a minus after an operator is the sign of the number
+        assert_eq!(parse_calc_expression("calc(100% + -48px)"), expected);
+    }
+
+    #[test]
+    fn test_parse_calc_expression_nested() {
+        assert_eq!(parse_calc_expression("calc((100% - 2 * 10px) / 2)"), Ok(PixelValue::calc(-10.0, 0.0, 50.0)));
+        assert_eq!(parse_calc_expression("calc(50% + calc(10px * 2))"), Ok(PixelValue::calc(20.0, 0.0, 50.0)));
+        assert_eq!(parse_calc_expression("calc(2 * (1em + (10px - 5%)))"), Ok(PixelValue::calc(20.0, 2.0, -10.0)));
+        // only one term left: a plain value instead of a calc() value
+        assert_eq!(parse_calc_expression("calc(3 * (10px + 5px))"), Ok(PixelValue::px(45.0)));
+        assert_eq!(parse_calc_expression("calc(100% - 50% * 2 + 1em)"), Ok(PixelValue::em(1.0)));
+    }
+
+    #[test]
+    fn test_parse_calc_expression_errors() {
+        assert_eq!(parse_calc_expression("calc(100% - 48px"), Err(CalcParseError::UnbalancedBraces("calc(100% - 48px")));
+        assert_eq!(parse_calc_expression("calc(10px * 10px)"), Err(CalcParseError::InvalidMultiplication));
+        assert_eq!(parse_calc_expression("calc(10px / 0)"), Err(CalcParseError::InvalidDivision));
+        assert_eq!(parse_calc_expression("calc(10px / 1px)"), Err(CalcParseError::InvalidDivision));
+        assert_eq!(parse_calc_expression("calc(10px + 2)"), Err(CalcParseError::MixedNumberAndLength));
+        assert_eq!(parse_calc_expression("calc(2 * 3)"), Err(CalcParseError::NotALength));
+        assert_eq!(parse_calc_expression("calc(10vw)"), Err(CalcParseError::InvalidUnit("vw")));
+        assert_eq!(parse_calc_expression("calc(10px +)"), Err(CalcParseError::UnexpectedToken(")")));
+    }
+
+    #[test]
+    fn test_parse_percentage_padding() {
+        assert_eq!(parse_layout_padding("5% calc(100% - 48px)"), Ok(LayoutPadding {
+            top: Some(PixelValue::percent(5.0)),
+            bottom: Some(PixelValue::percent(5.0)),
+            left: Some(PixelValue::calc(-48.0, 0.0, 100.0)),
+            right: Some(PixelValue::calc(-48.0, 0.0, 100.0)),
+        }));
+        assert_eq!(parse_layout_margin("10%"), Ok(LayoutMargin::all(PixelValue::percent(10.0))));
+        assert_eq!(
+            parse_key_value_pair(CssPropertyType::PaddingLeft, "calc(2 * 5%)"),
+            Ok(CssProperty::Padding(LayoutPadding { left: Some(PixelValue::percent(10.0)), .. Default::default() }))
+        );
+    }
+
     #[test]
     fn test_parse_linear_gradient_1() {
         assert_eq!(parse_style_background("linear-gradient(red, yellow)"),
@@ -8,12 +8,15 @@ use std::collections::BTreeMap;
 use std::fmt;
 
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutRect { pub origin: LayoutPoint, pub size: LayoutSize }
 /// Only used for calculations: Size (width, height) in layout space.
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutSize { pub width: f32, pub height: f32 }
 /// Only used for calculations: Point coordinate (x, y) in layout space.
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutPoint { pub x: f32, pub y: f32 }
 
 impl LayoutSize {
@@ -30,6 +33,7 @@ impl LayoutSize {
 
 /// Represents a parsed pair of `5px, 10px` values - useful for border radius calculation
 #[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct PixelSize { pub width: PixelValue, pub height: PixelValue }
 
 impl PixelSize {
@@ -48,6 +52,7 @@ impl PixelSize {
 
 /// Offsets of the border-width calculations
 #[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutSideOffsets {
     pub top: FloatValue,
     pub right: FloatValue,
@@ -57,10 +62,12 @@ pub struct LayoutSideOffsets {
 
 /// u8-based color, range 0 to 255 (similar to webrenders ColorU)
 #[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct ColorU { pub r: u8, pub g: u8, pub b: u8, pub a: u8 }
 
 /// f32-based color, range 0.0 to 1.0 (similar to webrenders ColorF)
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct ColorF { pub r: f32, pub g: f32, pub b: f32, pub a: f32 }
 
 impl From<ColorU> for ColorF {
@@ -86,6 +93,7 @@ impl From<ColorF> for ColorU {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct BorderRadius {
     pub top_left: PixelSize,
     pub top_right: PixelSize,
@@ -116,6 +124,7 @@ impl BorderRadius {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum BorderDetails {
     Normal(NormalBorder),
     NinePatch(NinePatchBorder),
@@ -123,6 +132,7 @@ pub enum BorderDetails {
 
 /// Represents a normal `border` property (no image border / nine-patch border)
 #[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct NormalBorder {
     pub left: BorderSide,
     pub right: BorderSide,
@@ -132,6 +142,7 @@ pub struct NormalBorder {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct BorderSide {
     pub color: ColorU,
     pub style: BorderStyle,
@@ -139,6 +150,7 @@ pub struct BorderSide {
 
 /// What direction should a `box-shadow` be clipped in (inset or outset)
 #[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum BoxShadowClipMode {
     Outset,
     Inset,
@@ -146,6 +158,7 @@ pub enum BoxShadowClipMode {
 
 /// Whether a `gradient` should be repeated or clamped to the edges.
 #[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum ExtendMode {
     Clamp,
     Repeat,
@@ -153,6 +166,7 @@ pub enum ExtendMode {
 
 /// Style of a `border`: solid, double, dash, ridge, etc.
 #[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum BorderStyle {
     None,
     Solid,
@@ -165,9 +179,118 @@ pub enum BorderStyle {
     Inset,
     Outset,
 }
+/// A border that is painted with an image, sliced into nine parts: the four corners
+/// are drawn unscaled, the four edges are stretched or repeated along the sides
+/// of the border and the center is only drawn if `fill` is set. The slices are
+/// already clamped to the size of the image, see `BorderImageSlice::clamp_to_image`.
 #[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct NinePatchBorder {
-    // not implemented or parse-able yet, so no fields!
+    pub image_width: u32,
+    pub image_height: u32,
+    pub slice: BorderImageSlice,
+    pub fill: bool,
+    pub repeat_horizontal: BorderImageRepeat,
+    pub repeat_vertical: BorderImageRepeat,
+}
+
+/// Insets of the `border-image` slices from the edges of the image, in image pixels
+#[derive(Debug, Default, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub struct BorderImageSlice {
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub left: u32,
+}
+
+impl BorderImageSlice {
+
+    pub fn uniform(value: u32) -> Self {
+        Self { top: value, right: value, bottom: value, left: value }
+    }
+
+    /// Clamps the slices to the size of the image: like in CSS, slices that
+    /// are larger than the image are clamped to the edge of the image and if
+    /// two opposite slices overlap, the center is empty and they are
+    /// scaled down proportionally, so that they meet in the middle:
+    ///
+    /// ```rust
+    /// # use azul_css::BorderImageSlice;
+    /// let slice = BorderImageSlice { top: 10, right: 40, bottom: 30, left: 200 };
+    /// assert_eq!(slice.clamp_to_image(100, 100), BorderImageSlice { top: 10, right: 29, bottom: 30, left: 71 });
+    /// ```
+    pub fn clamp_to_image(&self, image_width: u32, image_height: u32) -> Self {
+
+        fn clamp_pair(start: u32, end: u32, size: u32) -> (u32, u32) {
+            let start = start.min(size);
+            let end = end.min(size);
+            if start + end <= size {
+                (start, end)
+            } else {
+                let scaled_start = (start as u64 * size as u64 / (start as u64 + end as u64)) as u32;
+                (scaled_start, size - scaled_start)
+            }
+        }
+
+        let (top, bottom) = clamp_pair(self.top, self.bottom, image_height);
+        let (left, right) = clamp_pair(self.left, self.right, image_width);
+        Self { top, right, bottom, left }
+    }
+}
+
+/// How the edges (and the center) of a `border-image` fill the sides of the border
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub enum BorderImageRepeat {
+    /// Stretch the edge over the entire side (the default)
+    Stretch,
+    /// Tile the edge, the tiles at the ends are cut off
+    Repeat,
+    /// Tile the edge, the tiles are scaled so that a whole number of tiles fits
+    Round,
+    /// Tile the edge, the space between the tiles is distributed evenly
+    Space,
+}
+
+impl Default for BorderImageRepeat {
+    fn default() -> Self {
+        BorderImageRepeat::Stretch
+    }
+}
+
+/// Represents a `border-image` attribute: paints the border area (the widths of the
+/// `border`) with a gradient or a sliced image instead of the border colors.
+///
+/// Nothing is drawn if the node has no `border`. Gradient borders follow the
+/// `border-radius` (the inner edge is rounded with the radius minus the border width),
+/// image borders ignore it, like in CSS - the background is still rounded, so use
+/// images with matching rounded corners.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub struct StyleBorderImage {
+    /// `none`, a gradient or an image
+    pub source: StyleBackground,
+    /// Only used for image sources
+    pub slice: BorderImageSlice,
+    /// Whether the center slice of the image is drawn inside of the border
+    pub fill: bool,
+    pub repeat_horizontal: BorderImageRepeat,
+    pub repeat_vertical: BorderImageRepeat,
+}
+
+impl StyleBorderImage {
+    /// Returns the nine-patch that an image of the given size is sliced into
+    pub fn get_nine_patch(&self, image_width: u32, image_height: u32) -> NinePatchBorder {
+        NinePatchBorder {
+            image_width,
+            image_height,
+            slice: self.slice.clamp_to_image(image_width, image_height),
+            fill: self.fill,
+            repeat_horizontal: self.repeat_horizontal,
+            repeat_vertical: self.repeat_vertical,
+        }
+    }
 }
 
 /// Currently hard-coded: Height of one em in pixels
@@ -193,10 +316,15 @@ macro_rules! impl_pixel_value {($struct:ident) => (
         pub fn pt(value: f32) -> Self {
             $struct(PixelValue::pt(value))
         }
+
+        #[inline]
+        pub fn percent(value: f32) -> Self {
+            $struct(PixelValue::percent(value))
+        }
     }
 )}
 
-pub const CSS_PROPERTY_KEY_MAP: [(CssPropertyType, &'static str);53] = [
+pub const CSS_PROPERTY_KEY_MAP: [(CssPropertyType, &'static str);64] = [
     (CssPropertyType::BorderRadius,     "border-radius"),
     (CssPropertyType::BackgroundColor,  "background-color"),
     (CssPropertyType::BackgroundSize,   "background-size"),
@@ -215,6 +343,7 @@ pub const CSS_PROPERTY_KEY_MAP: [(CssPropertyType, &'static str);53] = [
     (CssPropertyType::MinHeight,        "min-height"),
     (CssPropertyType::MaxWidth,         "max-width"),
     (CssPropertyType::MaxHeight,        "max-height"),
+    (CssPropertyType::AspectRatio,      "aspect-ratio"),
     (CssPropertyType::Position,         "position"),
     (CssPropertyType::Top,              "top"),
     (CssPropertyType::Right,            "right"),
@@ -227,6 +356,11 @@ pub const CSS_PROPERTY_KEY_MAP: [(CssPropertyType, &'static str);53] = [
     (CssPropertyType::JustifyContent,   "justify-content"),
     (CssPropertyType::AlignItems,       "align-items"),
     (CssPropertyType::AlignContent,     "align-content"),
+    (CssPropertyType::GridTemplateColumns, "grid-template-columns"),
+    (CssPropertyType::GridTemplateRows, "grid-template-rows"),
+    (CssPropertyType::GridColumn,       "grid-column"),
+    (CssPropertyType::GridRow,          "grid-row"),
+    (CssPropertyType::Gap,              "gap"),
     (CssPropertyType::Overflow,         "overflow"),
     (CssPropertyType::OverflowX,        "overflow-x"),
     (CssPropertyType::OverflowY,        "overflow-y"),
@@ -245,11 +379,16 @@ pub const CSS_PROPERTY_KEY_MAP: [(CssPropertyType, &'static str);53] = [
     (CssPropertyType::BorderLeft,       "border-left"),
     (CssPropertyType::BorderRight,      "border-right"),
     (CssPropertyType::BorderBottom,     "border-bottom"),
+    (CssPropertyType::BorderImage,      "border-image"),
     (CssPropertyType::BoxShadow,        "box-shadow"),
     (CssPropertyType::BoxShadowTop,     "box-shadow-top"),
     (CssPropertyType::BoxShadowLeft,    "box-shadow-left"),
     (CssPropertyType::BoxShadowRight,   "box-shadow-right"),
     (CssPropertyType::BoxShadowBottom,  "box-shadow-bottom"),
+    (CssPropertyType::Outline,          "outline"),
+    (CssPropertyType::Transform,        "transform"),
+    (CssPropertyType::TransformOrigin,  "transform-origin"),
+    (CssPropertyType::ZIndex,           "z-index"),
 ];
 
 /// Returns a map useful for parsing the keys of CSS stylesheets
@@ -260,6 +399,7 @@ pub fn get_css_key_map() -> BTreeMap<&'static str, CssPropertyType> {
 /// Same as CssProperty, but without any data. Used to identify the
 /// key of the CSS key-value pair without parsing the value
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum CssPropertyType {
     BorderRadius,
     BackgroundColor,
@@ -279,6 +419,7 @@ pub enum CssPropertyType {
     MinHeight,
     MaxWidth,
     MaxHeight,
+    AspectRatio,
     Position,
     Top,
     Right,
@@ -292,6 +433,12 @@ pub enum CssPropertyType {
     AlignItems,
     AlignContent,
 
+    GridTemplateColumns,
+    GridTemplateRows,
+    GridColumn,
+    GridRow,
+    Gap,
+
     Overflow,
     OverflowX,
     OverflowY,
@@ -313,12 +460,20 @@ pub enum CssPropertyType {
     BorderLeft,
     BorderRight,
     BorderBottom,
+    BorderImage,
 
     BoxShadow,
     BoxShadowTop,
     BoxShadowLeft,
     BoxShadowRight,
     BoxShadowBottom,
+
+    Outline,
+
+    Transform,
+    TransformOrigin,
+
+    ZIndex,
 }
 
 impl CssPropertyType {
@@ -372,12 +527,17 @@ impl CssPropertyType {
             | BackgroundRepeat
             | TextColor
             | Background
+            | BorderImage
             | TextAlign
             | BoxShadow
             | BoxShadowTop
             | BoxShadowLeft
             | BoxShadowBottom
             | BoxShadowRight
+            | Outline
+            | Transform
+            | TransformOrigin
+            | ZIndex
             | Cursor => false,
             _ => true,
         }
@@ -393,6 +553,7 @@ impl fmt::Display for CssPropertyType {
 
 /// A property that can be used to style DOM nodes
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum CssProperty {
     BorderRadius(StyleBorderRadius),
     BackgroundColor(StyleBackgroundColor),
@@ -400,6 +561,7 @@ pub enum CssProperty {
     BackgroundRepeat(StyleBackgroundRepeat),
     TextColor(StyleTextColor),
     Border(StyleBorder),
+    BorderImage(StyleBorderImage),
     Background(StyleBackground),
     FontSize(StyleFontSize),
     FontFamily(StyleFontFamily),
@@ -414,6 +576,11 @@ pub enum CssProperty {
     MinHeight(LayoutMinHeight),
     MaxWidth(LayoutMaxWidth),
     MaxHeight(LayoutMaxHeight),
+    /// `width: min-content | max-content | fit-content`, replaces any pixel `width`
+    IntrinsicWidth(LayoutIntrinsicSize),
+    /// `height: min-content | max-content | fit-content`, replaces any pixel `height`
+    IntrinsicHeight(LayoutIntrinsicSize),
+    AspectRatio(LayoutAspectRatio),
     Position(LayoutPosition),
     Top(LayoutTop),
     Right(LayoutRight),
@@ -428,7 +595,16 @@ pub enum CssProperty {
     JustifyContent(LayoutJustifyContent),
     AlignItems(LayoutAlignItems),
     AlignContent(LayoutAlignContent),
+    GridTemplateColumns(LayoutGridTemplateColumns),
+    GridTemplateRows(LayoutGridTemplateRows),
+    GridColumn(LayoutGridColumn),
+    GridRow(LayoutGridRow),
+    Gap(LayoutGap),
     Overflow(LayoutOverflow),
+    Outline(StyleOutline),
+    Transform(StyleTransform),
+    TransformOrigin(StyleTransformOrigin),
+    ZIndex(StyleZIndex),
 }
 
 impl CssProperty {
@@ -440,6 +616,7 @@ impl CssProperty {
             CssProperty::BackgroundRepeat(_) => CssPropertyType::BackgroundRepeat,
             CssProperty::TextColor(_) => CssPropertyType::TextColor,
             CssProperty::Border(_) => CssPropertyType::Border,
+            CssProperty::BorderImage(_) => CssPropertyType::BorderImage,
             CssProperty::Background(_) => CssPropertyType::Background,
             CssProperty::FontSize(_) => CssPropertyType::FontSize,
             CssProperty::FontFamily(_) => CssPropertyType::FontFamily,
@@ -454,6 +631,9 @@ impl CssProperty {
             CssProperty::MinHeight(_) => CssPropertyType::MinHeight,
             CssProperty::MaxWidth(_) => CssPropertyType::MaxWidth,
             CssProperty::MaxHeight(_) => CssPropertyType::MaxHeight,
+            CssProperty::IntrinsicWidth(_) => CssPropertyType::Width,
+            CssProperty::IntrinsicHeight(_) => CssPropertyType::Height,
+            CssProperty::AspectRatio(_) => CssPropertyType::AspectRatio,
             CssProperty::Position(_) => CssPropertyType::Position,
             CssProperty::Top(_) => CssPropertyType::Top,
             CssProperty::Right(_) => CssPropertyType::Right,
@@ -468,7 +648,16 @@ impl CssProperty {
             CssProperty::JustifyContent(_) => CssPropertyType::JustifyContent,
             CssProperty::AlignItems(_) => CssPropertyType::AlignItems,
             CssProperty::AlignContent(_) => CssPropertyType::AlignContent,
+            CssProperty::GridTemplateColumns(_) => CssPropertyType::GridTemplateColumns,
+            CssProperty::GridTemplateRows(_) => CssPropertyType::GridTemplateRows,
+            CssProperty::GridColumn(_) => CssPropertyType::GridColumn,
+            CssProperty::GridRow(_) => CssPropertyType::GridRow,
+            CssProperty::Gap(_) => CssPropertyType::Gap,
             CssProperty::Overflow(_) => CssPropertyType::Overflow,
+            CssProperty::Outline(_) => CssPropertyType::Outline,
+            CssProperty::Transform(_) => CssPropertyType::Transform,
+            CssProperty::TransformOrigin(_) => CssPropertyType::TransformOrigin,
+            CssProperty::ZIndex(_) => CssPropertyType::ZIndex,
         }
     }
 }
@@ -477,6 +666,7 @@ impl_from!(StyleBorderRadius, CssProperty::BorderRadius);
 impl_from!(StyleBackground, CssProperty::Background);
 impl_from!(StyleBoxShadow, CssProperty::BoxShadow);
 impl_from!(StyleBorder, CssProperty::Border);
+impl_from!(StyleBorderImage, CssProperty::BorderImage);
 impl_from!(StyleFontSize, CssProperty::FontSize);
 impl_from!(StyleFontFamily, CssProperty::FontFamily);
 impl_from!(StyleTextAlignmentHorz, CssProperty::TextAlign);
@@ -487,6 +677,10 @@ impl_from!(StyleBackgroundSize, CssProperty::BackgroundSize);
 impl_from!(StyleBackgroundRepeat, CssProperty::BackgroundRepeat);
 impl_from!(StyleTextColor, CssProperty::TextColor);
 impl_from!(StyleCursor, CssProperty::Cursor);
+impl_from!(StyleOutline, CssProperty::Outline);
+impl_from!(StyleTransform, CssProperty::Transform);
+impl_from!(StyleTransformOrigin, CssProperty::TransformOrigin);
+impl_from!(StyleZIndex, CssProperty::ZIndex);
 
 impl_from!(LayoutOverflow, CssProperty::Overflow);
 impl_from!(LayoutWidth, CssProperty::Width);
@@ -495,6 +689,7 @@ impl_from!(LayoutMinWidth, CssProperty::MinWidth);
 impl_from!(LayoutMinHeight, CssProperty::MinHeight);
 impl_from!(LayoutMaxWidth, CssProperty::MaxWidth);
 impl_from!(LayoutMaxHeight, CssProperty::MaxHeight);
+impl_from!(LayoutAspectRatio, CssProperty::AspectRatio);
 
 impl_from!(LayoutPosition, CssProperty::Position);
 impl_from!(LayoutTop, CssProperty::Top);
@@ -513,6 +708,12 @@ impl_from!(LayoutJustifyContent, CssProperty::JustifyContent);
 impl_from!(LayoutAlignItems, CssProperty::AlignItems);
 impl_from!(LayoutAlignContent, CssProperty::AlignContent);
 
+impl_from!(LayoutGridTemplateColumns, CssProperty::GridTemplateColumns);
+impl_from!(LayoutGridTemplateRows, CssProperty::GridTemplateRows);
+impl_from!(LayoutGridColumn, CssProperty::GridColumn);
+impl_from!(LayoutGridRow, CssProperty::GridRow);
+impl_from!(LayoutGap, CssProperty::Gap);
+
 /// Multiplier for floating point accuracy. Elements such as px or %
 /// are only accurate until a certain number of decimal points, therefore
 /// they have to be casted to isizes in order to make the f32 values
@@ -522,6 +723,7 @@ pub const FP_PRECISION_MULTIPLIER: f32 = 10000.0;
 
 /// FloatValue, but associated with a certain metric (i.e. px, em, etc.)
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct PixelValue {
     pub metric: SizeMetric,
     pub number: FloatValue,
@@ -543,6 +745,26 @@ impl PixelValue {
         Self::from_metric(SizeMetric::Pt, value)
     }
 
+    /// Percentage of the containing block, i.e. `PixelValue::percent(50.0)` = `50%`
+    #[inline]
+    pub fn percent(value: f32) -> Self {
+        Self::from_metric(SizeMetric::Percent, value)
+    }
+
+    /// A `calc()` expression, normalized to `px + em + percent%`. Returns a plain
+    /// px, em or percentage value if only one of the terms is not zero.
+    pub fn calc(px: f32, em: f32, percent: f32) -> Self {
+        match (px != 0.0, em != 0.0, percent != 0.0) {
+            (_, false, false) => Self::px(px),
+            (false, true, false) => Self::em(em),
+            (false, false, true) => Self::percent(percent),
+            _ => Self {
+                metric: SizeMetric::Calc { em: em.into(), percent: percent.into() },
+                number: px.into(),
+            },
+        }
+    }
+
     #[inline]
     pub fn from_metric(metric: SizeMetric, value: f32) -> Self {
         Self {
@@ -551,13 +773,47 @@ impl PixelValue {
         }
     }
 
-    /// Returns the value of the SizeMetric in pixels
+    /// Returns the value of the SizeMetric in pixels. Percentages can only
+    /// be resolved during layout (see `to_pixels_with_basis`), they count as `0px`.
     #[inline]
     pub fn to_pixels(&self) -> f32 {
+        self.to_pixels_with_basis(0.0)
+    }
+
+    /// Returns the value in pixels, percentages are resolved relative to `basis`
+    /// (for example the width of the containing block)
+    pub fn to_pixels_with_basis(&self, basis: f32) -> f32 {
+        let (px, em, percent) = self.get_terms();
+        px + em * EM_HEIGHT + percent / 100.0 * basis
+    }
+
+    /// Returns whether the value depends on the size of the containing block
+    #[inline]
+    pub fn has_percentage(&self) -> bool {
+        self.get_terms().2 != 0.0
+    }
+
+    /// Interpolates between two values, i.e. for animating from `50%` to `calc(100% - 48px)`.
+    /// `t = 0.0` returns `self`, `t = 1.0` returns `other` (`pt` values are returned as `px`).
+    ///
+    /// Since every value is normalized to `px + em + percent%`, any two values can be
+    /// interpolated (term by term), the result is a `calc()` value if the metrics differ.
+    pub fn interpolate(&self, other: &Self, t: f32) -> Self {
+        let (px_a, em_a, percent_a) = self.get_terms();
+        let (px_b, em_b, percent_b) = other.get_terms();
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        Self::calc(lerp(px_a, px_b), lerp(em_a, em_b), lerp(percent_a, percent_b))
+    }
+
+    /// Returns the `(px, em, percent)` terms of the value (`pt` are converted to `px`)
+    fn get_terms(&self) -> (f32, f32, f32) {
+        let number = self.number.get();
         match self.metric {
-            SizeMetric::Px => { self.number.get() },
-            SizeMetric::Pt => { (self.number.get()) * PT_TO_PX },
-            SizeMetric::Em => { (self.number.get()) * EM_HEIGHT },
+            SizeMetric::Px => (number, 0.0, 0.0),
+            SizeMetric::Pt => (number * PT_TO_PX, 0.0, 0.0),
+            SizeMetric::Em => (0.0, number, 0.0),
+            SizeMetric::Percent => (0.0, 0.0, number),
+            SizeMetric::Calc { em, percent } => (number, em.get(), percent.get()),
         }
     }
 }
@@ -565,6 +821,7 @@ impl PixelValue {
 /// Wrapper around FloatValue, represents a percentage instead
 /// of just being a regular floating-point value, i.e `5` = `5%`
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct PercentageValue {
     number: FloatValue,
 }
@@ -582,6 +839,7 @@ impl PercentageValue {
 /// Wrapper around an f32 value that is internally casted to an isize, in order to
 /// provide hash-ability (to avoid numerical instability).
 #[derive(Debug, PartialEq, Copy, Clone, Hash, Eq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct FloatValue {
     number: isize,
 }
@@ -604,13 +862,21 @@ impl From<f32> for FloatValue {
 
 /// Enum representing the metric associated with a number (px, pt, em, etc.)
 #[derive(Debug, PartialEq, Clone, Copy, Hash, Eq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum SizeMetric {
     Px,
     Pt,
     Em,
+    /// Percentage of the containing block, only valid for layout properties
+    /// (width, height, padding, margin, ...), resolved during layout
+    Percent,
+    /// `calc()` expression: the `number` of the `PixelValue` (in px)
+    /// plus `em` em plus `percent` percent of the containing block
+    Calc { em: FloatValue, percent: FloatValue },
 }
 
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct StyleBorderRadius(pub BorderRadius);
 
 impl StyleBorderRadius {
@@ -621,6 +887,7 @@ impl StyleBorderRadius {
 
 /// Represents a `background-color` attribute
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct StyleBackgroundColor(pub ColorU);
 
 impl Default for StyleBackgroundColor {
@@ -632,6 +899,7 @@ impl Default for StyleBackgroundColor {
 
 /// Represents a `background-size` attribute
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum StyleBackgroundSize {
     Contain,
     Cover,
@@ -639,6 +907,7 @@ pub enum StyleBackgroundSize {
 
 /// Represents a `background-repeat` attribute
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum StyleBackgroundRepeat {
     NoRepeat,
     Repeat,
@@ -654,10 +923,12 @@ impl Default for StyleBackgroundRepeat {
 
 /// Represents a `color` attribute
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct StyleTextColor(pub ColorU);
 
 /// Represents a `padding` attribute
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutPadding {
     pub top: Option<PixelValue>,
     pub bottom: Option<PixelValue>,
@@ -700,6 +971,7 @@ struct_all!(LayoutMargin, PixelValue);
 
 /// Represents a parsed `padding` attribute
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutMargin {
     pub top: Option<PixelValue>,
     pub bottom: Option<PixelValue>,
@@ -709,6 +981,7 @@ pub struct LayoutMargin {
 
 /// Wrapper for the `overflow-{x,y}` + `overflow` property
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutOverflow {
     pub horizontal: TextOverflowBehaviour,
     pub vertical: TextOverflowBehaviour,
@@ -753,6 +1026,7 @@ impl LayoutOverflow {
 }
 
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct StyleBorder {
     pub top: Option<StyleBorderSide>,
     pub left: Option<StyleBorderSide>,
@@ -815,14 +1089,97 @@ const DEFAULT_BORDER_STYLE: BorderStyle = BorderStyle::Solid;
 const DEFAULT_BORDER_COLOR: ColorU = ColorU { r: 0, g: 0, b: 0, a: 255 };
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct StyleBorderSide {
     pub border_width: PixelValue,
     pub border_style: BorderStyle,
     pub border_color: ColorU,
 }
 
+/// Represents an `outline` attribute: drawn around the border box of the node,
+/// but (unlike the border) it doesn't take up any space in the layout
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub struct StyleOutline(pub StyleBorderSide);
+
+/// Represents a `transform` attribute, i.e. `translateY(-8px) rotate(45deg)`.
+///
+/// Like in CSS, the functions are applied from right to left. Transforms only
+/// affect the painting and hit-testing of a node, not the layout.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub struct StyleTransform(pub Vec<StyleTransformFunction>);
+
+/// A single function of a `transform` attribute
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub enum StyleTransformFunction {
+    /// `translate(x, y)`, `translateX(x)` or `translateY(y)`
+    Translate(PixelValue, PixelValue),
+    /// `scale(x, y)`, `scaleX(x)` or `scaleY(y)`
+    Scale(FloatValue, FloatValue),
+    /// `rotate(angle)`, clockwise, in degrees
+    Rotate(FloatValue),
+}
+
+/// Represents a `transform-origin` attribute: the point (relative to
+/// the top left corner of the node) that the transform is applied around.
+/// Defaults to the center of the node.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub struct StyleTransformOrigin {
+    pub x: TransformOriginOffset,
+    pub y: TransformOriginOffset,
+}
+
+impl Default for StyleTransformOrigin {
+    fn default() -> Self {
+        Self {
+            x: TransformOriginOffset::Percentage(PercentageValue::new(50.0)),
+            y: TransformOriginOffset::Percentage(PercentageValue::new(50.0)),
+        }
+    }
+}
+
+/// Represents a `z-index` attribute. A node with an integer z-index establishes a
+/// stacking context: it is painted (and hit-tested) together with its children,
+/// ordered by the z-index among the other stacking contexts of its parent context.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub enum StyleZIndex {
+    /// The node is painted in document order and doesn't establish a stacking context
+    Auto,
+    Integer(i32),
+}
+
+impl Default for StyleZIndex {
+    fn default() -> Self {
+        StyleZIndex::Auto
+    }
+}
+
+/// Offset of the `transform-origin` in one direction
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub enum TransformOriginOffset {
+    Pixels(PixelValue),
+    /// Percentage of the width / height of the node
+    Percentage(PercentageValue),
+}
+
+impl TransformOriginOffset {
+    /// Returns the offset in pixels, given the width / height of the node
+    pub fn to_pixels(&self, length: f32) -> f32 {
+        match self {
+            TransformOriginOffset::Pixels(p) => p.to_pixels(),
+            TransformOriginOffset::Percentage(p) => p.get() / 100.0 * length,
+        }
+    }
+}
+
 /// Represents a `box-shadow` attribute.
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct StyleBoxShadow {
     pub top: Option<Option<BoxShadowPreDisplayItem>>,
     pub left: Option<Option<BoxShadowPreDisplayItem>>,
@@ -835,6 +1192,7 @@ struct_all!(StyleBoxShadow, Option<BoxShadowPreDisplayItem>);
 
 // missing StyleBorderRadius & LayoutRect
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct BoxShadowPreDisplayItem {
     pub offset: [PixelValue;2],
     pub color: ColorU,
@@ -844,6 +1202,7 @@ pub struct BoxShadowPreDisplayItem {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum StyleBackground {
     LinearGradient(LinearGradient),
     RadialGradient(RadialGradient),
@@ -858,6 +1217,7 @@ impl<'a> From<CssImageId> for StyleBackground {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LinearGradient {
     pub direction: Direction,
     pub extend_mode: ExtendMode,
@@ -865,6 +1225,7 @@ pub struct LinearGradient {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct RadialGradient {
     pub shape: Shape,
     pub extend_mode: ExtendMode,
@@ -872,6 +1233,7 @@ pub struct RadialGradient {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum Direction {
     Angle(FloatValue),
     FromTo(DirectionCorner, DirectionCorner),
@@ -946,12 +1308,14 @@ impl Direction {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum Shape {
     Ellipse,
     Circle,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum StyleCursor {
     /// `alias`
     Alias,
@@ -1022,6 +1386,7 @@ impl Default for StyleCursor {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum DirectionCorner {
     Right,
     Left,
@@ -1077,6 +1442,7 @@ impl DirectionCorner {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum BackgroundType {
     LinearGradient,
     RepeatingLinearGradient,
@@ -1092,9 +1458,11 @@ pub enum BackgroundType {
 /// of the original source text. For example, when parsing a style
 /// from CSS, the original string can be deallocated afterwards.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct CssImageId(pub String);
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct GradientStopPre {
     pub offset: Option<PercentageValue>, // this is set to None if there was no offset that could be parsed
     pub color: ColorU,
@@ -1102,45 +1470,153 @@ pub struct GradientStopPre {
 
 /// Represents a `width` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutWidth(pub PixelValue);
 /// Represents a `min-width` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutMinWidth(pub PixelValue);
 /// Represents a `max-width` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutMaxWidth(pub PixelValue);
 /// Represents a `height` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutHeight(pub PixelValue);
 /// Represents a `min-height` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutMinHeight(pub PixelValue);
 /// Represents a `max-height` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutMaxHeight(pub PixelValue);
 
+/// Represents a `min-content`, `max-content` or `fit-content` value
+/// of a `width` or `height` attribute
+///
+/// - `MinContent`: The node is as small as possible without overflowing its content
+///   (i.e. the width of the longest word of a text)
+/// - `MaxContent`: The node takes up the full size of its content (i.e. the width of
+///   the longest line of a text if it isn't wrapped)
+/// - `FitContent`: The node takes up the available space, but is never smaller
+///   than the `min-content` or larger than the `max-content` size
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub enum LayoutIntrinsicSize {
+    MinContent,
+    MaxContent,
+    FitContent,
+}
+
+/// Represents an `aspect-ratio` attribute, i.e. `aspect-ratio: 16 / 9`
+///
+/// If only one of the `width` or `height` of a node is set, the other axis follows
+/// from the ratio (clamped by the `min-` and `max-` constraints of that axis).
+/// If neither is set, the width is determined by the flex layout and the height follows
+/// the width. If both the width and the height are set (either as pixel values or as
+/// `min-content` / `max-content` / `fit-content`), the `aspect-ratio` is ignored.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub struct LayoutAspectRatio {
+    pub width: FloatValue,
+    pub height: FloatValue,
+}
+
+impl LayoutAspectRatio {
+    /// Returns the ratio of the width to the height
+    pub fn get(&self) -> f32 {
+        self.width.get() / self.height.get()
+    }
+}
+
 /// Represents a `top` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutTop(pub PixelValue);
 /// Represents a `left` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutLeft(pub PixelValue);
 /// Represents a `right` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutRight(pub PixelValue);
 /// Represents a `bottom` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutBottom(pub PixelValue);
 
 /// Represents a `flex-grow` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutFlexGrow(pub FloatValue);
 /// Represents a `flex-shrink` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct LayoutFlexShrink(pub FloatValue);
 
+/// Size of a single column or row of a `grid-template-columns` / `grid-template-rows` attribute
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub enum GridTrackSize {
+    /// Fixed size, i.e. `100px`
+    Fixed(PixelValue),
+    /// Fraction of the space that is left over after all other tracks are sized, i.e. `1fr`
+    Fraction(FloatValue),
+    /// Size of the largest item in the track, stretched to fill the
+    /// left over space if there are no `fr` tracks
+    Auto,
+    /// The largest `min-content` size of the items in the track
+    MinContent,
+    /// The largest `max-content` size of the items in the track
+    MaxContent,
+}
+
+/// Represents a `grid-template-columns` attribute, i.e. `grid-template-columns: 100px auto 1fr`
+///
+/// Setting `grid-template-columns` or `grid-template-rows` turns the node into a grid container
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub struct LayoutGridTemplateColumns(pub Vec<GridTrackSize>);
+/// Represents a `grid-template-rows` attribute, i.e. `grid-template-rows: repeat(3, 40px)`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub struct LayoutGridTemplateRows(pub Vec<GridTrackSize>);
+
+/// Placement of a grid item along one axis, i.e. `2`, `1 / 3`, `span 2` or `2 / span 2`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub struct GridPlacement {
+    /// Grid line (starting at 1) that the item starts at, `None` if the item is placed automatically
+    pub start: Option<usize>,
+    /// Number of tracks that the item spans, at least 1
+    pub span: usize,
+}
+
+/// Represents a `grid-column` attribute
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub struct LayoutGridColumn(pub GridPlacement);
+/// Represents a `grid-row` attribute
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub struct LayoutGridRow(pub GridPlacement);
+
+/// Represents a `gap` attribute, i.e. `gap: 10px` or `gap: 10px 20px` (row gap, column gap)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
+pub struct LayoutGap {
+    /// Space between two rows
+    pub row: PixelValue,
+    /// Space between two columns
+    pub column: PixelValue,
+}
+
 /// Represents a `flex-direction` attribute - default: `Column`
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum LayoutDirection {
     Row,
     RowReverse,
@@ -1150,15 +1626,18 @@ pub enum LayoutDirection {
 
 /// Represents a `line-height` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct StyleLineHeight(pub PercentageValue);
 /// Represents a `letter-spacing` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct StyleLetterSpacing(pub PixelValue);
 
 /// Same as the `LayoutDirection`, but without the `-reverse` properties, used in the layout solver,
 /// makes decisions based on horizontal / vertical direction easier to write.
 /// Use `LayoutDirection::get_axis()` to get the axis for a given `LayoutDirection`.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum LayoutAxis {
     Horizontal,
     Vertical,
@@ -1181,12 +1660,17 @@ impl LayoutDirection {
 
 /// Represents a `position` attribute - default: `Static`
 ///
+/// `Absolute` nodes are positioned relative to the nearest positioned (or transformed)
+/// ancestor, `Fixed` nodes relative to the window (or the nearest transformed ancestor).
+///
 /// NOTE: No inline positioning is supported.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum LayoutPosition {
     Static,
     Relative,
     Absolute,
+    Fixed,
 }
 
 impl Default for LayoutPosition {
@@ -1203,6 +1687,7 @@ impl Default for LayoutDirection {
 
 /// Represents a `flex-wrap` attribute - default: `Wrap`
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum LayoutWrap {
     Wrap,
     NoWrap,
@@ -1216,6 +1701,7 @@ impl Default for LayoutWrap {
 
 /// Represents a `justify-content` attribute
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum LayoutJustifyContent {
     /// Default value. Items are positioned at the beginning of the container
     Start,
@@ -1237,6 +1723,7 @@ impl Default for LayoutJustifyContent {
 
 /// Represents a `align-items` attribute
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum LayoutAlignItems {
     /// Items are stretched to fit the container
     Stretch,
@@ -1256,6 +1743,7 @@ impl Default for LayoutAlignItems {
 
 /// Represents a `align-content` attribute
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum LayoutAlignContent {
     /// Default value. Lines stretch to take up the remaining space
     Stretch,
@@ -1277,6 +1765,7 @@ pub enum LayoutAlignContent {
 /// in order to be able to "merge" `overflow-x` and `overflow-y`
 /// into one property.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum TextOverflowBehaviour {
     NotModified,
     Modified(TextOverflowBehaviourInner),
@@ -1319,6 +1808,7 @@ impl Default for TextOverflowBehaviour {
 /// Represents a `overflow-x` or `overflow-y` property, see
 /// [`TextOverflowBehaviour`](./struct.TextOverflowBehaviour.html) - default: `Auto`
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum TextOverflowBehaviourInner {
     /// Always shows a scroll bar, overflows on scroll
     Scroll,
@@ -1338,6 +1828,7 @@ impl Default for TextOverflowBehaviourInner {
 
 /// Horizontal text alignment enum (left, center, right) - default: `Center`
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum StyleTextAlignmentHorz {
     Left,
     Center,
@@ -1352,6 +1843,7 @@ impl Default for StyleTextAlignmentHorz {
 
 /// Vertical text alignment enum (top, center, bottom) - default: `Center`
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum StyleTextAlignmentVert {
     Top,
     Center,
@@ -1367,6 +1859,7 @@ impl Default for StyleTextAlignmentVert {
 /// Stylistic options of the rectangle that don't influence the layout
 /// (todo: border-box?)
 #[derive(Default, Debug, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct RectStyle {
     /// Background color of this rectangle
     pub background_color: Option<StyleBackgroundColor>,
@@ -1380,6 +1873,8 @@ pub struct RectStyle {
     pub background: Option<StyleBackground>,
     /// Border
     pub border: Option<StyleBorder>,
+    /// `border-image` property, painted instead of the colors of the `border`
+    pub border_image: Option<StyleBorderImage>,
     /// Border radius
     pub border_radius: Option<StyleBorderRadius>,
     /// Font size
@@ -1396,12 +1891,21 @@ pub struct RectStyle {
     pub line_height: Option<StyleLineHeight>,
     /// `letter-spacing` property (modifies the width and height)
     pub letter_spacing: Option<StyleLetterSpacing>,
+    /// `outline` property (doesn't affect the layout)
+    pub outline: Option<StyleOutline>,
+    /// `transform` property (doesn't affect the layout)
+    pub transform: Option<StyleTransform>,
+    /// `transform-origin` property
+    pub transform_origin: Option<StyleTransformOrigin>,
+    /// `z-index` property (doesn't affect the layout)
+    pub z_index: Option<StyleZIndex>,
 }
 
 impl_pixel_value!(StyleLetterSpacing);
 
 // Layout constraints for a given rectangle, such as "width", "min-width", "height", etc.
-#[derive(Default, Debug, Copy, Clone, PartialEq, Hash)]
+#[derive(Default, Debug, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct RectLayout {
 
     pub width: Option<LayoutWidth>,
@@ -1410,6 +1914,9 @@ pub struct RectLayout {
     pub min_height: Option<LayoutMinHeight>,
     pub max_width: Option<LayoutMaxWidth>,
     pub max_height: Option<LayoutMaxHeight>,
+    pub intrinsic_width: Option<LayoutIntrinsicSize>,
+    pub intrinsic_height: Option<LayoutIntrinsicSize>,
+    pub aspect_ratio: Option<LayoutAspectRatio>,
 
     pub position: Option<LayoutPosition>,
     pub top: Option<LayoutTop>,
@@ -1427,6 +1934,12 @@ pub struct RectLayout {
     pub justify_content: Option<LayoutJustifyContent>,
     pub align_items: Option<LayoutAlignItems>,
     pub align_content: Option<LayoutAlignContent>,
+
+    pub grid_template_columns: Option<LayoutGridTemplateColumns>,
+    pub grid_template_rows: Option<LayoutGridTemplateRows>,
+    pub grid_column: Option<LayoutGridColumn>,
+    pub grid_row: Option<LayoutGridRow>,
+    pub gap: Option<LayoutGap>,
 }
 
 impl_pixel_value!(LayoutWidth);
@@ -1444,6 +1957,7 @@ impl_pixel_value!(LayoutLeft);
 
 /// Represents a `font-size` attribute
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct StyleFontSize(pub PixelValue);
 
 impl_pixel_value!(StyleFontSize);
@@ -1456,12 +1970,14 @@ impl StyleFontSize {
 
 /// Represents a `font-family` attribute
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub struct StyleFontFamily {
     // fonts in order of precedence, i.e. "Webly Sleeky UI", "monospace", etc.
     pub fonts: Vec<FontId>
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum FontId {
     BuiltinFont(String),
     ExternalFont(String),
@@ -1,5 +1,11 @@
 //! Provides datatypes used to describe an application's style using the Azul GUI framework.
 
+#[cfg(feature = "serde_serialization")]
+extern crate serde;
+#[cfg(feature = "serde_serialization")]
+#[macro_use]
+extern crate serde_derive;
+
 #[macro_use]
 mod macros;
 mod css;
@@ -10,6 +10,18 @@ extern crate azul_css_parser;
 pub const NATIVE_CSS: &str = concat!(
     include_str!("styles/native_windows.css"),
     include_str!("styles/shared/table.css"),
+    include_str!("styles/shared/text_input.css"),
+    include_str!("styles/shared/toggles.css"),
+    include_str!("styles/shared/color_picker.css"),
+    include_str!("styles/shared/dropdown.css"),
+    include_str!("styles/shared/modal.css"),
+    include_str!("styles/shared/data_table.css"),
+    include_str!("styles/shared/slider.css"),
+    include_str!("styles/shared/split_pane.css"),
+    include_str!("styles/shared/progress_bar.css"),
+    include_str!("styles/shared/tab_view.css"),
+    include_str!("styles/shared/tree_view.css"),
+    include_str!("styles/shared/toast.css"),
 );
 
 /// CSS mimicking the OS-native look - Linux: `styles/native_linux.css`
@@ -17,6 +29,18 @@ pub const NATIVE_CSS: &str = concat!(
 pub const NATIVE_CSS: &str = concat!(
     include_str!("styles/native_linux.css"),
     include_str!("styles/shared/table.css"),
+    include_str!("styles/shared/text_input.css"),
+    include_str!("styles/shared/toggles.css"),
+    include_str!("styles/shared/color_picker.css"),
+    include_str!("styles/shared/dropdown.css"),
+    include_str!("styles/shared/modal.css"),
+    include_str!("styles/shared/data_table.css"),
+    include_str!("styles/shared/slider.css"),
+    include_str!("styles/shared/split_pane.css"),
+    include_str!("styles/shared/progress_bar.css"),
+    include_str!("styles/shared/tab_view.css"),
+    include_str!("styles/shared/tree_view.css"),
+    include_str!("styles/shared/toast.css"),
 );
 
 /// CSS mimicking the OS-native look - Mac: `styles/native_macos.css`
@@ -24,6 +48,18 @@ pub const NATIVE_CSS: &str = concat!(
 pub const NATIVE_CSS: &str = concat!(
     include_str!("styles/native_macos.css"),
     include_str!("styles/shared/table.css"),
+    include_str!("styles/shared/text_input.css"),
+    include_str!("styles/shared/toggles.css"),
+    include_str!("styles/shared/color_picker.css"),
+    include_str!("styles/shared/dropdown.css"),
+    include_str!("styles/shared/modal.css"),
+    include_str!("styles/shared/data_table.css"),
+    include_str!("styles/shared/slider.css"),
+    include_str!("styles/shared/split_pane.css"),
+    include_str!("styles/shared/progress_bar.css"),
+    include_str!("styles/shared/tab_view.css"),
+    include_str!("styles/shared/tree_view.css"),
+    include_str!("styles/shared/toast.css"),
 );
 
 /// Returns the native style for the OS
@@ -0,0 +1,126 @@
+//! Benchmarks for building, hashing and comparing DOMs, for `into_ui_state` and
+//! for the frames of a window, run with `cargo bench --features benchmarks`.
+
+#[macro_use]
+extern crate criterion;
+extern crate azul;
+
+use criterion::Criterion;
+use azul::{prelude::*, dom_gen};
+
+struct BenchLayout { }
+
+impl Layout for BenchLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+fn callback(_: &mut AppState<BenchLayout>, _: &mut CallbackInfo<BenchLayout>) -> UpdateScreen {
+    DontRedraw
+}
+
+fn build(c: &mut Criterion) {
+    c.bench_function("build wide dom (10k siblings)", |b| {
+        b.iter(|| dom_gen::wide_dom::<BenchLayout>(10_000))
+    });
+    c.bench_function("build deep dom (1k nesting)", |b| {
+        b.iter(|| dom_gen::deep_dom::<BenchLayout>(1_000))
+    });
+    c.bench_function("add_child 1k x 20 node fragments", |b| {
+        b.iter(|| dom_gen::composed_dom::<BenchLayout>(1_000, 20))
+    });
+    c.bench_function("add_child 10k labels", |b| {
+        b.iter(|| {
+            let mut dom = Dom::<BenchLayout>::div();
+            for i in 0..10_000 {
+                dom.add_child(Dom::label(format!("{}", i)));
+            }
+            dom
+        })
+    });
+    c.bench_function("extend 10k labels", |b| {
+        b.iter(|| {
+            let mut dom = Dom::<BenchLayout>::div();
+            dom.extend((0..10_000).map(|i| NodeData::label(format!("{}", i))));
+            dom
+        })
+    });
+}
+
+fn hash(c: &mut Criterion) {
+    let dom = dom_gen::composed_dom::<BenchLayout>(1_000, 20);
+    c.bench_function("hash 20k nodes (uncached)", move |b| {
+        b.iter(|| {
+            dom_gen::clear_hash_caches(&dom);
+            dom_gen::hash_dom(&dom)
+        })
+    });
+
+    let dom = dom_gen::composed_dom::<BenchLayout>(1_000, 20);
+    dom_gen::hash_dom(&dom);
+    c.bench_function("hash 20k nodes (cached)", move |b| {
+        b.iter(|| dom_gen::hash_dom(&dom))
+    });
+
+    // Hashing the same DOM twice: the second pass only reads the cached node hashes
+    let dom = dom_gen::wide_dom::<BenchLayout>(10_000);
+    c.bench_function("hash 10k nodes twice", move |b| {
+        b.iter(|| {
+            dom_gen::clear_hash_caches(&dom);
+            (dom_gen::hash_dom(&dom), dom_gen::hash_dom(&dom))
+        })
+    });
+
+    let dom = dom_gen::wide_dom::<BenchLayout>(10_000);
+    c.bench_function("hash 10k nodes once", move |b| {
+        b.iter(|| {
+            dom_gen::clear_hash_caches(&dom);
+            dom_gen::hash_dom(&dom)
+        })
+    });
+}
+
+fn into_ui_state(c: &mut Criterion) {
+    c.bench_function_over_inputs("into_ui_state 10k nodes, % of nodes with callbacks", |b, &percent| {
+        let mut dom = dom_gen::wide_dom::<BenchLayout>(10_000);
+        dom_gen::distribute_callbacks(&mut dom, percent, Callback(callback));
+        b.iter_with_setup(|| dom.clone(), |dom| UiState::from_dom(dom))
+    }, vec![0, 10, 100]);
+}
+
+fn partial_eq(c: &mut Criterion) {
+    let a = dom_gen::composed_dom::<BenchLayout>(1_000, 20);
+    let b = a.clone();
+    c.bench_function("PartialEq 20k nodes (equal)", move |bencher| {
+        bencher.iter(|| a == b)
+    });
+}
+
+fn frames(c: &mut Criterion) {
+    let build_dom = || {
+        let mut dom = dom_gen::composed_dom::<BenchLayout>(100, 20);
+        dom_gen::distribute_callbacks(&mut dom, 10, Callback(callback));
+        dom
+    };
+
+    // The DOM doesn't change, so every frame re-uses the cached display list
+    let mut frames = dom_gen::WindowFrames::new(Css::default());
+    frames.frame(build_dom());
+    c.bench_function("idle frame 2k nodes", move |b| {
+        b.iter_with_setup(&build_dom, |dom| assert!(frames.frame(dom)))
+    });
+
+    // Every frame changes the DOM, so the cached display list can't be re-used
+    let mut frames = dom_gen::WindowFrames::new(Css::default());
+    let mut counter = 0;
+    c.bench_function("changed frame 2k nodes", move |b| {
+        b.iter_with_setup(|| {
+            counter += 1;
+            build_dom().with_child(Dom::label(format!("{}", counter)))
+        }, |dom| assert!(!frames.frame(dom)))
+    });
+}
+
+criterion_group!(benches, build, hash, into_ui_state, partial_eq, frames);
+criterion_main!(benches);
@@ -0,0 +1,94 @@
+//! Allocations and time per frame for building a DOM with and without the
+//! `DomAllocator`, run with `cargo bench --features benchmarks`.
+//!
+//! Criterion only measures the time, so the number of allocations per frame
+//! is counted with a wrapper around the system allocator and printed once.
+
+#[macro_use]
+extern crate criterion;
+extern crate azul;
+
+use std::{
+    alloc::{GlobalAlloc, System, Layout as AllocLayout},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use criterion::Criterion;
+use azul::{prelude::*, dom_gen};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: AllocLayout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: AllocLayout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: AllocLayout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+struct BenchLayout { }
+
+impl Layout for BenchLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+fn callback(_: &mut AppState<BenchLayout>, _: &mut CallbackInfo<BenchLayout>) -> UpdateScreen {
+    DontRedraw
+}
+
+const NODES: usize = 10_000;
+
+/// Runs one frame (build the DOM, then drop or recycle it), returns the number of allocations
+fn count_allocations<F: FnMut()>(mut frame: F) -> usize {
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    frame();
+    ALLOCATIONS.load(Ordering::SeqCst) - before
+}
+
+fn frame_allocations(c: &mut Criterion) {
+
+    let without_allocator = count_allocations(|| {
+        dom_gen::callback_dom::<BenchLayout>(NODES, Callback(callback));
+    });
+
+    // The first frame has no memory to re-use yet
+    let mut allocator = DomAllocator::new();
+    let dom = dom_gen::allocated_callback_dom(&mut allocator, NODES, Callback(callback));
+    dom_gen::recycle_dom(&mut allocator, dom);
+
+    let with_allocator = count_allocations(|| {
+        let dom = dom_gen::allocated_callback_dom(&mut allocator, NODES, Callback(callback));
+        dom_gen::recycle_dom(&mut allocator, dom);
+    });
+
+    println!("allocations per frame ({} nodes with a callback): {} without DomAllocator, {} with DomAllocator",
+        NODES + 1, without_allocator, with_allocator);
+
+    c.bench_function("frame 10k nodes without DomAllocator", |b| {
+        b.iter(|| dom_gen::callback_dom::<BenchLayout>(NODES, Callback(callback)))
+    });
+
+    c.bench_function("frame 10k nodes with DomAllocator", move |b| {
+        b.iter(|| {
+            let dom = dom_gen::allocated_callback_dom(&mut allocator, NODES, Callback(callback));
+            dom_gen::recycle_dom(&mut allocator, dom);
+        })
+    });
+}
+
+criterion_group!(benches, frame_allocations);
+criterion_main!(benches);
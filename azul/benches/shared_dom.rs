@@ -0,0 +1,89 @@
+//! Benchmarks for adding a static sidebar to the DOM of every frame, either by building
+//! it again or as a `SharedDom`, run with `cargo bench --features benchmarks`.
+//!
+//! The nodes of a `SharedDom` aren't cloned per frame, so the allocations of a frame with a
+//! shared sidebar don't depend on the size of the sidebar (printed for 301 and 3001 nodes).
+
+#[macro_use]
+extern crate criterion;
+extern crate azul;
+
+use std::{
+    alloc::{GlobalAlloc, Layout as AllocLayout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use criterion::Criterion;
+use azul::prelude::*;
+
+/// Counts the allocations, so that the benchmark can print the allocations per frame
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: AllocLayout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: AllocLayout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+struct BenchLayout { }
+
+impl Layout for BenchLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+/// `entries` entries with an icon and a label each: 3 * `entries` + 1 nodes
+fn sidebar(entries: usize) -> Dom<BenchLayout> {
+    Dom::div().with_id("sidebar").with_children((0..entries).map(|i| {
+        Dom::div().with_class("entry")
+            .with_child(Dom::div().with_class("icon"))
+            .with_child(Dom::label(format!("Entry {}", i)).with_class("entry-label"))
+    }))
+}
+
+fn frame(shared_sidebar: Option<&SharedDom<BenchLayout>>) -> UiState<BenchLayout> {
+    let mut dom = Dom::div().with_child(Dom::div().with_id("header"));
+    match shared_sidebar {
+        Some(shared) => dom.add_shared_child(shared),
+        None => dom.add_child(sidebar(1000)),
+    }
+    UiState::from_dom(dom.with_child(Dom::label("Body")))
+}
+
+fn allocations_per_frame(shared_sidebar: Option<&SharedDom<BenchLayout>>) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let ui_state = frame(shared_sidebar);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    drop(ui_state);
+    allocations
+}
+
+fn static_sidebar(c: &mut Criterion) {
+    let small_shared = sidebar(100).shared();
+    let shared = sidebar(1000).shared();
+    println!("allocations per frame with a 3001 node sidebar: {} rebuilt, {} shared",
+        allocations_per_frame(None), allocations_per_frame(Some(&shared)));
+    println!("allocations per frame with a shared sidebar: {} with 301 nodes, {} with 3001 nodes",
+        allocations_per_frame(Some(&small_shared)), allocations_per_frame(Some(&shared)));
+
+    c.bench_function("frame with 3k node sidebar (rebuilt)", |b| {
+        b.iter(|| frame(None))
+    });
+
+    c.bench_function("frame with 3k node sidebar (shared)", move |b| {
+        b.iter(|| frame(Some(&shared)))
+    });
+}
+
+criterion_group!(benches, static_sidebar);
+criterion_main!(benches);
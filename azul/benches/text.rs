@@ -0,0 +1,58 @@
+//! Benchmarks for shaping the text of labels, run with `cargo bench --features benchmarks`.
+
+#[macro_use]
+extern crate criterion;
+extern crate azul;
+
+use criterion::Criterion;
+use azul::{
+    prelude::{Font, FontId, StyleFontSize},
+    font::rusttype_load_font,
+    text_cache::TextCache,
+    text_layout::shape_text_cached,
+    testing::DEFAULT_TEST_FONT,
+};
+
+/// Like a chat UI: 1000 labels, but only 10 different strings ("Today", user names, etc.)
+fn label_texts() -> Vec<String> {
+    (0..1000).map(|i| format!("user {}", i % 10)).collect()
+}
+
+/// Shapes all labels, returns how often a text had to be shaped
+fn shape_labels(font: &Font<'static>, texts: &[String], shared_cache: bool) -> usize {
+    let font_id = FontId::ExternalFont("KoHo".into());
+    let font_size = StyleFontSize::px(12.0);
+    let mut text_cache = TextCache::default();
+    let mut shaping_count = 0;
+
+    for text in texts {
+        if !shared_cache {
+            // Every node shapes its own text
+            shaping_count += text_cache.get_shaping_count();
+            text_cache = TextCache::default();
+        }
+        shape_text_cached(text, font, &font_id, &font_size, None, &mut text_cache);
+    }
+
+    shaping_count + text_cache.get_shaping_count()
+}
+
+fn shaping(c: &mut Criterion) {
+    let (font, _) = rusttype_load_font(DEFAULT_TEST_FONT.to_vec(), None).unwrap();
+    let texts = label_texts();
+    println!("shaping runs for {} labels: {} per node, {} with the shared cache",
+        texts.len(), shape_labels(&font, &texts, false), shape_labels(&font, &texts, true));
+
+    let font_clone = font.clone();
+    let texts_clone = texts.clone();
+    c.bench_function("shape 1k labels, 10 distinct texts (per node)", move |b| {
+        b.iter(|| shape_labels(&font_clone, &texts_clone, false))
+    });
+
+    c.bench_function("shape 1k labels, 10 distinct texts (shared cache)", move |b| {
+        b.iter(|| shape_labels(&font, &texts, true))
+    });
+}
+
+criterion_group!(benches, shaping);
+criterion_main!(benches);
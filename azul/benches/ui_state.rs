@@ -0,0 +1,70 @@
+//! Benchmarks for the storage of the `UiState`, each compared with the storage that
+//! it replaced, run with `cargo bench --features benchmarks`.
+
+#[macro_use]
+extern crate criterion;
+extern crate azul;
+
+use criterion::Criterion;
+use azul::{prelude::*, dom_gen};
+
+struct BenchLayout { }
+
+impl Layout for BenchLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+fn callback(_: &mut AppState<BenchLayout>, _: &mut CallbackInfo<BenchLayout>) -> UpdateScreen {
+    DontRedraw
+}
+
+fn callback_dom(percent: usize) -> Dom<BenchLayout> {
+    let mut dom = dom_gen::wide_dom::<BenchLayout>(10_000);
+    dom_gen::distribute_callbacks(&mut dom, percent, Callback(callback));
+    dom
+}
+
+fn collect_callbacks(c: &mut Criterion) {
+    c.bench_function_over_inputs("collect hover callbacks 10k nodes (flat lists), % of nodes with callbacks", |b, &percent| {
+        let dom = callback_dom(percent);
+        b.iter(|| dom_gen::flat_hover_callbacks(&dom))
+    }, vec![10, 100]);
+
+    c.bench_function_over_inputs("collect hover callbacks 10k nodes (BTreeMap), % of nodes with callbacks", |b, &percent| {
+        let dom = callback_dom(percent);
+        b.iter(|| dom_gen::btree_hover_callbacks(&dom))
+    }, vec![10, 100]);
+}
+
+fn lookup_callbacks(c: &mut Criterion) {
+    let dom = callback_dom(100);
+    let nodes = dom.len();
+    let flat = dom_gen::flat_hover_callbacks(&dom);
+    c.bench_function("look up MouseUp callback of 10k nodes (flat lists)", move |b| {
+        b.iter(|| dom_gen::lookup_flat_hover_callbacks(&flat, nodes, HoverEventFilter::MouseUp))
+    });
+
+    let btree = dom_gen::btree_hover_callbacks(&dom);
+    c.bench_function("look up MouseUp callback of 10k nodes (BTreeMap)", move |b| {
+        b.iter(|| dom_gen::lookup_btree_hover_callbacks(&btree, nodes, HoverEventFilter::MouseUp))
+    });
+}
+
+fn collect_css_overrides(c: &mut Criterion) {
+    let mut dom = dom_gen::wide_dom::<BenchLayout>(10_000);
+    dom_gen::distribute_css_overrides(&mut dom, 30);
+    c.bench_function("collect CSS overrides 10k nodes, 30% with overrides (shared lists)", move |b| {
+        b.iter(|| dom_gen::shared_css_overrides(&dom))
+    });
+
+    let mut dom = dom_gen::wide_dom::<BenchLayout>(10_000);
+    dom_gen::distribute_css_overrides(&mut dom, 30);
+    c.bench_function("collect CSS overrides 10k nodes, 30% with overrides (cloned hash maps)", move |b| {
+        b.iter(|| dom_gen::cloned_css_overrides(&dom))
+    });
+}
+
+criterion_group!(benches, collect_callbacks, lookup_callbacks, collect_css_overrides);
+criterion_main!(benches);
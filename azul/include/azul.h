@@ -0,0 +1,217 @@
+#ifndef AZUL_H
+#define AZUL_H
+
+/* Warning, this file is autogenerated by cbindgen. Don't modify this manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/* The function completed successfully */
+#define AZUL_OK 0
+
+/* A handle or string argument was null */
+#define AZUL_ERROR_NULL_ARGUMENT 1
+
+/* The CSS passed to `azul_app_run` could not be parsed */
+#define AZUL_ERROR_CSS 2
+
+/* The window could not be created */
+#define AZUL_ERROR_WINDOW 3
+
+/* The app loop exited with an error */
+#define AZUL_ERROR_RUNTIME 4
+
+/* Returned by the `azul_app_add_*` functions if the resource could not be added */
+#define AZUL_INVALID_ID UINTPTR_MAX
+
+/* `azul_app_add_image_raw`: one byte per pixel (greyscale) */
+#define AZUL_IMAGE_FORMAT_R8 0
+
+/* `azul_app_add_image_raw`: four bytes per pixel, premultiplied BGRA */
+#define AZUL_IMAGE_FORMAT_BGRA8 1
+
+/* Event filter code for `azul_dom_add_callback`, see `On::MouseOver` */
+#define AZUL_ON_MOUSE_OVER 0
+
+/* Event filter code for `azul_dom_add_callback`, see `On::MouseDown` */
+#define AZUL_ON_MOUSE_DOWN 1
+
+/* Event filter code for `azul_dom_add_callback`, see `On::LeftMouseDown` */
+#define AZUL_ON_LEFT_MOUSE_DOWN 2
+
+/* Event filter code for `azul_dom_add_callback`, see `On::MiddleMouseDown` */
+#define AZUL_ON_MIDDLE_MOUSE_DOWN 3
+
+/* Event filter code for `azul_dom_add_callback`, see `On::RightMouseDown` */
+#define AZUL_ON_RIGHT_MOUSE_DOWN 4
+
+/* Event filter code for `azul_dom_add_callback`, see `On::MouseUp` */
+#define AZUL_ON_MOUSE_UP 5
+
+/* Event filter code for `azul_dom_add_callback`, see `On::LeftMouseUp` */
+#define AZUL_ON_LEFT_MOUSE_UP 6
+
+/* Event filter code for `azul_dom_add_callback`, see `On::MiddleMouseUp` */
+#define AZUL_ON_MIDDLE_MOUSE_UP 7
+
+/* Event filter code for `azul_dom_add_callback`, see `On::RightMouseUp` */
+#define AZUL_ON_RIGHT_MOUSE_UP 8
+
+/* Event filter code for `azul_dom_add_callback`, see `On::MouseEnter` */
+#define AZUL_ON_MOUSE_ENTER 9
+
+/* Event filter code for `azul_dom_add_callback`, see `On::MouseLeave` */
+#define AZUL_ON_MOUSE_LEAVE 10
+
+/* Event filter code for `azul_dom_add_callback`, see `On::Scroll` */
+#define AZUL_ON_SCROLL 11
+
+/* Event filter code for `azul_dom_add_callback`, see `On::TextInput` */
+#define AZUL_ON_TEXT_INPUT 12
+
+/* Event filter code for `azul_dom_add_callback`, see `On::VirtualKeyDown` */
+#define AZUL_ON_VIRTUAL_KEY_DOWN 13
+
+/* Event filter code for `azul_dom_add_callback`, see `On::VirtualKeyUp` */
+#define AZUL_ON_VIRTUAL_KEY_UP 14
+
+/* Event filter code for `azul_dom_add_callback`, see `On::HoveredFile` */
+#define AZUL_ON_HOVERED_FILE 15
+
+/* Event filter code for `azul_dom_add_callback`, see `On::DroppedFile` */
+#define AZUL_ON_DROPPED_FILE 16
+
+/* Event filter code for `azul_dom_add_callback`, see `On::HoveredFileCancelled` */
+#define AZUL_ON_HOVERED_FILE_CANCELLED 17
+
+/* Event filter code for `azul_dom_add_callback`, see `On::FocusReceived` */
+#define AZUL_ON_FOCUS_RECEIVED 18
+
+/* Event filter code for `azul_dom_add_callback`, see `On::FocusLost` */
+#define AZUL_ON_FOCUS_LOST 19
+
+/* Event filter code for `azul_dom_add_callback`, see `On::DoubleClick` */
+#define AZUL_ON_DOUBLE_CLICK 20
+
+/* Event filter code for `azul_dom_add_callback`, see `On::LeftDoubleClick` */
+#define AZUL_ON_LEFT_DOUBLE_CLICK 21
+
+/* Event filter code for `azul_dom_add_callback`, see `On::MiddleDoubleClick` */
+#define AZUL_ON_MIDDLE_DOUBLE_CLICK 22
+
+/* Event filter code for `azul_dom_add_callback`, see `On::RightDoubleClick` */
+#define AZUL_ON_RIGHT_DOUBLE_CLICK 23
+
+/* Event filter code for `azul_dom_add_callback`, see `On::ScrollUp` */
+#define AZUL_ON_SCROLL_UP 24
+
+/* Event filter code for `azul_dom_add_callback`, see `On::ScrollDown` */
+#define AZUL_ON_SCROLL_DOWN 25
+
+/* Event filter code for `azul_dom_add_callback`, see `On::ScrollHorizontal` */
+#define AZUL_ON_SCROLL_HORIZONTAL 26
+
+/* Event filter code for `azul_dom_add_callback`, see `On::DroppedData` */
+#define AZUL_ON_DROPPED_DATA 27
+
+/* Opaque handle to an `App` */
+typedef struct AzulApp AzulApp;
+
+/* Opaque handle to a `Dom`, together with its C callbacks */
+typedef struct AzulDom AzulDom;
+
+/* Opaque handle to a single `NodeData`, together with its C callbacks */
+typedef struct AzulNodeData AzulNodeData;
+
+/* Builds the DOM of the current frame, called by azul on every frame */
+typedef AzulDom *(*AzulLayoutFn)(void *user_data);
+
+/* Passed to every `AzulCallbackFn` */
+typedef struct AzulCallbackContext {
+  /* The `user_data` of `azul_app_new` */
+  void *user_data;
+  /* Index of the node that the event fired on, in document order */
+  uintptr_t node_index;
+} AzulCallbackContext;
+
+/*
+ Called when the event of `azul_dom_add_callback` fires, returns
+ `1` if the screen should be redrawn, `0` otherwise
+ */
+typedef uint8_t (*AzulCallbackFn)(AzulCallbackContext *context);
+
+/* Creates a new app, `layout_fn` is called with `user_data` on every frame */
+AzulApp *azul_app_new(AzulLayoutFn layout_fn, void *user_data);
+
+/* Frees an app that was never run */
+void azul_app_free(AzulApp *app);
+
+/*
+ Registers decoded pixels as an image, `format` is one of the `AZUL_IMAGE_FORMAT_*`
+ constants. Returns the ID for `azul_dom_new_image` or `AZUL_INVALID_ID`.
+ */
+uintptr_t azul_app_add_image_raw(AzulApp *app,
+                                 const char *id,
+                                 const uint8_t *pixels,
+                                 uintptr_t pixels_len,
+                                 uint32_t width,
+                                 uint32_t height,
+                                 uint32_t format);
+
+/* Registers a text, returns the ID for `azul_dom_new_text` or `AZUL_INVALID_ID` */
+uintptr_t azul_app_add_text(AzulApp *app, const char *text);
+
+/*
+ Opens a window styled with `css` (the native style if `css` is null)
+ and runs the app until all windows are closed. Takes ownership of the app.
+ */
+int32_t azul_app_run(AzulApp *app, const char *css);
+
+AzulDom *azul_dom_new_div(void);
+
+AzulDom *azul_dom_new_label(const char *text);
+
+/* `text_id` is the return value of `azul_app_add_text` */
+AzulDom *azul_dom_new_text(uintptr_t text_id);
+
+/* `image_id` is the return value of `azul_app_add_image_raw` */
+AzulDom *azul_dom_new_image(uintptr_t image_id);
+
+/* Creates a DOM with the node as its root, takes ownership of the node data */
+AzulDom *azul_dom_from_node_data(AzulNodeData *node_data);
+
+/*
+ Appends `child` to the root of `parent`, takes ownership of the child.
+ Returns `1` on success, `0` if one of the handles is null.
+ */
+uint8_t azul_dom_add_child(AzulDom *parent, AzulDom *child);
+
+uint8_t azul_dom_add_id(AzulDom *dom, const char *id);
+
+uint8_t azul_dom_add_class(AzulDom *dom, const char *class);
+
+/*
+ Calls `callback` when the event `event_filter` (one of the `AZUL_ON_*` constants)
+ fires on the root of the DOM. Returns `0` if a handle is null or the code is unknown.
+ */
+uint8_t azul_dom_add_callback(AzulDom *dom, uint32_t event_filter, AzulCallbackFn callback);
+
+/* Frees a DOM that was not passed to `azul_dom_add_child` or returned from the layout function */
+void azul_dom_free(AzulDom *dom);
+
+AzulNodeData *azul_node_data_new_div(void);
+
+uint8_t azul_node_data_add_id(AzulNodeData *node_data, const char *id);
+
+uint8_t azul_node_data_add_class(AzulNodeData *node_data, const char *class);
+
+/* Same as `azul_dom_add_callback`, but for a single node */
+uint8_t azul_node_data_add_callback(AzulNodeData *node_data,
+                                    uint32_t event_filter,
+                                    AzulCallbackFn callback);
+
+void azul_node_data_free(AzulNodeData *node_data);
+
+#endif /* AZUL_H */
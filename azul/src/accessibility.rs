@@ -0,0 +1,373 @@
+//! Platform-independent accessibility tree of a frame, for screen readers.
+//!
+//! `AccessibilityTree::new()` maps every node of a `UiState` to an accessible
+//! element with a role, a name, the solved bounds of the node and its focus state.
+//! The DOM doesn't have explicit accessibility attributes (yet), so the role and the
+//! name are inferred: a node that reacts to clicks is a button, named by the text of
+//! its descendants, labels and texts are static text and images are images.
+//!
+//! The platform side (Windows UI Automation, AT-SPI, AccessKit, ...) is an
+//! `AccessibilityAdapter`, set with `Window::with_accessibility_adapter()`. Every frame
+//! that rebuilds the `UiState` sends the new tree to the adapter, a frame that only moves
+//! the focus sends the focused node. The actions of the assistive technology are taken
+//! from the adapter before the events of the frame are handled: an `Invoke` calls the
+//! `LeftMouseUp` callback of the node, a `Focus` moves the keyboard focus to the node.
+//!
+//! Azul itself doesn't ship an adapter for any platform yet, since none of the platform
+//! accessibility APIs are dependencies of this crate - the application has to provide one.
+
+use std::collections::BTreeMap;
+use webrender::api::LayoutRect;
+use {
+    FastHashMap,
+    app::{CallbackEvent, CallCallbackReturn, call_normal_callbacks},
+    app_resources::AppResources,
+    app_state::AppState,
+    dom::{Callback, NodeType, EventFilter, HoverEventFilter, Redraw},
+    error_overlay::CallbackPanic,
+    focus::FocusTarget,
+    id_tree::NodeId,
+    text_cache::TextId,
+    traits::Layout,
+    ui_state::{UiState, find_callback},
+    window::LayoutSnapshot,
+    window_state::{CallbacksOfHitTest, DetermineCallbackResult},
+};
+
+/// What kind of element a node is, for the assistive technology
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AccessibleRole {
+    /// The root node of the window
+    Window,
+    /// A container without any content of its own
+    Group,
+    /// A node that reacts to clicks (has a `LeftMouseUp` or `MouseUp` callback)
+    Button,
+    /// A `Label` or `Text` node
+    StaticText,
+    /// An `Image` node
+    Image,
+    /// A `GlTexture` or `IFrame`, whose content isn't known
+    Unknown,
+}
+
+/// One node of the `AccessibilityTree`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessibleNode {
+    pub role: AccessibleRole,
+    /// What the screen reader reads for the node: the text of a label,
+    /// the text of the descendants of a button, the first ID of other nodes
+    pub name: Option<String>,
+    /// Solved rectangle of the node, in logical pixels relative to the window.
+    /// `None` if the node isn't part of the layout that the tree was built from.
+    pub bounds: Option<LayoutRect>,
+    /// Whether the node can receive the keyboard focus (has a tab index)
+    pub is_focusable: bool,
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
+}
+
+/// The accessible elements of one frame, indexed by the `NodeId` of the DOM
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessibilityTree {
+    pub nodes: BTreeMap<NodeId, AccessibleNode>,
+    pub root: NodeId,
+    pub focused_node: Option<NodeId>,
+}
+
+/// Action requested by the assistive technology
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AccessibilityAction {
+    /// Activate the node, like a click (UIA `Invoke`, AT-SPI `DoAction`)
+    Invoke(NodeId),
+    /// Move the keyboard focus to the node
+    Focus(NodeId),
+}
+
+/// The bridge to the accessibility API of the platform
+pub trait AccessibilityAdapter {
+    /// Called with the new tree whenever the `UiState` of the window changes
+    fn update_tree(&mut self, tree: &AccessibilityTree);
+    /// Called whenever the focused node changes, without rebuilding the tree
+    fn update_focus(&mut self, focused_node: Option<NodeId>);
+    /// Returns the actions that the assistive technology requested since the last call
+    fn take_actions(&mut self) -> Vec<AccessibilityAction>;
+}
+
+impl AccessibilityTree {
+
+    /// Builds the tree of the `ui_state`, `layout` has to be the layout of the same frame
+    /// (see `FakeWindow::get_layout_snapshot()`)
+    pub fn new<T: Layout>(
+        ui_state: &UiState<T>,
+        layout: &LayoutSnapshot,
+        focused_node: Option<NodeId>,
+        app_resources: &AppResources)
+    -> Self
+    {
+        Self::new_with_strings(ui_state, layout, focused_node, &app_resources.text_cache.string_cache)
+    }
+
+    pub(crate) fn new_with_strings<T: Layout>(
+        ui_state: &UiState<T>,
+        layout: &LayoutSnapshot,
+        focused_node: Option<NodeId>,
+        strings: &FastHashMap<TextId, String>)
+    -> Self
+    {
+        let dom = &ui_state.dom;
+        let node_hierarchy = &dom.arena.node_layout;
+        let node_data = &dom.arena.node_data;
+
+        let focusable_nodes = ui_state.tab_index_tags.values().map(|(node_id, _)| *node_id).collect::<Vec<NodeId>>();
+
+        let text_of = |node_id: NodeId| -> Option<String> {
+            match &node_data[node_id].node_type {
+                NodeType::Label(text) => Some(text.clone()),
+                NodeType::Text(text_id) => strings.get(text_id).cloned(),
+                _ => None,
+            }
+        };
+
+        let nodes = dom.root.descendants(node_hierarchy).map(|node_id| {
+
+            let role = if node_id == dom.root {
+                AccessibleRole::Window
+            } else if is_clickable(ui_state, node_id) {
+                AccessibleRole::Button
+            } else {
+                match node_data[node_id].node_type {
+                    NodeType::Div => AccessibleRole::Group,
+                    NodeType::Label(_) | NodeType::Text(_) => AccessibleRole::StaticText,
+                    NodeType::Image(_) => AccessibleRole::Image,
+                    NodeType::GlTexture(_) | NodeType::IFrame(_) => AccessibleRole::Unknown,
+                }
+            };
+
+            let name = match role {
+                AccessibleRole::Button | AccessibleRole::StaticText => {
+                    let texts = node_id.descendants(node_hierarchy).filter_map(&text_of).collect::<Vec<String>>();
+                    if texts.is_empty() { None } else { Some(texts.join(" ")) }
+                },
+                _ => None,
+            }.or_else(|| node_data[node_id].ids.first().cloned());
+
+            (node_id, AccessibleNode {
+                role,
+                name,
+                bounds: layout.get_rect(node_id),
+                is_focusable: focusable_nodes.contains(&node_id),
+                parent: node_hierarchy[node_id].parent,
+                children: node_id.children(node_hierarchy).collect(),
+            })
+        }).collect();
+
+        Self {
+            nodes,
+            root: dom.root,
+            focused_node,
+        }
+    }
+
+    /// Returns the callback that an `AccessibilityAction::Invoke` of the node
+    /// dispatches: the `LeftMouseUp` callback, or else the `MouseUp` callback
+    pub(crate) fn invoke_callback<T: Layout>(ui_state: &UiState<T>, node_id: NodeId) -> Option<Callback<T>> {
+        let callbacks = ui_state.hover_callbacks.get(&node_id)?;
+        find_callback(callbacks, &HoverEventFilter::LeftMouseUp)
+            .or_else(|| find_callback(callbacks, &HoverEventFilter::MouseUp))
+    }
+}
+
+/// Calls the callbacks of the `Invoke` actions and moves the focus to the node of the last
+/// `Focus` action. A focus that one of the callbacks sets wins over the `Focus` actions.
+pub(crate) fn dispatch_accessibility_actions<T: Layout>(
+    actions: &[AccessibilityAction],
+    callback_event: &CallbackEvent<T>,
+    app_state: &mut AppState<T>,
+    callback_panic: &mut Option<CallbackPanic>)
+-> CallCallbackReturn
+{
+    let mut callbacks = CallbacksOfHitTest::default();
+    let mut new_focus = None;
+
+    for action in actions {
+        match *action {
+            AccessibilityAction::Invoke(node_id) => {
+                if let Some(callback) = AccessibilityTree::invoke_callback(callback_event.ui_state, node_id) {
+                    callbacks.nodes_with_callbacks.entry(node_id)
+                        .or_insert_with(DetermineCallbackResult::default)
+                        .normal_callbacks.insert(EventFilter::Hover(HoverEventFilter::LeftMouseUp), callback);
+                }
+            },
+            AccessibilityAction::Focus(node_id) => new_focus = Some(FocusTarget::Id(node_id)),
+        }
+    }
+
+    let mut call_result = CallCallbackReturn::default();
+    call_normal_callbacks(&callbacks, callback_event, app_state, None, callback_panic, &mut call_result);
+
+    if call_result.callbacks_overwrites_focus.is_none() && new_focus.is_some() {
+        call_result.callbacks_overwrites_focus = new_focus;
+        // The focus is only applied when the DOM is restyled
+        call_result.should_update_screen = Redraw;
+    }
+
+    call_result
+}
+
+fn is_clickable<T: Layout>(ui_state: &UiState<T>, node_id: NodeId) -> bool {
+    let is_click_filter = |filter: &HoverEventFilter| *filter == HoverEventFilter::LeftMouseUp || *filter == HoverEventFilter::MouseUp;
+    ui_state.hover_callbacks.get(&node_id).map(|c| c.iter().any(|(f, _)| is_click_filter(f))).unwrap_or(false) ||
+    ui_state.hover_default_callbacks.get(&node_id).map(|c| c.iter().any(|(f, _)| is_click_filter(f))).unwrap_or(false)
+}
+
+#[cfg(test)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> ::dom::Dom<Self> {
+        ::dom::Dom::div()
+    }
+}
+
+#[cfg(test)]
+fn on_click(_: &mut ::app_state::AppState<TestLayout>, _: &mut ::window::CallbackInfo<TestLayout>) -> ::dom::UpdateScreen {
+    ::dom::Redraw
+}
+
+#[cfg(test)]
+fn on_hover(_: &mut ::app_state::AppState<TestLayout>, _: &mut ::window::CallbackInfo<TestLayout>) -> ::dom::UpdateScreen {
+    ::dom::DontRedraw
+}
+
+/// Platform API that records what it was told and replays scripted actions
+#[cfg(test)]
+#[derive(Default)]
+struct MockPlatform {
+    trees: Vec<AccessibilityTree>,
+    focus_updates: Vec<Option<NodeId>>,
+    pending_actions: Vec<AccessibilityAction>,
+}
+
+#[cfg(test)]
+impl AccessibilityAdapter for MockPlatform {
+    fn update_tree(&mut self, tree: &AccessibilityTree) {
+        self.trees.push(tree.clone());
+    }
+    fn update_focus(&mut self, focused_node: Option<NodeId>) {
+        self.focus_updates.push(focused_node);
+    }
+    fn take_actions(&mut self) -> Vec<AccessibilityAction> {
+        self.pending_actions.drain(..).collect()
+    }
+}
+
+// div
+//     div.button (LeftMouseUp, focusable)
+//         label "Save"
+//     label "Status: ok" (MouseEnter only)
+#[cfg(test)]
+fn test_ui_state() -> UiState<TestLayout> {
+    use dom::{Dom, On, TabIndex};
+    UiState::from_dom(Dom::div()
+        .with_child(Dom::div().with_class("button")
+            .with_callback(On::LeftMouseUp, Callback(on_click))
+            .with_tab_index(TabIndex::Auto)
+            .with_child(Dom::label("Save")))
+        .with_child(Dom::label("Status: ok").with_callback(On::MouseEnter, Callback(on_hover))))
+}
+
+#[cfg(test)]
+fn test_layout() -> LayoutSnapshot {
+    use std::rc::Rc;
+    use webrender::api::{LayoutPoint, LayoutSize};
+    use layout_result::LayoutResult;
+    let rect = |x: f32, y: f32, w: f32, h: f32| LayoutRect::new(LayoutPoint::new(x, y), LayoutSize::new(w, h));
+    LayoutSnapshot {
+        layout: Rc::new(LayoutResult::from_border_boxes(vec![
+            rect(0.0, 0.0, 200.0, 100.0),
+            rect(10.0, 10.0, 80.0, 30.0),
+            rect(15.0, 15.0, 70.0, 20.0),
+            rect(10.0, 50.0, 180.0, 20.0),
+        ])),
+        .. LayoutSnapshot::default()
+    }
+}
+
+#[test]
+fn test_roles_names_and_bounds_are_inferred() {
+
+    use webrender::api::{LayoutPoint, LayoutSize};
+
+    let ui_state = test_ui_state();
+    let tree = AccessibilityTree::new_with_strings(&ui_state, &test_layout(), None, &FastHashMap::default());
+
+    let button = &tree.nodes[&NodeId::new(1)];
+    assert_eq!(tree.nodes[&tree.root].role, AccessibleRole::Window);
+    assert_eq!(button.role, AccessibleRole::Button);
+    assert_eq!(button.name, Some("Save".to_string()));
+    assert_eq!(button.bounds, Some(LayoutRect::new(LayoutPoint::new(10.0, 10.0), LayoutSize::new(80.0, 30.0))));
+    assert!(button.is_focusable);
+    assert_eq!(button.children, vec![NodeId::new(2)]);
+
+    let status = &tree.nodes[&NodeId::new(3)];
+    assert_eq!(status.role, AccessibleRole::StaticText);
+    assert_eq!(status.name, Some("Status: ok".to_string()));
+    assert!(!status.is_focusable);
+}
+
+#[test]
+fn test_adapter_receives_tree_and_focus_and_invokes_the_click_callback() {
+
+    use glium::glutin::dpi::LogicalSize;
+    use window::new_window_id;
+
+    let ui_state = test_ui_state();
+    let mut platform = MockPlatform::default();
+    let button = NodeId::new(1);
+    let status = NodeId::new(3);
+
+    let tree = AccessibilityTree::new_with_strings(&ui_state, &test_layout(), None, &FastHashMap::default());
+    platform.update_tree(&tree);
+    platform.update_focus(Some(button));
+
+    assert_eq!(platform.trees[0].nodes.len(), 4);
+    assert_eq!(platform.focus_updates, vec![Some(button)]);
+
+    let window_id = new_window_id();
+    let callback_event = CallbackEvent {
+        window_id: &window_id,
+        ui_state: &ui_state,
+        hit_test_items: &[],
+        window_size: LogicalSize::new(200.0, 100.0),
+        window_position: None,
+    };
+
+    // Dispatches the actions that the platform requested, like the frame loop does
+    let dispatch = |platform: &mut MockPlatform, actions: Vec<AccessibilityAction>| {
+        platform.pending_actions.extend(actions);
+        let mut app_state = AppState::new(TestLayout { });
+        let mut callback_panic = None;
+        let call_result = dispatch_accessibility_actions(&platform.take_actions(), &callback_event, &mut app_state, &mut callback_panic);
+        assert!(callback_panic.is_none());
+        assert!(platform.take_actions().is_empty());
+        call_result
+    };
+
+    // The button invokes its LeftMouseUp callback, which redraws the screen
+    let call_result = dispatch(&mut platform, vec![AccessibilityAction::Invoke(button), AccessibilityAction::Invoke(status)]);
+    assert_eq!(call_result.should_update_screen, Redraw);
+    assert_eq!(call_result.callbacks_overwrites_focus, None);
+
+    // The label has no click callback
+    let call_result = dispatch(&mut platform, vec![AccessibilityAction::Invoke(status)]);
+    assert_eq!(call_result.should_update_screen, ::dom::DontRedraw);
+
+    // Moving the focus restyles the DOM
+    let call_result = dispatch(&mut platform, vec![AccessibilityAction::Focus(button)]);
+    assert_eq!(call_result.should_update_screen, Redraw);
+    assert_eq!(call_result.callbacks_overwrites_focus, Some(FocusTarget::Id(button)));
+
+    assert_eq!(AccessibilityTree::invoke_callback(&ui_state, button).map(|c| c.0 as usize), Some(on_click as usize));
+}
@@ -2,6 +2,7 @@ use std::{
     mem,
     fmt,
     io::Read,
+    path::PathBuf,
     collections::BTreeMap,
     sync::{Arc, Mutex, PoisonError},
 };
@@ -17,7 +18,7 @@ use glium::{
 use webrender::{
     PipelineInfo,
     api::{
-        HitTestResult, HitTestFlags, DevicePixel,
+        HitTestResult, HitTestItem, HitTestFlags, DevicePixel,
         WorldPoint, LayoutSize, LayoutPoint,
         Epoch, Transaction, ImageFormat as RawImageFormat,
     },
@@ -31,18 +32,31 @@ use images::ImageType;
 use azul_css::{FontId, PixelValue, StyleLetterSpacing};
 use {
     error::{FontError, ClipboardError},
-    window::{Window, WindowId, FakeWindow, ScrollStates},
-    window_state::WindowSize,
+    window::{Window, WindowId, FakeWindow, ScrollStates, LayoutSnapshot, CallbackInfo},
+    window_state::{WindowSize, CallbacksOfHitTest, handle_close_events, determine_drop_callbacks},
     text_cache::TextId,
-    dom::{ScrollTagId, UpdateScreen},
+    dom::{Dom, DomError, ScrollTagId, UpdateScreen},
     app_resources::AppResources,
     app_state::AppState,
     traits::Layout,
+    id_tree::NodeId,
     ui_state::UiState,
     ui_description::UiDescription,
     daemon::Daemon,
     focus::FocusTarget,
+    profiling::{FrameProfiler, FramePhase},
+    dispatch_trace::DispatchTraceEvent,
+    error_overlay::{CallbackPanic, catch_callback_panic, should_dismiss_error_overlay},
+    node_identity::resolve_node_identities,
+    dom::CallbackRegistry,
+    xml::{HotReloadUi, XmlFileWatcher, XmlReloadHandler},
+    menu::{WindowMenu, dispatch_menu_events},
+    accessibility::{AccessibilityTree, dispatch_accessibility_actions},
+    drag_drop::{DragData, DroppedData, add_dropped_data},
+    diagnostics::{AzulError, LogLevel, Logger, ErrorHandler, log, report},
 };
+#[cfg(feature = "logging")]
+use diagnostics::PanicReport;
 
 type DeviceUintSize = ::euclid::TypedSize2D<u32, DevicePixel>;
 type DeviceIntSize = ::euclid::TypedSize2D<i32, DevicePixel>;
@@ -66,6 +80,9 @@ pub enum RuntimeError<T: Layout> {
     MutexPoisonError(PoisonError<T>),
     MutexLockError,
     WindowIndexError,
+    /// The DOM returned by `Layout::layout()` is malformed, see `Dom::validate()`.
+    /// Doesn't stop the app, the window keeps showing the last valid frame.
+    InvalidDom(DomError),
 }
 
 impl<T: Layout> From<PoisonError<T>> for RuntimeError<T> {
@@ -89,6 +106,7 @@ impl<T: Layout> fmt::Debug for RuntimeError<T> {
             MutexPoisonError(e) => write!(f, "Mutex poisoned (thread panicked unexpectedly): {}", e),
             MutexLockError => write!(f, "Failed to lock application state mutex"),
             WindowIndexError => write!(f, "Invalid window index"),
+            InvalidDom(e) => write!(f, "Invalid DOM: {}", e),
         }
     }
 }
@@ -124,7 +142,7 @@ impl Default for FrameEventInfo {
 }
 
 /// Configuration for optional features, such as whether to enable logging or panic hooks
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 #[cfg_attr(not(feature = "logging"), derive(Copy))]
 pub struct AppConfig {
     /// If enabled, logs error and info messages.
@@ -146,6 +164,48 @@ pub struct AppConfig {
     /// (STUB) Whether keyboard navigation should be enabled (default: true).
     /// Currently not implemented.
     pub enable_tab_navigation: bool,
+    /// Whether the app should run in the deterministic mode (default: false),
+    /// see the [`deterministic`](../deterministic/index.html) module.
+    pub deterministic: bool,
+    /// If `false` (the default), a panic in a callback is caught and shown in an
+    /// error overlay inside of the window, instead of exiting the app, see the
+    /// [`error_overlay`](../error_overlay/index.html) module. Set this to `true`
+    /// to let the panic unwind (and exit the app) instead.
+    pub abort_on_callback_panic: bool,
+    /// Receives all warnings and log messages of azul (default: `None`, the messages
+    /// go to the `log` crate or to stderr), see the
+    /// [`diagnostics`](../diagnostics/index.html) module.
+    pub logger: Option<Logger>,
+    /// Called for every non-fatal internal error (default: `None`), for example
+    /// to send telemetry - the error is logged afterwards.
+    pub error_handler: Option<ErrorHandler>,
+    /// Called with the message, the backtrace and the last dispatched events
+    /// (and frame stats) before the app exits because of a panic (default: `None`).
+    /// Panics in callbacks that are shown in the error overlay don't invoke the hook.
+    #[cfg(feature = "logging")]
+    pub panic_hook: Option<fn(&PanicReport)>,
+}
+
+// Function pointers with reference arguments don't implement Debug
+impl fmt::Debug for AppConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = f.debug_struct("AppConfig");
+        #[cfg(feature = "logging")] {
+            s.field("enable_logging", &self.enable_logging);
+            s.field("log_file_path", &self.log_file_path);
+            s.field("enable_visual_panic_hook", &self.enable_visual_panic_hook);
+            s.field("enable_logging_on_panic", &self.enable_logging_on_panic);
+        }
+        s.field("enable_tab_navigation", &self.enable_tab_navigation);
+        s.field("deterministic", &self.deterministic);
+        s.field("abort_on_callback_panic", &self.abort_on_callback_panic);
+        s.field("logger", &self.logger.is_some());
+        s.field("error_handler", &self.error_handler.is_some());
+        #[cfg(feature = "logging")] {
+            s.field("panic_hook", &self.panic_hook.is_some());
+        }
+        s.finish()
+    }
 }
 
 impl Default for AppConfig {
@@ -160,16 +220,59 @@ impl Default for AppConfig {
             #[cfg(feature = "logging")]
             enable_logging_on_panic: true,
             enable_tab_navigation: true,
+            deterministic: false,
+            abort_on_callback_panic: false,
+            logger: None,
+            error_handler: None,
+            #[cfg(feature = "logging")]
+            panic_hook: None,
         }
     }
 }
 
+impl AppConfig {
+
+    /// Enables or disables the deterministic mode, for reproducible runs in
+    /// snapshot tests and bug reports: hashes, hit-testing tags and the order of
+    /// daemons don't depend on the run anymore. Costs a little bit of performance,
+    /// see the [`deterministic`](../deterministic/index.html) module for details.
+    pub fn deterministic(self, deterministic: bool) -> Self {
+        Self { deterministic, .. self }
+    }
+
+    /// Sends all warnings and log messages of azul to the `logger`
+    pub fn logger(self, logger: Logger) -> Self {
+        Self { logger: Some(logger), .. self }
+    }
+
+    /// Calls the `error_handler` for every non-fatal internal error
+    pub fn error_handler(self, error_handler: ErrorHandler) -> Self {
+        Self { error_handler: Some(error_handler), .. self }
+    }
+
+    /// Calls the `panic_hook` with a `PanicReport` if the app panics
+    #[cfg(feature = "logging")]
+    pub fn panic_hook(self, panic_hook: fn(&PanicReport)) -> Self {
+        Self { panic_hook: Some(panic_hook), .. self }
+    }
+}
+
 impl<T: Layout> App<T> {
 
     #[allow(unused_variables)]
     /// Create a new, empty application. This does not open any windows.
     pub fn new(initial_data: T, config: AppConfig) -> Self {
+
+        ::deterministic::set_deterministic(config.deterministic);
+        ::diagnostics::set_up_diagnostics(config.logger, config.error_handler);
+
         #[cfg(feature = "logging")] {
+            if let Some(panic_hook) = config.panic_hook {
+                *::logging::CUSTOM_PANIC_HOOK.lock().unwrap() = Some(panic_hook);
+                ::diagnostics::record_panic_context();
+                ::logging::set_up_panic_hooks();
+            }
+
             if let Some(log_level) = config.enable_logging {
                 ::logging::set_up_logging(config.log_file_path, log_level);
 
@@ -184,10 +287,38 @@ impl<T: Layout> App<T> {
             }
         }
 
+        let mut app_state = AppState::new(initial_data);
+        app_state.abort_on_callback_panic = config.abort_on_callback_panic;
+
         Self {
             windows: BTreeMap::new(),
-            app_state: AppState::new(initial_data),
+            app_state,
+        }
+    }
+
+    /// Loads the layout of all windows from an XML file instead of calling `Layout::layout()`,
+    /// see the [`xml`](../xml/index.html) module for the markup. In debug builds, the file is
+    /// re-parsed whenever it changes and the windows show the new UI on the next frame,
+    /// without restarting the app. The data in `T` is untouched, only the structure, the
+    /// IDs, the classes and the callbacks of the DOM are reloaded.
+    ///
+    /// The callbacks in the markup (`onmouseup="save"`) are looked up by their name in the
+    /// `callbacks` registry. If the markup can't be loaded, the error is shown in the error
+    /// overlay and the windows keep the last valid UI (or the DOM of `Layout::layout()`, if
+    /// the markup was never valid).
+    pub fn with_hot_reload_ui<P: Into<PathBuf>>(self, file_path: P, callbacks: CallbackRegistry<T>) -> Self {
+        self.with_hot_reload_ui_handler(Box::new(XmlFileWatcher::new(file_path)), callbacks)
+    }
+
+    /// Same as `with_hot_reload_ui`, but loads the markup from a custom source
+    pub fn with_hot_reload_ui_handler(mut self, handler: Box<dyn XmlReloadHandler>, callbacks: CallbackRegistry<T>) -> Self {
+        let mut hot_reload_ui = HotReloadUi::new(handler, callbacks);
+        hot_reload_ui.reload(::std::time::Instant::now());
+        for fake_window in self.app_state.windows.values_mut() {
+            fake_window.state.ui_reload_error = hot_reload_ui.error.clone();
         }
+        self.app_state.hot_reload_ui = Some(hot_reload_ui);
+        self
     }
 
     /// Spawn a new window on the screen. Note that this should only be used to
@@ -195,14 +326,23 @@ impl<T: Layout> App<T> {
     /// the `.run` method.
     pub fn push_window(&mut self, window: Window<T>) {
         use default_callbacks::DefaultCallbackSystem;
+        use dom::{TagIdGenerator, DomAllocator};
 
         let window_id = window.id;
-        let fake_window = FakeWindow {
+        let mut fake_window = FakeWindow {
             state: window.state.clone(),
             default_callbacks: DefaultCallbackSystem::new(),
             read_only_window: window.display.clone(),
+            tag_ids: TagIdGenerator::new(),
+            dom_allocator: DomAllocator::new(),
+            profiler: FrameProfiler::new(),
+            render_callback_placeholders: false,
+            layout_snapshot: LayoutSnapshot::default(),
+            menu: WindowMenu::default(),
         };
 
+        fake_window.state.ui_reload_error = self.app_state.hot_reload_ui.as_ref().and_then(|ui| ui.error.clone());
+
         self.app_state.windows.insert(window_id, fake_window);
         self.windows.insert(window_id, window);
     }
@@ -254,7 +394,12 @@ impl<T: Layout> App<T> {
             let app_state = &mut self.app_state;
             let mut ui_state_map = BTreeMap::new();
             for window_id in self.windows.keys() {
-              ui_state_map.insert(*window_id, UiState::from_app_state(app_state, window_id)?);
+              // Without a last valid frame, an invalid first DOM renders as an empty window
+              let ui_state = match UiState::from_app_state(app_state, window_id) {
+                  Err(RuntimeError::InvalidDom(_)) => UiState::from_dom(Dom::div()),
+                  other => other?,
+              };
+              ui_state_map.insert(*window_id, ui_state);
             }
             ui_state_map
         };
@@ -302,6 +447,7 @@ impl<T: Layout> App<T> {
 
             #[cfg(debug_assertions)] {
                 hot_reload_css(&mut self.windows, &mut last_style_reload, &mut should_print_css_error, &mut awakened_task)?;
+                hot_reload_ui(&mut self.app_state, &self.windows, &mut awakened_task);
             }
 
             // Close windows if necessary
@@ -309,13 +455,16 @@ impl<T: Layout> App<T> {
                 ui_state_cache.remove(&closed_window_id);
                 ui_description_cache.remove(&closed_window_id);
                 force_redraw_cache.remove(&closed_window_id);
-                self.windows.remove(&closed_window_id);
+                if let Some(closed_window) = self.windows.remove(&closed_window_id) {
+                    self.app_state.resources.remove_gpu_resources(&closed_window.internal.api);
+                }
             });
 
             let should_redraw_daemons = self.app_state.run_all_daemons();
             let should_redraw_tasks = self.app_state.clean_up_finished_tasks();
+            let should_redraw_toasts = self.app_state.update_toasts();
 
-            if [should_redraw_daemons, should_redraw_tasks].into_iter().any(|e| *e == Redraw) {
+            if [should_redraw_daemons, should_redraw_tasks, should_redraw_toasts].into_iter().any(|e| *e == Redraw) {
                 self.windows.iter().for_each(|(_, window)| window.events_loop.create_proxy().wakeup().unwrap_or(()));
                 awakened_task = self.windows.keys().map(|window_id| {
                     (*window_id, true)
@@ -503,7 +652,9 @@ fn render_single_window_content<T: Layout>(
 
     let mut events = Vec::new();
     window.events_loop.poll_events(|e| events.push(e));
-    if events.is_empty() {
+    let backend_drops = window.drag_drop_backend.as_mut().map(|backend| backend.take_drops()).unwrap_or_default();
+    let accessibility_actions = window.accessibility_adapter.as_mut().map(|adapter| adapter.take_actions()).unwrap_or_default();
+    if events.is_empty() && backend_drops.is_empty() && accessibility_actions.is_empty() {
         let window_should_close = false;
         return Ok((frame_was_resize, window_should_close));
     }
@@ -512,8 +663,65 @@ fn render_single_window_content<T: Layout>(
         window.state.update_window_state(&events, awakened_task[window_id]);
 
     if window_should_close {
-        let window_should_close = true;
-        return Ok((frame_was_resize, window_should_close));
+        let window_should_close = handle_close_events(&mut events, |event| -> Result<bool, RuntimeError<T>> {
+            let callback_result = call_callbacks(None, event, window, &window_id, &ui_state_cache[&window_id], app_state)?;
+            // i.e. to show a "Save changes?" dialog if the window stays open
+            if callback_result.should_update_screen == Redraw {
+                frame_event_info.should_redraw_window = true;
+            }
+            Ok(callback_result.close_prevented)
+        })?;
+
+        if window_should_close {
+            return Ok((frame_was_resize, window_should_close));
+        }
+    }
+
+    // While the error overlay is shown, Escape or a click only dismisses the overlay
+    if app_state.windows.get(window_id).ok_or(WindowIndexError)?.state.callback_panic.is_some() && should_dismiss_error_overlay(&events) {
+        app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?.state.dismiss_callback_panic();
+        frame_event_info.should_redraw_window = true;
+        frame_event_info.should_hittest = false;
+    }
+
+    #[cfg(feature = "debug-inspector")] {
+        let layout_snapshot = &mut app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?.layout_snapshot;
+        let inspector_result = window.inspector.handle_events(&events, layout_snapshot, window.state.size.dimensions);
+        if inspector_result.should_redraw {
+            frame_event_info.should_redraw_window = true;
+        }
+        // While picking a node, the application shouldn't react to the mouse
+        if inspector_result.consumed_mouse_input {
+            frame_event_info.should_hittest = false;
+        }
+    }
+
+    // Menu items selected in the native menu or via their accelerator
+    let menu_result = dispatch_menu_events(app_state, window_id, &events, &mut window.menu_backend, &mut window.synced_menu_generation);
+    if menu_result == Redraw {
+        frame_event_info.should_redraw_window = true;
+    }
+
+    // Clicks and focus changes requested by the screen reader
+    if !accessibility_actions.is_empty() {
+        let callback_event = CallbackEvent {
+            window_id,
+            ui_state: &ui_state_cache[window_id],
+            hit_test_items: &[],
+            window_size: window.state.size.dimensions,
+            window_position: window.state.position,
+        };
+        let mut callback_panic = None;
+        let accessibility_result = dispatch_accessibility_actions(&accessibility_actions, &callback_event, app_state, &mut callback_panic);
+        show_callback_panic(app_state, window_id, callback_panic)?;
+
+        if accessibility_result.should_update_screen == Redraw {
+            frame_event_info.should_redraw_window = true;
+        }
+
+        if let Some(overwrites_focus) = accessibility_result.callbacks_overwrites_focus {
+            window.state.pending_focus_target = Some(overwrites_focus);
+        }
     }
 
     let mut hit_test_results = None;
@@ -543,11 +751,45 @@ fn render_single_window_content<T: Layout>(
             if let Some(overwrites_focus) = callback_result.callbacks_overwrites_focus {
                 window.state.pending_focus_target = Some(overwrites_focus);
             }
+
+            if let (Some(drag_data), Some(backend)) = (callback_result.os_drag, window.drag_drop_backend.as_mut()) {
+                backend.start_drag(drag_data);
+            }
+        }
+    }
+
+    // Text and custom data that was dropped via the `DragDropBackend`
+    if !backend_drops.is_empty() {
+        let own_hit_test_results;
+        let drop_hit_test_results = match hit_test_results.as_ref() {
+            Some(hit_test_results) => Some(hit_test_results),
+            None => {
+                own_hit_test_results = do_hit_test(&window);
+                own_hit_test_results.as_ref()
+            },
+        };
+
+        let drop_result = call_drop_callbacks(
+            backend_drops,
+            drop_hit_test_results,
+            window,
+            &window_id,
+            &ui_state_cache[&window_id],
+            app_state
+        )?;
+
+        if drop_result.should_update_screen == Redraw {
+            frame_event_info.should_redraw_window = true;
+        }
+
+        if let Some(overwrites_focus) = drop_result.callbacks_overwrites_focus {
+            window.state.pending_focus_target = Some(overwrites_focus);
         }
     }
 
     // Scroll for the scrolled amount for each node that registered a scroll state.
     render_on_scroll(window, hit_test_results, &frame_event_info);
+    app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?.state.scroll_offsets = window.scroll_states.get_scroll_offsets();
 
     if frame_event_info.should_swap_window || frame_event_info.is_resize_event || force_redraw_cache[window_id] > 0 {
         window.display.swap_buffers()?;
@@ -594,39 +836,73 @@ fn render_single_window_content<T: Layout>(
     // Reset the scroll amount to 0 (for the next frame)
     window.clear_scroll_state();
 
+    let mut ui_state_changed = false;
+
     if frame_event_info.should_redraw_window || force_redraw_cache[window_id] > 0 {
 
         // Call the Layout::layout() fn, get the DOM
-        *ui_state_cache.get_mut(window_id).ok_or(WindowIndexError)? =
-            UiState::from_app_state(app_state, window_id)?;
+        let new_ui_state = match UiState::from_app_state(app_state, window_id) {
+            Ok(ui_state) => ui_state,
+            Err(InvalidDom(_)) => {
+                // The error is logged and stored in the window state, keep showing the last valid frame
+                *awakened_task.get_mut(window_id).ok_or(WindowIndexError)? = false;
+                let window_should_close = false;
+                return Ok((frame_was_resize, window_should_close));
+            },
+            Err(e) => return Err(e),
+        };
+        let old_ui_state = mem::replace(ui_state_cache.get_mut(window_id).ok_or(WindowIndexError)?, new_ui_state);
+
+        // Focus, hover, etc. refer to the NodeIds of the last frame
+        let node_id_mapping = resolve_node_identities(&old_ui_state.dom, &ui_state_cache[window_id].dom);
+        window.migrate_node_ids(&node_id_mapping);
+
+        // Keep the memory of the old DOM around, so that the next frame can re-use it
+        app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?
+            .dom_allocator.recycle(old_ui_state.dom);
 
         // Style the DOM (is_mouse_down is necessary for styling :hover, :active + :focus nodes)
         let is_mouse_down = window.state.mouse_state.mouse_down();
+        let styling_start = FrameProfiler::start();
 
         *ui_description_cache.get_mut(window_id).ok_or(WindowIndexError)? =
             UiDescription::match_css_to_dom(
                 ui_state_cache.get_mut(window_id).ok_or(WindowIndexError)?,
                 &window.css,
+                &window.css_index,
                 &mut window.state.focused_node,
                 &mut window.state.pending_focus_target,
                 &window.state.hovered_nodes,
                 is_mouse_down,
+                &mut app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?.tag_ids,
             );
 
+        app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?
+            .profiler.record(FramePhase::Styling, styling_start);
+
         // Render the window (webrender will send an Awakened event when the frame is done)
         let mut fake_window = app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?;
         render(
             &mut app_state.data,
             &ui_description_cache[window_id],
-            &ui_state_cache[window_id],
+            ui_state_cache.get_mut(window_id).ok_or(WindowIndexError)?,
             &mut *window,
             &mut fake_window,
             &mut app_state.resources,
         );
 
         *awakened_task.get_mut(window_id).ok_or(WindowIndexError)? = false;
+        ui_state_changed = true;
     }
 
+    update_accessibility_adapter(
+        window,
+        &ui_state_cache[window_id],
+        app_state.windows.get(window_id).ok_or(WindowIndexError)?,
+        &app_state.resources,
+        ui_state_changed,
+    );
+
     let window_should_close = false;
     Ok((frame_was_resize, window_should_close))
 }
@@ -641,6 +917,8 @@ fn hot_reload_css<T: Layout>(
 -> Result<(), RuntimeError<T>>
 {
     use self::RuntimeError::*;
+    use style::CssRuleIndex;
+
     for (window_id, window) in windows.iter_mut() {
         // Hot-reload a style if necessary
         let hot_reloader = match window.css_loader.as_mut() {
@@ -657,9 +935,11 @@ fn hot_reload_css<T: Layout>(
         match hot_reloader.reload_style() {
             Ok(mut new_css) => {
                 new_css.sort_by_specificity();
+                // The rule indices change with the CSS, so the index has to be rebuilt
+                window.css_index = CssRuleIndex::new(&new_css);
                 window.css = new_css;
                 if !(*should_print_error) {
-                    println!("--- OK: CSS parsed without errors, continuing hot-reload.");
+                    log(LogLevel::Info, "--- OK: CSS parsed without errors, continuing hot-reload.");
                 }
                 *last_style_reload = Instant::now();
                 window.events_loop.create_proxy().wakeup().unwrap_or(());
@@ -669,7 +949,7 @@ fn hot_reload_css<T: Layout>(
             },
             Err(why) => {
                 if *should_print_error {
-                    println!("{}", why);
+                    report(AzulError::CssReload(why.to_string()));
                 }
                 *should_print_error = false;
             },
@@ -679,6 +959,34 @@ fn hot_reload_css<T: Layout>(
     Ok(())
 }
 
+/// Reloads the markup of `App::with_hot_reload_ui()`, if the UI or the error
+/// changed, all windows are woken up to show the new UI (or the error overlay)
+#[cfg(debug_assertions)]
+fn hot_reload_ui<T: Layout>(
+    app_state: &mut AppState<T>,
+    windows: &BTreeMap<WindowId, Window<T>>,
+    awakened_tasks: &mut BTreeMap<WindowId, bool>)
+{
+    let ui_changed = match app_state.hot_reload_ui.as_mut() {
+        Some(hot_reload_ui) => hot_reload_ui.reload(Instant::now()),
+        None => return,
+    };
+
+    if !ui_changed {
+        return;
+    }
+
+    let error = app_state.hot_reload_ui.as_ref().and_then(|ui| ui.error.clone());
+    for fake_window in app_state.windows.values_mut() {
+        fake_window.state.ui_reload_error = error.clone();
+    }
+
+    for (window_id, window) in windows.iter() {
+        window.events_loop.create_proxy().wakeup().unwrap_or(());
+        awakened_tasks.insert(*window_id, true);
+    }
+}
+
 /// Returns the currently hit-tested results, in back-to-front order
 fn do_hit_test<T: Layout>(window: &Window<T>) -> Option<HitTestResult> {
 
@@ -700,13 +1008,140 @@ fn do_hit_test<T: Layout>(window: &Window<T>) -> Option<HitTestResult> {
 
 /// Struct returned from the `call_callbacks()` function -
 /// returns important information from the callbacks
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-struct CallCallbackReturn {
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct CallCallbackReturn {
     /// Whether one or more callbacks say to redraw the screen or not
     pub should_update_screen: UpdateScreen,
     /// Whether one or more callbacks have messed with the current
     /// focused element i.e. via `.clear_focus()` or similar.
     pub callbacks_overwrites_focus: Option<FocusTarget>,
+    /// Data that a callback wants to drag out of the window (`CallbackInfo::start_os_drag()`)
+    pub os_drag: Option<DragData>,
+    /// Whether a callback wants to keep the window open (`CallbackInfo::prevent_close()`),
+    /// only has an effect on a `CloseRequested` event, see `handle_close_events`
+    pub close_prevented: bool,
+}
+
+impl CallCallbackReturn {
+    /// Merges the return value of one callback and what it requested via its `CallbackInfo`
+    fn add_callback_result<T: Layout>(&mut self, result: UpdateScreen, callback_info: CallbackInfo<T>) {
+        use dom::Redraw;
+
+        if result == Redraw {
+            self.should_update_screen = Redraw;
+        }
+
+        // Overwrite the focus from the callback info
+        if let Some(new_focus) = callback_info.focus {
+            self.callbacks_overwrites_focus = Some(new_focus);
+        }
+
+        if let Some(drag_data) = callback_info.os_drag {
+            self.os_drag = Some(drag_data);
+        }
+
+        if callback_info.close_prevented {
+            self.close_prevented = true;
+        }
+    }
+}
+
+/// The parts of the `CallbackInfo` that are the same for all callbacks of one event
+pub(crate) struct CallbackEvent<'a, T: 'a + Layout> {
+    pub(crate) window_id: &'a WindowId,
+    pub(crate) ui_state: &'a UiState<T>,
+    pub(crate) hit_test_items: &'a [HitTestItem],
+    pub(crate) window_size: LogicalSize,
+    pub(crate) window_position: Option<LogicalPosition>,
+}
+
+impl<'a, T: 'a + Layout> CallbackEvent<'a, T> {
+    /// Returns the `CallbackInfo` for a callback of the `node_id`,
+    /// `hit_item` is set if the cursor is over the node
+    fn callback_info(&self, node_id: NodeId, hit_item: &Option<HitTestItem>) -> CallbackInfo<'a, T> {
+        CallbackInfo {
+            focus: None,
+            window_id: self.window_id,
+            hit_dom_node: node_id,
+            ui_state: self.ui_state,
+            hit_test_items: self.hit_test_items,
+            cursor_relative_to_item: hit_item.as_ref().map(|hi| (hi.point_relative_to_item.x, hi.point_relative_to_item.y)),
+            cursor_in_viewport: hit_item.as_ref().map(|hi| (hi.point_in_viewport.x, hi.point_in_viewport.y)),
+            window_size: self.window_size,
+            window_position: self.window_position,
+            os_drag: None,
+            close_prevented: false,
+        }
+    }
+}
+
+/// Sends the tree of the new frame to the `AccessibilityAdapter` of the window if the
+/// `UiState` changed, or else only the focused node if the focus moved
+fn update_accessibility_adapter<T: Layout>(
+    window: &mut Window<T>,
+    ui_state: &UiState<T>,
+    fake_window: &FakeWindow<T>,
+    app_resources: &AppResources,
+    ui_state_changed: bool)
+{
+    let focused_node = window.state.focused_node;
+
+    let adapter = match window.accessibility_adapter.as_mut() {
+        Some(adapter) => adapter,
+        None => return,
+    };
+
+    if ui_state_changed {
+        adapter.update_tree(&AccessibilityTree::new(ui_state, &fake_window.layout_snapshot, focused_node, app_resources));
+    } else if focused_node != window.accessible_focused_node {
+        adapter.update_focus(focused_node);
+    }
+
+    window.accessible_focused_node = focused_node;
+}
+
+/// Calls the `On::DroppedData` callbacks of the nodes under the cursor and the
+/// `WindowEventFilter::DroppedData` callbacks with the `drops` that the `DragDropBackend`
+/// of the window received, the callbacks read them via `WindowState::get_dropped_data()`
+fn call_drop_callbacks<T: Layout>(
+    drops: Vec<DroppedData>,
+    hit_test_results: Option<&HitTestResult>,
+    window: &Window<T>,
+    window_id: &WindowId,
+    ui_state: &UiState<T>,
+    app_state: &mut AppState<T>)
+-> Result<CallCallbackReturn, RuntimeError<T>>
+{
+    use self::RuntimeError::*;
+
+    let mut dropped_data = None;
+    for dropped in drops {
+        add_dropped_data(&mut dropped_data, dropped);
+    }
+
+    let hit_test_items = hit_test_results.map(|h| h.items.clone()).unwrap_or_default();
+    let callbacks = determine_drop_callbacks(&hit_test_items, ui_state);
+
+    let callback_event = CallbackEvent {
+        window_id,
+        ui_state,
+        hit_test_items: &hit_test_items,
+        window_size: window.state.size.dimensions,
+        window_position: window.state.position,
+    };
+
+    app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?
+        .set_dropped_data(&dropped_data);
+
+    let mut call_result = CallCallbackReturn::default();
+    let mut callback_panic = None;
+    call_normal_callbacks(&callbacks, &callback_event, app_state, None, &mut callback_panic, &mut call_result);
+    show_callback_panic(app_state, window_id, callback_panic)?;
+
+    app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?
+        .set_dropped_data(&None);
+
+    Ok(call_result)
 }
 
 /// Returns an bool whether the window should be redrawn or not (true - redraw the screen, false: don't redraw).
@@ -720,128 +1155,230 @@ fn call_callbacks<T: Layout>(
 -> Result<CallCallbackReturn, RuntimeError<T>>
 {
     use app_state::AppStateNoData;
-    use window::CallbackInfo;
-    use dom::{Redraw, DontRedraw};
+    use dom::Redraw;
     use window_state::{KeyboardState, MouseState};
     use self::RuntimeError::*;
 
-    let mut should_update_screen = DontRedraw;
-
     let hit_test_items = hit_test_results.map(|h| h.items.clone()).unwrap_or_default();
 
-    let callbacks_filter_list = window.state.determine_callbacks(&hit_test_items, event, ui_state);
+    let mut trace = match event {
+        Event::WindowEvent { .. } if app_state.windows.get(window_id).ok_or(WindowIndexError)?.state.is_dispatch_tracing_enabled()
+                                  || ::diagnostics::is_recording_panic_context() => {
+            Some(DispatchTraceEvent::new(event))
+        },
+        _ => None,
+    };
+
+    let callbacks_filter_list = window.state.determine_callbacks(&hit_test_items, event, ui_state, trace.as_mut());
+
+    let callback_event = CallbackEvent {
+        window_id,
+        ui_state,
+        hit_test_items: &hit_test_items,
+        // Already updated by `determine_callbacks` if the event resized or moved the window
+        window_size: window.state.size.dimensions,
+        window_position: window.state.position,
+    };
 
     // TODO: this should be refactored - currently very stateful and error-prone!
     app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?
         .set_keyboard_state(&window.state.keyboard_state);
     app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?
         .set_mouse_state(&window.state.mouse_state);
+    app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?
+        .set_dropped_data(&window.state.dropped_data);
 
-    let mut callbacks_overwrites_focus = None;
+    let mut call_result = CallCallbackReturn::default();
+    let mut callback_panic = None;
+    let abort_on_panic = app_state.abort_on_callback_panic;
 
     // Run all default callbacks - **before** the user-defined callbacks are run!
     {
         let mut lock = app_state.data.lock().map_err(|_| RuntimeError::MutexLockError)?;
 
         for (node_id, callback_results) in callbacks_filter_list.nodes_with_callbacks.iter() {
-            let hit_item = &callback_results.hit_test_item;
-            for default_callback_id in callback_results.default_callbacks.values() {
-
-                let mut callback_info = CallbackInfo {
-                    focus: None,
-                    window_id,
-                    hit_dom_node: *node_id,
-                    ui_state,
-                    hit_test_items: &hit_test_items,
-                    cursor_relative_to_item: hit_item.as_ref().map(|hi| (hi.point_relative_to_item.x, hi.point_relative_to_item.y)),
-                    cursor_in_viewport: hit_item.as_ref().map(|hi| (hi.point_in_viewport.x, hi.point_in_viewport.y)),
-                };
+            for (event_filter, default_callback_id) in callback_results.default_callbacks.iter() {
+
+                let mut callback_info = callback_event.callback_info(*node_id, &callback_results.hit_test_item);
 
                 let app_state_no_data = AppStateNoData {
                     windows: &app_state.windows,
                     resources: &mut app_state.resources,
                 };
 
-                if app_state.windows[window_id].default_callbacks.run_callback(
-                    &mut *lock,
-                    default_callback_id,
-                    app_state_no_data,
-                    &mut callback_info
-                ) == Redraw {
-                    should_update_screen = Redraw;
-                }
+                let default_callbacks = &app_state.windows[window_id].default_callbacks;
+
+                let result = catch_callback_panic(abort_on_panic, *node_id, *event_filter, true, || {
+                    default_callbacks.run_callback(
+                        &mut *lock,
+                        default_callback_id,
+                        app_state_no_data,
+                        &mut callback_info
+                    )
+                }).unwrap_or_else(|panic| record_callback_panic(&mut callback_panic, panic));
 
-                // Overwrite the focus from the callback info
-                if let Some(new_focus) = callback_info.focus {
-                    callbacks_overwrites_focus = Some(new_focus);
+                if let Some(trace) = trace.as_mut() {
+                    trace.record_call(*node_id, *event_filter, true, result);
                 }
+
+                call_result.add_callback_result(result, callback_info);
             }
         }
     }
 
-    for (node_id, callback_results) in callbacks_filter_list.nodes_with_callbacks.iter() {
-        let hit_item = &callback_results.hit_test_item;
-        for callback in callback_results.normal_callbacks.values() {
-
-            let mut callback_info = CallbackInfo {
-                focus: None,
-                window_id,
-                hit_dom_node: *node_id,
-                ui_state: &ui_state,
-                hit_test_items: &hit_test_items,
-                cursor_relative_to_item: hit_item.as_ref().map(|hi| (hi.point_relative_to_item.x, hi.point_relative_to_item.y)),
-                cursor_in_viewport: hit_item.as_ref().map(|hi| (hi.point_in_viewport.x, hi.point_in_viewport.y)),
-            };
-
-            if (callback.0)(app_state, &mut callback_info) == Redraw {
-                should_update_screen = Redraw;
-            }
+    call_normal_callbacks(&callbacks_filter_list, &callback_event, app_state, trace.as_mut(), &mut callback_panic, &mut call_result);
 
-            if let Some(new_focus) = callback_info.focus {
-                callbacks_overwrites_focus = Some(new_focus);
-            }
-        }
+    if callbacks_filter_list.needs_redraw_anyways {
+        call_result.should_update_screen = Redraw;
     }
 
-    if callbacks_filter_list.needs_redraw_anyways {
-        should_update_screen = Redraw;
+    show_callback_panic(app_state, window_id, callback_panic)?;
+
+    if let Some(mut trace) = trace {
+        trace.finish(call_result.should_update_screen);
+        ::diagnostics::record_dispatch_event(&trace);
+        if let Some(dispatch_trace) = app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?.state.dispatch_trace.as_mut() {
+            dispatch_trace.push(trace);
+        }
     }
 
     app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?
         .set_keyboard_state(&KeyboardState::default());
     app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?
         .set_mouse_state(&MouseState::default());
+    app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?
+        .set_dropped_data(&None);
 
-    Ok(CallCallbackReturn {
-        should_update_screen,
-        callbacks_overwrites_focus,
-    })
+    Ok(call_result)
+}
+
+/// Calls the user-defined callbacks that `WindowState::determine_callbacks()` selected for one
+/// event and merges their results into the `call_result`. `call_callbacks()` calls the default
+/// callbacks first. The first panic of a callback is stored in the `callback_panic`.
+pub(crate) fn call_normal_callbacks<T: Layout>(
+    callbacks_filter_list: &CallbacksOfHitTest<T>,
+    callback_event: &CallbackEvent<T>,
+    app_state: &mut AppState<T>,
+    mut trace: Option<&mut DispatchTraceEvent>,
+    callback_panic: &mut Option<CallbackPanic>,
+    call_result: &mut CallCallbackReturn)
+{
+    let abort_on_panic = app_state.abort_on_callback_panic;
+
+    for (node_id, callback_results) in callbacks_filter_list.nodes_with_callbacks.iter() {
+        for (event_filter, callback) in callback_results.normal_callbacks.iter() {
+
+            let mut callback_info = callback_event.callback_info(*node_id, &callback_results.hit_test_item);
+
+            let result = catch_callback_panic(abort_on_panic, *node_id, *event_filter, false, || {
+                (callback.0)(app_state, &mut callback_info)
+            }).unwrap_or_else(|panic| record_callback_panic(callback_panic, panic));
+
+            if let Some(trace) = trace.as_mut() {
+                trace.record_call(*node_id, *event_filter, false, result);
+            }
+
+            call_result.add_callback_result(result, callback_info);
+        }
+    }
 }
 
-fn render<T: Layout>(
+/// Logs a caught callback panic and keeps it if it is the first one of the event.
+/// Returns `Redraw`, so that the error overlay is shown.
+/// Shows the first panic of the callbacks of an event in the error overlay of the window,
+/// keeps showing an earlier panic until the user has dismissed it
+fn show_callback_panic<T: Layout>(app_state: &mut AppState<T>, window_id: &WindowId, callback_panic: Option<CallbackPanic>)
+-> Result<(), RuntimeError<T>>
+{
+    if let Some(panic) = callback_panic {
+        let window_state = &mut app_state.windows.get_mut(window_id).ok_or(RuntimeError::WindowIndexError)?.state;
+        if window_state.callback_panic.is_none() {
+            window_state.callback_panic = Some(panic);
+        }
+    }
+    Ok(())
+}
+
+fn record_callback_panic(first_panic: &mut Option<CallbackPanic>, panic: CallbackPanic) -> UpdateScreen {
+    use dom::Redraw;
+
+    report(AzulError::CallbackPanic(panic.clone()));
+
+    if first_panic.is_none() {
+        *first_panic = Some(panic);
+    }
+
+    Redraw
+}
+
+pub(crate) fn render<T: Layout>(
     app_data: &mut Arc<Mutex<T>>,
     ui_description: &UiDescription<T>,
-    ui_state: &UiState<T>,
+    ui_state: &mut UiState<T>,
     window: &mut Window<T>,
     fake_window: &mut FakeWindow<T>,
     app_resources: &mut AppResources)
 {
-    use display_list::DisplayList;
+    use display_list::{DisplayList, DisplayListCacheKey, resources_are_settled};
 
     use webrender::api::{Transaction, DeviceIntRect, DeviceIntPoint};
 
-    let display_list = DisplayList::new_from_ui_description(ui_description, ui_state);
+    let cache_key = if resources_are_settled(app_resources, &window.internal.api) {
+        DisplayListCacheKey::new(ui_description, ui_state, &window.state.size)
+    } else {
+        None
+    };
 
-    let (builder, scrolled_nodes) = display_list.into_display_list_builder(
-        app_data,
-        window,
-        fake_window,
-        app_resources,
-    );
+    // The debug overlay isn't part of the cache key either (and shouldn't cost anything if it is disabled)
+    let cache_key = if window.state.debug_overlay.is_empty() { cache_key } else { None };
+
+    // The error overlay isn't part of the cache key either
+    let cache_key = if window.state.callback_panic.is_none() && window.state.ui_reload_error.is_none() { cache_key } else { None };
+
+    // The frame HUD shows the stats of the previous frame, so it changes every frame
+    let cache_key = if window.state.frame_hud { None } else { cache_key };
+
+    // The inspector overlay isn't part of the cache key, so it has to be rebuilt every frame
+    #[cfg(feature = "debug-inspector")]
+    let cache_key = if window.inspector.is_open() { None } else { cache_key };
+
+    if cache_key.is_some() && cache_key == window.internal.last_display_list_cache_key {
+        // Nothing that affects the display list has changed since the last frame,
+        // re-submit the last display list instead of building it again. The tags of this
+        // frame are new, so the tags of the cached display list have to be used instead.
+        ui_state.reuse_tags(&window.internal.last_display_list_tags);
+        window.internal.display_list_cache_stats.hits += 1;
+    } else {
+        let display_list_start = FrameProfiler::start();
+        let nested_phases = [
+            fake_window.profiler.phase_snapshot(FramePhase::LayoutSolving),
+            fake_window.profiler.phase_snapshot(FramePhase::GlCallbacks),
+        ];
+        let untracked_before = fake_window.profiler.untracked_snapshot();
+
+        let display_list = DisplayList::new_from_ui_description(ui_description, ui_state);
+
+        let (builder, scrolled_nodes) = display_list.into_display_list_builder(
+            app_data,
+            window,
+            fake_window,
+            app_resources,
+        );
+
+        window.internal.last_display_list_builder = builder.finalize().2;
+        window.internal.last_scrolled_nodes = scrolled_nodes;
+        window.internal.last_display_list_cache_key = cache_key;
+        window.internal.last_display_list_tags = ui_state.node_ids_to_tag_ids.clone();
+        window.internal.display_list_cache_stats.misses += 1;
+
+        fake_window.profiler.record_excluding(FramePhase::DisplayListBuild, display_list_start, &nested_phases);
+        fake_window.profiler.subtract_untracked(FramePhase::DisplayListBuild, untracked_before);
+    }
+
+    let render_start = FrameProfiler::start();
 
-    // NOTE: Display list has to be rebuilt every frame, otherwise, the epochs get out of sync
-    window.internal.last_display_list_builder = builder.finalize().2;
-    window.internal.last_scrolled_nodes = scrolled_nodes;
+    // NOTE: The display list has to be re-submitted every frame (even if it
+    // was cached), otherwise, the epochs get out of sync
 
     let (logical_size, framebuffer_size) = convert_window_size(&window.state.size);
 
@@ -870,6 +1407,11 @@ fn render<T: Layout>(
     window.internal.api.send_transaction(window.internal.document_id, webrender_transaction);
     window.renderer.as_mut().unwrap().update();
     render_inner(window, framebuffer_size);
+
+    fake_window.state.scroll_offsets = window.scroll_states.get_scroll_offsets();
+    fake_window.profiler.record(FramePhase::Render, render_start);
+    fake_window.profiler.set_tags_created(fake_window.tag_ids.tags_created());
+    fake_window.profiler.end_frame(&mut fake_window.state);
 }
 
 /// Scroll all nodes in the ScrollStates to their correct position and insert
@@ -886,7 +1428,7 @@ fn scroll_all_nodes(scroll_states: &mut ScrollStates, txn: &mut Transaction) {
 }
 
 /// Returns the (logical_size, physical_size) as LayoutSizes, which can then be passed to webrender
-fn convert_window_size(size: &WindowSize) -> (LayoutSize, DeviceIntSize) {
+pub(crate) fn convert_window_size(size: &WindowSize) -> (LayoutSize, DeviceIntSize) {
     let logical_size = LayoutSize::new(size.dimensions.width as f32, size.dimensions.height as f32);
     let physical_size = size.dimensions.to_physical(size.hidpi_factor);
     let physical_size = DeviceIntSize::new(physical_size.width as i32, physical_size.height as i32);
@@ -1010,7 +1552,7 @@ fn increase_epoch(old: Epoch) -> Epoch {
 // to zero, which glium doesn't know about, so on the next frame it tries to draw with shader 0
 //
 // For some reason, webrender allows rendering negative width / height, although that doesn't make sense
-fn render_inner<T: Layout>(window: &mut Window<T>, framebuffer_size: DeviceIntSize) {
+pub(crate) fn render_inner<T: Layout>(window: &mut Window<T>, framebuffer_size: DeviceIntSize) {
 
     use gleam::gl;
     use window::get_gl_context;
@@ -4,7 +4,10 @@ use std::{
     cell::RefCell,
     collections::hash_map::Entry::*,
 };
-use webrender::api::{FontKey, FontInstanceKey};
+use webrender::api::{
+    RenderApi, IdNamespace, ResourceUpdate, AddImage, AddFont,
+    ImageKey, FontKey, FontInstanceKey,
+};
 pub use webrender::api::ImageFormat as RawImageFormat;
 #[cfg(feature = "image_loading")]
 use image::{self, ImageError};
@@ -18,48 +21,177 @@ use azul_css::{StyleFontSize, FontId, StyleLetterSpacing};
 use {
     text_layout::{split_text_into_words, TextSizePx},
     text_cache::{TextId, TextCache},
-    font::{FontState, FontError},
-    images::{ImageId, ImageState},
+    font::FontError,
+    images::{ImageId, ImageInfo, DecodedImage},
 };
+#[cfg(feature = "image_loading")]
+use diagnostics::{AzulError, report};
 
 /// Stores the resources for the application, souch as fonts, images and cached
 /// texts, also clipboard strings
 ///
-/// Images and fonts can be references across window contexts (not yet tested,
-/// but should work).
+/// The decoded images, parsed fonts and layouted texts are shared by all windows.
+/// Only the copies of the images and fonts on the GPU exist once per window,
+/// see `GpuResources`.
 pub struct AppResources {
     /// When looking up images, there are two sources: Either the indirect way via using a
     /// CssImageId (which is a String) or a direct ImageId. The indirect way requires one
     /// extra lookup (to map from the stringified ID to the actual image ID).
     pub(crate) css_ids_to_image_ids: FastHashMap<String, ImageId>,
-    /// The actual image cache, does NOT store the image data, only stores it temporarily
-    /// while it is being uploaded to the GPU via webrender.
-    pub(crate) images: FastHashMap<ImageId, ImageState>,
+    /// The decoded images, kept in memory so that windows that are opened
+    /// later on can upload them as well
+    pub(crate) images: FastHashMap<ImageId, DecodedImage>,
     // Fonts are trickier to handle than images.
     // First, we duplicate the font - webrender wants the raw font data,
-    // but we also need access to the font metrics. So we first parse the font
-    // to make sure that nothing is going wrong. In the next draw call, we
-    // upload the font and replace the FontState with the newly created font key
-    pub(crate) font_data: RefCell<FastHashMap<FontId, (Rc<Font<'static>>, Rc<Vec<u8>>, Rc<RefCell<FontState>>)>>,
-    // After we've looked up the FontKey in the font_data map, we can then access
-    // the font instance key (if there is any). If there is no font instance key,
-    // we first need to create one.
-    pub(crate) fonts: FastHashMap<FontKey, FastHashMap<Au, FontInstanceKey>>,
+    // but we also need access to the font metrics. So we parse the font
+    // to make sure that nothing is going wrong and keep the raw bytes
+    // for uploading the font to the GPU of each window.
+    pub(crate) font_data: RefCell<FastHashMap<FontId, (Rc<Font<'static>>, Rc<Vec<u8>>)>>,
+    /// The images and fonts on the GPU of each window, by the namespace of the `RenderApi` of the window
+    pub(crate) gpu_resources: FastHashMap<IdNamespace, GpuResources>,
     /// Stores long texts across frames
     pub(crate) text_cache: TextCache,
     /// Keyboard clipboard storage and retrieval functionality
     clipboard: SystemClipboard,
+    /// Headless rendering: if set, every `FontId::BuiltinFont` resolves to this
+    /// font instead of a system font, so that text renders the same on every machine
+    pub(crate) builtin_font_override: Option<Rc<Vec<u8>>>,
 }
 
 impl Default for AppResources {
     fn default() -> Self {
         Self {
             css_ids_to_image_ids: FastHashMap::default(),
-            fonts: FastHashMap::default(),
             font_data: RefCell::new(FastHashMap::default()),
             images: FastHashMap::default(),
+            gpu_resources: FastHashMap::default(),
             text_cache: TextCache::default(),
             clipboard: SystemClipboard::new().unwrap(),
+            builtin_font_override: None,
+        }
+    }
+}
+
+/// Generates the keys of the resources on the GPU of a window.
+/// Implemented by the `RenderApi`, so that the uploads can be tested without a window.
+pub(crate) trait ResourceKeyGenerator {
+    fn generate_image_key(&self) -> ImageKey;
+    fn generate_font_key(&self) -> FontKey;
+    fn generate_font_instance_key(&self) -> FontInstanceKey;
+}
+
+impl ResourceKeyGenerator for RenderApi {
+    fn generate_image_key(&self) -> ImageKey {
+        RenderApi::generate_image_key(self)
+    }
+    fn generate_font_key(&self) -> FontKey {
+        RenderApi::generate_font_key(self)
+    }
+    fn generate_font_instance_key(&self) -> FontInstanceKey {
+        RenderApi::generate_font_instance_key(self)
+    }
+}
+
+/// The images and fonts that were uploaded to the GPU of one window (each window has its own
+/// webrender instance, so the keys of one window are invalid in another window).
+///
+/// Resources are uploaded lazily, when the window builds its next display list, and
+/// deleted from the GPU of the window when they are deleted from the `AppResources`.
+#[derive(Debug, Default)]
+pub(crate) struct GpuResources {
+    pub(crate) images: FastHashMap<ImageId, ImageInfo>,
+    pub(crate) fonts: FastHashMap<FontId, FontKey>,
+    pub(crate) font_instances: FastHashMap<FontKey, FastHashMap<Au, FontInstanceKey>>,
+    /// Images that were deleted from the `AppResources`, but not yet from the GPU
+    deleted_images: Vec<ImageKey>,
+    /// Fonts (and their instances) that were deleted from the `AppResources`, but not yet from the GPU
+    deleted_fonts: Vec<(FontKey, Vec<FontInstanceKey>)>,
+}
+
+impl GpuResources {
+
+    /// Uploads the `images` and `fonts` that aren't on the GPU yet
+    /// and deletes the ones that were deleted from the `AppResources`
+    pub(crate) fn update<K: ResourceKeyGenerator>(
+        &mut self,
+        images: &FastHashMap<ImageId, DecodedImage>,
+        fonts: &FastHashMap<FontId, (Rc<Font<'static>>, Rc<Vec<u8>>)>,
+        key_generator: &K,
+        resource_updates: &mut Vec<ResourceUpdate>)
+    {
+        for image_key in self.deleted_images.drain(..) {
+            resource_updates.push(ResourceUpdate::DeleteImage(image_key));
+        }
+
+        for (font_key, font_instance_keys) in self.deleted_fonts.drain(..) {
+            for font_instance_key in font_instance_keys {
+                resource_updates.push(ResourceUpdate::DeleteFontInstance(font_instance_key));
+            }
+            resource_updates.push(ResourceUpdate::DeleteFont(font_key));
+        }
+
+        for (image_id, image) in images.iter() {
+            if !self.images.contains_key(image_id) {
+                let key = key_generator.generate_image_key();
+                resource_updates.push(ResourceUpdate::AddImage(AddImage {
+                    key,
+                    descriptor: image.descriptor,
+                    // Cheap, the pixels are reference-counted
+                    data: image.data.clone(),
+                    tiling: None,
+                }));
+                self.images.insert(*image_id, ImageInfo { key, descriptor: image.descriptor });
+            }
+        }
+
+        for (font_id, (_, font_bytes)) in fonts.iter() {
+            self.get_or_upload_font(font_id, font_bytes, key_generator, resource_updates);
+        }
+    }
+
+    /// Returns the key of the font, uploads the font first if necessary
+    pub(crate) fn get_or_upload_font<K: ResourceKeyGenerator>(
+        &mut self,
+        font_id: &FontId,
+        font_bytes: &Rc<Vec<u8>>,
+        key_generator: &K,
+        resource_updates: &mut Vec<ResourceUpdate>)
+    -> FontKey
+    {
+        *self.fonts.entry(font_id.clone()).or_insert_with(|| {
+            let key = key_generator.generate_font_key();
+            resource_updates.push(ResourceUpdate::AddFont(AddFont::Raw(key, (**font_bytes).clone(), 0))); // TODO: use the index better?
+            key
+        })
+    }
+
+    /// Returns whether all `images` and `fonts` are on the GPU and no deletions are pending
+    pub(crate) fn is_settled(
+        &self,
+        images: &FastHashMap<ImageId, DecodedImage>,
+        fonts: &FastHashMap<FontId, (Rc<Font<'static>>, Rc<Vec<u8>>)>)
+    -> bool
+    {
+        self.deleted_images.is_empty() &&
+        self.deleted_fonts.is_empty() &&
+        images.keys().all(|image_id| self.images.contains_key(image_id)) &&
+        fonts.keys().all(|font_id| self.fonts.contains_key(font_id))
+    }
+
+    /// Schedules the GPU copy of the image for deletion, if there is one
+    pub(crate) fn delete_image(&mut self, image_id: &ImageId) {
+        if let Some(image_info) = self.images.remove(image_id) {
+            self.deleted_images.push(image_info.key);
+        }
+    }
+
+    /// Schedules the GPU copy of the font and all of its instances for deletion, if there is one
+    pub(crate) fn delete_font(&mut self, font_id: &FontId) {
+        if let Some(font_key) = self.fonts.remove(font_id) {
+            let font_instance_keys = self.font_instances.remove(&font_key)
+                .map(|instances| instances.values().cloned().collect())
+                .unwrap_or_default();
+            self.deleted_fonts.push((font_key, font_instance_keys));
         }
     }
 }
@@ -78,25 +210,36 @@ impl AppResources {
     {
         use images; // the module, not the crate!
 
-        // TODO: Handle image decoding failure better!
+        fn decode_image<R: Read>(data: &mut R, image_type: ImageType) -> Result<DecodedImage, ImageError> {
+            let mut image_data = Vec::<u8>::new();
+            data.read_to_end(&mut image_data).map_err(|e| ImageError::IoError(e))?;
+            let image_format = image_type.into_image_format(&image_data)?;
+            let decoded = image::load_from_memory_with_format(&image_data, image_format)?;
+            let (data, descriptor) = images::prepare_image(decoded)?;
+            Ok(DecodedImage { data, descriptor })
+        }
 
-        let image_id = match self.css_ids_to_image_ids.entry(id.into()) {
+        let id: String = id.into();
+
+        let image_id = match self.css_ids_to_image_ids.entry(id.clone()) {
             Occupied(_) => return Ok(None),
-            Vacant(v) => {
-                let new_id = images::new_image_id();
-                v.insert(new_id)
-            },
+            Vacant(v) => *v.insert(images::new_image_id()),
         };
 
-        match self.images.entry(*image_id) {
+        match self.images.entry(image_id) {
             Occupied(_) => Ok(None),
-            Vacant(v) => {
-                let mut image_data = Vec::<u8>::new();
-                data.read_to_end(&mut image_data).map_err(|e| ImageError::IoError(e))?;
-                let image_format = image_type.into_image_format(&image_data)?;
-                let decoded = image::load_from_memory_with_format(&image_data, image_format)?;
-                v.insert(ImageState::ReadyForUpload(images::prepare_image(decoded)?));
-                Ok(Some(()))
+            Vacant(v) => match decode_image(data, image_type) {
+                Ok(image) => {
+                    v.insert(image);
+                    Ok(Some(()))
+                },
+                Err(e) => {
+                    // Otherwise, the ID would stay registered without an image
+                    // and the image couldn't be added again under the same ID
+                    self.css_ids_to_image_ids.remove(&id);
+                    report(AzulError::ImageDecode { id, message: e.to_string() });
+                    Err(e)
+                },
             },
         }
     }
@@ -131,33 +274,27 @@ impl AppResources {
         match self.images.entry(*image_id) {
             Occupied(_) => None,
             Vacant(v) => {
-                v.insert(ImageState::ReadyForUpload((data, descriptor)));
+                v.insert(DecodedImage { data, descriptor });
                 Some(())
             },
         }
     }
 
     /// See [`AppState::delete_image()`](../app_state/struct.AppState.html#method.delete_image)
+    ///
+    /// The image is removed right away, the copies on the GPU of the windows
+    /// are deleted when the windows build their next display list.
     pub fn delete_image<S: AsRef<str>>(&mut self, id: S)
         -> Option<()>
     {
         let image_id = self.css_ids_to_image_ids.remove(id.as_ref())?;
+        self.images.remove(&image_id)?;
 
-        match self.images.get_mut(&image_id) {
-            None => None,
-            Some(v) => {
-                let to_delete_image_key = match *v {
-                    ImageState::Uploaded(ref image_info) => {
-                        Some((Some(image_info.key.clone()), image_info.descriptor.clone()))
-                    },
-                    _ => None,
-                };
-                if let Some((key, descriptor)) = to_delete_image_key {
-                    *v = ImageState::AboutToBeDeleted((key, descriptor));
-                }
-                Some(())
-            }
+        for gpu_resources in self.gpu_resources.values_mut() {
+            gpu_resources.delete_image(&image_id);
         }
+
+        Some(())
     }
 
     /// See [`AppState::has_image()`](../app_state/struct.AppState.html#method.has_image)
@@ -190,53 +327,53 @@ impl AppResources {
             Vacant(v) => {
                 let mut font_data = Vec::<u8>::new();
                 data.read_to_end(&mut font_data).map_err(|e| FontError::IoError(e))?;
-                let (parsed_font, fd) = font::rusttype_load_font(font_data.clone(), None)?;
-                v.insert((Rc::new(parsed_font), Rc::new(fd), Rc::new(RefCell::new(FontState::ReadyForUpload(font_data)))));
+                let (parsed_font, fd) = font::rusttype_load_font(font_data, None)?;
+                v.insert((Rc::new(parsed_font), Rc::new(fd)));
                 Ok(Some(()))
             },
         }
     }
 
     /// Search for a builtin font on the users computer, validate and return it
-    fn get_builtin_font(id: String) -> Option<(::rusttype::Font<'static>, Vec<u8>, FontState)>
+    fn get_builtin_font(id: String) -> Option<(::rusttype::Font<'static>, Vec<u8>)>
     {
         use font_loader::system_fonts::{self, FontPropertyBuilder};
         use font::rusttype_load_font;
 
         let (font_bytes, idx) = system_fonts::get(&FontPropertyBuilder::new().family(&id).build())?;
-        let (f, b) = rusttype_load_font(font_bytes.clone(), Some(idx)).ok()?;
-        Some((f, b, FontState::ReadyForUpload(font_bytes)))
+        rusttype_load_font(font_bytes, Some(idx)).ok()
+    }
+
+    /// Validate a font from its raw bytes (used instead of the system fonts in headless rendering)
+    fn load_font_from_bytes(font_bytes: Vec<u8>) -> Option<(::rusttype::Font<'static>, Vec<u8>)>
+    {
+        use font::rusttype_load_font;
+
+        rusttype_load_font(font_bytes, None).ok()
     }
 
-    /// Internal API - we want the user to get the first two fields of the
-    fn get_font_internal(&self, id: &FontId) -> Option<(Rc<Font<'static>>, Rc<Vec<u8>>, Rc<RefCell<FontState>>)> {
+    /// Given a `FontId`, returns the `Font` and the original bytes making up the font
+    /// or `None`, if the `FontId` is invalid.
+    pub fn get_font(&self, id: &FontId) -> Option<(Rc<Font<'static>>, Rc<Vec<u8>>)> {
         match id {
             FontId::BuiltinFont(b) => {
                 if self.font_data.borrow().get(id).is_none() {
-                    let (font, font_bytes, font_state) = Self::get_builtin_font(b.clone())?;
-                    self.font_data.borrow_mut().insert(id.clone(), (Rc::new(font), Rc::new(font_bytes), Rc::new(RefCell::new(font_state))));
+                    let (font, font_bytes) = match &self.builtin_font_override {
+                        Some(override_bytes) => Self::load_font_from_bytes((**override_bytes).clone())?,
+                        None => Self::get_builtin_font(b.clone())?,
+                    };
+                    self.font_data.borrow_mut().insert(id.clone(), (Rc::new(font), Rc::new(font_bytes)));
                 }
-                self.font_data.borrow().get(id).and_then(|(font, bytes, state)| Some((font.clone(), bytes.clone(), state.clone())))
+                self.font_data.borrow().get(id).cloned()
             },
             FontId::ExternalFont(_) => {
                 // For external fonts, we assume that the application programmer has
                 // already loaded them, so we don't try to fallback to system fonts.
-                self.font_data.borrow().get(id).and_then(|(font, bytes, state)| Some((font.clone(), bytes.clone(), state.clone())))
+                self.font_data.borrow().get(id).cloned()
             },
         }
     }
 
-    /// Given a `FontId`, returns the `Font` and the original bytes making up the font
-    /// or `None`, if the `FontId` is invalid.
-    pub fn get_font(&self, id: &FontId) -> Option<(Rc<Font<'static>>, Rc<Vec<u8>>)> {
-        self.get_font_internal(id).and_then(|(font, bytes, _)| Some((font, bytes)))
-    }
-
-    /// Note the pub(crate) here: We don't want to expose the FontState in the public API
-    pub(crate) fn get_font_state(&self, id: &FontId) -> Option<Rc<RefCell<FontState>>> {
-        self.get_font_internal(id).and_then(|(_, _, state)| Some(state))
-    }
-
     /// Checks if a `FontId` is valid, i.e. if a font is currently ready-to-use
     pub fn has_font(&self, id: &FontId)
         -> bool
@@ -245,25 +382,84 @@ impl AppResources {
     }
 
     /// See [`AppState::delete_font()`](./struct.AppState.html#method.delete_font)
+    ///
+    /// The font is removed right away, the copies on the GPU of the windows
+    /// are deleted when the windows build their next display list.
     pub fn delete_font(&mut self, id: &FontId)
         -> Option<()>
     {
-        // TODO: can fonts that haven't been uploaded yet be deleted?
-        let mut to_delete_font_key = None;
-
-        match self.font_data.borrow().get(&id) {
-            None => return None,
-            Some(v) => match *(*v.2).borrow() {
-                FontState::Uploaded(font_key) => { to_delete_font_key = Some(font_key.clone()); },
-                _ => { },
-            }
+        self.font_data.borrow_mut().remove(id)?;
+
+        for gpu_resources in self.gpu_resources.values_mut() {
+            gpu_resources.delete_font(id);
         }
 
-        let mut borrow_mut = self.font_data.borrow_mut();
-        *borrow_mut.get_mut(&id).unwrap().2.borrow_mut() = FontState::AboutToBeDeleted(to_delete_font_key);
         Some(())
     }
 
+    /// Returns the resources on the GPU of the window with the given `RenderApi`
+    pub(crate) fn get_gpu_resources(&self, api: &RenderApi) -> Option<&GpuResources> {
+        self.gpu_resources.get(&api.get_namespace_id())
+    }
+
+    /// Uploads the images and fonts that the window with the given `RenderApi` doesn't have
+    /// on its GPU yet and deletes the ones that were deleted since its last display list
+    pub(crate) fn update_gpu_resources(&mut self, api: &RenderApi, resource_updates: &mut Vec<ResourceUpdate>) {
+        let fonts = self.font_data.borrow();
+        self.gpu_resources.entry(api.get_namespace_id()).or_insert_with(GpuResources::default)
+            .update(&self.images, &fonts, api, resource_updates);
+    }
+
+    /// Returns the key of the font instance with the given size on the GPU of the window with
+    /// the given `RenderApi`, uploads the font and creates the instance first if necessary
+    pub(crate) fn get_font_instance_key(
+        &mut self,
+        font_id: &FontId,
+        font_size: Au,
+        api: &RenderApi,
+        resource_updates: &mut Vec<ResourceUpdate>)
+    -> Option<FontInstanceKey>
+    {
+        use webrender::api::AddFontInstance;
+
+        // Loads builtin fonts if necessary
+        let (_, font_bytes) = self.get_font(font_id)?;
+        let gpu_resources = self.gpu_resources.entry(api.get_namespace_id()).or_insert_with(GpuResources::default);
+        let font_key = gpu_resources.get_or_upload_font(font_id, &font_bytes, api, resource_updates);
+
+        let font_instance_key = gpu_resources.font_instances
+            .entry(font_key).or_insert_with(FastHashMap::default)
+            .entry(font_size).or_insert_with(|| {
+                let key = api.generate_font_instance_key();
+                resource_updates.push(ResourceUpdate::AddFontInstance(AddFontInstance {
+                    key,
+                    font_key,
+                    glyph_size: font_size,
+                    options: None,
+                    platform_options: None,
+                    variations: Vec::new(),
+                }));
+                key
+            });
+
+        Some(*font_instance_key)
+    }
+
+    /// Returns whether all images and fonts are on the GPU of the window
+    /// with the given `RenderApi` and no deletions are pending
+    pub(crate) fn are_gpu_resources_settled(&self, api: &RenderApi) -> bool {
+        match self.get_gpu_resources(api) {
+            Some(gpu_resources) => gpu_resources.is_settled(&self.images, &self.font_data.borrow()),
+            None => self.images.is_empty() && self.font_data.borrow().is_empty(),
+        }
+    }
+
+    /// Forgets the GPU resources of a closed window (the GPU memory is
+    /// released together with the webrender instance of the window)
+    pub(crate) fn remove_gpu_resources(&mut self, api: &RenderApi) {
+        self.gpu_resources.remove(&api.get_namespace_id());
+    }
+
     /// Adds a string to the internal text cache, but only store it as a string,
     /// without caching the layout of the string.
     pub fn add_text_uncached<S: Into<String>>(&mut self, text: S)
@@ -322,6 +518,13 @@ impl AppResources {
         self.text_cache.clear_all_texts();
     }
 
+    /// Sets how many bytes the words and line breaks that are shared between nodes with
+    /// the same text may take up (default: `DEFAULT_TEXT_CACHE_MEMORY_LIMIT`), see
+    /// `TextCache::set_memory_limit`
+    pub fn set_text_cache_memory_limit(&mut self, bytes: usize) {
+        self.text_cache.set_memory_limit(bytes);
+    }
+
     /// Returns the contents of the system clipboard
     pub fn get_clipboard_string(&self)
     -> Result<String, ClipboardError>
@@ -336,3 +539,113 @@ impl AppResources {
         self.clipboard.set_string_contents(contents)
     }
 }
+
+/// Generates keys in a fixed namespace, like the `RenderApi` of a window would
+#[cfg(test)]
+struct FakeKeyGenerator {
+    namespace: IdNamespace,
+    next_key: ::std::cell::Cell<u32>,
+}
+
+#[cfg(test)]
+impl FakeKeyGenerator {
+    fn new(namespace: u32) -> Self {
+        Self { namespace: IdNamespace(namespace), next_key: ::std::cell::Cell::new(0) }
+    }
+    fn next(&self) -> u32 {
+        let key = self.next_key.get();
+        self.next_key.set(key + 1);
+        key
+    }
+}
+
+#[cfg(test)]
+impl ResourceKeyGenerator for FakeKeyGenerator {
+    fn generate_image_key(&self) -> ImageKey {
+        ImageKey::new(self.namespace, self.next())
+    }
+    fn generate_font_key(&self) -> FontKey {
+        FontKey::new(self.namespace, self.next())
+    }
+    fn generate_font_instance_key(&self) -> FontInstanceKey {
+        FontInstanceKey::new(self.namespace, self.next())
+    }
+}
+
+#[test]
+fn test_image_is_shared_between_windows() {
+    use std::sync::Arc;
+    use webrender::api::{ImageData, ImageDescriptor};
+    use images::new_image_id;
+
+    let image_id = new_image_id();
+    let mut images = FastHashMap::default();
+    images.insert(image_id, DecodedImage {
+        data: ImageData::new(vec![0, 0, 255, 255]),
+        descriptor: ImageDescriptor::new(1, 1, RawImageFormat::BGRA8, true, false),
+    });
+    let fonts = FastHashMap::default();
+
+    let keys_1 = FakeKeyGenerator::new(1);
+    let keys_2 = FakeKeyGenerator::new(2);
+    let mut window_1 = GpuResources::default();
+    let mut window_2 = GpuResources::default();
+
+    let mut updates_1 = Vec::new();
+    let mut updates_2 = Vec::new();
+    window_1.update(&images, &fonts, &keys_1, &mut updates_1);
+    window_2.update(&images, &fonts, &keys_2, &mut updates_2);
+
+    // One upload per window, each with a key from the namespace of the window,
+    // but both uploads point to the pixels that were decoded once
+    let uploaded_pixels = |updates: &[ResourceUpdate], namespace| -> Arc<Vec<u8>> {
+        assert_eq!(updates.len(), 1);
+        match &updates[0] {
+            ResourceUpdate::AddImage(AddImage { key, data: ImageData::Raw(pixels), .. }) => {
+                assert_eq!(key.0, IdNamespace(namespace));
+                pixels.clone()
+            },
+            _ => panic!("expected an image upload"),
+        }
+    };
+
+    let pixels_1 = uploaded_pixels(&updates_1, 1);
+    let pixels_2 = uploaded_pixels(&updates_2, 2);
+    match &images[&image_id].data {
+        ImageData::Raw(decoded) => {
+            assert!(Arc::ptr_eq(decoded, &pixels_1));
+            assert!(Arc::ptr_eq(decoded, &pixels_2));
+        },
+        _ => panic!("expected raw pixels"),
+    }
+    assert!(window_1.is_settled(&images, &fonts));
+    assert!(window_2.is_settled(&images, &fonts));
+
+    // Already uploaded images are not uploaded again
+    updates_1.clear();
+    window_1.update(&images, &fonts, &keys_1, &mut updates_1);
+    assert!(updates_1.is_empty());
+
+    // Deleting the image deletes it from the GPU of both windows
+    let key_1 = window_1.images[&image_id].key;
+    let key_2 = window_2.images[&image_id].key;
+    images.remove(&image_id);
+    window_1.delete_image(&image_id);
+    window_2.delete_image(&image_id);
+    assert!(!window_1.is_settled(&images, &fonts));
+
+    let mut updates_1 = Vec::new();
+    let mut updates_2 = Vec::new();
+    window_1.update(&images, &fonts, &keys_1, &mut updates_1);
+    window_2.update(&images, &fonts, &keys_2, &mut updates_2);
+
+    match (&updates_1[..], &updates_2[..]) {
+        ([ResourceUpdate::DeleteImage(deleted_1)], [ResourceUpdate::DeleteImage(deleted_2)]) => {
+            assert_eq!(*deleted_1, key_1);
+            assert_eq!(*deleted_2, key_2);
+        },
+        _ => panic!("expected one image deletion per window"),
+    }
+    assert!(window_1.images.is_empty() && window_2.images.is_empty());
+    assert!(window_1.is_settled(&images, &fonts));
+}
@@ -3,6 +3,7 @@ use std::{
     collections::{BTreeMap, hash_map::Entry::*},
     sync::{Arc, Mutex},
     rc::Rc,
+    time::Duration,
 };
 #[cfg(feature = "image_loading")]
 use image::ImageError;
@@ -22,6 +23,9 @@ use {
     font::FontError,
     error::ClipboardError,
     daemon::{Daemon, DaemonId, TerminateDaemon},
+    deterministic::{AnimationClock, is_deterministic},
+    xml::HotReloadUi,
+    toast::{Toast, ToastId, ToastCorner, ToastLayer},
 };
 
 /// Wrapper for your application data, stores the data, windows and resources, as
@@ -66,6 +70,16 @@ pub struct AppState<T: Layout> {
     pub(crate) daemons: FastHashMap<DaemonId, Daemon<T>>,
     /// Currently running tasks (asynchronous functions running each on a different thread)
     pub(crate) tasks: Vec<Task<T>>,
+    /// Clock that the daemons read the current time from, see `set_clock()`
+    pub(crate) clock: AnimationClock,
+    /// Whether panics in callbacks should unwind instead of being caught,
+    /// copied from `AppConfig::abort_on_callback_panic`
+    pub(crate) abort_on_callback_panic: bool,
+    /// The UI that is loaded from markup instead of `Layout::layout()`,
+    /// see `App::with_hot_reload_ui()`
+    pub(crate) hot_reload_ui: Option<HotReloadUi<T>>,
+    /// Visible and queued toasts, see `show_toast()`
+    pub(crate) toasts: ToastLayer<T>,
 }
 
 /// Same as the [AppState](./struct.AppState.html) but without the
@@ -91,6 +105,10 @@ impl<T: Layout> AppState<T> {
             resources: AppResources::default(),
             daemons: FastHashMap::default(),
             tasks: Vec::new(),
+            clock: AnimationClock::default(),
+            abort_on_callback_panic: false,
+            hot_reload_ui: None,
+            toasts: ToastLayer::default(),
         }
     }
 
@@ -243,6 +261,60 @@ impl<T: Layout> AppState<T> {
         }
     }
 
+    /// Replaces the clock that daemons (timers, animations) read the time from.
+    ///
+    /// With `AnimationClock::manual()`, timers only fire when the clock is advanced
+    /// via `advance_clock()`, so a replayed session fires the same timers in the same frames.
+    pub fn set_clock(&mut self, clock: AnimationClock) {
+        self.clock = clock;
+    }
+
+    /// Returns the clock that daemons read the time from
+    pub fn get_clock(&self) -> AnimationClock {
+        self.clock
+    }
+
+    /// Advances a manual clock by the `duration`, does nothing for the system clock
+    pub fn advance_clock(&mut self, duration: Duration) {
+        self.clock.advance(duration);
+    }
+
+    /// Shows a notification on top of the content of all windows, see the `toast` module.
+    ///
+    /// If `set_max_visible_toasts()` toasts are already visible, the toast is queued
+    /// and shown as soon as one of the visible toasts is dismissed.
+    pub fn show_toast(&mut self, toast: Toast<T>) -> ToastId {
+        let now = self.clock.now();
+        self.toasts.show(toast, now)
+    }
+
+    /// Fades out a visible toast or removes a queued toast.
+    ///
+    /// Returns false if the toast doesn't exist anymore or was already dismissed
+    pub fn dismiss_toast(&mut self, id: ToastId) -> bool {
+        let now = self.clock.now();
+        self.toasts.dismiss(id, now)
+    }
+
+    /// Sets the corner of the window that the toasts are stacked in (default: bottom right)
+    pub fn set_toast_corner(&mut self, corner: ToastCorner) {
+        self.toasts.corner = corner;
+    }
+
+    /// Sets how many toasts are visible at the same time (default: 3, minimum: 1)
+    pub fn set_max_visible_toasts(&mut self, max_visible: usize) {
+        self.toasts.max_visible = max_visible.max(1);
+    }
+
+    /// Dismisses expired toasts and shows queued toasts
+    #[must_use]
+    pub(crate) fn update_toasts(&mut self)
+    -> UpdateScreen
+    {
+        let now = self.clock.now();
+        self.toasts.update(now)
+    }
+
     /// Run all currently registered daemons
     #[must_use]
     pub(crate) fn run_all_daemons(&mut self)
@@ -251,9 +323,21 @@ impl<T: Layout> AppState<T> {
         let mut should_update_screen = DontRedraw;
         let mut lock = self.data.lock().unwrap();
         let mut daemons_to_terminate = Vec::new();
+        let now = self.clock.now();
+
+        // In the deterministic mode, the daemons run in the order of their ID
+        // instead of the (random) order of the hash map
+        let mut daemon_ids = self.daemons.keys().cloned().collect::<Vec<_>>();
+        if is_deterministic() {
+            daemon_ids.sort();
+        }
 
-        for (key, daemon) in self.daemons.iter_mut() {
-            let (should_update, should_terminate) = daemon.invoke_callback_with_data(&mut lock, &mut self.resources);
+        for key in daemon_ids {
+            let daemon = match self.daemons.get_mut(&key) {
+                Some(daemon) => daemon,
+                None => continue,
+            };
+            let (should_update, should_terminate) = daemon.invoke_callback_with_data(&mut lock, &mut self.resources, now);
 
             if should_update == Redraw &&
                should_update_screen == DontRedraw {
@@ -217,8 +217,8 @@ pub(crate) mod webrender_translate {
 
         match input {
             CssBorderDetails::Normal(normal) => WrBorderDetails::Normal(wr_translate_normal_border(normal)),
-            // TODO: Do 9patch border properly - currently this can't be reached since there
-            // is no parsing for 9patch border yet!
+            // Nine-patch borders need the key of the image on the GPU, so they are translated
+            // with `wr_translate_nine_patch_border` - `StyleBorder` never creates them
             CssBorderDetails::NinePatch(_) => WrBorderDetails::Normal(WrNormalBorder {
                 left: zero_border_side,
                 right: zero_border_side,
@@ -230,6 +230,38 @@ pub(crate) mod webrender_translate {
         }
     }
 
+    use azul_css::NinePatchBorder as CssNinePatchBorder;
+    use azul_css::BorderImageRepeat as CssBorderImageRepeat;
+    use webrender::api::{ImageKey, ImageBorder, NinePatchDescriptor, RepeatMode as WrRepeatMode};
+
+    #[inline(always)]
+    pub fn wr_translate_border_image_repeat(input: CssBorderImageRepeat) -> WrRepeatMode {
+        match input {
+            CssBorderImageRepeat::Stretch => WrRepeatMode::Stretch,
+            CssBorderImageRepeat::Repeat => WrRepeatMode::Repeat,
+            CssBorderImageRepeat::Round => WrRepeatMode::Round,
+            CssBorderImageRepeat::Space => WrRepeatMode::Space,
+        }
+    }
+
+    #[inline(always)]
+    pub fn wr_translate_nine_patch_border(input: CssNinePatchBorder, image_key: ImageKey) -> WrBorderDetails {
+        use euclid::SideOffsets2D;
+        let CssNinePatchBorder { image_width, image_height, slice, fill, repeat_horizontal, repeat_vertical } = input;
+        WrBorderDetails::Image(ImageBorder {
+            image_key,
+            patch: NinePatchDescriptor {
+                width: image_width,
+                height: image_height,
+                slice: SideOffsets2D::new(slice.top, slice.right, slice.bottom, slice.left),
+            },
+            fill,
+            outset: SideOffsets2D::zero(),
+            repeat_horizontal: wr_translate_border_image_repeat(repeat_horizontal),
+            repeat_vertical: wr_translate_border_image_repeat(repeat_vertical),
+        })
+    }
+
     use azul_css::StyleCursor as CssCursor;
     use glium::glutin::MouseCursor as WinitCursor;
 
@@ -18,6 +18,17 @@ pub enum TerminateDaemon {
     Continue,
 }
 
+/// Result of `Daemon::poll_timer()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum DaemonTimerState {
+    /// The daemon should be invoked now
+    Due,
+    /// The `run_every` duration hasn't elapsed since the last run
+    Waiting,
+    /// The `max_timeout` is reached, the daemon should be removed
+    TimedOut,
+}
+
 static MAX_DAEMON_ID: AtomicUsize = AtomicUsize::new(0);
 
 /// Generate a new, unique DaemonId
@@ -26,7 +37,7 @@ pub fn new_daemon_id() -> DaemonId {
 }
 
 /// ID for uniquely identifying a daemon
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct DaemonId(usize);
 
 /// A `Daemon` is a function that is run on every frame.
@@ -38,8 +49,10 @@ pub struct DaemonId(usize);
 /// They are fast enough to run under 16ms, so they can run on the main thread.
 /// A daemon can also act as a timer, so that a function is called every X duration.
 pub struct Daemon<T> {
-    created: Instant,
-    last_run: Instant,
+    /// Set when the daemon is polled for the first time
+    created: Option<Instant>,
+    /// `None` if the daemon hasn't run yet
+    last_run: Option<Instant>,
     run_every: Option<Duration>,
     max_timeout: Option<Duration>,
     callback: DaemonCallback<T>,
@@ -91,8 +104,8 @@ impl<T> Daemon<T> {
     /// with another one, or merge several daemons together.
     pub fn with_id(callback: DaemonCallback<T>, id: DaemonId) -> Self {
         Daemon {
-            created: Instant::now(),
-            last_run: Instant::now(),
+            created: None,
+            last_run: None,
             run_every: None,
             max_timeout: None,
             callback,
@@ -101,7 +114,7 @@ impl<T> Daemon<T> {
     }
 
     /// Converts the daemon into a countdown, by giving it a maximum duration
-    /// (counted from the creation of the Daemon, not the first use).
+    /// (counted from the first frame that the daemon is polled in, not the first use).
     pub fn with_timeout(self, timeout: Duration) -> Self {
         Self {
             max_timeout: Some(timeout),
@@ -114,36 +127,47 @@ impl<T> Daemon<T> {
     pub fn run_every(self, every: Duration) -> Self {
         Self {
             run_every: Some(every),
-            last_run: self.last_run - every,
             .. self
         }
     }
 
-    /// Crate-internal: Invokes the daemon if the timer and the max_timeout allow it to
-    pub(crate) fn invoke_callback_with_data(
-        &mut self,
-        data: &mut T,
-        app_resources: &mut AppResources)
-    -> (UpdateScreen, TerminateDaemon)
-    {
+    /// Crate-internal: Checks the max_timeout and the timer of the daemon at the time `now`
+    /// (read from the `AnimationClock` of the app). If the daemon is due, it counts as run.
+    pub(crate) fn poll_timer(&mut self, now: Instant) -> DaemonTimerState {
+
+        let created = *self.created.get_or_insert(now);
+
         // Check if the daemons timeout is reached
         if let Some(max_timeout) = self.max_timeout {
-            if Instant::now() - self.created > max_timeout {
-                return (DontRedraw, TerminateDaemon::Terminate);
+            if now - created > max_timeout {
+                return DaemonTimerState::TimedOut;
             }
         }
 
-        if let Some(run_every) = self.run_every {
-            if Instant::now() - self.last_run < run_every {
-                return (DontRedraw, TerminateDaemon::Continue);
+        if let (Some(run_every), Some(last_run)) = (self.run_every, self.last_run) {
+            if now - last_run < run_every {
+                return DaemonTimerState::Waiting;
             }
         }
 
-        let res = (self.callback.0)(data, app_resources);
+        self.last_run = Some(now);
 
-        self.last_run = Instant::now();
+        DaemonTimerState::Due
+    }
 
-        res
+    /// Crate-internal: Invokes the daemon if the timer and the max_timeout allow it to
+    pub(crate) fn invoke_callback_with_data(
+        &mut self,
+        data: &mut T,
+        app_resources: &mut AppResources,
+        now: Instant)
+    -> (UpdateScreen, TerminateDaemon)
+    {
+        match self.poll_timer(now) {
+            DaemonTimerState::TimedOut => (DontRedraw, TerminateDaemon::Terminate),
+            DaemonTimerState::Waiting => (DontRedraw, TerminateDaemon::Continue),
+            DaemonTimerState::Due => (self.callback.0)(data, app_resources),
+        }
     }
 }
 
@@ -0,0 +1,251 @@
+//! Layout debugging overlay, see `WindowState::set_debug_overlay()`.
+//!
+//! The overlay is drawn as an extra pass over the solved layout after the
+//! normal content has been pushed into the display list, so it is always
+//! on top of the content. If no flag is set, the pass is skipped entirely.
+
+use webrender::api::{LayoutRect, LayoutPoint, LayoutSize};
+use azul_css::{ColorU, NodeTypePath};
+use {
+    dom::NodeData,
+    id_tree::{NodeId, NodeDataContainer, ArenaNodeData},
+    display_list::DisplayRectangle,
+    layout_result::LayoutResult,
+    traits::Layout,
+};
+
+/// Which debugging information should be drawn on top of the window content
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DebugOverlayFlags {
+    /// Outline of every node, colored by the node type (div, text, image, ...)
+    pub bounding_boxes: bool,
+    /// Padding area of every node, in translucent green
+    pub padding: bool,
+    /// Margin area of every node, in translucent orange
+    pub margin: bool,
+    /// A red line at the baseline of every line of text
+    pub text_baselines: bool,
+    /// Outline of every node that scrolls its children
+    pub scroll_frames: bool,
+    /// A small badge in the top left corner of every node that has a hit-testing tag
+    pub hit_test_tags: bool,
+}
+
+impl DebugOverlayFlags {
+
+    /// All overlays enabled
+    pub fn all() -> Self {
+        Self {
+            bounding_boxes: true,
+            padding: true,
+            margin: true,
+            text_baselines: true,
+            scroll_frames: true,
+            hit_test_tags: true,
+        }
+    }
+
+    /// Returns whether no overlay is enabled, i.e. the overlay pass can be skipped
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Size of the badge of `DebugOverlayFlags::hit_test_tags`, in logical pixels
+const TAG_BADGE_SIZE: f32 = 6.0;
+
+const PADDING_COLOR: ColorU = ColorU { r: 80, g: 200, b: 80, a: 90 };
+const MARGIN_COLOR: ColorU = ColorU { r: 255, g: 160, b: 60, a: 90 };
+const BASELINE_COLOR: ColorU = ColorU { r: 255, g: 0, b: 0, a: 200 };
+const SCROLL_FRAME_COLOR: ColorU = ColorU { r: 200, g: 0, b: 200, a: 255 };
+const TAG_BADGE_COLOR: ColorU = ColorU { r: 255, g: 220, b: 0, a: 255 };
+
+fn bounding_box_color(node_type: NodeTypePath) -> ColorU {
+    match node_type {
+        NodeTypePath::Div => ColorU { r: 0, g: 120, b: 255, a: 200 },
+        NodeTypePath::P => ColorU { r: 0, g: 180, b: 120, a: 200 },
+        NodeTypePath::Img => ColorU { r: 220, g: 100, b: 0, a: 200 },
+        NodeTypePath::Texture => ColorU { r: 160, g: 0, b: 220, a: 200 },
+        NodeTypePath::IFrame => ColorU { r: 120, g: 120, b: 120, a: 200 },
+    }
+}
+
+/// One rectangle of the overlay
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct DebugRect {
+    pub(crate) rect: LayoutRect,
+    pub(crate) color: ColorU,
+    /// Whether only the 1px outline of the `rect` should be drawn
+    pub(crate) outline: bool,
+}
+
+impl DebugRect {
+    fn fill(rect: LayoutRect, color: ColorU) -> Self {
+        Self { rect, color, outline: false }
+    }
+
+    fn outline(rect: LayoutRect, color: ColorU) -> Self {
+        Self { rect, color, outline: true }
+    }
+
+    /// Splits an outline into the four 1px rectangles that have to be pushed
+    pub(crate) fn outline_edges(&self) -> [LayoutRect; 4] {
+        let LayoutRect { origin, size } = self.rect;
+        let line_width = 1.0_f32.min(size.width).min(size.height);
+        [
+            LayoutRect::new(origin, LayoutSize::new(size.width, line_width)),
+            LayoutRect::new(LayoutPoint::new(origin.x, origin.y + size.height - line_width), LayoutSize::new(size.width, line_width)),
+            LayoutRect::new(origin, LayoutSize::new(line_width, size.height)),
+            LayoutRect::new(LayoutPoint::new(origin.x + size.width - line_width, origin.y), LayoutSize::new(line_width, size.height)),
+        ]
+    }
+}
+
+/// Pushes the (up to four) non-empty strips of the frame between the `outer` and the `inner` rect
+fn push_frame(outer: LayoutRect, inner: LayoutRect, color: ColorU, out: &mut Vec<DebugRect>) {
+    let LayoutRect { origin, size } = outer;
+    let top = inner.origin.y - origin.y;
+    let left = inner.origin.x - origin.x;
+    let bottom = outer.max_y() - inner.max_y();
+    let right = outer.max_x() - inner.max_x();
+    let inner_height = (size.height - top - bottom).max(0.0);
+    let strips = [
+        (top, LayoutRect::new(origin, LayoutSize::new(size.width, top))),
+        (bottom, LayoutRect::new(LayoutPoint::new(origin.x, origin.y + size.height - bottom), LayoutSize::new(size.width, bottom))),
+        (left, LayoutRect::new(LayoutPoint::new(origin.x, origin.y + top), LayoutSize::new(left, inner_height))),
+        (right, LayoutRect::new(LayoutPoint::new(origin.x + size.width - right, origin.y + top), LayoutSize::new(right, inner_height))),
+    ];
+    out.extend(strips.iter().filter(|(width, _)| *width > 0.0).map(|(_, rect)| DebugRect::fill(*rect, color)));
+}
+
+/// Builds the rectangles of the overlay for one (already laid out) DOM
+pub(crate) fn build_debug_overlay<'a, T: Layout>(
+    flags: DebugOverlayFlags,
+    node_data: &ArenaNodeData<NodeData<T>>,
+    layout: &LayoutResult,
+    display_rects: &NodeDataContainer<DisplayRectangle<'a>>,
+    scroll_frames: &[NodeId],
+    text_baselines: &[LayoutRect])
+-> Vec<DebugRect>
+{
+    let mut debug_rects = Vec::new();
+
+    if flags.is_empty() {
+        return debug_rects;
+    }
+
+    for (node_id, node_layout) in layout.iter() {
+
+        let bounds = &node_layout.border_box;
+
+        if flags.margin {
+            push_frame(node_layout.margin_box, node_layout.border_box, MARGIN_COLOR, &mut debug_rects);
+        }
+
+        if flags.padding {
+            push_frame(node_layout.padding_box, node_layout.content_rect, PADDING_COLOR, &mut debug_rects);
+        }
+
+        if flags.bounding_boxes {
+            let node_type = node_data[node_id].node_type.get_path();
+            debug_rects.push(DebugRect::outline(*bounds, bounding_box_color(node_type)));
+        }
+
+        if flags.hit_test_tags && display_rects[node_id].tag.is_some() {
+            let badge_size = LayoutSize::new(TAG_BADGE_SIZE.min(bounds.size.width), TAG_BADGE_SIZE.min(bounds.size.height));
+            debug_rects.push(DebugRect::fill(LayoutRect::new(bounds.origin, badge_size), TAG_BADGE_COLOR));
+        }
+    }
+
+    if flags.scroll_frames {
+        debug_rects.extend(scroll_frames.iter()
+            .filter_map(|node_id| layout.get(*node_id))
+            .map(|node_layout| DebugRect::outline(node_layout.border_box, SCROLL_FRAME_COLOR)));
+    }
+
+    if flags.text_baselines {
+        debug_rects.extend(text_baselines.iter().map(|rect| DebugRect::fill(*rect, BASELINE_COLOR)));
+    }
+
+    debug_rects
+}
+
+#[test]
+fn test_debug_overlay_rect_count() {
+
+    use azul_css::{LayoutPadding, LayoutMargin, PixelValue};
+    use layout_result::NodeLayoutResult;
+    use dom::{Dom, NodeType, Callback, On, UpdateScreen, DontRedraw};
+    use app_state::AppState;
+    use window::CallbackInfo;
+    use ui_state::UiState;
+    use ui_description::StyledNode;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        DontRedraw
+    }
+
+    // div
+    //     label (has a callback -> tag)
+    //     div
+    let ui_state = UiState::from_dom(Dom::div()
+        .with_child(Dom::label("Hello").with_callback(On::MouseUp, Callback(callback)))
+        .with_child(Dom::div()));
+
+    let rect = |x: f32, y: f32, w: f32, h: f32| LayoutRect::new(LayoutPoint::new(x, y), LayoutSize::new(w, h));
+    let laid_out_rectangles = vec![
+        rect(0.0, 0.0, 100.0, 100.0),
+        rect(10.0, 10.0, 80.0, 20.0),
+        rect(10.0, 40.0, 80.0, 50.0),
+    ];
+
+    let styled_node = StyledNode::default();
+    let mut display_rects = NodeDataContainer::new(vec![
+        DisplayRectangle::new(None, &styled_node),
+        DisplayRectangle::new(ui_state.node_ids_to_tag_ids().get(&NodeId::new(1)).cloned(), &styled_node),
+        DisplayRectangle::new(None, &styled_node),
+    ]);
+    // Padding on all four sides of the root, margin only on the top of the last div
+    display_rects.internal[0].layout.padding = Some(LayoutPadding {
+        top: Some(PixelValue::px(5.0)),
+        bottom: Some(PixelValue::px(5.0)),
+        left: Some(PixelValue::px(5.0)),
+        right: Some(PixelValue::px(5.0)),
+    });
+    display_rects.internal[2].layout.margin = Some(LayoutMargin { top: Some(PixelValue::px(10.0)), .. LayoutMargin::default() });
+
+    let layout = LayoutResult {
+        nodes: laid_out_rectangles.iter().zip(display_rects.internal.iter())
+            .map(|(rect, display_rect)| NodeLayoutResult::from_border_box(*rect, &display_rect.layout, &display_rect.style))
+            .collect(),
+    };
+
+    let scroll_frames = [NodeId::new(0)];
+    let baselines = [rect(10.0, 25.0, 80.0, 1.0)];
+    let node_data = &ui_state.dom.arena.node_data;
+
+    let count = |flags| build_debug_overlay(flags, node_data, &layout, &display_rects, &scroll_frames, &baselines).len();
+
+    assert_eq!(count(DebugOverlayFlags::default()), 0);
+    assert_eq!(count(DebugOverlayFlags { bounding_boxes: true, .. Default::default() }), 3);
+    assert_eq!(count(DebugOverlayFlags { padding: true, .. Default::default() }), 4);
+    assert_eq!(count(DebugOverlayFlags { margin: true, .. Default::default() }), 1);
+    assert_eq!(count(DebugOverlayFlags { hit_test_tags: true, .. Default::default() }), 1);
+    assert_eq!(count(DebugOverlayFlags { scroll_frames: true, .. Default::default() }), 1);
+    assert_eq!(count(DebugOverlayFlags { text_baselines: true, .. Default::default() }), 1);
+    assert_eq!(count(DebugOverlayFlags::all()), 3 + 4 + 1 + 1 + 1 + 1);
+
+    // The margin is drawn outside of the node, the padding inside
+    let margin = build_debug_overlay(DebugOverlayFlags { margin: true, .. Default::default() }, node_data, &layout, &display_rects, &scroll_frames, &baselines);
+    assert_eq!(margin[0].rect, rect(10.0, 30.0, 80.0, 10.0));
+    let padding = build_debug_overlay(DebugOverlayFlags { padding: true, .. Default::default() }, node_data, &layout, &display_rects, &scroll_frames, &baselines);
+    assert_eq!(padding[2].rect, rect(0.0, 5.0, 5.0, 90.0));
+}
@@ -192,6 +192,7 @@ use std::{
 use {
     dom::{UpdateScreen, DontRedraw},
     traits::Layout,
+    diagnostics::{AzulError, report},
 };
 
 static LAST_DEFAULT_CALLBACK_ID: AtomicUsize = AtomicUsize::new(0);
@@ -273,9 +274,7 @@ impl<T: Layout> DefaultCallbackSystem<T> {
         if let Some((callback_ptr, callback_fn)) = self.callbacks.get(callback_id) {
             (callback_fn.0)(callback_ptr, app_state_no_data, window_event)
         } else {
-            #[cfg(feature = "logging")] {
-                warn!("Calling default callback with invalid ID {:?}", callback_id);
-            }
+            report(AzulError::InvalidDefaultCallback(*callback_id));
             DontRedraw
         }
     }
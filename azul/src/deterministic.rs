@@ -0,0 +1,290 @@
+//! Deterministic mode for reproducible runs, see `AppConfig::deterministic()`.
+//!
+//! In the deterministic mode, nothing that can be observed from the outside
+//! depends on run-to-run nondeterminism, so that replaying the same input
+//! produces the same `DomHash`es, hit-testing tags, dispatch traces and snapshots:
+//!
+//! - `DomHash`es are calculated with SipHash using fixed keys, regardless of the
+//!   `faster-hashing` feature. Function pointers (callbacks) are hashed relative
+//!   to an address inside of the binary, so that the hashes don't change when the
+//!   binary is loaded at a different (randomized) address.
+//! - Daemons run in the order of their `DaemonId` instead of the order of the hash map.
+//! - Hit-testing tags are handed out per window and restart at 1 on every frame,
+//!   so they only depend on the document order of the DOM. This is also true
+//!   outside of the deterministic mode, as is the order in which callbacks are
+//!   called (sorted by `NodeId`, then by `EventFilter`).
+//!
+//! The time is not part of the deterministic mode, daemons still use the system time
+//! unless the `AnimationClock` of the `AppState` is replaced with a manual clock
+//! (see `AppState::set_clock()`), which only advances when told to.
+//!
+//! ## Performance
+//!
+//! The cost is small: SipHash is slower than XxHash, but since every node caches
+//! its hash, only nodes that changed since the last frame are re-hashed. Sorting the
+//! daemons costs one sort of the (usually very few) daemon IDs per frame. Still, the
+//! mode is meant for tests and bug reports, not for release builds.
+
+use std::{
+    cell::Cell,
+    hash::Hasher,
+    collections::hash_map::DefaultHasher,
+    time::{Duration, Instant},
+};
+
+thread_local! {
+    /// Per thread, since DOMs are only built and hashed on the main thread
+    /// (and so that tests running in parallel don't influence each other)
+    static DETERMINISTIC: Cell<bool> = Cell::new(false);
+}
+
+/// Returns whether the deterministic mode is enabled on the current thread
+pub fn is_deterministic() -> bool {
+    DETERMINISTIC.with(|d| d.get())
+}
+
+/// Enables or disables the deterministic mode on the current thread,
+/// set by `App::new()` from the `AppConfig`
+pub(crate) fn set_deterministic(deterministic: bool) {
+    DETERMINISTIC.with(|d| d.set(deterministic));
+}
+
+/// Only used for its address, see `hash_fn_ptr`
+fn fn_ptr_anchor() { }
+
+/// Hashes the address of a function pointer. In the deterministic mode, the address
+/// is hashed relative to a function inside of this crate: address space layout
+/// randomization moves the entire binary, so the offset stays the same across runs.
+#[inline]
+pub(crate) fn hash_fn_ptr<H: Hasher>(fn_ptr: usize, state: &mut H) {
+    if is_deterministic() {
+        state.write_usize(fn_ptr.wrapping_sub(fn_ptr_anchor as usize));
+    } else {
+        state.write_usize(fn_ptr);
+    }
+}
+
+/// Hasher for the `DomHash` - XxHash with the `faster-hashing` feature,
+/// SipHash with fixed keys otherwise or in the deterministic mode.
+pub(crate) enum DomHasher {
+    #[cfg(feature = "faster-hashing")]
+    Fast(::twox_hash::XxHash),
+    Fixed(DefaultHasher),
+}
+
+impl DomHasher {
+    pub(crate) fn new() -> Self {
+        #[cfg(feature = "faster-hashing")] {
+            if !is_deterministic() {
+                return DomHasher::Fast(::twox_hash::XxHash::default());
+            }
+        }
+        DomHasher::Fixed(DefaultHasher::new())
+    }
+}
+
+impl Hasher for DomHasher {
+    fn finish(&self) -> u64 {
+        match self {
+            #[cfg(feature = "faster-hashing")]
+            DomHasher::Fast(hasher) => hasher.finish(),
+            DomHasher::Fixed(hasher) => hasher.finish(),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        match self {
+            #[cfg(feature = "faster-hashing")]
+            DomHasher::Fast(hasher) => hasher.write(bytes),
+            DomHasher::Fixed(hasher) => hasher.write(bytes),
+        }
+    }
+}
+
+/// The clock that daemons (timers, animations) read the current time from
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AnimationClock {
+    /// Reads the time from the operating system (default)
+    System,
+    /// Only advances when `AnimationClock::advance()` is called, so that
+    /// timers fire identically when a session is replayed
+    Manual {
+        /// Time at which the clock was created
+        start: Instant,
+        /// Time that has passed since `start`
+        elapsed: Duration,
+    },
+}
+
+impl Default for AnimationClock {
+    fn default() -> Self {
+        AnimationClock::System
+    }
+}
+
+impl AnimationClock {
+
+    /// Creates a manual clock that starts at the current time
+    pub fn manual() -> Self {
+        AnimationClock::Manual {
+            start: Instant::now(),
+            elapsed: Duration::from_secs(0),
+        }
+    }
+
+    /// Returns the current time of the clock
+    pub fn now(&self) -> Instant {
+        match self {
+            AnimationClock::System => Instant::now(),
+            AnimationClock::Manual { start, elapsed } => *start + *elapsed,
+        }
+    }
+
+    /// Advances a manual clock by the `duration`, does nothing for the system clock
+    pub fn advance(&mut self, duration: Duration) {
+        if let AnimationClock::Manual { elapsed, .. } = self {
+            *elapsed += duration;
+        }
+    }
+}
+
+#[cfg(test)]
+use {
+    dom::{Dom, NodeType, Callback, On, DomHash, UpdateScreen, Redraw, DontRedraw, TagId},
+    daemon::{Daemon, DaemonCallback, DaemonTimerState, TerminateDaemon},
+    dispatch_trace::{DispatchTraceEvent, left_click, hit_test_item},
+    app_resources::AppResources,
+    app_state::AppState,
+    window::CallbackInfo,
+    window_state::WindowState,
+    ui_state::UiState,
+    traits::Layout,
+};
+
+#[cfg(test)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::new(NodeType::Div)
+    }
+}
+
+#[cfg(test)]
+fn redraw(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+    Redraw
+}
+
+#[cfg(test)]
+fn dont_redraw(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+    DontRedraw
+}
+
+#[cfg(test)]
+fn tick(_: &mut TestLayout, _: &mut AppResources) -> (UpdateScreen, TerminateDaemon) {
+    (Redraw, TerminateDaemon::Continue)
+}
+
+/// Everything that a scripted session can observe
+#[cfg(test)]
+#[derive(Debug, PartialEq)]
+struct SessionOutput {
+    dom_hash: DomHash,
+    snapshot: String,
+    tags: Vec<TagId>,
+    traces: Vec<DispatchTraceEvent>,
+    daemon_states: Vec<Vec<DaemonTimerState>>,
+}
+
+#[cfg(test)]
+fn run_scripted_session() -> SessionOutput {
+
+    use glium::glutin::ElementState;
+    use std::collections::BTreeMap;
+
+    set_deterministic(true);
+
+    let ui_state = UiState::from_dom(Dom::new(NodeType::Div)
+        .with_child(Dom::label("Count: 0")
+            .with_id("counter")
+            .with_callback(On::MouseUp, Callback(redraw)))
+        .with_child(Dom::new(NodeType::Div)
+            .with_class("reset")
+            .with_callback(On::MouseDown, Callback(dont_redraw))
+            .with_callback(On::MouseUp, Callback(redraw))));
+
+    let tags = ui_state.node_ids_to_tag_ids().values().cloned().collect::<Vec<_>>();
+
+    // Click on every node with a tag
+    let mut window_state = WindowState::default();
+    let mut traces = Vec::new();
+    for tag in &tags {
+        for state in &[ElementState::Pressed, ElementState::Released] {
+            let event = left_click(*state);
+            let mut trace = DispatchTraceEvent::new(&event);
+            window_state.determine_callbacks(&[hit_test_item(*tag)], &event, &ui_state, Some(&mut trace));
+            trace.finish(DontRedraw);
+            traces.push(trace);
+        }
+    }
+
+    // Two timers and a countdown, driven by a manual clock
+    let mut clock = AnimationClock::manual();
+    let mut daemons = BTreeMap::new();
+    for daemon in vec![
+        Daemon::unique(DaemonCallback(tick)).run_every(Duration::from_millis(10)),
+        Daemon::unique(DaemonCallback(tick)).run_every(Duration::from_millis(25)),
+        Daemon::unique(DaemonCallback(tick)).with_timeout(Duration::from_millis(30)),
+    ] {
+        daemons.insert(daemon.id, daemon);
+    }
+
+    let mut daemon_states = Vec::new();
+    for _ in 0..10 {
+        daemon_states.push(daemons.values_mut().map(|d: &mut Daemon<TestLayout>| d.poll_timer(clock.now())).collect());
+        clock.advance(Duration::from_millis(5));
+    }
+
+    let output = SessionOutput {
+        dom_hash: ui_state.dom.calculate_dom_hash(),
+        snapshot: ui_state.dom.serialize_for_snapshot(),
+        tags,
+        traces,
+        daemon_states,
+    };
+
+    set_deterministic(false);
+
+    output
+}
+
+#[test]
+fn test_deterministic_session_is_reproducible() {
+    use self::DaemonTimerState::*;
+
+    let first = run_scripted_session();
+    let second = run_scripted_session();
+
+    assert_eq!(first, second);
+
+    // Tags only depend on the document order
+    assert_eq!(first.tags, vec![1, 2]);
+    assert_eq!(first.traces.len(), 4);
+
+    // 10ms timer, 25ms timer, 30ms countdown
+    assert_eq!(first.daemon_states[0], vec![Due, Due, Due]);
+    assert_eq!(first.daemon_states[1], vec![Waiting, Waiting, Due]);
+    assert_eq!(first.daemon_states[2], vec![Due, Waiting, Due]);
+    assert_eq!(first.daemon_states[5], vec![Waiting, Due, Due]);
+    assert_eq!(first.daemon_states[7], vec![Waiting, Waiting, TimedOut]);
+}
+
+#[test]
+fn test_manual_clock_only_advances_when_told_to() {
+    let mut clock = AnimationClock::manual();
+    let start = clock.now();
+    assert_eq!(clock.now(), start);
+    clock.advance(Duration::from_millis(16));
+    assert_eq!(clock.now() - start, Duration::from_millis(16));
+}
@@ -0,0 +1,341 @@
+//! Forwarding the internal warnings and non-fatal errors of azul to the app, see
+//! `AppConfig::logger`, `AppConfig::error_handler` and `AppConfig::panic_hook`.
+//!
+//! The internal code paths don't print directly: every non-fatal internal error
+//! (an invalid DOM, a callback that isn't in the registry, an image that couldn't be
+//! decoded, ...) is reported as an `AzulError` to the `error_handler`, for example to
+//! send telemetry, and then logged. Log messages go to the `logger` if one is set,
+//! otherwise to the `log` crate (with the `logging` feature) or to stderr - info
+//! messages (such as a successful hot-reload) go to stdout.
+//!
+//! The `logger` and the `error_handler` are installed by `App::new()` for the thread
+//! that creates the app - the layout, the callbacks and the event loop all run on this
+//! thread (and tests running in parallel don't see the warnings of each other).
+//! Messages from other threads (such as the render thread of webrender) go to the
+//! fallback.
+
+use std::{fmt, cell::{Cell, RefCell}, collections::VecDeque};
+use {
+    dom::{DomError, EventFilter},
+    id_tree::NodeId,
+    default_callbacks::DefaultCallbackId,
+    error_overlay::CallbackPanic,
+    dispatch_trace::DispatchTraceEvent,
+};
+#[cfg(feature = "profiling")]
+use profiling::FrameStats;
+
+/// How many of the last dispatched events are kept for the `PanicReport`
+pub const PANIC_REPORT_DISPATCH_EVENTS: usize = 16;
+
+/// How severe a log message is
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+/// Receives all log messages of azul instead of the `log` crate / stderr,
+/// for example to forward them to `tracing`
+pub type Logger = fn(LogLevel, &str);
+
+/// Receives every non-fatal internal error, see `AppConfig::error_handler`
+pub type ErrorHandler = fn(&AzulError);
+
+/// A non-fatal internal error - the app keeps running
+#[derive(Debug, Clone, PartialEq)]
+pub enum AzulError {
+    /// The DOM returned by `Layout::layout()` is invalid, the window keeps showing the last frame
+    InvalidDom(DomError),
+    /// The DOM returned by an `IFrameCallback` is invalid, the iframe is left empty
+    InvalidIFrameDom(DomError),
+    /// IFrames are nested deeper than `MAX_IFRAME_DEPTH` (most likely, an iframe
+    /// contains itself), the innermost iframe is left empty
+    IFrameRecursionLimit { depth: usize },
+    /// A callback of a deserialized DOM has no name or isn't in the `CallbackRegistry`,
+    /// so the callback was left out
+    CallbackNotFound { node: NodeId, event: EventFilter, name: Option<String> },
+    /// A callback panicked, see the [`error_overlay`](../error_overlay/index.html) module
+    CallbackPanic(CallbackPanic),
+    /// A default callback was called with an ID that isn't registered
+    InvalidDefaultCallback(DefaultCallbackId),
+    /// The image with the CSS ID `id` couldn't be decoded
+    ImageDecode { id: String, message: String },
+    /// A font size is too big or too small for webrender, the text isn't drawn
+    FontSizeOutOfRange { px: f32 },
+    /// A dynamic CSS property was overridden with a property of a different type
+    CssOverrideMismatch { dynamic_id: String },
+    /// The node that should be focused doesn't exist
+    FocusTargetNotFound(String),
+    /// The stylesheet couldn't be hot-reloaded, the last valid stylesheet is kept
+    CssReload(String),
+    /// The markup of `App::with_hot_reload_ui()` couldn't be reloaded, the last valid UI is kept
+    UiReload(String),
+}
+
+impl_display! {
+    AzulError,
+    {
+        InvalidDom(e) => format!("The DOM returned by Layout::layout() is invalid, keeping the last frame: {}", e),
+        InvalidIFrameDom(e) => format!("Not drawing the invalid DOM of an iframe: {}", e),
+        IFrameRecursionLimit { depth } => format!("Not drawing an iframe nested {} levels deep, does an iframe contain itself?", depth),
+        CallbackNotFound { node, event, name } => format!("Callback {:?} for {:?} of node {} not found in the registry", name, event, node),
+        CallbackPanic(panic) => format!("{}", panic),
+        InvalidDefaultCallback(id) => format!("Calling default callback with invalid ID {:?}", id),
+        ImageDecode { id, message } => format!("Could not decode image \"{}\": {}", id, message),
+        FontSizeOutOfRange { px } => format!("Font size {}px is too big or too small, not drawing the text", px),
+        CssOverrideMismatch { dynamic_id } => format!("Can't override the dynamic CSS property \"{}\" with a property of a different type", dynamic_id),
+        FocusTargetNotFound(target) => format!("Could not find the node to focus: {}", target),
+        CssReload(e) => format!("Could not reload the stylesheet, keeping the last valid stylesheet: {}", e),
+        UiReload(e) => format!("Could not reload the UI markup, keeping the last valid UI: {}", e),
+    }
+}
+
+impl AzulError {
+    /// The level that the error is logged with
+    pub fn level(&self) -> LogLevel {
+        use self::AzulError::*;
+        match self {
+            InvalidDom(_) | InvalidIFrameDom(_) | IFrameRecursionLimit { .. } | CallbackPanic(_) => LogLevel::Error,
+            _ => LogLevel::Warn,
+        }
+    }
+}
+
+thread_local! {
+    static LOGGER: Cell<Option<Logger>> = Cell::new(None);
+    static ERROR_HANDLER: Cell<Option<ErrorHandler>> = Cell::new(None);
+    /// Only recorded if a panic hook is set, see `PanicContext`
+    static PANIC_CONTEXT: RefCell<Option<PanicContext>> = RefCell::new(None);
+}
+
+/// Installs the `logger` and the `error_handler` for the current thread
+pub(crate) fn set_up_diagnostics(logger: Option<Logger>, error_handler: Option<ErrorHandler>) {
+    LOGGER.with(|l| l.set(logger));
+    ERROR_HANDLER.with(|h| h.set(error_handler));
+}
+
+/// Logs the message with the installed `Logger`, the `log` crate or stderr
+pub(crate) fn log(level: LogLevel, message: &str) {
+    if let Some(logger) = LOGGER.with(|l| l.get()) {
+        logger(level, message);
+        return;
+    }
+
+    match level {
+        LogLevel::Info => println!("{}", message),
+        #[cfg(feature = "logging")]
+        LogLevel::Warn => warn!("{}", message),
+        #[cfg(feature = "logging")]
+        LogLevel::Error => error!("{}", message),
+        #[cfg(not(feature = "logging"))]
+        LogLevel::Warn | LogLevel::Error => eprintln!("[{:?}] {}", level, message),
+    }
+}
+
+/// Reports the error to the installed `ErrorHandler` and logs it
+pub(crate) fn report(error: AzulError) {
+    if let Some(handler) = ERROR_HANDLER.with(|h| h.get()) {
+        handler(&error);
+    }
+    log(error.level(), &error.to_string());
+}
+
+/// What happened right before a panic, recorded on the thread that runs the app
+#[derive(Debug, Default, Clone)]
+struct PanicContext {
+    dispatch_events: VecDeque<DispatchTraceEvent>,
+    #[cfg(feature = "profiling")]
+    frame_stats: Option<FrameStats>,
+}
+
+/// Starts recording the last dispatched events and frame stats of the current thread for the `PanicReport`
+pub(crate) fn record_panic_context() {
+    PANIC_CONTEXT.with(|c| *c.borrow_mut() = Some(PanicContext::default()));
+}
+
+/// Whether the dispatched events have to be traced for the `PanicReport`
+pub(crate) fn is_recording_panic_context() -> bool {
+    PANIC_CONTEXT.with(|c| c.borrow().is_some())
+}
+
+/// Keeps the last `PANIC_REPORT_DISPATCH_EVENTS` events, if a panic hook is set
+pub(crate) fn record_dispatch_event(event: &DispatchTraceEvent) {
+    PANIC_CONTEXT.with(|c| if let Some(context) = c.borrow_mut().as_mut() {
+        if context.dispatch_events.len() == PANIC_REPORT_DISPATCH_EVENTS {
+            context.dispatch_events.pop_front();
+        }
+        context.dispatch_events.push_back(event.clone());
+    });
+}
+
+/// Keeps the stats of the last frame, if a panic hook is set
+#[cfg(feature = "profiling")]
+pub(crate) fn record_frame_stats(stats: FrameStats) {
+    PANIC_CONTEXT.with(|c| if let Some(context) = c.borrow_mut().as_mut() {
+        context.frame_stats = Some(stats);
+    });
+}
+
+/// Everything that is known about a panic that crashes the app,
+/// passed to the `AppConfig::panic_hook` (for example to attach it to a bug report)
+#[derive(Debug, Clone)]
+pub struct PanicReport {
+    /// The panic message
+    pub message: String,
+    /// `file at line N`, if known
+    pub location: Option<String>,
+    /// The name of the panicking thread
+    pub thread: String,
+    pub backtrace: String,
+    /// The last dispatched events of the thread that runs the app, oldest event first -
+    /// only recorded for windows that trace the dispatching (see
+    /// `WindowState::set_dispatch_tracing()`)
+    pub last_dispatch_events: Vec<DispatchTraceEvent>,
+    /// The stats of the last frame that was drawn (with the `profiling` feature)
+    #[cfg(feature = "profiling")]
+    pub last_frame_stats: Option<FrameStats>,
+}
+
+impl PanicReport {
+    /// Collects the recorded events and frame stats of the current thread into a report
+    pub(crate) fn new(message: String, location: Option<String>, thread: String, backtrace: String) -> Self {
+        // The hook may run while the thread is torn down or the context is borrowed
+        let context = PANIC_CONTEXT.try_with(|c| c.try_borrow().ok().and_then(|c| c.clone()))
+            .ok().and_then(|c| c).unwrap_or_default();
+        Self {
+            message,
+            location,
+            thread,
+            backtrace,
+            last_dispatch_events: context.dispatch_events.into_iter().collect(),
+            #[cfg(feature = "profiling")]
+            last_frame_stats: context.frame_stats,
+        }
+    }
+}
+
+impl fmt::Display for PanicReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Panic in thread {} at {}: {}",
+            self.thread, self.location.as_ref().map(|s| s.as_str()).unwrap_or("<unknown location>"), self.message)?;
+
+        writeln!(f, "\nLast dispatched events (oldest first):")?;
+        if self.last_dispatch_events.is_empty() {
+            writeln!(f, "    <none recorded>")?;
+        }
+        for event in &self.last_dispatch_events {
+            let called = event.called.iter().map(|c| format!("{:?} on node {}", c.filter, c.node_id)).collect::<Vec<_>>();
+            writeln!(f, "    {:?} -> called [{}]", event.event, called.join(", "))?;
+        }
+
+        #[cfg(feature = "profiling")] {
+            match &self.last_frame_stats {
+                Some(stats) => writeln!(f, "\nLast frame: {:?}", stats)?,
+                None => writeln!(f, "\nLast frame: <no frame drawn>")?,
+            }
+        }
+
+        write!(f, "\nBacktrace:\n{}", self.backtrace)
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static COLLECTED_MESSAGES: RefCell<Vec<(LogLevel, String)>> = RefCell::new(Vec::new());
+    static COLLECTED_ERRORS: RefCell<Vec<AzulError>> = RefCell::new(Vec::new());
+}
+
+/// Installs a logger and an error handler that collect everything on the current thread,
+/// see `take_collected()`
+#[cfg(test)]
+pub(crate) fn collect_diagnostics() {
+
+    fn collect_message(level: LogLevel, message: &str) {
+        COLLECTED_MESSAGES.with(|m| m.borrow_mut().push((level, message.to_string())));
+    }
+
+    fn collect_error(error: &AzulError) {
+        COLLECTED_ERRORS.with(|e| e.borrow_mut().push(error.clone()));
+    }
+
+    set_up_diagnostics(Some(collect_message), Some(collect_error));
+}
+
+/// Returns and clears the messages and errors collected since `collect_diagnostics()`
+#[cfg(test)]
+pub(crate) fn take_collected() -> (Vec<(LogLevel, String)>, Vec<AzulError>) {
+    (COLLECTED_MESSAGES.with(|m| m.borrow_mut().drain(..).collect()),
+     COLLECTED_ERRORS.with(|e| e.borrow_mut().drain(..).collect()))
+}
+
+#[test]
+fn test_unknown_focus_target_is_reported() {
+
+    use std::collections::BTreeMap;
+    use azul_css::{Css, CssPath, CssPathSelector};
+    use dom::{Dom, TagIdGenerator};
+    use traits::Layout;
+    use style::CssRuleIndex;
+    use ui_description::UiDescription;
+    use focus::FocusTarget;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    collect_diagnostics();
+
+    let mut tag_ids = TagIdGenerator::new();
+    let mut ui_state = Dom::<TestLayout>::div().with_child(Dom::div().with_class("row")).into_ui_state(&mut tag_ids);
+    let css = Css::default();
+    let missing = CssPath { selectors: vec![CssPathSelector::Class("missing".into())] };
+
+    UiDescription::match_css_to_dom(
+        &mut ui_state,
+        &css,
+        &CssRuleIndex::new(&css),
+        &mut None,
+        &mut Some(FocusTarget::Path(missing.clone())),
+        &BTreeMap::new(),
+        false,
+        &mut tag_ids,
+    );
+
+    let (messages, errors) = take_collected();
+    assert_eq!(errors, vec![AzulError::FocusTargetNotFound(missing.to_string())]);
+    assert_eq!(messages, vec![(LogLevel::Warn, format!("Could not find the node to focus: {}", missing))]);
+
+    // Nothing is collected anymore once the handlers are removed
+    set_up_diagnostics(None, None);
+    report(AzulError::CssReload("unexpected end of input".into()));
+    assert_eq!(take_collected(), (Vec::new(), Vec::new()));
+}
+
+#[test]
+fn test_panic_report_contains_recorded_events() {
+
+    use glium::glutin::Event;
+
+    // Nothing is recorded without a panic hook
+    record_dispatch_event(&DispatchTraceEvent::new(&Event::Awakened));
+    assert!(PanicReport::new("boom".into(), None, "main".into(), String::new()).last_dispatch_events.is_empty());
+
+    record_panic_context();
+    for _ in 0..(PANIC_REPORT_DISPATCH_EVENTS + 3) {
+        record_dispatch_event(&DispatchTraceEvent::new(&Event::Awakened));
+    }
+
+    let report = PanicReport::new("boom".into(), Some("src/main.rs at line 3".into()), "main".into(), "<backtrace>".into());
+    assert_eq!(report.last_dispatch_events.len(), PANIC_REPORT_DISPATCH_EVENTS);
+
+    let formatted = report.to_string();
+    assert!(formatted.starts_with("Panic in thread main at src/main.rs at line 3: boom\n"));
+    assert!(formatted.contains("    Awakened -> called []\n"));
+    assert!(formatted.ends_with("Backtrace:\n<backtrace>"));
+}
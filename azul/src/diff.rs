@@ -1,40 +1,218 @@
-//! Compares and diffs two DOM trees - necessary for tracking stateful events
-//! such as user focus and scroll states across frames
+//! Structural diff between two DOM trees, for finding out what a `layout()`
+//! call changed compared to the previous frame, see `Dom::diff()`
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub struct DomRange {
-    pub start: DomNodeInfo,
-    pub end: DomNodeInfo,
+use std::hash::{Hash, Hasher};
+use {
+    dom::{Dom, DomHash, NodeData, canonical_names},
+    id_tree::{NodeId, NodeDataContainer},
+    deterministic::DomHasher,
+    traits::Layout,
+};
+
+/// Which parts of a node changed between two DOMs
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ChangedFields {
+    /// The `NodeType`, including the text of a label or the ID of an image
+    pub node_type: bool,
+    /// The ids or classes (order and duplicates are ignored)
+    pub ids_and_classes: bool,
+    /// The callbacks or default callbacks
+    pub callbacks: bool,
+    /// The dynamic CSS overrides
+    pub css_overrides: bool,
+    /// `draggable`, `tab_index`, `key`, `attributes` or whether the node is the container created by `collect()`
+    pub attributes: bool,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub struct DomNodeInfo {
-    pub hash: DomHash,
-    pub id: NodeId,
-}
-
-impl DomRange {
-    /// Is `other` a subtree of `self`? - Assumes that the DOM was
-    /// constructed in a linear order, i.e. the child being within
-    /// the parents start / end bounds
-    pub fn contains(&self, other: &Self) -> bool {
-        other.start.id.index() >= self.start.id.index() &&
-        other.end.id.index() <= self.end.id.index()
+impl ChangedFields {
+
+    /// Compares two nodes field by field
+    fn between<T: Layout>(old: &NodeData<T>, new: &NodeData<T>) -> Self {
+        Self {
+            node_type: old.node_type != new.node_type,
+            ids_and_classes:
+                canonical_names(&old.ids) != canonical_names(&new.ids) ||
+                canonical_names(&old.classes) != canonical_names(&new.classes),
+            callbacks:
+                old.callbacks != new.callbacks ||
+                old.default_callback_ids != new.default_callback_ids,
+            css_overrides: old.dynamic_css_overrides != new.dynamic_css_overrides,
+            attributes:
+                old.draggable != new.draggable ||
+                old.tab_index != new.tab_index ||
+                old.key != new.key ||
+                old.attributes != new.attributes ||
+                old.is_synthetic_root != new.is_synthetic_root,
+        }
     }
 
-    /// Compares two DOM ranges without looking at the DOM hashes
-    pub fn equals_range(&self, other: &Self) -> bool {
-        other.start == self.start &&
-        other.end == self.end
+    /// Returns whether no field changed
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
     }
 }
 
+/// A single difference between two DOMs
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DomChange {
+    /// The node (and all nodes below it) only exists in the new DOM, `NodeId` of the new DOM
+    Added(NodeId),
+    /// The node (and all nodes below it) only exists in the old DOM, `NodeId` of the old DOM
+    Removed(NodeId),
+    /// The node exists in both DOMs, but its content changed, `NodeId` of the new DOM
+    Changed { node: NodeId, fields: ChangedFields },
+}
+
+/// Differences between two DOMs, in document order. Nodes are matched by their
+/// position in the tree (the n-th child of the matched parent), not by their content
+/// or key: inserting a node in the middle of a child list shows up as changes
+/// of all following siblings and an `Added` node at the end of the list.
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct DomDiff {
-    added_nodes: Vec<DomRange>,
-    removed_nodes: Vec<DomRange>,
+    pub changes: Vec<DomChange>,
+}
+
+impl DomDiff {
+    /// Returns whether the two DOMs are structurally identical
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Returns the hash of each node combined with the hashes of all nodes below it
+fn calculate_subtree_hashes<T: Layout>(dom: &Dom<T>) -> NodeDataContainer<DomHash> {
+
+    let mut subtree_hashes = NodeDataContainer::new(vec![DomHash(0); dom.len()]);
+    let nodes_in_document_order = dom.traverse().map(|(_, node_id)| node_id).collect::<Vec<NodeId>>();
+
+    // Reverse document order, so that the children are hashed before their parent
+    for node_id in nodes_in_document_order.into_iter().rev() {
+        let mut hasher = DomHasher::new();
+        dom.arena.node_data[node_id].calculate_node_data_hash().hash(&mut hasher);
+        for child_id in dom.children(node_id) {
+            subtree_hashes[child_id].hash(&mut hasher);
+        }
+        subtree_hashes[node_id] = DomHash(hasher.finish());
+    }
+
+    subtree_hashes
+}
+
+/// See `Dom::diff()`
+pub(crate) fn diff_dom_tree<T: Layout>(old: &Dom<T>, new: &Dom<T>) -> DomDiff {
+
+    let old_subtree_hashes = calculate_subtree_hashes(old);
+    let new_subtree_hashes = calculate_subtree_hashes(new);
+
+    let mut changes = Vec::new();
+
+    // (old node, new node): both `Some` if the node exists in both DOMs.
+    // Iterative instead of recursive, since DOMs can be nested very deeply.
+    let mut stack = vec![(Some(old.root), Some(new.root))];
+
+    while let Some(pair) = stack.pop() {
+
+        let (old_id, new_id) = match pair {
+            (Some(old_id), Some(new_id)) => (old_id, new_id),
+            (None, Some(new_id)) => { changes.push(DomChange::Added(new_id)); continue; },
+            (Some(old_id), None) => { changes.push(DomChange::Removed(old_id)); continue; },
+            (None, None) => continue,
+        };
+
+        // Fast path: the entire subtree is unchanged
+        if old_subtree_hashes[old_id] == new_subtree_hashes[new_id] {
+            continue;
+        }
+
+        if old.arena.node_data[old_id].calculate_node_data_hash() != new.arena.node_data[new_id].calculate_node_data_hash() {
+            let fields = ChangedFields::between(&old.arena.node_data[old_id], &new.arena.node_data[new_id]);
+            if !fields.is_empty() {
+                changes.push(DomChange::Changed { node: new_id, fields });
+            }
+        }
+
+        let old_children = old.children(old_id).collect::<Vec<NodeId>>();
+        let new_children = new.children(new_id).collect::<Vec<NodeId>>();
+        let child_count = old_children.len().max(new_children.len());
+
+        // Pushed in reverse, so that the children are popped in document order
+        for child_idx in (0..child_count).rev() {
+            stack.push((old_children.get(child_idx).cloned(), new_children.get(child_idx).cloned()));
+        }
+    }
+
+    DomDiff { changes }
+}
+
+#[cfg(test)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+#[test]
+fn test_diff_identical_doms() {
+    let dom = || Dom::<TestLayout>::div().with_class("list")
+        .with_child(Dom::label("a"))
+        .with_child(Dom::label("b"));
+
+    assert!(dom().diff(&dom()).is_empty());
 }
 
-pub(crate) fn diff_dom_tree(old: &Dom<DomHash>, new: Dom<DomHash>) -> DomDiff {
-    DomDiff::default()
-}
\ No newline at end of file
+#[test]
+fn test_diff_insertion_in_child_list() {
+
+    // div
+    // '- label "a"
+    // '- label "b"  <- "x" is inserted before this node
+    // '- label "c"
+    let list = |items: &[&str]| items.iter().fold(Dom::<TestLayout>::div(), |dom, item| dom.with_child(Dom::label(item.to_string())));
+
+    let old = list(&["a", "b", "c"]);
+    let new = list(&["a", "x", "b", "c"]);
+
+    let children = new.children(new.get_root()).collect::<Vec<NodeId>>();
+    let label_changed = ChangedFields { node_type: true, .. Default::default() };
+
+    // Positional matching: "a" is unchanged, all following siblings shift by one
+    assert_eq!(old.diff(&new).changes, vec![
+        DomChange::Changed { node: children[1], fields: label_changed },
+        DomChange::Changed { node: children[2], fields: label_changed },
+        DomChange::Added(children[3]),
+    ]);
+
+    // ... and the other way around
+    let old_children = old.children(old.get_root()).collect::<Vec<NodeId>>();
+    assert_eq!(new.diff(&old).changes, vec![
+        DomChange::Changed { node: old_children[1], fields: label_changed },
+        DomChange::Changed { node: old_children[2], fields: label_changed },
+        DomChange::Removed(children[3]),
+    ]);
+}
+
+#[test]
+fn test_diff_class_change_on_deep_leaf() {
+
+    fn dom(leaf_class: &str) -> Dom<TestLayout> {
+        Dom::div()
+            .with_child(Dom::label("sibling"))
+            .with_child(Dom::div().with_id("outer")
+                .with_child(Dom::div()
+                    .with_child(Dom::div().with_class(leaf_class))))
+    }
+
+    let old = dom("inactive");
+    let new = dom("active");
+    let leaf = new.get_node_by_id("outer")
+        .and_then(|outer| new.children(outer).next())
+        .and_then(|inner| new.children(inner).next())
+        .unwrap();
+
+    assert_eq!(old.diff(&new).changes, vec![
+        DomChange::Changed { node: leaf, fields: ChangedFields { ids_and_classes: true, .. Default::default() } },
+    ]);
+}
@@ -0,0 +1,362 @@
+//! Opt-in tracing of the event dispatch, see `WindowState::set_dispatch_tracing()`.
+//!
+//! For every input event, the trace records what the hit test found, which
+//! event filters the event was translated into, which callbacks were called
+//! (in the order in which they were called) and which callbacks of the hit
+//! (or focused) nodes were *not* called and why - answering the question
+//! "why didn't my callback fire?".
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use glium::glutin::Event;
+use webrender::api::HitTestItem;
+use {
+    dom::{
+        EventFilter, UpdateScreen, TagId, HoverEventFilter, FocusEventFilter,
+        WindowEventFilter, DesktopEventFilter,
+    },
+    id_tree::NodeId,
+    traits::Layout,
+    ui_state::UiState,
+    window_state::DetermineCallbackResult,
+};
+
+/// How many events a window keeps in its trace if `take_dispatch_trace()` is never called
+pub const DISPATCH_TRACE_MAX_LEN: usize = 1024;
+
+/// Everything that happened while dispatching one input event
+#[derive(Debug, Clone, PartialEq)]
+pub struct DispatchTraceEvent {
+    /// The event that was dispatched
+    pub event: Event,
+    /// The nodes that were hit by the cursor, in the order of the hit test (topmost node first)
+    pub hit_nodes: Vec<TracedNode>,
+    /// The filters that the event was translated into - only callbacks
+    /// registered for one of these filters can be called, sorted
+    pub filters: Vec<EventFilter>,
+    /// The callbacks that were called, in the order in which they were called
+    /// (default callbacks of widgets always run before the regular callbacks)
+    pub called: Vec<TracedCallback>,
+    /// Callbacks of the hit or focused nodes that were not called, sorted by node
+    pub skipped: Vec<SkippedCallback>,
+    /// Whether the screen was redrawn because of this event
+    pub update_screen: UpdateScreen,
+}
+
+/// A node that was hit by the hit test
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracedNode {
+    /// The hit-testing tag that webrender reported
+    pub tag: TagId,
+    /// The node that the tag belongs to, `None` if the tag doesn't belong to
+    /// the current DOM (i.e. the tag of a scrollbar)
+    pub node_id: Option<NodeId>,
+    /// The `#ids` of the node
+    pub ids: Vec<String>,
+    /// The `.classes` of the node
+    pub classes: Vec<String>,
+}
+
+/// A callback that was called
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TracedCallback {
+    pub node_id: NodeId,
+    pub filter: EventFilter,
+    /// Whether this was a default callback (i.e. the callback of a widget),
+    /// not a regular `Callback`
+    pub is_default: bool,
+    /// What the callback returned
+    pub result: UpdateScreen,
+}
+
+/// A callback of a hit or focused node that was not called
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SkippedCallback {
+    pub node_id: NodeId,
+    pub filter: EventFilter,
+    pub reason: SkipReason,
+}
+
+/// Why a callback was not called
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SkipReason {
+    /// The node was hit (or is focused), but the event didn't produce the filter
+    /// the callback was registered for (i.e. `On::MouseDown` on a mouse-up event)
+    FilterNotMatched,
+    /// The event would match the `Focus` callback, but the node doesn't have the keyboard focus
+    NodeNotFocused,
+    /// A `Not` callback wasn't called because the event happened on the node itself
+    NotFilterExcluded,
+}
+
+impl DispatchTraceEvent {
+
+    pub(crate) fn new(event: &Event) -> Self {
+        Self {
+            event: event.clone(),
+            hit_nodes: Vec::new(),
+            filters: Vec::new(),
+            called: Vec::new(),
+            skipped: Vec::new(),
+            update_screen: None,
+        }
+    }
+
+    pub(crate) fn record_hit_test<T: Layout>(&mut self, hit_test_items: &[HitTestItem], ui_state: &UiState<T>) {
+        self.hit_nodes = hit_test_items.iter().map(|item| {
+            let node_id = ui_state.tag_ids_to_node_ids.get(&item.tag.0).cloned();
+            let node_data = node_id.and_then(|id| ui_state.dom.get_node_content(id));
+            TracedNode {
+                tag: item.tag.0,
+                node_id,
+                ids: node_data.map(|n| n.ids.clone()).unwrap_or_default(),
+                classes: node_data.map(|n| n.classes.clone()).unwrap_or_default(),
+            }
+        }).collect();
+    }
+
+    pub(crate) fn record_filters(
+        &mut self,
+        window_events: &HashSet<WindowEventFilter>,
+        hover_events: &HashSet<HoverEventFilter>,
+        focus_events: &HashSet<FocusEventFilter>,
+        desktop_events: &HashSet<DesktopEventFilter>)
+    {
+        self.filters = window_events.iter().map(|e| EventFilter::Window(*e))
+            .chain(hover_events.iter().map(|e| EventFilter::Hover(*e)))
+            .chain(focus_events.iter().map(|e| EventFilter::Focus(*e)))
+            .chain(desktop_events.iter().map(|e| EventFilter::Desktop(*e)))
+            .collect();
+        self.filters.sort();
+    }
+
+    /// Records the `Hover` callbacks of the hit nodes and the `Focus` callbacks
+    /// of all nodes that didn't make it into the `nodes_with_callbacks`
+    pub(crate) fn record_skipped_callbacks<T: Layout>(
+        &mut self,
+        ui_state: &UiState<T>,
+        hit_nodes: &BTreeMap<NodeId, HitTestItem>,
+        focused_node: Option<NodeId>,
+        focus_events: &HashSet<FocusEventFilter>,
+        nodes_with_callbacks: &BTreeMap<NodeId, DetermineCallbackResult<T>>)
+    {
+        let is_called = |node_id: &NodeId, filter: &EventFilter| {
+            nodes_with_callbacks.get(node_id).map(|r| r.normal_callbacks.contains_key(filter)).unwrap_or(false)
+        };
+
+        for node_id in hit_nodes.keys() {
+            for (hover_filter, _) in ui_state.hover_callbacks(*node_id) {
+                let filter = EventFilter::Hover(*hover_filter);
+                if !is_called(node_id, &filter) {
+                    self.skip(*node_id, filter, SkipReason::FilterNotMatched);
+                }
+            }
+        }
+
+        for (node_id, focus_callbacks) in &ui_state.focus_callbacks {
+            for (focus_filter, _) in focus_callbacks {
+                let filter = EventFilter::Focus(*focus_filter);
+                if focused_node != Some(*node_id) {
+                    if focus_events.contains(focus_filter) {
+                        self.skip(*node_id, filter, SkipReason::NodeNotFocused);
+                    }
+                } else if !is_called(node_id, &filter) {
+                    self.skip(*node_id, filter, SkipReason::FilterNotMatched);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn skip(&mut self, node_id: NodeId, filter: EventFilter, reason: SkipReason) {
+        self.skipped.push(SkippedCallback { node_id, filter, reason });
+    }
+
+    pub(crate) fn record_call(&mut self, node_id: NodeId, filter: EventFilter, is_default: bool, result: UpdateScreen) {
+        self.called.push(TracedCallback { node_id, filter, is_default, result });
+    }
+
+    /// Sorts the skipped callbacks, so that the trace doesn't depend on hash map iteration order
+    pub(crate) fn finish(&mut self, update_screen: UpdateScreen) {
+        self.skipped.sort_by_key(|s| (s.node_id, s.filter));
+        self.update_screen = update_screen;
+    }
+}
+
+/// The recorded events of one window, oldest event first
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct DispatchTrace {
+    events: VecDeque<DispatchTraceEvent>,
+}
+
+impl DispatchTrace {
+
+    pub(crate) fn push(&mut self, event: DispatchTraceEvent) {
+        if self.events.len() == DISPATCH_TRACE_MAX_LEN {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    pub(crate) fn take(&mut self) -> Vec<DispatchTraceEvent> {
+        self.events.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+use {
+    dom::{Dom, NodeType, Callback, On, TabIndex, NotEventFilter, Redraw, DontRedraw},
+    app_state::AppState,
+    window::CallbackInfo,
+    window_state::WindowState,
+};
+
+#[cfg(test)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::new(NodeType::Div)
+    }
+}
+
+#[cfg(test)]
+fn redraw(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+    Redraw
+}
+
+#[cfg(test)]
+fn dont_redraw(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+    DontRedraw
+}
+
+#[cfg(test)]
+pub(crate) fn left_click(state: ::glium::glutin::ElementState) -> Event {
+    use glium::glutin::{WindowId, DeviceId, WindowEvent, MouseButton, ModifiersState};
+    Event::WindowEvent {
+        window_id: unsafe { WindowId::dummy() },
+        event: WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state,
+            button: MouseButton::Left,
+            modifiers: ModifiersState::default(),
+        },
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn window_event(event: ::glium::glutin::WindowEvent) -> Event {
+    use glium::glutin::WindowId;
+    Event::WindowEvent {
+        window_id: unsafe { WindowId::dummy() },
+        event,
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn hit_test_item(tag: TagId) -> HitTestItem {
+    use webrender::api::{PipelineId, LayoutPoint};
+    HitTestItem {
+        pipeline: PipelineId(0, 0),
+        tag: (tag, 0),
+        point_in_viewport: LayoutPoint::new(0.0, 0.0),
+        point_relative_to_item: LayoutPoint::new(0.0, 0.0),
+    }
+}
+
+#[test]
+fn test_dispatch_trace_of_click() {
+
+    use glium::glutin::ElementState;
+    use dom::HoverEventFilter::*;
+
+    let ui_state = UiState::from_dom(Dom::new(NodeType::Div)
+        .with_child(Dom::new(NodeType::Div)
+            .with_id("ok")
+            .with_class("button")
+            .with_callback(On::MouseDown, Callback(dont_redraw))
+            .with_callback(On::MouseUp, Callback(redraw)))
+        .with_child(Dom::new(NodeType::Div)
+            .with_callback(EventFilter::Not(NotEventFilter::Hover(MouseUp)), Callback(redraw))));
+
+    let button = NodeId::new(1);
+    let other = NodeId::new(2);
+    let button_tag = ui_state.node_ids_to_tag_ids()[&button];
+
+    let mut window_state = WindowState::default();
+    let event = left_click(ElementState::Released);
+    let mut trace = DispatchTraceEvent::new(&event);
+    let callbacks = window_state.determine_callbacks(&[hit_test_item(button_tag)], &event, &ui_state, Some(&mut trace));
+
+    assert_eq!(trace.hit_nodes, vec![TracedNode {
+        tag: button_tag,
+        node_id: Some(button),
+        ids: vec!["ok".into()],
+        classes: vec!["button".into()],
+    }]);
+    assert!(trace.filters.contains(&EventFilter::Hover(MouseUp)));
+    assert!(trace.filters.contains(&EventFilter::Window(WindowEventFilter::LeftMouseUp)));
+    assert!(!trace.filters.contains(&EventFilter::Hover(MouseDown)));
+
+    // The MouseUp callback of the button and the Not(MouseUp) callback of the
+    // other node are going to be called, the MouseDown callback of the button isn't
+    assert!(callbacks.nodes_with_callbacks[&button].normal_callbacks.contains_key(&EventFilter::Hover(MouseUp)));
+    assert!(callbacks.nodes_with_callbacks[&other].normal_callbacks.contains_key(&EventFilter::Not(NotEventFilter::Hover(MouseUp))));
+    assert_eq!(trace.skipped, vec![SkippedCallback {
+        node_id: button,
+        filter: EventFilter::Hover(MouseDown),
+        reason: SkipReason::FilterNotMatched,
+    }]);
+}
+
+#[test]
+fn test_dispatch_trace_of_skipped_focus_and_not_callbacks() {
+
+    use glium::glutin::ElementState;
+    use dom::HoverEventFilter::*;
+
+    let ui_state = UiState::from_dom(Dom::new(NodeType::Div)
+        .with_child(Dom::new(NodeType::Div)
+            .with_tab_index(TabIndex::Auto)
+            .with_callback(On::MouseDown, Callback(redraw))
+            .with_callback(EventFilter::Not(NotEventFilter::Hover(MouseDown)), Callback(redraw)))
+        .with_child(Dom::new(NodeType::Div)
+            .with_tab_index(TabIndex::Auto)
+            .with_callback(EventFilter::Focus(FocusEventFilter::MouseDown), Callback(redraw))));
+
+    let clicked = NodeId::new(1);
+    let unfocused = NodeId::new(2);
+    let clicked_tag = ui_state.node_ids_to_tag_ids()[&clicked];
+
+    let mut window_state = WindowState::default();
+    let event = left_click(ElementState::Pressed);
+    let mut trace = DispatchTraceEvent::new(&event);
+    window_state.determine_callbacks(&[hit_test_item(clicked_tag)], &event, &ui_state, Some(&mut trace));
+
+    // The click focuses the clicked node, so the Focus(MouseDown) callback of the other node
+    // isn't called, neither is the Not(MouseDown) callback of the clicked node itself
+    assert_eq!(window_state.focused_node, Some(clicked));
+    trace.finish(Redraw);
+    assert_eq!(trace.skipped, vec![
+        SkippedCallback {
+            node_id: clicked,
+            filter: EventFilter::Not(NotEventFilter::Hover(MouseDown)),
+            reason: SkipReason::NotFilterExcluded,
+        },
+        SkippedCallback {
+            node_id: unfocused,
+            filter: EventFilter::Focus(FocusEventFilter::MouseDown),
+            reason: SkipReason::NodeNotFocused,
+        },
+    ]);
+    assert_eq!(trace.update_screen, Redraw);
+}
+
+#[test]
+fn test_dispatch_trace_is_bounded() {
+    let mut trace = DispatchTrace::default();
+    for _ in 0..(DISPATCH_TRACE_MAX_LEN + 5) {
+        trace.push(DispatchTraceEvent::new(&Event::Awakened));
+    }
+    assert_eq!(trace.take().len(), DISPATCH_TRACE_MAX_LEN);
+    assert!(trace.take().is_empty());
+}
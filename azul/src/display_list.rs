@@ -3,6 +3,7 @@
 
 use std::{
     fmt,
+    rc::Rc,
     sync::{Arc, Mutex},
     collections::BTreeMap,
 };
@@ -12,13 +13,13 @@ use glium::glutin::dpi::{LogicalPosition, LogicalSize};
 use webrender::api::{
     LayoutPixel, RenderApi, FontInstanceKey,
     DisplayListBuilder, PrimitiveInfo, GradientStop, ColorF, PipelineId, Epoch,
-    ImageData, ImageKey, ImageDescriptor, ResourceUpdate, AddImage, AddFontInstance,
-    AddFont, BorderRadius, ClipMode, LayoutPoint, LayoutSize,
-    GlyphOptions, LayoutRect, BorderSide, FontKey, ExternalScrollId,
+    ImageData, ImageDescriptor, ResourceUpdate, AddImage,
+    BorderRadius, ClipMode, LayoutPoint, LayoutSize,
+    GlyphOptions, LayoutRect, BorderSide, ExternalScrollId,
     NormalBorder, ComplexClipRegion, LayoutPrimitiveInfo, ExternalImageId,
     ExternalImageData, ImageFormat, ExternalImageType, TextureTarget,
     ImageRendering, AlphaType, FontInstanceFlags, FontRenderMode, BorderDetails,
-    ColorU, BorderStyle,
+    ColorU, BorderStyle, GlyphInstance, ScrollSensitivity,
 };
 use azul_css::{
     Css, StyleTextAlignmentHorz, LayoutPosition,CssProperty, LayoutOverflow,
@@ -26,28 +27,49 @@ use azul_css::{
     StyleTextColor, StyleBackground, StyleBoxShadow, StyleBackgroundColor,
     StyleBackgroundSize, StyleBackgroundRepeat, StyleBorder, BoxShadowPreDisplayItem,
     LayoutPadding, SizeMetric, BoxShadowClipMode, FontId, StyleTextAlignmentVert,
-    RectStyle, RectLayout, ColorU as StyleColorU
+    RectStyle, RectLayout, StyleOutline, StyleZIndex, StyleBorderImage, ColorU as StyleColorU
 };
 use {
     FastHashMap,
     app_resources::AppResources,
     default_callbacks::StackCheckedPointer,
     traits::Layout,
-    ui_state::UiState,
+    ui_state::{UiState, CssOverrideList, find_css_override},
     ui_description::{UiDescription, StyledNode},
-    id_tree::{NodeDataContainer, NodeId, NodeHierarchy},
+    id_tree::{NodeDataContainer, ArenaNodeData, NodeId, NodeHierarchy},
     dom::{
-        IFrameCallback, NodeData, GlTextureCallback, ScrollTagId, DomHash, new_scroll_tag_id,
+        Dom, IFrameCallback, NodeData, GlTextureCallback, ScrollTagId, DomHash, TagIdGenerator,
         NodeType::{self, Div, Text, Image, GlTexture, IFrame, Label}
     },
-    text_layout::{TextOverflowPass2, ScrollbarInfo, Words, FontMetrics},
+    text_layout::{TextOverflowPass2, ScrollbarInfo, ShapedWords, FontMetrics},
     images::ImageId,
     text_cache::TextInfo,
     compositor::new_opengl_texture_id,
-    window::{Window, LayoutInfo, FakeWindow, ScrollStates, HidpiAdjustedBounds},
+    window::{Window, LayoutInfo, FakeWindow, ScrollStates, HidpiAdjustedBounds, LayoutSnapshot},
+    window_state::WindowSize,
+    profiling::{FrameProfiler, FramePhase},
+    style::CssRuleIndex,
+    debug_overlay::{DebugOverlayFlags, DebugRect, build_debug_overlay},
+    hit_test::{HitRegion, HitClip, HitTransform},
+    layout_result::{LayoutResult, NodeLayoutResult},
+    transform::{LayoutTransform2D, get_node_transform, wr_translate_transform},
+    error_overlay::{error_overlay_dom, ui_reload_error_overlay_dom, error_overlay_css, error_overlay_rect},
+    diagnostics::{AzulError, report},
 };
+#[cfg(feature = "debug-inspector")]
+use inspector::Inspector;
+#[cfg(feature = "profiling")]
+use frame_hud::{frame_hud_dom, frame_hud_rects, frame_hud_css, frame_hud_rect};
+#[cfg(feature = "html-export")]
+use html_export::ExportedFrame;
 
 const DEFAULT_FONT_COLOR: StyleTextColor = StyleTextColor(StyleColorU { r: 0, b: 0, g: 0, a: 255 });
+/// Color of the rectangle that replaces GL textures and iframes in headless rendering,
+/// see `testing::HeadlessOptions::callback_placeholders`
+const CALLBACK_PLACEHOLDER_COLOR: StyleBackgroundColor = StyleBackgroundColor(StyleColorU { r: 255, g: 0, b: 255, a: 255 });
+/// How deep iframes can be nested - deeper iframes are left empty, so that an
+/// iframe that contains itself doesn't overflow the stack
+pub const MAX_IFRAME_DEPTH: usize = 32;
 
 // In case no font size is specified for a node,
 // this will be substituted as the default font size
@@ -60,6 +82,7 @@ lazy_static! {
 
 pub(crate) struct DisplayList<'a, T: Layout + 'a> {
     pub(crate) ui_descr: &'a UiDescription<T>,
+    pub(crate) ui_state: &'a UiState<T>,
     pub(crate) rectangles: NodeDataContainer<DisplayRectangle<'a>>
 }
 
@@ -105,7 +128,7 @@ impl<'a, T: Layout + 'a> DisplayList<'a, T> {
     ///
     /// This only looks at the user-facing styles of the `UiDescription`, not the actual
     /// layout. The layout is done only in the `into_display_list_builder` step.
-    pub(crate) fn new_from_ui_description(ui_description: &'a UiDescription<T>, ui_state: &UiState<T>) -> Self {
+    pub(crate) fn new_from_ui_description(ui_description: &'a UiDescription<T>, ui_state: &'a UiState<T>) -> Self {
         let arena = ui_description.ui_descr_arena.borrow();
 
         let display_rect_arena = arena.node_data.transform(|node, node_id| {
@@ -118,6 +141,7 @@ impl<'a, T: Layout + 'a> DisplayList<'a, T> {
 
         Self {
             ui_descr: ui_description,
+            ui_state,
             rectangles: display_rect_arena,
         }
     }
@@ -141,6 +165,7 @@ impl<'a, T: Layout + 'a> DisplayList<'a, T> {
         // Upload image and font resources
         update_resources(&window.internal.api, app_resources, &mut resource_updates);
 
+        let layout_solving_start = FrameProfiler::start();
         let (laid_out_rectangles, node_depths, word_cache) = do_the_layout(
             node_hierarchy,
             node_data,
@@ -151,44 +176,136 @@ impl<'a, T: Layout + 'a> DisplayList<'a, T> {
             window.state.size.dimensions,
             LogicalPosition::new(0.0, 0.0)
         );
+        fake_window.profiler.record(FramePhase::LayoutSolving, layout_solving_start);
+        fake_window.profiler.add_relayouted_nodes(self.rectangles.len());
+        fake_window.profiler.add_display_rectangles(self.rectangles.len());
+
+        fake_window.state.hit_regions.clear();
 
         let mut scrollable_nodes = get_nodes_that_need_scroll_clip(
             node_hierarchy, &self.rectangles, node_data, &laid_out_rectangles,
-            &node_depths, window.internal.pipeline_id
+            &node_depths, window.internal.pipeline_id, &mut fake_window.tag_ids,
         );
 
+        let rects_in_rendering_order = determine_rendering_order(node_hierarchy, &self.rectangles);
+
+        fake_window.state.layout_result = Rc::new(build_layout_result(
+            node_hierarchy,
+            &self.rectangles,
+            &laid_out_rectangles,
+            &scrollable_nodes,
+            &rects_in_rendering_order,
+        ));
+        update_layout_snapshot(&mut fake_window.layout_snapshot, &fake_window.state.layout_result, &self.rectangles);
+        #[cfg(feature = "html-export")] {
+            fake_window.state.exported_frame = Some(Rc::new(ExportedFrame::new(
+                node_hierarchy,
+                node_data,
+                &fake_window.state.layout_result,
+                self.rectangles.internal.iter().map(|rect| rect.style.clone()).collect(),
+                window.state.size.dimensions,
+                app_resources,
+            )));
+        }
+
         // Make sure unused scroll states are garbage collected.
         window.scroll_states.remove_unused_scroll_states();
 
         let LogicalSize { width, height } = window.state.size.dimensions;
         let mut builder = DisplayListBuilder::with_capacity(window.internal.pipeline_id, TypedSize2D::new(width as f32, height as f32), self.rectangles.len());
 
-        let rects_in_rendering_order = determine_rendering_order(node_hierarchy, &self.rectangles, &laid_out_rectangles);
+        let mut text_baselines = Vec::new();
 
-        push_rectangles_into_displaylist(
-            &laid_out_rectangles,
-            window.internal.epoch,
-            rects_in_rendering_order,
-            &mut scrollable_nodes,
-            &mut window.scroll_states,
-            &DisplayListParametersRef {
+        {
+            let referenced_content = DisplayListParametersRef {
                 pipeline_id: window.internal.pipeline_id,
                 node_hierarchy: node_hierarchy,
                 node_data: node_data,
                 render_api: &window.internal.api,
                 display_rectangle_arena: &self.rectangles,
                 css: &window.css,
+                css_index: &window.css_index,
                 word_cache: &word_cache,
-            },
-            &mut DisplayListParametersMut {
+            };
+
+            let mut referenced_mutable_content = DisplayListParametersMut {
                 app_data: app_data_access,
                 app_resources,
                 fake_window,
                 builder: &mut builder,
                 resource_updates: &mut resource_updates,
                 pipeline_id: window.internal.pipeline_id,
-            },
-        );
+                debug_overlay: window.state.debug_overlay,
+                text_baselines: &mut text_baselines,
+                iframe_depth: 0,
+            };
+
+            push_rectangles_into_displaylist(
+                &laid_out_rectangles,
+                window.internal.epoch,
+                rects_in_rendering_order,
+                &mut scrollable_nodes,
+                &mut window.scroll_states,
+                &referenced_content,
+                &mut referenced_mutable_content,
+            );
+
+            if !window.state.debug_overlay.is_empty() {
+                let scroll_frames = scrollable_nodes.overflowing_nodes.keys().cloned().collect::<Vec<_>>();
+                let debug_rects = build_debug_overlay(
+                    window.state.debug_overlay,
+                    node_data,
+                    &referenced_mutable_content.fake_window.state.layout_result,
+                    &self.rectangles,
+                    &scroll_frames,
+                    referenced_mutable_content.text_baselines,
+                );
+                push_debug_overlay(&debug_rects, referenced_mutable_content.builder);
+            }
+
+            let error_overlay = match (&window.state.callback_panic, &window.state.ui_reload_error) {
+                (Some(panic), _) => Some(error_overlay_dom(panic)),
+                (None, Some(error)) => Some(ui_reload_error_overlay_dom(error)),
+                (None, None) => None,
+            };
+
+            if let Some(error_overlay) = error_overlay {
+                push_error_overlay(
+                    error_overlay,
+                    window.state.size.dimensions,
+                    window.internal.epoch,
+                    &mut scrollable_nodes,
+                    &referenced_content,
+                    &mut referenced_mutable_content,
+                );
+            }
+
+            #[cfg(feature = "profiling")] {
+                if window.state.frame_hud {
+                    push_frame_hud(
+                        window.state.size.dimensions,
+                        window.internal.epoch,
+                        &mut scrollable_nodes,
+                        &referenced_content,
+                        &mut referenced_mutable_content,
+                    );
+                }
+            }
+
+            #[cfg(feature = "debug-inspector")] {
+                if window.inspector.is_open() {
+                    push_inspector_overlay(
+                        &window.inspector,
+                        self.ui_state,
+                        window.state.size.dimensions,
+                        window.internal.epoch,
+                        &mut scrollable_nodes,
+                        &referenced_content,
+                        &mut referenced_mutable_content,
+                    );
+                }
+            }
+        }
 
         &window.internal.api.update_resources(resource_updates);
 
@@ -196,117 +313,100 @@ impl<'a, T: Layout + 'a> DisplayList<'a, T> {
     }
 }
 
-/// Looks if any new images need to be uploaded and stores the in the image resources
-fn update_resources(
-    api: &RenderApi,
-    app_resources: &mut AppResources,
-    resource_updates: &mut Vec<ResourceUpdate>)
+/// Records the layout result (and the computed styles, if requested) of the top-level DOM
+fn update_layout_snapshot<'a>(
+    snapshot: &mut LayoutSnapshot,
+    layout_result: &Rc<LayoutResult>,
+    display_rects: &NodeDataContainer<DisplayRectangle<'a>>)
 {
-    update_image_resources(api, app_resources, resource_updates);
-    update_font_resources(api, app_resources, resource_updates);
+    snapshot.layout = layout_result.clone();
+    snapshot.styles.clear();
+    if snapshot.capture_styles {
+        snapshot.styles.extend(display_rects.internal.iter().map(|rect| (rect.style.clone(), rect.layout.clone())));
+    }
 }
 
-fn update_image_resources(
-    api: &RenderApi,
-    app_resources: &mut AppResources,
-    resource_updates: &mut Vec<ResourceUpdate>)
+/// Calculates the box model, the scrollable size, the clipping and the drawing
+/// order of every node from the solved rectangles, see `WindowState::get_layout_result()`
+fn build_layout_result<'a>(
+    node_hierarchy: &NodeHierarchy,
+    display_rects: &NodeDataContainer<DisplayRectangle<'a>>,
+    laid_out_rectangles: &NodeDataContainer<LayoutRect>,
+    scrolled_nodes: &ScrolledNodes,
+    rendering_order: &RenderingOrder)
+-> LayoutResult
 {
-    use images::{ImageState, ImageInfo};
+    let mut nodes = display_rects.internal.iter().zip(laid_out_rectangles.internal.iter()).enumerate().map(|(idx, (display_rect, border_box))| {
 
-    let mut updated_images = Vec::<(ImageId, (ImageData, ImageDescriptor))>::new();
-    let mut to_delete_images = Vec::<(ImageId, Option<ImageKey>)>::new();
+        let node_id = NodeId::new(idx);
+        let style = &display_rect.style;
 
-    // possible performance bottleneck (duplicated cloning) !!
-    for (key, value) in app_resources.images.iter() {
-        match *value {
-            ImageState::ReadyForUpload(ref d) => {
-                updated_images.push((key.clone(), d.clone()));
-            },
-            ImageState::Uploaded(_) => { },
-            ImageState::AboutToBeDeleted((ref k, _)) => {
-                to_delete_images.push((key.clone(), k.clone()));
-            }
-        }
-    }
+        let scroll_content_size = if node_is_scroll_frame(style) && node_needs_to_clip_children(style) {
+            Some(match scrolled_nodes.overflowing_nodes.get(&node_id) {
+                Some(scroll_node) => border_box.union(&scroll_node.child_rect).size,
+                None => border_box.size,
+            })
+        } else {
+            None
+        };
 
-    // Remove any images that should be deleted
-    for (resource_key, image_key) in to_delete_images.into_iter() {
-        if let Some(image_key) = image_key {
-            resource_updates.push(ResourceUpdate::DeleteImage(image_key));
+        NodeLayoutResult {
+            scroll_content_size,
+            is_clipped: is_clipped_by_parents(node_id, node_hierarchy, display_rects, laid_out_rectangles, &scrolled_nodes.overflowing_nodes),
+            .. NodeLayoutResult::from_border_box(*border_box, &display_rect.layout, style)
         }
-        app_resources.images.remove(&resource_key);
-    }
-
-    // Upload all remaining images to the GPU only if the haven't been
-    // uploaded yet
-    for (resource_key, (data, descriptor)) in updated_images.into_iter() {
-
-        let key = api.generate_image_key();
-        resource_updates.push(ResourceUpdate::AddImage(
-            AddImage { key, descriptor, data, tiling: None }
-        ));
+    }).collect::<Vec<_>>();
 
-        *app_resources.images.get_mut(&resource_key).unwrap() =
-            ImageState::Uploaded(ImageInfo {
-                key: key,
-                descriptor: descriptor
-        });
+    for (z_order, item) in rendering_order.node_ids.iter().enumerate() {
+        nodes[item.node_id.index()].z_order = z_order;
     }
+
+    LayoutResult { nodes }
 }
 
-// almost the same as update_image_resources, but fonts
-// have two HashMaps that need to be updated
-fn update_font_resources(
-    api: &RenderApi,
-    app_resources: &mut AppResources,
-    resource_updates: &mut Vec<ResourceUpdate>)
+/// Whether the node is entirely outside of the clips (`overflow: hidden`, rounded corners)
+/// of its parents - scroll frames are ignored, since the node could be scrolled into view
+/// and the clips of transformed nodes can't be compared to the untransformed rectangle.
+fn is_clipped_by_parents<'a>(
+    node_id: NodeId,
+    node_hierarchy: &NodeHierarchy,
+    display_rects: &NodeDataContainer<DisplayRectangle<'a>>,
+    laid_out_rectangles: &NodeDataContainer<LayoutRect>,
+    overflowing_nodes: &BTreeMap<NodeId, OverflowingScrollNode>)
+-> bool
 {
-    use font::FontState;
-    use azul_css::FontId;
-
-    let mut updated_fonts = Vec::<(FontId, Vec<u8>)>::new();
-    let mut to_delete_fonts = Vec::<(FontId, Option<(FontKey, Vec<FontInstanceKey>)>)>::new();
-
-    for (key, value) in app_resources.font_data.borrow().iter() {
-        match &*(*value.2).borrow() {
-            FontState::ReadyForUpload(ref bytes) => {
-                updated_fonts.push((key.clone(), bytes.clone()));
+    let clip_stack = get_clip_stack(node_id, node_hierarchy, display_rects, laid_out_rectangles, overflowing_nodes);
+    let mut visible_rect = laid_out_rectangles[node_id];
+
+    for item in &clip_stack {
+        match item {
+            ClipStackItem::ReferenceFrame { .. } => return false,
+            ClipStackItem::Clip { region, .. } => {
+                visible_rect = match visible_rect.intersection(&region.rect) {
+                    Some(rect) => rect,
+                    None => return true,
+                };
             },
-            FontState::Uploaded(_) => { },
-            FontState::AboutToBeDeleted(ref font_key) => {
-                let to_delete_font_instances = font_key.and_then(|f_key| {
-                    let to_delete_font_instances = app_resources.fonts[&f_key].values().cloned().collect();
-                    Some((f_key.clone(), to_delete_font_instances))
-                });
-                to_delete_fonts.push((key.clone(), to_delete_font_instances));
-            }
+            ClipStackItem::ScrollFrame { .. } => { },
         }
     }
 
-    // Delete the complete font. Maybe a more granular option to
-    // keep the font data in memory should be added later
-    for (resource_key, to_delete_instances) in to_delete_fonts.into_iter() {
-        if let Some((font_key, font_instance_keys)) = to_delete_instances {
-            for instance in font_instance_keys {
-                resource_updates.push(ResourceUpdate::DeleteFontInstance(instance));
-            }
-            resource_updates.push(ResourceUpdate::DeleteFont(font_key));
-            app_resources.fonts.remove(&font_key);
-        }
-        app_resources.font_data.borrow_mut().remove(&resource_key);
-    }
+    false
+}
 
-    // Upload all remaining fonts to the GPU only if the haven't been uploaded yet
-    for (resource_key, data) in updated_fonts.into_iter() {
-        let key = api.generate_font_key();
-        resource_updates.push(ResourceUpdate::AddFont(AddFont::Raw(key, data, 0))); // TODO: use the index better?
-        let mut borrow_mut = app_resources.font_data.borrow_mut();
-        *borrow_mut.get_mut(&resource_key).unwrap().2.borrow_mut() = FontState::Uploaded(key);
-    }
+/// Uploads the images and fonts that the window doesn't have on its GPU yet
+/// and deletes the ones that were deleted since the last display list
+fn update_resources(
+    api: &RenderApi,
+    app_resources: &mut AppResources,
+    resource_updates: &mut Vec<ResourceUpdate>)
+{
+    app_resources.update_gpu_resources(api, resource_updates);
 }
 
-/// In order to render rectangles in the correct order, we have to group them together:
-/// As long as there are no position:absolute items, items are inserted in a parents-then-child order
+/// In order to render rectangles in the correct order, the nodes have to be sorted:
+/// As long as there are no position:absolute items or z-indices, items are inserted
+/// in a parents-then-child order
 ///
 /// ```no_run,ignore
 /// a
@@ -336,86 +436,64 @@ fn update_font_resources(
 /// ```
 ///
 /// will be rendered as: `a,b,e,f,g,c,d`, so that the `c,d` sub-DOM is on top of the rest
-/// of the content. To support this, the content needs to be grouped: Whenever there is a
-/// `position:absolute` encountered, the children are grouped into a new `ContentGroup`:
+/// of the content. If there are multiple position:absolute sub-DOMs, earlier ones are drawn
+/// on top of later ones. `position:fixed` sub-DOMs are drawn the same way.
 ///
-/// ```no_run,ignore
-/// Group 1: [a, b, c, e, f, g]
-/// Group 2: [c, d]
-/// ```
-/// Then the groups are simply rendered in-order: if there are multiple position:absolute
-/// groups, this has the side effect of later groups drawing on top of earlier groups.
-#[derive(Debug, Clone, PartialEq)]
-struct ContentGroup {
-    /// The parent of the current node group, i.e. either the root node (0)
-    /// or the last positioned node ()
-    root: RenderableNodeId,
-    /// Depth of the root node in the DOM hierarchy
-    root_depth: usize,
-    /// Node ids in order of drawing
-    node_ids: Vec<RenderableNodeId>,
-}
-
+/// Nodes with an integer `z-index` establish a stacking context: the node and its children
+/// are drawn together, after (on top of) the rest of the parent stacking context if the
+/// z-index is positive or zero and before it (but on top of the root of the parent context)
+/// if it is negative. Stacking contexts with the same parent are sorted by their z-index,
+/// contexts with the same z-index are drawn in document order. Absolutely positioned
+/// sub-DOMs without a z-index order the z-indices of their children the same way.
+///
+/// The hit-test uses the same order, so the node that is drawn on top receives the events.
 #[derive(Debug, Copy, Clone, PartialEq)]
 struct RenderableNodeId {
-    /// Whether the (hierarchical) children of this group need to be clipped (usually
-    /// because the parent has an `overflow:hidden` property set).
-    clip_children: bool,
     /// Whether the children overflow the parent (see `O`)
     scrolls_children: bool,
     /// The actual node ID of the content
     node_id: NodeId,
 }
 
+/// Nodes in the order in which they are pushed into the display list, see `RenderableNodeId`
 #[derive(Debug, Clone, PartialEq)]
-struct ContentGroupOrder {
-    groups: Vec<ContentGroup>,
+struct RenderingOrder {
+    node_ids: Vec<RenderableNodeId>,
 }
 
 fn determine_rendering_order<'a>(
     node_hierarchy: &NodeHierarchy,
     rectangles: &NodeDataContainer<DisplayRectangle<'a>>,
-    layouted_rects: &NodeDataContainer<LayoutRect>,
-) -> ContentGroupOrder
+) -> RenderingOrder
 {
-    let mut content_groups = Vec::new();
+    let mut node_ids = Vec::new();
 
     determine_rendering_order_inner(
         node_hierarchy,
         rectangles,
-        layouted_rects,
-        0, // depth of this node
         NodeId::new(0),
-        &mut content_groups
+        &mut node_ids
     );
 
-    ContentGroupOrder { groups: content_groups }
+    RenderingOrder { node_ids }
 }
 
+/// Appends the `root_id` and its children to the `node_ids`. Children that are absolutely
+/// positioned or establish a stacking context are sorted (recursively) with their children.
 fn determine_rendering_order_inner<'a>(
     node_hierarchy: &NodeHierarchy,
     rectangles: &NodeDataContainer<DisplayRectangle<'a>>,
-    layouted_rects: &NodeDataContainer<LayoutRect>,
     // recursive parameters
-    root_depth: usize,
     root_id: NodeId,
-    content_groups: &mut Vec<ContentGroup>,
+    node_ids: &mut Vec<RenderableNodeId>,
 )
 {
     use id_tree::NodeEdge;
 
-    let mut root_group = ContentGroup {
-        root: RenderableNodeId {
-            node_id: root_id,
-            clip_children: node_needs_to_clip_children(&rectangles[root_id].style),
-            scrolls_children: false, // TODO
-        },
-        root_depth,
-        node_ids: Vec::new(),
-    };
-
+    let mut in_flow_node_ids = Vec::new();
     let mut absolute_node_ids = Vec::new();
-    let mut depth = root_depth + 1;
+    // (z-index, node) of the child stacking contexts, in document order
+    let mut stacking_contexts = Vec::new();
 
     // Same as the traverse function, but allows us to skip items, returns the next element
     fn traverse_simple(root_id: NodeId, current_node: NodeEdge<NodeId>, node_hierarchy: &NodeHierarchy) -> Option<NodeEdge<NodeId>> {
@@ -445,33 +523,28 @@ fn determine_rendering_order_inner<'a>(
         let mut should_continue_loop = true;
 
         if next_node_id.clone().inner_value() != root_id {
-            match next_node_id {
-                NodeEdge::Start(node_id) => {
-                    let rect_node = &rectangles[node_id];
-                    let position = rect_node.layout.position.unwrap_or_default();
-                    if position == LayoutPosition::Absolute {
-                        // For now, ignore the node and put it aside for later
-                        absolute_node_ids.push((depth, node_id));
-                        // Skip this sub-tree and go straight to the next sibling
-                        // Since the tree is positioned absolute, we'll worry about it later
-                        current_node_edge = NodeEdge::End(node_id);
-                        should_continue_loop = false;
-                    } else {
-                        // TODO: Overflow hidden in horizontal / vertical direction
-                        let node_is_overflow_hidden = node_needs_to_clip_children(&rect_node.style);
-                        let node_needs_to_scroll_children = false; // TODO
-                        root_group.node_ids.push(RenderableNodeId {
-                            node_id,
-                            clip_children: node_is_overflow_hidden,
-                            scrolls_children: node_needs_to_scroll_children,
-                        });
-                    }
-
-                    depth += 1;
-                },
-                NodeEdge::End(node_id) => {
-                    depth -= 1;
-                },
+            if let NodeEdge::Start(node_id) = next_node_id {
+                let rect_node = &rectangles[node_id];
+                let position = rect_node.layout.position.unwrap_or_default();
+                if let Some(z_index) = get_z_index(rect_node) {
+                    // The sub-tree is drawn as a whole, after it is sorted by its z-index
+                    stacking_contexts.push((z_index, node_id));
+                    current_node_edge = NodeEdge::End(node_id);
+                    should_continue_loop = false;
+                } else if position == LayoutPosition::Absolute || position == LayoutPosition::Fixed {
+                    // For now, ignore the node and put it aside for later
+                    absolute_node_ids.push(node_id);
+                    // Skip this sub-tree and go straight to the next sibling
+                    // Since the tree is positioned absolute, we'll worry about it later
+                    current_node_edge = NodeEdge::End(node_id);
+                    should_continue_loop = false;
+                } else {
+                    let node_needs_to_scroll_children = false; // TODO
+                    in_flow_node_ids.push(RenderableNodeId {
+                        node_id,
+                        scrolls_children: node_needs_to_scroll_children,
+                    });
+                }
             }
         }
 
@@ -480,21 +553,47 @@ fn determine_rendering_order_inner<'a>(
         }
     }
 
-    content_groups.push(root_group);
+    // Stable sort, so that contexts with the same z-index stay in document order
+    stacking_contexts.sort_by_key(|(z_index, _)| *z_index);
+    let (negative_contexts, positive_contexts): (Vec<_>, Vec<_>) = stacking_contexts.into_iter()
+        .partition(|(z_index, _)| *z_index < 0);
+
+    node_ids.push(RenderableNodeId {
+        node_id: root_id,
+        scrolls_children: false, // TODO
+    });
+
+    for (_, context_node_id) in negative_contexts {
+        determine_rendering_order_inner(node_hierarchy, rectangles, context_node_id, node_ids);
+    }
+
+    node_ids.extend(in_flow_node_ids);
 
     // Note: Currently reversed order, so that earlier absolute
     // items are drawn on top of later absolute items
-    for (absolute_depth, absolute_node_id) in absolute_node_ids.into_iter().rev() {
-        determine_rendering_order_inner(node_hierarchy, rectangles, layouted_rects, absolute_depth, absolute_node_id, content_groups);
+    for absolute_node_id in absolute_node_ids.into_iter().rev() {
+        determine_rendering_order_inner(node_hierarchy, rectangles, absolute_node_id, node_ids);
+    }
+
+    for (_, context_node_id) in positive_contexts {
+        determine_rendering_order_inner(node_hierarchy, rectangles, context_node_id, node_ids);
+    }
+}
+
+/// Returns the z-index of the node, `None` if it doesn't establish a stacking context
+fn get_z_index<'a>(rect: &DisplayRectangle<'a>) -> Option<i32> {
+    match rect.style.z_index {
+        Some(StyleZIndex::Integer(z_index)) => Some(z_index),
+        Some(StyleZIndex::Auto) | None => None,
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct WordCache(BTreeMap<NodeId, (Words, FontMetrics)>);
+pub struct WordCache(BTreeMap<NodeId, (ShapedWords, FontMetrics)>);
 
 fn do_the_layout<'a,'b, T: Layout>(
     node_hierarchy: &NodeHierarchy,
-    node_data: &NodeDataContainer<NodeData<T>>,
+    node_data: &ArenaNodeData<NodeData<T>>,
     display_rects: &NodeDataContainer<DisplayRectangle<'a>>,
     resource_updates: &mut Vec<ResourceUpdate>,
     app_resources: &'b mut AppResources,
@@ -503,10 +602,11 @@ fn do_the_layout<'a,'b, T: Layout>(
     rect_offset: LogicalPosition)
 -> (NodeDataContainer<LayoutRect>, Vec<(usize, NodeId)>, WordCache)
 {
-    use text_layout::{split_text_into_words, get_words_cached};
-    use ui_solver::{solve_flex_layout_height, solve_flex_layout_width, get_x_positions, get_y_positions};
+    use text_layout::{shape_text_cached, get_words_cached};
+    use text_cache::ShapedTextKey;
+    use ui_solver::{solve_flex_layout_height, solve_flex_layout_width, get_x_positions, get_y_positions, ContentSize};
 
-    let word_cache: BTreeMap<NodeId, (Words, FontMetrics)> = node_hierarchy
+    let word_cache: BTreeMap<NodeId, (ShapedWords, FontMetrics)> = node_hierarchy
     .linear_iter()
     .filter_map(|id| {
         let (font, font_metrics, font_id, font_size) = match node_data[id].node_type {
@@ -538,47 +638,59 @@ fn do_the_layout<'a,'b, T: Layout>(
 
         match &node_data[id].node_type {
             NodeType::Label(ref string_to_render) => {
-                Some((id, (split_text_into_words(&string_to_render, &font, font_metrics.font_size_no_line_height, font_metrics.letter_spacing), font_metrics)))
+                // Labels with the same text, font, size and letter spacing share the same words
+                Some((id, (shape_text_cached(&string_to_render,
+                    &font,
+                    &font_id,
+                    &font_size,
+                    font_metrics.letter_spacing,
+                    &mut app_resources.text_cache), font_metrics)))
             },
             NodeType::Text(text_id) => {
                 // Cloning the words here due to lifetime problems
-                Some((id, (get_words_cached(&text_id,
+                let words = get_words_cached(&text_id,
                     &font,
                     &font_id,
                     &font_size,
                     font_metrics.font_size_no_line_height,
                     font_metrics.letter_spacing,
-                    &mut app_resources.text_cache).clone(), font_metrics)))
+                    &mut app_resources.text_cache).clone();
+                let key = ShapedTextKey::for_text_id(&text_id, &font_id, &font_size, font_metrics.letter_spacing);
+                Some((id, (ShapedWords { key, words: Rc::new(words) }, font_metrics)))
             },
             _ => None,
         }
     }).collect();
 
-    let preferred_widths = node_data.transform(|node, _| {
-        node.node_type.get_preferred_width(&app_resources.images)
+    let content_widths = node_data.transform(|node, id| {
+        node.node_type.get_content_width(
+            &app_resources.images,
+            word_cache.get(&id).and_then(|e| Some(&*e.0.words)),
+            word_cache.get(&id).and_then(|e| Some(e.1)),
+        )
     });
 
     let solved_widths = solve_flex_layout_width(
         node_hierarchy,
         &display_rects,
-        preferred_widths,
+        content_widths,
         rect_size.width as f32,
     );
 
-    let preferred_heights = node_data.transform(|node, id| {
+    let content_heights = node_data.transform(|node, id| {
         use text_layout::TextSizePx;
-        node.node_type.get_preferred_height_based_on_width(
+        ContentSize::from_preferred(node.node_type.get_preferred_height_based_on_width(
             TextSizePx(solved_widths.solved_widths[id].total()),
             &app_resources.images,
-            word_cache.get(&id).and_then(|e| Some(&e.0)),
+            word_cache.get(&id).and_then(|e| Some(&*e.0.words)),
             word_cache.get(&id).and_then(|e| Some(e.1)),
-        ).and_then(|text_size| Some(text_size.0))
+        ).and_then(|text_size| Some(text_size.0)))
     });
 
     let solved_heights = solve_flex_layout_height(
         node_hierarchy,
         &solved_widths,
-        preferred_heights,
+        content_heights,
         rect_size.height as f32,
     );
 
@@ -598,6 +710,92 @@ fn do_the_layout<'a,'b, T: Layout>(
     (layouted_arena, solved_widths.non_leaf_nodes_sorted_by_depth, WordCache(word_cache))
 }
 
+/// Key under which the last display list of a window is cached. If the key
+/// of the current frame is the same as the key of the last frame, the display list
+/// doesn't need to be rebuilt, only re-submitted.
+///
+/// Scroll offsets are not part of the key: scrolling only moves the scroll frames
+/// (see `scroll_all_nodes`) and never requires rebuilding the display list.
+///
+/// NOTE: There is no partial invalidation (i.e. rebuilding only the stacking
+/// contexts affected by a paint-only change) - if the key doesn't match,
+/// the entire display list is rebuilt.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct DisplayListCacheKey {
+    /// Hash of the DOM tree (node data and hierarchy). Equal DOMs (see the `PartialEq`
+    /// impl of `Dom`) have equal hashes, `Dom::content_eq` isn't used for the cache.
+    pub(crate) dom_hash: DomHash,
+    /// Hash of the styled nodes, after the cascade - since `:hover`, `:focus` and `:active`
+    /// are applied during the cascade, this also covers the hover / focus state
+    pub(crate) style_hash: u64,
+    /// Hash of which nodes have hit-testing tags. The tags themselves are handed out
+    /// by the (never restarted) generator of the window, so two identical frames get
+    /// different tags - only their offset to the first tag of the frame is hashed.
+    /// On a cache hit, the UiState is re-tagged with the tags of the cached display list,
+    /// see `UiState::reuse_tags`.
+    pub(crate) tags_hash: u64,
+    /// Logical width and height of the window, as raw bits
+    pub(crate) window_size: (u64, u64),
+    /// DPI factor of the window, as raw bits
+    pub(crate) hidpi_factor: u64,
+}
+
+impl DisplayListCacheKey {
+
+    /// Returns `None` if the display list of this frame can't be cached, i.e. because
+    /// the DOM contains OpenGL textures or iframes, which have to be re-rendered on every frame.
+    pub(crate) fn new<T: Layout>(
+        ui_description: &UiDescription<T>,
+        ui_state: &UiState<T>,
+        window_size: &WindowSize)
+    -> Option<Self>
+    {
+        use std::hash::{Hash, Hasher};
+        use std::collections::hash_map::DefaultHasher;
+
+        let arena = ui_description.ui_descr_arena.borrow();
+        if arena.node_data.iter().any(|node| match node.node_type {
+            GlTexture(_) | IFrame(_) => true,
+            _ => false,
+        }) {
+            return None;
+        }
+
+        let mut style_hasher = DefaultHasher::default();
+        ui_description.styled_nodes.hash(&mut style_hasher);
+
+        let mut tags_hasher = DefaultHasher::default();
+        let first_tag = ui_state.node_ids_to_tag_ids.values().min().cloned().unwrap_or(0);
+        for (node_id, tag_id) in &ui_state.node_ids_to_tag_ids {
+            (node_id, tag_id - first_tag).hash(&mut tags_hasher);
+        }
+
+        Some(Self {
+            dom_hash: ui_state.dom.calculate_dom_hash(),
+            style_hash: style_hasher.finish(),
+            tags_hash: tags_hasher.finish(),
+            window_size: (window_size.dimensions.width.to_bits(), window_size.dimensions.height.to_bits()),
+            hidpi_factor: window_size.hidpi_factor.to_bits(),
+        })
+    }
+}
+
+/// Returns whether the resources are in a state where a cached display list can be
+/// re-used: if images or fonts still need to be uploaded or deleted, the display
+/// list has to be rebuilt, since the resource updates happen while building it.
+pub(crate) fn resources_are_settled(app_resources: &AppResources, api: &RenderApi) -> bool {
+    app_resources.are_gpu_resources_settled(api)
+}
+
+/// Counts how often the display list cache of a window could be re-used
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DisplayListCacheStats {
+    /// How many frames re-used the display list of the last frame
+    pub hits: usize,
+    /// How many frames had to rebuild the display list
+    pub misses: usize,
+}
+
 #[derive(Default, Debug, Clone)]
 pub(crate)  struct ScrolledNodes {
     pub(crate) overflowing_nodes: BTreeMap<NodeId, OverflowingScrollNode>,
@@ -627,10 +825,11 @@ pub(crate) struct OverflowingScrollNode {
 fn get_nodes_that_need_scroll_clip<'a, T: 'a + Layout>(
     node_hierarchy: &NodeHierarchy,
     display_list_rects: &NodeDataContainer<DisplayRectangle<'a>>,
-    dom_rects: &NodeDataContainer<NodeData<T>>,
+    dom_rects: &ArenaNodeData<NodeData<T>>,
     layouted_rects: &NodeDataContainer<LayoutRect>,
     parents: &[(usize, NodeId)],
     pipeline_id: PipelineId,
+    tag_ids: &mut TagIdGenerator,
 ) -> ScrolledNodes {
 
     let mut nodes = BTreeMap::new();
@@ -640,7 +839,8 @@ fn get_nodes_that_need_scroll_clip<'a, T: 'a + Layout>(
 
         let mut children_sum_rect = None;
 
-        for child in parent.children(&node_hierarchy) {
+        // Fixed children don't scroll with the parent, so they can't make it overflow
+        for child in parent.children(&node_hierarchy).filter(|child| !is_fixed(&display_list_rects[*child])) {
             let old = children_sum_rect.unwrap_or(LayoutRect::zero());
             children_sum_rect = Some(old.union(&layouted_rects[child]));
         }
@@ -652,7 +852,7 @@ fn get_nodes_that_need_scroll_clip<'a, T: 'a + Layout>(
 
         let parent_rect = &layouted_rects.get(*parent).unwrap();
 
-        if children_sum_rect.contains_rect(parent_rect) {
+        if parent_rect.contains_rect(&children_sum_rect) {
             continue;
         }
 
@@ -665,7 +865,7 @@ fn get_nodes_that_need_scroll_clip<'a, T: 'a + Layout>(
         // Create a unique scroll tag for hit-testing
         let scroll_tag_id = match display_list_rects.get(*parent).and_then(|node| node.tag) {
             Some(existing_tag) => ScrollTagId(existing_tag),
-            None => new_scroll_tag_id(),
+            None => tag_ids.new_scroll_tag_id(),
         };
 
         tags_to_node_ids.insert(scroll_tag_id, *parent);
@@ -681,15 +881,28 @@ fn get_nodes_that_need_scroll_clip<'a, T: 'a + Layout>(
     ScrolledNodes { overflowing_nodes: nodes, tags_to_node_ids }
 }
 
+/// Whether the children of a node can be scrolled (`overflow: scroll` or `overflow: auto`),
+/// if they overflow the node
+fn node_is_scroll_frame(style: &RectStyle) -> bool {
+    let overflow = style.overflow.unwrap_or_default();
+    overflow.horizontal.can_overflow() || overflow.vertical.can_overflow()
+}
+
+/// Whether the (hierarchical) children of a node need to be clipped, either because the
+/// node has an `overflow:hidden` property set or because it has rounded corners
+///
+/// TODO: Overflow hidden in horizontal / vertical direction
 fn node_needs_to_clip_children(style: &RectStyle) -> bool {
     let overflow = style.overflow.unwrap_or_default();
+    let has_rounded_corners = style.border_radius.map(|radius| radius != StyleBorderRadius::zero()).unwrap_or(false);
     overflow.horizontal.clips_children() ||
-    overflow.vertical.clips_children()
+    overflow.vertical.clips_children() ||
+    has_rounded_corners
 }
 
 #[test]
 fn test_overflow_parsing() {
-    use azul_css::{TextOverflowBehaviour, TextOverflowBehaviourInner};
+    use azul_css::{TextOverflowBehaviour, TextOverflowBehaviourInner, PixelSize};
     let style1 = RectStyle::default();
     assert!(!node_needs_to_clip_children(&style1));
 
@@ -710,28 +923,538 @@ fn test_overflow_parsing() {
         .. Default::default()
     };
     assert!(node_needs_to_clip_children(&style3));
+
+    let style4 = RectStyle {
+        border_radius: Some(StyleBorderRadius::zero()),
+        .. Default::default()
+    };
+    assert!(!node_needs_to_clip_children(&style4));
+
+    let style5 = RectStyle {
+        border_radius: Some(StyleBorderRadius(azul_css::BorderRadius::uniform(PixelSize::new(PixelValue::px(5.0), PixelValue::px(5.0))))),
+        .. Default::default()
+    };
+    assert!(node_needs_to_clip_children(&style5));
+}
+
+#[test]
+fn test_display_list_cache_key() {
+
+    use dom::{Dom, Callback, On, UpdateScreen, DontRedraw};
+    use app_state::AppState;
+    use window::CallbackInfo;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    fn callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        DontRedraw
+    }
+
+    // The tags of a window are never restarted, so every frame gets new tags
+    let mut tag_ids = TagIdGenerator::new();
+
+    let mut cache_key = |dom: Dom<TestLayout>| -> Option<DisplayListCacheKey> {
+        let mut ui_state = dom.into_ui_state(&mut tag_ids);
+        let css = Css::default();
+        let ui_description = UiDescription::match_css_to_dom(
+            &mut ui_state,
+            &css,
+            &CssRuleIndex::new(&css),
+            &mut None,
+            &mut None,
+            &BTreeMap::new(),
+            false,
+            &mut tag_ids,
+        );
+        DisplayListCacheKey::new(&ui_description, &ui_state, &WindowSize::default())
+    };
+
+    let build_dom = || Dom::div()
+        .with_child(Dom::label("hello").with_class("a"))
+        .with_child(Dom::div().with_callback(On::MouseUp, Callback(callback)));
+
+    // Two identical frames produce the same key, even though their tags differ
+    let first_key = cache_key(build_dom());
+    assert!(first_key.is_some());
+    assert_eq!(first_key, cache_key(build_dom()));
+
+    // Any change in the DOM invalidates the key
+    assert!(first_key != cache_key(build_dom().with_class("b")));
+
+    // ... as does tagging a different node
+    let moved_callback = Dom::div()
+        .with_child(Dom::label("hello").with_class("a").with_callback(On::MouseUp, Callback(callback)))
+        .with_child(Dom::div());
+    assert!(first_key != cache_key(moved_callback));
+}
+
+#[test]
+fn test_hit_test_z_order_of_absolute_siblings() {
+
+    use dom::{Dom, Callback, On, UpdateScreen, DontRedraw};
+    use app_state::AppState;
+    use window::CallbackInfo;
+    use hit_test::hit_test_regions;
+    use glium::glutin::dpi::LogicalPosition;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    fn callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        DontRedraw
+    }
+
+    // div (tagged)
+    //     div (position: absolute, tagged)
+    //     div (position: absolute, tagged, overlaps the first one)
+    //     div (not tagged, covers everything)
+    let ui_state = UiState::from_dom(Dom::div()
+        .with_callback(On::MouseUp, Callback(callback))
+        .with_child(Dom::div().with_callback(On::MouseUp, Callback(callback)))
+        .with_child(Dom::div().with_callback(On::MouseUp, Callback(callback)))
+        .with_child(Dom::div()));
+
+    let rect = |x: f32, y: f32, w: f32, h: f32| LayoutRect::new(LayoutPoint::new(x, y), LayoutSize::new(w, h));
+    let laid_out_rectangles = NodeDataContainer::new(vec![
+        rect(0.0, 0.0, 100.0, 100.0),
+        rect(10.0, 10.0, 50.0, 50.0),
+        rect(30.0, 30.0, 50.0, 50.0),
+        rect(0.0, 0.0, 100.0, 100.0),
+    ]);
+
+    let styled_node = StyledNode::default();
+    let mut display_rects = ui_state.dom.arena.node_data.transform(|_, node_id| {
+        DisplayRectangle::new(ui_state.node_ids_to_tag_ids.get(&node_id).cloned(), &styled_node)
+    });
+    display_rects[NodeId::new(1)].layout.position = Some(LayoutPosition::Absolute);
+    display_rects[NodeId::new(2)].layout.position = Some(LayoutPosition::Absolute);
+
+    // Record the tagged rectangles in the order in which push_rectangles_into_displaylist
+    // would push them, the same way as displaylist_handle_rect does
+    let rendering_order = determine_rendering_order(&ui_state.dom.arena.node_layout, &display_rects);
+    let hit_regions = rendering_order.node_ids.iter()
+        .filter_map(|item| display_rects[item.node_id].tag.map(|tag_id| HitRegion {
+            node_id: item.node_id,
+            tag_id,
+            rect: laid_out_rectangles[item.node_id],
+            clips: Vec::new(),
+            transforms: Vec::new(),
+        }))
+        .collect::<Vec<_>>();
+
+    let hit_nodes = |x, y| hit_test_regions(&hit_regions, &FastHashMap::default(), LogicalPosition::new(x, y))
+        .into_iter().map(|item| (item.node_id, item.depth)).collect::<Vec<_>>();
+
+    // Earlier absolute siblings are drawn on top of later ones,
+    // the untagged node is invisible to the hit test
+    assert_eq!(hit_nodes(40.0, 40.0), vec![
+        (NodeId::new(1), 0),
+        (NodeId::new(2), 1),
+        (NodeId::new(0), 2),
+    ]);
+    assert_eq!(hit_nodes(70.0, 70.0), vec![
+        (NodeId::new(2), 0),
+        (NodeId::new(0), 1),
+    ]);
+    assert_eq!(hit_nodes(95.0, 95.0), vec![(NodeId::new(0), 0)]);
+}
+
+#[test]
+fn test_z_index_stacking_contexts() {
+
+    use dom::{Dom, Callback, On, UpdateScreen, DontRedraw};
+    use app_state::AppState;
+    use window::CallbackInfo;
+    use hit_test::hit_test_regions;
+    use glium::glutin::dpi::LogicalPosition;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    fn callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        DontRedraw
+    }
+
+    let tagged = || Dom::div().with_callback(On::MouseUp, Callback(callback));
+
+    // 0: div
+    //     1: div (z-index: 2)
+    //     2: div (z-index: 1)
+    //         3: div (z-index: 100, but nested in the context of 2)
+    //     4: div (z-index: 3)
+    //     5: div (z-index: -1, covers everything)
+    //     6: div (in the normal flow)
+    let ui_state = UiState::from_dom(tagged()
+        .with_child(tagged())
+        .with_child(tagged().with_child(tagged()))
+        .with_child(tagged())
+        .with_child(tagged())
+        .with_child(tagged()));
+
+    let rect = |x: f32, y: f32, w: f32, h: f32| LayoutRect::new(LayoutPoint::new(x, y), LayoutSize::new(w, h));
+    let laid_out_rectangles = NodeDataContainer::new(vec![
+        rect(0.0, 0.0, 100.0, 100.0),
+        rect(10.0, 10.0, 50.0, 50.0),
+        rect(20.0, 20.0, 50.0, 50.0),
+        rect(20.0, 20.0, 50.0, 50.0),
+        rect(30.0, 30.0, 50.0, 50.0),
+        rect(0.0, 0.0, 100.0, 100.0),
+        rect(90.0, 90.0, 10.0, 10.0),
+    ]);
+
+    let styled_node = StyledNode::default();
+    let mut display_rects = ui_state.dom.arena.node_data.transform(|_, node_id| {
+        DisplayRectangle::new(ui_state.node_ids_to_tag_ids.get(&node_id).cloned(), &styled_node)
+    });
+    for (node_id, z_index) in &[(1, 2), (2, 1), (3, 100), (4, 3), (5, -1)] {
+        display_rects[NodeId::new(*node_id)].style.z_index = Some(StyleZIndex::Integer(*z_index));
+    }
+
+    let rendering_order = determine_rendering_order(&ui_state.dom.arena.node_layout, &display_rects);
+    assert_eq!(
+        rendering_order.node_ids.iter().map(|item| item.node_id.index()).collect::<Vec<_>>(),
+        vec![0, 5, 6, 2, 3, 1, 4]
+    );
+
+    let hit_regions = rendering_order.node_ids.iter()
+        .filter_map(|item| display_rects[item.node_id].tag.map(|tag_id| HitRegion {
+            node_id: item.node_id,
+            tag_id,
+            rect: laid_out_rectangles[item.node_id],
+            clips: Vec::new(),
+            transforms: Vec::new(),
+        }))
+        .collect::<Vec<_>>();
+
+    let hit_nodes = |x, y| hit_test_regions(&hit_regions, &FastHashMap::default(), LogicalPosition::new(x, y))
+        .into_iter().map(|item| item.node_id.index()).collect::<Vec<_>>();
+
+    // The node with the highest z-index is hit first, the z-index of 3 only
+    // counts within the stacking context of its parent
+    assert_eq!(hit_nodes(40.0, 40.0), vec![4, 1, 3, 2, 5, 0]);
+    assert_eq!(hit_nodes(25.0, 25.0), vec![1, 3, 2, 5, 0]);
+    // Negative z-indices are drawn below the normal flow, but above the parent
+    assert_eq!(hit_nodes(95.0, 95.0), vec![6, 5, 0]);
+
+    // `z-index: auto` doesn't establish a stacking context
+    display_rects[NodeId::new(4)].style.z_index = Some(StyleZIndex::Auto);
+    let rendering_order = determine_rendering_order(&ui_state.dom.arena.node_layout, &display_rects);
+    assert_eq!(
+        rendering_order.node_ids.iter().map(|item| item.node_id.index()).collect::<Vec<_>>(),
+        vec![0, 5, 4, 6, 2, 3, 1]
+    );
+}
+
+#[test]
+fn test_clip_stack() {
+
+    use dom::Dom;
+    use azul_css::{
+        TextOverflowBehaviour, TextOverflowBehaviourInner, PixelSize,
+        StyleTransform, StyleTransformFunction,
+    };
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    // 0: div (border-radius)
+    //     1: div (overflow: hidden, transform)
+    //         2: div (transform)
+    //     3: div
+    //         4: div
+    let ui_state = UiState::from_dom(Dom::<TestLayout>::div()
+        .with_child(Dom::div().with_child(Dom::div()))
+        .with_child(Dom::div().with_child(Dom::div())));
+
+    let rect = |x: f32, y: f32, w: f32, h: f32| LayoutRect::new(LayoutPoint::new(x, y), LayoutSize::new(w, h));
+    let laid_out_rectangles = NodeDataContainer::new(vec![
+        rect(0.0, 0.0, 100.0, 100.0),
+        rect(0.0, 0.0, 100.0, 50.0),
+        rect(0.0, 0.0, 10.0, 10.0),
+        rect(0.0, 50.0, 100.0, 50.0),
+        rect(0.0, 50.0, 10.0, 10.0),
+    ]);
+
+    let styled_node = StyledNode::default();
+    let mut display_rects = ui_state.dom.arena.node_data.transform(|_, _| DisplayRectangle::new(None, &styled_node));
+    display_rects[NodeId::new(0)].style.border_radius = Some(StyleBorderRadius(azul_css::BorderRadius::uniform(PixelSize::new(PixelValue::px(5.0), PixelValue::px(5.0)))));
+    display_rects[NodeId::new(1)].style.overflow = Some(LayoutOverflow {
+        horizontal: TextOverflowBehaviour::Modified(TextOverflowBehaviourInner::Hidden),
+        vertical: TextOverflowBehaviour::Modified(TextOverflowBehaviourInner::Hidden),
+    });
+    let translate = |y: f32| Some(StyleTransform(vec![StyleTransformFunction::Translate(PixelValue::px(0.0), PixelValue::px(y))]));
+    display_rects[NodeId::new(1)].style.transform = translate(10.0);
+    display_rects[NodeId::new(2)].style.transform = translate(5.0);
+
+    let clip_stack = |node_id| get_clip_stack(NodeId::new(node_id), &ui_state.dom.arena.node_layout, &display_rects, &laid_out_rectangles, &BTreeMap::new())
+        .into_iter()
+        .map(|item| match item {
+            ClipStackItem::Clip { node_id, .. } => ("clip", node_id.index()),
+            ClipStackItem::ReferenceFrame { node_id, .. } => ("transform", node_id.index()),
+            ClipStackItem::ScrollFrame { node_id, .. } => ("scroll", node_id.index()),
+        })
+        .collect::<Vec<_>>();
+
+    // A node is never clipped by itself, only by its parents, but it is transformed
+    // by itself - the transform of a parent applies to the clip of the parent
+    assert_eq!(clip_stack(0), vec![]);
+    assert_eq!(clip_stack(1), vec![("clip", 0), ("transform", 1)]);
+    assert_eq!(clip_stack(2), vec![("clip", 0), ("transform", 1), ("clip", 1), ("transform", 2)]);
+    assert_eq!(clip_stack(3), vec![("clip", 0)]);
+    assert_eq!(clip_stack(4), vec![("clip", 0)]);
+
+    // Nested transforms compose for the hit-testing, the clip of node 1 is only moved by node 1
+    let (hit_clips, hit_transforms) = get_hit_clips(&get_clip_stack(NodeId::new(2), &ui_state.dom.arena.node_layout, &display_rects, &laid_out_rectangles, &BTreeMap::new()));
+    assert_eq!(hit_clips.len(), 2);
+    let translation = |transforms: &[HitTransform]| match transforms {
+        [HitTransform::Transform(transform)] => transform.transform_point(&LayoutPoint::new(0.0, 0.0)),
+        other => panic!("expected a single transform, got {:?}", other),
+    };
+    assert_eq!(hit_clips[0].transforms, vec![]);
+    assert_eq!(translation(&hit_clips[1].transforms), LayoutPoint::new(0.0, 10.0));
+    assert_eq!(translation(&hit_transforms), LayoutPoint::new(0.0, 15.0));
+
+    // Fixed nodes escape the clips of their parents, except for the ones
+    // of (and above) the transformed parent that is their containing block
+    display_rects[NodeId::new(2)].layout.position = Some(LayoutPosition::Fixed);
+    display_rects[NodeId::new(4)].layout.position = Some(LayoutPosition::Fixed);
+    let clip_stack = |node_id| get_clip_stack(NodeId::new(node_id), &ui_state.dom.arena.node_layout, &display_rects, &laid_out_rectangles, &BTreeMap::new()).len();
+    assert_eq!(clip_stack(2), 4);
+    assert_eq!(clip_stack(4), 0);
+}
+
+#[test]
+fn test_hit_test_clipped_and_scrolled_children() {
+
+    use dom::{Dom, Callback, On, UpdateScreen, DontRedraw};
+    use app_state::AppState;
+    use window::CallbackInfo;
+    use hit_test::hit_test_regions;
+    use glium::glutin::dpi::LogicalPosition;
+    use azul_css::{TextOverflowBehaviour, TextOverflowBehaviourInner};
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    fn callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        DontRedraw
+    }
+
+    let tagged = || Dom::div().with_callback(On::MouseUp, Callback(callback));
+
+    // 0: div
+    //     1: div (overflow: hidden)
+    //         2: button (overflows its parent on the right)
+    //     3: div (overflow: scroll)
+    //         4: div
+    //         5: div (overflows its parent at the bottom)
+    let ui_state = UiState::from_dom(Dom::div()
+        .with_child(Dom::div().with_child(tagged()))
+        .with_child(Dom::div().with_child(tagged()).with_child(tagged())));
+
+    let rect = |x: f32, y: f32, w: f32, h: f32| LayoutRect::new(LayoutPoint::new(x, y), LayoutSize::new(w, h));
+    let laid_out_rectangles = NodeDataContainer::new(vec![
+        rect(0.0, 0.0, 200.0, 200.0),
+        rect(0.0, 0.0, 100.0, 100.0),
+        rect(50.0, 0.0, 100.0, 20.0),
+        rect(0.0, 100.0, 100.0, 100.0),
+        rect(0.0, 100.0, 100.0, 60.0),
+        rect(0.0, 160.0, 100.0, 60.0),
+    ]);
+
+    let styled_node = StyledNode::default();
+    let mut display_rects = ui_state.dom.arena.node_data.transform(|_, node_id| {
+        DisplayRectangle::new(ui_state.node_ids_to_tag_ids.get(&node_id).cloned(), &styled_node)
+    });
+    let overflow = |behaviour| Some(LayoutOverflow {
+        horizontal: TextOverflowBehaviour::Modified(behaviour),
+        vertical: TextOverflowBehaviour::Modified(behaviour),
+    });
+    display_rects[NodeId::new(1)].style.overflow = overflow(TextOverflowBehaviourInner::Hidden);
+    display_rects[NodeId::new(3)].style.overflow = overflow(TextOverflowBehaviourInner::Scroll);
+
+    let scrolled_nodes = get_nodes_that_need_scroll_clip(
+        &ui_state.dom.arena.node_layout, &display_rects, &ui_state.dom.arena.node_data, &laid_out_rectangles,
+        &[(0, NodeId::new(0)), (1, NodeId::new(1)), (1, NodeId::new(3))], PipelineId(0, 0), &mut TagIdGenerator::new(),
+    );
+    assert_eq!(scrolled_nodes.overflowing_nodes.keys().cloned().collect::<Vec<_>>(), vec![NodeId::new(1), NodeId::new(3)]);
+    let scroll_id = scrolled_nodes.overflowing_nodes[&NodeId::new(3)].parent_external_scroll_id;
+
+    // Record the tagged rectangles with their clips, the same way as push_rectangles_into_displaylist_inner does
+    let rendering_order = determine_rendering_order(&ui_state.dom.arena.node_layout, &display_rects);
+    let hit_regions = rendering_order.node_ids.iter()
+        .filter_map(|item| display_rects[item.node_id].tag.map(|tag_id| {
+            let clip_stack = get_clip_stack(item.node_id, &ui_state.dom.arena.node_layout, &display_rects, &laid_out_rectangles, &scrolled_nodes.overflowing_nodes);
+            let (clips, transforms) = get_hit_clips(&clip_stack);
+            HitRegion { node_id: item.node_id, tag_id, rect: laid_out_rectangles[item.node_id], clips, transforms }
+        }))
+        .collect::<Vec<_>>();
+
+    let hit_nodes = |scroll_offsets: &FastHashMap<ExternalScrollId, LayoutPoint>, x, y| {
+        hit_test_regions(&hit_regions, scroll_offsets, LogicalPosition::new(x, y))
+            .into_iter().map(|item| item.node_id.index()).collect::<Vec<_>>()
+    };
+    let not_scrolled = FastHashMap::default();
+
+    // Only the visible half of the button can be clicked
+    assert_eq!(hit_nodes(&not_scrolled, 75.0, 10.0), vec![2]);
+    assert_eq!(hit_nodes(&not_scrolled, 125.0, 10.0), Vec::<usize>::new());
+
+    // Content that is scrolled out of view can't be clicked...
+    assert_eq!(hit_nodes(&not_scrolled, 50.0, 150.0), vec![4]);
+    assert_eq!(hit_nodes(&not_scrolled, 50.0, 190.0), vec![5]);
+    assert_eq!(hit_nodes(&not_scrolled, 50.0, 210.0), Vec::<usize>::new());
+
+    // ... until it is scrolled into view
+    let mut scrolled = FastHashMap::default();
+    scrolled.insert(scroll_id, LayoutPoint::new(0.0, 20.0));
+    assert_eq!(hit_nodes(&scrolled, 50.0, 135.0), vec![4]);
+    assert_eq!(hit_nodes(&scrolled, 50.0, 145.0), vec![5]);
+    assert_eq!(hit_nodes(&scrolled, 50.0, 195.0), vec![5]);
+    assert_eq!(hit_nodes(&scrolled, 50.0, 210.0), Vec::<usize>::new());
+}
+
+#[test]
+fn test_layout_result() {
+
+    use dom::Dom;
+    use azul_css::{TextOverflowBehaviour, TextOverflowBehaviourInner, StyleBorderSide, BorderStyle};
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    // 0: div
+    //     1: div (overflow: hidden, margin 5px, border 2px, padding 3px)
+    //         2: div (entirely outside of its parent)
+    //     3: div (overflow: scroll)
+    //         4: div
+    //         5: div (overflows its parent at the bottom)
+    let ui_state: UiState<TestLayout> = UiState::from_dom(Dom::div()
+        .with_child(Dom::div().with_child(Dom::div()))
+        .with_child(Dom::div().with_child(Dom::div()).with_child(Dom::div())));
+
+    let rect = |x: f32, y: f32, w: f32, h: f32| LayoutRect::new(LayoutPoint::new(x, y), LayoutSize::new(w, h));
+    let laid_out_rectangles = NodeDataContainer::new(vec![
+        rect(0.0, 0.0, 200.0, 200.0),
+        rect(0.0, 0.0, 100.0, 100.0),
+        rect(120.0, 0.0, 50.0, 20.0),
+        rect(0.0, 100.0, 100.0, 100.0),
+        rect(0.0, 100.0, 100.0, 60.0),
+        rect(0.0, 160.0, 100.0, 60.0),
+    ]);
+
+    let styled_node = StyledNode::default();
+    let mut display_rects = ui_state.dom.arena.node_data.transform(|_, _| DisplayRectangle::new(None, &styled_node));
+    let overflow = |behaviour| Some(LayoutOverflow {
+        horizontal: TextOverflowBehaviour::Modified(behaviour),
+        vertical: TextOverflowBehaviour::Modified(behaviour),
+    });
+    let px = |value| Some(PixelValue::px(value));
+    let border = Some(StyleBorderSide { border_width: PixelValue::px(2.0), border_style: BorderStyle::Solid, border_color: StyleColorU { r: 0, g: 0, b: 0, a: 255 } });
+
+    display_rects[NodeId::new(1)].style.overflow = overflow(TextOverflowBehaviourInner::Hidden);
+    display_rects[NodeId::new(1)].style.border = Some(StyleBorder { top: border, right: border, bottom: border, left: border });
+    display_rects[NodeId::new(1)].layout.margin = Some(LayoutMargin { top: px(5.0), right: px(5.0), bottom: px(5.0), left: px(5.0) });
+    display_rects[NodeId::new(1)].layout.padding = Some(LayoutPadding { top: px(3.0), right: px(3.0), bottom: px(3.0), left: px(3.0) });
+    display_rects[NodeId::new(3)].style.overflow = overflow(TextOverflowBehaviourInner::Scroll);
+
+    let node_hierarchy = &ui_state.dom.arena.node_layout;
+    let scrolled_nodes = get_nodes_that_need_scroll_clip(
+        node_hierarchy, &display_rects, &ui_state.dom.arena.node_data, &laid_out_rectangles,
+        &[(0, NodeId::new(0)), (1, NodeId::new(1)), (1, NodeId::new(3))], PipelineId(0, 0), &mut TagIdGenerator::new(),
+    );
+    let rendering_order = determine_rendering_order(node_hierarchy, &display_rects);
+    let layout = build_layout_result(node_hierarchy, &display_rects, &laid_out_rectangles, &scrolled_nodes, &rendering_order);
+
+    assert_eq!(layout.len(), 6);
+
+    let boxed = layout.get(NodeId::new(1)).unwrap();
+    assert_eq!(boxed.margin_box, rect(-5.0, -5.0, 110.0, 110.0));
+    assert_eq!(boxed.border_box, rect(0.0, 0.0, 100.0, 100.0));
+    assert_eq!(boxed.padding_box, rect(2.0, 2.0, 96.0, 96.0));
+    assert_eq!(boxed.content_rect, rect(5.0, 5.0, 90.0, 90.0));
+    // overflow: hidden doesn't make a node scrollable
+    assert_eq!(boxed.scroll_content_size, None);
+
+    // Nodes without margin, border and padding only have one box
+    let plain = layout.get(NodeId::new(4)).unwrap();
+    assert_eq!(plain.margin_box, plain.content_rect);
+
+    // The scroll container can be scrolled down to the end of its last child
+    assert_eq!(layout.get(NodeId::new(3)).unwrap().scroll_content_size, Some(LayoutSize::new(100.0, 120.0)));
+
+    // Only nodes outside of an overflow: hidden parent are clipped, not scrolled-away ones
+    let clipped = layout.iter().filter(|(_, node)| node.is_clipped).map(|(node_id, _)| node_id.index()).collect::<Vec<_>>();
+    assert_eq!(clipped, vec![2]);
+
+    // Siblings are drawn in document order, children on top of their parents
+    let z_order = |node_id| layout.get(NodeId::new(node_id)).unwrap().z_order;
+    assert!(z_order(0) < z_order(1));
+    assert!(z_order(1) < z_order(2));
+    assert!(z_order(4) < z_order(5));
+    assert_eq!(layout.nodes_at(50.0, 150.0), vec![NodeId::new(0), NodeId::new(3), NodeId::new(4)]);
 }
 
 fn push_rectangles_into_displaylist<'a, 'b, 'c, 'd, 'e, 'f, T: Layout>(
     solved_rects: &NodeDataContainer<LayoutRect>,
     epoch: Epoch,
-    content_grouped_rectangles: ContentGroupOrder,
+    rendering_order: RenderingOrder,
     scrollable_nodes: &mut ScrolledNodes,
     scroll_states: &mut ScrollStates,
     referenced_content: &DisplayListParametersRef<'a,'b,'c,'d,'e, T>,
     referenced_mutable_content: &mut DisplayListParametersMut<'f, T>)
 {
-/* -- disabled scrolling temporarily due to z-indexing problems
-    // A stack containing all the nodes which have a scroll clip pushed to the builder.
-    let mut stack: Vec<NodeId> = vec![];
-*/
+    // Create a scroll state for every scroll frame that doesn't have one yet, the
+    // state is keyed by the DOM hash of the node, so it survives re-layouts
+    for (node_id, scroll_node) in &scrollable_nodes.overflowing_nodes {
+        if node_is_scroll_frame(&referenced_content.display_rectangle_arena[*node_id].style) {
+            let OverflowingScrollNode { parent_external_scroll_id, parent_rect, child_rect, .. } = scroll_node;
+            scroll_states.ensure_initialized_scroll_state(
+                *parent_external_scroll_id,
+                child_rect.max_x() - parent_rect.max_x(),
+                child_rect.max_y() - parent_rect.max_y(),
+            );
+            // Marks the state as used, so that it isn't garbage-collected
+            let _ = scroll_states.get_scroll_amount(parent_external_scroll_id);
+        }
+    }
 
     let mut clip_stack = Vec::new();
 
-    for content_group in content_grouped_rectangles.groups {
-        // Push the root of the node
-
-        push_rectangles_into_displaylist_inner(content_group.root,
+    for item in rendering_order.node_ids {
+        push_rectangles_into_displaylist_inner(
+            item,
             solved_rects,
             epoch,
             scrollable_nodes,
@@ -739,90 +1462,11 @@ fn push_rectangles_into_displaylist<'a, 'b, 'c, 'd, 'e, 'f, T: Layout>(
             referenced_mutable_content,
             &mut clip_stack
         );
-
-        for item in content_group.node_ids {
-            push_rectangles_into_displaylist_inner(
-                item,
-                solved_rects,
-                epoch,
-                scrollable_nodes,
-                referenced_content,
-                referenced_mutable_content,
-                &mut clip_stack
-            );
-        }
     }
-/*
-    for (z_index, rects) in z_ordered_rectangles.0.into_iter() {
-        for rect_idx in rects {
-            let html_node = &arena[rect_idx];
-            let rectangle = DisplayListRectParams {
-                epoch,
-                rect_idx,
-                html_node: &html_node.node_type,
-            };
-
-            let styled_node = &referenced_content.display_rectangle_arena[rect_idx];
-            let solved_rect = solved_rects[rect_idx];
-
-            displaylist_handle_rect(solved_rect, scrollable_nodes, rectangle, referenced_content, referenced_mutable_content);
-/*
-            // If the current node is a parent that has overflow:hidden set, push
-            // the clip ID and the last child into the stack
-            if html_node.last_child.is_some() {
-                if node_needs_to_clip_children(&styled_node.style) {
-
-                }
-            }
-
-            // If the current node is the last child of the parent and the parent has
-            // overflow:hidden set, pop the last clip id
-            if clip_stack.last().cloned() == Some(rect_idx) {
-                referenced_mutable_content.builder.pop_clip_id();
-                clip_stack.pop();
-            }
-*/
-/* -- disabled scrolling temporarily due to z-indexing problems
-            if let Some(OverflowingScrollNode { parent_external_scroll_id, parent_rect, child_rect, .. }) = scrollable_nodes.overflowing_nodes.get(&rect_idx) {
-
-                // The unwraps on the following line must succeed, as if we have no children, we can't have a scrollable content.
-                stack.push(rect_idx.children(&arena).last().unwrap());
-
-                // Create a new scroll state for each node that is not present in the scroll states already.
-                // The arena containing the actual dom maps 1:1 to the arena containing the rectangles, so we
-                // can use the NodeIds from the layouted rectangles to access the NodeData corresponding
-                // to each Rectangle in the NodeData arena.
-                //
-                // This next unwrap is fine since we are sure the looked up NodeId exists in the arena!
-                scroll_states.ensure_initialized_scroll_state(
-                    *parent_external_scroll_id,
-                    child_rect.size.width - parent_rect.size.width,
-                    child_rect.size.height - parent_rect.size.height
-                );
-
-                // Set the scrolling clip
-                let clip_id = referenced_mutable_content.builder.define_scroll_frame(
-                    Some(*parent_external_scroll_id),
-                    *child_rect,
-                    *parent_rect,
-                    vec![],
-                    None,
-                    ScrollSensitivity::ScriptAndInputEvents,
-                );
 
-                referenced_mutable_content.builder.push_clip_id(clip_id);
-            }
-
-            if let Some(&child_idx) = stack.last() {
-                if child_idx == rect_idx {
-                    stack.pop();
-                    referenced_mutable_content.builder.pop_clip_id();
-                }
-            }
-*/
-        }
+    for pushed in clip_stack.drain(..).rev() {
+        pushed.pop(referenced_mutable_content.builder);
     }
-*/
 }
 
 fn push_rectangles_into_displaylist_inner<'a,'b,'c,'d,'e,'f, T: Layout>(
@@ -832,7 +1476,7 @@ fn push_rectangles_into_displaylist_inner<'a,'b,'c,'d,'e,'f, T: Layout>(
     scrollable_nodes: &mut ScrolledNodes,
     referenced_content: &DisplayListParametersRef<'a,'b,'c,'d,'e, T>,
     referenced_mutable_content: &mut DisplayListParametersMut<'f, T>,
-    clip_stack: &mut Vec<NodeId>)
+    clip_stack: &mut Vec<ClipStackItem>)
 {
     let html_node = &referenced_content.node_data[item.node_id];
     let solved_rect = solved_rects_data[item.node_id];
@@ -842,6 +1486,27 @@ fn push_rectangles_into_displaylist_inner<'a,'b,'c,'d,'e,'f, T: Layout>(
         html_node: &html_node.node_type,
     };
 
+    // The nodes are not necessarily drawn in DOM order (absolute nodes are drawn last),
+    // so instead of pushing / popping clips in the order of the nodes, make the clip stack
+    // match the clips and transforms of the current node: pop the items that don't belong
+    // to this node, then push the missing ones
+    let node_clip_stack = get_clip_stack(item.node_id, referenced_content.node_hierarchy, referenced_content.display_rectangle_arena, solved_rects_data, &scrollable_nodes.overflowing_nodes);
+    let items_to_keep = clip_stack.iter()
+        .zip(node_clip_stack.iter())
+        .take_while(|(pushed, needed)| pushed == needed)
+        .count();
+
+    for pushed in clip_stack.drain(items_to_keep..).rev() {
+        pushed.pop(referenced_mutable_content.builder);
+    }
+
+    for needed in &node_clip_stack[items_to_keep..] {
+        needed.push(referenced_mutable_content.builder);
+        clip_stack.push(*needed);
+    }
+
+    let hit_regions_len = referenced_mutable_content.fake_window.state.hit_regions.len();
+
     displaylist_handle_rect(
         solved_rect,
         scrollable_nodes,
@@ -850,24 +1515,173 @@ fn push_rectangles_into_displaylist_inner<'a,'b,'c,'d,'e,'f, T: Layout>(
         referenced_mutable_content
     );
 
-    if item.clip_children {
-        if let Some(last_child) = referenced_content.node_hierarchy[item.node_id].last_child {
-            let styled_node = &referenced_content.display_rectangle_arena[item.node_id];
-            let solved_rect = solved_rects_data[item.node_id];
-            let clip = get_clip_region(solved_rect, &styled_node)
-                .unwrap_or(ComplexClipRegion::new(solved_rect, BorderRadius::zero(), ClipMode::Clip));
-            let clip_id = referenced_mutable_content.builder.define_clip(solved_rect, vec![clip], /* image_mask: */ None);
-            referenced_mutable_content.builder.push_clip_id(clip_id);
-            clip_stack.push(last_child);
+    // The clips, transforms and scroll frames of the parents also apply to the hit-testing of this node
+    let (hit_clips, hit_transforms) = get_hit_clips(clip_stack);
+    for hit_region in &mut referenced_mutable_content.fake_window.state.hit_regions[hit_regions_len..] {
+        hit_region.clips = hit_clips.clone();
+        hit_region.transforms = hit_transforms.clone();
+    }
+}
+
+/// Clip or reference frame that is currently pushed to the display list builder
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ClipStackItem {
+    /// The node clips its children to the (rounded) `region`
+    Clip { node_id: NodeId, region: ComplexClipRegion },
+    /// The node and its children are drawn with the `transform` (in window coordinates)
+    ReferenceFrame { node_id: NodeId, transform: LayoutTransform2D },
+    /// The node clips its children to the (rounded) `region` and scrolls them, the
+    /// `content_rect` is the union of the node and its (unscrolled) children
+    ScrollFrame { node_id: NodeId, scroll_id: ExternalScrollId, region: ComplexClipRegion, content_rect: LayoutRect },
+}
+
+impl ClipStackItem {
+
+    fn push(&self, builder: &mut DisplayListBuilder) {
+        use webrender::api::PropertyBinding;
+        match self {
+            ClipStackItem::Clip { region, .. } => {
+                let clip_id = builder.define_clip(region.rect, vec![*region], /* image_mask: */ None);
+                builder.push_clip_id(clip_id);
+            },
+            ClipStackItem::ReferenceFrame { transform, .. } => {
+                // The reference frame is located at the origin of the window, since
+                // the transform is already in window coordinates
+                let info = LayoutPrimitiveInfo::new(LayoutRect::zero());
+                let frame_id = builder.push_reference_frame(&info, Some(PropertyBinding::Value(wr_translate_transform(transform))), None);
+                builder.push_clip_id(frame_id);
+            },
+            ClipStackItem::ScrollFrame { scroll_id, region, content_rect, .. } => {
+                let clip_id = builder.define_scroll_frame(
+                    Some(*scroll_id),
+                    *content_rect,
+                    region.rect,
+                    vec![*region],
+                    /* image_mask: */ None,
+                    ScrollSensitivity::ScriptAndInputEvents,
+                );
+                builder.push_clip_id(clip_id);
+            },
         }
     }
 
-    if clip_stack.last().cloned() == Some(item.node_id) {
-        referenced_mutable_content.builder.pop_clip_id();
-        clip_stack.pop();
+    fn pop(&self, builder: &mut DisplayListBuilder) {
+        match self {
+            ClipStackItem::Clip { .. } | ClipStackItem::ScrollFrame { .. } => {
+                builder.pop_clip_id();
+            },
+            ClipStackItem::ReferenceFrame { .. } => {
+                builder.pop_clip_id();
+                builder.pop_reference_frame();
+            },
+        }
     }
 }
 
+/// Returns the clips and transforms that apply to a node, outermost first:
+/// the transforms of the node and its parents and the clips and scroll frames of the
+/// parents (a node never clips or scrolls itself, only its children).
+///
+/// `position:fixed` nodes escape the clips of their parents up to their containing
+/// block (the nearest transformed parent), since they are positioned relative to it.
+fn get_clip_stack<'a>(
+    node_id: NodeId,
+    node_hierarchy: &NodeHierarchy,
+    rectangles: &NodeDataContainer<DisplayRectangle<'a>>,
+    solved_rects: &NodeDataContainer<LayoutRect>,
+    overflowing_nodes: &BTreeMap<NodeId, OverflowingScrollNode>)
+-> Vec<ClipStackItem>
+{
+    let mut clip_stack = Vec::new();
+
+    if let Some(transform) = get_transform(&rectangles[node_id], &solved_rects[node_id]) {
+        clip_stack.push(ClipStackItem::ReferenceFrame { node_id, transform });
+    }
+
+    let mut current_node = node_hierarchy[node_id].parent;
+    let mut escapes_parent_clips = is_fixed(&rectangles[node_id]);
+
+    while let Some(parent) = current_node {
+        let parent_rect = solved_rects[parent];
+        let parent_transform = get_transform(&rectangles[parent], &parent_rect);
+        if parent_transform.is_some() {
+            escapes_parent_clips = false;
+        }
+        // Pushed in reverse order: the clip of the parent is transformed by the parent
+        if node_needs_to_clip_children(&rectangles[parent].style) && !escapes_parent_clips {
+            let region = get_clip_region(parent_rect, &rectangles[parent])
+                .unwrap_or(ComplexClipRegion::new(parent_rect, BorderRadius::zero(), ClipMode::Clip));
+            match overflowing_nodes.get(&parent) {
+                Some(scroll_node) if node_is_scroll_frame(&rectangles[parent].style) => {
+                    clip_stack.push(ClipStackItem::ScrollFrame {
+                        node_id: parent,
+                        scroll_id: scroll_node.parent_external_scroll_id,
+                        region,
+                        content_rect: parent_rect.union(&scroll_node.child_rect),
+                    });
+                },
+                _ => clip_stack.push(ClipStackItem::Clip { node_id: parent, region }),
+            }
+        }
+        if let Some(transform) = parent_transform {
+            clip_stack.push(ClipStackItem::ReferenceFrame { node_id: parent, transform });
+        }
+        if is_fixed(&rectangles[parent]) {
+            escapes_parent_clips = true;
+        }
+        current_node = node_hierarchy[parent].parent;
+    }
+
+    clip_stack.reverse();
+    clip_stack
+}
+
+/// Returns whether the node is `position:fixed`
+fn is_fixed<'a>(rect: &DisplayRectangle<'a>) -> bool {
+    rect.layout.position == Some(LayoutPosition::Fixed)
+}
+
+/// Returns the transform of the node, `None` if the node has no (or an empty) `transform`
+fn get_transform<'a>(rect: &DisplayRectangle<'a>, bounds: &LayoutRect) -> Option<LayoutTransform2D> {
+    let transform = rect.style.transform.as_ref()?;
+    if transform.0.is_empty() {
+        return None;
+    }
+    Some(get_node_transform(transform, &rect.style.transform_origin.unwrap_or_default(), bounds))
+}
+
+/// Converts the clip stack into the clips and the transforms for the hit-testing
+/// (directly nested transforms are combined into one)
+fn get_hit_clips(clip_stack: &[ClipStackItem]) -> (Vec<HitClip>, Vec<HitTransform>) {
+
+    let mut hit_clips = Vec::new();
+    let mut current_transforms = Vec::<HitTransform>::new();
+
+    for item in clip_stack {
+        match item {
+            ClipStackItem::Clip { region, .. } => {
+                hit_clips.push(HitClip { region: *region, transforms: current_transforms.clone() });
+            },
+            ClipStackItem::ReferenceFrame { transform, .. } => {
+                // The transform of the child is applied first, then the one of the parent
+                match current_transforms.last_mut() {
+                    Some(HitTransform::Transform(parent_transform)) => {
+                        *parent_transform = transform.post_mul(parent_transform);
+                    },
+                    _ => current_transforms.push(HitTransform::Transform(*transform)),
+                }
+            },
+            ClipStackItem::ScrollFrame { scroll_id, region, .. } => {
+                // The scroll frame itself doesn't scroll, only its content
+                hit_clips.push(HitClip { region: *region, transforms: current_transforms.clone() });
+                current_transforms.push(HitTransform::Scroll(*scroll_id));
+            },
+        }
+    }
+
+    (hit_clips, current_transforms)
+}
+
 /// Parameters that apply to a single rectangle / div node
 #[derive(Copy, Clone)]
 pub(crate) struct DisplayListRectParams<'a, T: 'a + Layout> {
@@ -900,7 +1714,7 @@ fn displaylist_handle_rect<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
     use webrender::api::BorderRadius;
 
     let DisplayListParametersRef {
-        render_api, css,
+        render_api, css, css_index: _,
         display_rectangle_arena, word_cache, pipeline_id,
         node_hierarchy, node_data,
     } = referenced_content;
@@ -922,6 +1736,11 @@ fn displaylist_handle_rect<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
         }),
     };
 
+    // Record the tagged rectangle for WindowState::hit_test(), in drawing order
+    if let Some((tag_id, _)) = info.tag {
+        referenced_mutable_content.fake_window.state.hit_regions.push(HitRegion { node_id: rect_idx, tag_id, rect: bounds, clips: Vec::new(), transforms: Vec::new() });
+    }
+
     let clip_region_id = get_clip_region(bounds, &rect).map(|clip|
         referenced_mutable_content.builder.define_clip(bounds, vec![clip], None)
     );
@@ -968,16 +1787,28 @@ fn displaylist_handle_rect<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
             &rect.style.background_size,
             &rect.style.background_repeat,
             &referenced_mutable_content.app_resources,
+            render_api,
         );
     }
 
     if let Some(ref border) = rect.style.border {
-        push_border(
-            &info,
-            referenced_mutable_content.builder,
-            &border,
-            &rect.style.border_radius,
-        );
+        match rect.style.border_image {
+            Some(ref border_image) => push_border_image(
+                &info,
+                referenced_mutable_content.builder,
+                &border,
+                border_image,
+                &rect.style.border_radius,
+                &referenced_mutable_content.app_resources,
+                render_api,
+            ),
+            None => push_border(
+                &info,
+                referenced_mutable_content.builder,
+                &border,
+                &rect.style.border_radius,
+            ),
+        }
     }
 
     let (horz_alignment, vert_alignment) = determine_text_alignment(rect);
@@ -990,6 +1821,8 @@ fn displaylist_handle_rect<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
         bar_color: StyleBackgroundColor(StyleColorU { r: 193, g: 193, b: 193, a: 255 }),
     };
 
+    let collect_baselines = referenced_mutable_content.debug_overlay.text_baselines;
+
     // The only thing changed between TextId and String is
     //`TextInfo::Cached` vs `TextInfo::Uncached` - reduce code duplication
     let push_text_wrapper = |
@@ -1033,7 +1866,8 @@ fn displaylist_handle_rect<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
             horz_alignment,
             vert_alignment,
             &scrollbar_style,
-            &words.0);
+            &words.0,
+            collect_baselines);
 
         if text_clip_region_id.is_some() {
             builder.pop_clip_id();
@@ -1059,12 +1893,17 @@ fn displaylist_handle_rect<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
             &info,
             referenced_mutable_content.builder,
             referenced_mutable_content.app_resources,
+            render_api,
             image_id,
             info.rect.size),
         GlTexture(callback) => push_opengl_texture(callback, &info, rectangle, referenced_content, referenced_mutable_content),
         IFrame(callback) => push_iframe(callback, &info, scrollable_nodes, rectangle, referenced_content, referenced_mutable_content),
     };
 
+    if let Some(overflow) = &overflow_result {
+        referenced_mutable_content.text_baselines.extend(overflow.text_baselines.iter().cloned());
+    }
+
     // Push the inset shadow (if any)
     push_box_shadow(
         referenced_mutable_content.builder,
@@ -1090,6 +1929,16 @@ fn displaylist_handle_rect<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
     if clip_region_id.is_some() {
         referenced_mutable_content.builder.pop_clip_id();
     }
+
+    // The outline is drawn around the node, so it must not be clipped by the border-radius
+    if let Some(outline) = &rect.style.outline {
+        push_outline(
+            referenced_mutable_content.builder,
+            outline,
+            &bounds,
+            &rect.style.border_radius,
+        );
+    }
 }
 
 fn push_opengl_texture<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
@@ -1103,11 +1952,18 @@ fn push_opengl_texture<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
     use compositor::{ActiveTexture, ACTIVE_GL_TEXTURES};
     use gleam::gl;
 
+    if referenced_mutable_content.fake_window.render_callback_placeholders {
+        push_rect(info, referenced_mutable_content.builder, &CALLBACK_PLACEHOLDER_COLOR);
+        return None;
+    }
+
     let bounds = HidpiAdjustedBounds::from_bounds(&referenced_mutable_content.fake_window, info.rect);
 
     let texture;
 
     {
+        let gl_callback_start = FrameProfiler::start();
+
         // Make sure that the app data is locked before invoking the callback
         let _lock = referenced_mutable_content.app_data.lock().unwrap();
         texture = (texture_callback.0)(&texture_stack_ptr, LayoutInfo {
@@ -1120,6 +1976,8 @@ fn push_opengl_texture<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
 
         gl_context.bind_framebuffer(gl::FRAMEBUFFER, 0);
         gl_context.disable(gl::FRAMEBUFFER_SRGB);
+
+        referenced_mutable_content.fake_window.profiler.record(FramePhase::GlCallbacks, gl_callback_start);
     }
 
     let texture = texture?;
@@ -1165,7 +2023,15 @@ fn push_iframe<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
     referenced_mutable_content: &mut DisplayListParametersMut<'g, T>,
 ) -> Option<OverflowInfo>
 {
-    use glium::glutin::dpi::{LogicalPosition, LogicalSize};
+    if referenced_mutable_content.fake_window.render_callback_placeholders {
+        push_rect(info, referenced_mutable_content.builder, &CALLBACK_PLACEHOLDER_COLOR);
+        return None;
+    }
+
+    if referenced_mutable_content.iframe_depth >= MAX_IFRAME_DEPTH {
+        report(AzulError::IFrameRecursionLimit { depth: referenced_mutable_content.iframe_depth });
+        return None;
+    }
 
     let bounds = HidpiAdjustedBounds::from_bounds(&referenced_mutable_content.fake_window, info.rect);
 
@@ -1182,20 +2048,50 @@ fn push_iframe<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
         new_dom = (iframe_callback.0)(&iframe_pointer, window_info, bounds);
     }
 
+    referenced_mutable_content.iframe_depth += 1;
+    push_dom_into_rect(new_dom, info.rect, rectangle.epoch, parent_scrollable_nodes, referenced_content, referenced_mutable_content);
+    referenced_mutable_content.iframe_depth -= 1;
+
+    None
+}
+
+/// Styles the `new_dom` with the `referenced_content.css`, lays it out inside of the
+/// `rect` and pushes it into the display list - used for iframes and the inspector panel
+fn push_dom_into_rect<'b,'c,'d,'e,'f,'g, T: Layout>(
+    new_dom: Dom<T>,
+    rect: LayoutRect,
+    epoch: Epoch,
+    parent_scrollable_nodes: &mut ScrolledNodes,
+    referenced_content: &DisplayListParametersRef<'b,'c,'d,'e,'f, T>,
+    referenced_mutable_content: &mut DisplayListParametersMut<'g, T>,
+) {
+    use glium::glutin::dpi::{LogicalPosition, LogicalSize};
+
     // TODO: Right now, no focusing, hovering or :active allowed in iframes!
     let is_mouse_down = false;
     let mut focused_node = None;
     let mut focus_target = None;
     let hovered_nodes = BTreeMap::new();
 
-    let mut ui_state = new_dom.into_ui_state();
+    // The iframe shares the tag generator of its parent window,
+    // so that the tags of the iframe can't collide with the tags of the parent
+    // The DOM of an iframe comes from a user callback, so it has to be checked first
+    let mut ui_state = match new_dom.try_into_ui_state(&mut referenced_mutable_content.fake_window.tag_ids) {
+        Ok(ui_state) => ui_state,
+        Err(e) => {
+            report(AzulError::InvalidIFrameDom(e));
+            return;
+        },
+    };
     let ui_description = UiDescription::<T>::match_css_to_dom(
         &mut ui_state,
         &referenced_content.css,
+        &referenced_content.css_index,
         &mut focused_node,
         &mut focus_target,
         &hovered_nodes,
-        is_mouse_down
+        is_mouse_down,
+        &mut referenced_mutable_content.fake_window.tag_ids,
     );
 
     let display_list = DisplayList::new_from_ui_description(&ui_description, &ui_state);
@@ -1205,8 +2101,8 @@ fn push_iframe<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
     let node_data = &arena.node_data;
 
     // Insert the DOM into the solver so we can solve the layout of the rectangles
-    let rect_size = LogicalSize::new(info.rect.size.width as f64, info.rect.size.height as f64);
-    let rect_origin = LogicalPosition::new(info.rect.origin.x as f64, info.rect.origin.y as f64);
+    let rect_size = LogicalSize::new(rect.size.width as f64, rect.size.height as f64);
+    let rect_origin = LogicalPosition::new(rect.origin.x as f64, rect.origin.y as f64);
 
     let (laid_out_rectangles, node_depths, word_cache) = do_the_layout(
         &node_hierarchy,
@@ -1218,11 +2114,14 @@ fn push_iframe<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
         rect_size,
         rect_origin);
 
+    referenced_mutable_content.fake_window.profiler.add_relayouted_nodes(display_list.rectangles.len());
+    referenced_mutable_content.fake_window.profiler.add_display_rectangles(display_list.rectangles.len());
+
     let mut scrollable_nodes = get_nodes_that_need_scroll_clip(
         node_hierarchy, &display_list.rectangles, node_data, &laid_out_rectangles,
-        &node_depths, referenced_content.pipeline_id);
+        &node_depths, referenced_content.pipeline_id, &mut referenced_mutable_content.fake_window.tag_ids);
 
-    let rects_in_rendering_order = determine_rendering_order(node_hierarchy, &display_list.rectangles, &laid_out_rectangles);
+    let rects_in_rendering_order = determine_rendering_order(node_hierarchy, &display_list.rectangles);
 
     let referenced_content = DisplayListParametersRef {
         // Important: Need to update the ui description, otherwise this function would be endlessly recursive
@@ -1233,9 +2132,13 @@ fn push_iframe<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
         .. *referenced_content
     };
 
+    // The nodes of the sub-DOM don't belong to the DOM of the window, so their
+    // tags are pushed to webrender, but they aren't recorded for the hit test
+    let hit_regions_len = referenced_mutable_content.fake_window.state.hit_regions.len();
+
     push_rectangles_into_displaylist(
         &laid_out_rectangles,
-        rectangle.epoch,
+        epoch,
         rects_in_rendering_order,
         &mut scrollable_nodes,
         &mut ScrollStates::new(),
@@ -1243,10 +2146,98 @@ fn push_iframe<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
         referenced_mutable_content
     );
 
+    referenced_mutable_content.fake_window.state.hit_regions.truncate(hit_regions_len);
+
     parent_scrollable_nodes.overflowing_nodes.extend(scrollable_nodes.overflowing_nodes.into_iter());
     parent_scrollable_nodes.tags_to_node_ids.extend(scrollable_nodes.tags_to_node_ids.into_iter());
+}
 
-    None
+/// Draws the error overlay (a caught callback panic or a hot-reload error) on top of the window content
+fn push_error_overlay<'b,'c,'d,'e,'f,'g, T: Layout>(
+    overlay: Dom<T>,
+    window_size: LogicalSize,
+    epoch: Epoch,
+    scrollable_nodes: &mut ScrolledNodes,
+    referenced_content: &DisplayListParametersRef<'b,'c,'d,'e,'f, T>,
+    referenced_mutable_content: &mut DisplayListParametersMut<'g, T>,
+) {
+    let css = error_overlay_css();
+    let css_index = CssRuleIndex::new(&css);
+
+    let overlay_content = DisplayListParametersRef {
+        css: &css,
+        css_index: &css_index,
+        .. *referenced_content
+    };
+
+    push_dom_into_rect(overlay, error_overlay_rect(window_size), epoch, scrollable_nodes, &overlay_content, referenced_mutable_content);
+}
+
+/// Draws the frame HUD from the stats of the previous frame, without counting
+/// the time and the rectangles of the HUD in the stats of the current frame
+#[cfg(feature = "profiling")]
+fn push_frame_hud<'b,'c,'d,'e,'f,'g, T: Layout>(
+    window_size: LogicalSize,
+    epoch: Epoch,
+    scrollable_nodes: &mut ScrolledNodes,
+    referenced_content: &DisplayListParametersRef<'b,'c,'d,'e,'f, T>,
+    referenced_mutable_content: &mut DisplayListParametersMut<'g, T>,
+) {
+    let untracked = referenced_mutable_content.fake_window.profiler.begin_untracked();
+
+    let data = referenced_mutable_content.fake_window.state.frame_hud_data();
+    let hud_rect = frame_hud_rect(window_size);
+
+    let css = frame_hud_css();
+    let css_index = CssRuleIndex::new(&css);
+
+    let hud_content = DisplayListParametersRef {
+        css: &css,
+        css_index: &css_index,
+        .. *referenced_content
+    };
+
+    push_dom_into_rect(frame_hud_dom(&data), hud_rect, epoch, scrollable_nodes, &hud_content, referenced_mutable_content);
+    push_debug_overlay(&frame_hud_rects(&data, hud_rect), referenced_mutable_content.builder);
+
+    referenced_mutable_content.fake_window.profiler.end_untracked(untracked);
+}
+
+/// Highlights the hovered and selected node and draws the inspector panel on top of the window
+#[cfg(feature = "debug-inspector")]
+fn push_inspector_overlay<'b,'c,'d,'e,'f,'g, T: Layout>(
+    inspector: &Inspector,
+    ui_state: &UiState<T>,
+    window_size: LogicalSize,
+    epoch: Epoch,
+    scrollable_nodes: &mut ScrolledNodes,
+    referenced_content: &DisplayListParametersRef<'b,'c,'d,'e,'f, T>,
+    referenced_mutable_content: &mut DisplayListParametersMut<'g, T>,
+) {
+    use inspector::{HOVERED_NODE_COLOR, SELECTED_NODE_COLOR, panel_rect};
+
+    let (highlights, panel_dom) = {
+        let layout = &referenced_mutable_content.fake_window.layout_snapshot;
+        let highlights = [
+            (inspector.hovered_node(), HOVERED_NODE_COLOR),
+            (inspector.selected_node(), SELECTED_NODE_COLOR),
+        ].iter()
+        .filter_map(|(node_id, color)| node_id.and_then(|id| layout.get_rect(id)).map(|rect| (rect, *color)))
+        .collect::<Vec<_>>();
+        (highlights, inspector.panel_dom(ui_state, layout))
+    };
+
+    for (rect, color) in highlights {
+        push_rect(&LayoutPrimitiveInfo::new(rect), referenced_mutable_content.builder, &color);
+    }
+
+    let panel_content = DisplayListParametersRef {
+        css: &inspector.css,
+        css_index: &inspector.css_index,
+        .. *referenced_content
+    };
+
+    push_dom_into_rect(panel_dom, panel_rect(window_size), epoch, scrollable_nodes, &panel_content, referenced_mutable_content);
 }
 
 /// Since the display list can take a lot of parameters, we don't want to
@@ -1259,9 +2250,11 @@ fn push_iframe<'a,'b,'c,'d,'e,'f,'g, T: Layout>(
 struct DisplayListParametersRef<'a, 'b, 'c, 'd, 'e, T: 'a + Layout> {
     pub pipeline_id: PipelineId,
     pub node_hierarchy: &'e NodeHierarchy,
-    pub node_data: &'a NodeDataContainer<NodeData<T>>,
+    pub node_data: &'a ArenaNodeData<NodeData<T>>,
     /// The CSS that should be applied to the DOM
     pub css: &'b Css,
+    /// Index of the rules in `css`, for matching the rules to the nodes of iframes
+    pub css_index: &'b CssRuleIndex,
     /// Necessary to push
     pub render_api: &'c RenderApi,
     /// Reference to the arena that contains all the styled rectangles
@@ -1289,6 +2282,12 @@ struct DisplayListParametersMut<'a, T: 'a + Layout> {
     /// Window access, so that sub-items can register OpenGL textures
     pub fake_window: &'a mut FakeWindow<T>,
     pub pipeline_id: PipelineId,
+    /// Which layout debugging overlays are enabled
+    pub debug_overlay: DebugOverlayFlags,
+    /// Baselines of all text that was pushed, only filled if `debug_overlay.text_baselines` is set
+    pub text_baselines: &'a mut Vec<LayoutRect>,
+    /// How many iframes the currently pushed DOM is nested in
+    pub iframe_depth: usize,
 }
 
 #[inline]
@@ -1303,6 +2302,8 @@ fn push_rect(
 
 struct OverflowInfo {
     pub text_overflow: TextOverflowPass2,
+    /// Baselines of the text lines, only collected for the debug overlay
+    pub text_baselines: Vec<LayoutRect>,
 }
 
 /// Note: automatically pushes the scrollbars on the parent,
@@ -1320,7 +2321,8 @@ fn push_text(
     horz_alignment: StyleTextAlignmentHorz,
     vert_alignment: StyleTextAlignmentVert,
     scrollbar_info: &ScrollbarInfo,
-    words: &Words)
+    words: &ShapedWords,
+    collect_baselines: bool)
 -> Option<OverflowInfo>
 {
     use text_layout::{self, TextLayoutOptions};
@@ -1369,7 +2371,41 @@ fn push_text(
 
     builder.push_text(&info, &positioned_glyphs, font_instance_key, font_color, Some(options));
 
-    Some(OverflowInfo { text_overflow })
+    let text_baselines = if collect_baselines {
+        get_text_baselines(&positioned_glyphs, bounds)
+    } else {
+        Vec::new()
+    };
+
+    Some(OverflowInfo { text_overflow, text_baselines })
+}
+
+/// Returns one 1px high rectangle across the text `bounds` for every line of the text
+fn get_text_baselines(positioned_glyphs: &[GlyphInstance], bounds: &TypedRect<f32, LayoutPixel>) -> Vec<LayoutRect> {
+    // The glyphs are positioned on the baseline, line by line
+    let mut line_baselines = Vec::<f32>::new();
+    for glyph in positioned_glyphs {
+        if line_baselines.last() != Some(&glyph.point.y) {
+            line_baselines.push(glyph.point.y);
+        }
+    }
+    line_baselines.into_iter()
+        .map(|y| LayoutRect::new(LayoutPoint::new(bounds.origin.x, y), LayoutSize::new(bounds.size.width, 1.0)))
+        .collect()
+}
+
+/// Pushes the rectangles of the layout debugging overlay, see `WindowState::set_debug_overlay()`
+fn push_debug_overlay(debug_rects: &[DebugRect], builder: &mut DisplayListBuilder) {
+    for debug_rect in debug_rects {
+        let color = StyleBackgroundColor(debug_rect.color);
+        if debug_rect.outline {
+            for edge in debug_rect.outline_edges().iter() {
+                push_rect(&LayoutPrimitiveInfo::new(*edge), builder, &color);
+            }
+        } else {
+            push_rect(&LayoutPrimitiveInfo::new(debug_rect.rect), builder, &color);
+        }
+    }
 }
 
 /// Adds a scrollbar to the left or bottom side of a rectangle.
@@ -1602,16 +2638,20 @@ fn push_box_shadow(
             // clipped to the bounds -we trust that the calling function knows to do this
             *bounds
         } else {
-            // calculate the maximum extent of the outset shadow
+            // calculate the maximum extent of the outset shadow: the shadow is
+            // moved by the offset and grows by the spread + blur radius in each direction
             let mut clip_rect = *bounds;
 
             let origin_displace = (pre_shadow.spread_radius.to_pixels() + pre_shadow.blur_radius.to_pixels()) * 2.0;
-            clip_rect.origin.x = clip_rect.origin.x - pre_shadow.offset[0].to_pixels() - origin_displace;
-            clip_rect.origin.y = clip_rect.origin.y - pre_shadow.offset[1].to_pixels() - origin_displace;
+            clip_rect.origin.x = clip_rect.origin.x + pre_shadow.offset[0].to_pixels() - origin_displace;
+            clip_rect.origin.y = clip_rect.origin.y + pre_shadow.offset[1].to_pixels() - origin_displace;
 
             clip_rect.size.height = clip_rect.size.height + (origin_displace * 2.0);
             clip_rect.size.width = clip_rect.size.width + (origin_displace * 2.0);
-            clip_rect
+
+            // the shadow is only visible outside of the box, but the clip rect
+            // has to contain the box as well, otherwise an offset shadow is cut off
+            clip_rect.union(bounds)
         }
     }
 
@@ -1786,7 +2826,8 @@ fn push_background(
     background: &StyleBackground,
     background_size: &Option<StyleBackgroundSize>,
     background_repeat: &Option<StyleBackgroundRepeat>,
-    app_resources: &AppResources)
+    app_resources: &AppResources,
+    render_api: &RenderApi)
 {
     use azul_css::{Shape, StyleBackground::*};
     use css::webrender_translate::{
@@ -1849,7 +2890,7 @@ fn push_background(
 
                 let background_repeat = background_repeat.unwrap_or_default();
                 let background_repeat_info = get_background_repeat_info(&info, background_repeat, size);
-                push_image(&background_repeat_info, builder, app_resources, image_id, size);
+                push_image(&background_repeat_info, builder, app_resources, render_api, image_id, size);
             }
         },
         NoBackground => { },
@@ -1917,13 +2958,13 @@ fn push_image(
     info: &PrimitiveInfo<LayoutPixel>,
     builder: &mut DisplayListBuilder,
     app_resources: &AppResources,
+    render_api: &RenderApi,
     image_id: &ImageId,
     size: TypedSize2D<f32, LayoutPixel>)
 -> Option<OverflowInfo>
 {
-    use images::ImageState::*;
-
-    if let Uploaded(image_info) = app_resources.images.get(image_id)? {
+    // The images are uploaded to the GPU of the window before the display list is built
+    if let Some(image_info) = app_resources.get_gpu_resources(render_api)?.images.get(image_id) {
         builder.push_image(
             info,
             size,
@@ -1958,6 +2999,96 @@ fn push_border(
     }
 }
 
+/// Pushes the `border-image` of a node instead of the colors of its `border`. Gradients
+/// are drawn through a clip that only leaves the (rounded) border area visible, images
+/// are sliced into a nine-patch by webrender and ignore the `border-radius`.
+#[inline]
+fn push_border_image(
+    info: &PrimitiveInfo<LayoutPixel>,
+    builder: &mut DisplayListBuilder,
+    border: &StyleBorder,
+    border_image: &StyleBorderImage,
+    border_radius: &Option<StyleBorderRadius>,
+    app_resources: &AppResources,
+    render_api: &RenderApi)
+{
+    use css::webrender_translate::{
+        wr_translate_layout_side_offsets, wr_translate_border_radius, wr_translate_nine_patch_border,
+    };
+
+    let border_widths = match border.get_webrender_border(None) {
+        Some((border_widths, _)) => wr_translate_layout_side_offsets(border_widths),
+        None => return,
+    };
+
+    match &border_image.source {
+        StyleBackground::NoBackground => {
+            push_border(info, builder, border, border_radius);
+        },
+        StyleBackground::Image(css_image_id) => {
+            let image_info = app_resources.css_ids_to_image_ids.get(&css_image_id.0)
+                .and_then(|image_id| app_resources.get_gpu_resources(render_api)?.images.get(image_id));
+            if let Some(image_info) = image_info {
+                let size = image_info.descriptor.size;
+                let nine_patch = border_image.get_nine_patch(size.width as u32, size.height as u32);
+                builder.push_border(info, border_widths, wr_translate_nine_patch_border(nine_patch, image_info.key));
+            }
+        },
+        gradient => {
+            let outer_radius = wr_translate_border_radius(border_radius.map(|r| r.0).unwrap_or_default());
+            let inner_rect = LayoutRect::new(
+                LayoutPoint::new(info.rect.origin.x + border_widths.left, info.rect.origin.y + border_widths.top),
+                LayoutSize::new(
+                    (info.rect.size.width - border_widths.left - border_widths.right).max(0.0),
+                    (info.rect.size.height - border_widths.top - border_widths.bottom).max(0.0),
+                ),
+            );
+            // Like in CSS, the inner edge is rounded with the outer radius minus the border width
+            let inner_radius = |outer: LayoutSize, horizontal: f32, vertical: f32| {
+                LayoutSize::new((outer.width - horizontal).max(0.0), (outer.height - vertical).max(0.0))
+            };
+            let inner_radius = BorderRadius {
+                top_left: inner_radius(outer_radius.top_left, border_widths.left, border_widths.top),
+                top_right: inner_radius(outer_radius.top_right, border_widths.right, border_widths.top),
+                bottom_left: inner_radius(outer_radius.bottom_left, border_widths.left, border_widths.bottom),
+                bottom_right: inner_radius(outer_radius.bottom_right, border_widths.right, border_widths.bottom),
+            };
+
+            let border_area = builder.define_clip(info.rect, vec![
+                ComplexClipRegion::new(info.rect, outer_radius, ClipMode::Clip),
+                ComplexClipRegion::new(inner_rect, inner_radius, ClipMode::ClipOut),
+            ], None);
+
+            builder.push_clip_id(border_area);
+            push_background(info, &info.rect, builder, gradient, &None, &None, app_resources, render_api);
+            builder.pop_clip_id();
+        },
+    }
+}
+
+/// Pushes the `outline` of a node - the outline is drawn outside of the border box,
+/// so that it doesn't take up any space in the layout (`outline-offset` is not supported)
+#[inline]
+fn push_outline(
+    builder: &mut DisplayListBuilder,
+    outline: &StyleOutline,
+    bounds: &LayoutRect,
+    border_radius: &Option<StyleBorderRadius>)
+{
+    use azul_css::BorderStyle;
+
+    let StyleOutline(outline_side) = outline;
+    if outline_side.border_style == BorderStyle::None || outline_side.border_style == BorderStyle::Hidden {
+        return;
+    }
+
+    let outline_width = outline_side.border_width.to_pixels();
+    let outline_rect = bounds.inflate(outline_width, outline_width);
+    let info = LayoutPrimitiveInfo::with_clip_rect(outline_rect, outline_rect);
+
+    push_border(&info, builder, &StyleBorder::all(*outline_side), border_radius);
+}
+
 #[inline]
 fn push_font(
     font_id: &FontId,
@@ -1967,49 +3098,13 @@ fn push_font(
     render_api: &RenderApi)
 -> Option<FontInstanceKey>
 {
-    use font::FontState;
-
     if font_size_app_units < MIN_AU || font_size_app_units > MAX_AU {
-        #[cfg(feature = "logging")] {
-            error!("warning: too big or too small font size");
-        }
+        report(AzulError::FontSizeOutOfRange { px: font_size_app_units.to_f32_px() });
         return None;
     }
 
-    let font_state = app_resources.get_font_state(font_id)?;
-
-    let borrow = font_state.borrow();
-
-    match &*borrow {
-        FontState::Uploaded(font_key) => {
-            let font_sizes_hashmap = app_resources.fonts.entry(*font_key)
-                                     .or_insert(FastHashMap::default());
-            let font_instance_key = font_sizes_hashmap.entry(font_size_app_units)
-                .or_insert_with(|| {
-                    let f_instance_key = render_api.generate_font_instance_key();
-                    resource_updates.push(ResourceUpdate::AddFontInstance(
-                        AddFontInstance {
-                            key: f_instance_key,
-                            font_key: *font_key,
-                            glyph_size: font_size_app_units,
-                            options: None,
-                            platform_options: None,
-                            variations: Vec::new(),
-                        }
-                    ));
-                    f_instance_key
-                }
-            );
-
-            Some(*font_instance_key)
-        },
-        _ => {
-            // This can happen when the font is loaded for the first time in `.get_font_state`
-            // TODO: Make a pre-pass that queries and uploads all non-available fonts
-            // error!("warning: trying to use font {:?} that isn't yet available", font_id);
-            None
-        },
-    }
+    // Uploads the font to the GPU of this window first if it isn't there yet
+    app_resources.get_font_instance_key(font_id, font_size_app_units, render_api, resource_updates)
 }
 
 /// For a given rectangle, determines what text alignment should be used
@@ -2073,7 +3168,7 @@ fn subtract_padding(bounds: &TypedRect<f32, LayoutPixel>, padding: &LayoutPaddin
 fn populate_css_properties(
     rect: &mut DisplayRectangle,
     node_id: NodeId,
-    css_overrides: &BTreeMap<NodeId, FastHashMap<String, CssProperty>>)
+    css_overrides: &BTreeMap<NodeId, CssOverrideList>)
 {
     use azul_css::CssProperty::{self, *};
 
@@ -2085,6 +3180,7 @@ fn populate_css_properties(
             BackgroundRepeat(r) => { rect.style.background_repeat = Some(*r);               },
             TextColor(t)        => { rect.style.font_color = Some(*t);                      },
             Border(b)           => { StyleBorder::merge(&mut rect.style.border, &b);        },
+            BorderImage(b)      => { rect.style.border_image = Some(b.clone());             },
             Background(b)       => { rect.style.background = Some(b.clone());               },
             FontSize(f)         => { rect.style.font_size = Some(*f);                       },
             FontFamily(f)       => { rect.style.font_family = Some(f.clone());              },
@@ -2092,10 +3188,17 @@ fn populate_css_properties(
             Overflow(o)         => { LayoutOverflow::merge(&mut rect.style.overflow, &o);   },
             TextAlign(ta)       => { rect.style.text_align = Some(*ta);                     },
             BoxShadow(b)        => { StyleBoxShadow::merge(&mut rect.style.box_shadow, b);  },
+            Outline(o)          => { rect.style.outline = Some(*o);                         },
+            Transform(t)        => { rect.style.transform = Some(t.clone());                },
+            TransformOrigin(o)  => { rect.style.transform_origin = Some(*o);                },
+            ZIndex(z)           => { rect.style.z_index = Some(*z);                         },
             LineHeight(lh)      => { rect.style.line_height = Some(*lh);                    },
 
-            Width(w)            => { rect.layout.width = Some(*w);                          },
-            Height(h)           => { rect.layout.height = Some(*h);                         },
+            Width(w)            => { rect.layout.width = Some(*w); rect.layout.intrinsic_width = None;      },
+            Height(h)           => { rect.layout.height = Some(*h); rect.layout.intrinsic_height = None;    },
+            IntrinsicWidth(w)   => { rect.layout.intrinsic_width = Some(*w); rect.layout.width = None;      },
+            IntrinsicHeight(h)  => { rect.layout.intrinsic_height = Some(*h); rect.layout.height = None;    },
+            AspectRatio(a)      => { rect.layout.aspect_ratio = Some(*a);                   },
             MinWidth(mw)        => { rect.layout.min_width = Some(*mw);                     },
             MinHeight(mh)       => { rect.layout.min_height = Some(*mh);                    },
             MaxWidth(mw)        => { rect.layout.max_width = Some(*mw);                     },
@@ -2117,18 +3220,22 @@ fn populate_css_properties(
             JustifyContent(j)   => { rect.layout.justify_content = Some(*j);                },
             AlignItems(a)       => { rect.layout.align_items = Some(*a);                    },
             AlignContent(a)     => { rect.layout.align_content = Some(*a);                  },
+            GridTemplateColumns(c) => { rect.layout.grid_template_columns = Some(c.clone()); },
+            GridTemplateRows(r) => { rect.layout.grid_template_rows = Some(r.clone());      },
+            GridColumn(c)       => { rect.layout.grid_column = Some(*c);                    },
+            GridRow(r)          => { rect.layout.grid_row = Some(*r);                       },
+            Gap(g)              => { rect.layout.gap = Some(*g);                            },
             Cursor(_)           => { /* cursor neither affects layout nor styling */        },
         }
     }
 
     use azul_css::DynamicCssPropertyDefault;
 
-    // Assert that the types of two properties matches
+    // Assert that the types of two properties matches (`width: 50px` and `width: min-content` are the same type)
     fn property_type_matches(a: &CssProperty, b: &DynamicCssPropertyDefault) -> bool {
-        use std::mem::discriminant;
         use azul_css::DynamicCssPropertyDefault::*;
         match b {
-            Exact(e) => discriminant(a) == discriminant(e),
+            Exact(e) => a.get_type() == e.get_type(),
             Auto => true, // "auto" always matches
         }
     }
@@ -2139,18 +3246,12 @@ fn populate_css_properties(
         match constraint {
             Static(static_property) => apply_style_property(rect, static_property),
             Dynamic(dynamic_property) => {
-                if let Some(overridden_property) = css_overrides.get(&node_id).and_then(|overrides| overrides.get(&dynamic_property.dynamic_id)) {
+                if let Some(overridden_property) = css_overrides.get(&node_id).and_then(|overrides| find_css_override(overrides, &dynamic_property.dynamic_id)) {
                     // Only apply the dynamic style property default, if it isn't set to auto
                     if property_type_matches(overridden_property, &dynamic_property.default) {
                         apply_style_property(rect, overridden_property);
                     } else {
-                        #[cfg(feature = "logging")] {
-                            error!(
-                                "Dynamic style property on rect {:?} don't have the same discriminant type,\r\n
-                                cannot override {:?} with {:?} - enum discriminant mismatch",
-                                rect, dynamic_property.default, overridden_property
-                            )
-                        }
+                        report(AzulError::CssOverrideMismatch { dynamic_id: dynamic_property.dynamic_id.clone() });
                     }
                 } else if let DynamicCssPropertyDefault::Exact(default) = &dynamic_property.default {
                     apply_style_property(rect, default);
@@ -1,49 +1,228 @@
 use std::{
     fmt,
+    mem,
     rc::Rc,
+    cell::Cell,
+    borrow::Cow,
     hash::{Hash, Hasher},
-    sync::atomic::{AtomicUsize, Ordering},
     collections::BTreeMap,
     iter::FromIterator,
+    str::FromStr,
 };
 use glium::{Texture2d, framebuffer::SimpleFrameBuffer};
 use azul_css::{ NodeTypePath, CssProperty };
 use {
-    ui_state::UiState,
+    ui_state::{UiState, CallbackList, CssOverrideList, insert_callback, dedup_css_overrides},
     FastHashMap,
     window::{CallbackInfo, LayoutInfo},
-    images::{ImageId, ImageState},
+    images::{ImageId, DecodedImage},
     text_cache::TextId,
     traits::Layout,
     app_state::AppState,
-    id_tree::{Arena, NodeDataContainer},
+    app_resources::AppResources,
+    id_tree::{Arena, ArenaNodeData},
     default_callbacks::{DefaultCallbackId, StackCheckedPointer},
     window::HidpiAdjustedBounds,
     text_layout::{Words, FontMetrics, TextSizePx},
+    ui_solver::ContentSize,
+    deterministic::{DomHasher, hash_fn_ptr},
+    diff::{DomDiff, diff_dom_tree},
+    snapshot::format_line_diff,
+    xml::{XmlCallbackMap, XmlParseError, dom_from_xml},
 };
 
 pub use id_tree::{NodeHierarchy, Node, NodeId};
 
-static TAG_ID: AtomicUsize = AtomicUsize::new(1);
+/// Builds a `Dom` from a declarative description of the tree, instead of chaining
+/// `with_child(Dom::new(...).with_child(...))` calls:
+///
+/// ```no_run,ignore
+/// dom! {
+///     div.sidebar#main [
+///         label("Settings").title,
+///         div.row on: { MouseUp => toggle_dark_mode } [
+///             image(checkbox_image),
+///             label("Dark mode"),
+///         ],
+///         div."button-row" [
+///             { Button::with_label("Save").dom() },
+///         ],
+///     ]
+/// }
+/// ```
+///
+/// Every node starts with its type, followed by (in this order) its classes (`.class`), its
+/// IDs (`#id`), its callbacks (`on: { On::Event => callback }`) and its children (`[...]`,
+/// separated by commas). The type is either `div`, any other constructor of `Dom`
+/// (`label("text")`, `image(image_id)`, `text_id(text_id)`, ...) or an arbitrary expression
+/// that returns a `Dom` in braces. Classes and IDs that aren't valid Rust identifiers (such
+/// as `button-row`) have to be written as string literals.
+///
+/// The macro expands to the `Dom::new` / `with_class` / `with_id` / `with_callback` /
+/// `with_child` calls that build the same DOM by hand. Nodes that are nested very deeply
+/// or have a lot of children may need a higher `#![recursion_limit]`.
+#[macro_export]
+macro_rules! dom {
+    // Type of the node
+    (@node div $($rest:tt)*) => {
+        dom!(@modifiers ($crate::dom::Dom::div()) $($rest)*)
+    };
+    (@node { $dom:expr } $($rest:tt)*) => {
+        dom!(@modifiers ($dom) $($rest)*)
+    };
+    (@node $constructor:ident ( $($args:tt)* ) $($rest:tt)*) => {
+        dom!(@modifiers ($crate::dom::Dom::$constructor($($args)*)) $($rest)*)
+    };
+    (@node $($rest:tt)*) => {
+        compile_error!(concat!(
+            "dom!: expected `div`, a constructor of `Dom` such as `label(\"text\")` or `{ expression }`, found `",
+            stringify!($($rest)*), "`"
+        ))
+    };
+
+    // Classes, IDs, callbacks and children of the node
+    (@modifiers ($dom:expr)
+        $(. $class:tt)*
+        $(# $id:tt)*
+        $(on : { $($event:ident => $callback:expr),* $(,)* })*
+        $([ $($children:tt)* ])*
+    ) => {
+        dom!(@children
+            ($dom
+                $(.with_class(dom!(@name $class)))*
+                $(.with_id(dom!(@name $id)))*
+                $($(.with_callback($crate::dom::On::$event, $crate::dom::Callback($callback)))*)*)
+            $($($children)*)*
+        )
+    };
+    (@modifiers ($dom:expr) $($rest:tt)*) => {
+        compile_error!(concat!(
+            "dom!: expected `.class`, `#id`, `on: { Event => callback }` or `[children]` (in this order), found `",
+            stringify!($($rest)*), "`"
+        ))
+    };
+
+    // Children, one at a time
+    (@children ($parent:expr)) => {
+        $parent
+    };
+    (@children ($parent:expr)
+        $head:tt $(( $($args:tt)* ))* $(. $class:tt)* $(# $id:tt)* $(on : $on:tt)* $([ $($children:tt)* ])*
+        , $($rest:tt)*
+    ) => {
+        dom!(@children
+            ($parent.with_child(dom!($head $(( $($args)* ))* $(. $class)* $(# $id)* $(on : $on)* $([ $($children)* ])*)))
+            $($rest)*
+        )
+    };
+    (@children ($parent:expr)
+        $head:tt $(( $($args:tt)* ))* $(. $class:tt)* $(# $id:tt)* $(on : $on:tt)* $([ $($children:tt)* ])*
+    ) => {
+        $parent.with_child(dom!($head $(( $($args)* ))* $(. $class)* $(# $id)* $(on : $on)* $([ $($children)* ])*))
+    };
+    (@children ($parent:expr) $($rest:tt)*) => {
+        compile_error!(concat!("dom!: expected a comma-separated list of nodes, found `", stringify!($($rest)*), "`"))
+    };
+
+    // `.class` or `."class-name"`
+    (@name $name:ident) => {
+        stringify!($name)
+    };
+    (@name $name:expr) => {
+        $name
+    };
+
+    ($($node:tt)+) => {
+        dom!(@node $($node)+)
+    };
+}
 
-pub(crate) type TagId = u64;
+/// Hit-testing tag of a node, see `UiState::node_ids_to_tag_ids()`
+pub type TagId = u64;
 
 /// Same as the `TagId`, but only for scrollable nodes
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub(crate) struct ScrollTagId(pub TagId);
 
-pub(crate) fn new_tag_id() -> TagId {
-    TAG_ID.fetch_add(1, Ordering::SeqCst) as TagId
+/// Hands out the hit-testing tags for one window. Each window owns one generator,
+/// which is also used for the DOMs of all iframes inside that window, so that
+/// tags are unique within the window, but never interfere with other windows.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct TagIdGenerator {
+    next_tag_id: TagId,
+    /// First tag of the current frame, see `begin_frame()`
+    frame_start_tag_id: TagId,
+}
+
+impl Default for TagIdGenerator {
+    fn default() -> Self {
+        Self { next_tag_id: 1, frame_start_tag_id: 1 }
+    }
 }
 
-pub(crate) fn new_scroll_tag_id() -> ScrollTagId {
-    ScrollTagId(new_tag_id())
+impl TagIdGenerator {
+
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn new_tag_id(&mut self) -> TagId {
+        let tag_id = self.next_tag_id;
+        self.next_tag_id += 1;
+        tag_id
+    }
+
+    pub(crate) fn new_scroll_tag_id(&mut self) -> ScrollTagId {
+        ScrollTagId(self.new_tag_id())
+    }
+
+    /// Marks the start of a new frame for `tags_created()`. The tags themselves
+    /// keep counting up, so that they never collide with the tags of older frames.
+    pub(crate) fn begin_frame(&mut self) {
+        self.frame_start_tag_id = self.next_tag_id;
+    }
+
+    /// Returns how many tags were handed out since the last `begin_frame()`
+    pub(crate) fn tags_created(&self) -> usize {
+        (self.next_tag_id - self.frame_start_tag_id) as usize
+    }
 }
 
 /// Calculated hash of a DOM node, used for querying attributes of the DOM node
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Ord, PartialOrd)]
 pub struct DomHash(pub u64);
 
+/// Caches the `DomHash` of a `NodeData`, so that the strings of a node
+/// only have to be hashed once as long as the node isn't modified.
+///
+/// The cache is private to the crate and cleared by every mutating method on
+/// `NodeData` and `Dom`, including `Dom::get_node_data_mut`. Writing to the public
+/// fields of an owned `NodeData` directly does **not** clear the cache - call
+/// `NodeData::invalidate_hash_cache()` if you do that.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct DomHashCache {
+    /// Hash of the entire node, `None` if the node was modified since the last hash
+    node: Cell<Option<DomHash>>,
+    /// Hash of the text of a `NodeType::Label`, computed when the text is set,
+    /// so that long strings don't get re-hashed if only the classes / ids change
+    content: Cell<Option<u64>>,
+}
+
+impl DomHashCache {
+    /// Clears the cached node hash, but keeps the hash of the text content
+    #[inline]
+    fn invalidate_node(&self) {
+        self.node.set(None);
+    }
+
+    /// Clears both the node hash and the hash of the text content
+    #[inline]
+    pub(crate) fn invalidate_all(&self) {
+        self.node.set(None);
+        self.content.set(None);
+    }
+}
+
 /// A callback function has to return if the screen should
 /// be updated after the function has run.
 ///
@@ -90,7 +269,7 @@ impl<T: Layout> Clone for Callback<T> {
 /// than re-creating the whole DOM and serves as a caching mechanism.
 impl<T: Layout> Hash for Callback<T> {
   fn hash<H>(&self, state: &mut H) where H: Hasher {
-    state.write_usize(self.0 as usize);
+    hash_fn_ptr(self.0 as usize, state);
   }
 }
 
@@ -124,7 +303,7 @@ impl<T: Layout> Clone for GlTextureCallback<T> {
 
 impl<T: Layout> Hash for GlTextureCallback<T> {
   fn hash<H>(&self, state: &mut H) where H: Hasher {
-    state.write_usize(self.0 as usize);
+    hash_fn_ptr(self.0 as usize, state);
   }
 }
 
@@ -155,7 +334,7 @@ impl<T: Layout> Clone for IFrameCallback<T> {
 
 impl<T: Layout> Hash for IFrameCallback<T> {
   fn hash<H>(&self, state: &mut H) where H: Hasher {
-    state.write_usize(self.0 as usize);
+    hash_fn_ptr(self.0 as usize, state);
   }
 }
 
@@ -169,6 +348,104 @@ impl<T: Layout> Eq for IFrameCallback<T> { }
 
 impl<T: Layout> Copy for IFrameCallback<T> { }
 
+/// Maps human-readable names to callbacks (and back), so that callbacks
+/// can be referred to by name, for example in DOM snapshots
+/// (see `Dom::serialize_for_snapshot_with_names`)
+pub struct CallbackRegistry<T: Layout> {
+    callbacks: BTreeMap<String, Callback<T>>,
+    gl_callbacks: BTreeMap<String, GlTextureCallback<T>>,
+    iframe_callbacks: BTreeMap<String, IFrameCallback<T>>,
+    /// Images that markup can refer to by name, see `Dom::from_xml`
+    images: BTreeMap<String, ImageId>,
+    /// Reverse lookup: function address -> name
+    names: FastHashMap<usize, String>,
+}
+
+impl<T: Layout> Default for CallbackRegistry<T> {
+    fn default() -> Self {
+        Self {
+            callbacks: BTreeMap::new(),
+            gl_callbacks: BTreeMap::new(),
+            iframe_callbacks: BTreeMap::new(),
+            images: BTreeMap::new(),
+            names: FastHashMap::default(),
+        }
+    }
+}
+
+impl<T: Layout> fmt::Debug for CallbackRegistry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+            "CallbackRegistry {{ callbacks: {:?}, gl_callbacks: {:?}, iframe_callbacks: {:?}, images: {:?} }}",
+            self.callbacks.keys().collect::<Vec<_>>(),
+            self.gl_callbacks.keys().collect::<Vec<_>>(),
+            self.iframe_callbacks.keys().collect::<Vec<_>>(),
+            self.images,
+        )
+    }
+}
+
+impl<T: Layout> CallbackRegistry<T> {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a `Callback` under the given name. If the name is already taken,
+    /// the old callback is replaced.
+    pub fn register_callback<S: Into<String>>(&mut self, name: S, callback: Callback<T>) {
+        let name = name.into();
+        self.names.insert(callback.0 as usize, name.clone());
+        self.callbacks.insert(name, callback);
+    }
+
+    /// Same as `register_callback`, but for `GlTextureCallback`s
+    pub fn register_gl_callback<S: Into<String>>(&mut self, name: S, callback: GlTextureCallback<T>) {
+        let name = name.into();
+        self.names.insert(callback.0 as usize, name.clone());
+        self.gl_callbacks.insert(name, callback);
+    }
+
+    /// Same as `register_callback`, but for `IFrameCallback`s
+    pub fn register_iframe_callback<S: Into<String>>(&mut self, name: S, callback: IFrameCallback<T>) {
+        let name = name.into();
+        self.names.insert(callback.0 as usize, name.clone());
+        self.iframe_callbacks.insert(name, callback);
+    }
+
+    pub fn get_callback(&self, name: &str) -> Option<Callback<T>> {
+        self.callbacks.get(name).cloned()
+    }
+
+    pub fn get_gl_callback(&self, name: &str) -> Option<GlTextureCallback<T>> {
+        self.gl_callbacks.get(name).cloned()
+    }
+
+    pub fn get_iframe_callback(&self, name: &str) -> Option<IFrameCallback<T>> {
+        self.iframe_callbacks.get(name).cloned()
+    }
+
+    /// Registers an image under the given name, so that markup can refer to it
+    /// as `<img src-id="name"/>`. If the name is already taken, the old image is replaced.
+    pub fn register_image<S: Into<String>>(&mut self, name: S, image_id: ImageId) {
+        self.images.insert(name.into(), image_id);
+    }
+
+    pub fn get_image(&self, name: &str) -> Option<ImageId> {
+        self.images.get(name).cloned()
+    }
+
+    /// Returns the name of the callback with the given function address
+    pub(crate) fn name_of(&self, fn_address: usize) -> Option<&str> {
+        self.names.get(&fn_address).map(|s| s.as_str())
+    }
+
+    /// Returns the registered name or `<fn>`, never the (run-dependent) address
+    fn snapshot_name(&self, fn_address: usize) -> &str {
+        self.name_of(fn_address).unwrap_or("<fn>")
+    }
+}
+
 
 /// List of core DOM node types built-into by `azul`.
 pub enum NodeType<T: Layout> {
@@ -275,14 +552,28 @@ impl<T: Layout> NodeType<T> {
         }
     }
 
-    /// Returns the preferred width, for example for an image, that would be the
-    /// original width (an image always wants to take up the original space)
-    pub(crate) fn get_preferred_width(&self, image_cache: &FastHashMap<ImageId, ImageState>) -> Option<f32> {
+    /// Returns the width of the content, for example for an image, the preferred width would
+    /// be the original width (an image always wants to take up the original space). For a text,
+    /// the `min-content` width is the longest word and the `max-content` width the longest line.
+    pub(crate) fn get_content_width(
+        &self,
+        image_cache: &FastHashMap<ImageId, DecodedImage>,
+        words: Option<&Words>,
+        font_metrics: Option<FontMetrics>,
+    ) -> ContentSize
+    {
         use self::NodeType::*;
         match self {
-            Image(i) => image_cache.get(i).and_then(|image_state| Some(image_state.get_dimensions().0)),
-            Label(_) | Text(_) => /* TODO: Calculate the minimum width for the text? */ None,
-            _ => None,
+            Image(i) => ContentSize::from_preferred(image_cache.get(i).and_then(|image_state| Some(image_state.get_dimensions().0))),
+            Label(_) | Text(_) => match (words, font_metrics) {
+                (Some(words), Some(font_metrics)) => ContentSize {
+                    preferred: None,
+                    min_content: words.longest_word_width,
+                    max_content: words.get_max_content_width(&font_metrics),
+                },
+                _ => ContentSize::default(),
+            },
+            _ => ContentSize::default(),
         }
     }
 
@@ -290,7 +581,7 @@ impl<T: Layout> NodeType<T> {
     pub(crate) fn get_preferred_height_based_on_width(
         &self,
         div_width: TextSizePx,
-        image_cache: &FastHashMap<ImageId, ImageState>,
+        image_cache: &FastHashMap<ImageId, DecodedImage>,
         words: Option<&Words>,
         font_metrics: Option<FontMetrics>,
     ) -> Option<TextSizePx>
@@ -344,12 +635,34 @@ pub enum On {
     /// (Specialization of `MouseUp`). Fires only if the right mouse button has
     /// been released while cursor was over the element
     RightMouseUp,
+    /// A mouse button has been clicked twice on the element, within the
+    /// `DoubleClickSettings` of the window. Fires on the second `MouseUp`
+    /// (the `MouseUp` callbacks are still called for both clicks).
+    DoubleClick,
+    /// (Specialization of `DoubleClick`). Fires only if the left mouse button
+    /// has been clicked twice on the element
+    LeftDoubleClick,
+    /// (Specialization of `DoubleClick`). Fires only if the middle mouse button
+    /// has been clicked twice on the element
+    MiddleDoubleClick,
+    /// (Specialization of `DoubleClick`). Fires only if the right mouse button
+    /// has been clicked twice on the element
+    RightDoubleClick,
     /// Mouse cursor has entered the element
     MouseEnter,
     /// Mouse cursor has left the element
     MouseLeave,
     /// Mousewheel / touchpad scrolling
     Scroll,
+    /// (Specialization of `Scroll`). Fires only if the content is scrolled up
+    /// (the wheel was moved away from the user)
+    ScrollUp,
+    /// (Specialization of `Scroll`). Fires only if the content is scrolled down
+    /// (the wheel was moved towards the user)
+    ScrollDown,
+    /// (Specialization of `Scroll`). Fires only if the content is scrolled
+    /// horizontally, i.e. on a touchpad or with a tilting mouse wheel
+    ScrollHorizontal,
     /// The window received a unicode character (also respects the system locale).
     /// Check `keyboard_state.current_char` to get the current pressed character.
     TextInput,
@@ -362,10 +675,13 @@ pub enum On {
     VirtualKeyDown,
     /// A **virtual keycode** was release. See `VirtualKeyDown` for more info.
     VirtualKeyUp,
-    /// A file has been dropped on the element
-    HoveredFile,
     /// A file is being hovered on the element
+    HoveredFile,
+    /// A file has been dropped on the element, see `WindowState::get_dropped_data()`
     DroppedFile,
+    /// Text or custom data has been dropped on the element by the `DragDropBackend`
+    /// of the window, see `WindowState::get_dropped_data()`
+    DroppedData,
     /// A file was hovered, but has exited the window
     HoveredFileCancelled,
     /// Equivalent to `onfocus`
@@ -386,6 +702,7 @@ pub enum On {
 /// to a more specific event) and use
 ///
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum EventFilter {
     /// Calls the attached callback when the mouse is actively over the
     /// given element.
@@ -461,15 +778,23 @@ impl From<On> for EventFilter {
             LeftMouseUp          => EventFilter::Hover(HoverEventFilter::LeftMouseUp),
             MiddleMouseUp        => EventFilter::Hover(HoverEventFilter::MiddleMouseUp),
             RightMouseUp         => EventFilter::Hover(HoverEventFilter::RightMouseUp),
+            DoubleClick          => EventFilter::Hover(HoverEventFilter::DoubleClick),
+            LeftDoubleClick      => EventFilter::Hover(HoverEventFilter::LeftDoubleClick),
+            MiddleDoubleClick    => EventFilter::Hover(HoverEventFilter::MiddleDoubleClick),
+            RightDoubleClick     => EventFilter::Hover(HoverEventFilter::RightDoubleClick),
 
             MouseEnter           => EventFilter::Hover(HoverEventFilter::MouseEnter),
             MouseLeave           => EventFilter::Hover(HoverEventFilter::MouseLeave),
             Scroll               => EventFilter::Hover(HoverEventFilter::Scroll),
+            ScrollUp             => EventFilter::Hover(HoverEventFilter::ScrollUp),
+            ScrollDown           => EventFilter::Hover(HoverEventFilter::ScrollDown),
+            ScrollHorizontal     => EventFilter::Hover(HoverEventFilter::ScrollHorizontal),
             TextInput            => EventFilter::Focus(FocusEventFilter::TextInput),            // focus!
             VirtualKeyDown       => EventFilter::Window(WindowEventFilter::VirtualKeyDown),     // window!
             VirtualKeyUp         => EventFilter::Window(WindowEventFilter::VirtualKeyUp),       // window!
             HoveredFile          => EventFilter::Hover(HoverEventFilter::HoveredFile),
             DroppedFile          => EventFilter::Hover(HoverEventFilter::DroppedFile),
+            DroppedData          => EventFilter::Hover(HoverEventFilter::DroppedData),
             HoveredFileCancelled => EventFilter::Hover(HoverEventFilter::HoveredFileCancelled),
             FocusReceived        => EventFilter::Focus(FocusEventFilter::FocusReceived),        // focus!
             FocusLost            => EventFilter::Focus(FocusEventFilter::FocusLost),            // focus!
@@ -478,6 +803,7 @@ impl From<On> for EventFilter {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum HoverEventFilter {
     MouseOver,
     MouseDown,
@@ -488,14 +814,22 @@ pub enum HoverEventFilter {
     LeftMouseUp,
     RightMouseUp,
     MiddleMouseUp,
+    DoubleClick,
+    LeftDoubleClick,
+    RightDoubleClick,
+    MiddleDoubleClick,
     MouseEnter,
     MouseLeave,
     Scroll,
+    ScrollUp,
+    ScrollDown,
+    ScrollHorizontal,
     TextInput,
     VirtualKeyDown,
     VirtualKeyUp,
     HoveredFile,
     DroppedFile,
+    DroppedData,
     HoveredFileCancelled,
 }
 
@@ -512,26 +846,40 @@ impl HoverEventFilter {
             LeftMouseUp => Some(FocusEventFilter::LeftMouseUp),
             RightMouseUp => Some(FocusEventFilter::RightMouseUp),
             MiddleMouseUp => Some(FocusEventFilter::MiddleMouseUp),
+            DoubleClick => Some(FocusEventFilter::DoubleClick),
+            LeftDoubleClick => Some(FocusEventFilter::LeftDoubleClick),
+            RightDoubleClick => Some(FocusEventFilter::RightDoubleClick),
+            MiddleDoubleClick => Some(FocusEventFilter::MiddleDoubleClick),
             MouseEnter => Some(FocusEventFilter::MouseEnter),
             MouseLeave => Some(FocusEventFilter::MouseLeave),
             Scroll => Some(FocusEventFilter::Scroll),
+            ScrollUp => Some(FocusEventFilter::ScrollUp),
+            ScrollDown => Some(FocusEventFilter::ScrollDown),
+            ScrollHorizontal => Some(FocusEventFilter::ScrollHorizontal),
             TextInput => Some(FocusEventFilter::TextInput),
             VirtualKeyDown => Some(FocusEventFilter::VirtualKeyDown),
             VirtualKeyUp => Some(FocusEventFilter::VirtualKeyDown),
             HoveredFile => None,
             DroppedFile => None,
+            DroppedData => None,
             HoveredFileCancelled => None,
         }
     }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum NotEventFilter {
     Hover(HoverEventFilter),
     Focus(FocusEventFilter),
+    /// Fires when the window event happens while the node is neither hovered
+    /// nor focused, i.e. a global shortcut that shouldn't fire while a text field
+    /// has the keyboard focus
+    Window(WindowEventFilter),
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum FocusEventFilter {
     MouseOver,
     MouseDown,
@@ -542,9 +890,16 @@ pub enum FocusEventFilter {
     LeftMouseUp,
     RightMouseUp,
     MiddleMouseUp,
+    DoubleClick,
+    LeftDoubleClick,
+    RightDoubleClick,
+    MiddleDoubleClick,
     MouseEnter,
     MouseLeave,
     Scroll,
+    ScrollUp,
+    ScrollDown,
+    ScrollHorizontal,
     TextInput,
     VirtualKeyDown,
     VirtualKeyUp,
@@ -553,6 +908,7 @@ pub enum FocusEventFilter {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum WindowEventFilter {
     MouseOver,
     MouseDown,
@@ -566,12 +922,31 @@ pub enum WindowEventFilter {
     MouseEnter,
     MouseLeave,
     Scroll,
+    ScrollUp,
+    ScrollDown,
+    ScrollHorizontal,
     TextInput,
     VirtualKeyDown,
     VirtualKeyUp,
     HoveredFile,
     DroppedFile,
+    /// Text or custom data has been dropped on the window by its `DragDropBackend`
+    DroppedData,
     HoveredFileCancelled,
+    /// The window was resized, see `CallbackInfo::window_size()`
+    Resized,
+    /// The window was moved, see `CallbackInfo::window_position()`
+    Moved,
+    /// The window was minimized (the window system resized it to a size of zero)
+    Minimized,
+    /// The window was maximized. Note: the windowing backend doesn't report when the
+    /// user maximizes the window yet, so this filter currently never fires.
+    Maximized,
+    /// The user clicked the close button of the window (or pressed Alt+F4, etc.), for
+    /// example to save unsaved changes. The window is closed after the callbacks ran.
+    CloseRequested,
+    /// The window was destroyed by the window system
+    Closed,
 }
 
 impl WindowEventFilter {
@@ -588,20 +963,32 @@ impl WindowEventFilter {
             RightMouseUp => Some(HoverEventFilter::RightMouseUp),
             MiddleMouseUp => Some(HoverEventFilter::MiddleMouseUp),
             Scroll => Some(HoverEventFilter::Scroll),
+            ScrollUp => Some(HoverEventFilter::ScrollUp),
+            ScrollDown => Some(HoverEventFilter::ScrollDown),
+            ScrollHorizontal => Some(HoverEventFilter::ScrollHorizontal),
             TextInput => Some(HoverEventFilter::TextInput),
             VirtualKeyDown => Some(HoverEventFilter::VirtualKeyDown),
             VirtualKeyUp => Some(HoverEventFilter::VirtualKeyDown),
             HoveredFile => Some(HoverEventFilter::HoveredFile),
             DroppedFile => Some(HoverEventFilter::DroppedFile),
+            DroppedData => Some(HoverEventFilter::DroppedData),
             HoveredFileCancelled => Some(HoverEventFilter::HoveredFileCancelled),
             // MouseEnter and MouseLeave on the **window** does not mean a mouseenter and a mouseleave on the hovered element
             MouseEnter => None,
             MouseLeave => None,
+            // Events of the window itself, not of any element
+            Resized => None,
+            Moved => None,
+            Minimized => None,
+            Maximized => None,
+            CloseRequested => None,
+            Closed => None,
         }
     }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum DesktopEventFilter {
     DeviceAdded,
     DeviceRemoved,
@@ -616,8 +1003,16 @@ pub struct NodeData<T: Layout> {
     /// `div`
     pub node_type: NodeType<T>,
     /// `#main #something`
+    ///
+    /// The order of the IDs carries no meaning and duplicates are ignored:
+    /// `add_id` keeps the list sorted and deduplicated, and equality / hashing
+    /// compare the sorted set, even if the list was modified directly.
     pub ids: Vec<String>,
     /// `.myclass .otherclass`
+    ///
+    /// Same as for `ids`, `[".a", ".b"]`, `[".b", ".a"]` and `[".a", ".b", ".a"]`
+    /// are the same node. CSS rules are applied in the order of the stylesheet,
+    /// so the order of the classes doesn't influence the style either.
     pub classes: Vec<String>,
     /// `On::MouseUp` -> `Callback(my_button_click_handler)`
     pub callbacks: Vec<(EventFilter, Callback<T>)>,
@@ -651,9 +1046,34 @@ pub struct NodeData<T: Layout> {
     /// Note that without this, there can be no `On::FocusReceived` (equivalent to onfocus),
     /// `On::FocusLost` (equivalent to onblur), etc. events.
     pub tab_index: Option<TabIndex>,
+    /// Explicit identity of the node across frames (for example the database ID of the
+    /// row that the node displays). Focus, hover state, etc. follow the node with the
+    /// same key, even if it moved in the DOM. Should be unique within the DOM, similar
+    /// to the `key` attribute in React.
+    pub key: Option<u64>,
+    /// Arbitrary `(key, value)` metadata of the node, for example the database key of
+    /// the row that the node displays, which callbacks can read back from the hit node
+    /// via `CallbackInfo::target_attribute`. Not used for styling or layout.
+    ///
+    /// `add_attribute` keeps the list sorted by key, with one value per key. Unlike the
+    /// IDs and classes, the list is compared and hashed as it is.
+    pub attributes: Vec<(String, String)>,
+    /// Cached hash of this node, see `DomHashCache`. Not part of the
+    /// equality / hash of the node itself. Not public, so that it can only
+    /// be changed by the setters, which clear it.
+    pub(crate) hash_cache: DomHashCache,
+    /// Set on the container div that `collect()` creates around the collected nodes.
+    ///
+    /// `with_class()`, `with_id()`, etc. on a collected `Dom` style this container, not
+    /// the collected nodes (use `Dom::with_class_on_children` or `Dom::map_children` for
+    /// that). `debug_dump()` and snapshots print the container as `<synthetic-root>`,
+    /// so that this is easy to spot. Part of the equality / hash of the node, since
+    /// equal nodes have to produce equal snapshots.
+    pub is_synthetic_root: bool,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde_serialization", derive(Serialize, Deserialize))]
 pub enum TabIndex {
     /// Automatic tab index, similar to simply setting `focusable = "true"` or `tabindex = 0`
     /// (both have the effect of making the element focusable).
@@ -691,13 +1111,16 @@ impl Default for TabIndex {
 impl<T: Layout> PartialEq for NodeData<T> {
     fn eq(&self, other: &Self) -> bool {
         self.node_type == other.node_type &&
-        self.ids == other.ids &&
-        self.classes == other.classes &&
+        canonical_names(&self.ids) == canonical_names(&other.ids) &&
+        canonical_names(&self.classes) == canonical_names(&other.classes) &&
         self.callbacks == other.callbacks &&
         self.default_callback_ids == other.default_callback_ids &&
         self.dynamic_css_overrides == other.dynamic_css_overrides &&
         self.draggable == other.draggable &&
-        self.tab_index == other.tab_index
+        self.tab_index == other.tab_index &&
+        self.key == other.key &&
+        self.attributes == other.attributes &&
+        self.is_synthetic_root == other.is_synthetic_root
     }
 }
 
@@ -714,30 +1137,98 @@ impl<T: Layout> Default for NodeData<T> {
             dynamic_css_overrides: Vec::new(),
             draggable: false,
             tab_index: None,
+            key: None,
+            attributes: Vec::new(),
+            hash_cache: DomHashCache::default(),
+            is_synthetic_root: false,
         }
     }
 }
 
-impl<T: Layout> Hash for NodeData<T> {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.node_type.hash(state);
-        for id in &self.ids {
-            id.hash(state);
-        }
-        for class in &self.classes {
-            class.hash(state);
+/// Parses a node without callbacks from a CSS-selector-like string: the element name
+/// (see `NodeTypePath`), followed by any number of `#id` and `.class` fragments in any
+/// order, i.e. `"div#main.container.dark"`. The text of a label is given in parentheses,
+/// i.e. `"label(Hello world).title"`. Whitespace is only allowed around the selector.
+///
+/// `img`, `texture` and `iframe` nodes can't be parsed, since they need an image or callback.
+impl<T: Layout> FromStr for NodeData<T> {
+    type Err = NodeDataParseError;
+
+    fn from_str(selector: &str) -> Result<Self, Self::Err> {
+
+        use self::NodeDataParseError::*;
+
+        fn is_valid_name_char(c: char) -> bool {
+            c.is_alphanumeric() || c == '-' || c == '_'
         }
-        for callback in &self.callbacks {
-            callback.hash(state);
+
+        let selector = selector.trim();
+        if selector.is_empty() {
+            return Err(EmptySelector);
         }
-        for default_callback_id in &self.default_callback_ids {
-            default_callback_id.hash(state);
+
+        // The element name ends at the first fragment (or the text of the label)
+        let name_end = selector.find(|c: char| c == '#' || c == '.' || c == '(').unwrap_or(selector.len());
+        let (name, mut fragments) = selector.split_at(name_end);
+
+        if let Some(invalid) = name.chars().find(|c| !is_valid_name_char(*c)) {
+            return Err(InvalidCharacter(invalid));
         }
-        for dynamic_css_override in &self.dynamic_css_overrides {
-            dynamic_css_override.hash(state);
+
+        let node_type = if name == "label" {
+            let text = if fragments.starts_with('(') {
+                // The text can contain parentheses, the last one closes the label
+                let text_end = fragments.rfind(')').ok_or(UnclosedLabel)?;
+                let text = &fragments[1..text_end];
+                fragments = &fragments[(text_end + 1)..];
+                text
+            } else {
+                ""
+            };
+            NodeType::Label(text.to_string())
+        } else {
+            match NodeTypePath::from_str(name) {
+                Ok(NodeTypePath::Div) => NodeType::Div,
+                Ok(NodeTypePath::P) => NodeType::Label(String::new()),
+                Ok(other) => return Err(NodeTypeNeedsData(other)),
+                Err(_) => return Err(InvalidNodeType(name.to_string())),
+            }
+        };
+
+        let mut node_data = NodeData::new(node_type);
+
+        while let Some(fragment_type) = fragments.chars().next() {
+            if fragment_type != '#' && fragment_type != '.' {
+                return Err(InvalidCharacter(fragment_type));
+            }
+
+            let rest = &fragments[1..];
+            let value_end = rest.find(|c: char| c == '#' || c == '.').unwrap_or(rest.len());
+            let value = &rest[..value_end];
+
+            if let Some(invalid) = value.chars().find(|c| !is_valid_name_char(*c)) {
+                return Err(InvalidCharacter(invalid));
+            }
+
+            match (fragment_type, value.is_empty()) {
+                ('#', true) => return Err(EmptyId),
+                ('#', false) => node_data.add_id(value),
+                (_, true) => return Err(EmptyClass),
+                (_, false) => node_data.add_class(value),
+            }
+
+            fragments = &rest[value_end..];
         }
-        self.draggable.hash(state);
-        self.tab_index.hash(state);
+
+        Ok(node_data)
+    }
+}
+
+/// Hashes the cached `DomHash` of the node, so that hashing an entire arena
+/// doesn't re-hash the contents of nodes that haven't changed.
+impl<T: Layout> Hash for NodeData<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.calculate_node_data_hash().hash(state);
     }
 }
 
@@ -752,6 +1243,10 @@ impl<T: Layout> Clone for NodeData<T> {
             dynamic_css_overrides: self.dynamic_css_overrides.clone(),
             draggable: self.draggable.clone(),
             tab_index: self.tab_index.clone(),
+            key: self.key,
+            attributes: self.attributes.clone(),
+            hash_cache: self.hash_cache.clone(),
+            is_synthetic_root: self.is_synthetic_root,
         }
     }
 }
@@ -759,18 +1254,22 @@ impl<T: Layout> Clone for NodeData<T> {
 impl<T: Layout> fmt::Display for NodeData<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 
-        let html_type = self.node_type.get_path();
+        let html_type = if self.is_synthetic_root {
+            format!("{} <synthetic-root>", self.node_type.get_path())
+        } else {
+            format!("{}", self.node_type.get_path())
+        };
 
         let id_string = if self.ids.is_empty() {
             String::new()
         } else {
-            self.ids.iter().map(|x| format!("#{}", x)).collect::<Vec<String>>().join(" ")
+            canonical_names(&self.ids).iter().map(|x| format!("#{}", x)).collect::<Vec<String>>().join(" ")
         };
 
         let class_string = if self.classes.is_empty() {
             String::new()
         } else {
-            self.classes.iter().map(|x| format!(".{}", x)).collect::<Vec<String>>().join(" ")
+            canonical_names(&self.classes).iter().map(|x| format!(".{}", x)).collect::<Vec<String>>().join(" ")
         };
 
         write!(f, "[{} {} {}]", html_type, id_string, class_string)
@@ -789,6 +1288,9 @@ impl<T: Layout> fmt::Debug for NodeData<T> {
                 \tdynamic_css_overrides: {:?}, \
                 \tdraggable: {:?}, \
                 \ttab_index: {:?}, \
+                \tkey: {:?}, \
+                \tattributes: {:?}, \
+                \tis_synthetic_root: {:?}, \
             }}",
         self.node_type,
         self.ids,
@@ -797,292 +1299,302 @@ impl<T: Layout> fmt::Debug for NodeData<T> {
         self.default_callback_ids,
         self.dynamic_css_overrides,
         self.draggable,
-        self.tab_index)
+        self.tab_index,
+        self.key,
+        self.attributes,
+        self.is_synthetic_root)
     }
 }
 
 impl<T: Layout> NodeData<T> {
 
+    /// Returns the hash of this node, only re-hashes the node
+    /// if it has been modified since the last call.
     pub(crate) fn calculate_node_data_hash(&self) -> DomHash {
-        use std::hash::Hash;
-
-        // Pick hash algorithm based on features
-        #[cfg(feature = "faster-hashing")]
-        use twox_hash::XxHash as HashAlgorithm;
-        #[cfg(not(feature = "faster-hashing"))]
-        use std::collections::hash_map::DefaultHasher as HashAlgorithm;
-
-        let mut hasher = HashAlgorithm::default();
-        self.hash(&mut hasher);
-        DomHash(hasher.finish())
-    }
 
-    /// Creates a new NodeData
-    pub fn new(node_type: NodeType<T>) -> Self {
-        Self {
-            node_type,
-            .. Default::default()
+        if let Some(cached_hash) = self.hash_cache.node.get() {
+            return cached_hash;
         }
-    }
 
-    /// Checks whether this node is of the given node type (div, image, text)
-    pub fn is_node_type(&self, searched_type: NodeType<T>) -> bool {
-        self.node_type == searched_type
+        let mut hasher = DomHasher::new();
+        self.hash_fields(&mut hasher);
+        let hash = DomHash(hasher.finish());
+        self.hash_cache.node.set(Some(hash));
+        hash
     }
 
-    /// Checks whether this node has the searched ID attached
-    pub fn has_id(&self, id: &str) -> bool {
-        self.ids.iter().any(|self_id| self_id == id)
+    fn hash_fields<H: Hasher>(&self, state: &mut H) {
+        match self.node_type {
+            NodeType::Label(_) => {
+                mem::discriminant(&self.node_type).hash(state);
+                self.calculate_content_hash().hash(state);
+            },
+            _ => self.node_type.hash(state),
+        }
+        for id in canonical_names(&self.ids).iter() {
+            id.hash(state);
+        }
+        for class in canonical_names(&self.classes).iter() {
+            class.hash(state);
+        }
+        for callback in &self.callbacks {
+            callback.hash(state);
+        }
+        for default_callback_id in &self.default_callback_ids {
+            default_callback_id.hash(state);
+        }
+        for dynamic_css_override in &self.dynamic_css_overrides {
+            dynamic_css_override.hash(state);
+        }
+        self.draggable.hash(state);
+        self.tab_index.hash(state);
+        self.key.hash(state);
+        self.attributes.hash(state);
+        self.is_synthetic_root.hash(state);
     }
 
-    /// Checks whether this node has the searched class attached
-    pub fn has_class(&self, class: &str) -> bool {
-        self.classes.iter().any(|self_class| self_class == class)
-    }
-}
+    /// Compares the two nodes like `==`, but without comparing the function pointers of
+    /// the callbacks: callbacks are equal if they have the same event filters (in the
+    /// same order). The same goes for the default callbacks, whose IDs depend on the
+    /// order in which they were registered. Textures and iframes are equal if they
+    /// point to the same data, no matter which callback renders them.
+    ///
+    /// Useful for tests, where DOMs built by different helper functions (i.e. with
+    /// different, but equivalent callbacks) should still compare equal.
+    pub fn eq_ignoring_callbacks(&self, other: &Self) -> bool {
 
-/// The document model, similar to HTML. This is a create-only structure, you don't actually read anything back
-#[derive(Clone, PartialEq, Eq)]
-pub struct Dom<T: Layout> {
-    pub(crate) arena: Arena<NodeData<T>>,
-    pub(crate) root: NodeId,
-    pub(crate) head: NodeId,
-}
+        use self::NodeType::*;
 
-impl<T: Layout> fmt::Debug for Dom<T> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f,
-        "Dom {{ arena: {:?}, root: {:?}, head: {:?} }}",
-        self.arena,
-        self.root,
-        self.head)
-    }
-}
+        let same_type = match (&self.node_type, &other.node_type) {
+            (GlTexture((_, a)), GlTexture((_, b))) => a == b,
+            (IFrame((_, a)), IFrame((_, b))) => a == b,
+            (a, b) => a == b,
+        };
 
-impl<T: Layout> FromIterator<Dom<T>> for Dom<T> {
-    fn from_iter<I: IntoIterator<Item=Dom<T>>>(iter: I) -> Self {
-        let mut c = Dom::new(NodeType::Div);
-        for i in iter {
-            c.add_child(i);
+        fn same_filters<A, B>(a: &[(EventFilter, A)], b: &[(EventFilter, B)]) -> bool {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|((filter_a, _), (filter_b, _))| filter_a == filter_b)
         }
-        c
-    }
-}
 
-impl<T: Layout> FromIterator<NodeData<T>> for Dom<T> {
-    fn from_iter<I: IntoIterator<Item=NodeData<T>>>(iter: I) -> Self {
+        same_type &&
+        canonical_names(&self.ids) == canonical_names(&other.ids) &&
+        canonical_names(&self.classes) == canonical_names(&other.classes) &&
+        same_filters(&self.callbacks, &other.callbacks) &&
+        same_filters(&self.default_callback_ids, &other.default_callback_ids) &&
+        self.dynamic_css_overrides == other.dynamic_css_overrides &&
+        self.draggable == other.draggable &&
+        self.tab_index == other.tab_index &&
+        self.key == other.key &&
+        self.attributes == other.attributes &&
+        self.is_synthetic_root == other.is_synthetic_root
+    }
 
-        use id_tree::Node;
+    /// Returns whether the two nodes render the same, see `Dom::content_eq`
+    fn renders_like(&self, other: &Self, strings: &FastHashMap<TextId, String>) -> bool {
 
-        // We have to use a "root" node, otherwise we run into problems if
-        // the iterator executes 0 times (and therefore pushes 0 nodes)
+        use self::NodeType::*;
 
-        // "Root" node of this DOM
-        let mut node_data = vec![NodeData::new(NodeType::Div)];
-        let mut node_layout = vec![Node {
-            parent: None,
-            previous_sibling: None,
-            next_sibling: None,
-            last_child: None,
-            first_child: None,
-        }];
+        let same_type = match (&self.node_type, &other.node_type) {
+            (Div, Div) => true,
+            (Label(a), Label(b)) => a == b,
+            (Text(a), Text(b)) => a == b || (strings.get(a).is_some() && strings.get(a) == strings.get(b)),
+            (Image(a), Image(b)) => a == b,
+            // The content of textures and iframes is only known after calling the callback
+            _ => false,
+        };
 
-        let mut idx = 0;
+        fn sorted_overrides(overrides: &[(String, CssProperty)]) -> Vec<(String, CssProperty)> {
+            let mut overrides = dedup_css_overrides(overrides).to_vec();
+            overrides.sort_by(|a, b| a.0.cmp(&b.0));
+            overrides
+        }
 
-        for item in iter {
-            let node = Node {
-                parent: Some(NodeId::new(0)),
-                previous_sibling: if idx == 0 { None } else { Some(NodeId::new(idx)) },
-                next_sibling: Some(NodeId::new(idx + 2)),
-                last_child: None,
-                first_child: None,
-            };
-            node_layout.push(node);
-            node_data.push(item);
+        same_type &&
+        canonical_names(&self.ids) == canonical_names(&other.ids) &&
+        canonical_names(&self.classes) == canonical_names(&other.classes) &&
+        sorted_overrides(&self.dynamic_css_overrides) == sorted_overrides(&other.dynamic_css_overrides)
+    }
 
-            idx += 1;
-        }
+    /// Returns the (cached) hash of the text of a `Label`, `None` for all other node types
+    fn calculate_content_hash(&self) -> Option<u64> {
 
-        let nodes_len = node_layout.len();
+        let text = match &self.node_type {
+            NodeType::Label(text) => text,
+            _ => return None,
+        };
 
-        // nodes_len is always at least 1, since we pushed the original root node
-        // Check if there is a child DOM
-        if nodes_len > 1 {
-            if let Some(last) = node_layout.get_mut(nodes_len - 1) {
-                last.next_sibling = None;
-            }
-            node_layout[0].last_child = Some(NodeId::new(nodes_len - 1));
-            node_layout[0].first_child = Some(NodeId::new(1));
+        if let Some(cached_hash) = self.hash_cache.content.get() {
+            return Some(cached_hash);
         }
 
-        Dom {
-            head: NodeId::new(0),
-            root: NodeId::new(0),
-            arena: Arena {
-                node_data: NodeDataContainer::new(node_data),
-                node_layout: NodeHierarchy::new(node_layout),
-            },
-        }
+        let mut hasher = DomHasher::new();
+        text.hash(&mut hasher);
+        let hash = hasher.finish();
+        self.hash_cache.content.set(Some(hash));
+        Some(hash)
     }
-}
 
-impl<T: Layout> FromIterator<NodeType<T>> for Dom<T> {
-    fn from_iter<I: IntoIterator<Item=NodeType<T>>>(iter: I) -> Self {
-        iter.into_iter().map(|i| NodeData { node_type: i, .. Default::default() }).collect()
+    /// Clears the cached hash of this node. Only necessary if you modified
+    /// the public fields of the node directly instead of using the setters.
+    #[inline]
+    pub fn invalidate_hash_cache(&mut self) {
+        self.hash_cache.invalidate_all();
     }
-}
 
-impl<T: Layout> Dom<T> {
+    /// Resets the node to `NodeData::default()`, but keeps the capacity of its `Vec`s
+    fn clear_for_reuse(&mut self) {
+        self.node_type = NodeType::Div;
+        self.ids.clear();
+        self.classes.clear();
+        self.callbacks.clear();
+        self.default_callback_ids.clear();
+        self.dynamic_css_overrides.clear();
+        self.draggable = false;
+        self.tab_index = None;
+        self.key = None;
+        self.attributes.clear();
+        self.hash_cache.invalidate_all();
+        self.is_synthetic_root = false;
+    }
 
-    /// Creates an empty DOM with a give `NodeType`.
-    #[inline]
+    /// Creates a new NodeData
     pub fn new(node_type: NodeType<T>) -> Self {
-        Self::with_capacity(node_type, 0)
+        let node_data = Self {
+            node_type,
+            .. Default::default()
+        };
+        node_data.calculate_content_hash();
+        node_data
     }
 
-    /// Shorthand for `Dom::new(NodeType::Div)`.
+    /// Shorthand for `NodeData::new(NodeType::Div)`
     #[inline]
     pub fn div() -> Self {
         Self::new(NodeType::Div)
     }
 
-    /// Shorthand for `Dom::new(NodeType::Label(value.into()))`
+    /// Shorthand for `NodeData::new(NodeType::Label(value.into()))`
+    #[inline]
     pub fn label<S: Into<String>>(value: S) -> Self {
         Self::new(NodeType::Label(value.into()))
     }
 
-    /// Shorthand for `Dom::new(NodeType::Text(text_id))`
-    #[inline]
-    pub fn text_id(text_id: TextId) -> Self {
-        Self::new(NodeType::Text(text_id))
-    }
-
-    /// Shorthand for `Dom::new(NodeType::Image(image_id))`
+    /// Shorthand for `NodeData::new(NodeType::Image(image_id))`
     #[inline]
     pub fn image(image: ImageId) -> Self {
         Self::new(NodeType::Image(image))
     }
 
-    /// Shorthand for `Dom::new(NodeType::GlTexture((callback, ptr)))`
+    /// Replaces the node type, pre-hashes the text if the new node is a `Label`
     #[inline]
-    pub fn gl_texture(callback: GlTextureCallback<T>, ptr: StackCheckedPointer<T>) -> Self {
-        Self::new(NodeType::GlTexture((callback, ptr)))
+    pub fn set_node_type(&mut self, node_type: NodeType<T>) {
+        self.node_type = node_type;
+        self.hash_cache.invalidate_all();
+        self.calculate_content_hash();
     }
 
-    /// Shorthand for `Dom::new(NodeType::IFrame((callback, ptr)))`
+    /// Adds an ID to the node, keeps the IDs sorted, ignores duplicates
     #[inline]
-    pub fn iframe(callback: IFrameCallback<T>, ptr: StackCheckedPointer<T>) -> Self {
-        Self::new(NodeType::IFrame((callback, ptr)))
+    pub fn add_id<S: Into<String>>(&mut self, id: S) {
+        if insert_canonical_name(&mut self.ids, id.into()) {
+            self.hash_cache.invalidate_node();
+        }
     }
 
-    /// Returns the number of nodes in this DOM
+    /// Adds a class to the node, keeps the classes sorted, ignores duplicates
     #[inline]
-    pub fn len(&self) -> usize {
-        self.arena.len()
+    pub fn add_class<S: Into<String>>(&mut self, class: S) {
+        if insert_canonical_name(&mut self.classes, class.into()) {
+            self.hash_cache.invalidate_node();
+        }
     }
 
-    /// Creates an empty DOM with space reserved for `cap` nodes
     #[inline]
-    pub fn with_capacity(node_type: NodeType<T>, cap: usize) -> Self {
-        let mut arena = Arena::with_capacity(cap.saturating_add(1));
-        let root = arena.new_node(NodeData::new(node_type));
-        Self {
-            arena: arena,
-            root: root,
-            head: root,
-        }
+    pub fn add_callback<O: Into<EventFilter>>(&mut self, on: O, callback: Callback<T>) {
+        self.callbacks.push((on.into(), callback));
+        self.hash_cache.invalidate_node();
     }
 
-    /// Adds a child DOM to the current DOM
-    pub fn add_child(&mut self, mut child: Self) {
-
-        // Note: for a more readable Python version of this algorithm,
-        // see: https://gist.github.com/fschutt/4b3bd9a2654b548a6eb0b6a8623bdc8a#file-dow_new_2-py-L65-L107
-
-        let self_len = self.arena.len();
-        let child_len = child.arena.len();
-
-        if child_len == 0 {
-            // No nodes to append, nothing to do
-            return;
-        }
-
-        if self_len == 0 {
-            // Self has no nodes, therefore all child nodes will
-            // replace the self nodes, so
-            *self = child;
-            return;
-        }
-
-        let self_arena = &mut self.arena;
-        let child_arena = &mut child.arena;
+    #[inline]
+    pub fn add_default_callback_id<O: Into<EventFilter>>(&mut self, on: O, id: DefaultCallbackId) {
+        self.default_callback_ids.push((on.into(), id));
+        self.hash_cache.invalidate_node();
+    }
 
-        let mut last_sibling = None;
+    #[inline]
+    pub fn add_css_override<S: Into<String>>(&mut self, override_id: S, property: CssProperty) {
+        self.dynamic_css_overrides.push((override_id.into(), property));
+        self.hash_cache.invalidate_node();
+    }
 
-        for node_id in 0..child_len {
-            let node_id = NodeId::new(node_id);
-            let node_id_child: &mut Node = &mut child_arena.node_layout[node_id];
+    #[inline]
+    pub fn set_draggable(&mut self, draggable: bool) {
+        self.draggable = draggable;
+        self.hash_cache.invalidate_node();
+    }
 
-            // WARNING: Order of these blocks is important!
+    #[inline]
+    pub fn set_tab_index(&mut self, tab_index: Option<TabIndex>) {
+        self.tab_index = tab_index;
+        self.hash_cache.invalidate_node();
+    }
 
-            if node_id_child.previous_sibling.as_mut().and_then(|previous_sibling| {
-                // Some(previous_sibling) - increase the parent ID by the current arena length
-                *previous_sibling += self_len;
-                Some(previous_sibling)
-            }).is_none() {
-                // None - set the current heads' last child as the new previous sibling
-                let last_child = self_arena.node_layout[self.head].last_child;
-                if last_child.is_some() && node_id_child.parent.is_none() {
-                    node_id_child.previous_sibling = last_child;
-                    self_arena.node_layout[last_child.unwrap()].next_sibling = Some(node_id + self_len);
-                }
-            }
+    #[inline]
+    pub fn set_key(&mut self, key: Option<u64>) {
+        self.key = key;
+        self.hash_cache.invalidate_node();
+    }
 
-            if node_id_child.parent.as_mut().and_then(|parent| {
-                *parent += self_len;
-                Some(parent)
-            }).is_none() {
-                // Have we encountered the last root item?
-                if node_id_child.next_sibling.is_none() {
-                    last_sibling = Some(node_id);
+    /// Sets the attribute `key` to the `value`, replaces the previous value of the `key`
+    pub fn add_attribute<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) {
+        let (key, value) = (key.into(), value.into());
+        match self.attributes.binary_search_by(|(existing, _)| existing.as_str().cmp(&key)) {
+            Ok(position) => {
+                if self.attributes[position].1 == value {
+                    return;
                 }
-                node_id_child.parent = Some(self.head);
-            }
-
-            if let Some(next_sibling) = node_id_child.next_sibling.as_mut() {
-                *next_sibling += self_len;
-            }
-
-            if let Some(first_child) = node_id_child.first_child.as_mut() {
-                *first_child += self_len;
-            }
-
-            if let Some(last_child) = node_id_child.last_child.as_mut() {
-                *last_child += self_len;
-            }
+                self.attributes[position].1 = value;
+            },
+            Err(position) => self.attributes.insert(position, (key, value)),
         }
+        self.hash_cache.invalidate_node();
+    }
 
-        self_arena.node_layout[self.head].first_child.get_or_insert(NodeId::new(self_len));
-        self_arena.node_layout[self.head].last_child = Some(last_sibling.unwrap() + self_len);
+    /// Same as `add_attribute`, but easier to use for method chaining in a builder-style pattern
+    #[inline]
+    pub fn with_attribute<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.add_attribute(key, value);
+        self
+    }
 
-        (&mut *self_arena).append_arena(child_arena);
+    /// Returns the value of the attribute `key`, see `NodeData::attributes`
+    pub fn get_attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.iter().find(|(k, _)| k == key).map(|(_, value)| value.as_str())
     }
 
-    /// Same as `id`, but easier to use for method chaining in a builder-style pattern
+    /// Same as `add_id`, but easier to use for method chaining in a builder-style pattern
     #[inline]
     pub fn with_id<S: Into<String>>(mut self, id: S) -> Self {
         self.add_id(id);
         self
     }
 
-    /// Same as `id`, but easier to use for method chaining in a builder-style pattern
+    /// Same as `add_class`, but easier to use for method chaining in a builder-style pattern
     #[inline]
     pub fn with_class<S: Into<String>>(mut self, class: S) -> Self {
         self.add_class(class);
         self
     }
 
-    /// Same as `event`, but easier to use for method chaining in a builder-style pattern
+    /// Adds all classes, see `add_class`
+    #[inline]
+    pub fn with_classes(mut self, classes: Vec<String>) -> Self {
+        for class in classes {
+            self.add_class(class);
+        }
+        self
+    }
+
+    /// Same as `add_callback`, but easier to use for method chaining in a builder-style pattern
     #[inline]
     pub fn with_callback<O: Into<EventFilter>>(mut self, on: O, callback: Callback<T>) -> Self {
         self.add_callback(on, callback);
@@ -1090,8 +1602,14 @@ impl<T: Layout> Dom<T> {
     }
 
     #[inline]
-    pub fn with_child(mut self, child: Self) -> Self {
-        self.add_child(child);
+    pub fn with_tab_index(mut self, tab_index: TabIndex) -> Self {
+        self.set_tab_index(Some(tab_index));
+        self
+    }
+
+    #[inline]
+    pub fn is_draggable(mut self, draggable: bool) -> Self {
+        self.set_draggable(draggable);
         self
     }
 
@@ -1101,488 +1619,5538 @@ impl<T: Layout> Dom<T> {
         self
     }
 
+    /// Checks whether this node is of the given node type, including the content
+    /// (the text of a label, the ID of an image, etc.).
+    ///
+    /// To only check the kind of node, use `is_div()`, `is_label()`, `is_image()`, etc.
+    /// or `node_type_path()` instead, which don't need a `NodeType` to compare against.
+    pub fn is_node_type(&self, searched_type: NodeType<T>) -> bool {
+        self.node_type == searched_type
+    }
+
+    /// Returns the kind of the node, as it is used in CSS selectors (`div`, `p`, `img`, ...)
     #[inline]
-    pub fn with_tab_index(mut self, tab_index: TabIndex) -> Self {
-        self.add_tab_index(tab_index);
-        self
+    pub fn node_type_path(&self) -> NodeTypePath {
+        self.node_type.get_path()
     }
 
+    /// Returns whether the node is a `NodeType::Div`
     #[inline]
-    pub fn is_draggable(mut self, draggable: bool) -> Self {
-        self.set_draggable(draggable);
-        self
+    pub fn is_div(&self) -> bool {
+        match self.node_type { NodeType::Div => true, _ => false }
     }
 
+    /// Returns whether the node is a `NodeType::Label`
     #[inline]
-    pub fn add_id<S: Into<String>>(&mut self, id: S) {
-        self.arena.node_data[self.head].ids.push(id.into());
+    pub fn is_label(&self) -> bool {
+        match self.node_type { NodeType::Label(_) => true, _ => false }
     }
 
+    /// Returns whether the node is a `NodeType::Text`
     #[inline]
-    pub fn add_class<S: Into<String>>(&mut self, class: S) {
-        self.arena.node_data[self.head].classes.push(class.into());
+    pub fn is_text(&self) -> bool {
+        match self.node_type { NodeType::Text(_) => true, _ => false }
     }
 
+    /// Returns whether the node is a `NodeType::Image`
     #[inline]
-    pub fn add_callback<O: Into<EventFilter>>(&mut self, on: O, callback: Callback<T>) {
-        self.arena.node_data[self.head].callbacks.push((on.into(), callback));
+    pub fn is_image(&self) -> bool {
+        match self.node_type { NodeType::Image(_) => true, _ => false }
     }
 
+    /// Returns whether the node is a `NodeType::GlTexture`
     #[inline]
-    pub fn add_default_callback_id<O: Into<EventFilter>>(&mut self, on: O, id: DefaultCallbackId) {
-        self.arena.node_data[self.head].default_callback_ids.push((on.into(), id));
+    pub fn is_gl_texture(&self) -> bool {
+        match self.node_type { NodeType::GlTexture(_) => true, _ => false }
     }
 
+    /// Returns whether the node is a `NodeType::IFrame`
     #[inline]
-    pub fn add_tab_index(&mut self, tab_index: TabIndex) {
-        self.arena.node_data[self.head].tab_index = Some(tab_index);
+    pub fn is_iframe(&self) -> bool {
+        match self.node_type { NodeType::IFrame(_) => true, _ => false }
     }
 
+    /// Returns the text of a `Label`, `None` for all other node types
     #[inline]
-    pub fn add_css_override<S: Into<String>>(&mut self, override_id: S, property: CssProperty) {
-        self.arena.node_data[self.head].dynamic_css_overrides.push((override_id.into(), property));
+    pub fn get_label_text(&self) -> Option<&str> {
+        match &self.node_type { NodeType::Label(text) => Some(text.as_str()), _ => None }
     }
 
+    /// Returns the ID of an `Image`, `None` for all other node types
     #[inline]
-    pub fn set_draggable(&mut self, draggable: bool) {
-        self.arena.node_data[self.head].draggable = draggable;
+    pub fn get_image_id(&self) -> Option<ImageId> {
+        match self.node_type { NodeType::Image(id) => Some(id), _ => None }
     }
 
-    /// Prints a debug formatted version of the DOM for easier debugging
-    pub fn debug_dump(&self) {
-        println!("{}", self.arena.print_tree(|t| format!("{}", t)));
+    /// Returns the ID of a `Text`, `None` for all other node types
+    #[inline]
+    pub fn get_text_id(&self) -> Option<TextId> {
+        match self.node_type { NodeType::Text(id) => Some(id), _ => None }
     }
 
-    /// The UiState contains all the tags (for hit-testing) as well as the mapping
-    /// from Hit-testing tags to NodeIds (which are important for filtering input events
-    /// and routing input events to the callbacks).
-    pub(crate) fn into_ui_state(self) -> UiState<T> {
+    /// Returns the callback of a `GlTexture`, `None` for all other node types.
+    /// Two callbacks are equal if they point to the same function.
+    #[inline]
+    pub fn get_gl_texture_callback(&self) -> Option<GlTextureCallback<T>> {
+        match &self.node_type { NodeType::GlTexture((callback, _)) => Some(*callback), _ => None }
+    }
 
-        // NOTE: Originally it was allowed to create a DOM with
-        // multiple root elements using `add_sibling()` and `with_sibling()`.
-        //
-        // However, it was decided to remove these functions (in commit #586933),
-        // as they aren't practical (you can achieve the same thing with one
-        // wrapper div and multiple add_child() calls) and they create problems
-        // when layouting elements since add_sibling() essentially modifies the
-        // space that the parent can distribute, which in code, simply looks weird
-        // and led to bugs.
-        //
-        // It is assumed that the DOM returned by the user has exactly one root node
-        // with no further siblings and that the root node is the Node with the ID 0.
+    /// Returns the callback of an `IFrame`, `None` for all other node types.
+    /// Two callbacks are equal if they point to the same function.
+    #[inline]
+    pub fn get_iframe_callback(&self) -> Option<IFrameCallback<T>> {
+        match &self.node_type { NodeType::IFrame((callback, _)) => Some(*callback), _ => None }
+    }
 
-        // All tags that have can be focused (necessary for hit-testing)
-        let mut tab_index_tags = BTreeMap::new();
-        // All tags that have can be dragged & dropped (necessary for hit-testing)
-        let mut draggable_tags = BTreeMap::new();
+    /// Checks whether this node has the searched ID attached
+    pub fn has_id(&self, id: &str) -> bool {
+        self.ids.iter().any(|self_id| self_id == id)
+    }
 
-        // Mapping from tags to nodes (necessary so that the hit-testing can resolve the NodeId from any given tag)
-        let mut tag_ids_to_node_ids = BTreeMap::new();
-        // Mapping from nodes to tags, reverse mapping (not used right now, may be useful in the future)
-        let mut node_ids_to_tag_ids = BTreeMap::new();
-        // Which nodes have extra dynamic CSS overrides?
-        let mut dynamic_css_overrides = BTreeMap::new();
+    /// Checks whether this node has the searched class attached
+    pub fn has_class(&self, class: &str) -> bool {
+        self.classes.iter().any(|self_class| self_class == class)
+    }
+}
 
-        let mut hover_callbacks = BTreeMap::new();
-        let mut hover_default_callbacks = BTreeMap::new();
-        let mut focus_callbacks = BTreeMap::new();
-        let mut focus_default_callbacks = BTreeMap::new();
-        let mut not_callbacks = BTreeMap::new();
-        let mut not_default_callbacks = BTreeMap::new();
-        let mut window_callbacks = BTreeMap::new();
-        let mut window_default_callbacks = BTreeMap::new();
-        let mut desktop_callbacks = BTreeMap::new();
-        let mut desktop_default_callbacks = BTreeMap::new();
+/// Inserts the `name` into the sorted `names`, returns `false` if it was already present
+fn insert_canonical_name(names: &mut Vec<String>, name: String) -> bool {
+    if names.last().map(|last| *last < name).unwrap_or(true) {
+        names.push(name);
+        return true;
+    }
+    match names.binary_search(&name) {
+        Ok(_) => false,
+        Err(position) => { names.insert(position, name); true },
+    }
+}
 
-        // data.callbacks, HoverEventFilter, Callback<T>, as_hover_event_filter, hover_callbacks, <node_tag_id> (optional)
-        macro_rules! filter_and_insert_callbacks {
-            (
-                    $node_id:ident,
-                    $data_source:expr,
-                    $event_filter:ident,
-                    $callback_type:ty,
-                    $filter_func:ident,
-                    $final_callback_list:ident,
-            ) => {
-                let node_hover_callbacks: BTreeMap<$event_filter, $callback_type> = $data_source.iter()
-                .filter_map(|(event_filter, cb)| event_filter.$filter_func().map(|not_evt| (not_evt, *cb)))
-                .collect();
+/// Returns the IDs or classes sorted and deduplicated. Doesn't allocate if they
+/// already are (which is the case unless the public fields were modified directly).
+pub(crate) fn canonical_names(names: &[String]) -> Cow<[String]> {
+    if names.windows(2).all(|pair| pair[0] < pair[1]) {
+        Cow::Borrowed(names)
+    } else {
+        let mut sorted = names.to_vec();
+        sorted.sort();
+        sorted.dedup();
+        Cow::Owned(sorted)
+    }
+}
 
-                if !node_hover_callbacks.is_empty() {
-                    $final_callback_list.insert($node_id, node_hover_callbacks);
-                }
-            };
-            (
-                $node_id:ident,
-                $data_source:expr,
-                $event_filter:ident,
-                $callback_type:ty,
-                $filter_func:ident,
-                $final_callback_list:ident,
-                $node_tag_id:ident,
-            ) => {
-                let node_hover_callbacks: BTreeMap<$event_filter, $callback_type> = $data_source.iter()
-                .filter_map(|(event_filter, cb)| event_filter.$filter_func().map(|not_evt| (not_evt, *cb)))
-                .collect();
+/// Keeps the arena buffers and the nodes of the DOM of the last frame alive, so that
+/// the next frames DOM can re-use the memory instead of allocating it again.
+///
+/// Re-using the memory is opt-in: only DOMs created via `Dom::with_capacity_in()` use
+/// the recycled arena buffers and only nodes created via `DomAllocator::node()` use the
+/// recycled `Vec`s of the nodes (ids, classes, callbacks, etc.), all other constructors
+/// allocate normally. Strings (i.e. the text of labels or the class names) are always
+/// allocated again. The buffers are always cleared before they are re-used, only their
+/// capacity (never their content) survives from one frame to the next.
+pub struct DomAllocator<T: Layout> {
+    node_layout: Vec<Node>,
+    node_data: ArenaNodeData<NodeData<T>>,
+    /// Cleared nodes of the last frame, at most as many as the last frame had
+    spare_nodes: Vec<NodeData<T>>,
+}
 
-                if !node_hover_callbacks.is_empty() {
-                    $final_callback_list.insert($node_id, node_hover_callbacks);
-                    let tag_id = $node_tag_id.unwrap_or_else(|| new_tag_id());
-                    $node_tag_id = Some(tag_id);
-                }
-            };
+impl<T: Layout> Default for DomAllocator<T> {
+    fn default() -> Self {
+        Self {
+            node_layout: Vec::new(),
+            node_data: ArenaNodeData::default(),
+            spare_nodes: Vec::new(),
         }
+    }
+}
 
-        // Reset the tag
-        TAG_ID.swap(1, Ordering::SeqCst);
+/// Cloning an allocator does not clone the recycled buffers, the clone starts out empty.
+impl<T: Layout> Clone for DomAllocator<T> {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
 
-        {
-            let arena = &self.arena;
+impl<T: Layout> fmt::Debug for DomAllocator<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+            "DomAllocator {{ node_layout_capacity: {}, node_data_capacity: {}, spare_nodes: {} }}",
+            self.node_layout.capacity(),
+            self.node_data.capacity(),
+            self.spare_nodes.len(),
+        )
+    }
+}
 
-            debug_assert!(arena.node_layout[NodeId::new(0)].next_sibling.is_none());
+impl<T: Layout> DomAllocator<T> {
 
-            for node_id in arena.linear_iter() {
+    /// Creates an empty allocator, with no buffers to re-use
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-                let data = &arena.node_data[node_id];
+    /// Returns how many nodes can be stored in the recycled buffers without re-allocating
+    pub fn capacity(&self) -> usize {
+        self.node_layout.capacity().min(self.node_data.capacity())
+    }
 
-                let mut node_tag_id = None;
+    /// Returns how many cleared nodes of the last frame can be handed out by `node()`
+    pub fn spare_nodes(&self) -> usize {
+        self.spare_nodes.len()
+    }
 
-                // Optimization since on most nodes, the callbacks will be empty
-                if !data.callbacks.is_empty() {
+    /// Same as `NodeData::new(node_type)`, but re-uses the `Vec`s of a node
+    /// of the last frame, if there are any left
+    pub fn node(&mut self, node_type: NodeType<T>) -> NodeData<T> {
+        match self.spare_nodes.pop() {
+            Some(mut node) => {
+                node.node_type = node_type;
+                node.calculate_content_hash();
+                node
+            },
+            None => NodeData::new(node_type),
+        }
+    }
 
-                    // Filter and insert HoverEventFilter callbacks
-                    filter_and_insert_callbacks!(
-                        node_id,
-                        data.callbacks,
-                        HoverEventFilter,
-                        Callback<T>,
-                        as_hover_event_filter,
-                        hover_callbacks,
-                        node_tag_id,
-                    );
+    /// Clears the contents of the DOM, but keeps its buffers and its (cleared) nodes
+    /// for the next frame. The buffers are only kept if they are larger than the
+    /// buffers that are already stored.
+    pub(crate) fn recycle(&mut self, dom: Dom<T>) {
+        let Arena { node_layout, mut node_data } = dom.arena;
+
+        let mut node_layout = node_layout.internal;
+
+        node_layout.clear();
+
+        // Keep exactly as many spare nodes as the last frame had nodes, so that
+        // the spare nodes don't pile up if the DOM isn't built with `node()`.
+        // Nodes of a `SharedDom` aren't recycled, they belong to the `SharedDom`.
+        let node_count = node_data.len();
+        self.spare_nodes.truncate(node_count);
+        let missing_nodes = node_count - self.spare_nodes.len();
+        self.spare_nodes.extend(node_data.drain_owned().take(missing_nodes).map(|mut node| {
+            node.clear_for_reuse();
+            node
+        }));
+
+        if node_layout.capacity() > self.node_layout.capacity() {
+            self.node_layout = node_layout;
+        }
+        if node_data.capacity() > self.node_data.capacity() {
+            self.node_data = node_data;
+        }
+    }
 
-                    // Filter and insert FocusEventFilter callbacks
-                    filter_and_insert_callbacks!(
-                        node_id,
-                        data.callbacks,
-                        FocusEventFilter,
-                        Callback<T>,
-                        as_focus_event_filter,
-                        focus_callbacks,
-                        node_tag_id,
-                    );
+    /// Hands out the recycled (empty) arena buffers, the spare nodes stay in the allocator
+    fn take_arena(&mut self, cap: usize) -> Arena<NodeData<T>> {
+        let mut node_layout = mem::replace(&mut self.node_layout, Vec::new());
+        let mut node_data = mem::replace(&mut self.node_data, ArenaNodeData::default());
+        node_layout.reserve(cap);
+        node_data.reserve(cap);
+        Arena {
+            node_layout: NodeHierarchy::new(node_layout),
+            node_data,
+        }
+    }
+}
 
-                    filter_and_insert_callbacks!(
-                        node_id,
-                        data.callbacks,
-                        NotEventFilter,
-                        Callback<T>,
-                        as_not_event_filter,
-                        not_callbacks,
-                        node_tag_id,
-                    );
+/// The document model, similar to HTML. The DOM is usually only built and then handed
+/// to the framework, but nodes can be looked up again with `get_node_by_id`
+/// (for example to attach a default callback to a node of a composed widget)
+pub struct Dom<T: Layout> {
+    pub(crate) arena: Arena<NodeData<T>>,
+    pub(crate) root: NodeId,
+    pub(crate) head: NodeId,
+}
 
-                    filter_and_insert_callbacks!(
-                        node_id,
-                        data.callbacks,
-                        WindowEventFilter,
-                        Callback<T>,
-                        as_window_event_filter,
-                        window_callbacks,
-                    );
+// #[derive(Clone)] for Dom<T>
+impl<T: Layout> Clone for Dom<T> {
+    fn clone(&self) -> Self {
+        Self {
+            arena: self.arena.clone(),
+            root: self.root,
+            head: self.head,
+        }
+    }
+}
 
-                    filter_and_insert_callbacks!(
-                        node_id,
-                        data.callbacks,
-                        DesktopEventFilter,
-                        Callback<T>,
-                        as_desktop_event_filter,
-                        desktop_callbacks,
-                    );
-                }
+/// Several sibling DOMs without a common parent, for widgets that return more than one
+/// node (for example a label and its input). A `Dom` always has exactly one root, so a
+/// fragment can't be rendered on its own - it can only be added to a `Dom` with
+/// `Dom::add_fragment`, which makes the roots children of the current node, without
+/// a wrapper node that would take part in the layout.
+pub struct DomFragment<T: Layout> {
+    roots: Vec<Dom<T>>,
+}
 
-                if !data.default_callback_ids.is_empty() {
+// #[derive(Debug, Clone, PartialEq, Eq)] for DomFragment<T>, without requiring the bounds on T
+impl<T: Layout> fmt::Debug for DomFragment<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DomFragment {{ roots: {:?} }}", self.roots)
+    }
+}
 
-                    // Filter and insert HoverEventFilter callbacks
-                    filter_and_insert_callbacks!(
-                        node_id,
-                        data.default_callback_ids,
-                        HoverEventFilter,
-                        DefaultCallbackId,
-                        as_hover_event_filter,
-                        hover_default_callbacks,
-                        node_tag_id,
-                    );
+impl<T: Layout> Clone for DomFragment<T> {
+    fn clone(&self) -> Self {
+        Self { roots: self.roots.clone() }
+    }
+}
+
+impl<T: Layout> PartialEq for DomFragment<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.roots == other.roots
+    }
+}
+
+impl<T: Layout> Eq for DomFragment<T> { }
+
+impl<T: Layout> Default for DomFragment<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Layout> DomFragment<T> {
+
+    /// Creates an empty fragment
+    pub fn new() -> Self {
+        Self { roots: Vec::new() }
+    }
+
+    /// Appends a root after the existing roots
+    pub fn add_root(&mut self, root: Dom<T>) {
+        self.roots.push(root);
+    }
+
+    /// Same as `add_root`, but easier to use for method chaining in a builder-style pattern
+    pub fn with_root(mut self, root: Dom<T>) -> Self {
+        self.add_root(root);
+        self
+    }
+
+    /// Returns the number of roots (not nodes) of the fragment
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+}
+
+impl<T: Layout> FromIterator<Dom<T>> for DomFragment<T> {
+    fn from_iter<I: IntoIterator<Item=Dom<T>>>(iter: I) -> Self {
+        Self { roots: iter.into_iter().collect() }
+    }
+}
+
+/// An immutable DOM that is built once and added to the DOM of every frame, for example
+/// a large sidebar that never changes, see `Dom::shared` and `Dom::add_shared_child`.
+///
+/// Cloning a `SharedDom` only clones the `Rc`. The nodes are never copied when the `SharedDom`
+/// is added to a `Dom`: the arena of the `Dom` only references them (and keeps referencing
+/// them in the `UiState` of the frame), only the links between the nodes are copied. A
+/// shared node is copied into the arena of the `Dom` the first time it is modified (via
+/// `get_node_data_mut`, `mutate_nodes_by_class`, etc.), so the `SharedDom` itself is never
+/// modified. The hashes of the nodes are calculated once, when the `SharedDom` is created.
+pub struct SharedDom<T: Layout> {
+    dom: Rc<Dom<T>>,
+}
+
+impl<T: Layout> fmt::Debug for SharedDom<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SharedDom {{ dom: {:?} }}", self.dom)
+    }
+}
+
+impl<T: Layout> Clone for SharedDom<T> {
+    fn clone(&self) -> Self {
+        Self { dom: self.dom.clone() }
+    }
+}
+
+impl<T: Layout> PartialEq for SharedDom<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.dom, &other.dom) || self.dom == other.dom
+    }
+}
+
+impl<T: Layout> Eq for SharedDom<T> { }
+
+impl<T: Layout> SharedDom<T> {
+
+    /// Returns the shared DOM, for reading
+    #[inline]
+    pub fn get_dom(&self) -> &Dom<T> {
+        &self.dom
+    }
+
+    /// Number of nodes that `Dom::add_shared_child` appends
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.dom.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.dom.len() == 0
+    }
+}
+
+/// Semantic equality: two DOMs are equal if they have the same structure and all
+/// nodes are equal, where
+///
+/// - ids and classes are compared as sets (see `NodeData::classes`)
+/// - callbacks, `GlTexture` and `IFrame` nodes are compared by the identity of their
+///   function and data pointers, so a different (but equivalent) function is a different
+///   node, and a texture whose content changed is still the same node
+/// - labels are compared by their text, `TextId`s and `ImageId`s by their ID
+/// - cached hashes are ignored
+///
+/// The cached hashes of the nodes are compared first, so that unequal DOMs are usually
+/// rejected without comparing any strings, equal hashes are verified by comparing
+/// the nodes. This is the equality that decides whether a frame can re-use the
+/// display list of the last frame (DOMs with textures or iframes never do, since their
+/// content can change without the DOM changing). Use `content_eq` to compare
+/// what the DOMs would render instead.
+impl<T: Layout> PartialEq for Dom<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.root == other.root &&
+        self.head == other.head &&
+        self.arena.len() == other.arena.len() &&
+        self.calculate_dom_hash() == other.calculate_dom_hash() &&
+        self.arena == other.arena
+    }
+}
+
+impl<T: Layout> Eq for Dom<T> { }
+
+impl<T: Layout> fmt::Debug for Dom<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+        "Dom {{ arena: {:?}, root: {:?}, head: {:?} }}",
+        self.arena,
+        self.root,
+        self.head)
+    }
+}
+
+/// Number of nodes per `NodeType`, nodes per kind of event filter, callbacks and
+/// CSS overrides of a `Dom`, see `Dom::statistics()`
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DomStatistics {
+    /// Number of nodes that can be reached from the root
+    pub nodes: usize,
+    pub divs: usize,
+    pub labels: usize,
+    pub texts: usize,
+    pub images: usize,
+    pub gl_textures: usize,
+    pub iframes: usize,
+    /// Number of nodes with at least one (regular or default) hover callback
+    pub nodes_with_hover_callbacks: usize,
+    /// Number of nodes with at least one (regular or default) focus callback
+    pub nodes_with_focus_callbacks: usize,
+    /// Number of nodes with at least one (regular or default) `Not` callback
+    pub nodes_with_not_callbacks: usize,
+    /// Number of nodes with at least one (regular or default) window callback
+    pub nodes_with_window_callbacks: usize,
+    /// Number of nodes with at least one (regular or default) desktop callback
+    pub nodes_with_desktop_callbacks: usize,
+    /// Number of registered callbacks, regular and default callbacks of all nodes
+    pub callbacks: usize,
+    /// Number of dynamic CSS overrides of all nodes
+    pub dynamic_css_overrides: usize,
+    /// Depth of the deepest node, the root has a depth of 0
+    pub max_depth: usize,
+}
+
+impl fmt::Display for DomStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+            "{} nodes ({} divs, {} labels, {} texts, {} images, {} GL textures, {} iframes), max depth {}, \
+             {} callbacks (nodes with hover: {}, focus: {}, not: {}, window: {}, desktop: {}), {} CSS overrides",
+            self.nodes, self.divs, self.labels, self.texts, self.images, self.gl_textures, self.iframes, self.max_depth,
+            self.callbacks,
+            self.nodes_with_hover_callbacks,
+            self.nodes_with_focus_callbacks,
+            self.nodes_with_not_callbacks,
+            self.nodes_with_window_callbacks,
+            self.nodes_with_desktop_callbacks,
+            self.dynamic_css_overrides)
+    }
+}
+
+/// Broken structure of a `Dom`, returned by `Dom::validate()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DomError {
+    /// The `node` refers to the `target` node, which is outside of the arena
+    OutOfBounds { node: NodeId, target: NodeId },
+    /// The root isn't the first node of the arena
+    RootNotFirst { root: NodeId },
+    /// The root has a parent
+    RootHasParent { root: NodeId },
+    /// The root has a sibling, i.e. the DOM has more than one root
+    MultipleRoots { root: NodeId, sibling: NodeId },
+    /// The node isn't linked back to its previous sibling (or is linked to a wrong one)
+    BrokenSiblingLink { node: NodeId },
+    /// The `last_child` of the `parent` isn't the last node of its chain of children
+    BrokenLastChild { parent: NodeId },
+    /// The `node` is in the chain of children of `expected`, but has a different parent
+    WrongParent { node: NodeId, expected: NodeId },
+    /// The node is reached a second time when walking the tree from the root
+    Cycle { node: NodeId },
+    /// The node can't be reached from the root
+    Unreachable { node: NodeId },
+    /// The node doesn't exist in the DOM
+    NodeNotFound { node: NodeId },
+    /// The root can't be removed, see `Dom::remove_subtree`
+    CannotRemoveRoot { root: NodeId },
+    /// The new order isn't a permutation of the children of the `parent`, see `Dom::reorder_children`
+    InvalidChildOrder { parent: NodeId },
+    /// The `parent` has no child at the `index`, see `Dom::swap_children`
+    ChildIndexOutOfRange { parent: NodeId, index: usize },
+}
+
+impl_display! {
+    DomError,
+    {
+        OutOfBounds { node, target } => format!("Node {} refers to node {}, which is out of bounds", node, target),
+        RootNotFirst { root } => format!("The root {} isn't the first node", root),
+        RootHasParent { root } => format!("The root {} has a parent", root),
+        MultipleRoots { root, sibling } => format!("The root {} has a sibling {}", root, sibling),
+        BrokenSiblingLink { node } => format!("Node {} isn't linked to its previous sibling", node),
+        BrokenLastChild { parent } => format!("The last child of node {} isn't the end of its children", parent),
+        WrongParent { node, expected } => format!("Node {} is a child of node {}, but has a different parent", node, expected),
+        Cycle { node } => format!("Node {} is reached twice", node),
+        Unreachable { node } => format!("Node {} can't be reached from the root", node),
+        NodeNotFound { node } => format!("Node {} doesn't exist", node),
+        CannotRemoveRoot { root } => format!("The root {} can't be removed", root),
+        InvalidChildOrder { parent } => format!("The new order isn't a permutation of the children of node {}", parent),
+        ChildIndexOutOfRange { parent, index } => format!("Node {} has no child at index {}", parent, index),
+    }
+}
+
+/// Maximum number of nodes of a DOM that `Dom::try_add_child` (and `Dom::add_child` in
+/// debug builds) allows by default, 2^24 nodes. The `NodeData` of that many nodes alone
+/// takes several gigabytes and can't be laid out at interactive frame rates, so a DOM
+/// this large is almost certainly the result of a bug, for example a procedural generator
+/// that doesn't terminate. Use `Dom::try_add_child_with_limit` for a different limit.
+pub const DEFAULT_MAX_DOM_NODES: usize = 1 << 24;
+
+/// Error returned by `Dom::try_add_child`, the DOM isn't changed
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DomCapacityError {
+    /// Appending the `added` nodes to the `current` nodes would exceed the `max` number of nodes
+    TooManyNodes { current: usize, added: usize, max: usize },
+    /// The node of the child DOM links to a node that is outside of the child DOM
+    LinkOutOfBounds { node: NodeId },
+}
+
+impl_display! {
+    DomCapacityError,
+    {
+        TooManyNodes { current, added, max } => format!("Can't append {} nodes to a DOM with {} nodes, the maximum is {} nodes", added, current, max),
+        LinkOutOfBounds { node } => format!("Node {} of the appended DOM links to a node outside of it", node),
+    }
+}
+
+/// Error returned by `Dom::fill_slot`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SlotError {
+    /// No node of the DOM has the ID of the slot
+    NotFound { slot_id: String },
+    /// More than one node has the ID of the slot, so it isn't clear which one should be filled
+    Ambiguous { slot_id: String, nodes: Vec<NodeId> },
+}
+
+impl_display! {
+    SlotError,
+    {
+        NotFound { slot_id } => format!("No node has the slot ID \"{}\"", slot_id),
+        Ambiguous { slot_id, nodes } => format!("{} nodes have the slot ID \"{}\"", nodes.len(), slot_id),
+    }
+}
+
+/// Class of the slot nodes of a template that is removed when the slot is filled, see `Dom::fill_slot`
+pub const SLOT_PLACEHOLDER_CLASS: &str = "placeholder";
+
+/// Error returned when parsing a `NodeData` from a selector, see `NodeData::from_str`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NodeDataParseError {
+    /// The selector is empty or only consists of whitespace
+    EmptySelector,
+    /// The element name isn't the name of a `NodeTypePath` or `label`
+    InvalidNodeType(String),
+    /// The element can't be created without an image or a callback
+    NodeTypeNeedsData(NodeTypePath),
+    /// A `#` isn't followed by an ID
+    EmptyId,
+    /// A `.` isn't followed by a class
+    EmptyClass,
+    /// The text of a `label(` isn't closed by a `)`
+    UnclosedLabel,
+    /// The character isn't allowed in element names, IDs and classes
+    InvalidCharacter(char),
+}
+
+impl_display! {
+    NodeDataParseError,
+    {
+        EmptySelector => "The selector is empty",
+        InvalidNodeType(name) => format!("Invalid node type: \"{}\"", name),
+        NodeTypeNeedsData(path) => format!("A \"{}\" node can't be created from a selector", path),
+        EmptyId => "Empty ID after \"#\"",
+        EmptyClass => "Empty class after \".\"",
+        UnclosedLabel => "The text of the label isn't closed by a \")\"",
+        InvalidCharacter(c) => format!("Invalid character in selector: '{}'", c),
+    }
+}
+
+/// Error returned by `Dom::select` if the selector can't be parsed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DomSelectorParseError {
+    /// The selector is empty or only consists of whitespace
+    EmptySelector,
+    /// The element name isn't the name of a `NodeTypePath`
+    InvalidNodeType(String),
+    /// A `#` isn't followed by an ID
+    EmptyId,
+    /// A `.` isn't followed by a class
+    EmptyClass,
+    /// A `>` isn't preceded and followed by a selector
+    DanglingCombinator,
+    /// The character isn't allowed in element names, IDs and classes
+    InvalidCharacter(char),
+}
+
+impl_display! {
+    DomSelectorParseError,
+    {
+        EmptySelector => "The selector is empty",
+        InvalidNodeType(name) => format!("Invalid node type: \"{}\"", name),
+        EmptyId => "Empty ID after \"#\"",
+        EmptyClass => "Empty class after \".\"",
+        DanglingCombinator => "\">\" has to be between two selectors",
+        InvalidCharacter(c) => format!("Invalid character in selector: '{}'", c),
+    }
+}
+
+/// A parsed `Dom::select` selector, i.e. `div > .row` or `#table .cell`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DomSelector {
+    /// The first compound selector, matched against an ancestor of the matched node
+    /// (or the node itself, if there are no `combinators`)
+    first: CompoundSelector,
+    /// The following compound selectors, the last one is matched against the node itself
+    combinators: Vec<(SelectorCombinator, CompoundSelector)>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SelectorCombinator {
+    /// `a b`: `b` is any descendant of `a`
+    Descendant,
+    /// `a > b`: `b` is a direct child of `a`
+    Child,
+}
+
+/// A node type with any number of IDs and classes, i.e. `div#main.dark`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CompoundSelector {
+    node_type: Option<NodeTypePath>,
+    ids: Vec<String>,
+    classes: Vec<String>,
+}
+
+impl DomSelector {
+
+    fn parse(selector: &str) -> Result<Self, DomSelectorParseError> {
+
+        use self::DomSelectorParseError::*;
+
+        // `div>.row` is the same as `div > .row`
+        let spaced = selector.replace('>', " > ");
+        let mut tokens = spaced.split_whitespace();
+
+        let first = match tokens.next() {
+            None => return Err(EmptySelector),
+            Some(">") => return Err(DanglingCombinator),
+            Some(token) => CompoundSelector::parse(token)?,
+        };
+
+        let mut combinators = Vec::new();
+        let mut combinator = SelectorCombinator::Descendant;
+        let mut ends_with_combinator = false;
+
+        for token in tokens {
+            if token == ">" {
+                if ends_with_combinator {
+                    return Err(DanglingCombinator);
+                }
+                combinator = SelectorCombinator::Child;
+                ends_with_combinator = true;
+            } else {
+                combinators.push((combinator, CompoundSelector::parse(token)?));
+                combinator = SelectorCombinator::Descendant;
+                ends_with_combinator = false;
+            }
+        }
+
+        if ends_with_combinator {
+            return Err(DanglingCombinator);
+        }
+
+        Ok(DomSelector { first, combinators })
+    }
+
+    /// Returns whether the node matches the last compound selector and its
+    /// ancestors match the compound selectors before it
+    fn matches<T: Layout>(&self, dom: &Dom<T>, node_id: NodeId) -> bool {
+        self.matches_up_to(dom, node_id, self.combinators.len())
+    }
+
+    /// Returns whether the node matches the compound selector at `index`
+    /// (0 being `self.first`) and its ancestors match the ones before it
+    fn matches_up_to<T: Layout>(&self, dom: &Dom<T>, node_id: NodeId, index: usize) -> bool {
+
+        if index == 0 {
+            return self.first.matches(&dom.arena.node_data[node_id]);
+        }
+
+        let (combinator, compound) = &self.combinators[index - 1];
+        if !compound.matches(&dom.arena.node_data[node_id]) {
+            return false;
+        }
+
+        let mut parent = dom.arena.node_layout[node_id].parent;
+        while let Some(ancestor) = parent {
+            if self.matches_up_to(dom, ancestor, index - 1) {
+                return true;
+            }
+            if *combinator == SelectorCombinator::Child {
+                return false;
+            }
+            parent = dom.arena.node_layout[ancestor].parent;
+        }
+
+        false
+    }
+}
+
+impl CompoundSelector {
+
+    fn parse(selector: &str) -> Result<Self, DomSelectorParseError> {
+
+        use self::DomSelectorParseError::*;
+
+        fn is_valid_name_char(c: char) -> bool {
+            c.is_alphanumeric() || c == '-' || c == '_'
+        }
+
+        let name_end = selector.find(|c: char| c == '#' || c == '.').unwrap_or(selector.len());
+        let (name, mut fragments) = selector.split_at(name_end);
+
+        if let Some(invalid) = name.chars().find(|c| !is_valid_name_char(*c)) {
+            return Err(InvalidCharacter(invalid));
+        }
+
+        let node_type = if name.is_empty() {
+            None
+        } else {
+            Some(NodeTypePath::from_str(name).map_err(|_| InvalidNodeType(name.to_string()))?)
+        };
+
+        let mut compound = CompoundSelector { node_type, ids: Vec::new(), classes: Vec::new() };
+
+        while let Some(fragment_type) = fragments.chars().next() {
+
+            let rest = &fragments[1..];
+            let value_end = rest.find(|c: char| c == '#' || c == '.').unwrap_or(rest.len());
+            let value = &rest[..value_end];
+
+            if let Some(invalid) = value.chars().find(|c| !is_valid_name_char(*c)) {
+                return Err(InvalidCharacter(invalid));
+            }
+
+            match (fragment_type, value.is_empty()) {
+                ('#', true) => return Err(EmptyId),
+                ('#', false) => compound.ids.push(value.to_string()),
+                (_, true) => return Err(EmptyClass),
+                (_, false) => compound.classes.push(value.to_string()),
+            }
+
+            fragments = &rest[value_end..];
+        }
+
+        Ok(compound)
+    }
+
+    fn matches<T: Layout>(&self, node: &NodeData<T>) -> bool {
+        self.node_type.map(|node_type| node.node_type.get_path() == node_type).unwrap_or(true) &&
+        self.ids.iter().all(|id| node.has_id(id)) &&
+        self.classes.iter().all(|class| node.has_class(class))
+    }
+}
+
+impl<T: Layout> FromIterator<Dom<T>> for Dom<T> {
+    fn from_iter<I: IntoIterator<Item=Dom<T>>>(iter: I) -> Self {
+        let mut c = Dom::new(NodeType::Div);
+        c.arena.node_data[c.root].is_synthetic_root = true;
+        for i in iter {
+            c.add_child(i);
+        }
+        c
+    }
+}
+
+/// Returns the length of the DOM after appending `child_len` nodes to `self_len` nodes,
+/// or an error if that would be more than `max_nodes` (or overflow a `usize`)
+fn checked_dom_len(self_len: usize, child_len: usize, max_nodes: usize) -> Result<usize, DomCapacityError> {
+    self_len.checked_add(child_len)
+        .filter(|total_len| *total_len <= max_nodes)
+        .ok_or(DomCapacityError::TooManyNodes { current: self_len, added: child_len, max: max_nodes })
+}
+
+/// Shifts all links of the `node` by the `offset`, `None` if a shifted link
+/// overflows or isn't below the `total_len` of the arena
+fn shift_node_links(node: Node, offset: usize, total_len: usize) -> Option<Node> {
+
+    // `Some(None)` for no link, `None` for an invalid link
+    let shift = |link: Option<NodeId>| match link {
+        None => Some(None),
+        Some(node_id) => node_id.index().checked_add(offset)
+            .filter(|index| *index < total_len)
+            .map(|index| Some(NodeId::new(index))),
+    };
+
+    Some(Node {
+        parent: shift(node.parent)?,
+        previous_sibling: shift(node.previous_sibling)?,
+        next_sibling: shift(node.next_sibling)?,
+        first_child: shift(node.first_child)?,
+        last_child: shift(node.last_child)?,
+    })
+}
+
+/// Appends a new node as the last child of the `parent`, the same way as `add_child`:
+/// the new node becomes the next sibling of the previous last child of the `parent`
+fn push_child_node<T: Layout>(node_layout: &mut Vec<Node>, node_data: &mut ArenaNodeData<NodeData<T>>, parent: NodeId, item: NodeData<T>) -> NodeId {
+
+    let node_id = NodeId::new(node_layout.len());
+    let previous_sibling = node_layout[parent.index()].last_child;
+
+    if let Some(previous_sibling) = previous_sibling {
+        node_layout[previous_sibling.index()].next_sibling = Some(node_id);
+    }
+
+    node_layout.push(Node {
+        parent: Some(parent),
+        previous_sibling,
+        next_sibling: None,
+        last_child: None,
+        first_child: None,
+    });
+    node_data.push(item);
+
+    let parent_node = &mut node_layout[parent.index()];
+    if parent_node.first_child.is_none() {
+        parent_node.first_child = Some(node_id);
+    }
+    parent_node.last_child = Some(node_id);
+
+    node_id
+}
+
+impl<T: Layout> FromIterator<NodeData<T>> for Dom<T> {
+    fn from_iter<I: IntoIterator<Item=NodeData<T>>>(iter: I) -> Self {
+        Dom::from_nested_iter(iter.into_iter().map(|item| (item, Vec::<NodeData<T>>::new())))
+    }
+}
+
+impl<T: Layout> FromIterator<(NodeData<T>, Vec<NodeData<T>>)> for Dom<T> {
+    fn from_iter<I: IntoIterator<Item=(NodeData<T>, Vec<NodeData<T>>)>>(iter: I) -> Self {
+        Dom::from_nested_iter(iter)
+    }
+}
+
+impl<T: Layout> FromIterator<NodeType<T>> for Dom<T> {
+    fn from_iter<I: IntoIterator<Item=NodeType<T>>>(iter: I) -> Self {
+        iter.into_iter().map(|i| NodeData { node_type: i, .. Default::default() }).collect()
+    }
+}
+
+/// Appends the DOMs as the last children of the current node, without the wrapper
+/// div that `collect()` creates, see `Dom::add_children`
+impl<T: Layout> Extend<Dom<T>> for Dom<T> {
+    fn extend<I: IntoIterator<Item=Dom<T>>>(&mut self, iter: I) {
+        self.add_children(iter);
+    }
+}
+
+/// Appends the nodes as the last children of the current node, without the wrapper
+/// div that `collect()` creates. The arena is grown only once (by the lower bound of
+/// the `size_hint` of the iterator) and each node is linked in constant time.
+impl<T: Layout> Extend<NodeData<T>> for Dom<T> {
+    fn extend<I: IntoIterator<Item=NodeData<T>>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        let (min_nodes, _) = iter.size_hint();
+        self.arena.reserve(min_nodes);
+        for node in iter {
+            self.add_single_node(node);
+        }
+    }
+}
+
+impl<T: Layout> Dom<T> {
+
+    /// Creates an empty DOM with a give `NodeType`.
+    #[inline]
+    pub fn new(node_type: NodeType<T>) -> Self {
+        Self::with_capacity(node_type, 0)
+    }
+
+    /// Shorthand for `Dom::new(NodeType::Div)`.
+    #[inline]
+    pub fn div() -> Self {
+        Self::new(NodeType::Div)
+    }
+
+    /// Builds a two-level DOM, for example a list of expandable rows: every
+    /// `(parent, children)` item becomes a child of the (synthetic) root div,
+    /// with the `children` as its children. Same as collecting an iterator of
+    /// `(NodeData<T>, Vec<NodeData<T>>)`, but the children can be any iterator.
+    pub fn from_nested_iter<I, C>(iter: I) -> Self
+        where I: IntoIterator<Item=(NodeData<T>, C)>,
+              C: IntoIterator<Item=NodeData<T>>,
+    {
+        // We have to use a "root" node, otherwise we run into problems if
+        // the iterator executes 0 times (and therefore pushes 0 nodes)
+
+        // "Root" node of this DOM
+        let mut node_data = ArenaNodeData::new(vec![NodeData { is_synthetic_root: true, .. NodeData::new(NodeType::Div) }]);
+        let mut node_layout = vec![Node {
+            parent: None,
+            previous_sibling: None,
+            next_sibling: None,
+            last_child: None,
+            first_child: None,
+        }];
+
+        let root = NodeId::new(0);
+
+        // Link the nodes in one pass, the children of a parent are pushed
+        // right after it, so the node IDs are in depth-first order
+        for (parent, children) in iter {
+            let parent_id = push_child_node(&mut node_layout, &mut node_data, root, parent);
+            for child in children {
+                push_child_node(&mut node_layout, &mut node_data, parent_id, child);
+            }
+        }
+
+        Dom {
+            head: root,
+            root,
+            arena: Arena {
+                node_data,
+                node_layout: NodeHierarchy::new(node_layout),
+            },
+        }
+    }
+
+    /// Parses a DOM from XML / HTML-like markup, so that the static structure of the UI
+    /// can be defined in a markup file and only the callbacks have to be wired in Rust:
+    ///
+    /// ```no_run,ignore
+    /// <div id="toolbar" class="row">
+    ///     <img src-id="logo"/>
+    ///     <p class="button" onmouseup="save">Save</p>
+    /// </div>
+    /// ```
+    ///
+    /// `onmouseup="save"` attaches the callback that is registered as `"save"` in the
+    /// `callbacks` and `src-id="logo"` refers to an image registered via `register_image`.
+    /// See the `xml` module for the supported elements and attributes. Unknown elements
+    /// and names that aren't registered are reported with the line of the element.
+    pub fn from_xml(xml: &str, callbacks: &XmlCallbackMap<T>) -> Result<Self, XmlParseError> {
+        dom_from_xml(xml, callbacks)
+    }
+
+    /// Creates a DOM with a single node from a selector such as `"div#main.container"`
+    /// or `"label(Hello).title"`, see `NodeData::from_str` for the syntax.
+    pub fn from_selector(selector: &str) -> Result<Self, NodeDataParseError> {
+        let node_data = selector.parse::<NodeData<T>>()?;
+        let mut dom = Dom::div();
+        dom.arena.node_data[dom.root] = node_data;
+        Ok(dom)
+    }
+
+    /// Shorthand for `Dom::new(NodeType::Label(value.into()))`
+    pub fn label<S: Into<String>>(value: S) -> Self {
+        Self::new(NodeType::Label(value.into()))
+    }
+
+    /// Shorthand for `Dom::new(NodeType::Text(text_id))`
+    #[inline]
+    pub fn text_id(text_id: TextId) -> Self {
+        Self::new(NodeType::Text(text_id))
+    }
+
+    /// Shorthand for `Dom::new(NodeType::Image(image_id))`
+    #[inline]
+    pub fn image(image: ImageId) -> Self {
+        Self::new(NodeType::Image(image))
+    }
+
+    /// Shorthand for `Dom::new(NodeType::GlTexture((callback, ptr)))`
+    #[inline]
+    pub fn gl_texture(callback: GlTextureCallback<T>, ptr: StackCheckedPointer<T>) -> Self {
+        Self::new(NodeType::GlTexture((callback, ptr)))
+    }
+
+    /// Shorthand for `Dom::new(NodeType::IFrame((callback, ptr)))`
+    #[inline]
+    pub fn iframe(callback: IFrameCallback<T>, ptr: StackCheckedPointer<T>) -> Self {
+        Self::new(NodeType::IFrame((callback, ptr)))
+    }
+
+    /// Returns the number of nodes in this DOM
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Returns the ID of the root node (the node that `Dom::new` created)
+    #[inline]
+    pub fn get_root(&self) -> NodeId {
+        self.root
+    }
+
+    /// Returns the parent / child / sibling relations of the nodes,
+    /// use `NodeId::traverse()` to iterate over the nodes in document order
+    #[inline]
+    pub fn get_node_hierarchy(&self) -> &NodeHierarchy {
+        &self.arena.node_layout
+    }
+
+    /// Returns the type, ids, classes, callbacks, etc. of the node
+    #[inline]
+    pub fn get_node_content(&self, node_id: NodeId) -> Option<&NodeData<T>> {
+        self.arena.node_data.get(node_id)
+    }
+
+    /// Returns the first node that has the given ID, or `None` if no node has it.
+    ///
+    /// IDs aren't required to be unique: if several nodes have the same ID, the
+    /// node that was added to the DOM first (the node with the lowest `NodeId`) is
+    /// returned. For DOMs built with `with_child` / `add_child`, this is the first
+    /// node in document order. The `NodeId` refers to the position of the node in
+    /// this DOM, it changes if the DOM is appended to another DOM.
+    pub fn get_node_by_id(&self, id: &str) -> Option<NodeId> {
+        self.arena.node_data.iter()
+            .position(|node| node.ids.iter().any(|node_id| node_id == id))
+            .map(NodeId::new)
+    }
+
+    /// Same as `get_node_content`
+    #[inline]
+    pub fn get_node_data(&self, node_id: NodeId) -> Option<&NodeData<T>> {
+        self.arena.node_data.get(node_id)
+    }
+
+    /// Returns the node for modification, for example to add a callback to a node
+    /// that was found with `get_node_by_id`. Clears the cached hash of the node,
+    /// so the public fields of the node can be modified directly.
+    pub fn get_node_data_mut(&mut self, node_id: NodeId) -> Option<&mut NodeData<T>> {
+        let node = self.arena.node_data.get_mut(node_id)?;
+        node.invalidate_hash_cache();
+        Some(node)
+    }
+
+    /// Replaces the node type of the node, for example to replace a placeholder `Div` of a
+    /// template with an `Image`. The position, children, classes, callbacks, etc. of the node
+    /// stay the same.
+    pub fn replace_node_type(&mut self, node_id: NodeId, node_type: NodeType<T>) -> Result<(), DomError> {
+        let node = self.arena.node_data.get_mut(node_id).ok_or(DomError::NodeNotFound { node: node_id })?;
+        node.set_node_type(node_type);
+        Ok(())
+    }
+
+    /// Replaces all data of the node (but not its position and children), returns the old data
+    pub fn replace_node_data(&mut self, node_id: NodeId, node_data: NodeData<T>) -> Result<NodeData<T>, DomError> {
+        let node = self.arena.node_data.get_mut(node_id).ok_or(DomError::NodeNotFound { node: node_id })?;
+        let old = ::std::mem::replace(node, node_data);
+        // The new node could have been modified through its public fields
+        node.invalidate_hash_cache();
+        node.calculate_content_hash();
+        Ok(old)
+    }
+
+    /// Returns the direct children of the node, in the order they were added
+    pub fn children<'a>(&'a self, node_id: NodeId) -> impl Iterator<Item = NodeId> + 'a {
+        node_id.children(&self.arena.node_layout)
+    }
+
+    /// Returns all nodes below the node (not including the node itself), depth-first:
+    /// each node is followed by its children, before its next sibling
+    pub fn descendants<'a>(&'a self, node_id: NodeId) -> impl Iterator<Item = NodeId> + 'a {
+        node_id.descendants(&self.arena.node_layout).skip(1)
+    }
+
+    /// Returns the parent of the node, its parent and so on up to the root
+    /// (not including the node itself)
+    pub fn ancestors<'a>(&'a self, node_id: NodeId) -> impl Iterator<Item = NodeId> + 'a {
+        node_id.ancestors(&self.arena.node_layout).skip(1)
+    }
+
+    /// Returns the node itself or its nearest ancestor for which the `predicate` returns
+    /// `true`, like `Element.closest()` in HTML, for example to find the `.dropdown`
+    /// that a clicked item belongs to:
+    ///
+    /// ```no_run,ignore
+    /// let dropdown = dom.closest(item, |node| node.has_class("dropdown"));
+    /// ```
+    pub fn closest<F: Fn(&NodeData<T>) -> bool>(&self, node_id: NodeId, predicate: F) -> Option<NodeId> {
+        node_id.ancestors(&self.arena.node_layout).find(|ancestor| predicate(&self.arena.node_data[*ancestor]))
+    }
+
+    /// Returns the other children of the parent of the node (not including the node itself),
+    /// in the order they were added. The root node has no siblings.
+    pub fn siblings<'a>(&'a self, node_id: NodeId) -> impl Iterator<Item = NodeId> + 'a {
+        let node_layout = &self.arena.node_layout;
+        // `preceding_siblings` starts at the node itself, so the last one is the first child
+        let first_sibling = node_id.preceding_siblings(node_layout).last().unwrap_or(node_id);
+        first_sibling.following_siblings(node_layout).filter(move |sibling| *sibling != node_id)
+    }
+
+    /// Returns `(depth, NodeId)` of all nodes in paint order (depth-first, parents before
+    /// their children), starting with the root at a depth of 0, for example to show the
+    /// DOM in an inspector.
+    ///
+    /// The traversal always terminates, even if the links of the node hierarchy are
+    /// inconsistent: it stops at links that point outside of the DOM and yields at most
+    /// as many nodes as the DOM has (so a cycle can't make it loop forever).
+    pub fn traverse<'a>(&'a self) -> impl Iterator<Item = (usize, NodeId)> + 'a {
+        DepthFirstTraversal {
+            node_layout: &self.arena.node_layout,
+            next: Some((0, self.root)),
+            remaining: self.arena.len(),
+        }
+    }
+
+    /// Counts the nodes per `NodeType`, the nodes per kind of event filter, the callbacks
+    /// and CSS overrides and the maximum depth of the nodes that can be reached from the
+    /// root, in a single pass over the DOM - for example to log why a frame is slow.
+    /// The same numbers are available after `into_ui_state()`, see `UiState::statistics()`.
+    pub fn statistics(&self) -> DomStatistics {
+
+        let mut statistics = DomStatistics::default();
+
+        for (depth, node_id) in self.traverse() {
+
+            let node = &self.arena.node_data[node_id];
+
+            statistics.nodes += 1;
+            statistics.max_depth = statistics.max_depth.max(depth);
+
+            match node.node_type {
+                NodeType::Div => statistics.divs += 1,
+                NodeType::Label(_) => statistics.labels += 1,
+                NodeType::Text(_) => statistics.texts += 1,
+                NodeType::Image(_) => statistics.images += 1,
+                NodeType::GlTexture(_) => statistics.gl_textures += 1,
+                NodeType::IFrame(_) => statistics.iframes += 1,
+            }
+
+            let filters = node.callbacks.iter().map(|(filter, _)| filter)
+                .chain(node.default_callback_ids.iter().map(|(filter, _)| filter));
+
+            let (mut hover, mut focus, mut not, mut window, mut desktop) = (false, false, false, false, false);
+            for filter in filters {
+                match filter {
+                    EventFilter::Hover(_) => hover = true,
+                    EventFilter::Focus(_) => focus = true,
+                    EventFilter::Not(_) => not = true,
+                    EventFilter::Window(_) => window = true,
+                    EventFilter::Desktop(_) => desktop = true,
+                }
+            }
+
+            statistics.nodes_with_hover_callbacks += hover as usize;
+            statistics.nodes_with_focus_callbacks += focus as usize;
+            statistics.nodes_with_not_callbacks += not as usize;
+            statistics.nodes_with_window_callbacks += window as usize;
+            statistics.nodes_with_desktop_callbacks += desktop as usize;
+            statistics.callbacks += node.callbacks.len() + node.default_callback_ids.len();
+            statistics.dynamic_css_overrides += node.dynamic_css_overrides.len();
+        }
+
+        statistics
+    }
+
+    /// Returns all nodes that have the given class, in document order (parents before
+    /// their children, siblings in the order they were added)
+    pub fn find_all_by_class<'a>(&'a self, class: &str) -> impl Iterator<Item = NodeId> + 'a {
+        let class = class.to_string();
+        let node_data = &self.arena.node_data;
+        self.root.descendants(&self.arena.node_layout)
+            .filter(move |node_id| node_data[*node_id].classes.iter().any(|c| *c == class))
+    }
+
+    /// Returns all nodes that match the selector, in document order. Supported are a
+    /// small subset of CSS selectors: the node type (`div`, `p`, `img`, `texture`,
+    /// `iframe`, see `NodeData::node_type_path`), `#id` and `.class` fragments (which
+    /// can be combined, i.e. `div#main.dark`), the descendant combinator (`div .item`)
+    /// and the child combinator (`div > .item`):
+    ///
+    /// ```no_run,ignore
+    /// assert_eq!(dom.select("#table > .row")?.len(), 10);
+    /// ```
+    ///
+    /// Invalid selectors (i.e. `"div >"` or `"span"`) return an error instead of matching nothing.
+    pub fn select(&self, selector: &str) -> Result<Vec<NodeId>, DomSelectorParseError> {
+        let selector = DomSelector::parse(selector)?;
+        Ok(self.root.descendants(&self.arena.node_layout)
+            .filter(|node_id| selector.matches(self, *node_id))
+            .collect())
+    }
+
+    /// Calls `f` on every node that has the given class, in document order,
+    /// for example to add a callback to every `.row` of a table
+    pub fn mutate_nodes_by_class<F: FnMut(&mut NodeData<T>)>(&mut self, class: &str, mut f: F) {
+        let node_ids = self.find_all_by_class(class).collect::<Vec<_>>();
+        for node_id in node_ids {
+            let node = &mut self.arena.node_data[node_id];
+            f(node);
+            node.invalidate_hash_cache();
+        }
+    }
+
+    /// Removes the callbacks, default callbacks and tab indices of all nodes, so that the
+    /// DOM can't be clicked or focused anymore, similar to the `inert` attribute in HTML.
+    ///
+    /// Nodes without callbacks don't get a hit-testing tag, so the events only reach the
+    /// nodes that are painted over the inert DOM (see `widgets::modal`). The DOMs of
+    /// IFrames are built separately and aren't affected.
+    pub fn make_inert(&mut self) {
+        for node in self.arena.node_data.iter_mut() {
+            node.callbacks.clear();
+            node.default_callback_ids.clear();
+            node.tab_index = None;
+            node.draggable = false;
+            node.invalidate_hash_cache();
+        }
+    }
+
+    /// Returns the `NodeTypePath` of the head node, see `NodeData::node_type_path`
+    #[inline]
+    pub fn node_type_path(&self) -> NodeTypePath {
+        self.arena.node_data[self.head].node_type_path()
+    }
+
+    /// Returns whether the head node is a `NodeType::Div`
+    #[inline]
+    pub fn is_div(&self) -> bool {
+        self.arena.node_data[self.head].is_div()
+    }
+
+    /// Returns whether the head node is a `NodeType::Label`
+    #[inline]
+    pub fn is_label(&self) -> bool {
+        self.arena.node_data[self.head].is_label()
+    }
+
+    /// Returns whether the head node is a `NodeType::Text`
+    #[inline]
+    pub fn is_text(&self) -> bool {
+        self.arena.node_data[self.head].is_text()
+    }
+
+    /// Returns whether the head node is a `NodeType::Image`
+    #[inline]
+    pub fn is_image(&self) -> bool {
+        self.arena.node_data[self.head].is_image()
+    }
+
+    /// Returns whether the head node is a `NodeType::GlTexture`
+    #[inline]
+    pub fn is_gl_texture(&self) -> bool {
+        self.arena.node_data[self.head].is_gl_texture()
+    }
+
+    /// Returns whether the head node is a `NodeType::IFrame`
+    #[inline]
+    pub fn is_iframe(&self) -> bool {
+        self.arena.node_data[self.head].is_iframe()
+    }
+
+    /// Returns the text of the head node, if it is a `Label`
+    #[inline]
+    pub fn get_label_text(&self) -> Option<&str> {
+        self.arena.node_data[self.head].get_label_text()
+    }
+
+    /// Returns the image ID of the head node, if it is an `Image`
+    #[inline]
+    pub fn get_image_id(&self) -> Option<ImageId> {
+        self.arena.node_data[self.head].get_image_id()
+    }
+
+    /// Returns the text ID of the head node, if it is a `Text`
+    #[inline]
+    pub fn get_text_id(&self) -> Option<TextId> {
+        self.arena.node_data[self.head].get_text_id()
+    }
+
+    /// Returns the callback of the head node, if it is a `GlTexture`
+    #[inline]
+    pub fn get_gl_texture_callback(&self) -> Option<GlTextureCallback<T>> {
+        self.arena.node_data[self.head].get_gl_texture_callback()
+    }
+
+    /// Returns the callback of the head node, if it is an `IFrame`
+    #[inline]
+    pub fn get_iframe_callback(&self) -> Option<IFrameCallback<T>> {
+        self.arena.node_data[self.head].get_iframe_callback()
+    }
+
+    /// Creates an empty DOM with space reserved for `cap` nodes
+    #[inline]
+    pub fn with_capacity(node_type: NodeType<T>, cap: usize) -> Self {
+        let mut arena = Arena::with_capacity(cap.saturating_add(1));
+        let root = arena.new_node(NodeData::new(node_type));
+        Self {
+            arena: arena,
+            root: root,
+            head: root,
+        }
+    }
+
+    /// Same as `with_capacity`, but re-uses the memory of the last frames DOM,
+    /// see `LayoutInfo::get_dom_allocator()`.
+    ///
+    /// Only useful for large DOMs, in order to prevent allocating the
+    /// memory for the nodes again on every frame.
+    #[inline]
+    pub fn with_capacity_in(node_type: NodeType<T>, cap: usize, allocator: &mut DomAllocator<T>) -> Self {
+        let root_node = allocator.node(node_type);
+        let mut arena = allocator.take_arena(cap.saturating_add(1));
+        let root = arena.new_node(root_node);
+        Self {
+            arena: arena,
+            root: root,
+            head: root,
+        }
+    }
+
+    /// Adds a child DOM to the current DOM: the root of the `child` (and all of its
+    /// descendants) becomes the last child of the current node of `self`. Where the
+    /// current node of `child` points to doesn't matter and the current node of `self`
+    /// stays the same.
+    ///
+    /// The `child` must not contain nodes that can't be reached from its root (such as
+    /// siblings of the root). In debug builds, this panics, in release builds, these
+    /// nodes are dropped and only the subtree of the root is appended.
+    ///
+    /// Appends the `child` the same way as `try_add_child` and panics on an error.
+    /// The only difference between debug and release builds is that release builds
+    /// don't check whether the DOM gets larger than `DEFAULT_MAX_DOM_NODES`.
+    pub fn add_child(&mut self, child: Self) {
+        #[cfg(debug_assertions)]
+        let max_nodes = Some(DEFAULT_MAX_DOM_NODES);
+        #[cfg(not(debug_assertions))]
+        let max_nodes = None;
+
+        if let Err(e) = self.append_child(child, max_nodes) {
+            panic!("add_child: {}", e);
+        }
+    }
+
+    /// Same as `add_child`, but returns an error instead of appending the `child` if the
+    /// DOM would have more than `DEFAULT_MAX_DOM_NODES` nodes, or if the `child` links to
+    /// nodes outside of its arena. The `NodeId`s of the `child` are shifted with checked
+    /// arithmetic before anything is modified, so `self` is unchanged on failure.
+    pub fn try_add_child(&mut self, child: Self) -> Result<(), DomCapacityError> {
+        self.try_add_child_with_limit(child, DEFAULT_MAX_DOM_NODES)
+    }
+
+    /// Same as `try_add_child`, but with a custom maximum number of nodes, for example
+    /// to reject procedurally generated DOMs that would get too large to lay out
+    pub fn try_add_child_with_limit(&mut self, child: Self, max_nodes: usize) -> Result<(), DomCapacityError> {
+        self.append_child(child, Some(max_nodes))
+    }
+
+    /// Shared implementation of `add_child` and `try_add_child`, `max_nodes` is
+    /// `None` if the number of nodes shouldn't be limited
+    fn append_child(&mut self, child: Self, max_nodes: Option<usize>) -> Result<(), DomCapacityError> {
+
+        let self_len = self.arena.len();
+
+        if child.arena.len() == 0 {
+            return Ok(());
+        }
+
+        let mut child = child.into_reachable_subtree();
+        let child_len = child.arena.len();
+        let total_len = match max_nodes {
+            Some(max_nodes) => checked_dom_len(self_len, child_len, max_nodes)?,
+            // Both arenas are in memory, so their combined length can't overflow
+            None => self_len + child_len,
+        };
+
+        if self_len == 0 {
+            *self = child;
+            self.head = self.root;
+            return Ok(());
+        }
+
+        // Shift the links of the child into a new buffer first, so that an invalid
+        // link doesn't leave `self` with half of the child appended
+        let mut shifted_layout = Vec::with_capacity(child_len);
+        for (node_idx, node) in child.arena.node_layout.internal.iter().enumerate() {
+            match shift_node_links(*node, self_len, total_len) {
+                Some(shifted) => shifted_layout.push(shifted),
+                None => return Err(DomCapacityError::LinkOutOfBounds { node: NodeId::new(node_idx) }),
+            }
+        }
+
+        // After `into_reachable_subtree`, the root is the only node without a parent
+        let head = self.head;
+        let root = NodeId::new(self_len + child.root.index());
+        let previous_sibling = self.arena.node_layout[head].last_child;
+        {
+            let root_node = &mut shifted_layout[child.root.index()];
+            root_node.parent = Some(head);
+            root_node.previous_sibling = previous_sibling;
+            root_node.next_sibling = None;
+        }
+        if let Some(previous_sibling) = previous_sibling {
+            self.arena.node_layout[previous_sibling].next_sibling = Some(root);
+        }
+        {
+            let head_node = &mut self.arena.node_layout[head];
+            head_node.first_child.get_or_insert(root);
+            head_node.last_child = Some(root);
+        }
+
+        self.arena.node_layout.internal.extend(shifted_layout);
+        self.arena.node_data.append(&mut child.arena.node_data);
+
+        Ok(())
+    }
+
+    /// Drops the nodes of the DOM that can't be reached from its root (see `add_child`) - they
+    /// would otherwise be appended as extra children of `self.head`, depending on their index
+    /// in the arena. This includes DOMs with several top-level nodes (nodes without a parent).
+    fn into_reachable_subtree(self) -> Self {
+        let reachable_len = self.root.descendants(&self.arena.node_layout).count();
+        debug_assert!(reachable_len == self.arena.len(),
+            "add_child: {} of the {} nodes of the child DOM can't be reached from its root \
+             (does it have more than one top-level node? wrap them in a div or use `add_children`)",
+            self.arena.len() - reachable_len, self.arena.len());
+        if reachable_len != self.arena.len() {
+            self.root_subtree()
+        } else {
+            self
+        }
+    }
+
+    /// Adds all `children` as the last children of the current node, in the order of
+    /// the iterator - same as calling `add_child` for each child, but the arena is
+    /// grown only once (by the lower bound of the `size_hint` of the iterator) and
+    /// children that consist of a single node are linked without merging their arena.
+    pub fn add_children<I: IntoIterator<Item=Dom<T>>>(&mut self, children: I) {
+        let children = children.into_iter();
+        // Every child has at least one node
+        let (min_children, _) = children.size_hint();
+        self.arena.reserve(min_children);
+        for mut child in children {
+            if child.arena.len() == 1 {
+                self.add_single_node(child.arena.node_data.pop().unwrap());
+            } else {
+                self.add_child(child);
+            }
+        }
+    }
+
+    /// Appends a single node as the last child of the current node, with the same
+    /// links as `add_child(Dom::new(...))`, but without allocating a `Dom` for it
+    fn add_single_node(&mut self, node: NodeData<T>) {
+        if self.arena.len() == 0 {
+            let mut child = Dom::new(NodeType::Div);
+            child.arena.node_data[child.root] = node;
+            self.add_child(child);
+        } else {
+            push_child_node(&mut self.arena.node_layout.internal, &mut self.arena.node_data, self.head, node);
+        }
+    }
+
+    /// Adds the roots of the `fragment` as the last children of the current node,
+    /// in the order they were added to the fragment
+    pub fn add_fragment(&mut self, fragment: DomFragment<T>) {
+        self.add_children(fragment.roots);
+    }
+
+    /// Same as `add_fragment`, but easier to use for method chaining in a builder-style pattern
+    #[inline]
+    pub fn with_fragment(mut self, fragment: DomFragment<T>) -> Self {
+        self.add_fragment(fragment);
+        self
+    }
+
+    /// Same as `add_child`, but inserts the child before the `index`-th child of the
+    /// current node instead of appending it. If the `index` is out of range, the child
+    /// is appended.
+    ///
+    /// Note that the nodes of the `child` are still appended to the arena, so the
+    /// `NodeId`s of the DOM don't follow the document order anymore.
+    pub fn insert_child_at(&mut self, index: usize, child: Self) {
+
+        let self_len = self.arena.len();
+        let child_count = self.head.children(&self.arena.node_layout).count();
+        let next_sibling = self.head.children(&self.arena.node_layout).nth(index);
+
+        self.add_child(child);
+
+        // Appended: the child is the last child (or replaced the empty DOM)
+        let next_sibling = match next_sibling {
+            Some(next_sibling) if self_len != 0 && self.arena.len() != self_len => next_sibling,
+            _ => return,
+        };
+
+        let node_layout = &mut self.arena.node_layout;
+        let inserted = NodeId::new(self_len);
+
+        // Unlink the child from the end of the children of the head
+        // (it has a previous sibling, since `index < child_count`)
+        debug_assert!(index < child_count);
+        let last_child = node_layout[inserted].previous_sibling;
+        if let Some(last_child) = last_child {
+            node_layout[last_child].next_sibling = None;
+        }
+        node_layout[self.head].last_child = last_child;
+
+        // ... and link it in front of the `next_sibling`
+        let previous_sibling = node_layout[next_sibling].previous_sibling;
+        node_layout[inserted].previous_sibling = previous_sibling;
+        node_layout[inserted].next_sibling = Some(next_sibling);
+        node_layout[next_sibling].previous_sibling = Some(inserted);
+        match previous_sibling {
+            Some(previous_sibling) => node_layout[previous_sibling].next_sibling = Some(inserted),
+            None => node_layout[self.head].first_child = Some(inserted),
+        }
+    }
+
+    /// Same as `add_child`, but the root of the `child` becomes the last child of the
+    /// `parent` instead of the current node, for example to put content into a node
+    /// of a template that was looked up with `get_node_by_id`. The current node stays
+    /// the same.
+    pub fn append_child_at(&mut self, parent: NodeId, child: Self) -> Result<(), DomError> {
+
+        if parent.index() >= self.arena.len() {
+            return Err(DomError::NodeNotFound { node: parent });
+        }
+
+        let head = self.head;
+        self.head = parent;
+        self.add_child(child);
+        self.head = head;
+
+        Ok(())
+    }
+
+    /// Same as `add_child`, but the root of the `child` becomes the current node, so that
+    /// the following `add_child`, `add_id`, `add_class`, etc. calls go to the child, until
+    /// `pop_head` returns to the parent. Returns the `NodeId` of the new current node.
+    ///
+    /// Useful for building nested DOMs (such as the sections of a document) in a loop
+    /// with an explicit stack, instead of recursively building the children first.
+    pub fn push_head(&mut self, child: Self) -> NodeId {
+
+        let self_len = self.arena.len();
+
+        if child.arena.len() == 0 {
+            return self.head;
+        }
+
+        self.add_child(child);
+
+        // The nodes of the child are appended to the arena, starting with its root
+        if self_len != 0 {
+            self.head = NodeId::new(self_len);
+        }
+
+        self.head
+    }
+
+    /// Makes the parent of the current node the current node again, see `push_head`.
+    /// Does nothing if the current node has no parent (i.e. is the root).
+    pub fn pop_head(&mut self) {
+        if let Some(parent) = self.arena.node_layout[self.head].parent {
+            self.head = parent;
+        }
+    }
+
+    /// Appends the `content` to the node whose IDs contain the `slot_id` (as the last child,
+    /// see `append_child_at`), for example to fill the body of a prebuilt window chrome with
+    /// a header, a sidebar and an empty body. If the slot node has the `SLOT_PLACEHOLDER_CLASS`
+    /// (so that empty slots can be styled differently), the class is removed.
+    ///
+    /// Unlike `get_node_by_id`, the ID has to be unique: if no node or more than one node has
+    /// the `slot_id`, an error is returned and the DOM isn't changed.
+    pub fn fill_slot(&mut self, slot_id: &str, content: Self) -> Result<(), SlotError> {
+
+        let slot = {
+            let slots = self.arena.node_data.iter().enumerate()
+                .filter(|(_, node)| node.has_id(slot_id))
+                .map(|(node_idx, _)| NodeId::new(node_idx))
+                .collect::<Vec<_>>();
+
+            match slots.len() {
+                0 => return Err(SlotError::NotFound { slot_id: slot_id.to_string() }),
+                1 => slots[0],
+                _ => return Err(SlotError::Ambiguous { slot_id: slot_id.to_string(), nodes: slots }),
+            }
+        };
+
+        {
+            let slot_node = &mut self.arena.node_data[slot];
+            if slot_node.has_class(SLOT_PLACEHOLDER_CLASS) {
+                slot_node.classes.retain(|class| class != SLOT_PLACEHOLDER_CLASS);
+                slot_node.invalidate_hash_cache();
+            }
+        }
+
+        let head = self.head;
+        self.head = slot;
+        self.add_child(content);
+        self.head = head;
+
+        Ok(())
+    }
+
+    /// Reorders the children of the `parent`, so that `new_order` are its children in that
+    /// order, for example to sort the rows of a table. The `new_order` has to contain each
+    /// child of the `parent` exactly once.
+    ///
+    /// Only the sibling links are rewritten, the nodes themselves don't move, so all `NodeId`s
+    /// stay valid - but (like with `insert_child_at`) they don't follow the document order anymore.
+    pub fn reorder_children(&mut self, parent: NodeId, new_order: &[NodeId]) -> Result<(), DomError> {
+
+        if parent.index() >= self.arena.len() {
+            return Err(DomError::NodeNotFound { node: parent });
+        }
+
+        let mut children = self.children(parent).collect::<Vec<NodeId>>();
+        let mut sorted_order = new_order.to_vec();
+        children.sort();
+        sorted_order.sort();
+
+        if children != sorted_order {
+            return Err(DomError::InvalidChildOrder { parent });
+        }
+
+        self.relink_children(parent, new_order);
+        Ok(())
+    }
+
+    /// Swaps the `a`-th and the `b`-th child of the `parent`, see `reorder_children`
+    pub fn swap_children(&mut self, parent: NodeId, a: usize, b: usize) -> Result<(), DomError> {
+
+        if parent.index() >= self.arena.len() {
+            return Err(DomError::NodeNotFound { node: parent });
+        }
+
+        let mut children = self.children(parent).collect::<Vec<NodeId>>();
+
+        for index in &[a, b] {
+            if *index >= children.len() {
+                return Err(DomError::ChildIndexOutOfRange { parent, index: *index });
+            }
+        }
+
+        children.swap(a, b);
+        self.relink_children(parent, &children);
+        Ok(())
+    }
+
+    /// Links the `children` as the children of the `parent`, in the given order
+    fn relink_children(&mut self, parent: NodeId, children: &[NodeId]) {
+        let node_layout = &mut self.arena.node_layout;
+        for (idx, child) in children.iter().enumerate() {
+            node_layout[*child].previous_sibling = if idx == 0 { None } else { Some(children[idx - 1]) };
+            node_layout[*child].next_sibling = children.get(idx + 1).cloned();
+        }
+        node_layout[parent].first_child = children.first().cloned();
+        node_layout[parent].last_child = children.last().cloned();
+    }
+
+    /// Removes the node and all of its descendants from the DOM.
+    ///
+    /// The remaining nodes are moved to close the gap in the arena (see `compact`), so `NodeId`s
+    /// that were returned before (for example by `get_node_by_id`) are invalid afterwards. The
+    /// returned map has the new `NodeId` of each remaining node by its old `NodeId`, the removed
+    /// nodes aren't in it. If the current node (see `add_child`) is removed, the root becomes
+    /// the current node.
+    pub fn remove_subtree(&mut self, node_id: NodeId) -> Result<BTreeMap<NodeId, NodeId>, DomError> {
+        self.detach_subtree(node_id)?;
+        // The removed nodes can't be reached from the root anymore, so `compact` always moves nodes
+        Ok(self.compact().unwrap_or_default())
+    }
+
+    /// Unlinks the node from its parent and siblings, without removing it from the arena:
+    /// the node and its descendants stay in the arena as unreachable nodes until `compact()`.
+    pub(crate) fn detach_subtree(&mut self, node_id: NodeId) -> Result<(), DomError> {
+
+        if node_id.index() >= self.arena.len() {
+            return Err(DomError::NodeNotFound { node: node_id });
+        }
+
+        if node_id == self.root {
+            return Err(DomError::CannotRemoveRoot { root: self.root });
+        }
+
+        let node = self.arena.node_layout[node_id];
+        match node.previous_sibling {
+            Some(previous_sibling) => self.arena.node_layout[previous_sibling].next_sibling = node.next_sibling,
+            None => if let Some(parent) = node.parent { self.arena.node_layout[parent].first_child = node.next_sibling; },
+        }
+        match node.next_sibling {
+            Some(next_sibling) => self.arena.node_layout[next_sibling].previous_sibling = node.previous_sibling,
+            None => if let Some(parent) = node.parent { self.arena.node_layout[parent].last_child = node.previous_sibling; },
+        }
+
+        let detached = &mut self.arena.node_layout[node_id];
+        detached.parent = None;
+        detached.previous_sibling = None;
+        detached.next_sibling = None;
+
+        Ok(())
+    }
+
+    /// Removes all nodes that can't be reached from the root from the arena, so that the
+    /// arena is dense again. The remaining nodes keep their order in the arena, their links,
+    /// the root and the current node are updated (if the current node is removed, the root
+    /// becomes the current node).
+    ///
+    /// Returns the new `NodeId` of each remaining node, by its old `NodeId`, so that `NodeId`s
+    /// held outside of the DOM can be updated - an old `NodeId` that isn't in the map belonged
+    /// to a removed node. Returns `None` if the arena is already dense: nothing changes, all
+    /// `NodeId`s stay valid and nothing is allocated.
+    pub fn compact(&mut self) -> Option<BTreeMap<NodeId, NodeId>> {
+
+        let len = self.arena.len();
+
+        if len == 0 || self.root.descendants(&self.arena.node_layout).count() == len {
+            return None;
+        }
+
+        let mut reachable = vec![false; len];
+        for node_id in self.root.descendants(&self.arena.node_layout) {
+            reachable[node_id.index()] = true;
+        }
+
+        let mut new_ids = Vec::with_capacity(len);
+        let mut id_map = BTreeMap::new();
+        for (old_idx, is_reachable) in reachable.iter().enumerate() {
+            if *is_reachable {
+                let new_id = NodeId::new(id_map.len());
+                id_map.insert(NodeId::new(old_idx), new_id);
+                new_ids.push(Some(new_id));
+            } else {
+                new_ids.push(None);
+            }
+        }
+
+        let remap = |id: Option<NodeId>| id.and_then(|id| new_ids[id.index()]);
+        let node_layout = mem::replace(&mut self.arena.node_layout.internal, Vec::with_capacity(id_map.len()));
+        self.arena.node_data.retain_reachable(&reachable);
+
+        for (node, is_reachable) in node_layout.into_iter().zip(reachable.iter()) {
+            if !*is_reachable {
+                continue;
+            }
+            self.arena.node_layout.internal.push(Node {
+                parent: remap(node.parent),
+                previous_sibling: remap(node.previous_sibling),
+                next_sibling: remap(node.next_sibling),
+                first_child: remap(node.first_child),
+                last_child: remap(node.last_child),
+            });
+        }
+
+        self.root = remap(Some(self.root)).unwrap_or(self.root);
+        self.head = remap(Some(self.head)).unwrap_or(self.root);
+
+        Some(id_map)
+    }
+
+    /// Returns a copy of the subtree of the root, without the nodes
+    /// that can't be reached from the root (see `add_child`)
+    fn root_subtree(&self) -> Self {
+        self.extract_subtree(self.root)
+    }
+
+    /// Copies the node and all of its descendants into a new DOM, with the node as its
+    /// root (and current node), for example to cache a fragment that was built by an
+    /// expensive function and add it to different parents later on with `add_child`.
+    ///
+    /// The nodes are numbered densely in document order, so extracting a leaf returns
+    /// a DOM with a single node and extracting the root of a DOM built with `add_child`
+    /// returns a DOM that is equal to it. `self` isn't modified, use `remove_subtree`
+    /// to remove the nodes afterwards.
+    ///
+    /// Panics if the `node_id` isn't a node of this DOM.
+    pub fn extract_subtree(&self, node_id: NodeId) -> Self {
+
+        let node_layout = &self.arena.node_layout;
+        let subtree = node_id.descendants(node_layout).collect::<Vec<_>>();
+
+        let mut new_ids = vec![None; self.arena.len()];
+        for (new_idx, old_id) in subtree.iter().enumerate() {
+            new_ids[old_id.index()] = Some(NodeId::new(new_idx));
+        }
+
+        let remap = |id: Option<NodeId>| id.and_then(|id| new_ids[id.index()]);
+        let mut arena = Arena::with_capacity(subtree.len());
+
+        for old_id in &subtree {
+            let node = &node_layout[*old_id];
+            let is_root = *old_id == node_id;
+            arena.node_layout.internal.push(Node {
+                // The siblings of the extracted node aren't part of the new DOM
+                parent: if is_root { None } else { remap(node.parent) },
+                previous_sibling: if is_root { None } else { remap(node.previous_sibling) },
+                next_sibling: if is_root { None } else { remap(node.next_sibling) },
+                first_child: remap(node.first_child),
+                last_child: remap(node.last_child),
+            });
+            arena.node_data.push_node_of(&self.arena.node_data, *old_id);
+        }
+
+        let root = NodeId::new(0);
+        Dom { arena, root, head: root }
+    }
+
+    /// Same as `id`, but easier to use for method chaining in a builder-style pattern
+    #[inline]
+    pub fn with_id<S: Into<String>>(mut self, id: S) -> Self {
+        self.add_id(id);
+        self
+    }
+
+    /// Same as `id`, but easier to use for method chaining in a builder-style pattern
+    #[inline]
+    pub fn with_class<S: Into<String>>(mut self, class: S) -> Self {
+        self.add_class(class);
+        self
+    }
+
+    /// Same as `event`, but easier to use for method chaining in a builder-style pattern
+    #[inline]
+    pub fn with_callback<O: Into<EventFilter>>(mut self, on: O, callback: Callback<T>) -> Self {
+        self.add_callback(on, callback);
+        self
+    }
+
+    #[inline]
+    pub fn with_child(mut self, child: Self) -> Self {
+        self.add_child(child);
+        self
+    }
+
+    /// Same as `add_children`, but easier to use for method chaining in a builder-style pattern
+    #[inline]
+    pub fn with_children<I: IntoIterator<Item=Dom<T>>>(mut self, children: I) -> Self {
+        self.add_children(children);
+        self
+    }
+
+    /// Turns the DOM into a `SharedDom`, which can be stored (i.e. in the application data)
+    /// and added to the DOM of every frame with `add_shared_child`, instead of building the
+    /// same nodes again in every `layout()` call. The hashes of all nodes are calculated
+    /// here, so that they don't have to be calculated again for every frame.
+    pub fn shared(self) -> SharedDom<T> {
+        let Dom { arena, root, head } = if self.arena.len() == 0 { self } else { self.into_reachable_subtree() };
+        let Arena { node_layout, node_data } = arena;
+
+        let nodes = Rc::new(node_data.into_vec());
+        for node in nodes.iter() {
+            node.calculate_node_data_hash();
+        }
+
+        let mut node_data = ArenaNodeData::with_capacity(nodes.len());
+        node_data.push_shared(&nodes);
+        SharedDom { dom: Rc::new(Dom { arena: Arena { node_layout, node_data }, root, head }) }
+    }
+
+    /// Same as `add_child`, but for a `SharedDom`: the nodes of the shared DOM aren't copied,
+    /// the arena only references them (including their cached hashes). Only the links between
+    /// the nodes are copied, so adding a `SharedDom` costs the same few allocations, no matter
+    /// how many nodes it has. Modifying one of the appended nodes copies it (see `SharedDom`).
+    pub fn add_shared_child(&mut self, child: &SharedDom<T>) {
+        self.add_child(Dom::clone(&child.dom));
+    }
+
+    /// Same as `add_shared_child`, but easier to use for method chaining in a builder-style pattern
+    #[inline]
+    pub fn with_shared_child(mut self, child: &SharedDom<T>) -> Self {
+        self.add_shared_child(child);
+        self
+    }
+
+    /// Creates a DOM with the `container` as the root and the `children` as its children.
+    ///
+    /// Same as `children.collect::<Dom<T>>()`, but with a real container node
+    /// instead of a synthetic root div that has to be styled afterwards.
+    pub fn collect_with_container<I: IntoIterator<Item=Dom<T>>>(container: NodeData<T>, children: I) -> Self {
+        let mut dom = Dom::new(NodeType::Div);
+        dom.arena.node_data[dom.root] = NodeData { is_synthetic_root: false, .. container };
+        for child in children {
+            dom.add_child(child);
+        }
+        dom
+    }
+
+    /// Calls `f` on every direct child of the current node (for example the nodes
+    /// of a collected `Dom`), as opposed to `with_class()` etc., which modify the
+    /// current node (the container).
+    pub fn map_children<F: FnMut(&mut NodeData<T>)>(mut self, mut f: F) -> Self {
+        for child_id in self.head.children(&self.arena.node_layout) {
+            let child = &mut self.arena.node_data[child_id];
+            f(child);
+            child.invalidate_hash_cache();
+        }
+        self
+    }
+
+    /// Adds the class to every direct child of the current node, see `map_children`
+    pub fn with_class_on_children<S: Into<String>>(self, class: S) -> Self {
+        let class = class.into();
+        self.map_children(|child| child.add_class(class.clone()))
+    }
+
+    /// Adds the callback to every node below the current node that matches the `filter`
+    /// (and to the current node itself, if it matches), for example to give every item
+    /// of a menu the same callback without passing it to each item:
+    /// `.with_callback_on_descendants(On::MouseUp, Callback(select), |node| node.has_class("menu-item"))`
+    pub fn with_callback_on_descendants<O: Into<EventFilter>>(mut self, on: O, callback: Callback<T>, filter: fn(&NodeData<T>) -> bool) -> Self {
+        let on = on.into();
+        let matching_nodes = self.head.descendants(&self.arena.node_layout)
+            .filter(|node_id| filter(&self.arena.node_data[*node_id]))
+            .collect::<Vec<_>>();
+        for node_id in matching_nodes {
+            self.arena.node_data[node_id].add_callback(on, callback);
+        }
+        self
+    }
+
+    #[inline]
+    pub fn with_css_override<S: Into<String>>(mut self, id: S, property: CssProperty) -> Self {
+        self.add_css_override(id, property);
+        self
+    }
+
+    #[inline]
+    pub fn with_tab_index(mut self, tab_index: TabIndex) -> Self {
+        self.add_tab_index(tab_index);
+        self
+    }
+
+    #[inline]
+    pub fn is_draggable(mut self, draggable: bool) -> Self {
+        self.set_draggable(draggable);
+        self
+    }
+
+    /// Same as `set_key`, but easier to use for method chaining in a builder-style pattern
+    #[inline]
+    pub fn with_key(mut self, key: u64) -> Self {
+        self.set_key(key);
+        self
+    }
+
+    #[inline]
+    pub fn add_id<S: Into<String>>(&mut self, id: S) {
+        self.arena.node_data[self.head].add_id(id);
+    }
+
+    #[inline]
+    pub fn add_class<S: Into<String>>(&mut self, class: S) {
+        self.arena.node_data[self.head].add_class(class);
+    }
+
+    #[inline]
+    pub fn add_callback<O: Into<EventFilter>>(&mut self, on: O, callback: Callback<T>) {
+        self.arena.node_data[self.head].add_callback(on, callback);
+    }
+
+    #[inline]
+    pub fn add_default_callback_id<O: Into<EventFilter>>(&mut self, on: O, id: DefaultCallbackId) {
+        self.arena.node_data[self.head].add_default_callback_id(on, id);
+    }
+
+    #[inline]
+    pub fn add_tab_index(&mut self, tab_index: TabIndex) {
+        self.arena.node_data[self.head].set_tab_index(Some(tab_index));
+    }
+
+    #[inline]
+    pub fn add_css_override<S: Into<String>>(&mut self, override_id: S, property: CssProperty) {
+        self.arena.node_data[self.head].add_css_override(override_id, property);
+    }
+
+    #[inline]
+    pub fn set_draggable(&mut self, draggable: bool) {
+        self.arena.node_data[self.head].set_draggable(draggable);
+    }
+
+    /// Sets the key of the node, see `NodeData::key`
+    #[inline]
+    pub fn set_key(&mut self, key: u64) {
+        self.arena.node_data[self.head].set_key(Some(key));
+    }
+
+    /// Sets an attribute of the current node, see `NodeData::attributes`
+    #[inline]
+    pub fn add_attribute<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) {
+        self.arena.node_data[self.head].add_attribute(key, value);
+    }
+
+    /// Same as `add_attribute`, but easier to use for method chaining in a builder-style pattern
+    #[inline]
+    pub fn with_attribute<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.add_attribute(key, value);
+        self
+    }
+
+    /// Returns the hash of the entire DOM tree (node data and hierarchy).
+    ///
+    /// Cheap for nodes that haven't changed, since each `NodeData` caches its own hash.
+    pub(crate) fn calculate_dom_hash(&self) -> DomHash {
+        let mut hasher = DomHasher::new();
+        self.arena.hash(&mut hasher);
+        DomHash(hasher.finish())
+    }
+
+    /// Returns whether the two DOMs would render identically, which can be true
+    /// even if they aren't equal (`==`):
+    ///
+    /// - `TextId`s are compared by their text in the text cache of the `app_resources`
+    /// - callbacks, tab indices and `draggable` are ignored, since they don't render anything
+    /// - CSS overrides are compared after deduplicating them (the last override wins)
+    ///
+    /// It is stricter for nodes whose content isn't part of the DOM: a DOM with a
+    /// `GlTexture` or `IFrame` is never content-equal, not even to itself.
+    pub fn content_eq(&self, other: &Self, app_resources: &AppResources) -> bool {
+        self.content_eq_with_strings(other, &app_resources.text_cache.string_cache)
+    }
+
+    pub(crate) fn content_eq_with_strings(&self, other: &Self, strings: &FastHashMap<TextId, String>) -> bool {
+        self.root == other.root &&
+        self.arena.node_layout == other.arena.node_layout &&
+        self.arena.node_data.iter()
+            .zip(other.arena.node_data.iter())
+            .all(|(a, b)| a.renders_like(b, strings))
+    }
+
+    /// Compares the structure of the two DOMs: both have the same tree of nodes (in
+    /// document order, no matter where the nodes are stored in the arena) and the nodes
+    /// are equal according to `NodeData::eq_ignoring_callbacks`, i.e. the function
+    /// pointers of the callbacks are ignored.
+    pub fn eq_structure(&self, other: &Dom<T>) -> bool {
+        let mut self_nodes = self.traverse();
+        let mut other_nodes = other.traverse();
+        loop {
+            match (self_nodes.next(), other_nodes.next()) {
+                (None, None) => return true,
+                (Some((self_depth, self_node)), Some((other_depth, other_node))) => {
+                    if self_depth != other_depth ||
+                       !self.arena.node_data[self_node].eq_ignoring_callbacks(&other.arena.node_data[other_node]) {
+                        return false;
+                    }
+                },
+                _ => return false,
+            }
+        }
+    }
+
+    /// Panics if the DOM isn't structurally equal to the `expected` DOM (see `eq_structure`),
+    /// with a line-by-line diff of both DOMs serialized by `serialize_for_snapshot`.
+    ///
+    /// ```no_run,ignore
+    /// Button::labeled("OK").dom().assert_eq_structure(&expected_button_dom);
+    /// ```
+    pub fn assert_eq_structure(&self, expected: &Dom<T>) {
+        if !self.eq_structure(expected) {
+            panic!("DOM does not match the expected structure (- expected, + actual):\n\n{}",
+                format_line_diff(&expected.serialize_for_snapshot(), &self.serialize_for_snapshot()));
+        }
+    }
+
+    /// Compares this DOM (usually the DOM of the previous frame) against `other`
+    /// and returns which nodes were added, removed or changed, see `DomDiff`.
+    ///
+    /// Useful for logging what a `layout()` call actually changed. Unchanged
+    /// subtrees are skipped by comparing their (cached) hashes.
+    pub fn diff(&self, other: &Dom<T>) -> DomDiff {
+        diff_dom_tree(self, other)
+    }
+
+    /// Prints `debug_dump_string()` to stdout
+    pub fn debug_dump(&self) {
+        println!("{}", self.debug_dump_string());
+    }
+
+    /// Returns the DOM as an indented tree, one line per node with its `NodeId`, node type,
+    /// ids, classes, the event filters of its callbacks and default callbacks (in the order
+    /// they were added), its tab index and whether it is draggable, for example:
+    ///
+    /// ```no_run,ignore
+    /// -- 0: div #main on=[Hover(MouseUp)] tabindex=Auto
+    /// \t|-- 1: label "Hello" .greeting draggable
+    /// ```
+    pub fn debug_dump_string(&self) -> String {
+        self.arena.print_tree(|node| debug_dump_line(node, false))
+    }
+
+    /// Same as `debug_dump_string`, but every line ends with the `DomHash` of the node,
+    /// so that the nodes that changed between two dumps (i.e. of two frames) are easy to spot
+    pub fn debug_dump_string_with_hashes(&self) -> String {
+        self.arena.print_tree(|node| debug_dump_line(node, true))
+    }
+
+    /// Same as `debug_dump_string`, but every node is followed by its (deduplicated)
+    /// dynamic CSS overrides, sorted by their override id, for example:
+    ///
+    /// ```no_run,ignore
+    /// -- 0: div #main
+    ///    [[ main_width ]] = Width(LayoutWidth(PixelValue { .. }))
+    /// \t|-- 1: label "Hello"
+    /// ```
+    ///
+    /// See `UiState::dump_with_overrides` for the same output after `into_ui_state()`.
+    pub fn dump_with_overrides(&self) -> String {
+        let overrides = self.arena.node_data.iter().enumerate()
+            .filter(|(_, node)| !node.dynamic_css_overrides.is_empty())
+            .map(|(idx, node)| (NodeId::new(idx), dedup_css_overrides(&node.dynamic_css_overrides)))
+            .collect();
+        dump_tree_with_overrides(self, &overrides)
+    }
+
+    /// Serializes the DOM into a canonical text form, for comparing it against a
+    /// stored snapshot (see `assert_dom_snapshot!`). Callbacks are printed as `<fn>`,
+    /// use `serialize_for_snapshot_with_names` to print their names instead.
+    pub fn serialize_for_snapshot(&self) -> String {
+        self.serialize_for_snapshot_with_names(&CallbackRegistry::new())
+    }
+
+    /// Serializes the DOM into a canonical text form, one line per node (in document
+    /// order, indented by depth), followed by the callbacks and CSS overrides of the node.
+    ///
+    /// The output only depends on the structure of the DOM, not on the run:
+    ///
+    /// - ids and classes are sorted and deduplicated
+    /// - the container div created by `collect()` is marked as `<synthetic-root>`
+    /// - callbacks are sorted by their event filter and printed by their name in the
+    ///   `registry` (or `<fn>`), never by their address
+    /// - CSS overrides are deduplicated (the last value wins) and sorted by their id
+    /// - `TextId`s, `ImageId`s, `DefaultCallbackId`s, pointers and
+    ///   cached hashes are omitted, since they are run-dependent
+    pub fn serialize_for_snapshot_with_names(&self, registry: &CallbackRegistry<T>) -> String {
+
+        let mut out = String::new();
+
+        for (depth, node_id) in self.traverse() {
+            serialize_node_for_snapshot(&self.arena.node_data[node_id], registry, depth, &mut out);
+        }
+
+        out
+    }
+
+    /// Serializes the DOM into an HTML-like string, for debugging and for golden tests.
+    ///
+    /// Every node is printed as a tag named after its `NodeTypePath` (`div`, `p`, `img`,
+    /// `texture` or `iframe`), indented by its depth. Ids and classes are printed as the
+    /// `id` and `class` attributes, the text of a label as the content of the tag and the
+    /// event filters of the callbacks (including the default callbacks) as a
+    /// `data-callbacks="Hover(MouseUp), Focus(TextInput)"` attribute.
+    ///
+    /// Like `serialize_for_snapshot`, the output is deterministic: ids, classes and
+    /// event filters are sorted and deduplicated and run-dependent values (such as
+    /// the contents of `NodeType::Text`) are omitted.
+    pub fn to_html_string(&self) -> String {
+
+        let mut out = String::new();
+        // Tags that still have to be closed, with the depth of their node
+        let mut open_tags = Vec::<(usize, NodeTypePath)>::new();
+
+        for (depth, node_id) in self.traverse() {
+
+            while open_tags.last().map(|(open_depth, _)| *open_depth >= depth).unwrap_or(false) {
+                let (open_depth, path) = open_tags.pop().unwrap();
+                out.push_str(&format!("{}</{}>\n", "    ".repeat(open_depth), path));
+            }
+
+            let has_children = self.arena.node_layout[node_id].first_child.is_some();
+            let path = html_node_for_debug(&self.arena.node_data[node_id], depth, has_children, &mut out);
+            if has_children {
+                open_tags.push((depth, path));
+            }
+        }
+
+        while let Some((depth, path)) = open_tags.pop() {
+            out.push_str(&format!("{}</{}>\n", "    ".repeat(depth), path));
+        }
+
+        out
+    }
+
+    /// Checks the structure of the node hierarchy: all node ids are in bounds, the
+    /// root is the first node and has no parent or siblings, the sibling chains agree
+    /// with the `first_child`, `last_child` and `parent` of every node, there are no
+    /// cycles and every node can be reached from the root.
+    ///
+    /// DOMs built via `Dom::new`, `add_child` and `collect()` are always valid.
+    pub fn validate(&self) -> Result<(), DomError> {
+        self.validate_all().map_err(|errors| errors[0])
+    }
+
+    /// Same as `validate()`, but returns every broken invariant instead of only the first
+    /// one (in the same order), for tracking down where a mutation of the arena went wrong.
+    /// If links point outside of the arena, only these links are returned, since the tree
+    /// can't be walked safely.
+    pub fn validate_all(&self) -> Result<(), Vec<DomError>> {
+
+        use self::DomError::*;
+
+        let nodes = &self.arena.node_layout.internal;
+        let len = nodes.len();
+
+        if self.root.index() >= len {
+            return Err(vec![OutOfBounds { node: self.root, target: self.root }]);
+        }
+
+        let mut errors = Vec::new();
+
+        for (idx, node) in nodes.iter().enumerate() {
+            for link in &[node.parent, node.previous_sibling, node.next_sibling, node.first_child, node.last_child] {
+                if let Some(target) = link {
+                    if target.index() >= len {
+                        errors.push(OutOfBounds { node: NodeId::new(idx), target: *target });
+                    }
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        if self.root.index() != 0 {
+            errors.push(RootNotFirst { root: self.root });
+        }
+
+        let root = &nodes[self.root.index()];
+        if root.parent.is_some() {
+            errors.push(RootHasParent { root: self.root });
+        }
+        if let Some(sibling) = root.next_sibling.or(root.previous_sibling) {
+            errors.push(MultipleRoots { root: self.root, sibling });
+        }
+
+        // Walk the tree without the `id_tree` iterators, which would loop forever on a cycle
+        let mut visited = vec![false; len];
+        visited[self.root.index()] = true;
+        let mut stack = vec![self.root];
+
+        while let Some(parent) = stack.pop() {
+            let mut previous = None;
+            let mut current = nodes[parent.index()].first_child;
+            let mut is_cyclic = false;
+            while let Some(child) = current {
+                if visited[child.index()] {
+                    errors.push(Cycle { node: child });
+                    is_cyclic = true;
+                    break;
+                }
+                visited[child.index()] = true;
+                let child_node = &nodes[child.index()];
+                if child_node.parent != Some(parent) {
+                    errors.push(WrongParent { node: child, expected: parent });
+                }
+                if child_node.previous_sibling != previous {
+                    errors.push(BrokenSiblingLink { node: child });
+                }
+                stack.push(child);
+                previous = Some(child);
+                current = child_node.next_sibling;
+            }
+            // A cyclic chain of children has no last child
+            if !is_cyclic && nodes[parent.index()].last_child != previous {
+                errors.push(BrokenLastChild { parent });
+            }
+        }
+
+        errors.extend(visited.iter().enumerate()
+            .filter(|(_, visited)| !**visited)
+            .map(|(node, _)| Unreachable { node: NodeId::new(node) }));
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Validates the DOM (see `validate()`), then creates the `UiState`
+    pub(crate) fn try_into_ui_state(self, tag_ids: &mut TagIdGenerator) -> Result<UiState<T>, DomError> {
+        self.validate()?;
+        Ok(self.into_ui_state_unchecked(tag_ids))
+    }
+
+    /// The UiState contains all the tags (for hit-testing) as well as the mapping
+    /// from Hit-testing tags to NodeIds (which are important for filtering input events
+    /// and routing input events to the callbacks).
+    ///
+    /// The `tag_ids` have to come from the window that the DOM is rendered in,
+    /// so that the tags of iframes don't collide with the tags of the parent DOM.
+    ///
+    /// In debug builds, this panics if the DOM is malformed (see `validate_all()`),
+    /// before any tags are handed out.
+    pub(crate) fn into_ui_state(self, tag_ids: &mut TagIdGenerator) -> UiState<T> {
+        #[cfg(debug_assertions)] {
+            if let Err(errors) = self.validate_all() {
+                let errors = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+                panic!("into_ui_state: the DOM is malformed: {}", errors.join(", "));
+            }
+        }
+        self.into_ui_state_unchecked(tag_ids)
+    }
+
+    fn into_ui_state_unchecked(self, tag_ids: &mut TagIdGenerator) -> UiState<T> {
+
+        // NOTE: Originally it was allowed to create a DOM with
+        // multiple root elements using `add_sibling()` and `with_sibling()`.
+        //
+        // However, it was decided to remove these functions (in commit #586933),
+        // as they aren't practical (you can achieve the same thing with one
+        // wrapper div and multiple add_child() calls) and they create problems
+        // when layouting elements since add_sibling() essentially modifies the
+        // space that the parent can distribute, which in code, simply looks weird
+        // and led to bugs. Widgets that produce several siblings return a
+        // `DomFragment` instead, which can only be added to an existing parent.
+        //
+        // It is assumed that the DOM returned by the user has exactly one root node
+        // with no further siblings and that the root node is the Node with the ID 0.
+        // DOMs that didn't go through `add_child` / `collect()` have to be checked
+        // with `try_into_ui_state()` instead.
+
+        // All tags that have can be focused (necessary for hit-testing)
+        let mut tab_index_tags = BTreeMap::new();
+        // All tags that have can be dragged & dropped (necessary for hit-testing)
+        let mut draggable_tags = BTreeMap::new();
+
+        // Mapping from tags to nodes (necessary so that the hit-testing can resolve the NodeId from any given tag)
+        let mut tag_ids_to_node_ids = BTreeMap::new();
+        // Mapping from nodes to tags, reverse mapping (not used right now, may be useful in the future)
+        let mut node_ids_to_tag_ids = BTreeMap::new();
+        // Which nodes have extra dynamic CSS overrides?
+        let mut dynamic_css_overrides = BTreeMap::new();
+
+        let mut hover_callbacks = FastHashMap::default();
+        let mut hover_default_callbacks = FastHashMap::default();
+        let mut focus_callbacks = FastHashMap::default();
+        let mut focus_default_callbacks = FastHashMap::default();
+        let mut not_callbacks = FastHashMap::default();
+        let mut not_default_callbacks = FastHashMap::default();
+        let mut window_callbacks = FastHashMap::default();
+        let mut window_default_callbacks = FastHashMap::default();
+        let mut desktop_callbacks = FastHashMap::default();
+        let mut desktop_default_callbacks = FastHashMap::default();
+
+        // data.callbacks, HoverEventFilter, Callback<T>, as_hover_event_filter, hover_callbacks, <node_tag_id> (optional)
+        macro_rules! filter_and_insert_callbacks {
+            (
+                    $node_id:ident,
+                    $data_source:expr,
+                    $event_filter:ident,
+                    $callback_type:ty,
+                    $filter_func:ident,
+                    $final_callback_list:ident,
+            ) => {
+                let mut node_hover_callbacks: CallbackList<$event_filter, $callback_type> = Vec::new();
+                for (event_filter, cb) in $data_source.iter() {
+                    if let Some(evt) = event_filter.$filter_func() {
+                        insert_callback(&mut node_hover_callbacks, evt, *cb);
+                    }
+                }
+
+                if !node_hover_callbacks.is_empty() {
+                    $final_callback_list.insert($node_id, node_hover_callbacks);
+                }
+            };
+            (
+                $node_id:ident,
+                $data_source:expr,
+                $event_filter:ident,
+                $callback_type:ty,
+                $filter_func:ident,
+                $final_callback_list:ident,
+                $node_tag_id:ident,
+            ) => {
+                let mut node_hover_callbacks: CallbackList<$event_filter, $callback_type> = Vec::new();
+                for (event_filter, cb) in $data_source.iter() {
+                    if let Some(evt) = event_filter.$filter_func() {
+                        insert_callback(&mut node_hover_callbacks, evt, *cb);
+                    }
+                }
+
+                if !node_hover_callbacks.is_empty() {
+                    $final_callback_list.insert($node_id, node_hover_callbacks);
+                    let tag_id = $node_tag_id.unwrap_or_else(|| tag_ids.new_tag_id());
+                    $node_tag_id = Some(tag_id);
+                }
+            };
+        }
+
+        {
+            let arena = &self.arena;
+
+            for node_id in arena.linear_iter() {
+
+                let data = &arena.node_data[node_id];
+
+                let mut node_tag_id = None;
+
+                // Optimization since on most nodes, the callbacks will be empty
+                if !data.callbacks.is_empty() {
+
+                    // Filter and insert HoverEventFilter callbacks
+                    filter_and_insert_callbacks!(
+                        node_id,
+                        data.callbacks,
+                        HoverEventFilter,
+                        Callback<T>,
+                        as_hover_event_filter,
+                        hover_callbacks,
+                        node_tag_id,
+                    );
+
+                    // Filter and insert FocusEventFilter callbacks
+                    filter_and_insert_callbacks!(
+                        node_id,
+                        data.callbacks,
+                        FocusEventFilter,
+                        Callback<T>,
+                        as_focus_event_filter,
+                        focus_callbacks,
+                        node_tag_id,
+                    );
+
+                    filter_and_insert_callbacks!(
+                        node_id,
+                        data.callbacks,
+                        NotEventFilter,
+                        Callback<T>,
+                        as_not_event_filter,
+                        not_callbacks,
+                        node_tag_id,
+                    );
+
+                    filter_and_insert_callbacks!(
+                        node_id,
+                        data.callbacks,
+                        WindowEventFilter,
+                        Callback<T>,
+                        as_window_event_filter,
+                        window_callbacks,
+                    );
+
+                    filter_and_insert_callbacks!(
+                        node_id,
+                        data.callbacks,
+                        DesktopEventFilter,
+                        Callback<T>,
+                        as_desktop_event_filter,
+                        desktop_callbacks,
+                    );
+                }
+
+                if !data.default_callback_ids.is_empty() {
+
+                    // Filter and insert HoverEventFilter callbacks
+                    filter_and_insert_callbacks!(
+                        node_id,
+                        data.default_callback_ids,
+                        HoverEventFilter,
+                        DefaultCallbackId,
+                        as_hover_event_filter,
+                        hover_default_callbacks,
+                        node_tag_id,
+                    );
+
+                    // Filter and insert FocusEventFilter callbacks
+                    filter_and_insert_callbacks!(
+                        node_id,
+                        data.default_callback_ids,
+                        FocusEventFilter,
+                        DefaultCallbackId,
+                        as_focus_event_filter,
+                        focus_default_callbacks,
+                        node_tag_id,
+                    );
+
+                    filter_and_insert_callbacks!(
+                        node_id,
+                        data.default_callback_ids,
+                        NotEventFilter,
+                        DefaultCallbackId,
+                        as_not_event_filter,
+                        not_default_callbacks,
+                        node_tag_id,
+                    );
+
+                    filter_and_insert_callbacks!(
+                        node_id,
+                        data.default_callback_ids,
+                        WindowEventFilter,
+                        DefaultCallbackId,
+                        as_window_event_filter,
+                        window_default_callbacks,
+                    );
+
+                    filter_and_insert_callbacks!(
+                        node_id,
+                        data.default_callback_ids,
+                        DesktopEventFilter,
+                        DefaultCallbackId,
+                        as_desktop_event_filter,
+                        desktop_default_callbacks,
+                    );
+                }
+
+                if data.draggable {
+                    let tag_id = node_tag_id.unwrap_or_else(|| tag_ids.new_tag_id());
+                    draggable_tags.insert(tag_id, node_id);
+                    node_tag_id = Some(tag_id);
+                }
+
+                if let Some(tab_index) = data.tab_index {
+                    let tag_id = node_tag_id.unwrap_or_else(|| tag_ids.new_tag_id());
+                    tab_index_tags.insert(tag_id, (node_id, tab_index));
+                    node_tag_id = Some(tag_id);
+                }
+
+                if let Some(tag_id) = node_tag_id {
+                    tag_ids_to_node_ids.insert(tag_id, node_id);
+                    node_ids_to_tag_ids.insert(node_id, tag_id);
+                }
+
+                // Collect all the styling overrides, so that the cascade doesn't have to deduplicate them
+                if !data.dynamic_css_overrides.is_empty() {
+                    dynamic_css_overrides.insert(node_id, dedup_css_overrides(&data.dynamic_css_overrides));
+                }
+            }
+        }
+
+        UiState {
+
+            dom: self,
+            dynamic_css_overrides,
+            tag_ids_to_hover_active_states: BTreeMap::new(),
+
+            tab_index_tags,
+            draggable_tags,
+            node_ids_to_tag_ids,
+            tag_ids_to_node_ids,
+
+            hover_callbacks,
+            hover_default_callbacks,
+            focus_callbacks,
+            focus_default_callbacks,
+            not_callbacks,
+            not_default_callbacks,
+            window_callbacks,
+            window_default_callbacks,
+            desktop_callbacks,
+            desktop_default_callbacks,
+
+        }
+    }
+}
+
+/// OpenGL texture, use `ReadOnlyWindow::create_texture` to create a texture
+///
+/// **WARNING**: Don't forget to call `ReadOnlyWindow::unbind_framebuffer()`
+/// when you are done with your OpenGL drawing, otherwise WebRender will render
+/// to the texture, not the window, so your texture will actually never show up.
+/// If you use a `Texture` and you get a blank screen, this is probably why.
+#[derive(Debug, Clone)]
+pub struct Texture {
+    pub(crate) inner: Rc<Texture2d>,
+}
+
+impl Texture {
+    /// Note: You can initialize this texture from an existing (external texture).
+    pub fn new(tex: Texture2d) -> Self {
+        Self {
+            inner: Rc::new(tex),
+        }
+    }
+
+    /// Prepares the texture for drawing - you can only draw
+    /// on a framebuffer, the texture itself is readonly from the
+    /// OpenGL drivers point of view.
+    ///
+    /// **WARNING**: Don't forget to call `ReadOnlyWindow::unbind_framebuffer()`
+    /// when you are done with your OpenGL drawing, otherwise WebRender will render
+    /// to the texture instead of the window, so your texture will actually
+    /// never show up on the screen, since it is never rendered.
+    /// If you use a `Texture` and you get a blank screen, this is probably why.
+    pub fn as_surface<'a>(&'a self) -> SimpleFrameBuffer<'a> {
+        self.inner.as_surface()
+    }
+}
+
+impl Hash for Texture {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        use glium::GlObject;
+        self.inner.get_id().hash(state);
+    }
+}
+
+impl PartialEq for Texture {
+    /// Note: Comparison uses only the OpenGL ID, it doesn't compare the
+    /// actual contents of the texture.
+    fn eq(&self, other: &Texture) -> bool {
+        use glium::GlObject;
+        self.inner.get_id() == other.inner.get_id()
+    }
+}
+
+impl Eq for Texture { }
+
+/// Iterator returned by `Dom::traverse`
+struct DepthFirstTraversal<'a> {
+    node_layout: &'a NodeHierarchy,
+    next: Option<(usize, NodeId)>,
+    /// Guards against cycles in the node hierarchy
+    remaining: usize,
+}
+
+impl<'a> DepthFirstTraversal<'a> {
+
+    /// Returns the node that comes after the subtree of `node_id`: its next sibling or
+    /// the next sibling of the closest ancestor that has one, stopping at the root
+    fn next_after_subtree(&self, mut depth: usize, mut node_id: NodeId) -> Option<(usize, NodeId)> {
+        while depth > 0 {
+            let node = self.node_layout.get(node_id)?;
+            if let Some(next_sibling) = node.next_sibling {
+                return Some((depth, next_sibling));
+            }
+            node_id = node.parent?;
+            depth -= 1;
+        }
+        None
+    }
+}
+
+impl<'a> Iterator for DepthFirstTraversal<'a> {
+    type Item = (usize, NodeId);
+
+    fn next(&mut self) -> Option<(usize, NodeId)> {
+
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let (depth, node_id) = self.next.take()?;
+        let node = self.node_layout.get(node_id)?;
+        self.remaining -= 1;
+
+        self.next = match node.first_child {
+            Some(first_child) => Some((depth + 1, first_child)),
+            None => self.next_after_subtree(depth, node_id),
+        };
+
+        Some((depth, node_id))
+    }
+}
+
+/// Appends the opening tag of one node to the output of `Dom::to_html_string` (or the whole
+/// element, if the node has no children) and returns the name of the tag
+fn html_node_for_debug<T: Layout>(node: &NodeData<T>, depth: usize, has_children: bool, out: &mut String) -> NodeTypePath {
+
+    use std::fmt::Write;
+
+    let indent = "    ".repeat(depth);
+    let path = node.node_type_path();
+
+    let _ = write!(out, "{}<{}", indent, path);
+    let ids = canonical_names(&node.ids);
+    if !ids.is_empty() {
+        let _ = write!(out, " id=\"{}\"", escape_html(&ids.join(" ")));
+    }
+    let classes = canonical_names(&node.classes);
+    if !classes.is_empty() {
+        let _ = write!(out, " class=\"{}\"", escape_html(&classes.join(" ")));
+    }
+
+    let mut filters = node.callbacks.iter().map(|(filter, _)| *filter)
+        .chain(node.default_callback_ids.iter().map(|(filter, _)| *filter))
+        .collect::<Vec<_>>();
+    filters.sort();
+    filters.dedup();
+    if !filters.is_empty() {
+        let filters = filters.iter().map(|filter| format!("{:?}", filter)).collect::<Vec<_>>();
+        let _ = write!(out, " data-callbacks=\"{}\"", filters.join(", "));
+    }
+    out.push('>');
+
+    let content = match &node.node_type {
+        NodeType::Label(text) => Some(escape_html(text)),
+        _ => None,
+    };
+
+    if has_children {
+        out.push('\n');
+        if let Some(content) = content {
+            let _ = writeln!(out, "{}    {}", indent, content);
+        }
+    } else {
+        if let Some(content) = content {
+            out.push_str(&content);
+        }
+        let _ = writeln!(out, "</{}>", path);
+    }
+
+    path
+}
+
+/// Escapes the characters that have a special meaning in HTML text and attributes
+pub(crate) fn escape_html(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats the tree like `Dom::debug_dump_string`, followed by the overrides of each node
+/// (sorted by their id), shared by `Dom::dump_with_overrides` and `UiState::dump_with_overrides`
+pub(crate) fn dump_tree_with_overrides<T: Layout>(dom: &Dom<T>, overrides: &BTreeMap<NodeId, CssOverrideList>) -> String {
+
+    use std::fmt::Write;
+
+    fn dump_recursive<T: Layout>(
+        dom: &Dom<T>,
+        overrides: &BTreeMap<NodeId, CssOverrideList>,
+        out: &mut String,
+        node_id: NodeId,
+        indent: usize)
+    {
+        let tabs = "\t|".repeat(indent);
+        let _ = writeln!(out, "{}-- {}: {}", tabs, node_id.index(), debug_dump_line(&dom.arena.node_data[node_id], false));
+
+        if let Some(node_overrides) = overrides.get(&node_id) {
+            let mut node_overrides = node_overrides.iter().collect::<Vec<_>>();
+            node_overrides.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (override_id, property) in node_overrides {
+                let _ = writeln!(out, "{}   [[ {} ]] = {:?}", tabs, override_id, property);
+            }
+        }
+
+        let node = &dom.arena.node_layout[node_id];
+        if let Some(first_child) = node.first_child {
+            dump_recursive(dom, overrides, out, first_child, indent + 1);
+        }
+        if let Some(next_sibling) = node.next_sibling {
+            dump_recursive(dom, overrides, out, next_sibling, indent);
+        }
+    }
+
+    let mut out = String::new();
+    dump_recursive(dom, overrides, &mut out, dom.root, 0);
+    out
+}
+
+/// One line of `Dom::debug_dump_string`, without the `NodeId` and indentation
+fn debug_dump_line<T: Layout>(node: &NodeData<T>, with_hash: bool) -> String {
+
+    use std::fmt::Write;
+    use self::NodeType::*;
+
+    let mut line = match &node.node_type {
+        Div => "div".to_string(),
+        Label(text) => format!("label {:?}", text),
+        Text(text_id) => format!("text {:?}", text_id),
+        Image(image_id) => format!("image {:?}", image_id),
+        GlTexture(_) => "gltexture".to_string(),
+        IFrame(_) => "iframe".to_string(),
+    };
+
+    if node.is_synthetic_root {
+        line.push_str(" <synthetic-root>");
+    }
+    for id in canonical_names(&node.ids).iter() {
+        let _ = write!(line, " #{}", id);
+    }
+    for class in canonical_names(&node.classes).iter() {
+        let _ = write!(line, " .{}", class);
+    }
+
+    let filters = node.callbacks.iter().map(|(filter, _)| *filter)
+        .chain(node.default_callback_ids.iter().map(|(filter, _)| *filter))
+        .collect::<Vec<_>>();
+    if !filters.is_empty() {
+        let _ = write!(line, " on={:?}", filters);
+    }
+    if let Some(tab_index) = node.tab_index {
+        let _ = write!(line, " tabindex={:?}", tab_index);
+    }
+    if node.draggable {
+        line.push_str(" draggable");
+    }
+    if with_hash {
+        let _ = write!(line, " hash={:016x}", node.calculate_node_data_hash().0);
+    }
+
+    line
+}
+
+/// Appends the canonical form of one node to the snapshot, see `Dom::serialize_for_snapshot_with_names`
+fn serialize_node_for_snapshot<T: Layout>(node: &NodeData<T>, registry: &CallbackRegistry<T>, depth: usize, out: &mut String) {
+
+    use std::fmt::Write;
+    use self::NodeType::*;
+
+    let indent = "    ".repeat(depth);
+
+    let node_type = match &node.node_type {
+        Div => "div".to_string(),
+        Label(text) => format!("label {:?}", text),
+        Text(_) => "text".to_string(),
+        Image(_) => "image".to_string(),
+        GlTexture((callback, _)) => format!("gltexture {}", registry.snapshot_name(callback.0 as usize)),
+        IFrame((callback, _)) => format!("iframe {}", registry.snapshot_name(callback.0 as usize)),
+    };
+
+    out.push_str(&indent);
+    out.push_str(&node_type);
+    if node.is_synthetic_root {
+        out.push_str(" <synthetic-root>");
+    }
+    for id in canonical_names(&node.ids).iter() {
+        let _ = write!(out, " #{}", id);
+    }
+    for class in canonical_names(&node.classes).iter() {
+        let _ = write!(out, " .{}", class);
+    }
+    if let Some(tab_index) = node.tab_index {
+        let _ = write!(out, " tabindex={:?}", tab_index);
+    }
+    if node.draggable {
+        out.push_str(" draggable");
+    }
+    if let Some(key) = node.key {
+        let _ = write!(out, " key={}", key);
+    }
+    for (key, value) in &node.attributes {
+        let _ = write!(out, " [{}={:?}]", key, value);
+    }
+    out.push('\n');
+
+    // Stable sort: callbacks with the same filter keep their order, since the last one wins
+    let mut callbacks = node.callbacks.iter().collect::<Vec<_>>();
+    callbacks.sort_by_key(|(filter, _)| *filter);
+    for (filter, callback) in callbacks {
+        let _ = writeln!(out, "{}  on {:?} -> {}", indent, filter, registry.snapshot_name(callback.0 as usize));
+    }
+
+    let mut default_callbacks = node.default_callback_ids.iter().map(|(filter, _)| *filter).collect::<Vec<_>>();
+    default_callbacks.sort();
+    for filter in default_callbacks {
+        let _ = writeln!(out, "{}  on {:?} -> <default>", indent, filter);
+    }
+
+    let overrides = dedup_css_overrides(&node.dynamic_css_overrides);
+    let mut overrides = overrides.iter().collect::<Vec<_>>();
+    overrides.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (override_id, property) in overrides {
+        let _ = writeln!(out, "{}  [[ {} ]] = {:?}", indent, override_id, property);
+    }
+}
+
+#[test]
+fn test_dom_sibling_1() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+                .with_child(
+                    Dom::new(NodeType::Div)
+                    .with_id("sibling-1")
+                    .with_child(Dom::new(NodeType::Div)
+                        .with_id("sibling-1-child-1")))
+                .with_child(Dom::new(NodeType::Div)
+                    .with_id("sibling-2")
+                    .with_child(Dom::new(NodeType::Div)
+                        .with_id("sibling-2-child-1")))
+        }
+    }
+
+    let dom = TestLayout{ }.layout();
+    let arena = &dom.arena;
+
+    assert_eq!(NodeId::new(0), dom.root);
+
+    assert_eq!(vec![String::from("sibling-1")],
+        arena.node_data[
+            arena.node_layout[dom.root]
+            .first_child.expect("root has no first child")
+        ].ids);
+
+    assert_eq!(vec![String::from("sibling-2")],
+        arena.node_data[
+            arena.node_layout[
+                arena.node_layout[dom.root]
+                .first_child.expect("root has no first child")
+            ].next_sibling.expect("root has no second sibling")
+        ].ids);
+
+    assert_eq!(vec![String::from("sibling-1-child-1")],
+        arena.node_data[
+            arena.node_layout[
+                arena.node_layout[dom.root]
+                .first_child.expect("root has no first child")
+            ].first_child.expect("first child has no first child")
+        ].ids);
+
+    assert_eq!(vec![String::from("sibling-2-child-1")],
+        arena.node_data[
+            arena.node_layout[
+                arena.node_layout[
+                    arena.node_layout[dom.root]
+                    .first_child.expect("root has no first child")
+                ].next_sibling.expect("first child has no second sibling")
+            ].first_child.expect("second sibling has no first child")
+        ].ids);
+}
+
+#[test]
+fn test_dom_insert_child_at() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+                .with_child(Dom::new(NodeType::Div).with_id("sibling-1"))
+                .with_child(Dom::new(NodeType::Div).with_id("sibling-2"))
+        }
+    }
+
+    fn ids_of_children(dom: &Dom<TestLayout>) -> Vec<String> {
+        dom.root.children(&dom.arena.node_layout)
+            .map(|child| dom.arena.node_data[child].ids.join(" "))
+            .collect()
+    }
+
+    fn ids_of_reverse_children(dom: &Dom<TestLayout>) -> Vec<String> {
+        dom.root.reverse_children(&dom.arena.node_layout)
+            .map(|child| dom.arena.node_data[child].ids.join(" "))
+            .collect()
+    }
+
+    let inserted = || Dom::new(NodeType::Div)
+        .with_id("inserted")
+        .with_child(Dom::new(NodeType::Div).with_id("inserted-child-1"));
+
+    // Front
+    let mut dom = TestLayout { }.layout();
+    dom.insert_child_at(0, inserted());
+    assert_eq!(ids_of_children(&dom), vec!["inserted", "sibling-1", "sibling-2"]);
+    assert_eq!(ids_of_reverse_children(&dom), vec!["sibling-2", "sibling-1", "inserted"]);
+
+    // Middle
+    let mut dom = TestLayout { }.layout();
+    dom.insert_child_at(1, inserted());
+    assert_eq!(ids_of_children(&dom), vec!["sibling-1", "inserted", "sibling-2"]);
+    assert_eq!(ids_of_reverse_children(&dom), vec!["sibling-2", "inserted", "sibling-1"]);
+    assert_eq!(dom.validate(), Ok(()));
+
+    // The children of the inserted node stay its children
+    let inserted_node = dom.get_node_by_id("inserted").unwrap();
+    let inserted_child = dom.get_node_by_id("inserted-child-1").unwrap();
+    assert_eq!(dom.children(inserted_node).collect::<Vec<_>>(), vec![inserted_child]);
+    assert_eq!(dom.arena.node_layout[inserted_node].parent, Some(dom.root));
+
+    // End, and out of range indices append
+    for index in &[2, 100] {
+        let mut dom = TestLayout { }.layout();
+        dom.insert_child_at(*index, inserted());
+        assert_eq!(ids_of_children(&dom), vec!["sibling-1", "sibling-2", "inserted"]);
+        assert_eq!(ids_of_reverse_children(&dom), vec!["inserted", "sibling-2", "sibling-1"]);
+    }
+
+    // Into a node without children, and several insertions in a row
+    let mut dom = Dom::<TestLayout>::new(NodeType::Div);
+    dom.insert_child_at(0, Dom::new(NodeType::Div).with_id("c"));
+    dom.insert_child_at(0, Dom::new(NodeType::Div).with_id("a"));
+    dom.insert_child_at(1, Dom::new(NodeType::Div).with_id("b"));
+    assert_eq!(ids_of_children(&dom), vec!["a", "b", "c"]);
+    assert_eq!(ids_of_reverse_children(&dom), vec!["c", "b", "a"]);
+    assert_eq!(dom.validate(), Ok(()));
+}
+
+#[test]
+fn test_dom_from_iter_1() {
+
+    use id_tree::Node;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            (0..5).map(|e| NodeData::new(NodeType::Label(format!("{}", e + 1)))).collect()
+        }
+    }
+
+    let dom = TestLayout{ }.layout();
+    let arena = &dom.arena;
+
+    // We need to have 6 nodes:
+    //
+    // root                 NodeId(0)
+    //   |-> 1              NodeId(1)
+    //   |-> 2              NodeId(2)
+    //   |-> 3              NodeId(3)
+    //   |-> 4              NodeId(4)
+    //   '-> 5              NodeId(5)
+
+    assert_eq!(arena.len(), 6);
+
+    // Check root node
+    assert_eq!(arena.node_layout.get(NodeId::new(0)), Some(&Node {
+        parent: None,
+        previous_sibling: None,
+        next_sibling: None,
+        first_child: Some(NodeId::new(1)),
+        last_child: Some(NodeId::new(5)),
+    }));
+    assert_eq!(arena.node_data.get(NodeId::new(0)), Some(&NodeData::new(NodeType::Div)));
+
+    assert_eq!(arena.node_layout.get(NodeId::new(arena.node_layout.len() - 1)), Some(&Node {
+        parent: Some(NodeId::new(0)),
+        previous_sibling: Some(NodeId::new(4)),
+        next_sibling: None,
+        first_child: None,
+        last_child: None,
+    }));
+    assert_eq!(arena.node_data.get(NodeId::new(arena.node_data.len() - 1)), Some(&NodeData {
+        node_type: NodeType::Label(String::from("5")),
+        .. Default::default()
+    }));
+
+    // Every node in the middle links to both of its neighbours
+    for idx in 2..5 {
+        assert_eq!(arena.node_layout[NodeId::new(idx)].previous_sibling, Some(NodeId::new(idx - 1)));
+        assert_eq!(arena.node_layout[NodeId::new(idx)].next_sibling, Some(NodeId::new(idx + 1)));
+    }
+
+    assert_eq!(dom.validate(), Ok(()));
+}
+
+#[test]
+fn test_dom_from_iter_parents_and_children() {
+
+    use id_tree::Node;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            (0..3).map(|p| (
+                NodeData::new(NodeType::Label(format!("{}", p + 1))),
+                (0..2).map(|c| NodeData::new(NodeType::Label(format!("{}.{}", p + 1, c + 1)))).collect(),
+            )).collect()
+        }
+    }
+
+    let dom = TestLayout{ }.layout();
+    let arena = &dom.arena;
+
+    // We need to have 10 nodes:
+    //
+    // root                 NodeId(0)
+    //   |-> 1              NodeId(1)
+    //   |   |-> 1.1        NodeId(2)
+    //   |   '-> 1.2        NodeId(3)
+    //   |-> 2              NodeId(4)
+    //   |   |-> 2.1        NodeId(5)
+    //   |   '-> 2.2        NodeId(6)
+    //   '-> 3              NodeId(7)
+    //       |-> 3.1        NodeId(8)
+    //       '-> 3.2        NodeId(9)
+
+    assert_eq!(arena.len(), 10);
+
+    let node = |parent: Option<usize>, previous_sibling: Option<usize>, next_sibling: Option<usize>,
+                first_child: Option<usize>, last_child: Option<usize>| Node {
+        parent: parent.map(NodeId::new),
+        previous_sibling: previous_sibling.map(NodeId::new),
+        next_sibling: next_sibling.map(NodeId::new),
+        first_child: first_child.map(NodeId::new),
+        last_child: last_child.map(NodeId::new),
+    };
+
+    assert_eq!(arena.node_layout.internal, vec![
+        node(None,    None,    None,    Some(1), Some(7)),
+        node(Some(0), None,    Some(4), Some(2), Some(3)),
+        node(Some(1), None,    Some(3), None,    None),
+        node(Some(1), Some(2), None,    None,    None),
+        node(Some(0), Some(1), Some(7), Some(5), Some(6)),
+        node(Some(4), None,    Some(6), None,    None),
+        node(Some(4), Some(5), None,    None,    None),
+        node(Some(0), Some(4), None,    Some(8), Some(9)),
+        node(Some(7), None,    Some(9), None,    None),
+        node(Some(7), Some(8), None,    None,    None),
+    ]);
+    assert_eq!(arena.node_data.get(NodeId::new(9)), Some(&NodeData {
+        node_type: NodeType::Label(String::from("3.2")),
+        .. Default::default()
+    }));
+    assert_eq!(dom.validate(), Ok(()));
+
+    // Same as building the DOM by hand
+    let by_hand = (0..3).map(|p| (1..3).fold(Dom::label(format!("{}", p + 1)), |parent, c| {
+        parent.with_child(Dom::label(format!("{}.{}", p + 1, c)))
+    })).collect::<Dom<TestLayout>>();
+    assert_eq!(dom, by_hand);
+
+    // The children can be any iterator
+    let nested = Dom::from_nested_iter((0..3).map(|p| (
+        NodeData::new(NodeType::Label(format!("{}", p + 1))),
+        (0..2).map(move |c| NodeData::new(NodeType::Label(format!("{}.{}", p + 1, c + 1)))),
+    )));
+    assert_eq!(nested, dom);
+}
+
+#[test]
+fn test_dom_from_iter_single_and_two_elements() {
+
+    use id_tree::Node;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let labels = |count: usize| (0..count)
+        .map(|e| NodeData::new(NodeType::Label(format!("{}", e + 1))))
+        .collect::<Dom<TestLayout>>();
+
+    let child = |parent: usize, previous_sibling: Option<usize>, next_sibling: Option<usize>| Node {
+        parent: Some(NodeId::new(parent)),
+        previous_sibling: previous_sibling.map(NodeId::new),
+        next_sibling: next_sibling.map(NodeId::new),
+        first_child: None,
+        last_child: None,
+    };
+
+    let root = |last_child: usize| Node {
+        first_child: Some(NodeId::new(1)),
+        last_child: Some(NodeId::new(last_child)),
+        .. Node::default()
+    };
+
+    // root -> 1
+    let one = labels(1);
+    assert_eq!(one.arena.node_layout.internal, vec![root(1), child(0, None, None)]);
+    assert_eq!(one.validate(), Ok(()));
+    assert_eq!(one, Dom::div().with_child(Dom::label("1")));
+
+    // root -> 1, 2
+    let two = labels(2);
+    assert_eq!(two.arena.node_layout.internal, vec![root(2), child(0, None, Some(2)), child(0, Some(1), None)]);
+    assert_eq!(two.validate(), Ok(()));
+    assert_eq!(two, Dom::div().with_child(Dom::label("1")).with_child(Dom::label("2")));
+}
+
+#[test]
+fn test_add_child_attaches_root_of_child_and_keeps_head() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let child = || Dom::div().with_class("child")
+        .with_child(Dom::label("a"))
+        .with_child(Dom::label("b"));
+
+    // The head of the child points to "b", but the entire child is appended
+    let mut child_with_moved_head: Dom<TestLayout> = child();
+    child_with_moved_head.head = NodeId::new(2);
+
+    let mut dom = Dom::div().with_id("parent");
+    dom.add_child(child_with_moved_head);
+    assert_eq!(dom, Dom::div().with_id("parent").with_child(child()));
+    assert_eq!(dom.validate(), Ok(()));
+
+    // The child is appended to the head of the parent, which doesn't move
+    let mut dom = Dom::div().with_child(Dom::div().with_id("inner"));
+    dom.head = NodeId::new(1);
+    dom.add_child(child());
+    assert_eq!(dom.head, NodeId::new(1));
+    dom.head = dom.root;
+    assert_eq!(dom, Dom::div().with_child(Dom::div().with_id("inner").with_child(child())));
+    assert_eq!(dom.validate(), Ok(()));
+}
+
+/// Returns a DOM with a root, a child of the root and a sibling of the root,
+/// which can't be reached from the root
+#[cfg(test)]
+fn dom_with_sibling_of_root<T: Layout>() -> Dom<T> {
+    let mut dom = Dom::div().with_child(Dom::label("kept")).with_child(Dom::label("orphan"));
+    {
+        let nodes = &mut dom.arena.node_layout;
+        nodes[NodeId::new(0)].last_child = Some(NodeId::new(1));
+        nodes[NodeId::new(0)].next_sibling = Some(NodeId::new(2));
+        nodes[NodeId::new(1)].next_sibling = None;
+        nodes[NodeId::new(2)].parent = None;
+        nodes[NodeId::new(2)].previous_sibling = Some(NodeId::new(0));
+    }
+    dom
+}
+
+#[test]
+fn test_root_subtree_drops_unreachable_nodes() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let dom = dom_with_sibling_of_root::<TestLayout>();
+    assert_eq!(dom.validate(), Err(DomError::MultipleRoots { root: NodeId::new(0), sibling: NodeId::new(2) }));
+
+    let repaired = dom.root_subtree();
+    assert_eq!(repaired, Dom::div().with_child(Dom::label("kept")));
+    assert_eq!(repaired.validate(), Ok(()));
+
+    let repaired = dom_with_three_top_level_nodes::<TestLayout>().root_subtree();
+    assert_eq!(repaired, Dom::label("a").with_child(Dom::label("a.1")));
+    assert_eq!(repaired.validate_all(), Ok(()));
+}
+
+#[test]
+fn test_extract_subtree() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let fragment = || Dom::<TestLayout>::div().with_id("fragment")
+        .with_child(Dom::label("a").with_child(Dom::label("a.1")))
+        .with_child(Dom::label("b"));
+
+    let dom = Dom::div()
+        .with_child(Dom::label("header"))
+        .with_child(fragment())
+        .with_child(Dom::label("footer"));
+
+    let fragment_id = dom.get_node_by_id("fragment").unwrap();
+    let extracted = dom.extract_subtree(fragment_id);
+    assert_eq!(extracted, fragment());
+    assert_eq!(extracted.validate_all(), Ok(()));
+    assert_eq!(extracted.get_root(), NodeId::new(0));
+    assert_eq!(extracted.len(), 4);
+
+    // Leaf
+    let footer = dom.children(dom.get_root()).last().unwrap();
+    let leaf = dom.extract_subtree(footer);
+    assert_eq!(leaf, Dom::label("footer"));
+    assert_eq!(leaf.validate_all(), Ok(()));
+
+    // Root
+    assert_eq!(dom.extract_subtree(dom.get_root()), dom);
+
+    // The extracted DOM can be added to other parents
+    let reused = Dom::div().with_child(extracted.clone()).with_child(extracted);
+    assert_eq!(reused, Dom::div().with_child(fragment()).with_child(fragment()));
+    assert_eq!(reused.validate_all(), Ok(()));
+}
+
+/// A DOM whose arena has three nodes without a parent: `a` (the root, with a child `a.1`), `b` and `c`
+#[cfg(test)]
+fn dom_with_three_top_level_nodes<T: Layout>() -> Dom<T> {
+    let mut dom = Dom::label("a").with_child(Dom::label("a.1"));
+    for text in &["b", "c"] {
+        dom.arena.node_layout.internal.push(Node::default());
+        dom.arena.node_data.push(NodeData::new(NodeType::Label(text.to_string())));
+    }
+    dom
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "2 of the 4 nodes of the child DOM can't be reached from its root")]
+fn test_add_child_with_several_top_level_nodes_panics_in_debug() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    Dom::<TestLayout>::div().with_child(Dom::label("existing")).add_child(dom_with_three_top_level_nodes());
+}
+
+#[test]
+#[cfg(not(debug_assertions))]
+fn test_add_child_with_several_top_level_nodes_is_repaired_in_release() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let child = dom_with_three_top_level_nodes::<TestLayout>();
+    assert!(child.validate_all().is_err());
+
+    let mut dom = Dom::div().with_child(Dom::label("existing"));
+    dom.add_child(child);
+    dom.add_child(Dom::label("after"));
+
+    let expected = Dom::div()
+        .with_child(Dom::label("existing"))
+        .with_child(Dom::label("a").with_child(Dom::label("a.1")))
+        .with_child(Dom::label("after"));
+    assert_eq!(dom, expected);
+    assert_eq!(dom.validate_all(), Ok(()));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "can't be reached from its root")]
+fn test_add_child_with_unreachable_nodes_panics_in_debug() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    Dom::<TestLayout>::div().add_child(dom_with_sibling_of_root());
+}
+
+#[test]
+#[cfg(not(debug_assertions))]
+fn test_add_child_with_unreachable_nodes_is_repaired_in_release() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let dom = Dom::<TestLayout>::div().with_child(dom_with_sibling_of_root());
+    assert_eq!(dom, Dom::div().with_child(Dom::div().with_child(Dom::label("kept"))));
+    assert_eq!(dom.validate(), Ok(()));
+}
+
+#[test]
+fn test_try_add_child_checks_capacity() {
+
+    use self::DomCapacityError::*;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    // Arena lengths close to the maximum, which are too large to allocate in a test
+    assert_eq!(checked_dom_len(DEFAULT_MAX_DOM_NODES - 3, 3, DEFAULT_MAX_DOM_NODES), Ok(DEFAULT_MAX_DOM_NODES));
+    assert_eq!(checked_dom_len(DEFAULT_MAX_DOM_NODES - 3, 4, DEFAULT_MAX_DOM_NODES), Err(TooManyNodes { current: DEFAULT_MAX_DOM_NODES - 3, added: 4, max: DEFAULT_MAX_DOM_NODES }));
+    assert_eq!(checked_dom_len(::std::usize::MAX - 1, 2, ::std::usize::MAX), Err(TooManyNodes { current: ::std::usize::MAX - 1, added: 2, max: ::std::usize::MAX }));
+
+    let node = Node { parent: Some(NodeId::new(0)), last_child: Some(NodeId::new(5)), .. Node::default() };
+    let offset = ::std::usize::MAX - 10;
+    assert_eq!(shift_node_links(node, 100, 106), Some(Node { parent: Some(NodeId::new(100)), last_child: Some(NodeId::new(105)), .. Node::default() }));
+    assert_eq!(shift_node_links(node, 100, 105), None);
+    assert_eq!(shift_node_links(node, offset, ::std::usize::MAX), Some(Node { parent: Some(NodeId::new(offset)), last_child: Some(NodeId::new(offset + 5)), .. Node::default() }));
+    assert_eq!(shift_node_links(Node { last_child: Some(NodeId::new(20)), .. node }, offset, ::std::usize::MAX), None);
+
+    let child = || Dom::div().with_child(Dom::label("a")).with_child(Dom::label("b"));
+
+    // Same result as add_child
+    let mut dom = Dom::<TestLayout>::div().with_child(Dom::div());
+    dom.try_add_child(child()).unwrap();
+    assert_eq!(dom, Dom::div().with_child(Dom::div()).with_child(child()));
+    assert_eq!(dom.validate(), Ok(()));
+
+    // Over the limit, the DOM is unchanged
+    let original = dom.clone();
+    let len = dom.len();
+    assert_eq!(dom.try_add_child_with_limit(child(), len + 2), Err(TooManyNodes { current: len, added: 3, max: len + 2 }));
+    assert_eq!(dom.len(), len);
+    assert_eq!(dom.arena.node_layout, original.arena.node_layout);
+    dom.try_add_child_with_limit(child(), len + 3).unwrap();
+    assert_eq!(dom.len(), len + 3);
+
+    // A corrupted link of the child doesn't leave half of the child appended
+    let original = dom.clone();
+    let mut corrupted = child();
+    corrupted.arena.node_layout[NodeId::new(1)].last_child = Some(NodeId::new(50));
+    assert_eq!(dom.try_add_child(corrupted), Err(LinkOutOfBounds { node: NodeId::new(1) }));
+    assert_eq!(dom.arena.node_layout, original.arena.node_layout);
+    assert_eq!(dom, original);
+}
+
+#[test]
+#[should_panic(expected = "add_child: Node 1 of the appended DOM links to a node outside of it")]
+fn test_add_child_rejects_corrupted_links() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    // Same check in debug and release builds, only the node limit is debug-only
+    let mut corrupted = Dom::<TestLayout>::div().with_child(Dom::label("a")).with_child(Dom::label("b"));
+    corrupted.arena.node_layout[NodeId::new(1)].last_child = Some(NodeId::new(50));
+    Dom::div().add_child(corrupted);
+}
+
+#[test]
+fn test_try_into_ui_state_rejects_malformed_doms() {
+
+    use self::DomError::*;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    // root -> a, b
+    let valid = || Dom::<TestLayout>::div().with_child(Dom::label("a")).with_child(Dom::label("b"));
+    let error = |dom: Dom<TestLayout>| dom.try_into_ui_state(&mut TagIdGenerator::new()).err();
+
+    assert_eq!(error(valid()), None);
+    assert_eq!(error(dom_with_sibling_of_root()), Some(MultipleRoots { root: NodeId::new(0), sibling: NodeId::new(2) }));
+
+    // b -> a
+    let mut cycle = valid();
+    cycle.arena.node_layout[NodeId::new(2)].next_sibling = Some(NodeId::new(1));
+    assert_eq!(error(cycle), Some(Cycle { node: NodeId::new(1) }));
+
+    // b isn't in the chain of children of the root anymore
+    let mut unreachable = valid();
+    unreachable.arena.node_layout[NodeId::new(1)].next_sibling = None;
+    unreachable.arena.node_layout[NodeId::new(0)].last_child = Some(NodeId::new(1));
+    assert_eq!(error(unreachable), Some(Unreachable { node: NodeId::new(2) }));
+
+    let mut root_has_parent = valid();
+    root_has_parent.arena.node_layout[NodeId::new(0)].parent = Some(NodeId::new(1));
+    assert_eq!(error(root_has_parent), Some(RootHasParent { root: NodeId::new(0) }));
+
+    let mut broken_link = valid();
+    broken_link.arena.node_layout[NodeId::new(2)].previous_sibling = None;
+    assert_eq!(error(broken_link), Some(BrokenSiblingLink { node: NodeId::new(2) }));
+
+    let mut out_of_bounds = valid();
+    out_of_bounds.arena.node_layout[NodeId::new(1)].first_child = Some(NodeId::new(10));
+    assert_eq!(error(out_of_bounds), Some(OutOfBounds { node: NodeId::new(1), target: NodeId::new(10) }));
+}
+
+/// Test that there shouldn't be a DOM that has 0 nodes
+#[test]
+fn test_zero_size_dom() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    let mut null_dom =
+        (0..0)
+        .map(|_| NodeData { node_type: NodeType::Div, .. Default::default() })
+        .collect::<Dom<TestLayout>>();
+
+    assert!(null_dom.arena.len() == 1);
+
+    null_dom.add_class("hello"); // should not panic
+    null_dom.add_id("id-hello"); // should not panic
+}
+
+#[test]
+fn test_node_data_hash_cache_invalidation() {
+
+    use azul_css::LayoutWidth;
+    use default_callbacks::get_new_unique_default_callback_id;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    fn test_callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        Redraw
+    }
+
+    let mut node = NodeData::<TestLayout>::new(NodeType::Div);
+
+    macro_rules! assert_invalidates_hash {
+        ($mutation:expr) => {
+            let old_hash = node.calculate_node_data_hash();
+            assert_eq!(node.hash_cache.node.get(), Some(old_hash));
+            $mutation;
+            assert_eq!(node.hash_cache.node.get(), None);
+            assert!(node.calculate_node_data_hash() != old_hash);
+        };
+    }
+
+    assert_invalidates_hash!(node.set_node_type(NodeType::Label("hello".into())));
+    assert!(node.hash_cache.content.get().is_some());
+    assert_invalidates_hash!(node.add_id("id"));
+    assert_invalidates_hash!(node.add_class("class"));
+    assert_invalidates_hash!(node.add_callback(On::MouseUp, Callback(test_callback)));
+    assert_invalidates_hash!(node.add_default_callback_id(On::MouseUp, get_new_unique_default_callback_id()));
+    assert_invalidates_hash!(node.add_css_override("width", CssProperty::Width(LayoutWidth::px(500.0))));
+    assert_invalidates_hash!(node.set_draggable(true));
+    assert_invalidates_hash!(node.set_tab_index(Some(TabIndex::Auto)));
+    assert_invalidates_hash!(node.add_attribute("row", "1"));
+    assert_invalidates_hash!(node.add_attribute("row", "2"));
+
+    // Changing the classes must not re-hash the text of the label
+    let content_hash = node.hash_cache.content.get();
+    node.add_class("other_class");
+    assert_eq!(node.hash_cache.content.get(), content_hash);
+
+    // Changing the text must invalidate the text hash
+    node.set_node_type(NodeType::Label("world".into()));
+    assert!(node.hash_cache.content.get() != content_hash);
+}
+
+#[test]
+fn test_dom_hash_is_cached_across_passes() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    let dom = (0..10_000)
+        .map(|i| {
+            let mut node = NodeData::new(NodeType::Label(format!("node {}", i)));
+            node.add_class("item");
+            node
+        })
+        .collect::<Dom<TestLayout>>();
+
+    assert!(dom.arena.node_data.iter().all(|n| n.hash_cache.node.get().is_none()));
+    let first_pass = dom.arena.node_data.iter().map(|n| n.calculate_node_data_hash()).collect::<Vec<_>>();
+    assert!(dom.arena.node_data.iter().all(|n| n.hash_cache.node.get().is_some()));
+
+    // Replace the cached hashes with markers - if the second pass re-hashed
+    // any node, it would return the real hash of that node instead of the marker
+    let markers = (0..first_pass.len()).map(|i| DomHash(i as u64)).collect::<Vec<_>>();
+    for (node, marker) in dom.arena.node_data.iter().zip(markers.iter()) {
+        node.hash_cache.node.set(Some(*marker));
+    }
+    let second_pass = dom.arena.node_data.iter().map(|n| n.calculate_node_data_hash()).collect::<Vec<_>>();
+    assert_eq!(second_pass, markers);
+}
+
+#[test]
+fn test_node_attributes() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    fn table(row_key: &str) -> Dom<TestLayout> {
+        Dom::div().with_id("table")
+            .with_child(Dom::div().with_class("row").with_attribute("db-key", row_key).with_attribute("kind", "user")
+                .with_child(Dom::label("Alice")))
+    }
+
+    // One value per key, sorted by key
+    let node = NodeData::<TestLayout>::div().with_attribute("b", "1").with_attribute("a", "2").with_attribute("b", "3");
+    assert_eq!(node.attributes, vec![("a".to_string(), "2".to_string()), ("b".to_string(), "3".to_string())]);
+    assert_eq!(node.get_attribute("b"), Some("3"));
+    assert_eq!(node.get_attribute("c"), None);
+
+    // The attributes stay on their node when the DOM is appended to another DOM
+    let mut dom = Dom::<TestLayout>::div().with_child(Dom::div().with_id("header"));
+    dom.add_child(table("42"));
+    let row = dom.find_all_by_class("row").next().unwrap();
+    assert_eq!(dom.arena.node_data[row].get_attribute("db-key"), Some("42"));
+    assert_eq!(dom.arena.node_data[row].get_attribute("kind"), Some("user"));
+
+    let ui_state = UiState::from_dom(dom);
+    assert_eq!(ui_state.dom.arena.node_data[row].get_attribute("db-key"), Some("42"));
+
+    // Changing an attribute changes the hash of the DOM, so that the frame is re-rendered
+    assert_eq!(table("42").calculate_dom_hash(), table("42").calculate_dom_hash());
+    assert!(table("42").calculate_dom_hash() != table("43").calculate_dom_hash());
+    assert!(table("42") != table("43"));
+}
+
+/// Two UiStates that are built at the same time (i.e. for two windows)
+/// must not interfere with each others tag numbering
+#[test]
+fn test_concurrent_ui_states_have_independent_tags() {
+
+    use std::thread;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    fn test_callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        Redraw
+    }
+
+    fn build_tag_map() -> BTreeMap<TagId, NodeId> {
+        let dom: Dom<TestLayout> = (0..1000).map(|i| {
+            let mut node = NodeData::new(NodeType::Div);
+            if i % 2 == 0 {
+                node.add_callback(On::MouseUp, Callback(test_callback));
+            }
+            node
+        }).collect();
+        let mut tag_ids = TagIdGenerator::new();
+        dom.into_ui_state(&mut tag_ids).tag_ids_to_node_ids
+    }
+
+    let expected = build_tag_map();
+
+    let threads = (0..2).map(|_| thread::spawn(build_tag_map)).collect::<Vec<_>>();
+
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_dom_allocator_reuses_memory_without_stale_data() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    let mut allocator = DomAllocator::<TestLayout>::new();
+    assert_eq!(allocator.capacity(), 0);
+
+    // Frame 1: build a large DOM, then recycle it
+    let mut frame_1 = Dom::with_capacity_in(NodeType::Div, 100, &mut allocator);
+    for i in 0..100 {
+        frame_1.add_child(Dom::label(format!("frame 1, node {}", i)).with_class("stale"));
+    }
+    frame_1.add_class("stale");
+    allocator.recycle(frame_1);
+    assert!(allocator.capacity() >= 101);
+
+    // Frame 2: the memory is re-used, but none of the nodes of frame 1 are visible
+    let frame_2 = Dom::with_capacity_in(NodeType::Div, 0, &mut allocator);
+    assert_eq!(allocator.capacity(), 0);
+    assert_eq!(frame_2.len(), 1);
+    assert!(frame_2.arena.node_data.capacity() >= 101);
+    assert_eq!(frame_2.arena.node_data[frame_2.root], NodeData::new(NodeType::Div));
+    assert_eq!(frame_2.arena.node_layout[frame_2.root], Node::default());
+
+    // The nodes of frame 1 are kept (cleared), the root of frame 2 already took one
+    assert_eq!(allocator.spare_nodes(), 100);
+    let node = allocator.node(NodeType::Label("frame 2".into()));
+    assert_eq!(node, NodeData::label("frame 2"));
+    assert!(node.classes.is_empty() && node.classes.capacity() >= 1);
+    assert_eq!(node.calculate_node_data_hash(), NodeData::<TestLayout>::label("frame 2").calculate_node_data_hash());
+
+    // No more spare nodes are kept than the last frame had nodes
+    allocator.recycle(frame_2);
+    assert_eq!(allocator.spare_nodes(), 1);
+}
+
+#[test]
+fn test_ui_state_callback_lookup() {
+
+    use ui_state::find_callback;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    fn callback_a(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        Redraw
+    }
+
+    fn callback_b(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        DontRedraw
+    }
+
+    let dom: Dom<TestLayout> = (0..10_000).map(|i| {
+        let mut node = NodeData::new(NodeType::Div);
+        if i % 100 == 0 {
+            node.add_callback(On::MouseUp, Callback(callback_a));
+            node.add_callback(On::MouseDown, Callback(callback_a));
+            // Registering the same filter twice: the last callback wins
+            node.add_callback(On::MouseUp, Callback(callback_b));
+            node.add_callback(On::TextInput, Callback(callback_b));
+            node.add_callback(On::VirtualKeyDown, Callback(callback_a));
+        }
+        node
+    }).collect();
+
+    let mut tag_ids = TagIdGenerator::new();
+    let ui_state = dom.into_ui_state(&mut tag_ids);
+
+    // Only the nodes that actually have callbacks are stored
+    assert_eq!(ui_state.hover_callbacks.len(), 100);
+    assert_eq!(ui_state.focus_callbacks.len(), 100);
+    assert_eq!(ui_state.window_callbacks.len(), 100);
+    assert!(ui_state.not_callbacks.is_empty());
+    assert!(ui_state.hover_default_callbacks.is_empty());
+
+    for i in 0..10_000 {
+        let node_id = NodeId::new(i);
+        let hover = ui_state.hover_callbacks.get(&node_id);
+        if i % 100 != 0 {
+            assert!(hover.is_none());
+            assert!(!ui_state.node_ids_to_tag_ids.contains_key(&node_id));
+            continue;
+        }
+
+        let hover = hover.unwrap();
+        assert_eq!(hover.len(), 2);
+        assert_eq!(find_callback(hover, &HoverEventFilter::MouseUp), Some(Callback(callback_b)));
+        assert_eq!(find_callback(hover, &HoverEventFilter::MouseDown), Some(Callback(callback_a)));
+        assert_eq!(find_callback(hover, &HoverEventFilter::MouseOver), None);
+
+        let focus = &ui_state.focus_callbacks[&node_id];
+        assert_eq!(find_callback(focus, &FocusEventFilter::TextInput), Some(Callback(callback_b)));
+
+        let window = &ui_state.window_callbacks[&node_id];
+        assert_eq!(find_callback(window, &WindowEventFilter::VirtualKeyDown), Some(Callback(callback_a)));
+
+        // Hover and focus callbacks need a tag for hit-testing, window callbacks don't
+        assert!(ui_state.node_ids_to_tag_ids.contains_key(&node_id));
+    }
+}
+
+#[test]
+fn test_ui_state_deduplicates_css_overrides() {
+
+    use std::rc::Rc;
+    use azul_css::{LayoutWidth, LayoutHeight};
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    let dom: Dom<TestLayout> = Dom::new(NodeType::Div)
+        .with_child(Dom::new(NodeType::Div)
+            .with_css_override("width", CssProperty::Width(LayoutWidth::px(100.0)))
+            .with_css_override("height", CssProperty::Height(LayoutHeight::px(50.0)))
+            .with_css_override("width", CssProperty::Width(LayoutWidth::px(200.0))))
+        .with_child(Dom::new(NodeType::Div));
+
+    let mut tag_ids = TagIdGenerator::new();
+    let ui_state = dom.into_ui_state(&mut tag_ids);
+
+    // Only the node that has overrides is stored, the last override for "width" wins,
+    // but keeps the position of the first "width" override
+    assert_eq!(ui_state.dynamic_css_overrides.len(), 1);
+    let overrides = &ui_state.dynamic_css_overrides[&NodeId::new(1)];
+    assert_eq!(&overrides[..], &[
+        (String::from("width"), CssProperty::Width(LayoutWidth::px(200.0))),
+        (String::from("height"), CssProperty::Height(LayoutHeight::px(50.0))),
+    ][..]);
+
+    // Cloning the overrides (i.e. for the UiDescription) doesn't copy the properties
+    let cloned = ui_state.dynamic_css_overrides.clone();
+    assert!(Rc::ptr_eq(&cloned[&NodeId::new(1)], overrides));
+}
+
+#[test]
+fn test_dom_snapshot_is_canonical() {
+
+    use azul_css::LayoutWidth;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div).with_class("b").with_id("main").with_class("a")
+                .with_child(Dom::new(NodeType::Label("Hello".into()))
+                    .with_tab_index(TabIndex::Auto)
+                    .with_callback(On::MouseUp, Callback(on_click))
+                    .with_callback(On::MouseOver, Callback(unnamed))
+                    .with_css_override("width", CssProperty::Width(LayoutWidth::px(10.0)))
+                    .with_css_override("height", CssProperty::Width(LayoutWidth::px(5.0)))
+                    .with_css_override("width", CssProperty::Width(LayoutWidth::px(20.0))))
+                .with_child(Dom::new(NodeType::Div).with_class("a").with_class("a"))
+        }
+    }
+
+    fn on_click(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        Redraw
+    }
+
+    fn unnamed(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        DontRedraw
+    }
+
+    let mut registry = CallbackRegistry::new();
+    registry.register_callback("on_click", Callback(on_click));
+
+    let first = TestLayout { }.layout().serialize_for_snapshot_with_names(&registry);
+    let second = TestLayout { }.layout().serialize_for_snapshot_with_names(&registry);
+    assert_eq!(first.as_bytes(), second.as_bytes());
+
+    let expected = "\
+div #main .a .b
+    label \"Hello\" tabindex=Auto
+      on Hover(MouseOver) -> <fn>
+      on Hover(MouseUp) -> on_click
+      [[ height ]] = Width(LayoutWidth(PixelValue { metric: Px, number: FloatValue { number: 50000 } }))
+      [[ width ]] = Width(LayoutWidth(PixelValue { metric: Px, number: FloatValue { number: 200000 } }))
+    div .a
+";
+    assert_eq!(first, expected);
+
+    // Without a registry, no callback name (or address) is printed
+    let unnamed_snapshot = TestLayout { }.layout().serialize_for_snapshot();
+    assert!(unnamed_snapshot.contains("on Hover(MouseUp) -> <fn>"));
+    assert!(!unnamed_snapshot.contains("0x"));
+}
+
+#[test]
+fn test_ids_and_classes_are_order_insensitive() {
+
+    use std::collections::hash_map::DefaultHasher;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn hash_of(node: &NodeData<TestLayout>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        node.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let mut abc = NodeData::<TestLayout>::default();
+    for class in &["a", "b", "c"] {
+        abc.add_class(*class);
+    }
+    abc.add_id("x");
+    abc.add_id("y");
+
+    let mut cab = NodeData::<TestLayout>::default();
+    for class in &["c", "a", "b", "a", "c"] {
+        cab.add_class(*class);
+    }
+    cab.add_id("y");
+    cab.add_id("x");
+    cab.add_id("y");
+
+    // add_class / add_id keep the lists sorted and deduplicated
+    assert_eq!(cab.classes, vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+    assert_eq!(cab.ids, vec!["x".to_string(), "y".to_string()]);
+    assert_eq!(abc, cab);
+    assert_eq!(hash_of(&abc), hash_of(&cab));
+    assert_eq!(abc.to_string(), "[div #x #y .a .b .c]");
+    assert_eq!(cab.to_string(), abc.to_string());
+
+    // Lists that were modified directly are compared as sets, too
+    let mut direct = NodeData::<TestLayout>::default();
+    direct.classes = vec!["c".into(), "b".into(), "a".into(), "b".into()];
+    direct.ids = vec!["y".into(), "x".into()];
+    assert_eq!(direct, abc);
+    assert_eq!(hash_of(&direct), hash_of(&abc));
+    assert_eq!(direct.to_string(), abc.to_string());
+
+    let mut different = abc.clone();
+    different.add_class("d");
+    assert!(different != abc);
+    assert!(hash_of(&different) != hash_of(&abc));
+
+    let first: Dom<TestLayout> = Dom::div().with_class("b").with_class("a").with_class("b");
+    let second: Dom<TestLayout> = Dom::div().with_class("a").with_class("b");
+    assert_eq!(first.serialize_for_snapshot(), second.serialize_for_snapshot());
+    assert_eq!(first.serialize_for_snapshot(), "div .a .b\n");
+}
+
+#[test]
+fn test_node_type_checks_and_accessors() {
+
+    use images::new_image_id;
+    use text_cache::new_text_id;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn texture(_: &StackCheckedPointer<TestLayout>, _: LayoutInfo<TestLayout>, _: HidpiAdjustedBounds) -> Option<Texture> {
+        None
+    }
+
+    fn other_texture(_: &StackCheckedPointer<TestLayout>, _: LayoutInfo<TestLayout>, _: HidpiAdjustedBounds) -> Option<Texture> {
+        None
+    }
+
+    fn iframe(_: &StackCheckedPointer<TestLayout>, _: LayoutInfo<TestLayout>, _: HidpiAdjustedBounds) -> Dom<TestLayout> {
+        Dom::div()
+    }
+
+    let data = TestLayout { };
+    let image_id = new_image_id();
+    let text_id = new_text_id();
+
+    let div = Dom::<TestLayout>::div();
+    assert!(div.is_div() && !div.is_label() && !div.is_image());
+    assert_eq!(div.node_type_path(), NodeTypePath::Div);
+    assert_eq!(div.get_label_text(), None);
+
+    let label = Dom::<TestLayout>::label("Hello");
+    assert!(label.is_label() && !label.is_div() && !label.is_text());
+    assert_eq!(label.node_type_path(), NodeTypePath::P);
+    assert_eq!(label.get_label_text(), Some("Hello"));
+
+    let text = Dom::<TestLayout>::new(NodeType::Text(text_id));
+    assert!(text.is_text() && !text.is_label());
+    assert_eq!(text.node_type_path(), NodeTypePath::P);
+    assert_eq!(text.get_text_id(), Some(text_id));
+    assert_eq!(text.get_image_id(), None);
+
+    let image = Dom::<TestLayout>::new(NodeType::Image(image_id));
+    assert!(image.is_image() && !image.is_div());
+    assert_eq!(image.node_type_path(), NodeTypePath::Img);
+    assert_eq!(image.get_image_id(), Some(image_id));
+    assert_eq!(image.get_text_id(), None);
+
+    let gl_texture = Dom::gl_texture(GlTextureCallback(texture), StackCheckedPointer::new(&data, &data).unwrap());
+    assert!(gl_texture.is_gl_texture() && !gl_texture.is_iframe());
+    assert_eq!(gl_texture.node_type_path(), NodeTypePath::Texture);
+    assert_eq!(gl_texture.get_gl_texture_callback(), Some(GlTextureCallback(texture)));
+    assert!(gl_texture.get_gl_texture_callback() != Some(GlTextureCallback(other_texture)));
+    assert_eq!(gl_texture.get_iframe_callback(), None);
+
+    let iframe_dom = Dom::iframe(IFrameCallback(iframe), StackCheckedPointer::new(&data, &data).unwrap());
+    assert!(iframe_dom.is_iframe() && !iframe_dom.is_gl_texture());
+    assert_eq!(iframe_dom.node_type_path(), NodeTypePath::IFrame);
+    assert_eq!(iframe_dom.get_iframe_callback(), Some(IFrameCallback(iframe)));
+    assert_eq!(iframe_dom.get_gl_texture_callback(), None);
+
+    // The Dom methods operate on the head node, not on the last added child
+    let nested = Dom::<TestLayout>::label("parent").with_child(Dom::div());
+    assert!(nested.is_label() && !nested.is_div());
+    assert_eq!(nested.get_label_text(), Some("parent"));
+}
+
+#[test]
+fn test_builders_after_collect_target_container_or_children() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn row(idx: usize) -> Dom<TestLayout> {
+        Dom::label(format!("{}", idx))
+    }
+
+    // with_class after collect() styles the (synthetic) container
+    let list = (0..3).map(row).collect::<Dom<TestLayout>>().with_class("list");
+    assert!(list.get_node_content(list.get_root()).unwrap().is_synthetic_root);
+    assert_eq!(list.serialize_for_snapshot(), "\
+div <synthetic-root> .list
+    label \"0\"
+    label \"1\"
+    label \"2\"
+");
+
+    let from_node_data = vec![NodeData::new(NodeType::Div)].into_iter().collect::<Dom<TestLayout>>();
+    assert!(from_node_data.get_node_content(from_node_data.get_root()).unwrap().is_synthetic_root);
+    assert!(format!("{}", from_node_data.get_node_content(from_node_data.get_root()).unwrap()).contains("<synthetic-root>"));
+
+    // The marker is part of the node, since equal DOMs have to have equal snapshots
+    let mut appended = Dom::div().with_class("list").with_child(row(0)).with_child(row(1)).with_child(row(2));
+    assert!(!appended.serialize_for_snapshot().contains("<synthetic-root>"));
+    assert!(list != appended);
+    appended.arena.node_data[appended.root].is_synthetic_root = true;
+    appended.arena.node_data[appended.root].invalidate_hash_cache();
+    assert_eq!(list, appended);
+
+    // with_class_on_children / map_children style the collected nodes instead
+    let rows = (0..3).map(row).collect::<Dom<TestLayout>>()
+        .with_class_on_children("row")
+        .map_children(|child| child.set_draggable(true));
+    assert_eq!(rows.serialize_for_snapshot(), "\
+div <synthetic-root>
+    label \"0\" .row draggable
+    label \"1\" .row draggable
+    label \"2\" .row draggable
+");
+
+    // Only direct children are affected
+    let nested = Dom::<TestLayout>::div().with_child(Dom::div().with_child(Dom::div())).with_class_on_children("child");
+    assert_eq!(nested.serialize_for_snapshot(), "div\n    div .child\n        div\n");
+
+    // collect_with_container supplies the real container
+    let mut container = NodeData::new(NodeType::Div);
+    container.add_class("list");
+    let list = Dom::collect_with_container(container, (0..3).map(row));
+    assert_eq!(list.serialize_for_snapshot(), "\
+div .list
+    label \"0\"
+    label \"1\"
+    label \"2\"
+");
+    assert!(list.validate().is_ok());
+}
+
+#[test]
+fn test_dom_semantic_equality_and_content_eq() {
+
+    use text_cache::TextCache;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn on_click(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        Redraw
+    }
+
+    fn on_click_2(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        DontRedraw
+    }
+
+    fn texture(_: &StackCheckedPointer<TestLayout>, _: LayoutInfo<TestLayout>, _: HidpiAdjustedBounds) -> Option<Texture> {
+        None
+    }
+
+    let mut text_cache = TextCache::default();
+    let hello_1 = text_cache.add_text("Hello");
+    let hello_2 = text_cache.add_text("Hello");
+    let world = text_cache.add_text("World");
+    let strings = &text_cache.string_cache;
+
+    let build = |classes: &[&str], text: TextId, callback: Callback<TestLayout>| -> Dom<TestLayout> {
+        let mut dom = Dom::div().with_child(Dom::text_id(text).with_callback(On::MouseUp, callback));
+        for class in classes {
+            dom.add_class(*class);
+        }
+        dom
+    };
+
+    let a = build(&["a", "b"], hello_1, Callback(on_click));
+
+    // Class order doesn't matter, the text content does
+    assert_eq!(a, build(&["b", "a", "b"], hello_1, Callback(on_click)));
+    assert!(a != build(&["a"], hello_1, Callback(on_click)));
+    assert!(a != build(&["a", "b"], world, Callback(on_click)));
+
+    // Different TextIds with the same text and different callbacks aren't equal, but render the same
+    let b = build(&["a", "b"], hello_2, Callback(on_click_2));
+    assert!(a != b);
+    assert!(a.content_eq_with_strings(&b, strings));
+    assert!(!a.content_eq_with_strings(&build(&["a", "b"], world, Callback(on_click)), strings));
+    assert!(!a.content_eq_with_strings(&build(&["a"], hello_1, Callback(on_click)), strings));
+    assert!(!a.content_eq_with_strings(&Dom::div(), strings));
+
+    // Textures are equal by identity, but never content-equal
+    let data = TestLayout { };
+    let gl_texture = Dom::gl_texture(GlTextureCallback(texture), StackCheckedPointer::new(&data, &data).unwrap());
+    assert_eq!(gl_texture, gl_texture.clone());
+    assert!(!gl_texture.content_eq_with_strings(&gl_texture.clone(), strings));
+
+    // Equal DOMs have equal hashes and snapshots
+    let c = build(&["b", "a"], hello_1, Callback(on_click));
+    assert_eq!(a.calculate_dom_hash(), c.calculate_dom_hash());
+    assert_eq!(a.serialize_for_snapshot(), c.serialize_for_snapshot());
+}
 
-                    // Filter and insert FocusEventFilter callbacks
-                    filter_and_insert_callbacks!(
-                        node_id,
-                        data.default_callback_ids,
-                        FocusEventFilter,
-                        DefaultCallbackId,
-                        as_focus_event_filter,
-                        focus_default_callbacks,
-                        node_tag_id,
-                    );
+#[test]
+fn test_ui_state_tag_assignment_rules() {
 
-                    filter_and_insert_callbacks!(
-                        node_id,
-                        data.default_callback_ids,
-                        NotEventFilter,
-                        DefaultCallbackId,
-                        as_not_event_filter,
-                        not_default_callbacks,
-                        node_tag_id,
-                    );
+    use ui_state::UiStateSummary;
 
-                    filter_and_insert_callbacks!(
-                        node_id,
-                        data.default_callback_ids,
-                        WindowEventFilter,
-                        DefaultCallbackId,
-                        as_window_event_filter,
-                        window_default_callbacks,
-                    );
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    fn callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        DontRedraw
+    }
+
+    let mut draggable = Dom::new(NodeType::Div);
+    draggable.set_draggable(true);
+
+    let dom: Dom<TestLayout> = Dom::new(NodeType::Div)
+        // 1: only a window callback - no tag
+        .with_child(Dom::new(NodeType::Div).with_callback(On::VirtualKeyDown, Callback(callback)))
+        // 2: hover callback - tag
+        .with_child(Dom::new(NodeType::Div).with_callback(On::MouseUp, Callback(callback)))
+        // 3: focusable - tag
+        .with_child(Dom::new(NodeType::Div).with_tab_index(TabIndex::Auto))
+        // 4: draggable - tag
+        .with_child(draggable)
+        // 5: hover callback + focusable - only one tag for both
+        .with_child(Dom::new(NodeType::Div)
+            .with_callback(On::MouseOver, Callback(callback))
+            .with_tab_index(TabIndex::Global(5)))
+        // 6: only CSS overrides - no tag
+        .with_child(Dom::new(NodeType::Div).with_css_override("width", CssProperty::Width(::azul_css::LayoutWidth::px(5.0))));
+
+    let ui_state = UiState::from_dom(dom);
+
+    assert_eq!(ui_state.summary(), UiStateSummary {
+        nodes: 7,
+        tags: 4,
+        tab_index_tags: 2,
+        draggable_tags: 1,
+        nodes_with_hover_callbacks: 2,
+        nodes_with_focus_callbacks: 0,
+        nodes_with_not_callbacks: 0,
+        nodes_with_window_callbacks: 1,
+        nodes_with_desktop_callbacks: 0,
+        nodes_with_css_overrides: 1,
+    });
+
+    let tagged_nodes = ui_state.node_ids_to_tag_ids().keys().map(|n| n.index()).collect::<Vec<_>>();
+    assert_eq!(tagged_nodes, vec![2, 3, 4, 5]);
+
+    // The two maps are the reverse of each other
+    for (tag_id, node_id) in ui_state.tag_ids_to_node_ids() {
+        assert_eq!(ui_state.node_ids_to_tag_ids()[node_id], *tag_id);
+    }
+
+    assert_eq!(ui_state.nodes_with_window_callbacks(), vec![NodeId::new(1)]);
+    assert_eq!(ui_state.nodes_with_hover_callbacks(), vec![NodeId::new(2), NodeId::new(5)]);
+    assert_eq!(ui_state.draggable_tags().values().cloned().collect::<Vec<_>>(), vec![NodeId::new(4)]);
+
+    // Node 5 has one tag, shared by the focus and the hover callback
+    let node_5_tag = ui_state.node_ids_to_tag_ids()[&NodeId::new(5)];
+    assert_eq!(ui_state.tab_index_tags()[&node_5_tag], (NodeId::new(5), TabIndex::Global(5)));
+
+    assert_eq!(ui_state.hover_callbacks(NodeId::new(2)), &[(HoverEventFilter::MouseUp, Callback(callback))][..]);
+    assert!(ui_state.hover_callbacks(NodeId::new(1)).is_empty());
+    assert_eq!(ui_state.window_callbacks(NodeId::new(1)).len(), 1);
+}
+
+#[test]
+fn test_with_callback_on_descendants() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn on_hover(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        DontRedraw
+    }
+
+    // 0: menu, 1: "a", 2: submenu, 3: "b", 4: separator, 5: "c" - the IDs
+    // of the submenu are shifted when it is merged into the menu
+    let menu = || {
+        let submenu = Dom::div().with_class("submenu")
+            .with_child(Dom::label("b").with_class("menu-item"))
+            .with_child(Dom::label("-"))
+            .with_child(Dom::label("c").with_class("menu-item"));
+        Dom::<TestLayout>::div().with_class("menu")
+            .with_child(Dom::label("a").with_class("menu-item"))
+            .with_child(submenu)
+    };
+
+    let mut tag_ids = TagIdGenerator::new();
+
+    let ui_state = menu()
+        .with_callback_on_descendants(On::MouseOver, Callback(on_hover), |node| node.has_class("menu-item"))
+        .into_ui_state(&mut tag_ids);
+
+    assert_eq!(ui_state.nodes_with_hover_callbacks(), vec![NodeId::new(1), NodeId::new(3), NodeId::new(5)]);
+    assert_eq!(ui_state.hover_callbacks(NodeId::new(3)), &[(HoverEventFilter::MouseOver, Callback(on_hover))][..]);
+
+    // Only the subtree of the head is visited, the head itself only gets the callback if it matches
+    let mut dom = menu();
+    dom.head = NodeId::new(2);
+    let ui_state = dom
+        .with_callback_on_descendants(On::MouseOver, Callback(on_hover), |node| node.has_class("menu-item") || node.has_class("submenu"))
+        .into_ui_state(&mut tag_ids);
+
+    assert_eq!(ui_state.nodes_with_hover_callbacks(), vec![NodeId::new(2), NodeId::new(3), NodeId::new(5)]);
+}
+
+#[test]
+fn test_get_node_by_id_after_add_child() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn on_click(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen { Redraw }
+
+    let form = || Dom::div().with_id("form")
+        .with_child(Dom::label("Name").with_id("name"))
+        .with_child(Dom::div().with_id("buttons")
+            .with_child(Dom::label("Cancel").with_id("cancel-button"))
+            .with_child(Dom::label("Submit").with_id("submit-button")));
+
+    let standalone: Dom<TestLayout> = form();
+    assert_eq!(standalone.get_node_by_id("submit-button"), Some(NodeId::new(4)));
+
+    // The IDs of the nodes of the form are offset by the nodes that were added before it
+    let mut dom = Dom::div()
+        .with_child(Dom::div().with_id("header").with_child(Dom::label("Title")))
+        .with_child(form())
+        .with_child(Dom::div().with_id("footer"));
+
+    assert_eq!(dom.get_node_by_id("header"), Some(NodeId::new(1)));
+    assert_eq!(dom.get_node_by_id("form"), Some(NodeId::new(3)));
+    assert_eq!(dom.get_node_by_id("submit-button"), Some(NodeId::new(7)));
+    assert_eq!(dom.get_node_by_id("footer"), Some(NodeId::new(8)));
+    assert_eq!(dom.get_node_by_id("missing"), None);
+
+    let submit = dom.get_node_by_id("submit-button").unwrap();
+    assert_eq!(dom.get_node_data(submit).and_then(|node| node.get_label_text()), Some("Submit"));
+    assert_eq!(dom.get_node_hierarchy()[submit].parent, dom.get_node_by_id("buttons"));
+    assert_eq!(dom.get_node_data(NodeId::new(dom.len())), None);
+
+    // Modifying the node changes the hash of the DOM
+    let hash_before = dom.calculate_dom_hash();
+    dom.get_node_data_mut(submit).unwrap().callbacks.push((On::MouseUp.into(), Callback(on_click)));
+    assert!(dom.calculate_dom_hash() != hash_before);
+    assert_eq!(dom.get_node_data(submit).unwrap().callbacks.len(), 1);
+
+    // Duplicate IDs: the node that was added first wins
+    let dom: Dom<TestLayout> = Dom::div()
+        .with_child(Dom::div().with_child(Dom::label("first").with_id("duplicate")))
+        .with_child(Dom::label("second").with_id("duplicate"));
+    assert_eq!(dom.get_node_by_id("duplicate"), Some(NodeId::new(2)));
+}
+
+#[test]
+fn test_find_all_by_class_in_document_order() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn on_click(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen { Redraw }
+
+    let rows = |count: usize| (0..count)
+        .map(|i| NodeData { classes: vec!["row".to_string()], .. NodeData::new(NodeType::Label(format!("{}", i))) })
+        .collect::<Dom<TestLayout>>();
+
+    // Nested rows come before the following siblings of their parent
+    let mut table = Dom::div().with_class("table")
+        .with_child(rows(2).with_class("row"))
+        .with_child(Dom::label("footer"));
+    table.add_child(rows(1));
+
+    // Added after the children were appended
+    let mut dom = Dom::div().with_child(table).with_class("row");
+
+    let labels = dom.find_all_by_class("row")
+        .map(|node_id| dom.get_node_data(node_id).unwrap().get_label_text().map(|s| s.to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(labels, vec![None, None, Some("0".to_string()), Some("1".to_string()), Some("0".to_string())]);
+    assert_eq!(dom.find_all_by_class("row").next(), Some(dom.get_root()));
+    assert_eq!(dom.find_all_by_class("table").count(), 1);
+    assert_eq!(dom.find_all_by_class("missing").count(), 0);
+
+    let hash_before = dom.calculate_dom_hash();
+    dom.mutate_nodes_by_class("row", |node| node.add_callback(On::MouseUp, Callback(on_click)));
+    assert!(dom.calculate_dom_hash() != hash_before);
+    let nodes_with_callbacks = dom.find_all_by_class("row")
+        .filter(|node_id| dom.get_node_data(*node_id).unwrap().callbacks.len() == 1)
+        .count();
+    assert_eq!(nodes_with_callbacks, 5);
+}
+
+#[test]
+fn test_dom_select() {
+
+    use self::DomSelectorParseError::*;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let rows = |count: usize| (0..count)
+        .map(|i| Dom::div().with_class("row").with_child(Dom::label(format!("{}", i)).with_class("cell")))
+        .collect::<Vec<_>>();
+
+    let mut table = Dom::div().with_id("table");
+    table.add_children(rows(10));
+    let mut dom = Dom::<TestLayout>::div().with_id("main").with_class("dark")
+        .with_child(table)
+        .with_child(Dom::div().with_class("footer").with_child(Dom::div().with_class("row")));
+
+    {
+        let select = |selector: &str| dom.select(selector).unwrap();
+
+        assert_eq!(select("#table > .row").len(), 10);
+        assert_eq!(select("#table .cell").len(), 10);
+        assert_eq!(select("#table > .cell").len(), 0);
+        assert_eq!(select(".row").len(), 11);
+        assert_eq!(select("div .row").len(), 11);
+        assert_eq!(select("div>div>.row").len(), 11);
+        assert_eq!(select("p.cell").len(), 10);
+        assert_eq!(select("div.dark#main"), vec![dom.get_root()]);
+        assert_eq!(select("#main > .footer > .row"), dom.find_all_by_class("row").skip(10).collect::<Vec<_>>());
+        assert_eq!(select("  .row  .cell  "), dom.find_all_by_class("cell").collect::<Vec<_>>());
+        assert_eq!(select("img").len(), 0);
+        assert_eq!(select(".footer .cell").len(), 0);
+
+        // Results are in document order
+        let texts = select("p").into_iter()
+            .map(|node_id| dom.get_node_data(node_id).unwrap().get_label_text().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, (0..10).map(|i| format!("{}", i)).collect::<Vec<_>>());
+    }
+
+    assert_eq!(dom.select(""), Err(EmptySelector));
+    assert_eq!(dom.select("span"), Err(InvalidNodeType("span".to_string())));
+    assert_eq!(dom.select("div >"), Err(DanglingCombinator));
+    assert_eq!(dom.select("> div"), Err(DanglingCombinator));
+    assert_eq!(dom.select("div > > div"), Err(DanglingCombinator));
+    assert_eq!(dom.select("div#"), Err(EmptyId));
+    assert_eq!(dom.select("div."), Err(EmptyClass));
+    assert_eq!(dom.select("div:hover"), Err(InvalidCharacter(':')));
+
+    dom.mutate_nodes_by_class("footer", |node| node.add_class("row"));
+    assert_eq!(dom.select(".row .row").unwrap().len(), 1);
+}
+
+#[test]
+fn test_children_descendants_and_siblings() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let labels = |dom: &Dom<TestLayout>, nodes: Vec<NodeId>| nodes.into_iter()
+        .map(|node_id| dom.get_node_data(node_id).unwrap().get_label_text().unwrap_or("div").to_string())
+        .collect::<Vec<_>>();
+
+    // The NodeIds of the appended DOMs are shifted when the arenas are merged
+    let list = Dom::div().with_child(Dom::label("a")).with_child(Dom::label("b"));
+    let dom = Dom::div()
+        .with_child(Dom::label("header"))
+        .with_child(list.with_child(Dom::label("c")))
+        .with_child(Dom::label("footer"));
+
+    let root = dom.get_root();
+    assert_eq!(labels(&dom, dom.children(root).collect()), vec!["header", "div", "footer"]);
+    assert_eq!(labels(&dom, dom.descendants(root).collect()), vec!["header", "div", "a", "b", "c", "footer"]);
+    assert_eq!(dom.siblings(root).count(), 0);
+
+    let list = dom.children(root).nth(1).unwrap();
+    assert_eq!(labels(&dom, dom.children(list).collect()), vec!["a", "b", "c"]);
+    assert_eq!(labels(&dom, dom.siblings(list).collect()), vec!["header", "footer"]);
+
+    let b = dom.children(list).nth(1).unwrap();
+    assert_eq!(labels(&dom, dom.siblings(b).collect()), vec!["a", "c"]);
+    assert_eq!(dom.children(b).count(), 0);
+    assert_eq!(dom.descendants(b).count(), 0);
+
+    let footer = dom.children(root).last().unwrap();
+    assert_eq!(labels(&dom, dom.siblings(footer).collect()), vec!["header", "div"]);
+}
+
+#[test]
+fn test_ancestors_and_closest() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    // root.dropdown -> menu.dropdown -> list -> item
+    let dom = Dom::<TestLayout>::div().with_class("dropdown").with_id("root")
+        .with_child(Dom::label("header"))
+        .with_child(Dom::div().with_class("dropdown").with_id("menu")
+            .with_child(Dom::div().with_id("list")
+                .with_child(Dom::label("item").with_id("item"))));
+
+    let node = |id: &str| dom.get_node_by_id(id).unwrap();
+    let root = dom.get_root();
+
+    // Root-ward, without the node itself
+    assert_eq!(dom.ancestors(node("item")).collect::<Vec<_>>(), vec![node("list"), node("menu"), root]);
+    assert_eq!(dom.ancestors(root).count(), 0);
+
+    let is_dropdown = |node: &NodeData<TestLayout>| node.has_class("dropdown");
+    assert_eq!(dom.closest(node("item"), is_dropdown), Some(node("menu")));
+    assert_eq!(dom.closest(node("menu"), is_dropdown), Some(node("menu")));
+    assert_eq!(dom.closest(root, is_dropdown), Some(root));
+    assert_eq!(dom.closest(node("item"), |node| node.has_id("missing")), None);
+
+    let ui_state = dom.clone().into_ui_state(&mut TagIdGenerator::new());
+    assert_eq!(ui_state.ancestors(node("item")).collect::<Vec<_>>(), vec![node("list"), node("menu"), root]);
+    assert_eq!(ui_state.closest(node("list"), is_dropdown), Some(node("menu")));
+}
+
+#[test]
+fn test_traverse_with_depth() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let depths = |dom: &Dom<TestLayout>| dom.traverse().map(|(depth, node_id)| (depth, node_id.index())).collect::<Vec<_>>();
+
+    // `collect()` creates a root with the items as flat children
+    let flat = (0..5).map(|i| NodeData::new(NodeType::Label(format!("{}", i)))).collect::<Dom<TestLayout>>();
+    assert_eq!(depths(&flat), vec![(0, 0), (1, 1), (1, 2), (1, 3), (1, 4), (1, 5)]);
+
+    // root -> (a -> (b -> c), d), e
+    let nested = Dom::div()
+        .with_child(Dom::div()
+            .with_child(Dom::div().with_child(Dom::label("c")))
+            .with_child(Dom::label("d")))
+        .with_child(Dom::label("e"));
+    let labels = nested.traverse()
+        .map(|(depth, node_id)| (depth, nested.get_node_data(node_id).unwrap().get_label_text().unwrap_or("div")))
+        .collect::<Vec<_>>();
+    assert_eq!(labels, vec![(0, "div"), (1, "div"), (2, "div"), (3, "c"), (2, "d"), (1, "e")]);
+
+    // Inconsistent links: the traversal stops instead of looping or panicking
+    let valid = || Dom::<TestLayout>::div().with_child(Dom::label("a")).with_child(Dom::label("b"));
+
+    let mut cycle = valid();
+    cycle.arena.node_layout[NodeId::new(2)].next_sibling = Some(NodeId::new(1));
+    assert_eq!(cycle.traverse().count(), 3);
+
+    let mut child_cycle = valid();
+    child_cycle.arena.node_layout[NodeId::new(1)].first_child = Some(NodeId::new(0));
+    assert_eq!(child_cycle.traverse().count(), 3);
+
+    let mut out_of_bounds = valid();
+    out_of_bounds.arena.node_layout[NodeId::new(1)].first_child = Some(NodeId::new(10));
+    assert_eq!(depths(&out_of_bounds), vec![(0, 0), (1, 1)]);
+}
+
+#[test]
+fn test_remove_subtree() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let labels = |dom: &Dom<TestLayout>| dom.traverse()
+        .map(|(depth, node_id)| (depth, dom.get_node_data(node_id).unwrap().get_label_text().unwrap_or("div").to_string()))
+        .collect::<Vec<_>>();
+
+    // root -> header, (section -> a, b), footer
+    let page = || Dom::<TestLayout>::div()
+        .with_child(Dom::label("header"))
+        .with_child(Dom::div().with_id("section").with_child(Dom::label("a")).with_child(Dom::label("b")))
+        .with_child(Dom::label("footer"));
+
+    // Middle child with children
+    let mut dom = page();
+    let section = dom.get_node_by_id("section").unwrap();
+    let footer = dom.children(dom.get_root()).last().unwrap();
+    let id_map = dom.remove_subtree(section).unwrap();
+    assert_eq!(labels(&dom), vec![(0, "div".to_string()), (1, "header".to_string()), (1, "footer".to_string())]);
+
+    // The NodeIds after the removed nodes have moved, the removed nodes are gone
+    assert_eq!(footer, NodeId::new(5));
+    assert_eq!(id_map.get(&footer), Some(&NodeId::new(2)));
+    assert_eq!(dom.get_node_data(id_map[&footer]).unwrap().get_label_text(), Some("footer"));
+    assert_eq!(id_map.get(&section), None);
+    assert_eq!(id_map.len(), 3);
+    assert_eq!(dom.arena.len(), 3);
+    assert_eq!(dom.validate(), Ok(()));
+
+    // First and last child of a parent
+    let mut dom = page();
+    let section = dom.get_node_by_id("section").unwrap();
+    let a = dom.children(section).next().unwrap();
+    assert!(dom.remove_subtree(a).is_ok());
+    let b = dom.children(section).next().unwrap();
+    assert!(dom.remove_subtree(b).is_ok());
+    assert_eq!(dom.children(section).count(), 0);
+    let header = dom.children(dom.get_root()).next().unwrap();
+    assert!(dom.remove_subtree(header).is_ok());
+    assert_eq!(labels(&dom), vec![(0, "div".to_string()), (1, "div".to_string()), (1, "footer".to_string())]);
+    assert_eq!(dom.validate(), Ok(()));
+
+    // The DOM can still be extended and turned into a UI state
+    dom.add_child(Dom::label("appended"));
+    assert_eq!(dom.children(dom.get_root()).count(), 3);
+    assert!(dom.try_into_ui_state(&mut TagIdGenerator::new()).is_ok());
+
+    // The root can't be removed, neither can nodes that don't exist
+    let mut dom = page();
+    let root = dom.get_root();
+    assert_eq!(dom.remove_subtree(root), Err(DomError::CannotRemoveRoot { root }));
+    assert_eq!(dom.remove_subtree(NodeId::new(10)), Err(DomError::NodeNotFound { node: NodeId::new(10) }));
+    assert_eq!(dom.arena.len(), 6);
+
+    // Removing the current node resets it to the root
+    let mut dom = page();
+    dom.head = dom.get_node_by_id("section").unwrap();
+    let section = dom.head;
+    assert!(dom.remove_subtree(section).is_ok());
+    assert_eq!(dom.head, dom.get_root());
+}
+
+#[test]
+fn test_reorder_children() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        DontRedraw
+    }
+
+    let labels = |dom: &Dom<TestLayout>| dom.children(dom.get_root())
+        .map(|node_id| dom.get_node_data(node_id).unwrap().get_label_text().unwrap().to_string())
+        .collect::<Vec<_>>();
+
+    // root -> a (hover callback), b, c (focusable)
+    let list = || Dom::<TestLayout>::div()
+        .with_child(Dom::label("a").with_callback(On::MouseUp, Callback(callback)))
+        .with_child(Dom::label("b"))
+        .with_child(Dom::label("c").with_tab_index(TabIndex::Auto));
+
+    let mut dom = list();
+    let root = dom.get_root();
+    let (a, b, c) = (NodeId::new(1), NodeId::new(2), NodeId::new(3));
+
+    assert_eq!(dom.reorder_children(root, &[c, a, b]), Ok(()));
+    assert_eq!(labels(&dom), vec!["c", "a", "b"]);
+    assert_eq!(dom.validate(), Ok(()));
+
+    assert_eq!(dom.swap_children(root, 0, 2), Ok(()));
+    assert_eq!(labels(&dom), vec!["b", "a", "c"]);
+    assert_eq!(dom.validate(), Ok(()));
+
+    // Not a permutation of the children: missing, duplicate or foreign nodes
+    assert_eq!(dom.reorder_children(root, &[a, b]), Err(DomError::InvalidChildOrder { parent: root }));
+    assert_eq!(dom.reorder_children(root, &[a, a, b]), Err(DomError::InvalidChildOrder { parent: root }));
+    assert_eq!(dom.reorder_children(root, &[a, b, root]), Err(DomError::InvalidChildOrder { parent: root }));
+    assert_eq!(dom.swap_children(root, 0, 3), Err(DomError::ChildIndexOutOfRange { parent: root, index: 3 }));
+    assert_eq!(dom.reorder_children(NodeId::new(10), &[]), Err(DomError::NodeNotFound { node: NodeId::new(10) }));
+    assert_eq!(labels(&dom), vec!["b", "a", "c"]);
+
+    // The NodeData didn't move, so the UI state (which iterates the arena linearly,
+    // not in document order) still assigns the tags and callbacks to the right nodes
+    let mut reordered = list();
+    reordered.reorder_children(root, &[c, b, a]).unwrap();
+    let ui_state = reordered.try_into_ui_state(&mut TagIdGenerator::new()).unwrap();
+    let unchanged = list().try_into_ui_state(&mut TagIdGenerator::new()).unwrap();
+
+    assert_eq!(ui_state.node_ids_to_tag_ids(), unchanged.node_ids_to_tag_ids());
+    assert_eq!(ui_state.node_ids_to_tag_ids().keys().cloned().collect::<Vec<_>>(), vec![a, c]);
+    assert_eq!(ui_state.nodes_with_hover_callbacks(), vec![a]);
+    assert_eq!(ui_state.tab_index_tags().values().cloned().collect::<Vec<_>>(), vec![(c, TabIndex::Auto)]);
+    for (tag_id, node_id) in ui_state.tag_ids_to_node_ids() {
+        assert_eq!(ui_state.node_ids_to_tag_ids()[node_id], *tag_id);
+    }
+
+    // ... even though the document order differs from the arena order now
+    assert_eq!(ui_state.dom().children(root).collect::<Vec<_>>(), vec![c, b, a]);
+}
+
+#[test]
+fn test_dom_with_children() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    fn ids_of_children(dom: &Dom<TestLayout>) -> Vec<String> {
+        dom.root.children(&dom.arena.node_layout)
+            .map(|child| dom.arena.node_data[child].ids.join(" "))
+            .collect()
+    }
+
+    // Mixed with `with_child` before and after, the document order is kept
+    let dom = Dom::<TestLayout>::div()
+        .with_child(Dom::div().with_id("first"))
+        .with_children((0..3).map(|i| Dom::div().with_id(format!("list-{}", i)).with_child(Dom::label("item"))))
+        .with_children(Vec::new())
+        .with_child(Dom::div().with_id("last"));
+    assert_eq!(ids_of_children(&dom), vec!["first", "list-0", "list-1", "list-2", "last"]);
+    assert_eq!(dom.len(), 9);
+    assert_eq!(dom.validate(), Ok(()));
+
+    // With an exact size hint, the arena is only grown once: reallocating while
+    // appending would at least double the capacity
+    let mut dom = Dom::<TestLayout>::div();
+    dom.add_children((0..1000).map(|_| Dom::div()));
+    assert_eq!(dom.len(), 1001);
+    assert_eq!(dom.arena.capacity(), 1001);
+    assert_eq!(dom.root.children(&dom.arena.node_layout).count(), 1000);
+}
+
+#[test]
+fn test_dom_shared_child() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn sidebar() -> Dom<TestLayout> {
+        Dom::div().with_id("sidebar")
+            .with_children((0..100).map(|i| Dom::label(format!("Entry {}", i)).with_class("entry")))
+    }
+
+    let shared = sidebar().shared();
+    assert_eq!(shared.len(), 101);
+    assert!(shared.get_dom().arena.node_data.iter().all(|node| node.hash_cache.node.get().is_some()));
+
+    // Same DOM as adding the sidebar with add_child, in every frame
+    let frame = || Dom::<TestLayout>::div().with_child(Dom::div().with_id("header")).with_shared_child(&shared).with_child(Dom::div().with_id("body"));
+    let expected = Dom::<TestLayout>::div().with_child(Dom::div().with_id("header")).with_child(sidebar()).with_child(Dom::div().with_id("body"));
+    assert_eq!(frame(), expected);
+    assert_eq!(frame().validate(), Ok(()));
+    assert_eq!(frame().calculate_dom_hash(), expected.calculate_dom_hash());
+
+    // The nodes of the sidebar aren't copied, neither into the DOM nor into the UiState
+    let dom = frame();
+    let sidebar_id = dom.get_node_by_id("sidebar").unwrap();
+    assert!(dom.descendants(sidebar_id).all(|node_id| dom.arena.node_data.is_shared(node_id)));
+    assert!(dom.descendants(sidebar_id).all(|node_id| dom.arena.node_data[node_id].hash_cache.node.get().is_some()));
+    assert!(!dom.arena.node_data.is_shared(dom.get_node_by_id("header").unwrap()));
+
+    // NodeIds of the shared nodes are shifted, like with add_child
+    let ui_state = UiState::from_dom(frame());
+    assert_eq!(ui_state.dom.get_node_by_id("sidebar"), Some(NodeId::new(2)));
+    assert_eq!(ui_state.dom.arena.node_data[NodeId::new(3)].get_label_text(), Some("Entry 0"));
+    assert!(ui_state.dom.arena.node_data.is_shared(NodeId::new(3)));
+
+    // Modifying a shared node copies it, the SharedDom isn't modified
+    let mut dom = frame();
+    dom.mutate_nodes_by_class("entry", |node| node.add_class("selected"));
+    assert_eq!(dom.find_all_by_class("selected").count(), 100);
+    assert!(dom.find_all_by_class("entry").all(|node_id| !dom.arena.node_data.is_shared(node_id)));
+    assert!(dom.arena.node_data.is_shared(dom.get_node_by_id("sidebar").unwrap()));
+    assert_eq!(shared.get_dom().find_all_by_class("selected").count(), 0);
+    assert_eq!(frame(), expected);
+}
+
+#[test]
+fn test_dom_extend() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    // Single nodes mixed with small subtrees
+    let child = |i: usize| if i % 3 == 0 {
+        Dom::div().with_id(format!("row-{}", i)).with_child(Dom::label("a")).with_child(Dom::label("b"))
+    } else {
+        Dom::label(format!("{}", i))
+    };
+
+    let container = || Dom::<TestLayout>::div().with_id("container").with_child(Dom::div().with_id("header"));
+
+    let mut expected = container();
+    for i in 0..10_000 {
+        expected.add_child(child(i));
+    }
+
+    // Same tree as repeated add_child, without a wrapper div
+    let mut dom = container();
+    dom.extend((0..10_000).map(child));
+    assert_eq!(dom.arena.node_layout.internal, expected.arena.node_layout.internal);
+    assert_eq!(dom.arena.node_data, expected.arena.node_data);
+    assert_eq!(dom.validate(), Ok(()));
+
+    let mut expected = container();
+    for i in 0..10_000 {
+        expected.add_child(Dom::label(format!("{}", i)));
+    }
+
+    let mut dom = container();
+    dom.extend((0..10_000).map(|i| NodeData::label(format!("{}", i))));
+    assert_eq!(dom.arena.node_layout.internal, expected.arena.node_layout.internal);
+    assert_eq!(dom.arena.node_data, expected.arena.node_data);
+    assert_eq!(dom.root.children(&dom.arena.node_layout).count(), 10_001);
+    assert_eq!(dom.validate(), Ok(()));
+
+    // The arena is only grown once for an exact size hint
+    let mut dom = Dom::<TestLayout>::div();
+    dom.extend((0..1000).map(|_| NodeData::div()));
+    assert_eq!(dom.arena.capacity(), 1001);
+
+    // Nodes go under the current node
+    let mut dom = Dom::<TestLayout>::div();
+    dom.push_head(Dom::div().with_id("list"));
+    dom.extend(vec![NodeData::label("1"), NodeData::label("2")]);
+    let list = dom.get_node_by_id("list").unwrap();
+    assert_eq!(dom.children(list).count(), 2);
+    assert_eq!(dom.children(dom.get_root()).collect::<Vec<_>>(), vec![list]);
+}
+
+#[test]
+fn test_dom_append_child_at() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+                .with_child(Dom::div().with_id("table-header"))
+                .with_child(Dom::div().with_id("table-body").with_child(Dom::div().with_id("row-0")))
+        }
+    }
+
+    fn ids_of_children(dom: &Dom<TestLayout>, node_id: NodeId) -> Vec<String> {
+        node_id.children(&dom.arena.node_layout)
+            .map(|child| dom.arena.node_data[child].ids.join(" "))
+            .collect()
+    }
+
+    let mut dom = TestLayout { }.layout();
+    let body = dom.get_node_by_id("table-body").unwrap();
+
+    dom.append_child_at(body, Dom::div().with_id("row-1").with_child(Dom::label("cell"))).unwrap();
+    dom.append_child_at(body, Dom::div().with_id("row-2")).unwrap();
+    assert_eq!(ids_of_children(&dom, body), vec!["row-0", "row-1", "row-2"]);
+    assert_eq!(ids_of_children(&dom, dom.get_root()), vec!["table-header", "table-body"]);
+    assert_eq!(dom.validate(), Ok(()));
+
+    // The current node doesn't change
+    dom.add_child(Dom::div().with_id("footer"));
+    assert_eq!(ids_of_children(&dom, dom.get_root()), vec!["table-header", "table-body", "footer"]);
+
+    let len = dom.len();
+    let invalid = NodeId::new(len);
+    assert_eq!(dom.append_child_at(invalid, Dom::div()), Err(DomError::NodeNotFound { node: invalid }));
+    assert_eq!(dom.len(), len);
+}
+
+#[test]
+fn test_dom_push_and_pop_head() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn ids_of_children(dom: &Dom<TestLayout>, node_id: NodeId) -> Vec<String> {
+        node_id.children(&dom.arena.node_layout)
+            .map(|child| dom.arena.node_data[child].ids.join(" "))
+            .collect()
+    }
+
+    // (nesting level, title) of the sections of a document
+    let sections = [(0, "1"), (1, "1.1"), (2, "1.1.1"), (1, "1.2"), (0, "2")];
+
+    let mut dom = Dom::<TestLayout>::div().with_id("document");
+    let mut depth = 0;
+
+    for (level, title) in sections.iter() {
+        while depth > *level {
+            dom.pop_head();
+            depth -= 1;
+        }
+        let section = dom.push_head(Dom::div().with_class("section"));
+        depth += 1;
+        dom.add_id(*title);
+        dom.add_child(Dom::label(*title));
+        assert_eq!(dom.get_node_by_id(title), Some(section));
+    }
+
+    let root = dom.get_root();
+    {
+        let section = |id| dom.get_node_by_id(id).unwrap();
+        assert_eq!(ids_of_children(&dom, root), vec!["1", "2"]);
+        assert_eq!(ids_of_children(&dom, section("1")), vec!["", "1.1", "1.2"]);
+        assert_eq!(ids_of_children(&dom, section("1.1")), vec!["", "1.1.1"]);
+        assert_eq!(ids_of_children(&dom, section("1.1.1")), vec![""]);
+        assert_eq!(ids_of_children(&dom, section("1.2")), vec![""]);
+        assert_eq!(ids_of_children(&dom, section("2")), vec![""]);
+        assert_eq!(dom.arena.node_data[section("1.2")].classes, vec!["section".to_string()]);
+        assert_eq!(dom.validate(), Ok(()));
+    }
+
+    // Popping the root is a no-op, add_child then appends to the root again
+    dom.pop_head();
+    dom.pop_head();
+    assert_eq!(dom.head, root);
+    dom.add_child(Dom::div().with_id("footer"));
+    assert_eq!(ids_of_children(&dom, root), vec!["1", "2", "footer"]);
+    assert_eq!(dom.validate(), Ok(()));
+}
+
+#[test]
+fn test_dom_fill_slot() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn chrome() -> Dom<TestLayout> {
+        Dom::div().with_id("window")
+            .with_child(Dom::div().with_id("header").with_child(Dom::label("Title")))
+            .with_child(Dom::div().with_id("sidebar").with_class("placeholder"))
+            .with_child(Dom::div().with_id("body").with_class("content").with_class("placeholder"))
+    }
+
+    fn ids_of_descendants(dom: &Dom<TestLayout>, node_id: NodeId) -> Vec<String> {
+        dom.descendants(node_id)
+            .map(|descendant| dom.arena.node_data[descendant].ids.join(" "))
+            .filter(|ids| !ids.is_empty())
+            .collect()
+    }
+
+    let mut dom = chrome();
+    dom.fill_slot("sidebar", Dom::div().with_id("nav").with_child(Dom::div().with_id("nav-item"))).unwrap();
+    dom.fill_slot("body", Dom::div().with_id("editor")).unwrap();
+    dom.fill_slot("body", Dom::div().with_id("status")).unwrap();
+    assert_eq!(dom.validate(), Ok(()));
+
+    {
+        let sidebar = dom.get_node_by_id("sidebar").unwrap();
+        let body = dom.get_node_by_id("body").unwrap();
+        assert_eq!(ids_of_descendants(&dom, sidebar), vec!["nav", "nav-item"]);
+        assert_eq!(ids_of_descendants(&dom, body), vec!["editor", "status"]);
+        assert_eq!(ids_of_descendants(&dom, dom.get_root()), vec!["header", "sidebar", "nav", "nav-item", "body", "editor", "status"]);
+
+        // Only the placeholder class is removed
+        assert!(dom.arena.node_data[sidebar].classes.is_empty());
+        assert_eq!(dom.arena.node_data[body].classes, vec!["content".to_string()]);
+    }
 
-                    filter_and_insert_callbacks!(
-                        node_id,
-                        data.default_callback_ids,
-                        DesktopEventFilter,
-                        DefaultCallbackId,
-                        as_desktop_event_filter,
-                        desktop_default_callbacks,
-                    );
-                }
+    // The current node doesn't change
+    dom.add_child(Dom::div().with_id("footer"));
+    assert_eq!(dom.children(dom.get_root()).last().map(|footer| dom.arena.node_data[footer].ids.clone()), Some(vec!["footer".to_string()]));
 
-                if data.draggable {
-                    let tag_id = node_tag_id.unwrap_or_else(|| new_tag_id());
-                    draggable_tags.insert(tag_id, node_id);
-                    node_tag_id = Some(tag_id);
-                }
+    // Missing and duplicate slots don't change the DOM
+    let mut dom = chrome().with_child(Dom::div().with_id("body"));
+    let len = dom.len();
+    assert_eq!(dom.fill_slot("toolbar", Dom::div()), Err(SlotError::NotFound { slot_id: "toolbar".into() }));
+    assert_eq!(dom.fill_slot("body", Dom::div()), Err(SlotError::Ambiguous { slot_id: "body".into(), nodes: vec![NodeId::new(4), NodeId::new(5)] }));
+    assert_eq!(dom.len(), len);
+}
 
-                if let Some(tab_index) = data.tab_index {
-                    let tag_id = node_tag_id.unwrap_or_else(|| new_tag_id());
-                    tab_index_tags.insert(tag_id, (node_id, tab_index));
-                    node_tag_id = Some(tag_id);
-                }
+#[test]
+fn test_dom_add_fragment() {
 
-                if let Some(tag_id) = node_tag_id {
-                    tag_ids_to_node_ids.insert(tag_id, node_id);
-                    node_ids_to_tag_ids.insert(node_id, tag_id);
-                }
+    struct TestLayout { }
 
-                // Collect all the styling overrides into one hash map
-                if !data.dynamic_css_overrides.is_empty() {
-                    dynamic_css_overrides.insert(node_id, data.dynamic_css_overrides.iter().cloned().collect());
-                }
-            }
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
         }
+    }
 
-        UiState {
+    let fragment = DomFragment::new()
+        .with_root(Dom::label("Name").with_id("first"))
+        .with_root(Dom::div().with_id("second").with_child(Dom::div().with_id("nested")))
+        .with_root(Dom::div().with_id("third"));
+    assert_eq!(fragment.len(), 3);
+
+    let mut dom = Dom::<TestLayout>::div()
+        .with_child(Dom::div().with_id("before"))
+        .with_fragment(fragment);
+    dom.add_child(Dom::div().with_id("after"));
+
+    let root = dom.get_root();
+    let ids = |node_ids: Vec<NodeId>| node_ids.iter().map(|node_id| dom.arena.node_data[*node_id].ids.join(" ")).collect::<Vec<_>>();
+
+    // No wrapper node: the roots are siblings between the existing children
+    let children = dom.children(root).collect::<Vec<_>>();
+    assert_eq!(ids(children.clone()), vec!["before", "first", "second", "third", "after"]);
+    assert_eq!(dom.len(), 7);
+
+    let node_layout = &dom.arena.node_layout;
+    for (index, child) in children.iter().enumerate() {
+        assert_eq!(node_layout[*child].parent, Some(root));
+        assert_eq!(node_layout[*child].previous_sibling, if index == 0 { None } else { Some(children[index - 1]) });
+        assert_eq!(node_layout[*child].next_sibling, children.get(index + 1).cloned());
+    }
+    assert_eq!(node_layout[root].first_child, Some(children[0]));
+    assert_eq!(node_layout[root].last_child, Some(children[4]));
+
+    // The children of the roots are kept
+    let nested = dom.get_node_by_id("nested").unwrap();
+    assert_eq!(node_layout[nested].parent, Some(children[2]));
+    assert_eq!(dom.validate(), Ok(()));
+
+    // An empty fragment doesn't add anything
+    let len = dom.len();
+    dom.add_fragment(DomFragment::new());
+    assert_eq!(dom.len(), len);
+}
 
-            dom: self,
-            dynamic_css_overrides,
-            tag_ids_to_hover_active_states: BTreeMap::new(),
+#[test]
+fn test_node_data_builders_collect_into_dom() {
 
-            tab_index_tags,
-            draggable_tags,
-            node_ids_to_tag_ids,
-            tag_ids_to_node_ids,
+    use azul_css::LayoutWidth;
+    use images::new_image_id;
 
-            hover_callbacks,
-            hover_default_callbacks,
-            focus_callbacks,
-            focus_default_callbacks,
-            not_callbacks,
-            not_default_callbacks,
-            window_callbacks,
-            window_default_callbacks,
-            desktop_callbacks,
-            desktop_default_callbacks,
+    struct TestLayout { }
 
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
         }
     }
-}
 
-/// OpenGL texture, use `ReadOnlyWindow::create_texture` to create a texture
-///
-/// **WARNING**: Don't forget to call `ReadOnlyWindow::unbind_framebuffer()`
-/// when you are done with your OpenGL drawing, otherwise WebRender will render
-/// to the texture, not the window, so your texture will actually never show up.
-/// If you use a `Texture` and you get a blank screen, this is probably why.
-#[derive(Debug, Clone)]
-pub struct Texture {
-    pub(crate) inner: Rc<Texture2d>,
+    fn test_callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        Redraw
+    }
+
+    let image_id = new_image_id();
+    let width = CssProperty::Width(LayoutWidth::px(100.0));
+
+    let dom = vec![
+        NodeData::label("Title").with_id("title"),
+        NodeData::image(image_id).with_classes(vec!["icon".into(), "large".into()]),
+        NodeData::div().with_callback(On::MouseUp, Callback(test_callback)).with_tab_index(TabIndex::Auto),
+        NodeData::div().is_draggable(true),
+        NodeData::label("Footer").with_css_override("width", width.clone()),
+    ].into_iter().collect::<Dom<TestLayout>>();
+
+    // The synthetic root and the five nodes as its children
+    let root = dom.get_root();
+    let children = dom.children(root).collect::<Vec<_>>();
+    assert_eq!(dom.len(), 6);
+    assert_eq!(children.len(), 5);
+    assert!(children.iter().all(|child| dom.children(*child).next().is_none()));
+
+    let node = |index: usize| &dom.arena.node_data[children[index]];
+    assert_eq!(node(0).get_label_text(), Some("Title"));
+    assert!(node(0).has_id("title"));
+    assert_eq!(node(1).get_image_id(), Some(image_id));
+    assert_eq!(node(1).classes, vec!["icon".to_string(), "large".to_string()]);
+    assert!(node(2).is_div());
+    assert_eq!(node(2).callbacks.len(), 1);
+    assert_eq!(node(2).tab_index, Some(TabIndex::Auto));
+    assert!(node(3).draggable);
+    assert_eq!(node(4).dynamic_css_overrides, vec![("width".to_string(), width)]);
+
+    // The builders produce the same nodes as the chained `Dom` API
+    let chained = Dom::<TestLayout>::label("Title").with_id("title");
+    assert_eq!(chained.arena.node_data[chained.get_root()], *node(0));
 }
 
-impl Texture {
-    /// Note: You can initialize this texture from an existing (external texture).
-    pub fn new(tex: Texture2d) -> Self {
-        Self {
-            inner: Rc::new(tex),
+#[test]
+fn test_dom_replace_node() {
+
+    use images::new_image_id;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div().with_class("card")
+                .with_child(Dom::div().with_id("avatar").with_class("round"))
+                .with_child(Dom::div().with_id("body")
+                    .with_child(Dom::label("Name"))
+                    .with_child(Dom::label("Mail")))
         }
     }
 
-    /// Prepares the texture for drawing - you can only draw
-    /// on a framebuffer, the texture itself is readonly from the
-    /// OpenGL drivers point of view.
-    ///
-    /// **WARNING**: Don't forget to call `ReadOnlyWindow::unbind_framebuffer()`
-    /// when you are done with your OpenGL drawing, otherwise WebRender will render
-    /// to the texture instead of the window, so your texture will actually
-    /// never show up on the screen, since it is never rendered.
-    /// If you use a `Texture` and you get a blank screen, this is probably why.
-    pub fn as_surface<'a>(&'a self) -> SimpleFrameBuffer<'a> {
-        self.inner.as_surface()
+    fn test_callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        Redraw
     }
+
+    let mut dom = TestLayout { }.layout();
+    let hashes = dom.arena.node_data.iter().map(|n| n.calculate_node_data_hash()).collect::<Vec<_>>();
+
+    // Leaf: the node type changes, the ids and classes stay
+    let avatar = dom.get_node_by_id("avatar").unwrap();
+    let image_id = new_image_id();
+    dom.replace_node_type(avatar, NodeType::Image(image_id)).unwrap();
+    assert_eq!(dom.arena.node_data[avatar].node_type, NodeType::Image(image_id));
+    assert_eq!(dom.arena.node_data[avatar].classes, vec!["round".to_string()]);
+    assert!(dom.arena.node_data[avatar].calculate_node_data_hash() != hashes[avatar.index()]);
+
+    // Node with children: the children stay in place
+    let body = dom.get_node_by_id("body").unwrap();
+    let mut new_body = NodeData::new(NodeType::Div);
+    new_body.add_callback(On::MouseUp, Callback(test_callback));
+    let old_body = dom.replace_node_data(body, new_body).unwrap();
+    assert_eq!(old_body.ids, vec!["body".to_string()]);
+    assert_eq!(dom.children(body).count(), 2);
+    assert_eq!(dom.arena.node_data[body].callbacks.len(), 1);
+    assert!(dom.arena.node_data[body].calculate_node_data_hash() != hashes[body.index()]);
+
+    // Root
+    let root = dom.get_root();
+    let old_root = dom.replace_node_data(root, NodeData::new(NodeType::Label("replaced".into()))).unwrap();
+    assert_eq!(old_root.classes, vec!["card".to_string()]);
+    assert_eq!(dom.children(root).collect::<Vec<_>>(), vec![avatar, body]);
+    assert!(dom.arena.node_data[root].calculate_node_data_hash() != hashes[root.index()]);
+    assert_eq!(dom.validate(), Ok(()));
+
+    // The other nodes are unchanged
+    let label = dom.children(body).next().unwrap();
+    assert_eq!(dom.arena.node_data[label].calculate_node_data_hash(), hashes[label.index()]);
+
+    let invalid = NodeId::new(dom.len());
+    assert_eq!(dom.replace_node_type(invalid, NodeType::Div), Err(DomError::NodeNotFound { node: invalid }));
+    assert!(dom.replace_node_data(invalid, NodeData::new(NodeType::Div)).is_err());
 }
 
-impl Hash for Texture {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        use glium::GlObject;
-        self.inner.get_id().hash(state);
+#[test]
+fn test_dom_to_html_string() {
+
+    struct TestLayout { }
+
+    fn test_callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        Redraw
+    }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div().with_class("form").with_class("card")
+                .with_child(Dom::label("Name & <Mail>").with_id("title"))
+                .with_child(Dom::div().with_id("input")
+                    .with_callback(On::TextInput, Callback(test_callback))
+                    .with_callback(On::MouseUp, Callback(test_callback))
+                    .with_child(Dom::label("placeholder")))
+                .with_child(Dom::div())
+        }
     }
+
+    let expected = "\
+<div class=\"card form\">
+    <p id=\"title\">Name &amp; &lt;Mail&gt;</p>
+    <div id=\"input\" data-callbacks=\"Hover(MouseUp), Focus(TextInput)\">
+        <p>placeholder</p>
+    </div>
+    <div></div>
+</div>
+";
+
+    let dom = TestLayout { }.layout();
+    assert_eq!(dom.to_html_string(), expected);
+    // The output doesn't depend on the order in which the classes were added
+    assert_eq!(Dom::<TestLayout>::div().with_class("card").with_class("form").to_html_string(), "<div class=\"card form\"></div>\n");
 }
 
-impl PartialEq for Texture {
-    /// Note: Comparison uses only the OpenGL ID, it doesn't compare the
-    /// actual contents of the texture.
-    fn eq(&self, other: &Texture) -> bool {
-        use glium::GlObject;
-        self.inner.get_id() == other.inner.get_id()
+#[test]
+fn test_dom_debug_dump_string() {
+
+    use default_callbacks::get_new_unique_default_callback_id;
+
+    struct TestLayout { }
+
+    fn test_callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        Redraw
+    }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
     }
+
+    let dom = |title: &str| {
+        let mut input = Dom::div().with_id("input")
+            .with_callback(On::TextInput, Callback(test_callback))
+            .with_tab_index(TabIndex::Auto);
+        input.add_default_callback_id(On::MouseUp, get_new_unique_default_callback_id());
+        Dom::<TestLayout>::div().with_class("form").with_class("card")
+            .with_child(Dom::label(title).with_id("title").is_draggable(true))
+            .with_child(input.with_child(Dom::label("placeholder")))
+    };
+
+    let expected = "\
+-- 0: div .card .form
+\t|-- 1: label \"Settings\" #title draggable
+\t|-- 2: div #input on=[Focus(TextInput), Hover(MouseUp)] tabindex=Auto
+\t|\t|-- 3: label \"placeholder\"
+";
+    assert_eq!(dom("Settings").debug_dump_string(), expected);
+
+    // Only the line of the changed node (and none of the others) has a different hash
+    let before = dom("Settings").debug_dump_string_with_hashes();
+    let after = dom("Preferences").debug_dump_string_with_hashes();
+    let changed_lines = before.lines().zip(after.lines()).filter(|(a, b)| a != b).count();
+    assert_eq!(changed_lines, 1);
+    assert!(before.lines().all(|line| line.contains(" hash=")));
 }
 
-impl Eq for Texture { }
+#[test]
+fn test_dom_dump_with_overrides() {
+
+    use azul_css::{LayoutWidth, LayoutHeight};
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let width = |px| CssProperty::Width(LayoutWidth::px(px));
+    let height = |px| CssProperty::Height(LayoutHeight::px(px));
+
+    let dom = Dom::<TestLayout>::div().with_id("main")
+        .with_css_override("width", width(100.0))
+        .with_css_override("height", height(50.0))
+        .with_css_override("width", width(200.0))
+        .with_child(Dom::label("Hello"))
+        .with_child(Dom::div().with_css_override("child_width", width(10.0)));
+
+    let expected = format!("\
+-- 0: div #main
+   [[ height ]] = {:?}
+   [[ width ]] = {:?}
+\t|-- 1: label \"Hello\"
+\t|-- 2: div
+\t|   [[ child_width ]] = {:?}
+", height(50.0), width(200.0), width(10.0));
+
+    assert_eq!(dom.dump_with_overrides(), expected);
+
+    // The UiState prints the same overrides after they have been collected
+    let ui_state = dom.into_ui_state(&mut TagIdGenerator::new());
+    assert_eq!(ui_state.dump_with_overrides(), expected);
+}
 
 #[test]
-fn test_dom_sibling_1() {
+fn test_dom_macro() {
 
     struct TestLayout { }
 
     impl Layout for TestLayout {
         fn layout(&self) -> Dom<Self> {
-            Dom::new(NodeType::Div)
-                .with_child(
-                    Dom::new(NodeType::Div)
-                    .with_id("sibling-1")
-                    .with_child(Dom::new(NodeType::Div)
-                        .with_id("sibling-1-child-1")))
-                .with_child(Dom::new(NodeType::Div)
-                    .with_id("sibling-2")
-                    .with_child(Dom::new(NodeType::Div)
-                        .with_id("sibling-2-child-1")))
+            Dom::div()
         }
     }
 
-    let dom = TestLayout{ }.layout();
-    let arena = &dom.arena;
+    fn on_toggle(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        Redraw
+    }
 
-    assert_eq!(NodeId::new(0), dom.root);
+    fn on_focus(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        DontRedraw
+    }
 
-    assert_eq!(vec![String::from("sibling-1")],
-        arena.node_data[
-            arena.node_layout[dom.root]
-            .first_child.expect("root has no first child")
-        ].ids);
+    let footer = Dom::label("Footer").with_class("footer");
+
+    let from_macro: Dom<TestLayout> = dom! {
+        div.sidebar.dark#main [
+            label("Settings").title,
+            div.row on: { MouseUp => on_toggle, FocusReceived => on_focus } [
+                div."check-box",
+                label(format!("Dark {}", "mode")),
+            ],
+            div#"empty-row"[],
+            { footer.clone() },
+        ]
+    };
+
+    let from_builder = Dom::div().with_class("sidebar").with_class("dark").with_id("main")
+        .with_child(Dom::label("Settings").with_class("title"))
+        .with_child(Dom::div().with_class("row")
+            .with_callback(On::MouseUp, Callback(on_toggle))
+            .with_callback(On::FocusReceived, Callback(on_focus))
+            .with_child(Dom::div().with_class("check-box"))
+            .with_child(Dom::label("Dark mode")))
+        .with_child(Dom::div().with_id("empty-row"))
+        .with_child(footer);
+
+    assert_eq!(from_macro, from_builder);
+    assert_eq!(from_macro.traverse().collect::<Vec<_>>(), from_builder.traverse().collect::<Vec<_>>());
+
+    // Single nodes, trailing commas
+    let single: Dom<TestLayout> = dom!(label("Hello"));
+    assert_eq!(single, Dom::label("Hello"));
+    let trailing_comma: Dom<TestLayout> = dom!(div [ div, div, ]);
+    assert_eq!(trailing_comma, Dom::div().with_child(Dom::div()).with_child(Dom::div()));
+}
 
-    assert_eq!(vec![String::from("sibling-2")],
-        arena.node_data[
-            arena.node_layout[
-                arena.node_layout[dom.root]
-                .first_child.expect("root has no first child")
-            ].next_sibling.expect("root has no second sibling")
-        ].ids);
+#[test]
+fn test_node_data_from_str() {
 
-    assert_eq!(vec![String::from("sibling-1-child-1")],
-        arena.node_data[
-            arena.node_layout[
-                arena.node_layout[dom.root]
-                .first_child.expect("root has no first child")
-            ].first_child.expect("first child has no first child")
-        ].ids);
+    use self::NodeDataParseError::*;
 
-    assert_eq!(vec![String::from("sibling-2-child-1")],
-        arena.node_data[
-            arena.node_layout[
-                arena.node_layout[
-                    arena.node_layout[dom.root]
-                    .first_child.expect("root has no first child")
-                ].next_sibling.expect("first child has no second sibling")
-            ].first_child.expect("second sibling has no first child")
-        ].ids);
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn parse(selector: &str) -> Result<NodeData<TestLayout>, NodeDataParseError> {
+        selector.parse()
+    }
+
+    let main = NodeData::div().with_id("main").with_class("container").with_class("dark");
+
+    assert_eq!(parse("div"), Ok(NodeData::div()));
+    assert_eq!(parse("div#main.container.dark"), Ok(main.clone()));
+
+    // The order of the fragments doesn't matter
+    assert_eq!(parse("div.dark#main.container"), Ok(main.clone()));
+    assert_eq!(parse("div.container.dark#main"), Ok(main.clone()));
+
+    // Whitespace is only allowed around the selector
+    assert_eq!(parse("  div#main.container.dark\n"), Ok(main.clone()));
+    assert_eq!(parse("div #main"), Err(InvalidCharacter(' ')));
+    assert_eq!(parse("div#main .dark"), Err(InvalidCharacter(' ')));
+    assert_eq!(parse("   "), Err(EmptySelector));
+
+    // Labels
+    assert_eq!(parse("p.hint"), Ok(NodeData::label("").with_class("hint")));
+    assert_eq!(parse("label(Hello (world)).title"), Ok(NodeData::label("Hello (world)").with_class("title")));
+    assert_eq!(parse("label( spaced ).a.b"), Ok(NodeData::label(" spaced ").with_class("a").with_class("b")));
+    assert_eq!(parse("label(Hello"), Err(UnclosedLabel));
+    assert_eq!(parse("div(Hello)"), Err(InvalidCharacter('(')));
+
+    // Invalid names and fragments
+    assert_eq!(parse("span#main"), Err(InvalidNodeType("span".into())));
+    assert_eq!(parse("#main"), Err(InvalidNodeType("".into())));
+    assert_eq!(parse("img.logo"), Err(NodeTypeNeedsData(NodeTypePath::Img)));
+    assert_eq!(parse("div#.dark"), Err(EmptyId));
+    assert_eq!(parse("div.dark."), Err(EmptyClass));
+
+    let dom = Dom::<TestLayout>::from_selector("label(Hi)#greeting").unwrap();
+    assert_eq!(dom, Dom::label("Hi").with_id("greeting"));
+    assert_eq!(Dom::<TestLayout>::from_selector("iframe"), Err(NodeTypeNeedsData(NodeTypePath::IFrame)));
 }
 
 #[test]
-fn test_dom_from_iter_1() {
+fn test_eq_structure_ignores_callback_pointers() {
 
-    use id_tree::Node;
+    use std::panic;
 
     struct TestLayout { }
 
     impl Layout for TestLayout {
         fn layout(&self) -> Dom<Self> {
-            (0..5).map(|e| NodeData::new(NodeType::Label(format!("{}", e + 1)))).collect()
+            Dom::div()
         }
     }
 
-    let dom = TestLayout{ }.layout();
-    let arena = &dom.arena;
+    fn on_click_a(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen { DontRedraw }
+    fn on_click_b(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen { Redraw }
 
-    // We need to have 6 nodes:
-    //
-    // root                 NodeId(0)
-    //   |-> 1              NodeId(1)
-    //   |-> 2              NodeId(2)
-    //   |-> 3              NodeId(3)
-    //   |-> 4              NodeId(4)
-    //   '-> 5              NodeId(5)
+    // Two helpers that build the same button with different callbacks
+    fn button(label: &str, callback: fn(&mut AppState<TestLayout>, &mut CallbackInfo<TestLayout>) -> UpdateScreen) -> Dom<TestLayout> {
+        Dom::div().with_class("button")
+            .with_callback(On::MouseUp, Callback(callback))
+            .with_child(Dom::label(label))
+    }
 
-    assert_eq!(arena.len(), 6);
+    let a = Dom::div().with_child(button("OK", on_click_a)).with_child(button("Cancel", on_click_a));
+    let b = Dom::div().with_child(button("OK", on_click_b)).with_child(button("Cancel", on_click_b));
+
+    assert!(a != b);
+    assert!(a.eq_structure(&b));
+    a.assert_eq_structure(&b);
+
+    // Different event filters, a missing callback or a different label are still differences
+    let on_hover = Dom::div()
+        .with_child(Dom::div().with_class("button").with_callback(On::MouseOver, Callback(on_click_a)).with_child(Dom::label("OK")))
+        .with_child(button("Cancel", on_click_a));
+    assert!(!a.eq_structure(&on_hover));
+    assert!(!a.eq_structure(&Dom::div().with_child(button("OK", on_click_a)).with_child(Dom::div().with_class("button").with_child(Dom::label("Cancel")))));
+    assert!(!a.eq_structure(&Dom::div().with_child(button("OK", on_click_a)).with_child(button("Abort", on_click_a))));
+    assert!(!a.eq_structure(&Dom::div().with_child(button("OK", on_click_a))));
+
+    // The same nodes in a different tree aren't equal either
+    let flat = Dom::div().with_child(Dom::div().with_class("button")).with_child(Dom::label("OK"));
+    let nested = Dom::div().with_child(Dom::div().with_class("button").with_child(Dom::label("OK")));
+    assert!(!flat.eq_structure(&nested));
+
+    // The panic message contains a diff of the two trees
+    let panic = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        a.assert_eq_structure(&Dom::div().with_child(button("OK", on_click_b)).with_child(button("Abort", on_click_b)));
+    })).unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(message.contains("- ") && message.contains("Abort"), "{}", message);
+    assert!(message.contains("+ ") && message.contains("Cancel"), "{}", message);
+}
 
-    // Check root node
-    assert_eq!(arena.node_layout.get(NodeId::new(0)), Some(&Node {
-        parent: None,
-        previous_sibling: None,
-        next_sibling: None,
-        first_child: Some(NodeId::new(1)),
-        last_child: Some(NodeId::new(5)),
-    }));
-    assert_eq!(arena.node_data.get(NodeId::new(0)), Some(&NodeData::new(NodeType::Div)));
+#[test]
+fn test_dom_statistics() {
 
-    assert_eq!(arena.node_layout.get(NodeId::new(arena.node_layout.len() - 1)), Some(&Node {
-        parent: Some(NodeId::new(0)),
-        previous_sibling: Some(NodeId::new(4)),
-        next_sibling: None,
-        first_child: None,
-        last_child: None,
-    }));
-    assert_eq!(arena.node_data.get(NodeId::new(arena.node_data.len() - 1)), Some(&NodeData {
-        node_type: NodeType::Label(String::from("5")),
-        .. Default::default()
-    }));
+    use azul_css::LayoutWidth;
+    use images::new_image_id;
+    use text_cache::new_text_id;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+        Redraw
+    }
 
+    let dom = Dom::<TestLayout>::div()
+        .with_callback(On::VirtualKeyDown, Callback(callback))
+        .with_child(Dom::div()
+            .with_child(Dom::label("a").with_callback(On::MouseUp, Callback(callback)).with_callback(On::MouseDown, Callback(callback)))
+            .with_child(Dom::label("b").with_callback(On::TextInput, Callback(callback)))
+            .with_child(Dom::new(NodeType::Text(new_text_id()))
+                .with_css_override("width", CssProperty::Width(LayoutWidth::px(10.0)))
+                .with_css_override("min-width", CssProperty::Width(LayoutWidth::px(20.0)))))
+        .with_child(Dom::new(NodeType::Image(new_image_id())).with_callback(On::MouseUp, Callback(callback)));
+
+    let statistics = dom.statistics();
+    assert_eq!(statistics, DomStatistics {
+        nodes: 6,
+        divs: 2,
+        labels: 2,
+        texts: 1,
+        images: 1,
+        gl_textures: 0,
+        iframes: 0,
+        nodes_with_hover_callbacks: 2,
+        nodes_with_focus_callbacks: 1,
+        nodes_with_not_callbacks: 0,
+        nodes_with_window_callbacks: 1,
+        nodes_with_desktop_callbacks: 0,
+        callbacks: 5,
+        dynamic_css_overrides: 2,
+        max_depth: 2,
+    });
+    assert_eq!(statistics.to_string(),
+        "6 nodes (2 divs, 2 labels, 1 texts, 1 images, 0 GL textures, 0 iframes), max depth 2, \
+         5 callbacks (nodes with hover: 2, focus: 1, not: 0, window: 1, desktop: 0), 2 CSS overrides");
+
+    // The UiState keeps the DOM, so the numbers are the same after `into_ui_state`
+    let ui_state = dom.into_ui_state(&mut TagIdGenerator::new());
+    assert_eq!(ui_state.statistics(), statistics);
 }
 
-/// Test that there shouldn't be a DOM that has 0 nodes
 #[test]
-fn test_zero_size_dom() {
+fn test_validate_all_reports_every_broken_invariant() {
+
+    use self::DomError::*;
 
     struct TestLayout { }
 
     impl Layout for TestLayout {
         fn layout(&self) -> Dom<Self> {
-            Dom::new(NodeType::Div)
+            Dom::div()
         }
     }
 
-    let mut null_dom =
-        (0..0)
-        .map(|_| NodeData { node_type: NodeType::Div, .. Default::default() })
-        .collect::<Dom<TestLayout>>();
+    // root -> a (-> c), b
+    let valid = || Dom::<TestLayout>::div()
+        .with_child(Dom::label("a").with_child(Dom::label("c")))
+        .with_child(Dom::label("b"));
+
+    assert_eq!(valid().validate_all(), Ok(()));
+
+    // Two out-of-bounds links: the tree isn't walked at all
+    let mut out_of_bounds = valid();
+    out_of_bounds.arena.node_layout[NodeId::new(1)].first_child = Some(NodeId::new(10));
+    out_of_bounds.arena.node_layout[NodeId::new(3)].parent = Some(NodeId::new(11));
+    assert_eq!(out_of_bounds.validate_all(), Err(vec![
+        OutOfBounds { node: NodeId::new(1), target: NodeId::new(10) },
+        OutOfBounds { node: NodeId::new(3), target: NodeId::new(11) },
+    ]));
+
+    // c thinks that b is its parent and b has lost its link to a
+    let mut wrong_links = valid();
+    wrong_links.arena.node_layout[NodeId::new(2)].parent = Some(NodeId::new(3));
+    wrong_links.arena.node_layout[NodeId::new(3)].previous_sibling = None;
+    assert_eq!(wrong_links.validate_all(), Err(vec![
+        BrokenSiblingLink { node: NodeId::new(3) },
+        WrongParent { node: NodeId::new(2), expected: NodeId::new(1) },
+    ]));
+    assert_eq!(wrong_links.validate(), Err(BrokenSiblingLink { node: NodeId::new(3) }));
+
+    // The root points to the wrong last child and has a parent
+    let mut broken_root = valid();
+    broken_root.arena.node_layout[NodeId::new(0)].last_child = Some(NodeId::new(1));
+    broken_root.arena.node_layout[NodeId::new(0)].parent = Some(NodeId::new(2));
+    assert_eq!(broken_root.validate_all(), Err(vec![
+        RootHasParent { root: NodeId::new(0) },
+        BrokenLastChild { parent: NodeId::new(0) },
+    ]));
+
+    // c is its own ancestor: a cycle, and b can't be reached anymore
+    let mut own_ancestor = valid();
+    own_ancestor.arena.node_layout[NodeId::new(1)].next_sibling = None;
+    own_ancestor.arena.node_layout[NodeId::new(0)].last_child = Some(NodeId::new(1));
+    own_ancestor.arena.node_layout[NodeId::new(2)].first_child = Some(NodeId::new(1));
+    own_ancestor.arena.node_layout[NodeId::new(2)].last_child = Some(NodeId::new(1));
+    assert_eq!(own_ancestor.validate_all(), Err(vec![
+        Cycle { node: NodeId::new(1) },
+        Unreachable { node: NodeId::new(3) },
+    ]));
+
+    // A sibling of the root: several roots, the sibling is unreachable
+    assert_eq!(dom_with_sibling_of_root::<TestLayout>().validate_all(), Err(vec![
+        MultipleRoots { root: NodeId::new(0), sibling: NodeId::new(2) },
+        Unreachable { node: NodeId::new(2) },
+    ]));
+}
 
-    assert!(null_dom.arena.len() == 1);
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "the DOM is malformed: Node 1 refers to node 10")]
+fn test_into_ui_state_panics_on_malformed_dom_in_debug() {
 
-    null_dom.add_class("hello"); // should not panic
-    null_dom.add_id("id-hello"); // should not panic
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let mut dom = Dom::<TestLayout>::div().with_child(Dom::label("a"));
+    dom.arena.node_layout[NodeId::new(1)].first_child = Some(NodeId::new(10));
+    dom.into_ui_state(&mut TagIdGenerator::new());
 }
@@ -0,0 +1,372 @@
+//! Generators for DOMs of a given shape, shared by the benchmarks
+//! (`cargo bench --features benchmarks`) and the unit tests.
+//!
+//! All generators are deterministic: the same arguments always produce the same DOM,
+//! so that benchmark runs before and after an optimization can be compared.
+
+use std::collections::BTreeMap;
+use azul_css::{Css, CssProperty, LayoutWidth, LayoutHeight};
+use {
+    dom::{Dom, NodeData, NodeType, Callback, On, EventFilter, HoverEventFilter, DomHash, DomAllocator, TagId, TagIdGenerator},
+    traits::Layout,
+    id_tree::NodeId,
+    ui_state::{UiState, CallbackMap, CallbackList, CssOverrideList, insert_callback, find_callback, dedup_css_overrides},
+    FastHashMap,
+    ui_description::UiDescription,
+    style::CssRuleIndex,
+    window_state::WindowSize,
+    display_list::DisplayListCacheKey,
+};
+
+/// A root div with `siblings` labels as direct children (`siblings + 1` nodes)
+pub fn wide_dom<T: Layout>(siblings: usize) -> Dom<T> {
+    let mut dom = Dom::with_capacity(NodeType::Div, siblings + 1);
+    for i in 0..siblings {
+        dom.add_child(Dom::label(format!("{}", i)).with_class("item"));
+    }
+    dom
+}
+
+/// `depth` divs, each nested inside of the previous one (`depth` nodes)
+pub fn deep_dom<T: Layout>(depth: usize) -> Dom<T> {
+    let mut dom = Dom::div().with_class("level");
+    for _ in 1..depth {
+        dom = Dom::div().with_class("level").with_child(dom);
+    }
+    dom
+}
+
+/// A div with `size - 1` labels, used as a building block for `composed_dom`
+pub fn fragment<T: Layout>(size: usize) -> Dom<T> {
+    let mut dom = Dom::div().with_class("fragment");
+    for i in 1..size {
+        dom.add_child(Dom::label(format!("{}", i)));
+    }
+    dom
+}
+
+/// A root div with `fragments` children of `fragment(fragment_size)`,
+/// composed via `add_child` (`fragments * fragment_size + 1` nodes)
+pub fn composed_dom<T: Layout>(fragments: usize, fragment_size: usize) -> Dom<T> {
+    let mut dom = Dom::div();
+    for _ in 0..fragments {
+        dom.add_child(fragment(fragment_size));
+    }
+    dom
+}
+
+/// Adds an `On::MouseUp` callback to `percent`% of the nodes of the `dom`,
+/// spread evenly over the document order (0 = no node, 100 = every node)
+pub fn distribute_callbacks<T: Layout>(dom: &mut Dom<T>, percent: usize, callback: Callback<T>) {
+    let percent = percent.min(100);
+    for (idx, node) in dom.arena.node_data.iter_mut().enumerate() {
+        if (idx + 1) * percent / 100 > idx * percent / 100 {
+            node.add_callback(On::MouseUp, callback);
+        }
+    }
+}
+
+/// Collects the hover callbacks of the `dom` into flat per-node lists,
+/// the same way as `into_ui_state` does
+pub fn flat_hover_callbacks<T: Layout>(dom: &Dom<T>) -> CallbackMap<HoverEventFilter, Callback<T>> {
+    let mut hover_callbacks = FastHashMap::default();
+    for node_id in dom.arena.linear_iter() {
+        let mut node_callbacks: CallbackList<HoverEventFilter, Callback<T>> = Vec::new();
+        for (event_filter, callback) in &dom.arena.node_data[node_id].callbacks {
+            if let EventFilter::Hover(hover_filter) = event_filter {
+                insert_callback(&mut node_callbacks, *hover_filter, *callback);
+            }
+        }
+        if !node_callbacks.is_empty() {
+            hover_callbacks.insert(node_id, node_callbacks);
+        }
+    }
+    hover_callbacks
+}
+
+/// Collects the hover callbacks of the `dom` into a `BTreeMap` of `BTreeMap`s, the way
+/// `into_ui_state` stored them before the flat callback lists. Only used as the baseline
+/// for the benchmarks.
+pub fn btree_hover_callbacks<T: Layout>(dom: &Dom<T>) -> BTreeMap<NodeId, BTreeMap<HoverEventFilter, Callback<T>>> {
+    let mut hover_callbacks = BTreeMap::new();
+    for node_id in dom.arena.linear_iter() {
+        let node_callbacks: BTreeMap<HoverEventFilter, Callback<T>> = dom.arena.node_data[node_id].callbacks.iter()
+            .filter_map(|(event_filter, callback)| match event_filter {
+                EventFilter::Hover(hover_filter) => Some((*hover_filter, *callback)),
+                _ => None,
+            })
+            .collect();
+        if !node_callbacks.is_empty() {
+            hover_callbacks.insert(node_id, node_callbacks);
+        }
+    }
+    hover_callbacks
+}
+
+/// Looks up the `filter` callback of the first `nodes` nodes, as done by the event
+/// dispatch for every hit node, returns how many nodes have such a callback
+pub fn lookup_flat_hover_callbacks<T: Layout>(
+    hover_callbacks: &CallbackMap<HoverEventFilter, Callback<T>>,
+    nodes: usize,
+    filter: HoverEventFilter,
+) -> usize {
+    (0..nodes)
+        .filter(|i| hover_callbacks.get(&NodeId::new(*i)).and_then(|list| find_callback(list, &filter)).is_some())
+        .count()
+}
+
+/// Same as `lookup_flat_hover_callbacks`, but for the baseline `btree_hover_callbacks`
+pub fn lookup_btree_hover_callbacks<T: Layout>(
+    hover_callbacks: &BTreeMap<NodeId, BTreeMap<HoverEventFilter, Callback<T>>>,
+    nodes: usize,
+    filter: HoverEventFilter,
+) -> usize {
+    (0..nodes)
+        .filter(|i| hover_callbacks.get(&NodeId::new(*i)).and_then(|map| map.get(&filter)).is_some())
+        .count()
+}
+
+/// A root div with `nodes` divs as direct children, each with an `On::MouseUp`
+/// `callback` (`nodes + 1` nodes)
+pub fn callback_dom<T: Layout>(nodes: usize, callback: Callback<T>) -> Dom<T> {
+    let mut dom = Dom::with_capacity(NodeType::Div, nodes);
+    dom.extend((0..nodes).map(|_| {
+        let mut node = NodeData::new(NodeType::Div);
+        node.add_callback(On::MouseUp, callback);
+        node
+    }));
+    dom
+}
+
+/// Same as `callback_dom`, but built from the memory of the last frame in the `allocator`
+pub fn allocated_callback_dom<T: Layout>(allocator: &mut DomAllocator<T>, nodes: usize, callback: Callback<T>) -> Dom<T> {
+    let mut dom = Dom::with_capacity_in(NodeType::Div, nodes, allocator);
+    dom.extend((0..nodes).map(|_| {
+        let mut node = allocator.node(NodeType::Div);
+        node.add_callback(On::MouseUp, callback);
+        node
+    }));
+    dom
+}
+
+/// Hands the memory of the `dom` back to the `allocator`, as done by the event loop
+/// at the end of every frame
+pub fn recycle_dom<T: Layout>(allocator: &mut DomAllocator<T>, dom: Dom<T>) {
+    allocator.recycle(dom);
+}
+
+/// Adds the dynamic CSS overrides `width` (twice, so the last one has to win)
+/// and `height` to `percent`% of the nodes of the `dom`, spread evenly over the
+/// document order (0 = no node, 100 = every node)
+pub fn distribute_css_overrides<T: Layout>(dom: &mut Dom<T>, percent: usize) {
+    let percent = percent.min(100);
+    for (idx, node) in dom.arena.node_data.iter_mut().enumerate() {
+        if (idx + 1) * percent / 100 > idx * percent / 100 {
+            node.add_css_override("width", CssProperty::Width(LayoutWidth::px(100.0)));
+            node.add_css_override("height", CssProperty::Height(LayoutHeight::px(50.0)));
+            node.add_css_override("width", CssProperty::Width(LayoutWidth::px(200.0)));
+        }
+    }
+}
+
+/// Collects the deduplicated CSS overrides of the `dom` the same way as `into_ui_state`
+/// does, plus the copy for the `UiDescription` (which only clones the shared lists)
+pub fn shared_css_overrides<T: Layout>(dom: &Dom<T>)
+-> (BTreeMap<NodeId, CssOverrideList>, BTreeMap<NodeId, CssOverrideList>)
+{
+    let overrides: BTreeMap<NodeId, CssOverrideList> = dom.arena.linear_iter()
+        .filter(|node_id| !dom.arena.node_data[*node_id].dynamic_css_overrides.is_empty())
+        .map(|node_id| (node_id, dedup_css_overrides(&dom.arena.node_data[node_id].dynamic_css_overrides)))
+        .collect();
+    let styled_overrides = overrides.clone();
+    (overrides, styled_overrides)
+}
+
+/// Same as `shared_css_overrides`, but clones the overrides of every node into a hash map
+/// and the hash maps again for the `UiDescription`, the way `into_ui_state` stored them
+/// before the shared lists. Only used as the baseline for the benchmarks.
+pub fn cloned_css_overrides<T: Layout>(dom: &Dom<T>)
+-> (BTreeMap<NodeId, FastHashMap<String, CssProperty>>, BTreeMap<NodeId, FastHashMap<String, CssProperty>>)
+{
+    let overrides: BTreeMap<NodeId, FastHashMap<String, CssProperty>> = dom.arena.linear_iter()
+        .filter(|node_id| !dom.arena.node_data[*node_id].dynamic_css_overrides.is_empty())
+        .map(|node_id| (node_id, dom.arena.node_data[node_id].dynamic_css_overrides.iter().cloned().collect()))
+        .collect();
+    let styled_overrides = overrides.clone();
+    (overrides, styled_overrides)
+}
+
+/// Hashes every node of the `dom` (plus the node hierarchy), as done on every frame
+/// to check whether the display list can be re-used
+pub fn hash_dom<T: Layout>(dom: &Dom<T>) -> DomHash {
+    dom.calculate_dom_hash()
+}
+
+/// Clears the cached hashes of all nodes, so that the next `hash_dom`
+/// has to re-hash every node (i.e. the first frame or a completely new DOM)
+pub fn clear_hash_caches<T: Layout>(dom: &Dom<T>) {
+    for node in dom.arena.node_data.iter() {
+        node.hash_cache.invalidate_all();
+    }
+}
+
+/// Runs the CPU side of consecutive frames of one window, as done by the event loop
+/// when the window is redrawn: `into_ui_state`, the styling, and the check whether
+/// the display list of the last frame can be re-used. Building the display list
+/// itself requires an OpenGL context and isn't done.
+pub struct WindowFrames {
+    css: Css,
+    css_index: CssRuleIndex,
+    tag_ids: TagIdGenerator,
+    last_cache_key: Option<DisplayListCacheKey>,
+    last_tags: BTreeMap<NodeId, TagId>,
+}
+
+impl WindowFrames {
+
+    pub fn new(css: Css) -> Self {
+        Self {
+            css_index: CssRuleIndex::new(&css),
+            css,
+            tag_ids: TagIdGenerator::new(),
+            last_cache_key: None,
+            last_tags: BTreeMap::new(),
+        }
+    }
+
+    /// Runs one frame with the `dom`, returns whether the display list of
+    /// the last frame could be re-used (i.e. the frame was idle)
+    pub fn frame<T: Layout>(&mut self, dom: Dom<T>) -> bool {
+        let mut ui_state = dom.into_ui_state(&mut self.tag_ids);
+        let ui_description = UiDescription::match_css_to_dom(
+            &mut ui_state,
+            &self.css,
+            &self.css_index,
+            &mut None,
+            &mut None,
+            &BTreeMap::new(),
+            false,
+            &mut self.tag_ids,
+        );
+
+        let cache_key = DisplayListCacheKey::new(&ui_description, &ui_state, &WindowSize::default());
+        if cache_key.is_some() && cache_key == self.last_cache_key {
+            ui_state.reuse_tags(&self.last_tags);
+            true
+        } else {
+            self.last_cache_key = cache_key;
+            self.last_tags = ui_state.node_ids_to_tag_ids.clone();
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+use {
+    app_state::AppState,
+    window::CallbackInfo,
+    dom::{UpdateScreen, DontRedraw},
+};
+
+#[cfg(test)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+#[cfg(test)]
+fn callback(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+    DontRedraw
+}
+
+#[test]
+fn test_generated_dom_sizes() {
+    assert_eq!(wide_dom::<TestLayout>(100).len(), 101);
+    assert_eq!(deep_dom::<TestLayout>(50).len(), 50);
+    assert_eq!(fragment::<TestLayout>(20).len(), 20);
+    assert_eq!(composed_dom::<TestLayout>(10, 20).len(), 201);
+    assert_eq!(wide_dom::<TestLayout>(0).len(), 1);
+}
+
+#[test]
+fn test_allocated_callback_dom() {
+    let mut allocator = DomAllocator::new();
+    for _ in 0..3 {
+        let dom = allocated_callback_dom::<TestLayout>(&mut allocator, 50, Callback(callback));
+        assert!(dom == callback_dom(50, Callback(callback)));
+        recycle_dom(&mut allocator, dom);
+    }
+    assert_eq!(allocator.spare_nodes(), 51);
+}
+
+#[test]
+fn test_flat_and_btree_hover_callbacks_agree() {
+    let mut dom = wide_dom::<TestLayout>(99);
+    distribute_callbacks(&mut dom, 10, Callback(callback));
+    let flat = flat_hover_callbacks(&dom);
+    let btree = btree_hover_callbacks(&dom);
+    assert_eq!(flat.len(), 10);
+    assert_eq!(btree.len(), 10);
+    assert_eq!(lookup_flat_hover_callbacks(&flat, dom.len(), HoverEventFilter::MouseUp), 10);
+    assert_eq!(lookup_btree_hover_callbacks(&btree, dom.len(), HoverEventFilter::MouseUp), 10);
+    assert_eq!(lookup_flat_hover_callbacks(&flat, dom.len(), HoverEventFilter::MouseDown), 0);
+}
+
+#[test]
+fn test_shared_and_cloned_css_overrides_agree() {
+    let mut dom = wide_dom::<TestLayout>(99);
+    distribute_css_overrides(&mut dom, 30);
+    let (shared, _) = shared_css_overrides(&dom);
+    let (cloned, _) = cloned_css_overrides(&dom);
+    assert_eq!(shared.len(), 30);
+    assert_eq!(cloned.len(), 30);
+    for (node_id, overrides) in &shared {
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(cloned[node_id].len(), 2);
+        for (override_id, property) in overrides.iter() {
+            assert_eq!(&cloned[node_id][override_id], property);
+        }
+    }
+}
+
+#[test]
+fn test_distribute_callbacks() {
+    let tagged_nodes = |percent| {
+        let mut dom = wide_dom::<TestLayout>(99);
+        distribute_callbacks(&mut dom, percent, Callback(callback));
+        UiState::from_dom(dom).node_ids_to_tag_ids().len()
+    };
+
+    assert_eq!(tagged_nodes(0), 0);
+    assert_eq!(tagged_nodes(10), 10);
+    assert_eq!(tagged_nodes(100), 100);
+}
+
+#[test]
+fn test_clear_hash_caches_keeps_hash() {
+    let mut dom = composed_dom::<TestLayout>(5, 4);
+    distribute_callbacks(&mut dom, 50, Callback(callback));
+    let cached = hash_dom(&dom);
+    clear_hash_caches(&dom);
+    assert_eq!(hash_dom(&dom), cached);
+    assert_ne!(hash_dom(&composed_dom::<TestLayout>(5, 4)), cached);
+}
+
+#[test]
+fn test_idle_frames_reuse_the_display_list() {
+    let build_dom = || {
+        let mut dom = composed_dom::<TestLayout>(5, 4);
+        distribute_callbacks(&mut dom, 50, Callback(callback));
+        dom
+    };
+
+    let mut frames = WindowFrames::new(Css::default());
+    assert!(!frames.frame(build_dom()));
+    assert!(frames.frame(build_dom()));
+    assert!(frames.frame(build_dom()));
+    assert!(!frames.frame(composed_dom::<TestLayout>(5, 4)));
+}
@@ -0,0 +1,316 @@
+//! Drag and drop between the application and other applications
+//!
+//! Outgoing: a callback of a draggable node calls `CallbackInfo::start_os_drag()` with
+//! the `DragData` it wants to offer (text, files and / or custom formats). The
+//! `DragDropBackend` of the window then starts the drag in the OS - the drop target
+//! picks the format it understands via `DragData::negotiate()`, i.e. a text editor
+//! gets the text (or the file paths as text), a file manager gets the files.
+//!
+//! Incoming: glutin reports dropped files as `On::DroppedFile` events. A
+//! `DragDropBackend` can additionally deliver dropped text and custom formats, which
+//! are dispatched as `On::DroppedData` events to the nodes under the cursor (and as
+//! `WindowEventFilter::DroppedData` to the window callbacks). In both cases, the
+//! callback reads the data from `WindowState::get_dropped_data()`.
+//!
+//! The platform-specific part is the `DragDropBackend` trait (`Window::with_drag_drop_backend()`).
+//! Azul doesn't ship a backend for OLE (Windows), XDND (X11), the Wayland data device or
+//! `NSDragging` yet, since glutin doesn't expose the native drop target of the window -
+//! the application has to provide one. Without a backend, `start_os_drag()` does nothing
+//! and only files can be dropped onto the window.
+
+use std::path::{Path, PathBuf};
+
+/// MIME type of UTF-8 text
+pub const MIME_TEXT: &str = "text/plain;charset=utf-8";
+/// MIME type of a list of files, as `file://` URIs (RFC 2483)
+pub const MIME_URI_LIST: &str = "text/uri-list";
+
+/// Names that are used for UTF-8 text by the different platforms / toolkits
+const TEXT_FORMATS: &[&str] = &[MIME_TEXT, "text/plain", "UTF8_STRING", "STRING", "TEXT"];
+
+/// Data that is offered to other applications when dragging something out of the window
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct DragData {
+    /// Offered as `text/plain`
+    pub text: Option<String>,
+    /// Offered as `text/uri-list` and (if there is no `text`) as `text/plain`, one path per line
+    pub files: Vec<PathBuf>,
+    /// Application-specific formats: (MIME type, data), offered before the standard formats
+    pub custom: Vec<(String, Vec<u8>)>,
+}
+
+impl DragData {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_text<S: Into<String>>(self, text: S) -> Self {
+        Self { text: Some(text.into()), .. self }
+    }
+
+    pub fn with_file<P: Into<PathBuf>>(mut self, file: P) -> Self {
+        self.files.push(file.into());
+        self
+    }
+
+    pub fn with_custom<S: Into<String>>(mut self, format: S, data: Vec<u8>) -> Self {
+        self.custom.push((format.into(), data));
+        self
+    }
+
+    /// Whether there is nothing to drag
+    pub fn is_empty(&self) -> bool {
+        self.text.is_none() && self.files.is_empty() && self.custom.is_empty()
+    }
+
+    /// Returns the formats this data can be dropped as, most specific first
+    pub fn formats(&self) -> Vec<String> {
+        let mut formats = self.custom.iter().map(|(format, _)| format.clone()).collect::<Vec<_>>();
+        if !self.files.is_empty() {
+            formats.push(MIME_URI_LIST.to_string());
+        }
+        if self.text.is_some() || !self.files.is_empty() {
+            formats.push(MIME_TEXT.to_string());
+        }
+        formats
+    }
+
+    /// Returns the data as the given format, `None` if the format isn't offered
+    pub fn data_for_format(&self, format: &str) -> Option<Vec<u8>> {
+        if let Some((_, data)) = self.custom.iter().find(|(custom, _)| formats_match(custom, format)) {
+            return Some(data.clone());
+        }
+        if formats_match(format, MIME_URI_LIST) && !self.files.is_empty() {
+            let uris = self.files.iter().map(|file| path_to_file_uri(file)).collect::<Vec<_>>();
+            return Some((uris.join("\r\n") + "\r\n").into_bytes());
+        }
+        if is_text_format(format) {
+            if let Some(text) = &self.text {
+                return Some(text.clone().into_bytes());
+            }
+            if !self.files.is_empty() {
+                let paths = self.files.iter().map(|file| file.to_string_lossy().into_owned()).collect::<Vec<_>>();
+                return Some(paths.join("\n").into_bytes());
+            }
+        }
+        None
+    }
+
+    /// Picks the format for a drop target that accepts the `accepted` formats (in the
+    /// order of the targets preference): the first offered format that the target accepts.
+    /// Returns `None` if the target can't accept this data.
+    pub fn negotiate(&self, accepted: &[&str]) -> Option<String> {
+        self.formats().into_iter().find(|offered| {
+            accepted.iter().any(|accepted| formats_match(offered, accepted) || (is_text_format(offered) && is_text_format(accepted)))
+        })
+    }
+}
+
+/// Data that was dropped onto the window, see `WindowState::get_dropped_data()`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct DroppedData {
+    /// Dropped text, if the source offered text
+    pub text: Option<String>,
+    /// Dropped files
+    pub files: Vec<PathBuf>,
+    /// All other formats of the drop: (MIME type, data)
+    pub custom: Vec<(String, Vec<u8>)>,
+}
+
+impl DroppedData {
+
+    /// A single file, dropped onto the window (`WindowEvent::DroppedFile`)
+    pub fn from_file(file: PathBuf) -> Self {
+        Self { files: vec![file], .. Self::default() }
+    }
+
+    /// Converts the raw (format, data) pairs that a `DragDropBackend` received into the
+    /// typed data: `text/uri-list` becomes `files`, the text formats become `text` and
+    /// everything else is kept in `custom`.
+    pub fn from_formats(formats: Vec<(String, Vec<u8>)>) -> Self {
+        let mut dropped = Self::default();
+        for (format, data) in formats {
+            if formats_match(&format, MIME_URI_LIST) {
+                let uri_list = String::from_utf8_lossy(&data).into_owned();
+                dropped.files.extend(uri_list.lines().filter_map(file_uri_to_path));
+            } else if is_text_format(&format) {
+                if dropped.text.is_none() {
+                    // Windows terminates the text with a NUL byte
+                    let text = String::from_utf8_lossy(&data).trim_right_matches('\0').to_string();
+                    dropped.text = Some(text);
+                }
+            } else {
+                dropped.custom.push((format, data));
+            }
+        }
+        dropped
+    }
+
+    /// Returns the data of a custom format
+    pub fn get_custom(&self, format: &str) -> Option<&[u8]> {
+        self.custom.iter().find(|(custom, _)| formats_match(custom, format)).map(|(_, data)| &data[..])
+    }
+
+}
+
+/// Adds the data of another drop in the same frame (the first text wins)
+pub(crate) fn add_dropped_data(target: &mut Option<DroppedData>, other: DroppedData) {
+    match target {
+        Some(dropped_data) => {
+            if dropped_data.text.is_none() {
+                dropped_data.text = other.text;
+            }
+            dropped_data.files.extend(other.files);
+            dropped_data.custom.extend(other.custom);
+        },
+        None => *target = Some(other),
+    }
+}
+
+/// The platform side of drag and drop for one window, see the module documentation
+pub trait DragDropBackend {
+    /// Starts a drag out of the window with the data a callback passed to
+    /// `CallbackInfo::start_os_drag()`. Called after the callbacks of the event ran.
+    fn start_drag(&mut self, data: DragData);
+    /// Returns the drops (not including plain file drops, which glutin reports)
+    /// since the last call, see `DroppedData::from_formats()`. Each frame dispatches
+    /// all drops of the frame as one `On::DroppedData` event.
+    fn take_drops(&mut self) -> Vec<DroppedData>;
+}
+
+/// Compares two MIME types, ignoring case and whitespace around parameters
+fn formats_match(a: &str, b: &str) -> bool {
+    normalize_format(a) == normalize_format(b)
+}
+
+fn normalize_format(format: &str) -> String {
+    format.split(';').map(|part| part.trim().to_lowercase()).collect::<Vec<_>>().join(";")
+}
+
+fn is_text_format(format: &str) -> bool {
+    TEXT_FORMATS.iter().any(|text_format| formats_match(text_format, format))
+}
+
+/// Converts an absolute path to a `file://` URI, percent-encoding all reserved characters
+fn path_to_file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => uri.push(byte as char),
+            other => uri.push_str(&format!("%{:02X}", other)),
+        }
+    }
+    uri
+}
+
+/// Parses one line of a `text/uri-list`, `None` for comments and non-file URIs
+fn file_uri_to_path(line: &str) -> Option<PathBuf> {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return None;
+    }
+    let rest = line.get(..7).filter(|scheme| scheme.eq_ignore_ascii_case("file://")).map(|_| &line[7..])?;
+    // Skip the host ("file://localhost/home/...")
+    let path = &rest[rest.find('/')?..];
+
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut input = path.bytes();
+    while let Some(byte) = input.next() {
+        if byte != b'%' {
+            bytes.push(byte);
+            continue;
+        }
+        let hex = [input.next()?, input.next()?];
+        let hex = ::std::str::from_utf8(&hex).ok()?;
+        bytes.push(u8::from_str_radix(hex, 16).ok()?);
+    }
+    let path = String::from_utf8(bytes).ok()?;
+
+    // "/C:/Users" on Windows
+    if cfg!(windows) && path.len() > 2 && path.as_bytes()[2] == b':' {
+        return Some(PathBuf::from(path[1..].replace('/', "\\")));
+    }
+
+    Some(PathBuf::from(path))
+}
+
+/// Drop target that records the drags and replays scripted drops
+#[cfg(test)]
+#[derive(Default)]
+struct MockDragDropBackend {
+    started_drags: Vec<DragData>,
+    pending_drops: Vec<Vec<(String, Vec<u8>)>>,
+}
+
+#[cfg(test)]
+impl DragDropBackend for MockDragDropBackend {
+    fn start_drag(&mut self, data: DragData) {
+        self.started_drags.push(data);
+    }
+    fn take_drops(&mut self) -> Vec<DroppedData> {
+        self.pending_drops.drain(..).map(DroppedData::from_formats).collect()
+    }
+}
+
+#[test]
+fn test_outgoing_drag_format_negotiation() {
+
+    let mut backend = MockDragDropBackend::default();
+    backend.start_drag(DragData::new()
+        .with_file("/home/user/My Notes.txt")
+        .with_custom("application/x-row-id", vec![4, 2]));
+    let drag = &backend.started_drags[0];
+
+    assert_eq!(drag.formats(), vec!["application/x-row-id", MIME_URI_LIST, MIME_TEXT]);
+
+    // A file manager gets the file, a text editor gets the path as text, the
+    // application itself gets the row
+    assert_eq!(drag.negotiate(&["text/uri-list"]), Some(MIME_URI_LIST.to_string()));
+    assert_eq!(drag.negotiate(&["UTF8_STRING"]), Some(MIME_TEXT.to_string()));
+    assert_eq!(drag.negotiate(&["text/plain", "Application/X-Row-Id"]), Some("application/x-row-id".to_string()));
+    assert_eq!(drag.negotiate(&["image/png"]), None);
+
+    assert_eq!(drag.data_for_format(MIME_URI_LIST), Some(b"file:///home/user/My%20Notes.txt\r\n".to_vec()));
+    assert_eq!(drag.data_for_format("STRING"), Some(b"/home/user/My Notes.txt".to_vec()));
+    assert_eq!(drag.data_for_format("application/x-row-id"), Some(vec![4, 2]));
+
+    // Text takes precedence over the file paths
+    let text_drag = DragData::new().with_text("row 4").with_file("/tmp/row4.csv");
+    assert_eq!(text_drag.data_for_format("text/plain"), Some(b"row 4".to_vec()));
+    assert_eq!(DragData::new().negotiate(&[MIME_TEXT]), None);
+}
+
+#[test]
+fn test_incoming_drop_conversion() {
+
+    let mut backend = MockDragDropBackend::default();
+    backend.pending_drops = vec![
+        vec![
+            ("text/uri-list".to_string(), b"# comment\r\nfile:///home/user/My%20Notes.txt\r\nfile://localhost/tmp/a.csv\r\nhttps://example.com/\r\n".to_vec()),
+            ("text/plain;charset=utf-8".to_string(), b"hello\0".to_vec()),
+            ("STRING".to_string(), b"ignored, the first text format wins".to_vec()),
+            ("application/x-row-id".to_string(), vec![4, 2]),
+        ],
+        vec![],
+    ];
+
+    let drops = backend.take_drops();
+    assert_eq!(drops.len(), 2);
+    assert_eq!(drops[0], DroppedData {
+        text: Some("hello".to_string()),
+        files: vec![PathBuf::from("/home/user/My Notes.txt"), PathBuf::from("/tmp/a.csv")],
+        custom: vec![("application/x-row-id".to_string(), vec![4, 2])],
+    });
+    assert_eq!(drops[0].get_custom("application/x-row-id"), Some(&[4, 2][..]));
+    assert_eq!(drops[1], DroppedData::default());
+
+    // Round trip through the outgoing side
+    let drag = DragData::new().with_file("/tmp/50% done #1.txt");
+    let dropped = DroppedData::from_formats(vec![(MIME_URI_LIST.to_string(), drag.data_for_format(MIME_URI_LIST).unwrap())]);
+    assert_eq!(dropped.files, drag.files);
+}
@@ -1,5 +1,6 @@
 pub use app::RuntimeError;
 pub use font::FontError;
+pub use dom::{DomError, NodeDataParseError};
 #[cfg(feature = "image_loading")]
 pub use image::ImageError;
 
@@ -0,0 +1,255 @@
+//! Catching panics in user callbacks, see `AppConfig::abort_on_callback_panic`.
+//!
+//! By default, a panic inside of a `Callback` doesn't unwind through the event loop
+//! (which would close the app and lose the data of the user). Instead, the panic is
+//! caught, logged and stored in the `WindowState` of the window (see
+//! `WindowState::get_callback_panic()`) and an error overlay is drawn on top of the
+//! window content, showing the panic message and the node and event filter of the
+//! callback. Pressing Escape or clicking anywhere dismisses the overlay.
+//!
+//! ## Caveats
+//!
+//! Callbacks get a `&mut AppState<T>`, which isn't `UnwindSafe`, so the callback is
+//! called inside of an `AssertUnwindSafe`. If a callback panics halfway through modifying
+//! the app data, the data stays as the callback left it (for example, only one of two
+//! related fields may be updated) and the app continues with that data. The mutex around
+//! the data doesn't get poisoned, since the panic is caught while the lock is held.
+//!
+//! Panics can't be caught if the app is compiled with `panic = "abort"`.
+
+use std::{
+    fmt,
+    cell::Cell,
+    panic::{self, AssertUnwindSafe},
+};
+use glium::glutin::{Event, WindowEvent, ElementState, MouseButton, VirtualKeyCode, dpi::LogicalSize};
+use webrender::api::{LayoutRect, LayoutPoint, LayoutSize};
+use azul_css::{
+    Css, CssRuleBlock, CssPath, CssPathSelector, CssDeclaration, CssProperty, ColorU,
+    StyleBackgroundColor, StyleTextColor, StyleFontSize, LayoutPadding, PixelValue,
+};
+use {
+    dom::{Dom, EventFilter, UpdateScreen},
+    id_tree::NodeId,
+    traits::Layout,
+};
+
+/// Maximum height of the error overlay, in logical pixels
+const OVERLAY_MAX_HEIGHT: f32 = 160.0;
+
+thread_local! {
+    /// Set while a callback is called by `catch_callback_panic`,
+    /// so that the panic hook knows that the app doesn't have to exit
+    static IS_CATCHING_CALLBACK_PANIC: Cell<bool> = Cell::new(false);
+}
+
+/// Returns whether the current thread is running a callback whose panic is going to be caught
+pub(crate) fn is_catching_callback_panic() -> bool {
+    IS_CATCHING_CALLBACK_PANIC.with(|c| c.get())
+}
+
+/// A panic that was caught while calling a callback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallbackPanic {
+    /// The panic message (or `"<unknown panic payload>"` if the payload isn't a string)
+    pub message: String,
+    /// The node that the callback was attached to
+    pub node_id: NodeId,
+    /// The event filter that the callback was registered for
+    pub event_filter: EventFilter,
+    /// Whether the panicking callback was a default callback (of a widget)
+    pub is_default_callback: bool,
+}
+
+impl fmt::Display for CallbackPanic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} callback for {:?} of node {} panicked: {}",
+            if self.is_default_callback { "Default" } else { "User" },
+            self.event_filter,
+            self.node_id,
+            self.message)
+    }
+}
+
+/// Calls the `callback` and catches a panic inside of it, unless `abort_on_panic` is set
+pub(crate) fn catch_callback_panic<F: FnOnce() -> UpdateScreen>(
+    abort_on_panic: bool,
+    node_id: NodeId,
+    event_filter: EventFilter,
+    is_default_callback: bool,
+    callback: F)
+-> Result<UpdateScreen, CallbackPanic>
+{
+    if abort_on_panic {
+        return Ok(callback());
+    }
+
+    let was_catching = IS_CATCHING_CALLBACK_PANIC.with(|c| c.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(callback));
+    IS_CATCHING_CALLBACK_PANIC.with(|c| c.set(was_catching));
+
+    result.map_err(|payload| {
+        let message = payload.downcast_ref::<String>().cloned()
+            .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_else(|| String::from("<unknown panic payload>"));
+        CallbackPanic { message, node_id, event_filter, is_default_callback }
+    })
+}
+
+/// Returns whether the events dismiss the error overlay (Escape or a left click)
+pub(crate) fn should_dismiss_error_overlay(events: &[Event]) -> bool {
+    events.iter().any(|event| match event {
+        Event::WindowEvent { event: WindowEvent::KeyboardInput { input, .. }, .. } => {
+            input.state == ElementState::Pressed && input.virtual_keycode == Some(VirtualKeyCode::Escape)
+        },
+        Event::WindowEvent { event: WindowEvent::MouseInput { state: ElementState::Released, button: MouseButton::Left, .. }, .. } => true,
+        _ => false,
+    })
+}
+
+/// The rectangle of the overlay: a band over the top of the window
+pub(crate) fn error_overlay_rect(window_size: LogicalSize) -> LayoutRect {
+    LayoutRect::new(
+        LayoutPoint::new(0.0, 0.0),
+        LayoutSize::new(window_size.width as f32, (window_size.height as f32).min(OVERLAY_MAX_HEIGHT)),
+    )
+}
+
+/// Builds the DOM of the error overlay
+pub(crate) fn error_overlay_dom<T: Layout>(panic: &CallbackPanic) -> Dom<T> {
+    overlay_dom(
+        "A callback panicked, the app is still running",
+        panic.message.clone(),
+        &[
+            format!("{} callback for {:?} of node {}",
+                if panic.is_default_callback { "Default" } else { "User" },
+                panic.event_filter,
+                panic.node_id),
+            "Press Escape or click to dismiss".to_string(),
+        ])
+}
+
+/// Builds the DOM of the error overlay for markup of `App::with_hot_reload_ui()` that can't be loaded
+pub(crate) fn ui_reload_error_overlay_dom<T: Layout>(error: &str) -> Dom<T> {
+    overlay_dom(
+        "The UI markup could not be reloaded",
+        error.to_string(),
+        &["The last valid UI is shown until the markup is fixed".to_string()])
+}
+
+fn overlay_dom<T: Layout>(heading: &str, message: String, details: &[String]) -> Dom<T> {
+    let mut overlay = Dom::div().with_class("azul-error-overlay")
+        .with_child(Dom::label(heading).with_class("azul-error-heading"))
+        .with_child(Dom::label(message).with_class("azul-error-message"));
+    for detail in details {
+        overlay.add_child(Dom::label(detail.clone()).with_class("azul-error-detail"));
+    }
+    overlay
+}
+
+/// Stylesheet of the error overlay. Built in code instead of parsed,
+/// so that the overlay also works without the `css-parser` feature
+pub(crate) fn error_overlay_css() -> Css {
+
+    fn class_rule(class: &str, properties: Vec<CssProperty>) -> CssRuleBlock {
+        CssRuleBlock {
+            path: CssPath { selectors: vec![CssPathSelector::Class(class.into())] },
+            declarations: properties.into_iter().map(CssDeclaration::Static).collect(),
+        }
+    }
+
+    let padding = Some(PixelValue::px(10.0));
+    let white = StyleTextColor(ColorU { r: 255, g: 255, b: 255, a: 255 });
+
+    Css::from(vec![
+        class_rule("azul-error-overlay", vec![
+            CssProperty::BackgroundColor(StyleBackgroundColor(ColorU { r: 160, g: 20, b: 20, a: 235 })),
+            CssProperty::Padding(LayoutPadding { top: padding, bottom: padding, left: padding, right: padding }),
+        ]),
+        class_rule("azul-error-heading", vec![
+            CssProperty::TextColor(white),
+            CssProperty::FontSize(StyleFontSize::px(18.0)),
+        ]),
+        class_rule("azul-error-message", vec![
+            CssProperty::TextColor(white),
+            CssProperty::FontSize(StyleFontSize::px(15.0)),
+        ]),
+        class_rule("azul-error-detail", vec![
+            CssProperty::TextColor(StyleTextColor(ColorU { r: 255, g: 210, b: 210, a: 255 })),
+            CssProperty::FontSize(StyleFontSize::px(12.0)),
+        ]),
+    ])
+}
+
+#[cfg(test)]
+fn panicking_callback() -> UpdateScreen {
+    panic!("index out of bounds: the len is 3 but the index is 7");
+}
+
+#[test]
+fn test_callback_panic_is_caught() {
+
+    use dom::{HoverEventFilter, Redraw};
+
+    let filter = EventFilter::Hover(HoverEventFilter::MouseUp);
+
+    assert_eq!(catch_callback_panic(false, NodeId::new(1), filter, false, || Redraw), Ok(Redraw));
+
+    let panic = catch_callback_panic(false, NodeId::new(1), filter, false, panicking_callback).unwrap_err();
+    assert_eq!(panic, CallbackPanic {
+        message: "index out of bounds: the len is 3 but the index is 7".into(),
+        node_id: NodeId::new(1),
+        event_filter: filter,
+        is_default_callback: false,
+    });
+    assert!(!is_catching_callback_panic());
+
+    // Non-string payloads still produce a panic
+    let panic = catch_callback_panic(false, NodeId::new(2), filter, true, || panic::resume_unwind(Box::new(5_u32))).unwrap_err();
+    assert_eq!(panic.message, "<unknown panic payload>");
+}
+
+#[test]
+fn test_callback_panic_aborts_if_configured() {
+    use dom::HoverEventFilter;
+    let filter = EventFilter::Hover(HoverEventFilter::MouseUp);
+    let result = panic::catch_unwind(|| catch_callback_panic(true, NodeId::new(1), filter, false, panicking_callback));
+    assert!(result.is_err());
+}
+
+#[cfg(test)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+#[test]
+fn test_error_overlay_dom_shows_panic() {
+    use dom::HoverEventFilter;
+
+    let panic = CallbackPanic {
+        message: "called `Option::unwrap()` on a `None` value".into(),
+        node_id: NodeId::new(3),
+        event_filter: EventFilter::Hover(HoverEventFilter::MouseUp),
+        is_default_callback: false,
+    };
+
+    let snapshot = error_overlay_dom::<TestLayout>(&panic).serialize_for_snapshot();
+    assert!(snapshot.contains("azul-error-overlay"));
+    assert!(snapshot.contains("called `Option::unwrap()` on a `None` value"));
+    assert!(snapshot.contains("User callback for Hover(MouseUp) of node 3"));
+
+    assert_eq!(error_overlay_css().rules.len(), 4);
+}
+
+#[test]
+fn test_error_overlay_is_dismissed_by_click() {
+    use dispatch_trace::left_click;
+    assert!(!should_dismiss_error_overlay(&[]));
+    assert!(!should_dismiss_error_overlay(&[left_click(ElementState::Pressed)]));
+    assert!(should_dismiss_error_overlay(&[left_click(ElementState::Released)]));
+}
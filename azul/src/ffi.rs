@@ -0,0 +1,643 @@
+//! C ABI for building DOMs and running an app from another language
+//! (with the `ffi` feature). The matching header is `include/azul.h`, generated
+//! with `cbindgen --config cbindgen.toml --output include/azul.h` (run in `azul/`).
+//!
+//! The app data on the Rust side is an `FfiLayout`, which calls the `AzulLayoutFn`
+//! of the C side on every frame. The layout function builds a DOM with the
+//! `azul_dom_*` functions and returns it. Since `Callback`s are plain Rust function
+//! pointers, the C callbacks are stored next to the DOM and called through one
+//! Rust trampoline per event filter.
+//!
+//! # Ownership
+//!
+//! - Every `*_new*` function returns an owned handle, which has to be either passed
+//!   to a function that takes ownership or freed with the matching `*_free` function.
+//! - `azul_dom_add_child` takes ownership of the child, `azul_dom_from_node_data`
+//!   takes ownership of the node data and `azul_app_run` takes ownership of the app.
+//! - The DOM that the `AzulLayoutFn` returns is owned by azul.
+//! - Strings are borrowed for the duration of the call and copied, they have to be
+//!   nul-terminated. Invalid UTF-8 is replaced with U+FFFD.
+//! - The `user_data` pointer is never dereferenced or freed by azul.
+//!
+//! Every function accepts null handles: functions returning a handle return null,
+//! functions returning a status return `AZUL_ERROR_NULL_ARGUMENT` (or `0` for `u8`
+//! success flags) and the `*_free` functions do nothing.
+
+use std::{
+    cell::RefCell,
+    ffi::CStr,
+    os::raw::{c_char, c_void},
+    ptr, slice,
+    sync::Mutex,
+};
+use azul_css::Css;
+use {
+    FastHashMap,
+    app::{App, AppConfig},
+    app_state::AppState,
+    app_resources::RawImageFormat,
+    dom::{Dom, NodeData, NodeType, On, Callback, UpdateScreen, Redraw, DontRedraw},
+    id_tree::NodeId,
+    images::ImageId,
+    text_cache::TextId,
+    traits::Layout,
+    window::{Window, WindowCreateOptions, CallbackInfo},
+};
+#[cfg(not(test))]
+use window::LayoutInfo;
+
+/// Builds the DOM of the current frame, called by azul on every frame
+pub type AzulLayoutFn = extern "C" fn(user_data: *mut c_void) -> *mut AzulDom;
+
+/// Called when the event of `azul_dom_add_callback` fires, returns
+/// `1` if the screen should be redrawn, `0` otherwise
+pub type AzulCallbackFn = extern "C" fn(context: *mut AzulCallbackContext) -> u8;
+
+/// Passed to every `AzulCallbackFn`
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct AzulCallbackContext {
+    /// The `user_data` of `azul_app_new`
+    pub user_data: *mut c_void,
+    /// Index of the node that the event fired on, in document order
+    pub node_index: usize,
+}
+
+/// The app data of an app that is driven through the C ABI
+pub struct FfiLayout {
+    layout_fn: AzulLayoutFn,
+    user_data: *mut c_void,
+    /// C callbacks of the DOM of the current frame
+    callbacks: RefCell<FastHashMap<(NodeId, On), AzulCallbackFn>>,
+}
+
+impl FfiLayout {
+    fn call_layout_fn(&self) -> Dom<Self> {
+        let dom = (self.layout_fn)(self.user_data);
+        if dom.is_null() {
+            *self.callbacks.borrow_mut() = FastHashMap::default();
+            return Dom::div();
+        }
+        let AzulDom { dom, callbacks } = *unsafe { Box::from_raw(dom) };
+        *self.callbacks.borrow_mut() = callbacks.into_iter().map(|(node_id, on, callback)| ((node_id, on), callback)).collect();
+        dom
+    }
+}
+
+impl Layout for FfiLayout {
+    #[cfg(not(test))]
+    fn layout(&self, _: LayoutInfo<Self>) -> Dom<Self> {
+        self.call_layout_fn()
+    }
+    #[cfg(test)]
+    fn layout(&self) -> Dom<Self> {
+        self.call_layout_fn()
+    }
+}
+
+/// Opaque handle to an `App`
+pub struct AzulApp {
+    app: App<FfiLayout>,
+}
+
+/// Opaque handle to a `Dom`, together with its C callbacks
+pub struct AzulDom {
+    dom: Dom<FfiLayout>,
+    callbacks: Vec<(NodeId, On, AzulCallbackFn)>,
+}
+
+/// Opaque handle to a single `NodeData`, together with its C callbacks
+pub struct AzulNodeData {
+    node_data: NodeData<FfiLayout>,
+    callbacks: Vec<(On, AzulCallbackFn)>,
+}
+
+/// The function completed successfully
+pub const AZUL_OK: i32 = 0;
+/// A handle or string argument was null
+pub const AZUL_ERROR_NULL_ARGUMENT: i32 = 1;
+/// The CSS passed to `azul_app_run` could not be parsed
+pub const AZUL_ERROR_CSS: i32 = 2;
+/// The window could not be created
+pub const AZUL_ERROR_WINDOW: i32 = 3;
+/// The app loop exited with an error
+pub const AZUL_ERROR_RUNTIME: i32 = 4;
+
+/// Returned by the `azul_app_add_*` functions if the resource could not be added
+pub const AZUL_INVALID_ID: usize = ::std::usize::MAX;
+
+/// `azul_app_add_image_raw`: one byte per pixel (greyscale)
+pub const AZUL_IMAGE_FORMAT_R8: u32 = 0;
+/// `azul_app_add_image_raw`: four bytes per pixel, premultiplied BGRA
+pub const AZUL_IMAGE_FORMAT_BGRA8: u32 = 1;
+
+macro_rules! ffi_event_filters {
+    ($($(#[$attr:meta])* $code_name:ident = $code:tt => $on:ident, $trampoline:ident;)+) => {
+        $(
+            $(#[$attr])*
+            pub const $code_name: u32 = $code;
+
+            fn $trampoline(app_state: &mut AppState<FfiLayout>, info: &mut CallbackInfo<FfiLayout>) -> UpdateScreen {
+                call_ffi_callback(On::$on, &app_state.data, info.hit_dom_node)
+            }
+        )+
+
+        /// Maps an event filter code to the `On` and the trampoline of the event filter
+        fn event_filter_from_code(code: u32) -> Option<(On, Callback<FfiLayout>)> {
+            match code {
+                $($code => Some((On::$on, Callback($trampoline))),)+
+                _ => None,
+            }
+        }
+    };
+}
+
+ffi_event_filters! {
+    /// Event filter code for `azul_dom_add_callback`, see `On::MouseOver`
+    AZUL_ON_MOUSE_OVER = 0 => MouseOver, trampoline_mouse_over;
+    /// Event filter code for `azul_dom_add_callback`, see `On::MouseDown`
+    AZUL_ON_MOUSE_DOWN = 1 => MouseDown, trampoline_mouse_down;
+    /// Event filter code for `azul_dom_add_callback`, see `On::LeftMouseDown`
+    AZUL_ON_LEFT_MOUSE_DOWN = 2 => LeftMouseDown, trampoline_left_mouse_down;
+    /// Event filter code for `azul_dom_add_callback`, see `On::MiddleMouseDown`
+    AZUL_ON_MIDDLE_MOUSE_DOWN = 3 => MiddleMouseDown, trampoline_middle_mouse_down;
+    /// Event filter code for `azul_dom_add_callback`, see `On::RightMouseDown`
+    AZUL_ON_RIGHT_MOUSE_DOWN = 4 => RightMouseDown, trampoline_right_mouse_down;
+    /// Event filter code for `azul_dom_add_callback`, see `On::MouseUp`
+    AZUL_ON_MOUSE_UP = 5 => MouseUp, trampoline_mouse_up;
+    /// Event filter code for `azul_dom_add_callback`, see `On::LeftMouseUp`
+    AZUL_ON_LEFT_MOUSE_UP = 6 => LeftMouseUp, trampoline_left_mouse_up;
+    /// Event filter code for `azul_dom_add_callback`, see `On::MiddleMouseUp`
+    AZUL_ON_MIDDLE_MOUSE_UP = 7 => MiddleMouseUp, trampoline_middle_mouse_up;
+    /// Event filter code for `azul_dom_add_callback`, see `On::RightMouseUp`
+    AZUL_ON_RIGHT_MOUSE_UP = 8 => RightMouseUp, trampoline_right_mouse_up;
+    /// Event filter code for `azul_dom_add_callback`, see `On::MouseEnter`
+    AZUL_ON_MOUSE_ENTER = 9 => MouseEnter, trampoline_mouse_enter;
+    /// Event filter code for `azul_dom_add_callback`, see `On::MouseLeave`
+    AZUL_ON_MOUSE_LEAVE = 10 => MouseLeave, trampoline_mouse_leave;
+    /// Event filter code for `azul_dom_add_callback`, see `On::Scroll`
+    AZUL_ON_SCROLL = 11 => Scroll, trampoline_scroll;
+    /// Event filter code for `azul_dom_add_callback`, see `On::TextInput`
+    AZUL_ON_TEXT_INPUT = 12 => TextInput, trampoline_text_input;
+    /// Event filter code for `azul_dom_add_callback`, see `On::VirtualKeyDown`
+    AZUL_ON_VIRTUAL_KEY_DOWN = 13 => VirtualKeyDown, trampoline_virtual_key_down;
+    /// Event filter code for `azul_dom_add_callback`, see `On::VirtualKeyUp`
+    AZUL_ON_VIRTUAL_KEY_UP = 14 => VirtualKeyUp, trampoline_virtual_key_up;
+    /// Event filter code for `azul_dom_add_callback`, see `On::HoveredFile`
+    AZUL_ON_HOVERED_FILE = 15 => HoveredFile, trampoline_hovered_file;
+    /// Event filter code for `azul_dom_add_callback`, see `On::DroppedFile`
+    AZUL_ON_DROPPED_FILE = 16 => DroppedFile, trampoline_dropped_file;
+    /// Event filter code for `azul_dom_add_callback`, see `On::HoveredFileCancelled`
+    AZUL_ON_HOVERED_FILE_CANCELLED = 17 => HoveredFileCancelled, trampoline_hovered_file_cancelled;
+    /// Event filter code for `azul_dom_add_callback`, see `On::FocusReceived`
+    AZUL_ON_FOCUS_RECEIVED = 18 => FocusReceived, trampoline_focus_received;
+    /// Event filter code for `azul_dom_add_callback`, see `On::FocusLost`
+    AZUL_ON_FOCUS_LOST = 19 => FocusLost, trampoline_focus_lost;
+    /// Event filter code for `azul_dom_add_callback`, see `On::DoubleClick`
+    AZUL_ON_DOUBLE_CLICK = 20 => DoubleClick, trampoline_double_click;
+    /// Event filter code for `azul_dom_add_callback`, see `On::LeftDoubleClick`
+    AZUL_ON_LEFT_DOUBLE_CLICK = 21 => LeftDoubleClick, trampoline_left_double_click;
+    /// Event filter code for `azul_dom_add_callback`, see `On::MiddleDoubleClick`
+    AZUL_ON_MIDDLE_DOUBLE_CLICK = 22 => MiddleDoubleClick, trampoline_middle_double_click;
+    /// Event filter code for `azul_dom_add_callback`, see `On::RightDoubleClick`
+    AZUL_ON_RIGHT_DOUBLE_CLICK = 23 => RightDoubleClick, trampoline_right_double_click;
+    /// Event filter code for `azul_dom_add_callback`, see `On::ScrollUp`
+    AZUL_ON_SCROLL_UP = 24 => ScrollUp, trampoline_scroll_up;
+    /// Event filter code for `azul_dom_add_callback`, see `On::ScrollDown`
+    AZUL_ON_SCROLL_DOWN = 25 => ScrollDown, trampoline_scroll_down;
+    /// Event filter code for `azul_dom_add_callback`, see `On::ScrollHorizontal`
+    AZUL_ON_SCROLL_HORIZONTAL = 26 => ScrollHorizontal, trampoline_scroll_horizontal;
+    /// Event filter code for `azul_dom_add_callback`, see `On::DroppedData`
+    AZUL_ON_DROPPED_DATA = 27 => DroppedData, trampoline_dropped_data;
+}
+
+fn call_ffi_callback(on: On, data: &Mutex<FfiLayout>, hit_dom_node: NodeId) -> UpdateScreen {
+
+    // Don't hold the lock while the C side runs
+    let (callback, user_data) = {
+        let data = match data.lock() {
+            Ok(data) => data,
+            Err(_) => return DontRedraw,
+        };
+        let callback = data.callbacks.borrow().get(&(hit_dom_node, on)).cloned();
+        (callback, data.user_data)
+    };
+
+    let callback = match callback {
+        Some(callback) => callback,
+        None => return DontRedraw,
+    };
+
+    let mut context = AzulCallbackContext {
+        user_data,
+        node_index: hit_dom_node.index(),
+    };
+
+    if callback(&mut context) != 0 { Redraw } else { DontRedraw }
+}
+
+/// Copies a borrowed C string, `None` if the pointer is null
+unsafe fn string_from_c(string: *const c_char) -> Option<String> {
+    if string.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(string).to_string_lossy().into_owned())
+    }
+}
+
+fn into_handle<T>(value: T) -> *mut T {
+    Box::into_raw(Box::new(value))
+}
+
+// --- App
+
+/// Creates a new app, `layout_fn` is called with `user_data` on every frame
+#[no_mangle]
+pub extern "C" fn azul_app_new(layout_fn: Option<AzulLayoutFn>, user_data: *mut c_void) -> *mut AzulApp {
+    let layout_fn = match layout_fn {
+        Some(layout_fn) => layout_fn,
+        None => return ptr::null_mut(),
+    };
+    let data = FfiLayout {
+        layout_fn,
+        user_data,
+        callbacks: RefCell::new(FastHashMap::default()),
+    };
+    into_handle(AzulApp { app: App::new(data, AppConfig::default()) })
+}
+
+/// Frees an app that was never run
+#[no_mangle]
+pub unsafe extern "C" fn azul_app_free(app: *mut AzulApp) {
+    if !app.is_null() {
+        drop(Box::from_raw(app));
+    }
+}
+
+/// Registers decoded pixels as an image, `format` is one of the `AZUL_IMAGE_FORMAT_*`
+/// constants. Returns the ID for `azul_dom_new_image` or `AZUL_INVALID_ID`.
+#[no_mangle]
+pub unsafe extern "C" fn azul_app_add_image_raw(
+    app: *mut AzulApp,
+    id: *const c_char,
+    pixels: *const u8,
+    pixels_len: usize,
+    width: u32,
+    height: u32,
+    format: u32)
+-> usize
+{
+    let (app, id) = match (app.as_mut(), string_from_c(id)) {
+        (Some(app), Some(id)) => (app, id),
+        _ => return AZUL_INVALID_ID,
+    };
+    let (format, bytes_per_pixel) = match format {
+        AZUL_IMAGE_FORMAT_R8 => (RawImageFormat::R8, 1),
+        AZUL_IMAGE_FORMAT_BGRA8 => (RawImageFormat::BGRA8, 4),
+        _ => return AZUL_INVALID_ID,
+    };
+    if pixels.is_null() || pixels_len != width as usize * height as usize * bytes_per_pixel {
+        return AZUL_INVALID_ID;
+    }
+    let pixels = slice::from_raw_parts(pixels, pixels_len).to_vec();
+    app.app.add_image_raw(id.as_str(), pixels, (width, height), format);
+    app.app.app_state.resources.get_image(&id).map(|image_id| image_id.to_raw()).unwrap_or(AZUL_INVALID_ID)
+}
+
+/// Registers a text, returns the ID for `azul_dom_new_text` or `AZUL_INVALID_ID`
+#[no_mangle]
+pub unsafe extern "C" fn azul_app_add_text(app: *mut AzulApp, text: *const c_char) -> usize {
+    match (app.as_mut(), string_from_c(text)) {
+        (Some(app), Some(text)) => app.app.add_text_uncached(text).to_raw(),
+        _ => AZUL_INVALID_ID,
+    }
+}
+
+/// Opens a window styled with `css` (the native style if `css` is null)
+/// and runs the app until all windows are closed. Takes ownership of the app.
+#[no_mangle]
+pub unsafe extern "C" fn azul_app_run(app: *mut AzulApp, css: *const c_char) -> i32 {
+
+    if app.is_null() {
+        return AZUL_ERROR_NULL_ARGUMENT;
+    }
+    let app = Box::from_raw(app).app;
+
+    let css = match string_from_c(css) {
+        Some(css) => match ::css::from_str(&css) {
+            Ok(css) => css,
+            Err(_) => return AZUL_ERROR_CSS,
+        },
+        None => default_css(),
+    };
+
+    let window = match Window::new(WindowCreateOptions::default(), css) {
+        Ok(window) => window,
+        Err(_) => return AZUL_ERROR_WINDOW,
+    };
+
+    match app.run(window) {
+        Ok(_) => AZUL_OK,
+        Err(_) => AZUL_ERROR_RUNTIME,
+    }
+}
+
+#[cfg(feature = "native-style")]
+fn default_css() -> Css {
+    ::css::native()
+}
+
+#[cfg(not(feature = "native-style"))]
+fn default_css() -> Css {
+    Css::default()
+}
+
+// --- Dom
+
+fn new_dom(node_type: NodeType<FfiLayout>) -> *mut AzulDom {
+    into_handle(AzulDom { dom: Dom::new(node_type), callbacks: Vec::new() })
+}
+
+#[no_mangle]
+pub extern "C" fn azul_dom_new_div() -> *mut AzulDom {
+    new_dom(NodeType::Div)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn azul_dom_new_label(text: *const c_char) -> *mut AzulDom {
+    match string_from_c(text) {
+        Some(text) => new_dom(NodeType::Label(text)),
+        None => ptr::null_mut(),
+    }
+}
+
+/// `text_id` is the return value of `azul_app_add_text`
+#[no_mangle]
+pub extern "C" fn azul_dom_new_text(text_id: usize) -> *mut AzulDom {
+    if text_id == AZUL_INVALID_ID {
+        return ptr::null_mut();
+    }
+    new_dom(NodeType::Text(TextId::from_raw(text_id)))
+}
+
+/// `image_id` is the return value of `azul_app_add_image_raw`
+#[no_mangle]
+pub extern "C" fn azul_dom_new_image(image_id: usize) -> *mut AzulDom {
+    if image_id == AZUL_INVALID_ID {
+        return ptr::null_mut();
+    }
+    new_dom(NodeType::Image(ImageId::from_raw(image_id)))
+}
+
+/// Creates a DOM with the node as its root, takes ownership of the node data
+#[no_mangle]
+pub unsafe extern "C" fn azul_dom_from_node_data(node_data: *mut AzulNodeData) -> *mut AzulDom {
+    if node_data.is_null() {
+        return ptr::null_mut();
+    }
+    let AzulNodeData { mut node_data, callbacks } = *Box::from_raw(node_data);
+    node_data.invalidate_hash_cache();
+    let mut dom = Dom::div();
+    let root = dom.root;
+    dom.arena.node_data[root] = node_data;
+    into_handle(AzulDom {
+        dom,
+        callbacks: callbacks.into_iter().map(|(on, callback)| (root, on, callback)).collect(),
+    })
+}
+
+/// Appends `child` to the root of `parent`, takes ownership of the child.
+/// Returns `1` on success, `0` if one of the handles is null.
+#[no_mangle]
+pub unsafe extern "C" fn azul_dom_add_child(parent: *mut AzulDom, child: *mut AzulDom) -> u8 {
+    let parent = match parent.as_mut() {
+        Some(parent) => parent,
+        None => return 0,
+    };
+    if child.is_null() {
+        return 0;
+    }
+    let AzulDom { dom: child_dom, callbacks: child_callbacks } = *Box::from_raw(child);
+    // add_child appends the nodes of the child after the nodes of the parent
+    let offset = parent.dom.len();
+    parent.dom.add_child(child_dom);
+    parent.callbacks.extend(child_callbacks.into_iter().map(|(node_id, on, callback)| {
+        (NodeId::new(node_id.index() + offset), on, callback)
+    }));
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn azul_dom_add_id(dom: *mut AzulDom, id: *const c_char) -> u8 {
+    match (dom.as_mut(), string_from_c(id)) {
+        (Some(dom), Some(id)) => { dom.dom.add_id(id); 1 },
+        _ => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn azul_dom_add_class(dom: *mut AzulDom, class: *const c_char) -> u8 {
+    match (dom.as_mut(), string_from_c(class)) {
+        (Some(dom), Some(class)) => { dom.dom.add_class(class); 1 },
+        _ => 0,
+    }
+}
+
+/// Calls `callback` when the event `event_filter` (one of the `AZUL_ON_*` constants)
+/// fires on the root of the DOM. Returns `0` if a handle is null or the code is unknown.
+#[no_mangle]
+pub unsafe extern "C" fn azul_dom_add_callback(dom: *mut AzulDom, event_filter: u32, callback: Option<AzulCallbackFn>) -> u8 {
+    match (dom.as_mut(), event_filter_from_code(event_filter), callback) {
+        (Some(dom), Some((on, trampoline)), Some(callback)) => {
+            dom.dom.add_callback(on, trampoline);
+            let root = dom.dom.root;
+            dom.callbacks.retain(|(node_id, existing_on, _)| !(*node_id == root && *existing_on == on));
+            dom.callbacks.push((root, on, callback));
+            1
+        },
+        _ => 0,
+    }
+}
+
+/// Frees a DOM that was not passed to `azul_dom_add_child` or returned from the layout function
+#[no_mangle]
+pub unsafe extern "C" fn azul_dom_free(dom: *mut AzulDom) {
+    if !dom.is_null() {
+        drop(Box::from_raw(dom));
+    }
+}
+
+// --- NodeData
+
+#[no_mangle]
+pub extern "C" fn azul_node_data_new_div() -> *mut AzulNodeData {
+    into_handle(AzulNodeData { node_data: NodeData::new(NodeType::Div), callbacks: Vec::new() })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn azul_node_data_add_id(node_data: *mut AzulNodeData, id: *const c_char) -> u8 {
+    match (node_data.as_mut(), string_from_c(id)) {
+        (Some(node_data), Some(id)) => { node_data.node_data.add_id(id); 1 },
+        _ => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn azul_node_data_add_class(node_data: *mut AzulNodeData, class: *const c_char) -> u8 {
+    match (node_data.as_mut(), string_from_c(class)) {
+        (Some(node_data), Some(class)) => { node_data.node_data.add_class(class); 1 },
+        _ => 0,
+    }
+}
+
+/// Same as `azul_dom_add_callback`, but for a single node
+#[no_mangle]
+pub unsafe extern "C" fn azul_node_data_add_callback(node_data: *mut AzulNodeData, event_filter: u32, callback: Option<AzulCallbackFn>) -> u8 {
+    match (node_data.as_mut(), event_filter_from_code(event_filter), callback) {
+        (Some(node_data), Some((on, trampoline)), Some(callback)) => {
+            node_data.node_data.add_callback(on, trampoline);
+            node_data.callbacks.retain(|(existing_on, _)| *existing_on != on);
+            node_data.callbacks.push((on, callback));
+            1
+        },
+        _ => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn azul_node_data_free(node_data: *mut AzulNodeData) {
+    if !node_data.is_null() {
+        drop(Box::from_raw(node_data));
+    }
+}
+
+#[cfg(test)]
+use std::ffi::CString;
+
+#[cfg(test)]
+extern "C" fn test_counter_callback(context: *mut AzulCallbackContext) -> u8 {
+    let context = unsafe { &mut *context };
+    let counter = unsafe { &mut *(context.user_data as *mut usize) };
+    *counter += 1;
+    1
+}
+
+/// row 0: div.row > label "first"
+/// row 1: div.row (LeftMouseUp) > label "second"
+#[cfg(test)]
+extern "C" fn test_layout_fn(_: *mut c_void) -> *mut AzulDom {
+    unsafe {
+        let row_class = CString::new("row").unwrap();
+        let root = azul_dom_new_div();
+        assert_eq!(azul_dom_add_id(root, CString::new("list").unwrap().as_ptr()), 1);
+        for (row_idx, text) in ["first", "second"].iter().enumerate() {
+            let row = azul_dom_new_div();
+            azul_dom_add_class(row, row_class.as_ptr());
+            if row_idx == 1 {
+                assert_eq!(azul_dom_add_callback(row, AZUL_ON_LEFT_MOUSE_UP, Some(test_counter_callback)), 1);
+            }
+            azul_dom_add_child(row, azul_dom_new_label(CString::new(*text).unwrap().as_ptr()));
+            azul_dom_add_child(root, row);
+        }
+        root
+    }
+}
+
+#[test]
+fn test_ffi_dom_matches_rust_dom() {
+
+    let ffi_layout = FfiLayout {
+        layout_fn: test_layout_fn,
+        user_data: ptr::null_mut(),
+        callbacks: RefCell::new(FastHashMap::default()),
+    };
+
+    let dom = ffi_layout.layout();
+    let expected: Dom<FfiLayout> = Dom::div().with_id("list")
+        .with_child(Dom::div().with_class("row").with_child(Dom::label("first")))
+        .with_child(Dom::div().with_class("row").with_callback(On::LeftMouseUp, Callback(trampoline_left_mouse_up)).with_child(Dom::label("second")));
+
+    assert_eq!(dom.serialize_for_snapshot(), expected.serialize_for_snapshot());
+
+    // The callback of the second row is registered under the NodeId of the second row
+    let callbacks = ffi_layout.callbacks.borrow();
+    assert_eq!(callbacks.len(), 1);
+    assert!(callbacks.contains_key(&(NodeId::new(3), On::LeftMouseUp)));
+}
+
+#[test]
+fn test_ffi_callback_is_called_through_the_trampoline() {
+
+    let mut counter = 0_usize;
+    let ffi_layout = FfiLayout {
+        layout_fn: test_layout_fn,
+        user_data: &mut counter as *mut usize as *mut c_void,
+        callbacks: RefCell::new(FastHashMap::default()),
+    };
+    let _ = ffi_layout.layout();
+    let data = Mutex::new(ffi_layout);
+
+    assert_eq!(call_ffi_callback(On::LeftMouseUp, &data, NodeId::new(3)), Redraw);
+    // No C callback for this event on this node / for this node at all
+    assert_eq!(call_ffi_callback(On::MouseDown, &data, NodeId::new(3)), DontRedraw);
+    assert_eq!(call_ffi_callback(On::LeftMouseUp, &data, NodeId::new(1)), DontRedraw);
+
+    drop(data);
+    assert_eq!(counter, 1);
+}
+
+#[test]
+fn test_ffi_null_arguments() {
+    unsafe {
+        assert!(azul_app_new(None, ptr::null_mut()).is_null());
+        assert!(azul_dom_new_label(ptr::null()).is_null());
+        assert!(azul_dom_new_text(AZUL_INVALID_ID).is_null());
+        assert!(azul_dom_from_node_data(ptr::null_mut()).is_null());
+        assert_eq!(azul_dom_add_child(ptr::null_mut(), ptr::null_mut()), 0);
+        assert_eq!(azul_dom_add_id(ptr::null_mut(), ptr::null()), 0);
+        assert_eq!(azul_dom_add_callback(ptr::null_mut(), AZUL_ON_MOUSE_UP, Some(test_counter_callback)), 0);
+        assert_eq!(azul_app_run(ptr::null_mut(), ptr::null()), AZUL_ERROR_NULL_ARGUMENT);
+        azul_dom_free(ptr::null_mut());
+        azul_node_data_free(ptr::null_mut());
+        azul_app_free(ptr::null_mut());
+
+        // Unknown event filter codes are rejected, the DOM stays valid
+        let dom = azul_dom_new_div();
+        assert_eq!(azul_dom_add_callback(dom, 999, Some(test_counter_callback)), 0);
+        assert_eq!(azul_dom_add_child(dom, ptr::null_mut()), 0);
+        azul_dom_free(dom);
+    }
+}
+
+#[test]
+fn test_ffi_node_data_becomes_dom_root() {
+    unsafe {
+        let node_data = azul_node_data_new_div();
+        azul_node_data_add_class(node_data, CString::new("button").unwrap().as_ptr());
+        assert_eq!(azul_node_data_add_callback(node_data, AZUL_ON_MOUSE_UP, Some(test_counter_callback)), 1);
+        let dom = azul_dom_from_node_data(node_data);
+        let dom = Box::from_raw(dom);
+        assert!(dom.dom.arena.node_data[dom.dom.root].has_class("button"));
+        assert_eq!(dom.callbacks.len(), 1);
+        assert_eq!(dom.callbacks[0].0, dom.dom.root);
+    }
+}
+
+/// Checks that `include/azul.h` and the C example compile. Needs a C compiler (`cc`),
+/// so it is ignored by default. Running the example needs a display and azul built
+/// as a static library, see the comment at the top of `examples/ffi/hello_world.c`.
+#[test]
+#[ignore]
+fn test_ffi_c_example_compiles() {
+    use std::process::Command;
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let status = Command::new("cc")
+        .arg("-fsyntax-only")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I").arg(format!("{}/include", manifest_dir))
+        .arg(format!("{}/../examples/ffi/hello_world.c", manifest_dir))
+        .status()
+        .expect("could not run the C compiler");
+    assert!(status.success());
+}
@@ -1,20 +1,6 @@
 //! Module for loading and handling fonts
-use webrender::api::FontKey;
 use rusttype::{Error as RusttypeError, Font, FontCollection};
 
-#[derive(Debug, Clone)]
-pub(crate) enum FontState {
-    // Font is available for the renderer
-    Uploaded(FontKey),
-    // Raw bytes for the font, to be uploaded in the next
-    // draw call (for WebRender's add_raw_font function)
-    ReadyForUpload(Vec<u8>),
-    /// Font that is about to be deleted
-    /// We need both the ID (to delete the bytes of the font)
-    /// as well as the FontKey to delete all the font instances
-    AboutToBeDeleted(Option<FontKey>),
-}
-
 #[derive(Debug)]
 pub enum FontError {
     /// Font failed to upload to the GPU
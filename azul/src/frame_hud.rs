@@ -0,0 +1,320 @@
+//! Frame-budget HUD, see `WindowState::set_frame_hud()`.
+//!
+//! The HUD is drawn in the top right corner of the window, on top of the content:
+//! the current FPS, a graph of the frame times of the last frames (with a line at
+//! the 60 FPS budget), one bar per `FramePhase` and the node, tag and display
+//! rectangle counters. It shows the stats of the *previous* frame - the stats of
+//! the frame that is being drawn aren't complete yet. Drawing the HUD is left out
+//! of the stats (see `FrameProfiler::begin_untracked()`), so that it doesn't
+//! perturb the numbers that it shows, but the display list can't be cached from
+//! one frame to the next while the HUD is visible.
+//!
+//! The module only exists with the `profiling` feature, without the feature the
+//! stats aren't recorded and the HUD is never drawn. If the HUD is hidden, it costs nothing.
+//!
+//! The numbers of the HUD are available as a `FrameHudData`, see
+//! `WindowState::frame_hud_data()`, for example to check in a test what the HUD
+//! shows for a `FrameStatsHistory` with fake stats.
+
+use std::time::Duration;
+use glium::glutin::dpi::LogicalSize;
+use webrender::api::{LayoutRect, LayoutPoint, LayoutSize};
+use azul_css::{
+    Css, CssRuleBlock, CssPath, CssPathSelector, CssDeclaration, CssProperty, ColorU,
+    StyleBackgroundColor, StyleTextColor, StyleFontSize, LayoutPadding, LayoutHeight, PixelValue,
+};
+use {
+    dom::Dom,
+    debug_overlay::DebugRect,
+    profiling::{FramePhase, FrameStatsHistory},
+    traits::Layout,
+};
+
+/// Time that a frame may take to keep up with 60 frames per second
+pub const FRAME_BUDGET: Duration = Duration::from_nanos(16_666_667);
+
+/// How many of the last frames are shown in the frame time graph
+const GRAPH_FRAMES: usize = 60;
+/// The FPS is averaged over the frames of (at least) this time span
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+const HUD_WIDTH: f32 = 240.0;
+const HUD_MARGIN: f32 = 8.0;
+const HUD_PADDING: f32 = 6.0;
+const ROW_HEIGHT: f32 = 14.0;
+const GRAPH_HEIGHT: f32 = 40.0;
+/// Width of the phase labels, the phase bars are drawn to the right of them
+const LABEL_WIDTH: f32 = 120.0;
+/// The graph and the bars go up to twice the `FRAME_BUDGET`
+const GRAPH_SCALE: f32 = 2.0;
+
+const GRAPH_BACKGROUND_COLOR: ColorU = ColorU { r: 255, g: 255, b: 255, a: 30 };
+const BUDGET_LINE_COLOR: ColorU = ColorU { r: 255, g: 220, b: 0, a: 200 };
+const WITHIN_BUDGET_COLOR: ColorU = ColorU { r: 80, g: 220, b: 80, a: 255 };
+const OVER_BUDGET_COLOR: ColorU = ColorU { r: 255, g: 70, b: 70, a: 255 };
+const PHASE_BAR_COLOR: ColorU = ColorU { r: 90, g: 170, b: 255, a: 255 };
+
+/// The numbers that the frame HUD shows, see `WindowState::frame_hud_data()`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FrameHudData {
+    /// Frames per second, averaged over the frames of the last second -
+    /// zero if fewer than two frames have been drawn
+    pub fps: f32,
+    /// Total time of the last frames, oldest frame first
+    pub frame_times: Vec<Duration>,
+    /// How long each phase of the last frame took, in the order of `FramePhase::ALL`
+    pub phases: Vec<(FramePhase, Duration)>,
+    /// Number of nodes in the DOM of the last frame
+    pub node_count: usize,
+    /// Number of hit-testing tags that were handed out during the last frame
+    pub tags_created: usize,
+    /// Number of rectangles that were pushed into the display list of the last frame
+    pub display_rectangles: usize,
+}
+
+impl FrameHudData {
+
+    /// Computes the numbers of the HUD from the stats of the last frames
+    pub fn from_history(history: &FrameStatsHistory) -> Self {
+
+        let last = history.last().cloned().unwrap_or_default();
+        let skip = history.len().saturating_sub(GRAPH_FRAMES);
+
+        Self {
+            fps: frames_per_second(history),
+            frame_times: history.iter().skip(skip).map(|stats| stats.total).collect(),
+            phases: FramePhase::ALL.iter().map(|phase| (*phase, last.phase(*phase))).collect(),
+            node_count: last.node_count,
+            tags_created: last.tags_created,
+            display_rectangles: last.display_rectangles,
+        }
+    }
+
+    /// Returns the total time of the last frame
+    pub fn last_frame_time(&self) -> Duration {
+        self.frame_times.last().cloned().unwrap_or_default()
+    }
+}
+
+fn frames_per_second(history: &FrameStatsHistory) -> f32 {
+
+    let mut elapsed = Duration::from_secs(0);
+    let mut frames = 0;
+
+    for stats in history.iter().rev() {
+        // The first frame of the window has no previous frame
+        if stats.since_last_frame == Duration::from_secs(0) {
+            break;
+        }
+        elapsed += stats.since_last_frame;
+        frames += 1;
+        if elapsed >= FPS_WINDOW {
+            break;
+        }
+    }
+
+    if frames == 0 {
+        0.0
+    } else {
+        frames as f32 * 1000.0 / as_millis(elapsed)
+    }
+}
+
+fn as_millis(duration: Duration) -> f32 {
+    duration.as_secs() as f32 * 1000.0 + duration.subsec_nanos() as f32 / 1_000_000.0
+}
+
+/// Returns the length of a bar for the `duration`, if `max_length` corresponds to `GRAPH_SCALE` frame budgets
+fn bar_length(duration: Duration, max_length: f32) -> f32 {
+    (as_millis(duration) / (as_millis(FRAME_BUDGET) * GRAPH_SCALE)).min(1.0) * max_length
+}
+
+fn phase_name(phase: FramePhase) -> &'static str {
+    match phase {
+        FramePhase::Layout => "Layout",
+        FramePhase::IntoUiState => "UI state",
+        FramePhase::Styling => "Style",
+        FramePhase::LayoutSolving => "Solve layout",
+        FramePhase::DisplayListBuild => "Display list",
+        FramePhase::GlCallbacks => "GL callbacks",
+        FramePhase::Render => "GPU / render",
+    }
+}
+
+/// Rows of text: the FPS row, one row per phase and the counter row
+fn row_count() -> usize {
+    FramePhase::ALL.len() + 2
+}
+
+/// The rectangle of the HUD: a fixed-size box in the top right corner of the window
+pub(crate) fn frame_hud_rect(window_size: LogicalSize) -> LayoutRect {
+    let height = 2.0 * HUD_PADDING + GRAPH_HEIGHT + row_count() as f32 * ROW_HEIGHT;
+    LayoutRect::new(
+        LayoutPoint::new((window_size.width as f32 - HUD_WIDTH - HUD_MARGIN).max(0.0), HUD_MARGIN),
+        LayoutSize::new(HUD_WIDTH, height),
+    )
+}
+
+/// Builds the DOM of the HUD: the text rows, with an empty row for the frame
+/// time graph below the FPS row. The graph and the bars are drawn by `frame_hud_rects()`.
+pub(crate) fn frame_hud_dom<T: Layout>(data: &FrameHudData) -> Dom<T> {
+
+    let mut hud = Dom::div().with_class("azul-frame-hud")
+        .with_child(Dom::label(format!("{:.1} FPS   {:.2} ms", data.fps, as_millis(data.last_frame_time())))
+            .with_class("azul-frame-hud-row"))
+        .with_child(Dom::div().with_class("azul-frame-hud-graph"));
+
+    for (phase, duration) in &data.phases {
+        hud.add_child(Dom::label(format!("{} {:.2} ms", phase_name(*phase), as_millis(*duration)))
+            .with_class("azul-frame-hud-row"));
+    }
+
+    hud.add_child(Dom::label(format!("{} nodes  {} tags  {} rects", data.node_count, data.tags_created, data.display_rectangles))
+        .with_class("azul-frame-hud-row"));
+
+    hud
+}
+
+/// The frame time graph and the phase bars of the HUD, inside of the `hud_rect`
+pub(crate) fn frame_hud_rects(data: &FrameHudData, hud_rect: LayoutRect) -> Vec<DebugRect> {
+
+    let mut rects = Vec::new();
+
+    let content_x = hud_rect.origin.x + HUD_PADDING;
+    let content_width = hud_rect.size.width - 2.0 * HUD_PADDING;
+
+    // Frame time graph, one bar per frame, growing from the bottom of the graph
+    let graph = LayoutRect::new(
+        LayoutPoint::new(content_x, hud_rect.origin.y + HUD_PADDING + ROW_HEIGHT),
+        LayoutSize::new(content_width, GRAPH_HEIGHT),
+    );
+    rects.push(DebugRect { rect: graph, color: GRAPH_BACKGROUND_COLOR, outline: false });
+
+    let frame_width = content_width / GRAPH_FRAMES as f32;
+    for (i, frame_time) in data.frame_times.iter().enumerate() {
+        let height = bar_length(*frame_time, GRAPH_HEIGHT);
+        let color = if *frame_time > FRAME_BUDGET { OVER_BUDGET_COLOR } else { WITHIN_BUDGET_COLOR };
+        rects.push(DebugRect {
+            rect: LayoutRect::new(
+                LayoutPoint::new(graph.origin.x + i as f32 * frame_width, graph.max_y() - height),
+                LayoutSize::new(frame_width.max(1.0), height),
+            ),
+            color,
+            outline: false,
+        });
+    }
+
+    let budget_y = graph.max_y() - GRAPH_HEIGHT / GRAPH_SCALE;
+    rects.push(DebugRect {
+        rect: LayoutRect::new(LayoutPoint::new(graph.origin.x, budget_y), LayoutSize::new(content_width, 1.0)),
+        color: BUDGET_LINE_COLOR,
+        outline: false,
+    });
+
+    // One bar per phase, to the right of the label of the phase
+    let bar_max_width = content_width - LABEL_WIDTH;
+    for (i, (_, duration)) in data.phases.iter().enumerate() {
+        let row_y = graph.max_y() + i as f32 * ROW_HEIGHT;
+        rects.push(DebugRect {
+            rect: LayoutRect::new(
+                LayoutPoint::new(content_x + LABEL_WIDTH, row_y + 3.0),
+                LayoutSize::new(bar_length(*duration, bar_max_width), ROW_HEIGHT - 6.0),
+            ),
+            color: PHASE_BAR_COLOR,
+            outline: false,
+        });
+    }
+
+    rects
+}
+
+/// Stylesheet of the HUD, built in code like the stylesheet of the error overlay
+pub(crate) fn frame_hud_css() -> Css {
+
+    fn class_rule(class: &str, properties: Vec<CssProperty>) -> CssRuleBlock {
+        CssRuleBlock {
+            path: CssPath { selectors: vec![CssPathSelector::Class(class.into())] },
+            declarations: properties.into_iter().map(CssDeclaration::Static).collect(),
+        }
+    }
+
+    let padding = Some(PixelValue::px(HUD_PADDING));
+
+    Css::from(vec![
+        class_rule("azul-frame-hud", vec![
+            CssProperty::BackgroundColor(StyleBackgroundColor(ColorU { r: 20, g: 20, b: 20, a: 210 })),
+            CssProperty::Padding(LayoutPadding { top: padding, bottom: padding, left: padding, right: padding }),
+        ]),
+        class_rule("azul-frame-hud-row", vec![
+            CssProperty::Height(LayoutHeight::px(ROW_HEIGHT)),
+            CssProperty::TextColor(StyleTextColor(ColorU { r: 255, g: 255, b: 255, a: 255 })),
+            CssProperty::FontSize(StyleFontSize::px(11.0)),
+        ]),
+        class_rule("azul-frame-hud-graph", vec![
+            CssProperty::Height(LayoutHeight::px(GRAPH_HEIGHT)),
+        ]),
+    ])
+}
+
+#[cfg(test)]
+fn fake_stats(total_ms: u64, since_last_frame_ms: u64) -> ::profiling::FrameStats {
+    ::profiling::FrameStats {
+        total: Duration::from_millis(total_ms),
+        since_last_frame: Duration::from_millis(since_last_frame_ms),
+        .. Default::default()
+    }
+}
+
+#[test]
+fn test_frame_hud_reflects_injected_stats() {
+
+    let last = fake_stats(12, 20)
+        .with_phase(FramePhase::Layout, Duration::from_millis(2))
+        .with_phase(FramePhase::Styling, Duration::from_millis(3))
+        .with_phase(FramePhase::DisplayListBuild, Duration::from_millis(4))
+        .with_phase(FramePhase::Render, Duration::from_millis(40));
+    let last = ::profiling::FrameStats { node_count: 120, tags_created: 7, display_rectangles: 300, .. last };
+
+    // The first frame has no previous frame and doesn't count towards the FPS
+    let history = vec![fake_stats(30, 0), fake_stats(8, 20), fake_stats(10, 20), last]
+        .into_iter().collect::<FrameStatsHistory>();
+
+    let data = FrameHudData::from_history(&history);
+
+    assert_eq!(data.fps, 50.0);
+    assert_eq!(data.frame_times, vec![
+        Duration::from_millis(30), Duration::from_millis(8), Duration::from_millis(10), Duration::from_millis(12),
+    ]);
+    assert_eq!(data.last_frame_time(), Duration::from_millis(12));
+    assert_eq!(data.phases.len(), FramePhase::ALL.len());
+    assert_eq!(data.phases[0], (FramePhase::Layout, Duration::from_millis(2)));
+    assert_eq!(data.phases[2], (FramePhase::Styling, Duration::from_millis(3)));
+    assert_eq!(data.phases[6], (FramePhase::Render, Duration::from_millis(40)));
+    assert_eq!((data.node_count, data.tags_created, data.display_rectangles), (120, 7, 300));
+
+    // Graph background, one bar per frame, budget line, one bar per phase
+    let hud_rect = frame_hud_rect(LogicalSize::new(800.0, 600.0));
+    let rects = frame_hud_rects(&data, hud_rect);
+    assert_eq!(rects.len(), 1 + 4 + 1 + FramePhase::ALL.len());
+    assert!(rects.iter().all(|r| hud_rect.contains_rect(&r.rect)));
+
+    // Frames over the budget are red
+    assert_eq!(rects[1].color, OVER_BUDGET_COLOR);
+    assert_eq!(rects[2].color, WITHIN_BUDGET_COLOR);
+
+    // The phase bars are proportional to the phase times, capped at twice the budget
+    let layout_bar = rects[6].rect.size.width;
+    let styling_bar = rects[8].rect.size.width;
+    let render_bar = rects[12].rect.size.width;
+    assert!((styling_bar / layout_bar - 1.5).abs() < 0.01);
+    assert_eq!(render_bar, HUD_WIDTH - 2.0 * HUD_PADDING - LABEL_WIDTH);
+    assert_eq!(rects[7].rect.size.width, 0.0);
+}
+
+#[test]
+fn test_frame_hud_without_frames_is_empty() {
+    let data = FrameHudData::from_history(&FrameStatsHistory::default());
+    assert_eq!(data.fps, 0.0);
+    assert!(data.frame_times.is_empty());
+    assert!(data.phases.iter().all(|(_, duration)| *duration == Duration::from_secs(0)));
+}
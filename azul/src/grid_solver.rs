@@ -0,0 +1,345 @@
+//! Item placement and track sizing for grid containers (nodes with a
+//! `grid-template-columns` or `grid-template-rows` attribute).
+//!
+//! The functions in this module only operate on the track definitions and the sizes of
+//! the grid items, the `ui_solver` takes care of feeding them with the actual nodes.
+
+use azul_css::{GridTrackSize, GridPlacement};
+
+/// Cells that a grid item occupies (indices of the tracks, starting at 0)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct GridArea {
+    pub column: usize,
+    pub column_span: usize,
+    pub row: usize,
+    pub row_span: usize,
+}
+
+/// The size a grid item needs along one axis (including its margin), plus
+/// the tracks the item spans along that axis
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct GridItemContribution {
+    /// Index of the first track the item occupies
+    pub start: usize,
+    /// Number of tracks the item occupies
+    pub span: usize,
+    pub min_content: f32,
+    pub max_content: f32,
+}
+
+/// Places the grid items, given as `(grid-column, grid-row)` in DOM order, into the grid
+///
+/// Items with both a column and a row line are placed first. All other items are placed
+/// in DOM order: Items with only a column line go into the first row where they fit,
+/// items with only a row line into the first column where they fit, the remaining items
+/// flow row by row into the next free cells. The number of columns is fixed (the explicit
+/// columns or the largest column line of any item), rows are added as needed.
+pub(crate) fn place_grid_items(explicit_columns: usize, items: &[(Option<GridPlacement>, Option<GridPlacement>)]) -> Vec<GridArea> {
+
+    let column_count = items.iter()
+        .filter_map(|(column, _)| column.and_then(|c| c.start.and_then(|start| Some(start - 1 + c.span))))
+        .max()
+        .unwrap_or(0)
+        .max(explicit_columns)
+        .max(1);
+
+    // occupied[row][column]
+    let mut occupied = Vec::<Vec<bool>>::new();
+
+    fn fits(occupied: &[Vec<bool>], area: &GridArea) -> bool {
+        (area.row..area.row + area.row_span).all(|row| {
+            (area.column..area.column + area.column_span).all(|column| {
+                !occupied.get(row).and_then(|r| r.get(column)).cloned().unwrap_or(false)
+            })
+        })
+    }
+
+    fn occupy(occupied: &mut Vec<Vec<bool>>, column_count: usize, area: &GridArea) {
+        while occupied.len() < area.row + area.row_span {
+            occupied.push(vec![false; column_count]);
+        }
+        for row in area.row..area.row + area.row_span {
+            for column in area.column..(area.column + area.column_span).min(column_count) {
+                occupied[row][column] = true;
+            }
+        }
+    }
+
+    let mut areas = vec![None; items.len()];
+
+    // 1. Items with a fixed position
+    for (item_idx, (column, row)) in items.iter().enumerate() {
+        if let (Some(column), Some(row)) = (column, row) {
+            if let (Some(column_start), Some(row_start)) = (column.start, row.start) {
+                let area = GridArea {
+                    column: column_start - 1,
+                    column_span: column.span,
+                    row: row_start - 1,
+                    row_span: row.span,
+                };
+                occupy(&mut occupied, column_count, &area);
+                areas[item_idx] = Some(area);
+            }
+        }
+    }
+
+    // 2. Auto-placed items, in DOM order
+    let (mut cursor_row, mut cursor_column) = (0, 0);
+
+    for (item_idx, (column, row)) in items.iter().enumerate() {
+
+        if areas[item_idx].is_some() {
+            continue;
+        }
+
+        let column_span = column.map(|c| c.span).unwrap_or(1).min(column_count);
+        let row_span = row.map(|r| r.span).unwrap_or(1);
+        let column_start = column.and_then(|c| c.start);
+        let row_start = row.and_then(|r| r.start);
+
+        let area = match (column_start, row_start) {
+            (Some(column_start), _) => {
+                // Fixed column: first row where the item fits
+                (0..).map(|row| GridArea { column: column_start - 1, column_span, row, row_span })
+                    .find(|area| fits(&occupied, area))
+                    .unwrap()
+            },
+            (None, Some(row_start)) => {
+                // Fixed row: first column where the item fits, overlaps if the row is full
+                let area = |column| GridArea { column, column_span, row: row_start - 1, row_span };
+                (0..=(column_count - column_span)).map(area)
+                    .find(|area| fits(&occupied, area))
+                    .unwrap_or(area(0))
+            },
+            (None, None) => {
+                // Next free cell after the previous auto-placed item
+                let area = (cursor_row..).flat_map(|row| {
+                    let first_column = if row == cursor_row { cursor_column } else { 0 };
+                    (first_column..=(column_count - column_span)).map(move |column| GridArea { column, column_span, row, row_span })
+                }).find(|area| fits(&occupied, area)).unwrap();
+                cursor_row = area.row;
+                cursor_column = area.column + area.column_span;
+                area
+            },
+        };
+
+        occupy(&mut occupied, column_count, &area);
+        areas[item_idx] = Some(area);
+    }
+
+    areas.into_iter().map(|area| area.unwrap()).collect()
+}
+
+/// Resolves the sizes of the tracks (columns or rows) of a grid:
+///
+/// 1. Fixed tracks (`100px`) are set to their size
+/// 2. Content-sized tracks (`auto`, `min-content`, `max-content`) grow to fit the items that
+///    span only that track, then grow evenly to fit items that span multiple tracks (items
+///    spanning a `fr` track don't affect the content-sized tracks)
+/// 3. The space that is left over (`available_space` minus the tracks and gaps) is
+///    distributed to the `fr` tracks, proportional to their fraction. If there are
+///    no `fr` tracks, the `auto` tracks are stretched evenly instead.
+///
+/// Items can span more tracks than defined in `tracks`, the additional tracks are `auto` tracks.
+/// `fr` tracks don't grow to fit their content, so they can be smaller than their items.
+pub(crate) fn solve_grid_tracks(tracks: &[GridTrackSize], items: &[GridItemContribution], available_space: f32, gap: f32) -> Vec<f32> {
+
+    use azul_css::GridTrackSize::*;
+
+    let track_count = items.iter().map(|item| item.start + item.span).max().unwrap_or(0).max(tracks.len());
+    let track = |track_idx: usize| tracks.get(track_idx).cloned().unwrap_or(Auto);
+    let is_fraction = |track_idx: usize| match track(track_idx) { Fraction(_) => true, _ => false };
+    let is_content_sized = |track_idx: usize| match track(track_idx) { Auto | MinContent | MaxContent => true, _ => false };
+
+    let mut track_sizes = vec![0.0; track_count];
+
+    // 1. fixed tracks
+    for (track_idx, track_size) in track_sizes.iter_mut().enumerate() {
+        if let Fixed(px) = track(track_idx) {
+            *track_size = px.to_pixels();
+        }
+    }
+
+    // 2. content-sized tracks, items spanning a single track
+    for item in items.iter().filter(|item| item.span == 1) {
+        let contribution = match track(item.start) {
+            MinContent => item.min_content,
+            Auto | MaxContent => item.max_content,
+            Fixed(_) | Fraction(_) => continue,
+        };
+        track_sizes[item.start] = track_sizes[item.start].max(contribution);
+    }
+
+    // content-sized tracks, items spanning multiple tracks
+    for item in items.iter().filter(|item| item.span > 1) {
+
+        let spanned_tracks = item.start..(item.start + item.span);
+
+        if spanned_tracks.clone().any(&is_fraction) {
+            continue;
+        }
+
+        let content_sized_tracks = spanned_tracks.clone().filter(|t| is_content_sized(*t)).collect::<Vec<usize>>();
+        if content_sized_tracks.is_empty() {
+            continue;
+        }
+
+        let contribution = if content_sized_tracks.iter().all(|t| track(*t) == MinContent) {
+            item.min_content
+        } else {
+            item.max_content
+        };
+
+        let spanned_size = spanned_tracks.map(|t| track_sizes[t]).sum::<f32>() + gap * (item.span - 1) as f32;
+        let missing_space = contribution - spanned_size;
+
+        if missing_space > 0.0 {
+            for track_idx in &content_sized_tracks {
+                track_sizes[*track_idx] += missing_space / content_sized_tracks.len() as f32;
+            }
+        }
+    }
+
+    // 3. distribute the left over space
+    let used_space = track_sizes.iter().sum::<f32>() + gap * track_count.saturating_sub(1) as f32;
+    let free_space = (available_space - used_space).max(0.0);
+
+    let sum_of_fractions: f32 = (0..track_count).filter_map(|t| match track(t) { Fraction(fr) => Some(fr.get()), _ => None }).sum();
+
+    if sum_of_fractions > 0.0 {
+        // If the fractions add up to less than 1fr, they don't fill the whole free space
+        let space_per_fraction = free_space / sum_of_fractions.max(1.0);
+        for (track_idx, track_size) in track_sizes.iter_mut().enumerate() {
+            if let Fraction(fr) = track(track_idx) {
+                *track_size = fr.get() * space_per_fraction;
+            }
+        }
+    } else {
+        let auto_tracks = (0..track_count).filter(|t| track(*t) == Auto).collect::<Vec<usize>>();
+        for track_idx in &auto_tracks {
+            track_sizes[*track_idx] += free_space / auto_tracks.len() as f32;
+        }
+    }
+
+    track_sizes
+}
+
+/// Returns the start offset of each track, relative to the start of the grid
+pub(crate) fn get_track_offsets(track_sizes: &[f32], gap: f32) -> Vec<f32> {
+    let mut offset = 0.0;
+    track_sizes.iter().map(|track_size| {
+        let track_offset = offset;
+        offset += track_size + gap;
+        track_offset
+    }).collect()
+}
+
+/// Returns the size of an item spanning `span` tracks starting at `start`, including the gaps in between
+pub(crate) fn get_spanned_size(track_sizes: &[f32], gap: f32, start: usize, span: usize) -> f32 {
+    track_sizes[start..(start + span).min(track_sizes.len())].iter().sum::<f32>() + gap * span.saturating_sub(1) as f32
+}
+
+/// Returns the size of the whole grid if every track only takes up the space of its
+/// content (the `fr` tracks collapse to 0), used for the content size of the grid container
+pub(crate) fn get_grid_content_size(tracks: &[GridTrackSize], items: &[GridItemContribution], gap: f32) -> f32 {
+    let track_sizes = solve_grid_tracks(tracks, items, 0.0, gap);
+    track_sizes.iter().sum::<f32>() + gap * track_sizes.len().saturating_sub(1) as f32
+}
+
+#[cfg(test)]
+fn item(start: usize, span: usize, min_content: f32, max_content: f32) -> GridItemContribution {
+    GridItemContribution { start, span, min_content, max_content }
+}
+
+#[test]
+fn test_solve_grid_tracks_mixed() {
+    use azul_css::{PixelValue, FloatValue};
+
+    let tracks = [
+        GridTrackSize::Fixed(PixelValue::px(100.0)),
+        GridTrackSize::Auto,
+        GridTrackSize::Fraction(FloatValue::new(1.0)),
+        GridTrackSize::Fraction(FloatValue::new(2.0)),
+    ];
+
+    // 510px - 100px (fixed) - 50px (auto) - 3 * 10px (gaps) = 330px for 3fr
+    let items = [item(1, 1, 30.0, 50.0), item(2, 1, 400.0, 400.0)];
+    assert_eq!(solve_grid_tracks(&tracks, &items, 510.0, 10.0), vec![100.0, 50.0, 110.0, 220.0]);
+
+    // Not enough space: the fr tracks collapse, the other tracks keep their size
+    assert_eq!(solve_grid_tracks(&tracks, &items, 100.0, 10.0), vec![100.0, 50.0, 0.0, 0.0]);
+}
+
+#[test]
+fn test_solve_grid_tracks_content_sized() {
+    use azul_css::{PixelValue, FloatValue};
+
+    // Without fr tracks, the auto tracks are stretched evenly
+    let tracks = [GridTrackSize::Fixed(PixelValue::px(100.0)), GridTrackSize::Auto, GridTrackSize::Auto];
+    let items = [item(1, 1, 10.0, 40.0), item(2, 1, 10.0, 60.0)];
+    assert_eq!(solve_grid_tracks(&tracks, &items, 300.0, 0.0), vec![100.0, 90.0, 110.0]);
+
+    // min-content and max-content tracks don't stretch
+    let tracks = [GridTrackSize::MinContent, GridTrackSize::MaxContent];
+    let items = [item(0, 1, 30.0, 80.0), item(1, 1, 30.0, 80.0)];
+    assert_eq!(solve_grid_tracks(&tracks, &items, 500.0, 0.0), vec![30.0, 80.0]);
+
+    // Items spanning two auto tracks grow both tracks evenly
+    let tracks = [GridTrackSize::Auto, GridTrackSize::Auto];
+    assert_eq!(solve_grid_tracks(&tracks, &[item(0, 2, 100.0, 100.0)], 0.0, 10.0), vec![45.0, 45.0]);
+
+    // ... unless they span a fr track
+    let tracks = [GridTrackSize::Auto, GridTrackSize::Fraction(FloatValue::new(1.0))];
+    assert_eq!(solve_grid_tracks(&tracks, &[item(0, 2, 100.0, 100.0)], 0.0, 10.0), vec![0.0, 0.0]);
+
+    // Implicit tracks are auto tracks
+    let tracks = [GridTrackSize::Fixed(PixelValue::px(100.0))];
+    assert_eq!(solve_grid_tracks(&tracks, &[item(1, 1, 20.0, 40.0)], 0.0, 0.0), vec![100.0, 40.0]);
+
+    // Less than 1fr in total only takes up a part of the free space
+    let tracks = [GridTrackSize::Fraction(FloatValue::new(0.5))];
+    assert_eq!(solve_grid_tracks(&tracks, &[], 200.0, 0.0), vec![100.0]);
+}
+
+#[test]
+fn test_track_offsets() {
+    let track_sizes = [100.0, 50.0, 110.0];
+    assert_eq!(get_track_offsets(&track_sizes, 10.0), vec![0.0, 110.0, 170.0]);
+    assert_eq!(get_spanned_size(&track_sizes, 10.0, 1, 2), 170.0);
+    assert_eq!(get_spanned_size(&track_sizes, 10.0, 0, 1), 100.0);
+}
+
+#[test]
+fn test_place_grid_items() {
+
+    let area = |column, column_span, row, row_span| GridArea { column, column_span, row, row_span };
+    let placement = |start, span| Some(GridPlacement { start, span });
+
+    // Auto-placement: row by row
+    let items = vec![(None, None); 5];
+    assert_eq!(place_grid_items(3, &items), vec![
+        area(0, 1, 0, 1), area(1, 1, 0, 1), area(2, 1, 0, 1),
+        area(0, 1, 1, 1), area(1, 1, 1, 1),
+    ]);
+
+    // Spanning items that don't fit into the current row go into the next row
+    let items = vec![(None, None), (None, None), (placement(None, 2), None), (None, None)];
+    assert_eq!(place_grid_items(3, &items), vec![
+        area(0, 1, 0, 1), area(1, 1, 0, 1),
+        area(0, 2, 1, 1), area(2, 1, 1, 1),
+    ]);
+
+    // Items with a fixed position are placed first, the other items flow around them
+    let items = vec![(None, None), (placement(Some(1), 1), placement(Some(1), 2)), (None, None), (None, None)];
+    assert_eq!(place_grid_items(2, &items), vec![
+        area(1, 1, 0, 1), area(0, 1, 0, 2), area(1, 1, 1, 1), area(0, 1, 2, 1),
+    ]);
+
+    // Only the column is fixed: first row where the column is free
+    let items = vec![(None, None), (placement(Some(1), 1), None)];
+    assert_eq!(place_grid_items(2, &items), vec![area(0, 1, 0, 1), area(0, 1, 1, 1)]);
+
+    // Column lines beyond the explicit columns add implicit columns
+    let items = vec![(placement(Some(3), 1), None), (None, None)];
+    assert_eq!(place_grid_items(1, &items), vec![area(2, 1, 0, 1), area(0, 1, 0, 1)]);
+}
@@ -0,0 +1,238 @@
+//! Hit testing outside of the event dispatch, see `WindowState::hit_test()`.
+//!
+//! While building the display list, every rectangle that is pushed with a hit-testing
+//! tag is also recorded as a `HitRegion` (in the order in which it is drawn), in the same
+//! function that hands the tag over to webrender. So the regions contain exactly the
+//! tags that the event loop can hit and they are stacked in the same z-order.
+//!
+//! Nodes without a tag are invisible to the hit test. A node gets a tag if it has a
+//! hover, focus or `Not` callback (window and desktop callbacks don't need one), a
+//! `:hover` / `:active` style, if it is draggable, focusable (`tab_index`) or if its
+//! children overflow it (scroll frames).
+//! The content of iframes is not recorded, since their nodes don't belong to the DOM of
+//! the window.
+//!
+//! Each region also stores the clips of its parents (`overflow: hidden` or `border-radius`),
+//! so that a point in the clipped-away part of a child (for example outside of the rounded
+//! corner of a parent) doesn't hit the child. Transformed nodes are hit-tested by mapping
+//! the point back through the inverse of their `transform` (and the ones of their parents).
+//!
+//! Parents with `overflow: scroll` or `overflow: auto` are scroll frames: their children
+//! are clipped like by `overflow: hidden`, but moved by the current scroll offset of the
+//! frame. Since scrolling doesn't rebuild the display list, the regions only store which
+//! scroll frames they are in and the offsets are looked up when hit-testing, so that
+//! content that was scrolled out of view isn't hit, but content that was scrolled into
+//! view is.
+
+use webrender::api::{LayoutRect, LayoutPoint, LayoutSize, ComplexClipRegion, ExternalScrollId};
+use glium::glutin::dpi::LogicalPosition;
+use {
+    FastHashMap,
+    dom::TagId,
+    id_tree::NodeId,
+    transform::{LayoutTransform2D, untransform_point},
+};
+
+/// A tagged rectangle of the last frame
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct HitRegion {
+    pub(crate) node_id: NodeId,
+    pub(crate) tag_id: TagId,
+    pub(crate) rect: LayoutRect,
+    /// Clips of the parents of the node, outermost first - the point
+    /// has to be inside of all of them in order to hit the node
+    pub(crate) clips: Vec<HitClip>,
+    /// Transforms and scroll frames of the node and its parents, outermost first (empty if untransformed)
+    pub(crate) transforms: Vec<HitTransform>,
+}
+
+/// Clip of a parent of a `HitRegion`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct HitClip {
+    pub(crate) region: ComplexClipRegion,
+    /// Transforms and scroll frames of the parent that clips, outermost first
+    /// (the clip is transformed and scrolled together with the parent)
+    pub(crate) transforms: Vec<HitTransform>,
+}
+
+/// Moves a `HitRegion` or a `HitClip` away from its laid-out position
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum HitTransform {
+    /// A `transform` (in window coordinates)
+    Transform(LayoutTransform2D),
+    /// The content of the scroll frame is moved by the current scroll offset of the frame
+    Scroll(ExternalScrollId),
+}
+
+/// A node under the point of a `WindowState::hit_test()`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HitTestItem {
+    /// The node that was hit
+    pub node_id: NodeId,
+    /// The hit-testing tag of the node
+    pub tag_id: TagId,
+    /// Position in the stack of nodes under the point: 0 for the topmost node,
+    /// 1 for the node directly below it, etc.
+    pub depth: usize,
+    /// Solved rectangle of the node, in logical pixels relative to the window
+    pub rect: LayoutRect,
+    /// The point, relative to the top left corner of the `rect` - if the node is
+    /// transformed, in the untransformed coordinates of the node
+    pub point_relative_to_item: LayoutPoint,
+}
+
+/// Returns all regions that contain the `point`, topmost first
+/// (the `regions` are stored in drawing order, so the last region is on top)
+///
+/// `scroll_offsets` are the current offsets of the scroll frames, missing frames aren't scrolled
+pub(crate) fn hit_test_regions(
+    regions: &[HitRegion],
+    scroll_offsets: &FastHashMap<ExternalScrollId, LayoutPoint>,
+    point: LogicalPosition)
+-> Vec<HitTestItem>
+{
+    let point = LayoutPoint::new(point.x as f32, point.y as f32);
+    regions.iter().rev()
+        .filter(|region| region.clips.iter().all(|clip| {
+            local_point(&clip.transforms, scroll_offsets, &point).map(|p| clip_region_contains(&clip.region, &p)).unwrap_or(false)
+        }))
+        .filter_map(|region| {
+            let point = local_point(&region.transforms, scroll_offsets, &point)?;
+            if region.rect.contains(&point) { Some((region, point)) } else { None }
+        })
+        .enumerate()
+        .map(|(depth, (region, point))| HitTestItem {
+            node_id: region.node_id,
+            tag_id: region.tag_id,
+            depth,
+            rect: region.rect,
+            point_relative_to_item: LayoutPoint::new(point.x - region.rect.origin.x, point.y - region.rect.origin.y),
+        })
+        .collect()
+}
+
+/// Maps the point in window coordinates to the untransformed, unscrolled coordinates of a node
+fn local_point(
+    transforms: &[HitTransform],
+    scroll_offsets: &FastHashMap<ExternalScrollId, LayoutPoint>,
+    point: &LayoutPoint)
+-> Option<LayoutPoint>
+{
+    // The outermost transform is applied last, so it has to be undone first
+    transforms.iter().fold(Some(*point), |point, transform| {
+        let point = point?;
+        match transform {
+            HitTransform::Transform(transform) => untransform_point(transform, &point),
+            HitTransform::Scroll(scroll_id) => {
+                let offset = scroll_offsets.get(scroll_id).cloned().unwrap_or(LayoutPoint::zero());
+                Some(LayoutPoint::new(point.x + offset.x, point.y + offset.y))
+            },
+        }
+    })
+}
+
+/// Returns whether the `point` is inside of the rounded rectangle of the `clip`
+fn clip_region_contains(clip: &ComplexClipRegion, point: &LayoutPoint) -> bool {
+
+    if !clip.rect.contains(point) {
+        return false;
+    }
+
+    let rect = &clip.rect;
+    let (left, top) = (rect.origin.x, rect.origin.y);
+    let (right, bottom) = (rect.origin.x + rect.size.width, rect.origin.y + rect.size.height);
+
+    // (center of the corner ellipse, radii of the ellipse, direction of the corner)
+    let corners = [
+        (LayoutPoint::new(left + clip.radii.top_left.width, top + clip.radii.top_left.height), clip.radii.top_left, (-1.0, -1.0)),
+        (LayoutPoint::new(right - clip.radii.top_right.width, top + clip.radii.top_right.height), clip.radii.top_right, (1.0, -1.0)),
+        (LayoutPoint::new(left + clip.radii.bottom_left.width, bottom - clip.radii.bottom_left.height), clip.radii.bottom_left, (-1.0, 1.0)),
+        (LayoutPoint::new(right - clip.radii.bottom_right.width, bottom - clip.radii.bottom_right.height), clip.radii.bottom_right, (1.0, 1.0)),
+    ];
+
+    corners.iter().all(|&(center, radii, (direction_x, direction_y))| {
+        let dx = point.x - center.x;
+        let dy = point.y - center.y;
+        // Only the area between the corner of the rectangle and the center of the ellipse is rounded
+        if radii.width <= 0.0 || radii.height <= 0.0 || dx * direction_x <= 0.0 || dy * direction_y <= 0.0 {
+            return true;
+        }
+        let dx = dx / radii.width;
+        let dy = dy / radii.height;
+        dx * dx + dy * dy <= 1.0
+    })
+}
+
+#[test]
+fn test_hit_test_regions_topmost_first() {
+    let rect = |x: f32, y: f32, w: f32, h: f32| LayoutRect::new(LayoutPoint::new(x, y), LayoutSize::new(w, h));
+    let regions = [
+        HitRegion { node_id: NodeId::new(0), tag_id: 1, rect: rect(0.0, 0.0, 100.0, 100.0), clips: Vec::new(), transforms: Vec::new() },
+        HitRegion { node_id: NodeId::new(1), tag_id: 2, rect: rect(10.0, 10.0, 50.0, 50.0), clips: Vec::new(), transforms: Vec::new() },
+        HitRegion { node_id: NodeId::new(2), tag_id: 3, rect: rect(70.0, 70.0, 20.0, 20.0), clips: Vec::new(), transforms: Vec::new() },
+    ];
+
+    let hit = hit_test_regions(&regions, &FastHashMap::default(), LogicalPosition::new(20.0, 30.0));
+    assert_eq!(hit.iter().map(|item| (item.node_id, item.depth)).collect::<Vec<_>>(), vec![
+        (NodeId::new(1), 0),
+        (NodeId::new(0), 1),
+    ]);
+    assert_eq!(hit[0].point_relative_to_item, LayoutPoint::new(10.0, 20.0));
+
+    assert!(hit_test_regions(&regions, &FastHashMap::default(), LogicalPosition::new(200.0, 0.0)).is_empty());
+}
+
+#[test]
+fn test_hit_test_regions_rounded_parent_clip() {
+    use webrender::api::{BorderRadius, ClipMode};
+
+    let rect = |x: f32, y: f32, w: f32, h: f32| LayoutRect::new(LayoutPoint::new(x, y), LayoutSize::new(w, h));
+
+    // A child that fills its parent, the parent has a border-radius of 20px
+    let parent_clip = ComplexClipRegion::new(rect(0.0, 0.0, 100.0, 100.0), BorderRadius::uniform(20.0), ClipMode::Clip);
+    let regions = [
+        HitRegion { node_id: NodeId::new(0), tag_id: 1, rect: rect(0.0, 0.0, 100.0, 100.0), clips: Vec::new(), transforms: Vec::new() },
+        HitRegion { node_id: NodeId::new(1), tag_id: 2, rect: rect(0.0, 0.0, 100.0, 100.0), clips: vec![HitClip { region: parent_clip, transforms: Vec::new() }], transforms: Vec::new() },
+    ];
+
+    let hit_nodes = |x, y| hit_test_regions(&regions, &FastHashMap::default(), LogicalPosition::new(x, y))
+        .into_iter().map(|item| item.node_id).collect::<Vec<_>>();
+
+    // Inside of the rounded rectangle
+    assert_eq!(hit_nodes(50.0, 50.0), vec![NodeId::new(1), NodeId::new(0)]);
+    assert_eq!(hit_nodes(10.0, 50.0), vec![NodeId::new(1), NodeId::new(0)]);
+    assert_eq!(hit_nodes(10.0, 10.0), vec![NodeId::new(1), NodeId::new(0)]);
+    // In the clipped-away corners, only the (unclipped) parent is hit
+    assert_eq!(hit_nodes(2.0, 2.0), vec![NodeId::new(0)]);
+    assert_eq!(hit_nodes(98.0, 2.0), vec![NodeId::new(0)]);
+    assert_eq!(hit_nodes(2.0, 98.0), vec![NodeId::new(0)]);
+    assert_eq!(hit_nodes(97.0, 97.0), vec![NodeId::new(0)]);
+}
+
+#[test]
+fn test_hit_test_regions_rotated_button() {
+    use azul_css::{StyleTransform, StyleTransformFunction, StyleTransformOrigin, FloatValue};
+    use transform::get_node_transform;
+
+    // A 100x20 button at (100, 100), rotated by 90 degrees around its center (150, 110),
+    // so that it visually covers the area from (140, 60) to (160, 160)
+    let button_rect = LayoutRect::new(LayoutPoint::new(100.0, 100.0), LayoutSize::new(100.0, 20.0));
+    let transform = get_node_transform(
+        &StyleTransform(vec![StyleTransformFunction::Rotate(FloatValue::new(90.0))]),
+        &StyleTransformOrigin::default(),
+        &button_rect,
+    );
+    let regions = [
+        HitRegion { node_id: NodeId::new(0), tag_id: 1, rect: button_rect, clips: Vec::new(), transforms: vec![HitTransform::Transform(transform)] },
+    ];
+
+    // Inside of the untransformed rectangle, but outside of the rotated button
+    assert!(hit_test_regions(&regions, &FastHashMap::default(), LogicalPosition::new(110.0, 110.0)).is_empty());
+
+    // Outside of the untransformed rectangle, but inside of the rotated button:
+    // the top end of the rotated button is the left end of the untransformed one
+    let hit = hit_test_regions(&regions, &FastHashMap::default(), LogicalPosition::new(155.0, 62.0));
+    assert_eq!(hit.len(), 1);
+    let relative = hit[0].point_relative_to_item;
+    assert!((relative.x - 2.0).abs() < 0.001 && (relative.y - 5.0).abs() < 0.001, "{:?}", relative);
+}
@@ -0,0 +1,634 @@
+//! Exporting the last frame of a window as a static HTML document,
+//! see `WindowState::export_frame_html()`.
+//!
+//! Every node of the DOM becomes one absolutely positioned element (`<div>` for
+//! containers, `<p>` for text, `<img>` for images) with the solved rectangle of the
+//! node and the computed colors, borders and fonts as inline styles. The elements are
+//! nested like the DOM, so the document can be inspected with the developer tools
+//! of a browser. Images are embedded as base64-encoded PNGs, so the exported file
+//! is self-contained.
+//!
+//! The export approximates the frame: the positions and sizes are exact, but the
+//! browser breaks the lines of text with its own fonts. The content of `GlTexture`
+//! and `IFrame` nodes is drawn by callbacks that can't be re-run outside of the
+//! render loop, so these nodes are exported as outlined placeholders.
+
+use std::{
+    fmt::Write as FmtWrite,
+    fs,
+    io::Error as IoError,
+    path::Path,
+    sync::Arc,
+};
+use glium::glutin::dpi::LogicalSize;
+use webrender::api::{LayoutRect, ImageData, ImageFormat as RawImageFormat};
+use azul_css::{RectStyle, ColorU, BorderStyle, FontId, StyleBorderSide, StyleTextAlignmentHorz};
+use {
+    FastHashMap,
+    app_resources::AppResources,
+    dom::{NodeData, NodeType, escape_html},
+    id_tree::{NodeId, NodeHierarchy, ArenaNodeData},
+    images::ImageId,
+    layout_result::LayoutResult,
+    text_cache::TextId,
+    traits::Layout,
+};
+
+/// Error returned by `WindowState::export_frame_html()`
+#[derive(Debug)]
+pub enum ExportError {
+    /// No frame has been drawn yet
+    NoFrame,
+    /// The HTML file could not be written
+    Io(IoError),
+}
+
+impl_display! {
+    ExportError,
+    {
+        NoFrame => "No frame has been drawn yet, there is nothing to export",
+        Io(e) => format!("Could not write the exported frame: {}", e),
+    }
+}
+
+impl_from!(IoError, ExportError::Io);
+
+/// Everything of one frame that is needed to write it as HTML, recorded while the
+/// frame is laid out (only with the `html-export` feature)
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ExportedFrame {
+    /// Size of the window, in logical pixels
+    pub(crate) window_size: LogicalSize,
+    /// Nodes in document order, indexed by the `NodeId` of the DOM
+    pub(crate) nodes: Vec<ExportedNode>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ExportedNode {
+    pub(crate) parent: Option<usize>,
+    pub(crate) children: Vec<usize>,
+    /// Solved rectangle, relative to the window
+    pub(crate) rect: LayoutRect,
+    pub(crate) style: RectStyle,
+    pub(crate) ids: Vec<String>,
+    pub(crate) classes: Vec<String>,
+    pub(crate) content: ExportedContent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ExportedContent {
+    Div,
+    Text(String),
+    /// `image` is `None` if the pixels of the image aren't available
+    Image { name: Option<String>, image: Option<ExportedImage> },
+    GlTexture,
+    IFrame,
+}
+
+/// Decoded pixels of an image, in the format they were uploaded to the GPU in
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ExportedImage {
+    pub(crate) pixels: Arc<Vec<u8>>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// Bytes per row, `None` if the rows are tightly packed
+    pub(crate) stride: Option<u32>,
+    pub(crate) format: RawImageFormat,
+}
+
+impl ExportedFrame {
+
+    /// Records the frame, `styles` are the computed styles of the nodes (in document order)
+    pub(crate) fn new<T: Layout>(
+        node_hierarchy: &NodeHierarchy,
+        node_data: &ArenaNodeData<NodeData<T>>,
+        layout: &LayoutResult,
+        styles: Vec<RectStyle>,
+        window_size: LogicalSize,
+        app_resources: &AppResources)
+    -> Self
+    {
+        let image_names = app_resources.css_ids_to_image_ids.iter()
+            .map(|(name, image_id)| (*image_id, name.clone()))
+            .collect::<FastHashMap<ImageId, String>>();
+
+        let images = app_resources.images.iter().filter_map(|(image_id, image)| match &image.data {
+            ImageData::Raw(pixels) => Some((*image_id, ExportedImage {
+                pixels: pixels.clone(),
+                width: image.descriptor.size.width,
+                height: image.descriptor.size.height,
+                stride: image.descriptor.stride,
+                format: image.descriptor.format,
+            })),
+            _ => None,
+        }).collect::<FastHashMap<ImageId, ExportedImage>>();
+
+        Self::new_with_resources(
+            node_hierarchy, node_data, layout, styles, window_size,
+            &app_resources.text_cache.string_cache, &image_names, &images,
+        )
+    }
+
+    pub(crate) fn new_with_resources<T: Layout>(
+        node_hierarchy: &NodeHierarchy,
+        node_data: &ArenaNodeData<NodeData<T>>,
+        layout: &LayoutResult,
+        styles: Vec<RectStyle>,
+        window_size: LogicalSize,
+        strings: &FastHashMap<TextId, String>,
+        image_names: &FastHashMap<ImageId, String>,
+        images: &FastHashMap<ImageId, ExportedImage>)
+    -> Self
+    {
+        let nodes = styles.into_iter().enumerate().map(|(node_idx, style)| {
+
+            let node_id = NodeId::new(node_idx);
+            let node = &node_data[node_id];
+
+            let content = match &node.node_type {
+                NodeType::Div => ExportedContent::Div,
+                NodeType::Label(text) => ExportedContent::Text(text.clone()),
+                NodeType::Text(text_id) => ExportedContent::Text(strings.get(text_id).cloned().unwrap_or_default()),
+                NodeType::Image(image_id) => ExportedContent::Image {
+                    name: image_names.get(image_id).cloned(),
+                    image: images.get(image_id).cloned(),
+                },
+                NodeType::GlTexture(_) => ExportedContent::GlTexture,
+                NodeType::IFrame(_) => ExportedContent::IFrame,
+            };
+
+            ExportedNode {
+                parent: node_hierarchy[node_id].parent.map(|p| p.index()),
+                children: node_id.children(node_hierarchy).map(|c| c.index()).collect(),
+                rect: layout.get(node_id).map(|node| node.border_box).unwrap_or(LayoutRect::zero()),
+                style,
+                ids: node.ids.clone(),
+                classes: node.classes.clone(),
+                content,
+            }
+        }).collect();
+
+        Self { window_size, nodes }
+    }
+
+    /// Writes the frame as a complete HTML document
+    pub(crate) fn to_html(&self, title: &str) -> String {
+
+        let mut html = String::new();
+
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        write!(html, "<title>{}</title>\n", escape_html(title)).unwrap();
+        html.push_str("<style>\n");
+        html.push_str("body { margin: 0; }\n");
+        write!(html,
+            ".azul-frame {{ position: relative; overflow: hidden; width: {}px; height: {}px; }}\n",
+            self.window_size.width, self.window_size.height
+        ).unwrap();
+        html.push_str(".azul-frame * { position: absolute; box-sizing: border-box; margin: 0; padding: 0; }\n");
+        html.push_str(".azul-frame p { white-space: pre-wrap; }\n");
+        html.push_str(".azul-placeholder { outline: 1px dashed #ff00ff; }\n");
+        html.push_str("</style>\n</head>\n<body>\n<div class=\"azul-frame\">\n");
+
+        for (node_idx, node) in self.nodes.iter().enumerate() {
+            if node.parent.is_none() {
+                self.write_node(&mut html, node_idx, 1);
+            }
+        }
+
+        html.push_str("</div>\n</body>\n</html>\n");
+        html
+    }
+
+    fn write_node(&self, html: &mut String, node_idx: usize, depth: usize) {
+
+        let node = &self.nodes[node_idx];
+        let indent = "  ".repeat(depth);
+
+        // Absolutely positioned elements are placed relative to the padding box
+        // of their parent, i.e. inside of the border of the parent
+        let (parent_x, parent_y) = match node.parent {
+            Some(parent) => {
+                let parent = &self.nodes[parent];
+                (
+                    parent.rect.origin.x + border_width(parent.style.border.and_then(|b| b.left)),
+                    parent.rect.origin.y + border_width(parent.style.border.and_then(|b| b.top)),
+                )
+            },
+            None => (0.0, 0.0),
+        };
+
+        let mut style = format!(
+            "left:{}px;top:{}px;width:{}px;height:{}px;",
+            node.rect.origin.x - parent_x, node.rect.origin.y - parent_y,
+            node.rect.size.width, node.rect.size.height,
+        );
+        write_style(&mut style, &node.style);
+
+        let tag = match node.content {
+            ExportedContent::Text(_) => "p",
+            ExportedContent::Image { .. } => "img",
+            _ => "div",
+        };
+
+        write!(html, "{}<{} data-azul-node=\"{}\"", indent, tag, node_idx).unwrap();
+        if !node.ids.is_empty() {
+            write!(html, " data-azul-ids=\"{}\"", escape_html(&node.ids.join(" "))).unwrap();
+        }
+        let mut classes = node.classes.clone();
+        match node.content {
+            ExportedContent::GlTexture | ExportedContent::IFrame => classes.push("azul-placeholder".into()),
+            _ => { },
+        }
+        if !classes.is_empty() {
+            write!(html, " class=\"{}\"", escape_html(&classes.join(" "))).unwrap();
+        }
+
+        match &node.content {
+            ExportedContent::Div => { },
+            ExportedContent::Text(_) => { },
+            ExportedContent::Image { name, image } => {
+                if let Some(name) = name {
+                    write!(html, " alt=\"{}\"", escape_html(name)).unwrap();
+                }
+                if let Some(png) = image.as_ref().and_then(|image| image.to_rgba()).map(|(w, h, rgba)| encode_png(w, h, &rgba)) {
+                    write!(html, " src=\"data:image/png;base64,{}\"", encode_base64(&png)).unwrap();
+                }
+            },
+            ExportedContent::GlTexture => html.push_str(" data-azul-type=\"gl-texture\""),
+            ExportedContent::IFrame => html.push_str(" data-azul-type=\"iframe\""),
+        }
+
+        write!(html, " style=\"{}\"", escape_html(&style)).unwrap();
+
+        if let ExportedContent::Image { .. } = node.content {
+            // <img> can't have children, so its children (if any) are written after it
+            html.push_str(">\n");
+            for child in &node.children {
+                self.write_node(html, *child, depth);
+            }
+            return;
+        }
+
+        html.push('>');
+
+        if let ExportedContent::Text(text) = &node.content {
+            html.push_str(&escape_html(text));
+        }
+
+        if node.children.is_empty() {
+            write!(html, "</{}>\n", tag).unwrap();
+        } else {
+            html.push('\n');
+            for child in &node.children {
+                self.write_node(html, *child, depth + 1);
+            }
+            write!(html, "{}</{}>\n", indent, tag).unwrap();
+        }
+    }
+}
+
+impl ExportedImage {
+
+    /// Converts the pixels to non-premultiplied RGBA8, `None` for formats that
+    /// can't be converted (floating point and integer textures)
+    fn to_rgba(&self) -> Option<(u32, u32, Vec<u8>)> {
+
+        let bytes_per_pixel = match self.format {
+            RawImageFormat::R8 => 1,
+            RawImageFormat::BGRA8 => 4,
+            _ => return None,
+        };
+
+        let row_len = self.width as usize * bytes_per_pixel;
+        let stride = self.stride.map(|s| s as usize).unwrap_or(row_len);
+        let mut rgba = Vec::with_capacity(self.width as usize * self.height as usize * 4);
+
+        for row_idx in 0..self.height as usize {
+            let row = self.pixels.get(row_idx * stride..row_idx * stride + row_len)?;
+            match self.format {
+                RawImageFormat::R8 => {
+                    for grey in row {
+                        rgba.extend_from_slice(&[*grey, *grey, *grey, 255]);
+                    }
+                },
+                _ => {
+                    for bgra in row.chunks(4) {
+                        let (b, g, r, a) = (bgra[0], bgra[1], bgra[2], bgra[3]);
+                        // The images are premultiplied when they are loaded
+                        let unpremultiply = |c: u8| if a == 0 { 0 } else { ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8 };
+                        rgba.extend_from_slice(&[unpremultiply(r), unpremultiply(g), unpremultiply(b), a]);
+                    }
+                },
+            }
+        }
+
+        Some((self.width, self.height, rgba))
+    }
+}
+
+/// Writes the computed colors, borders and fonts as inline CSS
+fn write_style(css: &mut String, style: &RectStyle) {
+
+    if let Some(background) = style.background_color {
+        write!(css, "background-color:{};", css_color(background.0)).unwrap();
+    }
+
+    if let Some(border) = style.border {
+        let sides = [("top", border.top), ("right", border.right), ("bottom", border.bottom), ("left", border.left)];
+        for (side_name, side) in sides.iter() {
+            if let Some(side) = side {
+                write!(css, "border-{}:{}px {} {};",
+                    side_name, side.border_width.to_pixels(),
+                    css_border_style(side.border_style), css_color(side.border_color)
+                ).unwrap();
+            }
+        }
+    }
+
+    if let Some(radius) = style.border_radius {
+        let radius = radius.0;
+        write!(css, "border-radius:{}px {}px {}px {}px;",
+            radius.top_left.width.to_pixels(), radius.top_right.width.to_pixels(),
+            radius.bottom_right.width.to_pixels(), radius.bottom_left.width.to_pixels(),
+        ).unwrap();
+    }
+
+    if let Some(font_color) = style.font_color {
+        write!(css, "color:{};", css_color(font_color.0)).unwrap();
+    }
+
+    if let Some(font_size) = style.font_size {
+        write!(css, "font-size:{}px;", font_size.to_pixels()).unwrap();
+    }
+
+    if let Some(font_family) = &style.font_family {
+        let families = font_family.fonts.iter().map(|font| match font {
+            FontId::BuiltinFont(name) => name.clone(),
+            FontId::ExternalFont(name) => format!("'{}'", name.replace('\'', "")),
+        }).collect::<Vec<String>>();
+        write!(css, "font-family:{};", families.join(",")).unwrap();
+    }
+
+    if let Some(text_align) = style.text_align {
+        let text_align = match text_align {
+            StyleTextAlignmentHorz::Left => "left",
+            StyleTextAlignmentHorz::Center => "center",
+            StyleTextAlignmentHorz::Right => "right",
+        };
+        write!(css, "text-align:{};", text_align).unwrap();
+    }
+}
+
+fn border_width(side: Option<StyleBorderSide>) -> f32 {
+    side.map(|side| side.border_width.to_pixels()).unwrap_or(0.0)
+}
+
+fn css_color(color: ColorU) -> String {
+    format!("rgba({},{},{},{})", color.r, color.g, color.b, color.a as f32 / 255.0)
+}
+
+fn css_border_style(style: BorderStyle) -> &'static str {
+    use self::BorderStyle::*;
+    match style {
+        None => "none",
+        Solid => "solid",
+        Double => "double",
+        Dotted => "dotted",
+        Dashed => "dashed",
+        Hidden => "hidden",
+        Groove => "groove",
+        Ridge => "ridge",
+        Inset => "inset",
+        Outset => "outset",
+    }
+}
+
+/// Encodes RGBA8 pixels as an (uncompressed) PNG, so that the export
+/// doesn't depend on the `image_loading` feature
+fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+
+    // Every row starts with the filter type (0 = no filter)
+    let mut raw = Vec::with_capacity(rgba.len() + height as usize);
+    for row in rgba.chunks(width.max(1) as usize * 4) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    // zlib stream with "stored" (uncompressed) deflate blocks
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        zlib.push(if is_last { 1 } else { 0 });
+        zlib.extend_from_slice(&[len as u8, (len >> 8) as u8, !len as u8, (!len >> 8) as u8]);
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&u32_be(adler32(&raw)));
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&u32_be(width));
+    ihdr.extend_from_slice(&u32_be(height));
+    // 8 bits per channel, RGBA, default compression / filter / no interlacing
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    write_png_chunk(&mut png, b"IHDR", &ihdr);
+    write_png_chunk(&mut png, b"IDAT", &zlib);
+    write_png_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_png_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&u32_be(data.len() as u32));
+    let crc_start = png.len();
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[crc_start..]);
+    png.extend_from_slice(&u32_be(crc));
+}
+
+fn u32_be(value: u32) -> [u8; 4] {
+    [(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        encoded.push(ALPHABET[(n >> 18) as usize & 63] as char);
+        encoded.push(ALPHABET[(n >> 12) as usize & 63] as char);
+        encoded.push(if chunk.len() > 1 { ALPHABET[(n >> 6) as usize & 63] as char } else { '=' });
+        encoded.push(if chunk.len() > 2 { ALPHABET[n as usize & 63] as char } else { '=' });
+    }
+    encoded
+}
+
+/// Writes the frame to `path`, used by `WindowState::export_frame_html()`
+pub(crate) fn write_frame_html(frame: Option<&ExportedFrame>, title: &str, path: &Path) -> Result<(), ExportError> {
+    let frame = frame.ok_or(ExportError::NoFrame)?;
+    fs::write(path, frame.to_html(title))?;
+    Ok(())
+}
+
+/// Checks that every opening tag of the document is closed in the right order
+/// (void elements and the doctype excepted) and returns the names of the opened elements
+#[cfg(test)]
+fn check_well_formed(html: &str) -> Result<Vec<String>, String> {
+
+    const VOID_ELEMENTS: &[&str] = &["meta", "img", "br", "hr", "input", "link"];
+
+    let mut open = Vec::<String>::new();
+    let mut elements = Vec::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        let end = rest[start..].find('>').ok_or_else(|| "unclosed tag".to_string())? + start;
+        let tag = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        if tag.starts_with('!') {
+            continue;
+        }
+        if tag.starts_with('/') {
+            match open.pop() {
+                Some(ref name) if name == &tag[1..] => { },
+                other => return Err(format!("</{}> closes {:?}", &tag[1..], other)),
+            }
+            continue;
+        }
+
+        let name = tag.split_whitespace().next().unwrap_or("").to_string();
+        // Attribute values are escaped, so a raw '<' or '>' can't appear inside of a tag
+        if tag.contains('<') {
+            return Err(format!("'<' inside of tag {}", name));
+        }
+        elements.push(name.clone());
+        if !VOID_ELEMENTS.contains(&name.as_str()) {
+            open.push(name);
+        }
+    }
+
+    if open.is_empty() { Ok(elements) } else { Err(format!("unclosed elements: {:?}", open)) }
+}
+
+#[cfg(test)]
+fn test_frame() -> (ExportedFrame, usize) {
+
+    use webrender::api::{LayoutPoint, LayoutSize};
+    use azul_css::{StyleBackgroundColor, StyleBorder, PixelValue};
+    use dom::Dom;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let dom: Dom<TestLayout> = Dom::div().with_id("root")
+        .with_child(Dom::label("Hello <world> & \"friends\"").with_class("greeting"))
+        .with_child(Dom::div().with_class("sidebar")
+            .with_child(Dom::div()));
+
+    let rect = |x, y, w, h| LayoutRect::new(LayoutPoint::new(x, y), LayoutSize::new(w, h));
+    let layout = LayoutResult::from_border_boxes(vec![
+        rect(0.0, 0.0, 400.0, 300.0),
+        rect(10.0, 20.0, 200.0, 30.0),
+        rect(250.0, 0.0, 150.0, 300.0),
+        rect(260.0, 15.0, 100.0, 100.0),
+    ]);
+
+    let border = StyleBorderSide { border_width: PixelValue::px(5.0), border_style: BorderStyle::Solid, border_color: ColorU { r: 0, g: 0, b: 0, a: 255 } };
+    let mut sidebar_style = RectStyle::default();
+    sidebar_style.background_color = Some(StyleBackgroundColor(ColorU { r: 255, g: 0, b: 0, a: 255 }));
+    sidebar_style.border = Some(StyleBorder { top: Some(border), left: Some(border), bottom: None, right: None });
+
+    let styles = vec![RectStyle::default(), RectStyle::default(), sidebar_style, RectStyle::default()];
+
+    let frame = ExportedFrame::new_with_resources(
+        &dom.arena.node_layout, &dom.arena.node_data, &layout, styles,
+        LogicalSize::new(400.0, 300.0),
+        &FastHashMap::default(), &FastHashMap::default(), &FastHashMap::default(),
+    );
+
+    (frame, dom.len())
+}
+
+#[test]
+fn test_exported_html_is_well_formed() {
+
+    let (frame, node_count) = test_frame();
+    let html = frame.to_html("Test <window>");
+
+    let elements = check_well_formed(&html).unwrap();
+    assert_eq!(html.matches("data-azul-node=").count(), node_count);
+    assert_eq!(elements.iter().filter(|e| *e == "p").count(), 1);
+    assert!(html.contains("<title>Test &lt;window&gt;</title>"));
+    assert!(html.contains(">Hello &lt;world&gt; &amp; &quot;friends&quot;</p>"));
+}
+
+#[test]
+fn test_exported_positions_match_the_layout() {
+
+    let (frame, _) = test_frame();
+    let html = frame.to_html("");
+
+    assert!(html.contains("<div data-azul-node=\"0\" data-azul-ids=\"root\" style=\"left:0px;top:0px;width:400px;height:300px;\""));
+    assert!(html.contains("<p data-azul-node=\"1\" class=\"greeting\" style=\"left:10px;top:20px;width:200px;height:30px;\""));
+    assert!(html.contains("<div data-azul-node=\"2\" class=\"sidebar\" style=\"left:250px;top:0px;width:150px;height:300px;\
+        background-color:rgba(255,0,0,1);border-top:5px solid rgba(0,0,0,1);border-left:5px solid rgba(0,0,0,1);\""));
+    // The child is positioned inside of the border of its parent
+    assert!(html.contains("<div data-azul-node=\"3\" style=\"left:5px;top:10px;width:100px;height:100px;\""));
+}
+
+#[test]
+fn test_images_are_embedded_as_png() {
+
+    assert_eq!(encode_base64(b"Man"), "TWFu");
+    assert_eq!(encode_base64(b"Ma"), "TWE=");
+    assert_eq!(encode_base64(b"M"), "TQ==");
+    assert_eq!(crc32(b"IEND"), 0xae42_6082);
+
+    // 2x1 image: premultiplied half-transparent red, opaque blue (BGRA)
+    let image = ExportedImage {
+        pixels: Arc::new(vec![0, 0, 128, 128, 255, 0, 0, 255]),
+        width: 2,
+        height: 1,
+        stride: None,
+        format: RawImageFormat::BGRA8,
+    };
+    let (width, height, rgba) = image.to_rgba().unwrap();
+    assert_eq!((width, height), (2, 1));
+    assert_eq!(rgba, vec![255, 0, 0, 128, 0, 0, 255, 255]);
+
+    let png = encode_png(width, height, &rgba);
+    assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+    assert_eq!(&png[png.len() - 12..], &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]);
+}
@@ -1,8 +1,11 @@
 //! ID-based node tree
 
 use std::{
+    fmt,
+    hash::{Hash, Hasher},
     ops::{Index, IndexMut},
     collections::BTreeMap,
+    rc::Rc,
 };
 
 pub use self::node_id::NodeId;
@@ -103,7 +106,7 @@ impl Node {
 #[derive(Debug, Default, Clone, PartialEq, Hash, Eq)]
 pub struct Arena<T> {
     pub(crate) node_layout: NodeHierarchy,
-    pub(crate) node_data: NodeDataContainer<T>,
+    pub(crate) node_data: ArenaNodeData<T>,
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Hash, Eq)]
@@ -219,6 +222,10 @@ impl<T> NodeDataContainer<T> {
         self.internal.get(id.index())
     }
 
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut T> {
+        self.internal.get_mut(id.index())
+    }
+
     pub fn linear_iter(&self) -> LinearIterator {
         LinearIterator {
             arena_len: self.len(),
@@ -249,6 +256,241 @@ impl<T> IndexMut<NodeId> for NodeDataContainer<T> {
     }
 }
 
+/// The node data of an `Arena`. Every node is either owned by the arena or shared with
+/// other arenas, i.e. the nodes of a `SharedDom` that are added to the DOM of every frame.
+/// Sharing a node only clones an `Rc`, never the node itself. A shared node is copied into
+/// the arena the first time it is accessed mutably (copy-on-write), so that modifying the
+/// node never modifies the other arenas.
+pub struct ArenaNodeData<T> {
+    slots: Vec<NodeSlot<T>>,
+}
+
+enum NodeSlot<T> {
+    Owned(T),
+    /// Node at the index of the shared nodes
+    Shared(Rc<Vec<T>>, usize),
+}
+
+impl<T: Clone> Clone for NodeSlot<T> {
+    fn clone(&self) -> Self {
+        match *self {
+            NodeSlot::Owned(ref node) => NodeSlot::Owned(node.clone()),
+            NodeSlot::Shared(ref nodes, index) => NodeSlot::Shared(nodes.clone(), index),
+        }
+    }
+}
+
+impl<T> NodeSlot<T> {
+    #[inline]
+    fn get(&self) -> &T {
+        match *self {
+            NodeSlot::Owned(ref node) => node,
+            NodeSlot::Shared(ref nodes, index) => &nodes[index],
+        }
+    }
+}
+
+impl<T: Clone> NodeSlot<T> {
+
+    /// Copies the node if it is shared, so that it can be modified
+    fn get_mut(&mut self) -> &mut T {
+        let copy = match *self {
+            NodeSlot::Owned(_) => None,
+            NodeSlot::Shared(ref nodes, index) => Some(nodes[index].clone()),
+        };
+        if let Some(node) = copy {
+            *self = NodeSlot::Owned(node);
+        }
+        match *self {
+            NodeSlot::Owned(ref mut node) => node,
+            NodeSlot::Shared(..) => unreachable!(),
+        }
+    }
+
+    fn into_owned(self) -> T {
+        match self {
+            NodeSlot::Owned(node) => node,
+            NodeSlot::Shared(nodes, index) => nodes[index].clone(),
+        }
+    }
+}
+
+impl<T> ArenaNodeData<T> {
+
+    pub fn new(data: Vec<T>) -> Self {
+        Self {
+            slots: data.into_iter().map(NodeSlot::Owned).collect(),
+        }
+    }
+
+    pub fn with_capacity(cap: usize) -> Self {
+        Self {
+            slots: Vec::with_capacity(cap),
+        }
+    }
+
+    pub fn len(&self) -> usize { self.slots.len() }
+
+    pub fn is_empty(&self) -> bool { self.slots.is_empty() }
+
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.slots.get(id.index()).map(NodeSlot::get)
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item=&'a T> + 'a {
+        self.slots.iter().map(NodeSlot::get)
+    }
+
+    pub fn transform<U, F>(&self, closure: F) -> NodeDataContainer<U> where F: Fn(&T, NodeId) -> U {
+        NodeDataContainer {
+            internal: self.iter().enumerate().map(|(node_id, node)| closure(node, NodeId::new(node_id))).collect(),
+        }
+    }
+
+    pub fn linear_iter(&self) -> LinearIterator {
+        LinearIterator {
+            arena_len: self.len(),
+            position: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, node: T) {
+        self.slots.push(NodeSlot::Owned(node));
+    }
+
+    /// Appends all `nodes` as shared nodes, without cloning any of them
+    pub(crate) fn push_shared(&mut self, nodes: &Rc<Vec<T>>) {
+        self.slots.extend((0..nodes.len()).map(|index| NodeSlot::Shared(nodes.clone(), index)));
+    }
+
+    /// Returns whether the node is (still) shared with other arenas
+    #[cfg(test)]
+    pub(crate) fn is_shared(&self, id: NodeId) -> bool {
+        match self.slots.get(id.index()) {
+            Some(NodeSlot::Shared(..)) => true,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn append(&mut self, other: &mut ArenaNodeData<T>) {
+        self.slots.append(&mut other.slots);
+    }
+
+    /// Removes the nodes that aren't `reachable`, without copying the shared nodes
+    pub(crate) fn retain_reachable(&mut self, reachable: &[bool]) {
+        let mut index = 0;
+        self.slots.retain(|_| {
+            let keep = reachable[index];
+            index += 1;
+            keep
+        });
+    }
+
+    /// Removes all nodes (the capacity stays the same) and returns the owned nodes,
+    /// the shared nodes are dropped
+    pub(crate) fn drain_owned<'a>(&'a mut self) -> impl Iterator<Item=T> + 'a {
+        self.slots.drain(..).filter_map(|slot| match slot {
+            NodeSlot::Owned(node) => Some(node),
+            NodeSlot::Shared(..) => None,
+        })
+    }
+
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.slots.reserve(additional);
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+}
+
+impl<T: Clone> ArenaNodeData<T> {
+
+    /// Same as `get`, but copies the node if it is shared
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut T> {
+        self.slots.get_mut(id.index()).map(NodeSlot::get_mut)
+    }
+
+    /// Copies all shared nodes, so that every node can be modified
+    pub fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item=&'a mut T> + 'a {
+        self.slots.iter_mut().map(NodeSlot::get_mut)
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        self.slots.pop().map(NodeSlot::into_owned)
+    }
+
+    /// Returns all nodes, the shared nodes are copied
+    pub(crate) fn into_vec(self) -> Vec<T> {
+        self.slots.into_iter().map(NodeSlot::into_owned).collect()
+    }
+
+    /// Appends the node `id` of the `other` arena, a shared node stays shared
+    pub(crate) fn push_node_of(&mut self, other: &ArenaNodeData<T>, id: NodeId) {
+        self.slots.push(other.slots[id.index()].clone());
+    }
+}
+
+impl<T> Default for ArenaNodeData<T> {
+    fn default() -> Self {
+        Self { slots: Vec::new() }
+    }
+}
+
+/// Cloning only clones the owned nodes, shared nodes stay shared
+impl<T: Clone> Clone for ArenaNodeData<T> {
+    fn clone(&self) -> Self {
+        Self { slots: self.slots.clone() }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArenaNodeData<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Shared and owned nodes compare (and hash) by their content
+impl<T: PartialEq> PartialEq for ArenaNodeData<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().zip(other.iter()).all(|(a, b)| a == b)
+    }
+}
+
+impl<T: Eq> Eq for ArenaNodeData<T> { }
+
+impl<T: Hash> Hash for ArenaNodeData<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for node in self.iter() {
+            node.hash(state);
+        }
+    }
+}
+
+impl<T> Index<NodeId> for ArenaNodeData<T> {
+    type Output = T;
+
+    fn index(&self, node_id: NodeId) -> &T {
+        #[cfg(debug_assertions)] {
+            self.slots.get(node_id.index()).unwrap().get()
+        } #[cfg(not(debug_assertions))] {
+            unsafe { self.slots.get_unchecked(node_id.index()).get() }
+        }
+    }
+}
+
+/// Copies the node if it is shared, see `ArenaNodeData`
+impl<T: Clone> IndexMut<NodeId> for ArenaNodeData<T> {
+    fn index_mut(&mut self, node_id: NodeId) -> &mut T {
+        #[cfg(debug_assertions)] {
+            self.slots.get_mut(node_id.index()).unwrap().get_mut()
+        } #[cfg(not(debug_assertions))] {
+            unsafe { self.slots.get_unchecked_mut(node_id.index()).get_mut() }
+        }
+    }
+}
+
 impl<T> Arena<T> {
 
     pub fn new() -> Arena<T> {
@@ -258,7 +500,7 @@ impl<T> Arena<T> {
     pub fn with_capacity(cap: usize) -> Arena<T> {
         Arena {
             node_layout: NodeHierarchy { internal: Vec::with_capacity(cap) },
-            node_data: NodeDataContainer { internal: Vec::<T>::with_capacity(cap) },
+            node_data: ArenaNodeData::with_capacity(cap),
         }
     }
 
@@ -272,7 +514,7 @@ impl<T> Arena<T> {
             previous_sibling: None,
             next_sibling: None,
         });
-        self.node_data.internal.push(data);
+        self.node_data.push(data);
         NodeId::new(next_index)
     }
 
@@ -293,13 +535,25 @@ impl<T> Arena<T> {
         }
     }
 
+    /// Reserves space for at least `additional` more nodes
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.node_layout.internal.reserve(additional);
+        self.node_data.reserve(additional);
+    }
+
+    /// Returns how many nodes the arena can hold without reallocating
+    #[cfg(test)]
+    pub(crate) fn capacity(&self) -> usize {
+        self.node_layout.internal.capacity().min(self.node_data.capacity())
+    }
+
     /// Appends another arena to the end of the current arena
     /// (by simply appending the two Vec of nodes)
     /// Can potentially mess up internal IDs, only use this if you
     /// know what you're doing
     pub fn append_arena(&mut self, other: &mut Arena<T>) {
         self.node_layout.internal.append(&mut other.node_layout.internal);
-        self.node_data.internal.append(&mut other.node_data.internal);
+        self.node_data.append(&mut other.node_data);
     }
 
     /// Transform keeps the relative order of parents / children
@@ -309,7 +563,7 @@ impl<T> Arena<T> {
         // TODO if T: Send (which is usually the case), then we could use rayon here!
         Arena {
             node_layout: self.node_layout.clone(),
-            node_data: self.node_data.transform(closure),
+            node_data: ArenaNodeData::new(self.node_data.transform(closure).internal),
         }
     }
 
@@ -318,7 +572,7 @@ impl<T> Arena<T> {
     }
 
     pub(crate) fn node_data_ref(&self, node_id: &NodeId) -> Option<&T> {
-        self.node_data.internal.get(node_id.index())
+        self.node_data.get(*node_id)
     }
 
     pub(crate) fn node_info_mut(&self, node_id: &NodeId) -> Option<&Node> {
@@ -326,14 +580,14 @@ impl<T> Arena<T> {
     }
 
     pub(crate) fn node_data_mut(&self, node_id: &NodeId) -> Option<&T> {
-        self.node_data.internal.get(node_id.index())
+        self.node_data.get(*node_id)
     }
 
     pub(crate) fn get_node_hierarchy(&self) -> &NodeHierarchy {
         &self.node_layout
     }
 
-    pub(crate) fn get_node_data(&self) -> &NodeDataContainer<T> {
+    pub(crate) fn get_node_data(&self) -> &ArenaNodeData<T> {
         &self.node_data
     }
 
@@ -365,7 +619,7 @@ impl<T: Copy> Arena<T> {
     #[inline]
     pub fn get_all_node_ids(&self) -> BTreeMap<NodeId, T> {
         use std::iter::FromIterator;
-        BTreeMap::from_iter(self.node_data.internal.iter().enumerate().map(|(i, node)|
+        BTreeMap::from_iter(self.node_data.iter().enumerate().map(|(i, node)|
             (NodeId::new(i), *node)
         ))
     }
@@ -657,4 +911,4 @@ impl<'a> Iterator for ReverseTraverse<'a> {
             None => None
         }
     }
-}
\ No newline at end of file
+}
\ No newline at end of file
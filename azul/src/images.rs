@@ -21,6 +21,20 @@ pub struct ImageId {
     id: usize,
 }
 
+impl ImageId {
+
+    /// Returns the number behind the ID, for serializing it
+    pub(crate) fn to_raw(&self) -> usize {
+        self.id
+    }
+
+    /// Re-creates an ID from `to_raw()`. The ID only refers to the same image
+    /// in the resources of the app that the ID was serialized in.
+    pub(crate) fn from_raw(id: usize) -> Self {
+        Self { id }
+    }
+}
+
 pub(crate) fn new_image_id() -> ImageId {
     let unique_id =IMAGE_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
     ImageId {
@@ -44,31 +58,25 @@ pub enum ImageType {
     GuessImageFormat,
 }
 
+/// An image on the GPU of one window
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct ImageInfo {
     pub(crate) key: ImageKey,
     pub(crate) descriptor: ImageDescriptor,
 }
 
+/// A decoded image, shared by all windows. The pixels are reference-counted,
+/// so uploading the image to the GPU of another window doesn't copy them.
 #[derive(Debug, Clone)]
-pub(crate) enum ImageState {
-    // resource is available for the renderer
-    Uploaded(ImageInfo),
-    // image is loaded & decoded, but not yet available
-    ReadyForUpload((ImageData, ImageDescriptor)),
-    // Image is about to get deleted in the next frame
-    AboutToBeDeleted((Option<ImageKey>, ImageDescriptor)),
+pub(crate) struct DecodedImage {
+    pub(crate) data: ImageData,
+    pub(crate) descriptor: ImageDescriptor,
 }
 
-impl ImageState {
+impl DecodedImage {
     /// Returns the original dimensions of the image
     pub fn get_dimensions(&self) -> (f32, f32) {
-        use self::ImageState::*;
-        match self {
-            Uploaded(ImageInfo { descriptor, .. }) |
-            ReadyForUpload((_, descriptor)) |
-            AboutToBeDeleted((_, descriptor)) => (descriptor.size.width as f32, descriptor.size.height as f32)
-        }
+        (self.descriptor.size.width as f32, self.descriptor.size.height as f32)
     }
 }
 
@@ -245,4 +253,4 @@ fn test_premultiply() {
     let mut color = [255, 0, 0, 127];
     premultiply(&mut color);
     assert_eq!(color, [127, 0, 0, 127]);
-}
\ No newline at end of file
+}
\ No newline at end of file
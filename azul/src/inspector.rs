@@ -0,0 +1,427 @@
+//! Built-in DOM inspector, only available with the `debug-inspector` feature.
+//!
+//! Pressing the hotkey (F12 by default, see `WindowCreateOptions::inspector_hotkey`)
+//! opens a panel on the right side of the window, which shows the node tree
+//! of the current frame. While the inspector is in "pick mode", hovering over
+//! the window highlights the node under the cursor and clicking selects it -
+//! the panel then shows the solved rectangle and the computed style of the
+//! selected node. Escape toggles the pick mode, so that the application can be
+//! used normally while the inspector stays open.
+//!
+//! The panel is a regular `Dom`, built from the public `UiState` and
+//! `LayoutSnapshot` getters, styled with its own stylesheet and drawn on
+//! top of the application, the same way as an iframe.
+
+use std::fmt::Write;
+use glium::glutin::{
+    Event, WindowEvent, KeyboardInput, ElementState, MouseButton, VirtualKeyCode,
+    dpi::LogicalSize,
+};
+use webrender::api::{LayoutRect, LayoutPoint, LayoutSize};
+use azul_css::{Css, StyleBackgroundColor, StyleColorU};
+use {
+    dom::{Dom, NodeData, NodeType},
+    id_tree::{NodeId, NodeEdge},
+    style::CssRuleIndex,
+    traits::Layout,
+    ui_state::UiState,
+    window::LayoutSnapshot,
+    node_identity::NodeIdMapping,
+};
+
+/// Hotkey that toggles the inspector if no other hotkey is configured
+pub const DEFAULT_INSPECTOR_HOTKEY: VirtualKeyCode = VirtualKeyCode::F12;
+
+/// The panel never gets wider than this (in logical pixels), or wider than half the window
+const PANEL_MAX_WIDTH: f32 = 360.0;
+/// Maximum number of nodes that are listed in the tree, so that huge DOMs
+/// don't make the inspector itself the bottleneck
+const MAX_TREE_LINES: usize = 200;
+
+pub(crate) const HOVERED_NODE_COLOR: StyleBackgroundColor = StyleBackgroundColor(StyleColorU { r: 0, g: 120, b: 255, a: 80 });
+pub(crate) const SELECTED_NODE_COLOR: StyleBackgroundColor = StyleBackgroundColor(StyleColorU { r: 255, g: 140, b: 0, a: 100 });
+
+const INSPECTOR_CSS: &str = "
+    .azul-inspector-panel {
+        flex-direction: column;
+        background-color: #1e1e1ef0;
+        padding: 5px;
+    }
+    .azul-inspector-heading {
+        color: #ffffff;
+        font-size: 13px;
+        height: 18px;
+    }
+    .azul-inspector-node,
+    .azul-inspector-hovered,
+    .azul-inspector-selected,
+    .azul-inspector-detail {
+        font-size: 11px;
+        height: 14px;
+    }
+    .azul-inspector-node { color: #c8c8c8; }
+    .azul-inspector-hovered { color: #4ea6ff; }
+    .azul-inspector-selected { color: #ffa030; }
+    .azul-inspector-detail { color: #e0e0e0; }
+";
+
+/// Result of `Inspector::handle_events`
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct InspectorEventResult {
+    /// The inspector changed its state and has to be redrawn
+    pub(crate) should_redraw: bool,
+    /// The mouse events of this frame were used for picking a node and
+    /// should not be forwarded to the callbacks of the application
+    pub(crate) consumed_mouse_input: bool,
+}
+
+/// State of the inspector of one window
+pub(crate) struct Inspector {
+    hotkey: Option<VirtualKeyCode>,
+    is_open: bool,
+    is_picking: bool,
+    hovered_node: Option<NodeId>,
+    selected_node: Option<NodeId>,
+    pub(crate) css: Css,
+    pub(crate) css_index: CssRuleIndex,
+}
+
+impl Inspector {
+
+    /// Creates a closed inspector - if `hotkey` is `None`, the inspector can never be opened
+    pub(crate) fn new(hotkey: Option<VirtualKeyCode>) -> Self {
+        let mut css = ::css::from_str(INSPECTOR_CSS).expect("inspector stylesheet is invalid");
+        css.sort_by_specificity();
+        let css_index = CssRuleIndex::new(&css);
+        Self {
+            hotkey,
+            is_open: false,
+            is_picking: false,
+            hovered_node: None,
+            selected_node: None,
+            css,
+            css_index,
+        }
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    pub(crate) fn hovered_node(&self) -> Option<NodeId> {
+        self.hovered_node
+    }
+
+    pub(crate) fn selected_node(&self) -> Option<NodeId> {
+        self.selected_node
+    }
+
+    /// Moves the hovered and selected node to the `NodeId`s of the new frame
+    pub(crate) fn migrate_node_ids(&mut self, mapping: &NodeIdMapping) {
+        self.hovered_node = self.hovered_node.and_then(|node_id| mapping.get(node_id));
+        self.selected_node = self.selected_node.and_then(|node_id| mapping.get(node_id));
+    }
+
+    /// Updates the inspector from the events of this frame. The `layout` is the
+    /// snapshot of the last frame, the inspector requests the computed styles
+    /// to be captured while it is open.
+    pub(crate) fn handle_events(&mut self, events: &[Event], layout: &mut LayoutSnapshot, window_size: LogicalSize)
+    -> InspectorEventResult
+    {
+        let mut result = InspectorEventResult::default();
+
+        for event in events {
+            let event = match event {
+                Event::WindowEvent { event, .. } => event,
+                _ => continue,
+            };
+
+            match event {
+                WindowEvent::KeyboardInput { input: KeyboardInput { state: ElementState::Pressed, virtual_keycode: Some(key), .. }, .. } => {
+                    if self.hotkey == Some(*key) {
+                        self.is_open = !self.is_open;
+                        self.is_picking = self.is_open;
+                        self.hovered_node = None;
+                        result.should_redraw = true;
+                    } else if self.is_open && *key == VirtualKeyCode::Escape {
+                        self.is_picking = !self.is_picking;
+                        self.hovered_node = None;
+                        result.should_redraw = true;
+                    }
+                },
+                WindowEvent::CursorMoved { position, .. } if self.is_picking => {
+                    let hovered_node = if panel_rect(window_size).contains(&LayoutPoint::new(position.x as f32, position.y as f32)) {
+                        None
+                    } else {
+                        // The last node in document order is the one that is drawn on top
+                        layout.nodes_at(position.x as f32, position.y as f32).last().cloned()
+                    };
+                    if hovered_node != self.hovered_node {
+                        self.hovered_node = hovered_node;
+                        result.should_redraw = true;
+                    }
+                    result.consumed_mouse_input = true;
+                },
+                WindowEvent::MouseInput { state, button: MouseButton::Left, .. } if self.is_picking => {
+                    if *state == ElementState::Pressed && self.hovered_node.is_some() {
+                        self.selected_node = self.hovered_node;
+                        result.should_redraw = true;
+                    }
+                    result.consumed_mouse_input = true;
+                },
+                _ => { },
+            }
+        }
+
+        // Node IDs of the last frame are meaningless if the DOM got smaller
+        if self.selected_node.map(|n| n.index() >= layout.len()).unwrap_or(false) {
+            self.selected_node = None;
+        }
+
+        layout.capture_styles = self.is_open;
+        result
+    }
+
+    /// Builds the DOM of the panel: the node tree of the `ui_state` and
+    /// the details of the selected node
+    pub(crate) fn panel_dom<T: Layout>(&self, ui_state: &UiState<T>, layout: &LayoutSnapshot) -> Dom<T> {
+
+        let dom = ui_state.dom();
+        let node_hierarchy = dom.get_node_hierarchy();
+
+        let mut panel = Dom::div().with_class("azul-inspector-panel");
+        let mode = if self.is_picking { "picking, Esc to stop" } else { "Esc to pick" };
+        panel.add_child(Dom::label(format!("{} nodes ({})", dom.len(), mode)).with_class("azul-inspector-heading"));
+
+        let mut depth = 0;
+        let mut lines = 0;
+        for edge in dom.get_root().traverse(node_hierarchy) {
+            let node_id = match edge {
+                NodeEdge::Start(node_id) => { depth += 1; node_id },
+                NodeEdge::End(_) => { depth -= 1; continue; },
+            };
+            if lines == MAX_TREE_LINES {
+                panel.add_child(Dom::label("...").with_class("azul-inspector-node"));
+                break;
+            }
+            let node_data = match dom.get_node_content(node_id) {
+                Some(s) => s,
+                None => continue,
+            };
+            let class = if Some(node_id) == self.selected_node {
+                "azul-inspector-selected"
+            } else if Some(node_id) == self.hovered_node {
+                "azul-inspector-hovered"
+            } else {
+                "azul-inspector-node"
+            };
+            panel.add_child(Dom::label(format_tree_line(node_data, depth - 1)).with_class(class));
+            lines += 1;
+        }
+
+        let selected_node = match self.selected_node {
+            Some(s) => s,
+            None => return panel,
+        };
+
+        panel.add_child(Dom::label(format!("Node {}", selected_node)).with_class("azul-inspector-heading"));
+
+        let mut details = Vec::new();
+        if let Some(rect) = layout.get_rect(selected_node) {
+            details.push(format!(
+                "rect: x: {}, y: {}, width: {}, height: {}",
+                rect.origin.x, rect.origin.y, rect.size.width, rect.size.height
+            ));
+        }
+        if let Some(tag) = ui_state.node_ids_to_tag_ids().get(&selected_node) {
+            details.push(format!("tag: {}", tag));
+        }
+        if let Some(layout_properties) = layout.get_layout(selected_node) {
+            details.extend(set_properties(&format!("{:#?}", layout_properties)));
+        }
+        if let Some(style) = layout.get_style(selected_node) {
+            details.extend(set_properties(&format!("{:#?}", style)));
+        }
+
+        for line in details {
+            panel.add_child(Dom::label(line).with_class("azul-inspector-detail"));
+        }
+
+        panel
+    }
+}
+
+/// The area of the window that the inspector panel covers
+pub(crate) fn panel_rect(window_size: LogicalSize) -> LayoutRect {
+    let width = window_size.width as f32;
+    let height = window_size.height as f32;
+    let panel_width = PANEL_MAX_WIDTH.min(width / 2.0);
+    LayoutRect::new(LayoutPoint::new(width - panel_width, 0.0), LayoutSize::new(panel_width, height))
+}
+
+/// Formats one node of the tree, i.e. `| | div #id .class`
+fn format_tree_line<T: Layout>(node: &NodeData<T>, depth: usize) -> String {
+    use self::NodeType::*;
+
+    let mut line = "| ".repeat(depth);
+    line.push_str(match node.node_type {
+        Div => "div",
+        Label(_) => "label",
+        Text(_) => "text",
+        Image(_) => "image",
+        GlTexture(_) => "gltexture",
+        IFrame(_) => "iframe",
+    });
+    for id in &node.ids {
+        let _ = write!(line, " #{}", id);
+    }
+    for class in &node.classes {
+        let _ = write!(line, " .{}", class);
+    }
+    line
+}
+
+/// Filters the pretty-printed `RectStyle` / `RectLayout` down to the properties that are set
+fn set_properties(debug_output: &str) -> Vec<String> {
+    debug_output.lines()
+        .filter(|line| !line.trim_right().ends_with("None,"))
+        .map(|line| line.trim_right().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+fn key_event(key: VirtualKeyCode) -> Event {
+    use glium::glutin::{WindowId, ModifiersState};
+    Event::WindowEvent {
+        window_id: unsafe { WindowId::dummy() },
+        event: WindowEvent::KeyboardInput {
+            device_id: unsafe { ::glium::glutin::DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state: ElementState::Pressed,
+                virtual_keycode: Some(key),
+                modifiers: ModifiersState::default(),
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+fn mouse_events(x: f64, y: f64) -> Vec<Event> {
+    use glium::glutin::{WindowId, DeviceId, ModifiersState, dpi::LogicalPosition};
+    let window_event = |event| Event::WindowEvent { window_id: unsafe { WindowId::dummy() }, event };
+    let events = vec![
+        window_event(WindowEvent::CursorMoved {
+            device_id: unsafe { DeviceId::dummy() },
+            position: LogicalPosition::new(x, y),
+            modifiers: ModifiersState::default(),
+        }),
+        window_event(WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state: ElementState::Pressed,
+            button: MouseButton::Left,
+            modifiers: ModifiersState::default(),
+        }),
+        window_event(WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state: ElementState::Released,
+            button: MouseButton::Left,
+            modifiers: ModifiersState::default(),
+        }),
+    ];
+    events
+}
+
+#[test]
+fn test_inspector_picks_topmost_node() {
+
+    use std::rc::Rc;
+    use layout_result::LayoutResult;
+
+    let window_size = LogicalSize::new(800.0, 600.0);
+    let mut layout = LayoutSnapshot {
+        layout: Rc::new(LayoutResult::from_border_boxes(vec![
+            LayoutRect::new(LayoutPoint::new(0.0, 0.0), LayoutSize::new(800.0, 600.0)),
+            LayoutRect::new(LayoutPoint::new(10.0, 10.0), LayoutSize::new(100.0, 50.0)),
+            LayoutRect::new(LayoutPoint::new(20.0, 20.0), LayoutSize::new(20.0, 20.0)),
+        ])),
+        .. LayoutSnapshot::default()
+    };
+
+    let mut inspector = Inspector::new(Some(DEFAULT_INSPECTOR_HOTKEY));
+
+    // Closed inspector doesn't touch the mouse events
+    let result = inspector.handle_events(&mouse_events(25.0, 25.0), &mut layout, window_size);
+    assert!(!result.consumed_mouse_input);
+    assert_eq!(inspector.selected_node(), None);
+
+    let result = inspector.handle_events(&[key_event(DEFAULT_INSPECTOR_HOTKEY)], &mut layout, window_size);
+    assert!(result.should_redraw);
+    assert!(inspector.is_open());
+    assert!(layout.capture_styles);
+
+    let result = inspector.handle_events(&mouse_events(25.0, 25.0), &mut layout, window_size);
+    assert!(result.consumed_mouse_input);
+    assert_eq!(inspector.selected_node(), Some(NodeId::new(2)));
+
+    let result = inspector.handle_events(&mouse_events(50.0, 50.0), &mut layout, window_size);
+    assert!(result.consumed_mouse_input);
+    assert_eq!(inspector.selected_node(), Some(NodeId::new(1)));
+
+    // Clicking on the panel itself doesn't change the selection
+    inspector.handle_events(&mouse_events(700.0, 50.0), &mut layout, window_size);
+    assert_eq!(inspector.selected_node(), Some(NodeId::new(1)));
+
+    // Escape stops picking, the mouse goes to the application again
+    inspector.handle_events(&[key_event(VirtualKeyCode::Escape)], &mut layout, window_size);
+    let result = inspector.handle_events(&mouse_events(25.0, 25.0), &mut layout, window_size);
+    assert!(!result.consumed_mouse_input);
+    assert_eq!(inspector.selected_node(), Some(NodeId::new(1)));
+
+    inspector.handle_events(&[key_event(DEFAULT_INSPECTOR_HOTKEY)], &mut layout, window_size);
+    assert!(!inspector.is_open());
+    assert!(!layout.capture_styles);
+}
+
+#[test]
+fn test_inspector_panel_lists_node_tree() {
+
+    use std::rc::Rc;
+    use layout_result::LayoutResult;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div().with_id("calculator")
+                .with_child(Dom::label("7").with_class("button"))
+                .with_child(Dom::label("8").with_class("button"))
+        }
+    }
+
+    let ui_state = UiState::from_dom(TestLayout { }.layout());
+    let layout = LayoutSnapshot {
+        layout: Rc::new(LayoutResult::from_border_boxes(vec![
+            LayoutRect::new(LayoutPoint::new(0.0, 0.0), LayoutSize::new(100.0, 50.0)),
+            LayoutRect::new(LayoutPoint::new(0.0, 0.0), LayoutSize::new(50.0, 50.0)),
+            LayoutRect::new(LayoutPoint::new(50.0, 0.0), LayoutSize::new(50.0, 50.0)),
+        ])),
+        .. LayoutSnapshot::default()
+    };
+
+    let mut inspector = Inspector::new(Some(DEFAULT_INSPECTOR_HOTKEY));
+    inspector.is_open = true;
+    inspector.selected_node = Some(NodeId::new(2));
+
+    let expected = "\
+div .azul-inspector-panel
+    label \"3 nodes (Esc to pick)\" .azul-inspector-heading
+    label \"div #calculator\" .azul-inspector-node
+    label \"| label .button\" .azul-inspector-node
+    label \"| label .button\" .azul-inspector-selected
+    label \"Node 2\" .azul-inspector-heading
+    label \"rect: x: 50, y: 0, width: 50, height: 50\" .azul-inspector-detail
+";
+
+    assert_eq!(inspector.panel_dom(&ui_state, &layout).serialize_for_snapshot(), expected);
+}
@@ -0,0 +1,134 @@
+//! The solved layout of the last frame of a window, see `WindowState::get_layout_result()`.
+//!
+//! The layout result is recorded once per frame, after the layout is solved, and is the
+//! only place where the box model of the nodes is calculated: the `LayoutSnapshot`, the
+//! debug overlay, the inspector and the HTML export all read from it.
+
+use webrender::api::{LayoutRect, LayoutPoint, LayoutSize};
+use azul_css::{RectStyle, RectLayout, PixelValue, StyleBorderSide};
+use id_tree::NodeId;
+
+/// The solved layout of one node, see `LayoutResult`.
+///
+/// All rectangles are in logical pixels relative to the window, without the
+/// transforms of the node (or its parents) and without scroll offsets.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NodeLayoutResult {
+    /// The border box, extended by the margin
+    pub margin_box: LayoutRect,
+    /// The solved rectangle of the node (content, padding and border)
+    pub border_box: LayoutRect,
+    /// The border box without the border
+    pub padding_box: LayoutRect,
+    /// The padding box without the padding: the area that the text,
+    /// image or children of the node are laid out in
+    pub content_rect: LayoutRect,
+    /// Size of the scrollable area (the border box united with the rectangles of the
+    /// children) if the node is a scroll container (`overflow: scroll` or `overflow: auto`),
+    /// `None` otherwise
+    pub scroll_content_size: Option<LayoutSize>,
+    /// Whether the node is entirely outside of the clip of a parent (`overflow: hidden` or
+    /// rounded corners), i.e. nothing of the node was visible. Nodes that are only scrolled
+    /// out of view are not clipped, and neither are nodes inside of a transformed node.
+    pub is_clipped: bool,
+    /// Position of the node in the drawing order: nodes with a higher `z_order` are drawn
+    /// on top of nodes with a lower one (the hit-test uses the same order)
+    pub z_order: usize,
+}
+
+impl NodeLayoutResult {
+
+    /// Calculates the boxes of a node from its solved rectangle (the border box).
+    /// The node isn't scrollable, not clipped and at the bottom of the drawing order.
+    pub(crate) fn from_border_box(border_box: LayoutRect, layout: &RectLayout, style: &RectStyle) -> Self {
+
+        let margin = layout.margin.map(|m| edges(m.top, m.right, m.bottom, m.left)).unwrap_or_default();
+        let padding = layout.padding.map(|p| edges(p.top, p.right, p.bottom, p.left)).unwrap_or_default();
+        let border = style.border.map(|b| {
+            let width = |side: Option<StyleBorderSide>| side.map(|s| s.border_width);
+            edges(width(b.top), width(b.right), width(b.bottom), width(b.left))
+        }).unwrap_or_default();
+
+        let padding_box = shrink(border_box, border);
+
+        Self {
+            margin_box: grow(border_box, margin),
+            border_box,
+            padding_box,
+            content_rect: shrink(padding_box, padding),
+            scroll_content_size: None,
+            is_clipped: false,
+            z_order: 0,
+        }
+    }
+}
+
+/// Edge sizes (top, right, bottom, left) in pixels, negative values are ignored
+fn edges(top: Option<PixelValue>, right: Option<PixelValue>, bottom: Option<PixelValue>, left: Option<PixelValue>) -> [f32; 4] {
+    let px = |v: Option<PixelValue>| v.map(|v| v.to_pixels()).unwrap_or(0.0).max(0.0);
+    [px(top), px(right), px(bottom), px(left)]
+}
+
+fn grow(rect: LayoutRect, [top, right, bottom, left]: [f32; 4]) -> LayoutRect {
+    LayoutRect::new(
+        LayoutPoint::new(rect.origin.x - left, rect.origin.y - top),
+        LayoutSize::new(rect.size.width + left + right, rect.size.height + top + bottom),
+    )
+}
+
+/// Same as `grow`, but inwards - the size doesn't get negative if the edges are larger than the rect
+fn shrink(rect: LayoutRect, [top, right, bottom, left]: [f32; 4]) -> LayoutRect {
+    LayoutRect::new(
+        LayoutPoint::new(rect.origin.x + left, rect.origin.y + top),
+        LayoutSize::new((rect.size.width - left - right).max(0.0), (rect.size.height - top - bottom).max(0.0)),
+    )
+}
+
+/// The solved layout of one frame of a window, indexed by the `NodeId`
+/// of the DOM that `layout()` returned (nodes of iframes are not included).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LayoutResult {
+    pub(crate) nodes: Vec<NodeLayoutResult>,
+}
+
+impl LayoutResult {
+
+    /// Layout result where every node only has a border box, for tests of the consumers
+    #[cfg(test)]
+    pub(crate) fn from_border_boxes(border_boxes: Vec<LayoutRect>) -> Self {
+        Self {
+            nodes: border_boxes.into_iter().enumerate().map(|(z_order, border_box)| NodeLayoutResult {
+                z_order,
+                .. NodeLayoutResult::from_border_box(border_box, &RectLayout::default(), &RectStyle::default())
+            }).collect(),
+        }
+    }
+
+    /// Number of nodes in the layout
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the layout of the node, `None` if the node wasn't part of the last frame
+    pub fn get(&self, node_id: NodeId) -> Option<&NodeLayoutResult> {
+        self.nodes.get(node_id.index())
+    }
+
+    /// Returns the layouts of all nodes, in document order
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (NodeId, &'a NodeLayoutResult)> + 'a {
+        self.nodes.iter().enumerate().map(|(idx, node)| (NodeId::new(idx), node))
+    }
+
+    /// Returns all nodes whose border box contains the point, in document order
+    pub fn nodes_at(&self, x: f32, y: f32) -> Vec<NodeId> {
+        let point = LayoutPoint::new(x, y);
+        self.iter()
+            .filter(|(_, node)| node.border_box.contains(&point))
+            .map(|(node_id, _)| node_id)
+            .collect()
+    }
+}
@@ -79,6 +79,10 @@ extern crate serde;
 #[cfg(feature = "serde_serialization")]
 #[cfg_attr(feature = "serde_serialization", macro_use)]
 extern crate serde_derive;
+#[cfg(any(test, feature = "property-testing"))]
+extern crate quickcheck;
+#[cfg(all(test, feature = "serde_serialization"))]
+extern crate serde_json;
 
 pub(crate) use azul_dependencies::glium as glium;
 pub(crate) use azul_dependencies::gleam as gleam;
@@ -138,7 +142,10 @@ pub mod default_callbacks;
 /// Bindings to the native file-chooser, color picker, etc. dialogs
 pub mod dialogs;
 /// DOM / HTML node handling
+#[macro_use]
 pub mod dom;
+/// Structural diff between two DOMs, see `Dom::diff()`
+pub mod diff;
 /// Re-exports of errors
 pub mod error;
 /// Font handling
@@ -157,6 +164,58 @@ pub mod widgets;
 pub mod window;
 /// Window state handling, event filtering
 pub mod window_state;
+/// State handling for user interfaces (tags and callbacks of the DOM)
+pub mod ui_state;
+/// Per-frame timings and statistics (with the `profiling` feature)
+pub mod profiling;
+/// Opt-in recording of how input events are dispatched to callbacks
+pub mod dispatch_trace;
+/// Forwarding internal warnings and non-fatal errors to the app (logger, error handler, panic hook)
+pub mod diagnostics;
+/// Layout debugging overlay (bounding boxes, padding, baselines, ...)
+pub mod debug_overlay;
+/// FPS and frame-budget HUD, drawn from the stats of the `profiling` feature
+#[cfg(feature = "profiling")]
+pub mod frame_hud;
+/// Deterministic mode for reproducible runs, injectable animation clock
+pub mod deterministic;
+/// Transient notifications on top of the window content
+pub mod toast;
+/// Querying the nodes under a point, see `WindowState::hit_test()`
+pub mod hit_test;
+/// The solved layout (box model, scroll sizes, drawing order) of the last frame
+pub mod layout_result;
+/// Catching panics in callbacks and showing them in an error overlay
+pub mod error_overlay;
+/// Headless rendering into an image and DOM generators for property-based tests
+pub mod testing;
+/// DOM generators for the benchmarks and unit tests (with the `benchmarks` feature)
+#[cfg(any(test, feature = "benchmarks"))]
+pub mod dom_gen;
+/// Serializing the structure of a `Dom` with serde (with the `serde_serialization` feature)
+#[cfg(feature = "serde_serialization")]
+pub mod serialization;
+/// Comparing DOMs against stored snapshots, see `assert_dom_snapshot!`
+#[macro_use]
+pub mod snapshot;
+/// Built-in DOM inspector overlay (with the `debug-inspector` feature)
+#[cfg(feature = "debug-inspector")]
+pub mod inspector;
+/// Accessibility tree of a frame and the interface to the accessibility API of the platform
+pub mod accessibility;
+/// Application menu bars with accelerators, native or rendered into the DOM
+pub mod menu;
+/// Drag and drop of text, files and custom formats between applications
+pub mod drag_drop;
+/// Exporting the last frame as a static HTML document (with the `html-export` feature)
+#[cfg(feature = "html-export")]
+pub mod html_export;
+/// C ABI for building DOMs and running an app from other languages (with the `ffi` feature)
+#[cfg(feature = "ffi")]
+pub mod ffi;
+/// Converting Markdown documents to a `Dom` (with the `markdown` feature)
+#[cfg(feature = "markdown")]
+pub mod markdown;
 
 /// UI Description & display list handling (webrender)
 mod ui_description;
@@ -165,8 +224,6 @@ mod ui_description;
 mod display_list;
 /// Slab allocator for nodes, based on IDs (replaces kuchiki + markup5ever)
 mod id_tree;
-/// State handling for user interfaces
-mod ui_state;
 /// Image handling
 mod images;
 /// The compositor takes all textures (user-defined + the UI texture(s)) and draws them on
@@ -177,8 +234,14 @@ mod compositor;
 mod logging;
 /// Flexbox-based UI solver
 mod ui_solver;
+/// Item placement and track sizing for grid containers
+mod grid_solver;
 /// DOM styling module
 mod style;
+/// Matching the nodes of two frames, for migrating focus, hover, etc. to the new frame
+mod node_identity;
+/// 2D transforms of nodes (`transform`, `transform-origin`)
+mod transform;
 
 // Faster implementation of a HashMap (optional, disabled by default, turn on with --feature="faster-hashing")
 
@@ -197,18 +260,33 @@ pub mod prelude {
     pub use app::{App, AppConfig};
     pub use app_state::AppState;
     pub use dom::{
-        Dom, DomHash, NodeType, NodeData, Callback, On,
+        Dom, DomFragment, SharedDom, DomHash, DomAllocator, NodeType, NodeData, Callback, On,
         UpdateScreen, Redraw, DontRedraw, Texture, GlTextureCallback,
         IFrameCallback, TabIndex, EventFilter, HoverEventFilter, FocusEventFilter,
-        NotEventFilter, WindowEventFilter, DesktopEventFilter,
+        NotEventFilter, WindowEventFilter, DesktopEventFilter, CallbackRegistry,
+        DomStatistics,
     };
+    pub use diff::{DomDiff, DomChange, ChangedFields};
     pub use traits::{Layout, Modify};
     pub use window::{
         MonitorIter, Window, WindowCreateOptions, WindowId,
         MouseMode, UpdateBehaviour, UpdateMode, HidpiAdjustedBounds,
-        WindowMonitorTarget, RendererType, CallbackInfo, LayoutInfo, ReadOnlyWindow
+        WindowMonitorTarget, RendererType, CallbackInfo, LayoutInfo, ReadOnlyWindow, LayoutSnapshot
     };
-    pub use window_state::{WindowState, KeyboardState, MouseState, DebugState, keymap, AcceleratorKey};
+    pub use window_state::{WindowState, KeyboardState, MouseState, DebugState, DoubleClickSettings, keymap, AcceleratorKey};
+    pub use ui_state::{UiState, UiStateSummary};
+    pub use debug_overlay::DebugOverlayFlags;
+    pub use hit_test::HitTestItem;
+    pub use layout_result::{LayoutResult, NodeLayoutResult};
+    pub use accessibility::{AccessibilityTree, AccessibleNode, AccessibleRole, AccessibilityAction, AccessibilityAdapter};
+    pub use error_overlay::CallbackPanic;
+    pub use diagnostics::{AzulError, LogLevel, PanicReport};
+    pub use drag_drop::{DragData, DroppedData, DragDropBackend};
+    pub use menu::{MenuBar, Menu, MenuEntry, MenuItem, MenuItemUpdate, MenuCallback, MenuCallbackInfo, KeyCombo, MenuBackend};
+    pub use dispatch_trace::{DispatchTraceEvent, TracedNode, TracedCallback, SkippedCallback, SkipReason};
+    pub use profiling::{FrameStats, FramePhase, FrameStatsHistory};
+    #[cfg(feature = "profiling")]
+    pub use frame_hud::FrameHudData;
     pub use images::{ImageType, ImageId};
     pub use text_cache::{TextCache, TextId};
     pub use glium::glutin::{
@@ -219,12 +297,20 @@ pub mod prelude {
     pub use rusttype::Font;
     pub use app_resources::{AppResources, RawImageFormat};
     pub use daemon::{TerminateDaemon, DaemonId, DaemonCallback, Daemon};
+    pub use deterministic::AnimationClock;
+    pub use toast::{Toast, ToastId, ToastContent, ToastSeverity, ToastCorner};
     pub use default_callbacks::StackCheckedPointer;
     pub use text_layout::{TextLayoutOptions, GlyphInstance};
 
     #[cfg(any(feature = "css-parser", feature = "native-style"))]
     pub use css;
 
+    #[cfg(feature = "html-export")]
+    pub use html_export::ExportError;
+
+    #[cfg(feature = "markdown")]
+    pub use markdown::MarkdownOptions;
+
     #[cfg(feature = "logging")]
     pub use log::LevelFilter;
 }
@@ -1,9 +1,15 @@
 use dialogs::msg_box_ok;
 use log::LevelFilter;
-use std::sync::atomic::{Ordering, AtomicBool};
+use std::sync::{Mutex, atomic::{Ordering, AtomicBool}};
+use diagnostics::PanicReport;
 
 pub(crate) static SHOULD_ENABLE_PANIC_HOOK: AtomicBool = AtomicBool::new(false);
 
+lazy_static! {
+    /// The `AppConfig::panic_hook`, global since the panic hook is global, too
+    pub(crate) static ref CUSTOM_PANIC_HOOK: Mutex<Option<fn(&PanicReport)>> = Mutex::new(None);
+}
+
 pub(crate) fn set_up_logging(log_file_path: Option<String>, log_level: LevelFilter) {
 
     use fern::InitError;
@@ -94,6 +100,18 @@ pub(crate) fn set_up_panic_hooks() {
         let thread = thread::current();
         let thread_name = thread.name().unwrap_or("<unnamed thread>");
 
+        // Panics in callbacks are caught and shown in the window, the app keeps running
+        if ::error_overlay::is_catching_callback_panic() {
+            error!("A callback panicked in: {} in thread {}: {}\r\n\r\nBacktrace:\r\n\r\n{}\r\n",
+                location_str.as_ref().map(|s| s.as_str()).unwrap_or("<unknown location>"), thread_name, panic_str, backtrace_str);
+            return;
+        }
+
+        let custom_hook = CUSTOM_PANIC_HOOK.try_lock().ok().and_then(|hook| *hook);
+        if let Some(hook) = custom_hook {
+            hook(&PanicReport::new(panic_str.to_string(), location_str.clone(), thread_name.to_string(), backtrace_str.clone()));
+        }
+
         let error_str = format!(
             "An unexpected panic ocurred, the program has to exit.\r\n\
              Please report this error and attach the log file found in the directory of the executable.\r\n\
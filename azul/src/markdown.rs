@@ -0,0 +1,700 @@
+//! Converting Markdown to a `Dom`, see `Dom::from_markdown()`.
+//!
+//! Supports ATX headings (`# Title`), paragraphs, `*emphasis*` / `**strong**` (also with
+//! underscores), `` `code` ``, fenced and indented code blocks, bullet and numbered lists
+//! (nested by indentation), block quotes, horizontal rules, `[links](url)` and
+//! `![images](name)`. HTML blocks, tables, reference links and setext headings are not
+//! supported, they are rendered as plain text.
+//!
+//! Every node carries a predictable class, so that the document can be styled in CSS:
+//!
+//! | Markdown                  | DOM                                                        |
+//! |---------------------------|------------------------------------------------------------|
+//! | document                  | `div.md-document`                                          |
+//! | `# Heading` ... `######`  | `div.md-h1` ... `div.md-h6`                                |
+//! | paragraph                 | `div.md-p`                                                 |
+//! | text                      | `label.md-text`, `label.md-em`, `label.md-strong`          |
+//! | `` `code` ``              | `label.md-code`                                            |
+//! | code block                | `div.md-pre` (and `.md-lang-rust`, ...) > `label.md-code`  |
+//! | list                      | `div.md-ul` / `div.md-ol` > `div.md-li`                    |
+//! | list item                 | `label.md-li-marker` + `div.md-li-content`                 |
+//! | `> quote`                 | `div.md-blockquote`                                        |
+//! | `---`                     | `div.md-hr`                                                |
+//! | `[text](url)`             | `label.md-link`                                            |
+//! | `![alt](name)`            | `image.md-img`, or `label.md-img-alt` if it isn't resolved |
+//!
+//! Since there is no inline text flow, the text runs of a paragraph are separate labels -
+//! `.md-p { flex-direction: row; flex-wrap: wrap; }` lays them out like a line of text.
+//! The text of a code block is kept verbatim, including its line breaks and indentation.
+//! Items of a list without blank lines between them ("tight" lists) contain the text
+//! runs directly instead of a `div.md-p`.
+
+use {
+    dom::{Dom, NodeData, Callback, On},
+    images::ImageId,
+    traits::Layout,
+    window::CallbackInfo,
+};
+
+/// The target of a link is stored as an ID of the link node, with this prefix,
+/// see `link_target()`
+pub const LINK_TARGET_ID_PREFIX: &str = "md-href:";
+
+/// Options for `Dom::from_markdown()`
+pub struct MarkdownOptions<T: Layout> {
+    /// Attached to every link (`On::LeftMouseUp`), use `link_target()` in the
+    /// callback to get the URL of the clicked link. Default: `None`, links aren't clickable
+    pub on_link_click: Option<Callback<T>>,
+    /// Resolves the source of an image (`![alt](source)`) to an image that was added
+    /// to the `AppResources`. Images that aren't resolved are rendered as their alt text.
+    /// Default: `None`, all images are rendered as their alt text
+    pub resolve_image: Option<fn(&str) -> Option<ImageId>>,
+}
+
+impl<T: Layout> Default for MarkdownOptions<T> {
+    fn default() -> Self {
+        Self {
+            on_link_click: None,
+            resolve_image: None,
+        }
+    }
+}
+
+impl<T: Layout> Clone for MarkdownOptions<T> {
+    fn clone(&self) -> Self {
+        Self {
+            on_link_click: self.on_link_click,
+            resolve_image: self.resolve_image,
+        }
+    }
+}
+
+impl<T: Layout> Copy for MarkdownOptions<T> { }
+
+impl<T: Layout> ::std::fmt::Debug for MarkdownOptions<T> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "MarkdownOptions {{ on_link_click: {:?}, resolve_image: {:?} }}",
+            self.on_link_click, self.resolve_image.map(|f| f as usize))
+    }
+}
+
+impl<T: Layout> Dom<T> {
+    /// Converts a Markdown document to a `div.md-document`, see the `markdown` module
+    /// for the supported syntax and the classes of the nodes
+    pub fn from_markdown(src: &str, options: MarkdownOptions<T>) -> Self {
+        let lines = src.lines().map(|line| line.replace('\t', "    ")).collect::<Vec<String>>();
+        let mut document = Dom::div().with_class("md-document");
+        add_blocks(&lines, &options, false, &mut document);
+        document
+    }
+}
+
+/// Returns the URL of the clicked link, in the `on_link_click` callback of `MarkdownOptions`
+pub fn link_target<'a, 'b, T: Layout>(info: &'b CallbackInfo<'a, T>) -> Option<&'b str> {
+    info.get_node_content(info.hit_dom_node).and_then(link_target_of_node)
+}
+
+fn link_target_of_node<T: Layout>(node: &NodeData<T>) -> Option<&str> {
+    node.ids.iter()
+        .find(|id| id.starts_with(LINK_TARGET_ID_PREFIX))
+        .map(|id| &id[LINK_TARGET_ID_PREFIX.len()..])
+}
+
+// --- blocks
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+/// Number of leading spaces (tabs are replaced by four spaces before parsing)
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_left_matches(' ').len()
+}
+
+/// Returns the opening fence (` ``` ` or `~~~`) of a fenced code block
+fn code_fence(line: &str) -> Option<&str> {
+    let trimmed = line.trim_left();
+    if indentation(line) < 4 && (trimmed.starts_with("```") || trimmed.starts_with("~~~")) {
+        Some(&trimmed[..3])
+    } else {
+        None
+    }
+}
+
+/// Returns the level and the text of an ATX heading
+fn heading(line: &str) -> Option<(usize, &str)> {
+    if indentation(line) >= 4 {
+        return None;
+    }
+    let trimmed = line.trim_left();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    let rest = &trimmed[level..];
+    if level == 0 || level > 6 || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    // Optional closing sequence: "## Title ##"
+    let text = rest.trim();
+    let without_closing = text.trim_right_matches('#');
+    let text = if without_closing.is_empty() || without_closing.ends_with(' ') { without_closing.trim() } else { text };
+    Some((level, text))
+}
+
+fn is_rule(line: &str) -> bool {
+    if indentation(line) >= 4 {
+        return false;
+    }
+    let chars = line.chars().filter(|c| !c.is_whitespace()).collect::<Vec<char>>();
+    chars.len() >= 3 && (chars[0] == '-' || chars[0] == '*' || chars[0] == '_') && chars.iter().all(|c| *c == chars[0])
+}
+
+fn is_quote(line: &str) -> bool {
+    indentation(line) < 4 && line.trim_left().starts_with('>')
+}
+
+/// Strips the `>` (and one space after it) from a line of a block quote
+fn strip_quote(line: &str) -> String {
+    let trimmed = line.trim_left();
+    let rest = &trimmed[1..];
+    if rest.starts_with(' ') { rest[1..].to_string() } else { rest.to_string() }
+}
+
+/// Start of a list item: `- `, `* `, `+ `, `1. ` or `1) `
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct ListMarker {
+    /// `Some(start number)` for numbered lists
+    number: Option<usize>,
+    /// The bullet character or the delimiter after the number
+    delimiter: char,
+    /// Column at which the content of the item starts
+    content_offset: usize,
+}
+
+impl ListMarker {
+    fn is_same_list(&self, other: &ListMarker) -> bool {
+        self.number.is_some() == other.number.is_some() && self.delimiter == other.delimiter
+    }
+}
+
+fn list_marker(line: &str) -> Option<ListMarker> {
+
+    let indent = indentation(line);
+    if indent >= 4 || is_rule(line) {
+        return None;
+    }
+
+    let trimmed = line.trim_left();
+    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+
+    let (number, delimiter, marker_len) = if digits > 0 && digits < 10 {
+        let delimiter = trimmed[digits..].chars().next()?;
+        if delimiter != '.' && delimiter != ')' {
+            return None;
+        }
+        (Some(trimmed[..digits].parse().ok()?), delimiter, digits + 1)
+    } else {
+        let bullet = trimmed.chars().next()?;
+        if bullet != '-' && bullet != '*' && bullet != '+' {
+            return None;
+        }
+        (None, bullet, 1)
+    };
+
+    let rest = &trimmed[marker_len..];
+    if rest.is_empty() || rest.starts_with(' ') {
+        Some(ListMarker { number, delimiter, content_offset: indent + marker_len + 1 })
+    } else {
+        None
+    }
+}
+
+/// Whether the line interrupts a paragraph
+fn starts_block(line: &str) -> bool {
+    code_fence(line).is_some() || heading(line).is_some() || is_rule(line) || is_quote(line) || list_marker(line).is_some()
+}
+
+/// Removes `count` columns of indentation (or less, if the line is indented less)
+fn unindent(line: &str, count: usize) -> String {
+    let indent = indentation(line).min(count);
+    line[indent..].to_string()
+}
+
+/// Parses the block structure of `lines` and appends the blocks to `parent`. In `tight`
+/// list items, paragraphs aren't wrapped in a `div.md-p`.
+fn add_blocks<T: Layout>(lines: &[String], options: &MarkdownOptions<T>, tight: bool, parent: &mut Dom<T>) {
+
+    let mut i = 0;
+
+    while i < lines.len() {
+
+        let line = lines[i].as_str();
+
+        if is_blank(line) {
+            i += 1;
+        } else if let Some(fence) = code_fence(line) {
+            let language = line.trim_left()[3..].trim_matches(|c: char| c == '`' || c == '~' || c.is_whitespace());
+            let fence_indent = indentation(line);
+            let mut code = Vec::new();
+            i += 1;
+            while i < lines.len() && !lines[i].trim_left().starts_with(fence) {
+                code.push(unindent(&lines[i], fence_indent));
+                i += 1;
+            }
+            i += 1; // closing fence
+            parent.add_child(code_block(&code, language));
+        } else if indentation(line) >= 4 {
+            let mut code = Vec::new();
+            while i < lines.len() && (is_blank(&lines[i]) || indentation(&lines[i]) >= 4) {
+                code.push(unindent(&lines[i], 4));
+                i += 1;
+            }
+            while code.last().map(|l| is_blank(l)).unwrap_or(false) {
+                code.pop();
+            }
+            parent.add_child(code_block(&code, ""));
+        } else if let Some((level, text)) = heading(line) {
+            let mut heading = Dom::div().with_class(format!("md-h{}", level));
+            add_inlines(text, options, &mut heading);
+            parent.add_child(heading);
+            i += 1;
+        } else if is_rule(line) {
+            parent.add_child(Dom::div().with_class("md-hr"));
+            i += 1;
+        } else if is_quote(line) {
+            let mut quoted = Vec::new();
+            while i < lines.len() && is_quote(&lines[i]) {
+                quoted.push(strip_quote(&lines[i]));
+                i += 1;
+            }
+            let mut quote = Dom::div().with_class("md-blockquote");
+            add_blocks(&quoted, options, false, &mut quote);
+            parent.add_child(quote);
+        } else if let Some(marker) = list_marker(line) {
+            i = add_list(lines, i, marker, options, parent);
+        } else {
+            let mut text = line.trim().to_string();
+            i += 1;
+            while i < lines.len() && !is_blank(&lines[i]) && !starts_block(&lines[i]) {
+                text.push(' ');
+                text.push_str(lines[i].trim());
+                i += 1;
+            }
+            if tight {
+                add_inlines(&text, options, parent);
+            } else {
+                let mut paragraph = Dom::div().with_class("md-p");
+                add_inlines(&text, options, &mut paragraph);
+                parent.add_child(paragraph);
+            }
+        }
+    }
+}
+
+fn code_block<T: Layout>(lines: &[String], language: &str) -> Dom<T> {
+    let mut pre = Dom::div().with_class("md-pre");
+    if !language.is_empty() {
+        pre.add_class(format!("md-lang-{}", language));
+    }
+    pre.with_child(Dom::label(lines.join("\n")).with_class("md-code"))
+}
+
+/// Parses the list that starts at `lines[start]`, appends it to `parent` and
+/// returns the index of the first line after the list
+fn add_list<T: Layout>(lines: &[String], start: usize, first_marker: ListMarker, options: &MarkdownOptions<T>, parent: &mut Dom<T>) -> usize {
+
+    // Lines of each item, without the marker and the indentation of the item
+    let mut items = Vec::<Vec<String>>::new();
+    let mut is_tight = true;
+    let mut i = start;
+
+    while i < lines.len() {
+
+        let marker = match list_marker(&lines[i]) {
+            Some(marker) if marker.is_same_list(&first_marker) => marker,
+            _ => break,
+        };
+
+        let first_line = &lines[i][marker.content_offset.min(lines[i].len())..];
+        let mut item = vec![first_line.to_string()];
+        i += 1;
+
+        loop {
+            if i >= lines.len() {
+                break;
+            }
+            let line = &lines[i];
+            if is_blank(line) {
+                // A blank line continues the item only if the next line is indented
+                let next = lines[i..].iter().position(|l| !is_blank(l)).map(|p| i + p);
+                match next {
+                    Some(next) if indentation(&lines[next]) >= marker.content_offset => {
+                        is_tight = false;
+                        item.push(String::new());
+                        i += 1;
+                    },
+                    Some(next) if list_marker(&lines[next]).map(|m| m.is_same_list(&first_marker)).unwrap_or(false) => {
+                        is_tight = false;
+                        i = next;
+                        break;
+                    },
+                    _ => break,
+                }
+            } else if indentation(line) >= marker.content_offset {
+                item.push(unindent(line, marker.content_offset));
+                i += 1;
+            } else if !starts_block(line) && !item.last().map(|l| is_blank(l)).unwrap_or(true) {
+                // Lazy continuation of the paragraph of the item
+                item.push(line.trim().to_string());
+                i += 1;
+            } else {
+                break;
+            }
+        }
+
+        items.push(item);
+    }
+
+    let mut list = Dom::div().with_class(if first_marker.number.is_some() { "md-ol" } else { "md-ul" });
+
+    for (item_idx, item_lines) in items.iter().enumerate() {
+        let marker_text = match first_marker.number {
+            Some(number) => format!("{}{}", number + item_idx, first_marker.delimiter),
+            None => "\u{2022}".to_string(),
+        };
+        let mut content = Dom::div().with_class("md-li-content");
+        add_blocks(item_lines, options, is_tight, &mut content);
+        list.add_child(Dom::div().with_class("md-li")
+            .with_child(Dom::label(marker_text).with_class("md-li-marker"))
+            .with_child(content));
+    }
+
+    parent.add_child(list);
+
+    i
+}
+
+// --- inlines
+
+/// Parses emphasis, code spans, links and images in `text` and appends one node per run
+fn add_inlines<T: Layout>(text: &str, options: &MarkdownOptions<T>, parent: &mut Dom<T>) {
+
+    let chars = text.chars().collect::<Vec<char>>();
+    let mut run = String::new();
+    let mut em = false;
+    let mut strong = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+
+        let c = chars[i];
+
+        if c == '\\' && i + 1 < chars.len() && chars[i + 1].is_ascii_punctuation() {
+            run.push(chars[i + 1]);
+            i += 2;
+        } else if c == '`' {
+            let ticks = chars[i..].iter().take_while(|c| **c == '`').count();
+            match find_run(&chars, i + ticks, '`', ticks) {
+                Some(end) => {
+                    flush_run(&mut run, em, strong, parent);
+                    let code = chars[i + ticks..end].iter().collect::<String>();
+                    parent.add_child(Dom::label(code.trim()).with_class("md-code"));
+                    i = end + ticks;
+                },
+                None => {
+                    run.extend(chars[i..i + ticks].iter());
+                    i += ticks;
+                },
+            }
+        } else if c == '!' && i + 1 < chars.len() && chars[i + 1] == '[' && parse_link(&chars, i + 1).is_some() {
+            let (alt, source, end) = parse_link(&chars, i + 1).unwrap();
+            flush_run(&mut run, em, strong, parent);
+            match options.resolve_image.and_then(|resolve| resolve(&source)) {
+                Some(image_id) => parent.add_child(Dom::image(image_id).with_class("md-img")),
+                None => parent.add_child(Dom::label(alt).with_class("md-img-alt")),
+            }
+            i = end;
+        } else if c == '[' && parse_link(&chars, i).is_some() {
+            let (link_text, url, end) = parse_link(&chars, i).unwrap();
+            flush_run(&mut run, em, strong, parent);
+            let mut link = Dom::label(link_text).with_class("md-link").with_id(format!("{}{}", LINK_TARGET_ID_PREFIX, url));
+            if let Some(callback) = options.on_link_click {
+                link.add_callback(On::LeftMouseUp, callback);
+            }
+            parent.add_child(link);
+            i = end;
+        } else if c == '*' || c == '_' {
+            let count = chars[i..].iter().take_while(|d| **d == c).count();
+            let previous = if i == 0 { None } else { Some(chars[i - 1]) };
+            let next = chars.get(i + count).cloned();
+            let is_intraword_underscore = c == '_' &&
+                previous.map(|p| p.is_alphanumeric()).unwrap_or(false) &&
+                next.map(|n| n.is_alphanumeric()).unwrap_or(false);
+
+            // "***" opens / closes both, the strong delimiter is handled first
+            let length = if count >= 2 { 2 } else { 1 };
+            let is_open = if length == 2 { strong } else { em };
+            let can_open = next.map(|n| !n.is_whitespace()).unwrap_or(false) && find_run(&chars, i + length, c, length).is_some();
+
+            if is_intraword_underscore || !(is_open || can_open) {
+                run.extend(chars[i..i + count].iter());
+                i += count;
+            } else {
+                flush_run(&mut run, em, strong, parent);
+                if length == 2 { strong = !strong; } else { em = !em; }
+                i += length;
+            }
+        } else {
+            run.push(c);
+            i += 1;
+        }
+    }
+
+    flush_run(&mut run, em, strong, parent);
+}
+
+/// Appends the text collected so far as a label
+fn flush_run<T: Layout>(run: &mut String, em: bool, strong: bool, parent: &mut Dom<T>) {
+
+    if run.is_empty() {
+        return;
+    }
+
+    let mut label = Dom::label(run.clone());
+    if !em && !strong {
+        label.add_class("md-text");
+    }
+    if em {
+        label.add_class("md-em");
+    }
+    if strong {
+        label.add_class("md-strong");
+    }
+
+    parent.add_child(label);
+    run.clear();
+}
+
+/// Returns the position of the next run of exactly `length` `delimiter` characters
+fn find_run(chars: &[char], start: usize, delimiter: char, length: usize) -> Option<usize> {
+    let mut i = start;
+    while i < chars.len() {
+        if chars[i] == delimiter {
+            let count = chars[i..].iter().take_while(|c| **c == delimiter).count();
+            if count == length || (delimiter != '`' && count > length) {
+                return Some(i);
+            }
+            i += count;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Parses `[text](url "title")` starting at the `[`, returns the text, the URL
+/// and the position after the closing `)`
+fn parse_link(chars: &[char], start: usize) -> Option<(String, String, usize)> {
+
+    let mut depth = 0;
+    let mut text_end = None;
+
+    for (offset, c) in chars[start..].iter().enumerate() {
+        match *c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    text_end = Some(start + offset);
+                    break;
+                }
+            },
+            _ => { },
+        }
+    }
+
+    let text_end = text_end?;
+    if chars.get(text_end + 1) != Some(&'(') {
+        return None;
+    }
+
+    let url_start = text_end + 2;
+    let url_end = url_start + chars[url_start..].iter().position(|c| *c == ')')?;
+
+    // Formatting inside of the link text isn't supported, the markers are removed
+    let text = chars[start + 1..text_end].iter().filter(|c| **c != '*' && **c != '`').collect::<String>();
+    let destination = chars[url_start..url_end].iter().collect::<String>();
+    let url = destination.split_whitespace().next().unwrap_or("").trim_matches(|c| c == '<' || c == '>').to_string();
+
+    Some((text, url, url_end + 1))
+}
+
+#[cfg(test)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+#[cfg(test)]
+fn open_link(_: &mut ::app_state::AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> ::dom::UpdateScreen {
+    ::dom::Redraw
+}
+
+#[cfg(test)]
+fn resolve_test_image(source: &str) -> Option<ImageId> {
+    if source == "logo.png" { Some(ImageId::from_raw(0)) } else { None }
+}
+
+#[test]
+fn test_markdown_document_snapshot() {
+
+    use dom::CallbackRegistry;
+
+    let src = "\
+# Changelog ##
+
+Version **1.2** fixes *many* bugs,
+see [the issue tracker](https://example.com/issues \"Issues\").
+
+## Fixed
+
+- Crash on `resize`
+- Wrong \\*escaped\\* text
+  continued here
+    1. nested
+    2. list
+
+```rust
+fn main() {
+    println!(\"hi\");
+}
+```
+
+> Quote with __strong__ snake_case_word
+
+---
+
+![Logo](logo.png) ![Missing](missing.png)
+";
+
+    let mut registry = CallbackRegistry::new();
+    registry.register_callback("open_link", Callback(open_link));
+
+    let options = MarkdownOptions {
+        on_link_click: Some(Callback(open_link)),
+        resolve_image: Some(resolve_test_image),
+    };
+
+    let dom = Dom::<TestLayout>::from_markdown(src, options);
+
+    let expected = "\
+div .md-document
+    div .md-h1
+        label \"Changelog\" .md-text
+    div .md-p
+        label \"Version \" .md-text
+        label \"1.2\" .md-strong
+        label \" fixes \" .md-text
+        label \"many\" .md-em
+        label \" bugs, see \" .md-text
+        label \"the issue tracker\" #md-href:https://example.com/issues .md-link
+          on Hover(LeftMouseUp) -> open_link
+        label \".\" .md-text
+    div .md-h2
+        label \"Fixed\" .md-text
+    div .md-ul
+        div .md-li
+            label \"\u{2022}\" .md-li-marker
+            div .md-li-content
+                label \"Crash on \" .md-text
+                label \"resize\" .md-code
+        div .md-li
+            label \"\u{2022}\" .md-li-marker
+            div .md-li-content
+                label \"Wrong *escaped* text continued here\" .md-text
+                div .md-ol
+                    div .md-li
+                        label \"1.\" .md-li-marker
+                        div .md-li-content
+                            label \"nested\" .md-text
+                    div .md-li
+                        label \"2.\" .md-li-marker
+                        div .md-li-content
+                            label \"list\" .md-text
+    div .md-lang-rust .md-pre
+        label \"fn main() {\\n    println!(\\\"hi\\\");\\n}\" .md-code
+    div .md-blockquote
+        div .md-p
+            label \"Quote with \" .md-text
+            label \"strong\" .md-strong
+            label \" snake_case_word\" .md-text
+    div .md-hr
+    div .md-p
+        image .md-img
+        label \" \" .md-text
+        label \"Missing\" .md-img-alt
+";
+
+    assert_eq!(dom.serialize_for_snapshot_with_names(&registry), expected);
+}
+
+#[test]
+fn test_markdown_links_have_callbacks_and_targets() {
+
+    use dom::EventFilter;
+
+    let options = MarkdownOptions {
+        on_link_click: Some(Callback(open_link)),
+        .. MarkdownOptions::default()
+    };
+
+    let dom = Dom::<TestLayout>::from_markdown("[one](a.html) and [two](<b.html>)", options);
+
+    let links = dom.arena.node_data.iter().filter(|node| node.has_class("md-link")).collect::<Vec<_>>();
+    assert_eq!(links.len(), 2);
+    assert_eq!(link_target_of_node(links[0]), Some("a.html"));
+    assert_eq!(link_target_of_node(links[1]), Some("b.html"));
+
+    for link in &links {
+        assert_eq!(link.callbacks, vec![(EventFilter::Hover(::dom::HoverEventFilter::LeftMouseUp), Callback(open_link))]);
+    }
+
+    // Without a callback, links are only styled
+    let dom = Dom::<TestLayout>::from_markdown("[one](a.html)", MarkdownOptions::default());
+    assert!(dom.arena.node_data.iter().all(|node| node.callbacks.is_empty()));
+}
+
+#[test]
+fn test_markdown_unmatched_delimiters_are_text() {
+    let dom = Dom::<TestLayout>::from_markdown("2 * 3 = 6, a_b and `tick", MarkdownOptions::default());
+    assert_eq!(dom.serialize_for_snapshot(), "\
+div .md-document
+    div .md-p
+        label \"2 * 3 = 6, a_b and `tick\" .md-text
+");
+}
+
+#[test]
+fn test_markdown_loose_list_and_indented_code() {
+    let dom = Dom::<TestLayout>::from_markdown("1) first\n\n2) second\n\ntext\n\n    let x = 5;\n      indented\n", MarkdownOptions::default());
+    assert_eq!(dom.serialize_for_snapshot(), "\
+div .md-document
+    div .md-ol
+        div .md-li
+            label \"1)\" .md-li-marker
+            div .md-li-content
+                div .md-p
+                    label \"first\" .md-text
+        div .md-li
+            label \"2)\" .md-li-marker
+            div .md-li-content
+                div .md-p
+                    label \"second\" .md-text
+    div .md-p
+        label \"text\" .md-text
+    div .md-pre
+        label \"let x = 5;\\n  indented\" .md-code
+");
+}
@@ -0,0 +1,783 @@
+//! Application menus (File, Edit, ...) with keyboard accelerators, see `FakeWindow::set_menu()`.
+//!
+//! A `MenuBar` is a tree of `Menu`s, separators and `MenuItem`s. Selecting an item calls
+//! its `MenuCallback` on the UI thread, between the events of a frame, like any other
+//! callback. The accelerators of the enabled items (`Ctrl+S`, ...) are dispatched
+//! automatically when the key combination is pressed in the window, no `keymap()`
+//! needed. Items can be enabled, disabled or (un)checked at runtime with
+//! `FakeWindow::update_menu_item()`.
+//!
+//! The menu is set on the `FakeWindow` and not on the `WindowState`: the `WindowState` isn't
+//! generic over the `Layout`, but the `MenuCallback`s of the items are. The `FakeWindow`
+//! holds the other per-window state that depends on `T` (the default callbacks), and the
+//! callbacks can reach it via `app_state.windows`, like the `WindowState`.
+//!
+//! The menu is shown by a `MenuBackend`, if the window has one (`Window::with_menu_backend()`),
+//! for example the global menu bar on macOS or the Win32 menu of a window. Azul doesn't ship
+//! a native backend: the menu APIs of the platforms (Win32, AppKit) aren't dependencies of
+//! this crate and glutin doesn't expose the native menu of the window, so the application
+//! has to provide the backend. By default, the menu is rendered into the DOM: put
+//! `info.window.menu_bar_dom()` at the top of the DOM that `Layout::layout()` returns and
+//! style the `azul-menu-*` classes. In the DOM menu, `Alt` + the mnemonic of a menu opens
+//! it (the letter after the `&` in `"&File"`) and Escape closes it.
+
+use std::fmt;
+use glium::glutin::{Event, WindowEvent, ElementState, KeyboardInput, VirtualKeyCode};
+use {
+    app_state::AppState,
+    dom::{Dom, Callback, NodeData, On, UpdateScreen, Redraw, DontRedraw},
+    id_tree::{NodeId, NodeHierarchy, ArenaNodeData},
+    traits::Layout,
+    window::{CallbackInfo, WindowId},
+};
+
+/// Position of an item in the `MenuBar`: the index of the menu, then the index of the
+/// entry in the menu (separators count), then the index in the submenu, etc.
+pub type MenuItemPath = Vec<usize>;
+
+/// Callback of a `MenuItem`, called when the item is selected
+pub struct MenuCallback<T: Layout>(pub fn(&mut AppState<T>, &mut MenuCallbackInfo) -> UpdateScreen);
+
+// #[derive(Debug, Clone, PartialEq, Hash, Eq)] for MenuCallback<T>
+
+impl<T: Layout> fmt::Debug for MenuCallback<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MenuCallback @ 0x{:x}", self.0 as usize)
+    }
+}
+
+impl<T: Layout> Clone for MenuCallback<T> {
+    fn clone(&self) -> Self {
+        MenuCallback(self.0.clone())
+    }
+}
+
+impl<T: Layout> PartialEq for MenuCallback<T> {
+    fn eq(&self, rhs: &Self) -> bool {
+        self.0 as usize == rhs.0 as usize
+    }
+}
+
+impl<T: Layout> Eq for MenuCallback<T> { }
+
+impl<T: Layout> Copy for MenuCallback<T> { }
+
+/// What a `MenuCallback` gets to know about the selection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MenuCallbackInfo {
+    /// The window whose menu the item belongs to
+    pub window_id: WindowId,
+    /// The selected item
+    pub item_path: MenuItemPath,
+    /// Whether the item was checked when it was selected (the check mark isn't
+    /// toggled automatically, use `FakeWindow::update_menu_item()`)
+    pub checked: bool,
+}
+
+/// A key combination, such as `Ctrl+Shift+S`. All modifiers have to match exactly.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KeyCombo {
+    pub key: VirtualKeyCode,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// The `Super / Windows / Command` key
+    pub logo: bool,
+}
+
+impl KeyCombo {
+
+    /// The key without modifiers
+    pub fn new(key: VirtualKeyCode) -> Self {
+        Self { key, ctrl: false, alt: false, shift: false, logo: false }
+    }
+
+    pub fn ctrl(self) -> Self {
+        Self { ctrl: true, .. self }
+    }
+
+    pub fn alt(self) -> Self {
+        Self { alt: true, .. self }
+    }
+
+    pub fn shift(self) -> Self {
+        Self { shift: true, .. self }
+    }
+
+    pub fn logo(self) -> Self {
+        Self { logo: true, .. self }
+    }
+
+    /// Returns the key combination of a key press
+    pub(crate) fn from_input(input: &KeyboardInput) -> Option<Self> {
+        Some(Self {
+            key: input.virtual_keycode?,
+            ctrl: input.modifiers.ctrl,
+            alt: input.modifiers.alt,
+            shift: input.modifiers.shift,
+            logo: input.modifiers.logo,
+        })
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ctrl { write!(f, "Ctrl+")?; }
+        if self.alt { write!(f, "Alt+")?; }
+        if self.shift { write!(f, "Shift+")?; }
+        if self.logo { write!(f, "Super+")?; }
+        match self.key {
+            VirtualKeyCode::Key0 => write!(f, "0"),
+            VirtualKeyCode::Key1 => write!(f, "1"),
+            VirtualKeyCode::Key2 => write!(f, "2"),
+            VirtualKeyCode::Key3 => write!(f, "3"),
+            VirtualKeyCode::Key4 => write!(f, "4"),
+            VirtualKeyCode::Key5 => write!(f, "5"),
+            VirtualKeyCode::Key6 => write!(f, "6"),
+            VirtualKeyCode::Key7 => write!(f, "7"),
+            VirtualKeyCode::Key8 => write!(f, "8"),
+            VirtualKeyCode::Key9 => write!(f, "9"),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// An item of a `Menu` that can be selected
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuItem<T: Layout> {
+    /// Text of the item, `&` marks the mnemonic (`"&Save"`), `&&` is a literal `&`
+    pub label: String,
+    /// Key combination that selects the item without opening the menu
+    pub accelerator: Option<KeyCombo>,
+    /// Disabled items are greyed out and can't be selected (default: true)
+    pub enabled: bool,
+    /// Whether the item shows a check mark (default: false)
+    pub checked: bool,
+    pub callback: MenuCallback<T>,
+}
+
+impl<T: Layout> MenuItem<T> {
+
+    /// Creates an enabled, unchecked item without an accelerator
+    pub fn new<S: Into<String>>(label: S, callback: MenuCallback<T>) -> Self {
+        Self {
+            label: label.into(),
+            accelerator: None,
+            enabled: true,
+            checked: false,
+            callback,
+        }
+    }
+
+    pub fn with_accelerator(self, accelerator: KeyCombo) -> Self {
+        Self { accelerator: Some(accelerator), .. self }
+    }
+
+    pub fn with_enabled(self, enabled: bool) -> Self {
+        Self { enabled, .. self }
+    }
+
+    pub fn with_checked(self, checked: bool) -> Self {
+        Self { checked, .. self }
+    }
+}
+
+/// One entry of a `Menu`
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuEntry<T: Layout> {
+    Item(MenuItem<T>),
+    Separator,
+    SubMenu(Menu<T>),
+}
+
+/// A menu of the `MenuBar` or a submenu
+#[derive(Debug, Clone, PartialEq)]
+pub struct Menu<T: Layout> {
+    /// Title of the menu, `&` marks the mnemonic (`"&File"`)
+    pub label: String,
+    pub entries: Vec<MenuEntry<T>>,
+}
+
+impl<T: Layout> Menu<T> {
+
+    pub fn new<S: Into<String>>(label: S) -> Self {
+        Self { label: label.into(), entries: Vec::new() }
+    }
+
+    pub fn with_item(mut self, item: MenuItem<T>) -> Self {
+        self.entries.push(MenuEntry::Item(item));
+        self
+    }
+
+    pub fn with_separator(mut self) -> Self {
+        self.entries.push(MenuEntry::Separator);
+        self
+    }
+
+    pub fn with_submenu(mut self, submenu: Menu<T>) -> Self {
+        self.entries.push(MenuEntry::SubMenu(submenu));
+        self
+    }
+
+    /// Returns the (lowercase) character after the `&` in the label
+    pub fn mnemonic(&self) -> Option<char> {
+        parse_label(&self.label).1
+    }
+}
+
+/// The menus of a window, see the module documentation
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuBar<T: Layout> {
+    pub menus: Vec<Menu<T>>,
+}
+
+/// Changes to a `MenuItem` at runtime, fields that are `None` are left as they are
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MenuItemUpdate {
+    pub label: Option<String>,
+    pub enabled: Option<bool>,
+    pub checked: Option<bool>,
+}
+
+impl<T: Layout> MenuBar<T> {
+
+    pub fn new() -> Self {
+        Self { menus: Vec::new() }
+    }
+
+    pub fn with_menu(mut self, menu: Menu<T>) -> Self {
+        self.menus.push(menu);
+        self
+    }
+
+    /// Returns the item at the `path`, `None` if the path doesn't point to an item
+    pub fn get_item(&self, path: &[usize]) -> Option<&MenuItem<T>> {
+        let (first, rest) = path.split_first()?;
+        let mut entries = &self.menus.get(*first)?.entries;
+        for (depth, index) in rest.iter().enumerate() {
+            match entries.get(*index)? {
+                MenuEntry::Item(item) if depth == rest.len() - 1 => return Some(item),
+                MenuEntry::SubMenu(submenu) => entries = &submenu.entries,
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// Returns the item at the `path`, `None` if the path doesn't point to an item
+    pub fn get_item_mut(&mut self, path: &[usize]) -> Option<&mut MenuItem<T>> {
+        let (first, rest) = path.split_first()?;
+        let (last, rest) = rest.split_last()?;
+        let mut entries = &mut self.menus.get_mut(*first)?.entries;
+        for index in rest {
+            entries = match entries.get_mut(*index)? {
+                MenuEntry::SubMenu(submenu) => &mut submenu.entries,
+                _ => return None,
+            };
+        }
+        match entries.get_mut(*last)? {
+            MenuEntry::Item(item) => Some(item),
+            _ => None,
+        }
+    }
+
+    /// Applies the `update` to the item at the `path`, returns `None` if there is no item at the `path`
+    pub fn update_item(&mut self, path: &[usize], update: MenuItemUpdate) -> Option<()> {
+        let item = self.get_item_mut(path)?;
+        if let Some(label) = update.label {
+            item.label = label;
+        }
+        if let Some(enabled) = update.enabled {
+            item.enabled = enabled;
+        }
+        if let Some(checked) = update.checked {
+            item.checked = checked;
+        }
+        Some(())
+    }
+
+    /// Returns the accelerators of all enabled items, in menu order. If two items
+    /// have the same accelerator, the first one is selected.
+    pub fn accelerators(&self) -> Vec<(KeyCombo, MenuItemPath)> {
+        let mut accelerators = Vec::new();
+        for (menu_idx, menu) in self.menus.iter().enumerate() {
+            collect_accelerators(&menu.entries, &mut vec![menu_idx], &mut accelerators);
+        }
+        accelerators
+    }
+
+    /// Returns the enabled item that the key combination selects
+    pub fn find_accelerator(&self, key_combo: &KeyCombo) -> Option<MenuItemPath> {
+        self.accelerators().into_iter().find(|(k, _)| k == key_combo).map(|(_, path)| path)
+    }
+
+    /// Renders the menu bar as a DOM, with the `open_menu` expanded, see the module documentation
+    pub(crate) fn dom(&self, open_menu: Option<usize>) -> Dom<T> {
+        let mut bar = Dom::div().with_class("azul-menu-bar");
+        for (menu_idx, menu) in self.menus.iter().enumerate() {
+            let is_open = open_menu == Some(menu_idx);
+            let mut menu_dom = Dom::div().with_class("azul-menu")
+                .with_child(Dom::label(parse_label(&menu.label).0).with_class("azul-menu-title")
+                    .with_callback(On::LeftMouseUp, Callback(on_menu_title_click)));
+            if is_open {
+                menu_dom.add_class("azul-menu-open");
+                menu_dom.add_child(entries_dom(&menu.entries));
+            }
+            bar.add_child(menu_dom);
+        }
+        bar
+    }
+}
+
+impl<T: Layout> Default for MenuBar<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn collect_accelerators<T: Layout>(entries: &[MenuEntry<T>], path: &mut MenuItemPath, accelerators: &mut Vec<(KeyCombo, MenuItemPath)>) {
+    for (entry_idx, entry) in entries.iter().enumerate() {
+        path.push(entry_idx);
+        match entry {
+            MenuEntry::Item(MenuItem { accelerator: Some(accelerator), enabled: true, .. }) => {
+                accelerators.push((*accelerator, path.clone()));
+            },
+            MenuEntry::SubMenu(submenu) => collect_accelerators(&submenu.entries, path, accelerators),
+            _ => { },
+        }
+        path.pop();
+    }
+}
+
+/// Removes the `&` mnemonic markers from a label, returns the label and the mnemonic
+fn parse_label(label: &str) -> (String, Option<char>) {
+    let mut text = String::with_capacity(label.len());
+    let mut mnemonic = None;
+    let mut chars = label.chars();
+    while let Some(c) = chars.next() {
+        if c != '&' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('&') => text.push('&'),
+            Some(next) => {
+                if mnemonic.is_none() {
+                    mnemonic = next.to_lowercase().next();
+                }
+                text.push(next);
+            },
+            None => { },
+        }
+    }
+    (text, mnemonic)
+}
+
+fn entries_dom<T: Layout>(entries: &[MenuEntry<T>]) -> Dom<T> {
+    let mut entries_dom = Dom::div().with_class("azul-menu-entries");
+    for entry in entries {
+        entries_dom.add_child(match entry {
+            MenuEntry::Item(item) => {
+                let mut item_dom = Dom::div().with_class("azul-menu-item");
+                if item.enabled {
+                    item_dom.add_callback(On::LeftMouseUp, Callback(on_menu_item_click));
+                } else {
+                    item_dom.add_class("azul-menu-item-disabled");
+                }
+                if item.checked {
+                    item_dom.add_class("azul-menu-item-checked");
+                }
+                item_dom.add_child(Dom::label(if item.checked { "\u{2713}" } else { "" }).with_class("azul-menu-item-check"));
+                item_dom.add_child(Dom::label(parse_label(&item.label).0).with_class("azul-menu-item-label"));
+                if let Some(accelerator) = item.accelerator {
+                    item_dom.add_child(Dom::label(accelerator.to_string()).with_class("azul-menu-item-accelerator"));
+                }
+                item_dom
+            },
+            MenuEntry::Separator => Dom::div().with_class("azul-menu-separator"),
+            MenuEntry::SubMenu(submenu) => Dom::div().with_class("azul-submenu")
+                .with_child(Dom::label(parse_label(&submenu.label).0).with_class("azul-submenu-title"))
+                .with_child(entries_dom(&submenu.entries)),
+        });
+    }
+    entries_dom
+}
+
+/// Returns the path of the item that the `azul-menu-item` node of a `MenuBar::dom()` shows
+fn menu_item_path<T: Layout>(node_hierarchy: &NodeHierarchy, node_data: &ArenaNodeData<NodeData<T>>, item_node: NodeId) -> Option<MenuItemPath> {
+    let mut path = Vec::new();
+    let mut node = item_node;
+    loop {
+        // node is an item or a submenu inside of an `azul-menu-entries` div
+        let entries = node_hierarchy[node].parent?;
+        path.push(node_hierarchy.get_index_in_parent(node));
+        let container = node_hierarchy[entries].parent?;
+        if node_data[container].has_class("azul-submenu") {
+            node = container;
+        } else {
+            // The `azul-menu` div, its index in the `azul-menu-bar` is the index of the menu
+            node_hierarchy[container].parent?;
+            path.push(node_hierarchy.get_index_in_parent(container));
+            break;
+        }
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// Opens or closes the menu whose title was clicked in the DOM menu
+fn on_menu_title_click<T: Layout>(app_state: &mut AppState<T>, info: &mut CallbackInfo<T>) -> UpdateScreen {
+    let menu_div = info.target_parent()?;
+    let (menu_idx, _) = info.get_index_in_parent(menu_div)?;
+    let menu = &mut app_state.windows.get_mut(info.window_id)?.menu;
+    menu.open_menu = if menu.open_menu == Some(menu_idx) { None } else { Some(menu_idx) };
+    Redraw
+}
+
+/// Closes the DOM menu and calls the callback of the clicked item
+fn on_menu_item_click<T: Layout>(app_state: &mut AppState<T>, info: &mut CallbackInfo<T>) -> UpdateScreen {
+    let arena = &info.ui_state.dom.arena;
+    let path = menu_item_path(&arena.node_layout, &arena.node_data, info.hit_dom_node)?;
+    let window_id = *info.window_id;
+    let selection = {
+        let menu = &mut app_state.windows.get_mut(&window_id)?.menu;
+        menu.open_menu = None;
+        menu.resolve_selection(path)?
+    };
+    selection.call(app_state, window_id);
+    Redraw
+}
+
+/// A selected item whose callback still has to be called
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MenuSelection<T: Layout> {
+    pub(crate) item_path: MenuItemPath,
+    pub(crate) checked: bool,
+    pub(crate) callback: MenuCallback<T>,
+}
+
+impl<T: Layout> MenuSelection<T> {
+    pub(crate) fn call(self, app_state: &mut AppState<T>, window_id: WindowId) -> UpdateScreen {
+        let mut info = MenuCallbackInfo { window_id, item_path: self.item_path, checked: self.checked };
+        (self.callback.0)(app_state, &mut info)
+    }
+}
+
+/// The native side of the menu of a window, see `Window::with_menu_backend()`
+pub trait MenuBackend<T: Layout> {
+    /// Called with the new menu whenever it is set or one of its items changes
+    /// (`None` if the menu was removed)
+    fn set_menu(&mut self, menu: Option<&MenuBar<T>>);
+    /// Returns the items that the user selected in the native menu since the last call
+    fn take_selections(&mut self) -> Vec<MenuItemPath>;
+    /// Whether the platform dispatches the accelerators itself (like the key equivalents
+    /// of the macOS menu bar), in which case azul doesn't dispatch them a second time
+    fn handles_accelerators(&self) -> bool {
+        false
+    }
+}
+
+/// The menu of a `FakeWindow` and the state of the DOM menu
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct WindowMenu<T: Layout> {
+    pub(crate) menu_bar: Option<MenuBar<T>>,
+    /// Incremented on every change, so that the `MenuBackend` is only updated if necessary
+    pub(crate) generation: usize,
+    /// The menu that is expanded in the DOM menu
+    pub(crate) open_menu: Option<usize>,
+}
+
+impl<T: Layout> Default for WindowMenu<T> {
+    fn default() -> Self {
+        Self { menu_bar: None, generation: 0, open_menu: None }
+    }
+}
+
+impl<T: Layout> WindowMenu<T> {
+
+    pub(crate) fn set_menu(&mut self, menu_bar: Option<MenuBar<T>>) {
+        self.menu_bar = menu_bar;
+        self.open_menu = None;
+        self.generation += 1;
+    }
+
+    pub(crate) fn update_item(&mut self, path: &[usize], update: MenuItemUpdate) -> Option<()> {
+        self.menu_bar.as_mut()?.update_item(path, update)?;
+        self.generation += 1;
+        Some(())
+    }
+
+    /// Returns the callback of the item, `None` if the item doesn't exist or is disabled
+    pub(crate) fn resolve_selection(&self, item_path: MenuItemPath) -> Option<MenuSelection<T>> {
+        let item = self.menu_bar.as_ref()?.get_item(&item_path)?;
+        if !item.enabled {
+            return None;
+        }
+        Some(MenuSelection { checked: item.checked, callback: item.callback, item_path })
+    }
+
+    /// Processes the key presses of a frame: returns the items selected by accelerators
+    /// (unless `dispatch_accelerators` is false) and opens / closes the DOM menu on
+    /// `Alt` + mnemonic / Escape. The bool is whether the DOM menu was opened or closed.
+    pub(crate) fn handle_key_events(&mut self, events: &[Event], dispatch_accelerators: bool) -> (Vec<MenuSelection<T>>, bool) {
+
+        let mut selections = Vec::new();
+        let mut open_menu_changed = false;
+
+        let menu_bar = match &self.menu_bar {
+            Some(menu_bar) => menu_bar,
+            None => return (selections, open_menu_changed),
+        };
+
+        let mut open_menu = self.open_menu;
+
+        for event in events {
+
+            let key_combo = match event {
+                Event::WindowEvent { event: WindowEvent::KeyboardInput { input, .. }, .. } if input.state == ElementState::Pressed => {
+                    match KeyCombo::from_input(input) {
+                        Some(key_combo) => key_combo,
+                        None => continue,
+                    }
+                },
+                _ => continue,
+            };
+
+            if dispatch_accelerators {
+                if let Some(path) = menu_bar.find_accelerator(&key_combo) {
+                    if let Some(selection) = self.resolve_selection(path) {
+                        selections.push(selection);
+                        continue;
+                    }
+                }
+            }
+
+            if key_combo == KeyCombo::new(VirtualKeyCode::Escape) && open_menu.is_some() {
+                open_menu = None;
+                open_menu_changed = true;
+            } else if key_combo.alt && !key_combo.ctrl && !key_combo.logo {
+                let pressed = format!("{:?}", key_combo.key).to_lowercase();
+                let mnemonic_menu = menu_bar.menus.iter().position(|menu| {
+                    menu.mnemonic().map(|m| pressed == m.to_string()).unwrap_or(false)
+                });
+                if let Some(menu_idx) = mnemonic_menu {
+                    open_menu = Some(menu_idx);
+                    open_menu_changed = true;
+                }
+            }
+        }
+
+        self.open_menu = open_menu;
+        (selections, open_menu_changed)
+    }
+}
+
+/// Calls the callbacks of the menu items selected in the events of this frame (native
+/// menu and accelerators) and updates the `MenuBackend` of the window if the menu changed.
+/// Returns whether the window has to be redrawn.
+pub(crate) fn dispatch_menu_events<T: Layout>(
+    app_state: &mut AppState<T>,
+    window_id: &WindowId,
+    events: &[Event],
+    backend: &mut Option<Box<dyn MenuBackend<T>>>,
+    synced_generation: &mut usize)
+-> UpdateScreen
+{
+    let mut should_redraw = DontRedraw;
+
+    let selections = match app_state.windows.get_mut(window_id) {
+        Some(fake_window) => {
+            let menu = &mut fake_window.menu;
+            let dispatch_accelerators = !backend.as_ref().map(|b| b.handles_accelerators()).unwrap_or(false);
+            let (mut selections, open_menu_changed) = menu.handle_key_events(events, dispatch_accelerators);
+            if open_menu_changed {
+                should_redraw = Redraw;
+            }
+            if let Some(backend) = backend.as_mut() {
+                let native_selections = backend.take_selections().into_iter().filter_map(|path| menu.resolve_selection(path));
+                selections.extend(native_selections);
+            }
+            selections
+        },
+        None => return DontRedraw,
+    };
+
+    for selection in selections {
+        if selection.call(app_state, *window_id) == Redraw {
+            should_redraw = Redraw;
+        }
+    }
+
+    // The callbacks may have changed the menu
+    if let (Some(backend), Some(fake_window)) = (backend.as_mut(), app_state.windows.get(window_id)) {
+        if fake_window.menu.generation != *synced_generation {
+            backend.set_menu(fake_window.menu.menu_bar.as_ref());
+            *synced_generation = fake_window.menu.generation;
+        }
+    }
+
+    should_redraw
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+#[cfg(test)]
+fn on_new(_: &mut AppState<TestLayout>, _: &mut MenuCallbackInfo) -> UpdateScreen { Redraw }
+#[cfg(test)]
+fn on_save(_: &mut AppState<TestLayout>, _: &mut MenuCallbackInfo) -> UpdateScreen { Redraw }
+#[cfg(test)]
+fn on_word_wrap(_: &mut AppState<TestLayout>, _: &mut MenuCallbackInfo) -> UpdateScreen { Redraw }
+#[cfg(test)]
+fn on_undo(_: &mut AppState<TestLayout>, _: &mut MenuCallbackInfo) -> UpdateScreen { DontRedraw }
+
+// File (0): New (0), Save (1), --- (2), Export (3) > Word wrap (0)
+// Edit (1): Undo (0, disabled)
+#[cfg(test)]
+fn test_menu_bar() -> MenuBar<TestLayout> {
+    use glium::glutin::VirtualKeyCode::*;
+    MenuBar::new()
+        .with_menu(Menu::new("&File")
+            .with_item(MenuItem::new("&New", MenuCallback(on_new)).with_accelerator(KeyCombo::new(N).ctrl()))
+            .with_item(MenuItem::new("&Save", MenuCallback(on_save)).with_accelerator(KeyCombo::new(S).ctrl()))
+            .with_separator()
+            .with_submenu(Menu::new("&Export")
+                .with_item(MenuItem::new("Word wrap", MenuCallback(on_word_wrap)).with_accelerator(KeyCombo::new(W).alt()).with_checked(true))))
+        .with_menu(Menu::new("&Edit")
+            .with_item(MenuItem::new("&Undo", MenuCallback(on_undo)).with_accelerator(KeyCombo::new(Z).ctrl()).with_enabled(false)))
+}
+
+#[cfg(test)]
+fn key_press(key: VirtualKeyCode, ctrl: bool, alt: bool) -> Event {
+    use glium::glutin::{WindowId, DeviceId, ModifiersState};
+    Event::WindowEvent {
+        window_id: unsafe { WindowId::dummy() },
+        event: WindowEvent::KeyboardInput {
+            device_id: unsafe { DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state: ElementState::Pressed,
+                virtual_keycode: Some(key),
+                modifiers: ModifiersState { ctrl, alt, .. ModifiersState::default() },
+            },
+        },
+    }
+}
+
+/// Native menu that records the menus it was given and replays scripted selections
+#[cfg(test)]
+#[derive(Default)]
+struct MockMenuBackend {
+    menus: Vec<Option<MenuBar<TestLayout>>>,
+    pending_selections: Vec<MenuItemPath>,
+}
+
+#[cfg(test)]
+impl MenuBackend<TestLayout> for MockMenuBackend {
+    fn set_menu(&mut self, menu: Option<&MenuBar<TestLayout>>) {
+        self.menus.push(menu.cloned());
+    }
+    fn take_selections(&mut self) -> Vec<MenuItemPath> {
+        self.pending_selections.drain(..).collect()
+    }
+}
+
+#[test]
+fn test_accelerators_are_registered_for_enabled_items() {
+
+    use glium::glutin::VirtualKeyCode::*;
+
+    let menu_bar = test_menu_bar();
+
+    assert_eq!(menu_bar.accelerators(), vec![
+        (KeyCombo::new(N).ctrl(), vec![0, 0]),
+        (KeyCombo::new(S).ctrl(), vec![0, 1]),
+        (KeyCombo::new(W).alt(), vec![0, 3, 0]),
+    ]);
+    assert_eq!(menu_bar.find_accelerator(&KeyCombo::new(S).ctrl()), Some(vec![0, 1]));
+    assert_eq!(menu_bar.find_accelerator(&KeyCombo::new(S).ctrl().shift()), None);
+    assert_eq!(menu_bar.find_accelerator(&KeyCombo::new(Z).ctrl()), None);
+    assert_eq!(KeyCombo::new(S).ctrl().shift().to_string(), "Ctrl+Shift+S");
+}
+
+#[test]
+fn test_key_presses_and_native_selections_dispatch_callbacks() {
+
+    use glium::glutin::VirtualKeyCode::*;
+
+    let mut menu = WindowMenu::default();
+    menu.set_menu(Some(test_menu_bar()));
+
+    let events = vec![
+        key_press(S, true, false),
+        key_press(Z, true, false), // disabled
+        key_press(W, false, true),
+        key_press(S, false, false), // no modifier
+    ];
+    let (selections, open_menu_changed) = menu.handle_key_events(&events, true);
+    let callbacks = selections.iter().map(|s| (s.item_path.clone(), s.checked, s.callback.0 as usize)).collect::<Vec<_>>();
+    assert_eq!(callbacks, vec![
+        (vec![0, 1], false, on_save as usize),
+        (vec![0, 3, 0], true, on_word_wrap as usize),
+    ]);
+    assert!(!open_menu_changed);
+
+    // A backend that handles accelerators itself doesn't get them dispatched twice
+    let (selections, _) = menu.handle_key_events(&events, false);
+    assert!(selections.is_empty());
+
+    // Selections in the native menu
+    let mut backend = MockMenuBackend::default();
+    backend.set_menu(menu.menu_bar.as_ref());
+    backend.pending_selections = vec![vec![0, 0], vec![1, 0], vec![0, 2]];
+    let selections = backend.take_selections().into_iter().filter_map(|path| menu.resolve_selection(path)).collect::<Vec<_>>();
+    assert_eq!(selections.len(), 1);
+    assert_eq!(selections[0].callback, MenuCallback(on_new));
+    assert_eq!(backend.menus, vec![Some(test_menu_bar())]);
+
+    // Enabling the item at runtime enables its accelerator
+    let generation = menu.generation;
+    assert_eq!(menu.update_item(&[1, 0], MenuItemUpdate { enabled: Some(true), .. MenuItemUpdate::default() }), Some(()));
+    assert_eq!(menu.update_item(&[0, 2], MenuItemUpdate::default()), None);
+    assert_eq!(menu.generation, generation + 1);
+    let (selections, _) = menu.handle_key_events(&[key_press(Z, true, false)], true);
+    assert_eq!(selections[0].callback, MenuCallback(on_undo));
+}
+
+#[test]
+fn test_dom_menu_mnemonics_and_item_paths() {
+
+    use glium::glutin::VirtualKeyCode::*;
+
+    let mut menu = WindowMenu::default();
+    menu.set_menu(Some(test_menu_bar()));
+
+    // Alt+E opens the Edit menu, Escape closes it
+    assert_eq!(menu.handle_key_events(&[key_press(E, false, true)], true).1, true);
+    assert_eq!(menu.open_menu, Some(1));
+    assert_eq!(menu.handle_key_events(&[key_press(Escape, false, false)], true).1, true);
+    assert_eq!(menu.open_menu, None);
+
+    let dom = menu.menu_bar.as_ref().unwrap().dom(Some(0));
+    let snapshot = dom.serialize_for_snapshot();
+    assert!(snapshot.contains("label \"File\" .azul-menu-title"));
+    assert!(snapshot.contains("label \"Ctrl+S\" .azul-menu-item-accelerator"));
+    assert!(snapshot.contains("div .azul-menu-item .azul-menu-item-checked"));
+    assert!(!snapshot.contains("Undo"), "closed menus don't render their items");
+
+    // Every rendered item maps back to its path
+    let arena = &dom.arena;
+    let item_paths = dom.root.descendants(&arena.node_layout)
+        .filter(|node| arena.node_data[*node].has_class("azul-menu-item"))
+        .map(|node| menu_item_path(&arena.node_layout, &arena.node_data, node))
+        .collect::<Vec<_>>();
+    assert_eq!(item_paths, vec![Some(vec![0, 0]), Some(vec![0, 1]), Some(vec![0, 3, 0])]);
+}
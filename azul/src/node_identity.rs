@@ -0,0 +1,281 @@
+//! Matching the nodes of the last frame to the nodes of the new frame.
+//!
+//! `NodeId`s are re-created on every call to `Layout::layout()`, so per-node runtime
+//! state (focus, hover, the nodes selected in the inspector, ...) has to be migrated
+//! to the new DOM. A node of the last frame is the same node as a node of the new
+//! frame if (checked in this order):
+//!
+//! 1. both have the same explicit key (`Dom::with_key`), if the key is unique in both DOMs
+//! 2. both have the same ID string (`Dom::with_id`), if the ID is unique in both DOMs
+//! 3. their parents are the same node, they have the same `DomHash` and the same index
+//!    among the not yet matched siblings with that hash
+//!
+//! The roots of the two DOMs are always the same node (unless a key or ID says otherwise).
+//! Without keys, inserting a node in front of identical siblings (same type, classes,
+//! callbacks, etc.) shifts the state to the next sibling, since nothing distinguishes the
+//! siblings - use `with_key` for lists of identical rows.
+
+use std::collections::{BTreeMap, VecDeque};
+use {
+    dom::{Dom, DomHash},
+    id_tree::NodeId,
+    traits::Layout,
+};
+
+/// Maps the `NodeId`s of the last frame to the `NodeId`s of the new frame,
+/// returned by `resolve_node_identities`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NodeIdMapping {
+    old_to_new: Vec<Option<NodeId>>,
+    new_to_old: Vec<Option<NodeId>>,
+}
+
+impl NodeIdMapping {
+
+    fn new(old_len: usize, new_len: usize) -> Self {
+        Self {
+            old_to_new: vec![None; old_len],
+            new_to_old: vec![None; new_len],
+        }
+    }
+
+    /// Returns the node of the new frame that the `old` node of the last frame became,
+    /// `None` if the node was removed
+    #[inline]
+    pub(crate) fn get(&self, old: NodeId) -> Option<NodeId> {
+        self.old_to_new.get(old.index()).and_then(|new| *new)
+    }
+
+    /// Returns the node of the last frame that the `new` node was, `None` if the node was inserted
+    #[inline]
+    pub(crate) fn get_old(&self, new: NodeId) -> Option<NodeId> {
+        self.new_to_old.get(new.index()).and_then(|old| *old)
+    }
+
+    fn link(&mut self, old: NodeId, new: NodeId) {
+        self.old_to_new[old.index()] = Some(new);
+        self.new_to_old[new.index()] = Some(old);
+    }
+
+    fn is_old_matched(&self, old: NodeId) -> bool {
+        self.old_to_new[old.index()].is_some()
+    }
+
+    fn is_new_matched(&self, new: NodeId) -> bool {
+        self.new_to_old[new.index()].is_some()
+    }
+}
+
+/// Matches the nodes of the `old` DOM to the nodes of the `new` DOM, see the module documentation
+pub(crate) fn resolve_node_identities<T: Layout>(old: &Dom<T>, new: &Dom<T>) -> NodeIdMapping {
+
+    let mut mapping = NodeIdMapping::new(old.len(), new.len());
+
+    if old.len() == 0 || new.len() == 0 {
+        return mapping;
+    }
+
+    // 1. Explicit keys
+    let mut keys = BTreeMap::<u64, (Vec<NodeId>, Vec<NodeId>)>::new();
+    for (node_idx, node) in old.arena.node_data.iter().enumerate() {
+        if let Some(key) = node.key {
+            keys.entry(key).or_insert_with(Default::default).0.push(NodeId::new(node_idx));
+        }
+    }
+    for (node_idx, node) in new.arena.node_data.iter().enumerate() {
+        if let Some(key) = node.key {
+            keys.entry(key).or_insert_with(Default::default).1.push(NodeId::new(node_idx));
+        }
+    }
+    for (old_nodes, new_nodes) in keys.values() {
+        if old_nodes.len() == 1 && new_nodes.len() == 1 {
+            mapping.link(old_nodes[0], new_nodes[0]);
+        }
+    }
+
+    // 2. ID strings
+    let mut ids = BTreeMap::<&str, (Vec<NodeId>, Vec<NodeId>)>::new();
+    for (node_idx, node) in old.arena.node_data.iter().enumerate() {
+        for id in &node.ids {
+            ids.entry(id.as_str()).or_insert_with(Default::default).0.push(NodeId::new(node_idx));
+        }
+    }
+    for (node_idx, node) in new.arena.node_data.iter().enumerate() {
+        for id in &node.ids {
+            ids.entry(id.as_str()).or_insert_with(Default::default).1.push(NodeId::new(node_idx));
+        }
+    }
+    for (old_nodes, new_nodes) in ids.values() {
+        if old_nodes.len() == 1 && new_nodes.len() == 1 &&
+           !mapping.is_old_matched(old_nodes[0]) && !mapping.is_new_matched(new_nodes[0]) {
+            mapping.link(old_nodes[0], new_nodes[0]);
+        }
+    }
+
+    // 3. Structure: parents are visited before their children, so the children of a
+    // matched parent are matched before they are visited themselves
+    if !mapping.is_old_matched(old.root) && !mapping.is_new_matched(new.root) {
+        mapping.link(old.root, new.root);
+    }
+
+    for new_parent in new.root.descendants(&new.arena.node_layout) {
+
+        let old_parent = match mapping.get_old(new_parent) {
+            Some(old_parent) => old_parent,
+            None => continue,
+        };
+
+        let mut candidates = BTreeMap::<DomHash, VecDeque<NodeId>>::new();
+        for old_child in old_parent.children(&old.arena.node_layout) {
+            if !mapping.is_old_matched(old_child) {
+                let hash = old.arena.node_data[old_child].calculate_node_data_hash();
+                candidates.entry(hash).or_insert_with(VecDeque::new).push_back(old_child);
+            }
+        }
+
+        if candidates.is_empty() {
+            continue;
+        }
+
+        for new_child in new_parent.children(&new.arena.node_layout) {
+            if mapping.is_new_matched(new_child) {
+                continue;
+            }
+            let hash = new.arena.node_data[new_child].calculate_node_data_hash();
+            if let Some(old_child) = candidates.get_mut(&hash).and_then(|c| c.pop_front()) {
+                mapping.link(old_child, new_child);
+            }
+        }
+    }
+
+    mapping
+}
+
+#[cfg(test)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+/// Row with a label inside, the label is the node that has the state
+#[cfg(test)]
+fn row(text: &str) -> Dom<TestLayout> {
+    Dom::div().with_class("row").with_child(Dom::label(text))
+}
+
+#[cfg(test)]
+fn list(rows: Vec<Dom<TestLayout>>) -> Dom<TestLayout> {
+    let mut list = Dom::div().with_class("list");
+    for row in rows {
+        list.add_child(row);
+    }
+    list
+}
+
+/// Returns the label of the n-th row of a `list`
+#[cfg(test)]
+fn label_of_row(dom: &Dom<TestLayout>, row_idx: usize) -> NodeId {
+    let row = dom.root.children(&dom.arena.node_layout).nth(row_idx).unwrap();
+    row.children(&dom.arena.node_layout).next().unwrap()
+}
+
+#[test]
+fn test_insertion_between_identical_keyed_rows() {
+
+    let old = list(vec![row("x").with_key(1), row("x").with_key(2), row("x").with_key(3)]);
+    let new = list(vec![row("x").with_key(1), row("x").with_key(4), row("x").with_key(2), row("x").with_key(3)]);
+
+    let mapping = resolve_node_identities(&old, &new);
+
+    assert_eq!(mapping.get(old.root), Some(new.root));
+    assert_eq!(mapping.get(label_of_row(&old, 0)), Some(label_of_row(&new, 0)));
+    assert_eq!(mapping.get(label_of_row(&old, 1)), Some(label_of_row(&new, 2)));
+    assert_eq!(mapping.get(label_of_row(&old, 2)), Some(label_of_row(&new, 3)));
+    assert_eq!(mapping.get_old(label_of_row(&new, 1)), None);
+}
+
+#[test]
+fn test_insertion_between_rows_with_different_content() {
+
+    let old = list(vec![row("a"), row("b"), row("c")]);
+    let new = list(vec![row("a"), row("new"), row("b"), row("c")]);
+
+    let mapping = resolve_node_identities(&old, &new);
+
+    assert_eq!(mapping.get(label_of_row(&old, 0)), Some(label_of_row(&new, 0)));
+    assert_eq!(mapping.get(label_of_row(&old, 1)), Some(label_of_row(&new, 2)));
+    assert_eq!(mapping.get(label_of_row(&old, 2)), Some(label_of_row(&new, 3)));
+    assert_eq!(mapping.get_old(label_of_row(&new, 1)), None);
+}
+
+#[test]
+fn test_identical_rows_without_keys_are_matched_by_index() {
+
+    let old = list(vec![row("x"), row("x")]);
+    let new = list(vec![row("x"), row("x"), row("x")]);
+
+    let mapping = resolve_node_identities(&old, &new);
+
+    assert_eq!(mapping.get(label_of_row(&old, 0)), Some(label_of_row(&new, 0)));
+    assert_eq!(mapping.get(label_of_row(&old, 1)), Some(label_of_row(&new, 1)));
+    assert_eq!(mapping.get_old(label_of_row(&new, 2)), None);
+}
+
+#[test]
+fn test_removed_nodes_are_not_mapped() {
+
+    let old = list(vec![row("a"), row("b"), row("c")]);
+    let new = list(vec![row("a"), row("c")]);
+
+    let mapping = resolve_node_identities(&old, &new);
+
+    assert_eq!(mapping.get(label_of_row(&old, 0)), Some(label_of_row(&new, 0)));
+    assert_eq!(mapping.get(label_of_row(&old, 1)), None);
+    assert_eq!(mapping.get(label_of_row(&old, 2)), Some(label_of_row(&new, 1)));
+
+    // Everything is removed
+    let mapping = resolve_node_identities(&old, &Dom::new(::dom::NodeType::Label("other".into())));
+    assert_eq!(mapping.get(label_of_row(&old, 0)), None);
+}
+
+#[test]
+fn test_nodes_with_ids_are_matched_across_parents() {
+
+    let old = list(vec![row("a"), row("b").with_child(Dom::div().with_id("moved"))]);
+    let new = list(vec![row("a").with_child(Dom::div().with_id("moved")), row("b")]);
+
+    let mapping = resolve_node_identities(&old, &new);
+
+    let old_moved = old.root.descendants(&old.arena.node_layout).find(|n| old.arena.node_data[*n].has_id("moved")).unwrap();
+    let new_moved = new.root.descendants(&new.arena.node_layout).find(|n| new.arena.node_data[*n].has_id("moved")).unwrap();
+    assert_eq!(mapping.get(old_moved), Some(new_moved));
+}
+
+#[test]
+fn test_focus_follows_the_keyed_row() {
+
+    use window_state::WindowState;
+    use focus::FocusTarget;
+
+    let old = list(vec![row("x").with_key(1), row("x").with_key(2)]);
+    let new = list(vec![row("x").with_key(3), row("x").with_key(1), row("x").with_key(2)]);
+    let mapping = resolve_node_identities(&old, &new);
+
+    let mut state = WindowState::default();
+    state.focused_node = Some(label_of_row(&old, 1));
+    state.pending_focus_target = Some(FocusTarget::Id(label_of_row(&old, 0)));
+    state.migrate_node_ids(&mapping);
+
+    assert_eq!(state.focused_node, Some(label_of_row(&new, 2)));
+    assert_eq!(state.pending_focus_target, Some(FocusTarget::Id(label_of_row(&new, 1))));
+
+    // The focused row is removed
+    let removed = list(vec![row("x").with_key(1)]);
+    state.migrate_node_ids(&resolve_node_identities(&new, &removed));
+    assert_eq!(state.focused_node, None);
+    assert_eq!(state.pending_focus_target, Some(FocusTarget::Id(label_of_row(&removed, 0))));
+}
@@ -0,0 +1,428 @@
+//! Per-frame timing and statistics, see `WindowState::last_frame_stats()`.
+//!
+//! The measurements are only taken if the `profiling` feature is enabled.
+//! Without the feature, the `FrameProfiler` is a zero-sized type and
+//! all of its methods are empty, so the instrumentation compiles away.
+
+use std::{
+    fmt,
+    time::Duration,
+    collections::VecDeque,
+};
+#[cfg(feature = "profiling")]
+use std::time::Instant;
+use window_state::WindowState;
+
+/// How many frames the `FrameStatsHistory` keeps around
+pub const FRAME_STATS_HISTORY_LEN: usize = 120;
+
+const PHASE_COUNT: usize = 7;
+
+/// The phases that a frame goes through, in the order in which they run
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FramePhase {
+    /// The user-defined `Layout::layout()` function
+    Layout,
+    /// Converting the `Dom` into a `UiState` (tags, callbacks)
+    IntoUiState,
+    /// Matching the CSS rules against the DOM (the cascade)
+    Styling,
+    /// Solving the flexbox layout and the text layout
+    LayoutSolving,
+    /// Building the display list, without the time spent in
+    /// `LayoutSolving` and `GlCallbacks`
+    DisplayListBuild,
+    /// The user-defined `GlTextureCallback`s
+    GlCallbacks,
+    /// Submitting the display list to webrender and drawing the frame on the GPU
+    Render,
+}
+
+impl FramePhase {
+    /// All phases, in the order in which they run
+    pub const ALL: [FramePhase; PHASE_COUNT] = [
+        FramePhase::Layout,
+        FramePhase::IntoUiState,
+        FramePhase::Styling,
+        FramePhase::LayoutSolving,
+        FramePhase::DisplayListBuild,
+        FramePhase::GlCallbacks,
+        FramePhase::Render,
+    ];
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Timing and statistics of one frame
+#[derive(Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FrameStats {
+    phases: [Duration; PHASE_COUNT],
+    /// Wall-clock time from the start of the `layout()` call until the frame was drawn
+    pub total: Duration,
+    /// Time from the start of the previous frame to the start of this frame,
+    /// zero for the first frame of the window
+    pub since_last_frame: Duration,
+    /// Number of nodes in the DOM (without the nodes of iframes)
+    pub node_count: usize,
+    /// Number of hit-testing tags that were handed out during this frame
+    pub tags_created: usize,
+    /// Number of nodes that were laid out - zero if the display list could
+    /// be re-used from the last frame
+    pub relayouted_nodes: usize,
+    /// Number of rectangles that were pushed into the display list
+    pub display_rectangles: usize,
+}
+
+impl FrameStats {
+    /// Returns how long the given phase took
+    pub fn phase(&self, phase: FramePhase) -> Duration {
+        self.phases[phase.index()]
+    }
+
+    /// Sets how long the given phase took - for injecting fake stats in
+    /// tests, for example to check what the frame HUD shows for them
+    pub fn with_phase(mut self, phase: FramePhase, duration: Duration) -> Self {
+        self.phases[phase.index()] = duration;
+        self
+    }
+
+    /// Returns the sum of all phases - the difference to `total` is the time
+    /// that was spent between the phases
+    pub fn phase_sum(&self) -> Duration {
+        self.phases.iter().fold(Duration::from_secs(0), |sum, d| sum + *d)
+    }
+}
+
+impl fmt::Debug for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = f.debug_struct("FrameStats");
+        for phase in FramePhase::ALL.iter() {
+            s.field(&format!("{:?}", phase), &self.phase(*phase));
+        }
+        s.field("total", &self.total)
+         .field("since_last_frame", &self.since_last_frame)
+         .field("node_count", &self.node_count)
+         .field("tags_created", &self.tags_created)
+         .field("relayouted_nodes", &self.relayouted_nodes)
+         .field("display_rectangles", &self.display_rectangles)
+         .finish()
+    }
+}
+
+/// The statistics of the last `FRAME_STATS_HISTORY_LEN` frames, oldest frame first
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct FrameStatsHistory {
+    frames: VecDeque<FrameStats>,
+}
+
+impl FrameStatsHistory {
+
+    /// Returns the stats of the last frame that was drawn
+    pub fn last(&self) -> Option<&FrameStats> {
+        self.frames.back()
+    }
+
+    /// Iterates over the stored frames, oldest frame first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &FrameStats> {
+        self.frames.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, stats: FrameStats) {
+        if self.frames.len() == FRAME_STATS_HISTORY_LEN {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(stats);
+    }
+}
+
+impl ::std::iter::FromIterator<FrameStats> for FrameStatsHistory {
+    /// Builds a history from the given frames (oldest frame first), only
+    /// the last `FRAME_STATS_HISTORY_LEN` frames are kept
+    fn from_iter<I: IntoIterator<Item = FrameStats>>(iter: I) -> Self {
+        let mut history = Self::default();
+        for stats in iter {
+            history.push(stats);
+        }
+        history
+    }
+}
+
+/// Start time of a phase, returned by `FrameProfiler::start()`
+#[cfg(feature = "profiling")]
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct PhaseStart(Instant);
+#[cfg(not(feature = "profiling"))]
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct PhaseStart;
+
+/// Start of a section that shouldn't show up in the stats, returned by
+/// `FrameProfiler::begin_untracked()`
+#[cfg(feature = "profiling")]
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct UntrackedStart {
+    start: Instant,
+    relayouted_nodes: usize,
+    display_rectangles: usize,
+}
+#[cfg(not(feature = "profiling"))]
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct UntrackedStart;
+
+/// Collects the `FrameStats` of the frame that is currently being built
+#[cfg(feature = "profiling")]
+#[derive(Debug, Default, Clone)]
+pub(crate) struct FrameProfiler {
+    frame_start: Option<Instant>,
+    last_frame_start: Option<Instant>,
+    /// Time spent in untracked sections (the frame HUD) during the current frame
+    untracked: Duration,
+    current: FrameStats,
+}
+
+#[cfg(feature = "profiling")]
+impl FrameProfiler {
+
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a new frame, discarding the measurements of an unfinished frame
+    #[inline]
+    pub(crate) fn begin_frame(&mut self) {
+        let now = Instant::now();
+        self.current = FrameStats::default();
+        self.current.since_last_frame = self.last_frame_start.map(|last| now - last).unwrap_or_default();
+        self.frame_start = Some(now);
+        self.last_frame_start = Some(now);
+        self.untracked = Duration::from_secs(0);
+    }
+
+    #[inline]
+    pub(crate) fn start() -> PhaseStart {
+        PhaseStart(Instant::now())
+    }
+
+    /// Adds the time since `start` to the given phase
+    #[inline]
+    pub(crate) fn record(&mut self, phase: FramePhase, start: PhaseStart) {
+        self.current.phases[phase.index()] += start.0.elapsed();
+    }
+
+    /// Same as `record`, but subtracts the time that was spent in the `nested`
+    /// phases since `start` - necessary for phases that contain other phases
+    #[inline]
+    pub(crate) fn record_excluding(&mut self, phase: FramePhase, start: PhaseStart, nested: &[(FramePhase, Duration)]) {
+        let nested_time = nested.iter()
+            .map(|(p, before)| self.current.phases[p.index()].checked_sub(*before).unwrap_or_default())
+            .fold(Duration::from_secs(0), |sum, d| sum + d);
+        let elapsed = start.0.elapsed().checked_sub(nested_time).unwrap_or_default();
+        self.current.phases[phase.index()] += elapsed;
+    }
+
+    /// Returns the time recorded for the phase so far, used as the
+    /// "before" value for `record_excluding`
+    #[inline]
+    pub(crate) fn phase_snapshot(&self, phase: FramePhase) -> (FramePhase, Duration) {
+        (phase, self.current.phases[phase.index()])
+    }
+
+    /// Starts a section whose time and counters (relayouted nodes, display rectangles)
+    /// are left out of the stats, so that drawing the frame HUD doesn't perturb the
+    /// numbers that it shows
+    #[inline]
+    pub(crate) fn begin_untracked(&self) -> UntrackedStart {
+        UntrackedStart {
+            start: Instant::now(),
+            relayouted_nodes: self.current.relayouted_nodes,
+            display_rectangles: self.current.display_rectangles,
+        }
+    }
+
+    /// Ends a section started with `begin_untracked()`: resets the counters and
+    /// removes the time of the section from the `total` of the frame
+    #[inline]
+    pub(crate) fn end_untracked(&mut self, start: UntrackedStart) {
+        self.untracked += start.start.elapsed();
+        self.current.relayouted_nodes = start.relayouted_nodes;
+        self.current.display_rectangles = start.display_rectangles;
+    }
+
+    /// Returns the untracked time so far, used as the "before" value for `subtract_untracked`
+    #[inline]
+    pub(crate) fn untracked_snapshot(&self) -> Duration {
+        self.untracked
+    }
+
+    /// Removes the untracked time since `before` from the phase, necessary
+    /// for phases that contain untracked sections
+    #[inline]
+    pub(crate) fn subtract_untracked(&mut self, phase: FramePhase, before: Duration) {
+        let untracked = self.untracked.checked_sub(before).unwrap_or_default();
+        let recorded = &mut self.current.phases[phase.index()];
+        *recorded = recorded.checked_sub(untracked).unwrap_or_default();
+    }
+
+    #[inline]
+    pub(crate) fn set_node_count(&mut self, node_count: usize) {
+        self.current.node_count = node_count;
+    }
+
+    #[inline]
+    pub(crate) fn set_tags_created(&mut self, tags_created: usize) {
+        self.current.tags_created = tags_created;
+    }
+
+    #[inline]
+    pub(crate) fn add_relayouted_nodes(&mut self, nodes: usize) {
+        self.current.relayouted_nodes += nodes;
+    }
+
+    #[inline]
+    pub(crate) fn add_display_rectangles(&mut self, rectangles: usize) {
+        self.current.display_rectangles += rectangles;
+    }
+
+    /// Finishes the current frame and stores its stats in the `WindowState`
+    pub(crate) fn end_frame(&mut self, window_state: &mut WindowState) {
+        if let Some(stats) = self.finish_frame() {
+            ::diagnostics::record_frame_stats(stats);
+            window_state.frame_stats.push(stats);
+        }
+    }
+
+    fn finish_frame(&mut self) -> Option<FrameStats> {
+        let frame_start = self.frame_start.take()?;
+        self.current.total = frame_start.elapsed().checked_sub(self.untracked).unwrap_or_default();
+        Some(self.current)
+    }
+}
+
+/// Zero-sized stand-in for the `FrameProfiler` if the `profiling` feature is disabled
+#[cfg(not(feature = "profiling"))]
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct FrameProfiler;
+
+#[cfg(not(feature = "profiling"))]
+impl FrameProfiler {
+    pub(crate) fn new() -> Self { FrameProfiler }
+    #[inline(always)]
+    pub(crate) fn begin_frame(&mut self) { }
+    #[inline(always)]
+    pub(crate) fn start() -> PhaseStart { PhaseStart }
+    #[inline(always)]
+    pub(crate) fn record(&mut self, _: FramePhase, _: PhaseStart) { }
+    #[inline(always)]
+    pub(crate) fn record_excluding(&mut self, _: FramePhase, _: PhaseStart, _: &[(FramePhase, Duration)]) { }
+    #[inline(always)]
+    pub(crate) fn phase_snapshot(&self, phase: FramePhase) -> (FramePhase, Duration) { (phase, Duration::from_secs(0)) }
+    #[inline(always)]
+    pub(crate) fn begin_untracked(&self) -> UntrackedStart { UntrackedStart }
+    #[inline(always)]
+    pub(crate) fn end_untracked(&mut self, _: UntrackedStart) { }
+    #[inline(always)]
+    pub(crate) fn untracked_snapshot(&self) -> Duration { Duration::from_secs(0) }
+    #[inline(always)]
+    pub(crate) fn subtract_untracked(&mut self, _: FramePhase, _: Duration) { }
+    #[inline(always)]
+    pub(crate) fn set_node_count(&mut self, _: usize) { }
+    #[inline(always)]
+    pub(crate) fn set_tags_created(&mut self, _: usize) { }
+    #[inline(always)]
+    pub(crate) fn add_relayouted_nodes(&mut self, _: usize) { }
+    #[inline(always)]
+    pub(crate) fn add_display_rectangles(&mut self, _: usize) { }
+    #[inline(always)]
+    pub(crate) fn end_frame(&mut self, _: &mut WindowState) { }
+}
+
+#[cfg(feature = "profiling")]
+#[test]
+fn test_frame_phases_sum_to_frame_total() {
+
+    use std::thread::sleep;
+
+    let mut profiler = FrameProfiler::new();
+
+    profiler.begin_frame();
+
+    let start = FrameProfiler::start();
+    sleep(Duration::from_millis(5));
+    profiler.record(FramePhase::Layout, start);
+
+    // Display list build with a nested GL callback: the callback time
+    // must not be counted twice
+    let display_list_start = FrameProfiler::start();
+    let gl_before = profiler.phase_snapshot(FramePhase::GlCallbacks);
+    sleep(Duration::from_millis(5));
+    let gl_start = FrameProfiler::start();
+    sleep(Duration::from_millis(10));
+    profiler.record(FramePhase::GlCallbacks, gl_start);
+    profiler.record_excluding(FramePhase::DisplayListBuild, display_list_start, &[gl_before]);
+
+    let stats = profiler.finish_frame().unwrap();
+    assert!(stats.phase(FramePhase::Layout) >= Duration::from_millis(5));
+    assert!(stats.phase(FramePhase::GlCallbacks) >= Duration::from_millis(10));
+    assert!(stats.phase(FramePhase::DisplayListBuild) >= Duration::from_millis(5));
+    assert!(stats.phase(FramePhase::DisplayListBuild) < Duration::from_millis(10));
+    assert!(stats.phase_sum() <= stats.total);
+    assert!(stats.total - stats.phase_sum() < Duration::from_millis(5));
+}
+
+#[cfg(not(feature = "profiling"))]
+#[test]
+fn test_frame_profiler_compiles_away_without_feature() {
+    use std::mem::size_of;
+    assert_eq!(size_of::<FrameProfiler>(), 0);
+    assert_eq!(size_of::<PhaseStart>(), 0);
+}
+
+#[test]
+fn test_frame_stats_history_is_bounded() {
+    let mut history = FrameStatsHistory::default();
+    for i in 0..(FRAME_STATS_HISTORY_LEN + 10) {
+        history.push(FrameStats { node_count: i, .. FrameStats::default() });
+    }
+    assert_eq!(history.len(), FRAME_STATS_HISTORY_LEN);
+    assert_eq!(history.iter().next().unwrap().node_count, 10);
+    assert_eq!(history.last().unwrap().node_count, FRAME_STATS_HISTORY_LEN + 9);
+}
+
+#[cfg(feature = "profiling")]
+#[test]
+fn test_untracked_section_is_left_out_of_frame_stats() {
+
+    use std::thread::sleep;
+
+    let mut profiler = FrameProfiler::new();
+
+    profiler.begin_frame();
+
+    let display_list_start = FrameProfiler::start();
+    let untracked_before = profiler.untracked_snapshot();
+    profiler.add_display_rectangles(3);
+
+    let untracked = profiler.begin_untracked();
+    sleep(Duration::from_millis(20));
+    profiler.add_display_rectangles(50);
+    profiler.add_relayouted_nodes(10);
+    profiler.end_untracked(untracked);
+
+    profiler.record(FramePhase::DisplayListBuild, display_list_start);
+    profiler.subtract_untracked(FramePhase::DisplayListBuild, untracked_before);
+
+    let stats = profiler.finish_frame().unwrap();
+    assert_eq!(stats.display_rectangles, 3);
+    assert_eq!(stats.relayouted_nodes, 0);
+    assert!(stats.phase(FramePhase::DisplayListBuild) < Duration::from_millis(20));
+    assert!(stats.total < Duration::from_millis(20));
+}
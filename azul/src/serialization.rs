@@ -0,0 +1,347 @@
+//! Serializing the structure of a `Dom` with serde, for example to attach the UI
+//! tree of a released app to a bug report and to look at it in a viewer.
+//!
+//! A `Dom` is converted into a tree of `SerializedNode`s, which implement `Serialize`
+//! and `Deserialize`. Callbacks can't be serialized, so they are stored as the name they
+//! are registered under in a `CallbackRegistry` (or `null` if they aren't registered).
+//! When the `Dom` is reconstructed, the names are looked up in the registry of the
+//! viewer - callbacks that can't be found are left out and reported as a
+//! `DeserializeWarning`.
+//!
+//! What isn't restored:
+//!
+//! - `TextId`s and `ImageId`s are stored as opaque numbers, they only refer to the same
+//!   text / image in the resources of the app that serialized the DOM
+//! - `GlTexture` and `IFrame` nodes need a pointer to the data of the app, so they are
+//!   replaced by divs (the name of their callback is part of the warning)
+//! - default callbacks (of widgets) are registered at runtime and are left out
+
+use serde::{Serialize, Serializer};
+use azul_css::CssProperty;
+use {
+    dom::{Dom, NodeData, NodeType, CallbackRegistry, EventFilter, TabIndex},
+    id_tree::NodeId,
+    images::ImageId,
+    text_cache::TextId,
+    traits::Layout,
+    diagnostics::{AzulError, report},
+};
+
+/// Serializable form of one node of a `Dom`, together with its children
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SerializedNode {
+    pub node_type: SerializedNodeType,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub classes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tab_index: Option<TabIndex>,
+    #[serde(default)]
+    pub draggable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<(String, String)>,
+    #[serde(default)]
+    pub is_synthetic_root: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub callbacks: Vec<SerializedCallback>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub css_overrides: Vec<SerializedCssOverride>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<SerializedNode>,
+}
+
+/// Serializable form of a `NodeType`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SerializedNodeType {
+    Div,
+    Label(String),
+    /// The raw `TextId`
+    Text(usize),
+    /// The raw `ImageId`
+    Image(usize),
+    /// The registered name of the `GlTextureCallback`
+    GlTexture(Option<String>),
+    /// The registered name of the `IFrameCallback`
+    IFrame(Option<String>),
+}
+
+/// A callback, stored as the name it is registered under
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SerializedCallback {
+    pub event: EventFilter,
+    /// `None` if the callback isn't registered in the `CallbackRegistry`
+    pub name: Option<String>,
+}
+
+/// A dynamic CSS override, see `NodeData::dynamic_css_overrides`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SerializedCssOverride {
+    pub id: String,
+    pub property: CssProperty,
+}
+
+/// Parts of a `SerializedNode` that couldn't be restored by `Dom::from_serialized`.
+/// The `node` is the ID of the node in the restored `Dom`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeserializeWarning {
+    /// The callback isn't in the registry (or was serialized without a name), so it was left out
+    CallbackNotFound { node: NodeId, event: EventFilter, name: Option<String> },
+    /// The `GlTexture` or `IFrame` node was replaced by a div, since its data can't be restored
+    ContentNotRestored { node: NodeId, name: Option<String> },
+}
+
+impl_display! {
+    DeserializeWarning,
+    {
+        CallbackNotFound { node, event, name } => format!("Callback {:?} for {:?} of node {} not found in the registry", name, event, node),
+        ContentNotRestored { node, name } => format!("Texture / iframe {:?} of node {} was replaced by a div", name, node),
+    }
+}
+
+impl<T: Layout> Dom<T> {
+
+    /// Converts the DOM into a serializable tree, callbacks are stored
+    /// by the name they are registered under in the `registry`
+    pub fn to_serialized(&self, registry: &CallbackRegistry<T>) -> SerializedNode {
+        serialize_node(self, self.root, registry)
+    }
+
+    /// Reconstructs a DOM from a serialized tree, looking up the callbacks in the `registry`.
+    /// Returns the DOM and everything that couldn't be restored.
+    pub fn from_serialized(serialized: &SerializedNode, registry: &CallbackRegistry<T>) -> (Self, Vec<DeserializeWarning>) {
+        let mut warnings = Vec::new();
+        let mut next_node_id = 0;
+        let dom = deserialize_node(serialized, registry, &mut next_node_id, &mut warnings);
+        for warning in &warnings {
+            if let &DeserializeWarning::CallbackNotFound { node, event, ref name } = warning {
+                report(AzulError::CallbackNotFound { node, event, name: name.clone() });
+            }
+        }
+        (dom, warnings)
+    }
+}
+
+/// Serializes the DOM without a `CallbackRegistry`, so all callbacks are `null`.
+/// Use `Dom::to_serialized` to keep the names of the callbacks.
+impl<T: Layout> Serialize for Dom<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_serialized(&CallbackRegistry::new()).serialize(serializer)
+    }
+}
+
+fn serialize_node<T: Layout>(dom: &Dom<T>, node_id: NodeId, registry: &CallbackRegistry<T>) -> SerializedNode {
+
+    let node = &dom.arena.node_data[node_id];
+    let name_of = |fn_address: usize| registry.name_of(fn_address).map(|name| name.to_string());
+
+    let node_type = match &node.node_type {
+        NodeType::Div => SerializedNodeType::Div,
+        NodeType::Label(text) => SerializedNodeType::Label(text.clone()),
+        NodeType::Text(text_id) => SerializedNodeType::Text(text_id.to_raw()),
+        NodeType::Image(image_id) => SerializedNodeType::Image(image_id.to_raw()),
+        NodeType::GlTexture((callback, _)) => SerializedNodeType::GlTexture(name_of(callback.0 as usize)),
+        NodeType::IFrame((callback, _)) => SerializedNodeType::IFrame(name_of(callback.0 as usize)),
+    };
+
+    SerializedNode {
+        node_type,
+        ids: node.ids.clone(),
+        classes: node.classes.clone(),
+        tab_index: node.tab_index,
+        draggable: node.draggable,
+        key: node.key,
+        attributes: node.attributes.clone(),
+        is_synthetic_root: node.is_synthetic_root,
+        callbacks: node.callbacks.iter().map(|(event, callback)| SerializedCallback {
+            event: *event,
+            name: name_of(callback.0 as usize),
+        }).collect(),
+        css_overrides: node.dynamic_css_overrides.iter().map(|(id, property)| SerializedCssOverride {
+            id: id.clone(),
+            property: property.clone(),
+        }).collect(),
+        children: node_id.children(&dom.arena.node_layout).map(|child| serialize_node(dom, child, registry)).collect(),
+    }
+}
+
+/// Nodes are numbered in document order, which is the order of the nodes in a `Dom` built via `add_child`
+fn deserialize_node<T: Layout>(
+    serialized: &SerializedNode,
+    registry: &CallbackRegistry<T>,
+    next_node_id: &mut usize,
+    warnings: &mut Vec<DeserializeWarning>)
+-> Dom<T>
+{
+    use self::DeserializeWarning::*;
+
+    let node_id = NodeId::new(*next_node_id);
+    *next_node_id += 1;
+
+    let node_type = match &serialized.node_type {
+        SerializedNodeType::Div => NodeType::Div,
+        SerializedNodeType::Label(text) => NodeType::Label(text.clone()),
+        SerializedNodeType::Text(raw) => NodeType::Text(TextId::from_raw(*raw)),
+        SerializedNodeType::Image(raw) => NodeType::Image(ImageId::from_raw(*raw)),
+        SerializedNodeType::GlTexture(name) | SerializedNodeType::IFrame(name) => {
+            warnings.push(ContentNotRestored { node: node_id, name: name.clone() });
+            NodeType::Div
+        },
+    };
+
+    let mut node = NodeData::new(node_type);
+    for id in &serialized.ids {
+        node.add_id(id.clone());
+    }
+    for class in &serialized.classes {
+        node.add_class(class.clone());
+    }
+    node.set_tab_index(serialized.tab_index);
+    node.set_draggable(serialized.draggable);
+    node.set_key(serialized.key);
+    for (key, value) in &serialized.attributes {
+        node.add_attribute(key.clone(), value.clone());
+    }
+    node.is_synthetic_root = serialized.is_synthetic_root;
+
+    for SerializedCallback { event, name } in &serialized.callbacks {
+        match name.as_ref().and_then(|name| registry.get_callback(name)) {
+            Some(callback) => node.add_callback(*event, callback),
+            None => warnings.push(CallbackNotFound { node: node_id, event: *event, name: name.clone() }),
+        }
+    }
+
+    for SerializedCssOverride { id, property } in &serialized.css_overrides {
+        node.add_css_override(id.clone(), property.clone());
+    }
+
+    let mut dom = Dom::new(NodeType::Div);
+    dom.arena.node_data[dom.root] = node;
+
+    for child in &serialized.children {
+        dom.add_child(deserialize_node(child, registry, next_node_id, warnings));
+    }
+
+    dom
+}
+
+#[cfg(test)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+#[cfg(test)]
+use {
+    serde_json,
+    app_state::AppState,
+    window::CallbackInfo,
+    dom::{UpdateScreen, Redraw, DontRedraw, Callback, On},
+};
+
+#[cfg(test)]
+fn on_click(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+    Redraw
+}
+
+#[cfg(test)]
+fn on_hover(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+    DontRedraw
+}
+
+#[cfg(test)]
+fn representative_dom() -> Dom<TestLayout> {
+
+    use azul_css::LayoutWidth;
+    use images::new_image_id;
+    use text_cache::new_text_id;
+
+    Dom::div().with_id("main").with_class("app")
+        .with_child(Dom::label("Hello \"world\"")
+            .with_class("title")
+            .with_tab_index(TabIndex::Auto)
+            .with_key(7)
+            .with_callback(On::MouseUp, Callback(on_click))
+            .with_css_override("width", CssProperty::Width(LayoutWidth::px(20.0))))
+        .with_child((0..2).map(|_| Dom::image(new_image_id()).with_class("icon")).collect::<Dom<TestLayout>>())
+        .with_child(Dom::text_id(new_text_id()).is_draggable(true))
+}
+
+#[test]
+fn test_dom_json_round_trip() {
+
+    let mut registry = CallbackRegistry::new();
+    registry.register_callback("on_click", Callback(on_click));
+
+    let dom = representative_dom();
+    let json = serde_json::to_string(&dom.to_serialized(&registry)).unwrap();
+    assert!(json.contains("\"on_click\""));
+
+    let parsed: SerializedNode = serde_json::from_str(&json).unwrap();
+    let (restored, warnings) = Dom::from_serialized(&parsed, &registry);
+
+    assert_eq!(warnings, Vec::new());
+    assert_eq!(restored, dom);
+    assert_eq!(restored.serialize_for_snapshot_with_names(&registry), dom.serialize_for_snapshot_with_names(&registry));
+    assert!(restored.validate().is_ok());
+}
+
+#[test]
+fn test_unknown_callbacks_are_left_out_with_a_warning() {
+
+    let mut registry = CallbackRegistry::new();
+    registry.register_callback("on_click", Callback(on_click));
+    registry.register_callback("on_hover", Callback(on_hover));
+
+    let dom: Dom<TestLayout> = Dom::div()
+        .with_child(Dom::div().with_callback(On::MouseUp, Callback(on_click)).with_callback(On::MouseOver, Callback(on_hover)));
+
+    // The viewer only knows the "on_click" callback
+    let mut viewer_registry = CallbackRegistry::new();
+    viewer_registry.register_callback("on_click", Callback(on_click));
+
+    let json = serde_json::to_string(&dom.to_serialized(&registry)).unwrap();
+    let parsed: SerializedNode = serde_json::from_str(&json).unwrap();
+    let (restored, warnings) = Dom::from_serialized(&parsed, &viewer_registry);
+
+    assert_eq!(warnings, vec![DeserializeWarning::CallbackNotFound {
+        node: NodeId::new(1),
+        event: On::MouseOver.into(),
+        name: Some("on_hover".into()),
+    }]);
+    assert_eq!(restored.get_node_content(NodeId::new(1)).unwrap().callbacks, vec![(On::MouseUp.into(), Callback(on_click))]);
+
+    // Without a registry, all callbacks are null
+    let json = serde_json::to_string(&dom).unwrap();
+    let parsed: SerializedNode = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.children[0].callbacks.iter().map(|c| c.name.clone()).collect::<Vec<_>>(), vec![None, None]);
+    let (_, warnings) = Dom::<TestLayout>::from_serialized(&parsed, &registry);
+    assert_eq!(warnings.len(), 2);
+}
+
+#[test]
+fn test_unknown_callbacks_are_reported_to_the_error_handler() {
+
+    use diagnostics::{LogLevel, collect_diagnostics, take_collected};
+
+    let mut registry = CallbackRegistry::new();
+    registry.register_callback("on_hover", Callback(on_hover));
+
+    let dom: Dom<TestLayout> = Dom::div().with_callback(On::MouseOver, Callback(on_hover));
+    let parsed: SerializedNode = serde_json::from_str(&serde_json::to_string(&dom.to_serialized(&registry)).unwrap()).unwrap();
+
+    collect_diagnostics();
+    let (_, warnings) = Dom::from_serialized(&parsed, &CallbackRegistry::<TestLayout>::new());
+    let (messages, errors) = take_collected();
+
+    let expected = AzulError::CallbackNotFound { node: NodeId::new(0), event: On::MouseOver.into(), name: Some("on_hover".into()) };
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(messages, vec![(LogLevel::Warn, expected.to_string())]);
+    assert_eq!(errors, vec![expected]);
+}
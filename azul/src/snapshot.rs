@@ -0,0 +1,101 @@
+//! Comparing serialized DOMs against snapshot files, see `assert_dom_snapshot!`
+
+use std::{env, fs, path::Path};
+
+/// If this environment variable is set, snapshot files (and golden images)
+/// are rewritten with the current output instead of being compared
+pub const UPDATE_SNAPSHOTS_ENV_VAR: &str = "AZUL_UPDATE_GOLDEN";
+
+/// Asserts that the `Dom` serializes to the contents of the snapshot file.
+///
+/// If the file doesn't exist yet or if `AZUL_UPDATE_GOLDEN` is set, the file
+/// is (re-)written instead. An optional third argument is the `CallbackRegistry`
+/// used to print the names of the callbacks.
+///
+/// ```no_run,ignore
+/// assert_dom_snapshot!(dom, "tests/snapshots/settings_page.txt");
+/// assert_dom_snapshot!(dom, "tests/snapshots/settings_page.txt", &callback_registry);
+/// ```
+#[macro_export]
+macro_rules! assert_dom_snapshot {
+    ($dom:expr, $path:expr) => {
+        $crate::snapshot::assert_snapshot_file(&$dom.serialize_for_snapshot(), $path)
+    };
+    ($dom:expr, $path:expr, $registry:expr) => {
+        $crate::snapshot::assert_snapshot_file(&$dom.serialize_for_snapshot_with_names($registry), $path)
+    };
+}
+
+/// Compares `actual` against the contents of the file at `path`, panics with
+/// both versions if they differ. See `assert_dom_snapshot!`.
+pub fn assert_snapshot_file<P: AsRef<Path>>(actual: &str, path: P) {
+
+    let path = path.as_ref();
+
+    if env::var(UPDATE_SNAPSHOTS_ENV_VAR).is_ok() || !path.exists() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap_or_else(|e| panic!("could not create {}: {}", parent.display(), e));
+        }
+        fs::write(path, actual).unwrap_or_else(|e| panic!("could not write snapshot {}: {}", path.display(), e));
+        return;
+    }
+
+    let expected = fs::read_to_string(path).unwrap_or_else(|e| panic!("could not read snapshot {}: {}", path.display(), e));
+
+    // Snapshots checked out on Windows may have CRLF line endings
+    if expected.replace("\r\n", "\n") != actual {
+        panic!("DOM does not match snapshot {} (set {} to update it)\n\n--- expected:\n{}\n--- actual:\n{}",
+            path.display(), UPDATE_SNAPSHOTS_ENV_VAR, expected, actual);
+    }
+}
+
+/// Formats a line-by-line diff of the two texts: unchanged lines are prefixed
+/// with two spaces, lines that are only in `expected` with `- `, lines that
+/// are only in `actual` with `+ `.
+pub(crate) fn format_line_diff(expected: &str, actual: &str) -> String {
+
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+
+    // Length of the longest common subsequence of expected[i..] and actual[j..]
+    let mut common = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            out.push_str("  ");
+            out.push_str(expected[i]);
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && common[i + 1][j] >= common[i][j + 1]) {
+            out.push_str("- ");
+            out.push_str(expected[i]);
+            i += 1;
+        } else {
+            out.push_str("+ ");
+            out.push_str(actual[j]);
+            j += 1;
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+#[test]
+fn test_format_line_diff() {
+    assert_eq!(format_line_diff("a\nb\nc\n", "a\nc\nd\n"), "  a\n- b\n  c\n+ d\n");
+    assert_eq!(format_line_diff("", "a\n"), "+ a\n");
+    assert_eq!(format_line_diff("a\n", "a\n"), "  a\n");
+}
@@ -1,18 +1,23 @@
 //! DOM-tree to CSS style tree stying
 
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+};
 use azul_css::{
-    Css, CssContentGroup, CssDeclaration, CssPath,
+    Css, CssContentGroup, CssDeclaration, CssPath, NodeTypePath,
     CssPathSelector, CssPathPseudoSelector, CssNthChildSelector::*,
 };
 use webrender::api::HitTestItem;
 use {
+    FastHashMap,
     traits::Layout,
     ui_description::{UiDescription, StyledNode},
     dom::NodeData,
     ui_state::UiState,
-    id_tree::{NodeId, NodeHierarchy, NodeDataContainer},
+    id_tree::{NodeId, NodeHierarchy, NodeDataContainer, ArenaNodeData},
     focus::FocusTarget,
+    diagnostics::{AzulError, report},
 };
 
 /// Has all the necessary information about the style CSS path
@@ -42,6 +47,7 @@ pub(crate) fn matches_html_element<'a, T: Layout>(
     let mut current_node = Some(node_id);
     let mut direct_parent_has_to_match = false;
     let mut last_selector_matched = false;
+    let mut is_rightmost_group = true;
 
     for (content_group, reason) in CssGroupIterator::new(&css_path.selectors) {
         let cur_node_id = match current_node {
@@ -54,6 +60,12 @@ pub(crate) fn matches_html_element<'a, T: Layout>(
             },
         };
         let current_selector_matches = selector_group_matches(&content_group, &html_node_tree[cur_node_id]);
+        if is_rightmost_group && !current_selector_matches {
+            // The rightmost group always has to match the node itself:
+            // ".a .b" can never select a node that doesn't have the class "b"
+            return false;
+        }
+        is_rightmost_group = false;
         if direct_parent_has_to_match && !current_selector_matches {
             // If the element was a ">" element and the current,
             // direct parent does not match, return false
@@ -192,7 +204,7 @@ fn test_css_group_iterator() {
 }
 
 pub(crate) fn construct_html_cascade_tree<'a, T: Layout>(
-    input: &'a NodeDataContainer<NodeData<T>>,
+    input: &'a ArenaNodeData<NodeData<T>>,
     node_hierarchy: &NodeHierarchy,
     node_depths_sorted: &[(usize, NodeId)],
     focused_item: Option<NodeId>,
@@ -375,9 +387,179 @@ fn selector_group_matches<'a, T: Layout>(selectors: &[&CssPathSelector], html_no
     true
 }
 
+/// Buckets the rules of a `Css` by the rightmost simple selector of their path,
+/// so that each node only has to be tested against the rules that could possibly
+/// select it, instead of against every rule in the stylesheet.
+///
+/// The index refers to the rules by their position in `Css::rules`, so it has to
+/// be rebuilt whenever the `Css` changes (i.e. on a hot-reload).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct CssRuleIndex {
+    /// Rules whose rightmost group contains an `#id`
+    ids: FastHashMap<String, Vec<usize>>,
+    /// Rules whose rightmost group contains a `.class`, but no `#id`
+    classes: FastHashMap<String, Vec<usize>>,
+    /// Rules whose rightmost group only contains a node type (i.e. `div`)
+    types: FastHashMap<NodeTypePath, Vec<usize>>,
+    /// Rules that have to be tested against every node (i.e. `*` or `:hover`)
+    universal: Vec<usize>,
+    /// For each rule: the bloom filter bits of the ids, classes and node
+    /// types that the ancestors of a node must have in order to be matched
+    ancestor_masks: Vec<AncestorBloom>,
+}
+
+/// Bloom filter of the ids, classes and node types of all ancestors of a node
+type AncestorBloom = u64;
+
+impl CssRuleIndex {
+
+    pub(crate) fn new(css: &Css) -> Self {
+        use self::CssPathSelector::*;
+
+        let mut index = Self::default();
+
+        for (rule_idx, rule) in css.rules.iter().enumerate() {
+
+            let mut groups = CssGroupIterator::new(&rule.path.selectors);
+            let (rightmost_group, rightmost_reason) = match groups.next() {
+                Some(s) => s,
+                None => {
+                    // Empty paths never match any node
+                    index.ancestor_masks.push(0);
+                    continue;
+                },
+            };
+
+            let id = rightmost_group.iter().filter_map(|s| if let Id(id) = s { Some(id) } else { None }).next();
+            let class = rightmost_group.iter().filter_map(|s| if let Class(c) = s { Some(c) } else { None }).next();
+            let node_type = rightmost_group.iter().filter_map(|s| if let Type(t) = s { Some(t) } else { None }).next();
+
+            match (id, class, node_type) {
+                (Some(id), _, _) => index.ids.entry(id.clone()).or_insert_with(Vec::new).push(rule_idx),
+                (None, Some(c), _) => index.classes.entry(c.clone()).or_insert_with(Vec::new).push(rule_idx),
+                (None, None, Some(t)) => index.types.entry(*t).or_insert_with(Vec::new).push(rule_idx),
+                (None, None, None) => index.universal.push(rule_idx),
+            }
+
+            // Only the groups that are guaranteed to be checked against an ancestor
+            // can be required: the leftmost group (which decides the result) and
+            // every group that is the direct parent of the group on its right
+            //
+            // If one of the ancestor groups is `*`, the matching can stop early
+            // at the root node, so nothing can be required from the ancestors
+            let ancestor_groups = groups.collect::<Vec<_>>();
+            let mut mask = 0;
+            let mut previous_reason = rightmost_reason;
+            let can_stop_at_root = ancestor_groups.iter().any(|(group, _)| *group == [&Global]);
+            for (group_idx, (group, reason)) in ancestor_groups.iter().enumerate().filter(|_| !can_stop_at_root) {
+                let is_leftmost = group_idx == ancestor_groups.len() - 1;
+                if is_leftmost || previous_reason == CssGroupSplitReason::DirectChildren {
+                    for selector in group {
+                        mask |= match selector {
+                            Id(id) => bloom_bits(0, id),
+                            Class(c) => bloom_bits(1, c),
+                            Type(t) => bloom_bits(2, t),
+                            _ => 0,
+                        };
+                    }
+                }
+                previous_reason = *reason;
+            }
+            index.ancestor_masks.push(mask);
+        }
+
+        index
+    }
+
+    /// Number of rules in the `Css` that this index was built from
+    pub(crate) fn len(&self) -> usize {
+        self.ancestor_masks.len()
+    }
+
+    /// Writes the indices of all rules that match the node into `matching_rules`,
+    /// in the order in which they appear in the `Css`
+    pub(crate) fn get_matching_rules<'a, T: Layout>(
+        &self,
+        css: &Css,
+        node_id: NodeId,
+        node_hierarchy: &NodeHierarchy,
+        html_node_tree: &NodeDataContainer<HtmlCascadeInfo<'a, T>>,
+        ancestor_blooms: &[AncestorBloom],
+        matching_rules: &mut Vec<usize>)
+    {
+        debug_assert_eq!(self.len(), css.rules.len(), "CssRuleIndex is out of date");
+
+        matching_rules.clear();
+
+        let node_data = html_node_tree[node_id].node_data;
+
+        for id in &node_data.ids {
+            if let Some(rules) = self.ids.get(id) {
+                matching_rules.extend(rules.iter().cloned());
+            }
+        }
+        for class in &node_data.classes {
+            if let Some(rules) = self.classes.get(class) {
+                matching_rules.extend(rules.iter().cloned());
+            }
+        }
+        if let Some(rules) = self.types.get(&node_data.node_type.get_path()) {
+            matching_rules.extend(rules.iter().cloned());
+        }
+        matching_rules.extend(self.universal.iter().cloned());
+
+        // Rules have to be applied in the order of the stylesheet
+        matching_rules.sort_unstable();
+        matching_rules.dedup();
+
+        let ancestor_bloom = ancestor_blooms[node_id.index()];
+        matching_rules.retain(|rule_idx| {
+            let mask = self.ancestor_masks[*rule_idx];
+            ancestor_bloom & mask == mask &&
+            matches_html_element(&css.rules[*rule_idx].path, node_id, node_hierarchy, html_node_tree)
+        });
+    }
+}
+
+/// Returns the two bits that represent a `#id` (kind 0), `.class` (kind 1)
+/// or node type (kind 2) in an `AncestorBloom`
+fn bloom_bits<H: Hash + ?Sized>(kind: u8, item: &H) -> AncestorBloom {
+    let mut hasher = DefaultHasher::new();
+    kind.hash(&mut hasher);
+    item.hash(&mut hasher);
+    let hash = hasher.finish();
+    (1 << (hash & 63)) | (1 << ((hash >> 6) & 63))
+}
+
+fn node_bloom_bits<T: Layout>(node_data: &NodeData<T>) -> AncestorBloom {
+    node_data.ids.iter().fold(0, |bloom, id| bloom | bloom_bits(0, id)) |
+    node_data.classes.iter().fold(0, |bloom, c| bloom | bloom_bits(1, c)) |
+    bloom_bits(2, &node_data.node_type.get_path())
+}
+
+/// Calculates the `AncestorBloom` for every node in the tree, indexed by `NodeId`
+fn calculate_ancestor_blooms<T: Layout>(
+    node_hierarchy: &NodeHierarchy,
+    node_data: &ArenaNodeData<NodeData<T>>,
+    parents_sorted_by_depth: &[(usize, NodeId)])
+-> Vec<AncestorBloom>
+{
+    let mut blooms = vec![0; node_hierarchy.len()];
+    // Parents are sorted by depth, so the bloom of a parent is always
+    // calculated before it is passed on to the children
+    for (_depth, parent_id) in parents_sorted_by_depth {
+        let bloom_for_children = blooms[parent_id.index()] | node_bloom_bits(&node_data[*parent_id]);
+        for child_id in parent_id.children(node_hierarchy) {
+            blooms[child_id.index()] = bloom_for_children;
+        }
+    }
+    blooms
+}
+
 pub(crate) fn match_dom_selectors<T: Layout>(
     ui_state: &UiState<T>,
     css: &Css,
+    css_index: &CssRuleIndex,
     focused_node: &mut Option<NodeId>,
     pending_focus_target: &mut Option<FocusTarget>,
     hovered_nodes: &BTreeMap<NodeId, HitTestItem>,
@@ -409,17 +591,23 @@ pub(crate) fn match_dom_selectors<T: Layout>(
         &mut html_tree,
     );
 
+    let ancestor_blooms = calculate_ancestor_blooms(
+        &ui_state.dom.arena.node_layout,
+        &ui_state.dom.arena.node_data,
+        &non_leaf_nodes,
+    );
+
+    // Re-used for every node, to avoid allocating
+    let mut matching_rules = Vec::new();
+
     for (_depth, parent_id) in non_leaf_nodes {
 
         let mut parent_rules = styled_nodes.get(&parent_id).cloned().unwrap_or_default();
 
-        // Iterate through all CSS rules, test if they match
-        // This is technically O(n ^ 2), however, there are usually not that many CSS blocks,
-        // so the cost of this should be insignificant.
-        for applying_rule in css.rules.iter().filter(|rule| {
-            matches_html_element(&rule.path, parent_id, &ui_state.dom.arena.node_layout, &html_tree)
-        }) {
-            parent_rules.css_constraints.extend(applying_rule.declarations.clone());
+        // Only test the CSS rules that could possibly match the node
+        css_index.get_matching_rules(css, parent_id, &ui_state.dom.arena.node_layout, &html_tree, &ancestor_blooms, &mut matching_rules);
+        for rule_idx in &matching_rules {
+            parent_rules.css_constraints.extend(css.rules[*rule_idx].declarations.iter().cloned());
         }
 
         let inheritable_rules: Vec<CssDeclaration> = parent_rules.css_constraints.iter().filter(|prop| prop.is_inheritable()).cloned().collect();
@@ -433,13 +621,9 @@ pub(crate) fn match_dom_selectors<T: Layout>(
                     // Style children that themselves aren't parents
                     let mut child_rules = inheritable_rules.clone();
 
-                    // Iterate through all style rules, test if they match
-                    // This is technically O(n ^ 2), however, there are usually not that many style blocks,
-                    // so the cost of this should be insignificant.
-                    for applying_rule in css.rules.iter().filter(|rule| {
-                        matches_html_element(&rule.path, child_id, &ui_state.dom.arena.node_layout, &html_tree)
-                    }) {
-                        child_rules.extend(applying_rule.declarations.clone());
+                    css_index.get_matching_rules(css, child_id, &ui_state.dom.arena.node_layout, &html_tree, &ancestor_blooms, &mut matching_rules);
+                    for rule_idx in &matching_rules {
+                        child_rules.extend(css.rules[*rule_idx].declarations.iter().cloned());
                     }
 
                     styled_nodes.insert(child_id, StyledNode { css_constraints: child_rules });
@@ -478,6 +662,7 @@ pub(crate) fn match_dom_selectors<T: Layout>(
         ui_descr_root: root,
         styled_nodes: styled_nodes,
         default_style_of_node: StyledNode::default(),
+        // Only clones the reference-counted pointers, not the overrides themselves
         dynamic_css_overrides: ui_state.dynamic_css_overrides.clone(),
         selected_hover_nodes,
     }
@@ -504,10 +689,10 @@ fn update_focus_from_callbacks<'a, T: 'a + Layout>(
 
     match new_focus_target {
         FocusTarget::Id(node_id) => {
-            if html_node_tree.len() < node_id.index() {
+            if node_id.index() < html_node_tree.len() {
                 *focused_node = Some(node_id);
             } else {
-                warn!("Focusing on node with invalid ID: {}", node_id);
+                report(AzulError::FocusTargetNotFound(format!("node {}", node_id)));
             }
         },
         FocusTarget::NoFocus => { *focused_node = None; },
@@ -516,7 +701,7 @@ fn update_focus_from_callbacks<'a, T: 'a + Layout>(
             .find(|node_id| matches_html_element(&css_path, *node_id, &node_hierarchy, &html_node_tree)) {
                  *focused_node = Some(new_focused_node_id);
             } else {
-                warn!("Could not find focus node for path: {}", css_path);
+                report(AzulError::FocusTargetNotFound(css_path.to_string()));
             }
         },
     }
@@ -532,4 +717,195 @@ fn update_focus_from_callbacks<'a, T: 'a + Layout>(
     }
 
     *pending_focus_target = None;
-}
\ No newline at end of file
+}
+/// The `CssRuleIndex` has to select exactly the same rules (in the same order)
+/// as testing every rule against every node
+#[test]
+fn test_css_rule_index_matches_brute_force() {
+
+    use azul_css::{CssRuleBlock, CssNthChildSelector};
+    use dom::{Dom, NodeType, TagIdGenerator};
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    /// Deterministic pseudo-random numbers, so that failures are reproducible
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, max: usize) -> usize {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((self.0 >> 33) as usize) % max
+        }
+    }
+
+    const CLASSES: [&str; 4] = ["a", "b", "c", "d"];
+    const IDS: [&str; 3] = ["x", "y", "z"];
+
+    fn random_dom(rng: &mut Rng, depth: usize) -> Dom<TestLayout> {
+        let mut dom = if rng.next(3) == 0 { Dom::label("text") } else { Dom::div() };
+        for _ in 0..rng.next(3) {
+            dom.add_class(CLASSES[rng.next(CLASSES.len())]);
+        }
+        if rng.next(4) == 0 {
+            dom.add_id(IDS[rng.next(IDS.len())]);
+        }
+        if depth > 0 {
+            for _ in 0..rng.next(4) {
+                dom.add_child(random_dom(rng, depth - 1));
+            }
+        }
+        dom
+    }
+
+    fn random_selector(rng: &mut Rng) -> CssPathSelector {
+        use self::CssPathSelector::*;
+        match rng.next(9) {
+            0 => Global,
+            1 => Type(NodeTypePath::Div),
+            2 => Type(NodeTypePath::P),
+            3 | 4 => Class(CLASSES[rng.next(CLASSES.len())].into()),
+            5 => Id(IDS[rng.next(IDS.len())].into()),
+            6 => PseudoSelector(CssPathPseudoSelector::First),
+            7 => PseudoSelector(CssPathPseudoSelector::Last),
+            _ => PseudoSelector(CssPathPseudoSelector::NthChild(CssNthChildSelector::Even)),
+        }
+    }
+
+    fn random_path(rng: &mut Rng) -> CssPath {
+        use self::CssPathSelector::*;
+        let mut selectors = Vec::new();
+        let group_count = 1 + rng.next(3);
+        for group_idx in 0..group_count {
+            if group_idx != 0 {
+                selectors.push(if rng.next(2) == 0 { Children } else { DirectChildren });
+            }
+            for _ in 0..(1 + rng.next(2)) {
+                selectors.push(random_selector(rng));
+            }
+        }
+        CssPath { selectors }
+    }
+
+    for seed in 0..30 {
+
+        let mut rng = Rng(seed);
+
+        let dom = Dom::div().with_child(random_dom(&mut rng, 5));
+        let css = Css {
+            rules: (0..80).map(|_| CssRuleBlock { path: random_path(&mut rng), declarations: Vec::new() }).collect(),
+        };
+
+        let css_index = CssRuleIndex::new(&css);
+        let ui_state = dom.into_ui_state(&mut TagIdGenerator::new());
+        let node_hierarchy = &ui_state.dom.arena.node_layout;
+        let non_leaf_nodes = node_hierarchy.get_parents_sorted_by_depth();
+        let html_tree = construct_html_cascade_tree(
+            &ui_state.dom.arena.node_data,
+            node_hierarchy,
+            &non_leaf_nodes,
+            None,
+            &BTreeMap::new(),
+            false,
+        );
+        let ancestor_blooms = calculate_ancestor_blooms(node_hierarchy, &ui_state.dom.arena.node_data, &non_leaf_nodes);
+
+        let mut matching_rules = Vec::new();
+
+        for node_id in html_tree.linear_iter() {
+            let brute_force = css.rules.iter().enumerate()
+                .filter(|(_, rule)| matches_html_element(&rule.path, node_id, node_hierarchy, &html_tree))
+                .map(|(rule_idx, _)| rule_idx)
+                .collect::<Vec<_>>();
+
+            css_index.get_matching_rules(&css, node_id, node_hierarchy, &html_tree, &ancestor_blooms, &mut matching_rules);
+
+            assert_eq!(matching_rules, brute_force, "seed {}, node {}", seed, node_id);
+        }
+    }
+}
+
+#[test]
+fn test_rightmost_selector_group_must_match() {
+
+    use self::CssPathSelector::*;
+    use dom::{Dom, NodeType, TagIdGenerator};
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    // ".a .b" must not select the child, since it doesn't have the class "b"
+    let dom: Dom<TestLayout> = Dom::div().with_class("a").with_child(Dom::div().with_class("c"));
+    let ui_state = dom.into_ui_state(&mut TagIdGenerator::new());
+    let node_hierarchy = &ui_state.dom.arena.node_layout;
+    let non_leaf_nodes = node_hierarchy.get_parents_sorted_by_depth();
+    let html_tree = construct_html_cascade_tree(&ui_state.dom.arena.node_data, node_hierarchy, &non_leaf_nodes, None, &BTreeMap::new(), false);
+
+    let a_b = CssPath { selectors: vec![Class("a".into()), Children, Class("b".into())] };
+    let a_c = CssPath { selectors: vec![Class("a".into()), Children, Class("c".into())] };
+
+    assert!(!matches_html_element(&a_b, NodeId::new(1), node_hierarchy, &html_tree));
+    assert!(matches_html_element(&a_c, NodeId::new(1), node_hierarchy, &html_tree));
+}
+
+/// The order of the classes on a node (and duplicated classes) must not change
+/// which rules match or the order in which they are applied
+#[test]
+fn test_class_order_does_not_change_matching_rules() {
+
+    use azul_css::CssRuleBlock;
+    use dom::{Dom, NodeType, TagIdGenerator};
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    fn class_path(classes: &[&str]) -> CssPath {
+        CssPath { selectors: classes.iter().map(|c| CssPathSelector::Class((*c).into())).collect() }
+    }
+
+    let css = Css {
+        rules: vec![
+            CssRuleBlock { path: class_path(&["b"]), declarations: Vec::new() },
+            CssRuleBlock { path: class_path(&["a", "b"]), declarations: Vec::new() },
+            CssRuleBlock { path: class_path(&["a"]), declarations: Vec::new() },
+            CssRuleBlock { path: class_path(&["b", "a"]), declarations: Vec::new() },
+            CssRuleBlock { path: class_path(&["c"]), declarations: Vec::new() },
+        ],
+    };
+    let css_index = CssRuleIndex::new(&css);
+
+    let matching_rules_for = |classes: Vec<String>| -> Vec<usize> {
+        let mut child = Dom::div();
+        // Set the classes directly, since add_class would already sort them
+        child.arena.node_data[child.root].classes = classes;
+        let dom: Dom<TestLayout> = Dom::div().with_child(child);
+        let ui_state = dom.into_ui_state(&mut TagIdGenerator::new());
+        let node_hierarchy = &ui_state.dom.arena.node_layout;
+        let non_leaf_nodes = node_hierarchy.get_parents_sorted_by_depth();
+        let html_tree = construct_html_cascade_tree(&ui_state.dom.arena.node_data, node_hierarchy, &non_leaf_nodes, None, &BTreeMap::new(), false);
+        let ancestor_blooms = calculate_ancestor_blooms(node_hierarchy, &ui_state.dom.arena.node_data, &non_leaf_nodes);
+        let mut matching_rules = Vec::new();
+        css_index.get_matching_rules(&css, NodeId::new(1), node_hierarchy, &html_tree, &ancestor_blooms, &mut matching_rules);
+        matching_rules
+    };
+
+    let expected = vec![0, 1, 2, 3];
+    assert_eq!(matching_rules_for(vec!["a".into(), "b".into()]), expected);
+    assert_eq!(matching_rules_for(vec!["b".into(), "a".into()]), expected);
+    assert_eq!(matching_rules_for(vec!["b".into(), "a".into(), "b".into()]), expected);
+}
@@ -0,0 +1,559 @@
+//! Random DOMs and DOM invariant checkers for property-based tests
+//! (with the `property-testing` feature), built on `quickcheck`.
+//!
+//! `Dom<T>` can't implement `Arbitrary` itself (it isn't `Send` and can contain
+//! callbacks), so the generator produces a `DomShape` instead: a plain tree of divs
+//! and labels with ids and classes, which can be shrunk by quickcheck and turned
+//! into a `Dom<T>` of any `T` via `DomShape::to_dom()`.
+//!
+//! ```no_run,ignore
+//! use azul::testing::{DomShape, check_add_child};
+//!
+//! fn prop(parent: DomShape, child: DomShape) -> bool {
+//!     check_add_child(&parent.to_dom::<MyApp>(), &child.to_dom()).is_ok()
+//! }
+//!
+//! quickcheck::quickcheck(prop as fn(DomShape, DomShape) -> bool);
+//! ```
+
+use quickcheck::{Arbitrary, Gen};
+use {
+    dom::{Dom, DomError, NodeData, NodeType},
+    traits::Layout,
+};
+
+/// Ids that generated nodes can have, in sorted order
+const IDS: &[&str] = &["content", "footer", "header", "sidebar"];
+/// Classes that generated nodes can have, in sorted order
+const CLASSES: &[&str] = &["active", "column", "item", "row", "selected"];
+/// Words that the text of generated labels is made of
+const WORDS: &[&str] = &["Hello", "world", "Save", "Cancel", "0", "42", "item"];
+
+/// Controls the shape of the DOMs generated by `DomShape::generate`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DomShapeConfig {
+    /// Maximum depth of the tree, a depth of 1 only generates the root node
+    pub max_depth: usize,
+    /// Maximum number of children of a div
+    pub max_children: usize,
+    /// How many of the nodes are labels (0 - 100). Labels never have children.
+    pub label_percent: usize,
+    /// Percentage of nodes that get each of the possible ids / classes (0 - 100)
+    pub attribute_percent: usize,
+}
+
+impl Default for DomShapeConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            max_children: 4,
+            label_percent: 40,
+            attribute_percent: 20,
+        }
+    }
+}
+
+/// Node type of a `DomShape`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShapeNodeType {
+    Div,
+    Label(String),
+}
+
+/// A plain description of a DOM, generated by quickcheck and converted via `to_dom()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomShape {
+    pub node_type: ShapeNodeType,
+    /// Sorted and deduplicated, like in the snapshot serialization
+    pub ids: Vec<String>,
+    /// Sorted and deduplicated, like in the snapshot serialization
+    pub classes: Vec<String>,
+    pub children: Vec<DomShape>,
+}
+
+/// Returns a number in `0..max` (`0` if `max` is `0`)
+fn pick<G: Gen>(g: &mut G, max: usize) -> usize {
+    if max == 0 { 0 } else { u32::arbitrary(g) as usize % max }
+}
+
+/// Returns `true` in `percent`% of the calls
+fn chance<G: Gen>(g: &mut G, percent: usize) -> bool {
+    pick(g, 100) < percent
+}
+
+impl DomShape {
+
+    /// Generates a random shape with the given `config`
+    pub fn generate<G: Gen>(g: &mut G, config: &DomShapeConfig) -> Self {
+        Self::generate_at_depth(g, config, 1)
+    }
+
+    fn generate_at_depth<G: Gen>(g: &mut G, config: &DomShapeConfig, depth: usize) -> Self {
+
+        let is_label = chance(g, config.label_percent);
+
+        let node_type = if is_label {
+            let words = (0..pick(g, 3) + 1).map(|_| WORDS[pick(g, WORDS.len())]).collect::<Vec<_>>();
+            ShapeNodeType::Label(words.join(" "))
+        } else {
+            ShapeNodeType::Div
+        };
+
+        let ids = IDS.iter().filter(|_| chance(g, config.attribute_percent)).map(|s| s.to_string()).collect();
+        let classes = CLASSES.iter().filter(|_| chance(g, config.attribute_percent)).map(|s| s.to_string()).collect();
+
+        let children = if is_label || depth >= config.max_depth {
+            Vec::new()
+        } else {
+            (0..pick(g, config.max_children + 1)).map(|_| Self::generate_at_depth(g, config, depth + 1)).collect()
+        };
+
+        Self { node_type, ids, classes, children }
+    }
+
+    /// Returns the number of nodes in this shape
+    pub fn len(&self) -> usize {
+        1 + self.children.iter().map(|c| c.len()).sum::<usize>()
+    }
+
+    fn to_node_type<T: Layout>(&self) -> NodeType<T> {
+        match &self.node_type {
+            ShapeNodeType::Div => NodeType::Div,
+            ShapeNodeType::Label(text) => NodeType::Label(text.clone()),
+        }
+    }
+
+    /// Returns the `NodeData` of the root node of this shape (without the children)
+    pub fn to_node_data<T: Layout>(&self) -> NodeData<T> {
+        let mut node = NodeData::new(self.to_node_type());
+        for id in &self.ids {
+            node.add_id(id.clone());
+        }
+        for class in &self.classes {
+            node.add_class(class.clone());
+        }
+        node
+    }
+
+    /// Builds the `Dom`, appending the children via `add_child`
+    pub fn to_dom<T: Layout>(&self) -> Dom<T> {
+        let mut dom = Dom::new(self.to_node_type());
+        for id in &self.ids {
+            dom.add_id(id.clone());
+        }
+        for class in &self.classes {
+            dom.add_class(class.clone());
+        }
+        for child in &self.children {
+            dom.add_child(child.to_dom());
+        }
+        dom
+    }
+
+    /// Parses the output of `Dom::serialize_for_snapshot()` back into a shape.
+    ///
+    /// Only the subset that a `DomShape` can describe is supported: divs and labels
+    /// (whose text doesn't need escaping) with ids and classes. Anything else,
+    /// such as callbacks or CSS overrides, is rejected.
+    pub fn from_snapshot(snapshot: &str) -> Result<Self, SnapshotParseError> {
+
+        use self::SnapshotParseError::*;
+
+        /// Appends the last node on the stack to its parent, returns the node if it is the root
+        fn finish_node(stack: &mut Vec<DomShape>) -> Option<DomShape> {
+            let finished = stack.pop()?;
+            match stack.last_mut() {
+                Some(parent) => { parent.children.push(finished); None },
+                None => Some(finished),
+            }
+        }
+
+        // Nodes whose children are still being parsed, from the root to the current node
+        let mut stack = Vec::new();
+
+        for (line_idx, line) in snapshot.lines().enumerate() {
+
+            let line_nr = line_idx + 1;
+            let indent = line.chars().take_while(|c| *c == ' ').count();
+
+            if indent % 4 != 0 || indent / 4 > stack.len() {
+                return Err(Indentation(line_nr));
+            }
+
+            let depth = indent / 4;
+
+            if depth == 0 && !stack.is_empty() {
+                return Err(MultipleRoots(line_nr));
+            }
+
+            let node = parse_snapshot_line(&line[indent..]).ok_or_else(|| Unsupported(line_nr, line.to_string()))?;
+
+            while stack.len() > depth {
+                finish_node(&mut stack);
+            }
+
+            stack.push(node);
+        }
+
+        let mut root = None;
+        while !stack.is_empty() {
+            root = finish_node(&mut stack);
+        }
+
+        root.ok_or(Empty)
+    }
+}
+
+/// Parses `div #id .class` or `label "text" #id .class`
+fn parse_snapshot_line(line: &str) -> Option<DomShape> {
+
+    let (node_type, rest) = if line == "div" || line.starts_with("div ") {
+        (ShapeNodeType::Div, &line[3..])
+    } else if line.starts_with("label \"") {
+        let text_start = "label \"".len();
+        let text_len = line[text_start..].find('"')?;
+        let text = &line[text_start..text_start + text_len];
+        if text.contains('\\') {
+            return None;
+        }
+        (ShapeNodeType::Label(text.to_string()), &line[text_start + text_len + 1..])
+    } else {
+        return None;
+    };
+
+    let mut shape = DomShape { node_type, ids: Vec::new(), classes: Vec::new(), children: Vec::new() };
+
+    for token in rest.split_whitespace() {
+        if token.starts_with('#') {
+            shape.ids.push(token[1..].to_string());
+        } else if token.starts_with('.') {
+            shape.classes.push(token[1..].to_string());
+        } else if token == "<synthetic-root>" {
+            // DomShapes don't distinguish collected containers from regular divs
+            continue;
+        } else {
+            return None;
+        }
+    }
+
+    Some(shape)
+}
+
+impl Arbitrary for DomShape {
+
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        Self::generate(g, &DomShapeConfig::default())
+    }
+
+    fn shrink(&self) -> Box<Iterator<Item=Self>> {
+
+        let mut smaller = Vec::new();
+
+        // Replace the node with one of its subtrees
+        smaller.extend(self.children.iter().cloned());
+
+        // Remove one of the children
+        for idx in 0..self.children.len() {
+            let mut shape = self.clone();
+            shape.children.remove(idx);
+            smaller.push(shape);
+        }
+
+        // Shrink one of the children
+        for (idx, child) in self.children.iter().enumerate() {
+            for shrunk_child in child.shrink() {
+                let mut shape = self.clone();
+                shape.children[idx] = shrunk_child;
+                smaller.push(shape);
+            }
+        }
+
+        if !self.ids.is_empty() || !self.classes.is_empty() {
+            smaller.push(DomShape { ids: Vec::new(), classes: Vec::new(), .. self.clone() });
+        }
+
+        Box::new(smaller.into_iter())
+    }
+}
+
+/// Error returned by `DomShape::from_snapshot()`, with the (1-based) line number
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotParseError {
+    /// The snapshot doesn't contain any node
+    Empty,
+    /// The line isn't indented by a multiple of four spaces, or more than one level deeper than its parent
+    Indentation(usize),
+    /// A second node without indentation
+    MultipleRoots(usize),
+    /// The line contains something that a `DomShape` can't describe
+    Unsupported(usize, String),
+}
+
+impl_display! {
+    SnapshotParseError,
+    {
+        Empty => "Snapshot is empty",
+        Indentation(line) => format!("Invalid indentation in line {}", line),
+        MultipleRoots(line) => format!("Second root node in line {}", line),
+        Unsupported(line, content) => format!("Unsupported content in line {}: {:?}", line, content),
+    }
+}
+
+/// A broken invariant of a `Dom`, returned by the `check_*` functions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// The node hierarchy is broken, see `Dom::validate()`
+    Hierarchy(DomError),
+    /// The DOM doesn't have the expected number of nodes
+    NodeCount { expected: usize, actual: usize },
+    /// The nodes aren't in the expected document order, starting at this position
+    DocumentOrder { position: usize },
+}
+
+impl_display! {
+    InvariantViolation,
+    {
+        Hierarchy(e) => format!("Invalid node hierarchy: {}", e),
+        NodeCount { expected, actual } => format!("Expected {} nodes, got {}", expected, actual),
+        DocumentOrder { position } => format!("Document order differs at position {}", position),
+    }
+}
+
+impl_from!(DomError, InvariantViolation::Hierarchy);
+
+/// Checks the structure of the node hierarchy, see `Dom::validate()`
+pub fn check_hierarchy<T: Layout>(dom: &Dom<T>) -> Result<(), InvariantViolation> {
+    dom.validate().map_err(InvariantViolation::Hierarchy)
+}
+
+/// Checks that the `dom` has `expected` nodes
+pub fn check_node_count<T: Layout>(dom: &Dom<T>, expected: usize) -> Result<(), InvariantViolation> {
+    if dom.len() == expected {
+        Ok(())
+    } else {
+        Err(InvariantViolation::NodeCount { expected, actual: dom.len() })
+    }
+}
+
+/// Returns the depth and the content of all nodes, in document order
+pub fn document_order<T: Layout>(dom: &Dom<T>) -> Vec<(usize, &NodeData<T>)> {
+
+    use id_tree::NodeEdge;
+
+    let mut order = Vec::with_capacity(dom.len());
+    let mut depth = 0;
+
+    for edge in dom.root.traverse(&dom.arena.node_layout) {
+        match edge {
+            NodeEdge::Start(node_id) => {
+                order.push((depth, &dom.arena.node_data[node_id]));
+                depth += 1;
+            },
+            NodeEdge::End(_) => depth -= 1,
+        }
+    }
+
+    order
+}
+
+/// Checks that `actual` contains the same nodes at the same depths as `expected`, in the same order
+pub fn check_document_order<T: Layout>(expected: &[(usize, &NodeData<T>)], actual: &[(usize, &NodeData<T>)])
+-> Result<(), InvariantViolation>
+{
+    match expected.iter().zip(actual.iter()).position(|(e, a)| e != a) {
+        Some(position) => Err(InvariantViolation::DocumentOrder { position }),
+        None if expected.len() != actual.len() => Err(InvariantViolation::DocumentOrder { position: expected.len().min(actual.len()) }),
+        None => Ok(()),
+    }
+}
+
+/// Appends a copy of the `child` to a copy of the `parent` and checks that the result is a
+/// valid hierarchy, contains the nodes of both DOMs and keeps the document order of both:
+/// the nodes of the `parent` first, then the nodes of the `child`, one level deeper.
+///
+/// The `parent` has to be a DOM whose current node is its root (which is the case for
+/// all DOMs built via the `Dom::with_*` functions), since `add_child` appends to the current node.
+pub fn check_add_child<T: Layout>(parent: &Dom<T>, child: &Dom<T>) -> Result<Dom<T>, InvariantViolation> {
+
+    let mut combined = parent.clone();
+    combined.add_child(child.clone());
+
+    check_hierarchy(&combined)?;
+    check_node_count(&combined, parent.len() + child.len())?;
+
+    {
+        let mut expected = document_order(parent);
+        expected.extend(document_order(child).into_iter().map(|(depth, node)| (depth + 1, node)));
+        check_document_order(&expected, &document_order(&combined))?;
+    }
+
+    Ok(combined)
+}
+
+#[cfg(test)]
+use quickcheck::{quickcheck, TestResult, StdThreadGen};
+#[cfg(test)]
+use id_tree::NodeId;
+
+#[cfg(test)]
+#[derive(Debug)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+#[cfg(test)]
+fn to_test_result(result: Result<(), InvariantViolation>) -> TestResult {
+    match result {
+        Ok(()) => TestResult::passed(),
+        Err(e) => TestResult::error(format!("{}", e)),
+    }
+}
+
+#[test]
+fn test_generated_shapes_respect_config() {
+
+    let mut g = StdThreadGen::new(100);
+
+    let config = DomShapeConfig { max_depth: 1, .. DomShapeConfig::default() };
+    for _ in 0..20 {
+        assert_eq!(DomShape::generate(&mut g, &config).len(), 1);
+    }
+
+    let config = DomShapeConfig { max_depth: 3, max_children: 2, label_percent: 0, attribute_percent: 100 };
+    for _ in 0..20 {
+        let shape = DomShape::generate(&mut g, &config);
+        assert!(shape.len() <= 1 + 2 + 4);
+        assert_eq!(shape.node_type, ShapeNodeType::Div);
+        assert_eq!(shape.ids.len(), IDS.len());
+        check_hierarchy(&shape.to_dom::<TestLayout>()).unwrap();
+    }
+}
+
+#[test]
+fn test_check_hierarchy_detects_broken_links() {
+
+    let mut dom = DomShape {
+        node_type: ShapeNodeType::Div,
+        ids: Vec::new(),
+        classes: Vec::new(),
+        children: vec![DomShape { node_type: ShapeNodeType::Label("a".into()), ids: Vec::new(), classes: Vec::new(), children: Vec::new() }; 3],
+    }.to_dom::<TestLayout>();
+
+    check_hierarchy(&dom).unwrap();
+
+    dom.arena.node_layout.internal[3].previous_sibling = Some(NodeId::new(1));
+    assert_eq!(check_hierarchy(&dom), Err(InvariantViolation::Hierarchy(DomError::BrokenSiblingLink { node: NodeId::new(3) })));
+}
+
+#[test]
+fn prop_add_child_preserves_validity_and_count() {
+    fn prop(parent: DomShape, child: DomShape) -> TestResult {
+        to_test_result(check_add_child(&parent.to_dom::<TestLayout>(), &child.to_dom()).map(|_| ()))
+    }
+    quickcheck(prop as fn(DomShape, DomShape) -> TestResult);
+}
+
+#[test]
+fn prop_from_iter_equals_add_child() {
+    fn prop(shapes: Vec<DomShape>) -> bool {
+        let collected = shapes.iter().map(|s| s.to_node_data()).collect::<Dom<TestLayout>>();
+        let mut appended = Dom::div();
+        appended.arena.node_data[appended.root].is_synthetic_root = true;
+        for shape in &shapes {
+            appended.add_child(DomShape { children: Vec::new(), .. shape.clone() }.to_dom());
+        }
+        check_hierarchy(&collected).is_ok() && collected == appended
+    }
+    quickcheck(prop as fn(Vec<DomShape>) -> bool);
+}
+
+#[test]
+fn prop_snapshot_round_trip_preserves_equality() {
+    fn prop(shape: DomShape) -> bool {
+        let dom = shape.to_dom::<TestLayout>();
+        let parsed = DomShape::from_snapshot(&dom.serialize_for_snapshot());
+        parsed.as_ref() == Ok(&shape) && parsed.unwrap().to_dom::<TestLayout>() == dom
+    }
+    quickcheck(prop as fn(DomShape) -> bool);
+}
+
+#[test]
+fn prop_equal_doms_have_equal_snapshots() {
+    fn prop(a: DomShape, b: DomShape) -> bool {
+
+        let a_dom = a.to_dom::<TestLayout>();
+        let b_dom = b.to_dom::<TestLayout>();
+
+        // Same DOM, but with the ids and classes written directly in reverse order, with duplicates
+        let mut permuted = a.to_dom::<TestLayout>();
+        for node in permuted.arena.node_data.iter_mut() {
+            node.ids.reverse();
+            node.classes.reverse();
+            if let Some(class) = node.classes.first().cloned() {
+                node.classes.push(class);
+            }
+            node.invalidate_hash_cache();
+        }
+
+        a_dom == permuted &&
+        a_dom.serialize_for_snapshot() == permuted.serialize_for_snapshot() &&
+        (a_dom != b_dom || a_dom.serialize_for_snapshot() == b_dom.serialize_for_snapshot())
+    }
+    quickcheck(prop as fn(DomShape, DomShape) -> bool);
+}
+
+#[test]
+fn test_compact_after_detaching_random_subtrees() {
+
+    let mut g = StdThreadGen::new(100);
+
+    for _ in 0..20 {
+
+        // 500 nodes, each appended to a random earlier node, labeled with its (original) NodeId
+        let mut dom = Dom::<TestLayout>::label("0");
+        for idx in 1..500 {
+            let parent = NodeId::new(pick(&mut g, idx));
+            dom.append_child_at(parent, Dom::label(idx.to_string())).unwrap();
+        }
+
+        // Detached subtrees stay in the arena as unreachable nodes
+        for _ in 0..pick(&mut g, 20) + 1 {
+            let node_id = NodeId::new(pick(&mut g, dom.len() - 1) + 1);
+            dom.detach_subtree(node_id).unwrap();
+        }
+
+        let expected = document_order(&dom).into_iter().map(|(depth, node)| (depth, node.clone())).collect::<Vec<_>>();
+        let id_map = dom.compact();
+
+        check_hierarchy(&dom).unwrap();
+        check_node_count(&dom, expected.len()).unwrap();
+        check_document_order(&expected.iter().map(|(depth, node)| (*depth, node)).collect::<Vec<_>>(), &document_order(&dom)).unwrap();
+
+        match id_map {
+            None => assert_eq!(expected.len(), 500),
+            Some(id_map) => {
+                assert_eq!(id_map.len(), expected.len());
+                for (old_id, new_id) in &id_map {
+                    assert_eq!(dom.get_node_data(*new_id).unwrap().get_label_text(), Some(&*old_id.index().to_string()));
+                }
+            },
+        }
+
+        // Compacting a dense arena doesn't change anything
+        let compacted = dom.clone();
+        assert_eq!(dom.compact(), None);
+        assert!(dom == compacted);
+
+        // `remove_subtree` compacts right away
+        while dom.len() > 1 {
+            let node_id = NodeId::new(pick(&mut g, dom.len() - 1) + 1);
+            dom.remove_subtree(node_id).unwrap();
+            check_hierarchy(&dom).unwrap();
+        }
+    }
+}
@@ -0,0 +1,704 @@
+//! Headless rendering of a `Layout` into an RGBA image, for visual regression tests
+//!
+//! `render_to_image()` runs the same pipeline as a frame of the `App` (layout,
+//! styling, layout solving, display list building and rendering via webrender),
+//! but into a hidden window whose pixels are read back afterwards.
+//!
+//! To make the output reproducible across machines, all builtin fonts (`font-family: sans-serif`,
+//! etc.) are replaced with a bundled font, the system fonts are never queried. Since
+//! GL textures and iframes draw arbitrary content, they can optionally be replaced
+//! by a solid placeholder rectangle, see `HeadlessOptions::callback_placeholders`.
+//!
+//! Note that a headless rendering still needs an OpenGL context (either a hardware
+//! or an OSMesa software context), so tests using this module can't run on machines
+//! without a display server.
+
+use std::{
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+#[cfg(feature = "image_loading")]
+use std::{io::Error as IoError, path::Path};
+use glium::glutin::{Event, dpi::LogicalSize};
+#[cfg(feature = "image_loading")]
+use image::ImageError;
+use azul_css::{Css, FontId};
+use {
+    app::{render, render_inner, convert_window_size},
+    app_resources::{AppResources, RawImageFormat},
+    default_callbacks::DefaultCallbackSystem,
+    dom::{Dom, TagIdGenerator, DomAllocator},
+    font::FontError,
+    profiling::FrameProfiler,
+    traits::Layout,
+    ui_description::UiDescription,
+    window::{Window, WindowCreateOptions, WindowCreateError, FakeWindow, LayoutSnapshot, RendererType, get_gl_context},
+    menu::WindowMenu,
+};
+#[cfg(not(test))]
+use window::LayoutInfo;
+
+/// Random DOMs and DOM invariant checkers for property-based tests (with the `property-testing` feature)
+#[cfg(any(test, feature = "property-testing"))]
+pub mod dom_properties;
+#[cfg(any(test, feature = "property-testing"))]
+pub use self::dom_properties::{
+    DomShape, DomShapeConfig, ShapeNodeType, SnapshotParseError, InvariantViolation,
+    check_hierarchy, check_node_count, check_document_order, check_add_child, document_order,
+};
+
+/// The font that replaces all builtin fonts in headless rendering (KoHo Light, OFL licensed)
+pub const DEFAULT_TEST_FONT: &[u8] = include_bytes!("../../../assets/fonts/KoHo-Light.ttf");
+
+/// Pixels of a rendered frame
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawImage {
+    /// RGBA8 pixels, row by row, starting at the top left corner
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl RawImage {
+
+    /// Returns the RGBA value of the pixel at (x, y), `None` if the pixel is out of bounds
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let idx = ((y * self.width + x) * 4) as usize;
+        Some([self.pixels[idx], self.pixels[idx + 1], self.pixels[idx + 2], self.pixels[idx + 3]])
+    }
+
+    /// Compares two images channel by channel. Returns how many pixels have
+    /// at least one channel that differs by more than `tolerance`, or `None`
+    /// if the images don't have the same size.
+    pub fn count_different_pixels(&self, other: &RawImage, tolerance: u8) -> Option<usize> {
+        if self.width != other.width || self.height != other.height {
+            return None;
+        }
+        Some(self.pixels.chunks(4).zip(other.pixels.chunks(4))
+            .filter(|(a, b)| a.iter().zip(b.iter()).any(|(a, b)| (*a as i16 - *b as i16).abs() > tolerance as i16))
+            .count())
+    }
+
+    /// Returns whether the two images have the same size and
+    /// no channel of any pixel differs by more than `tolerance`
+    pub fn matches(&self, other: &RawImage, tolerance: u8) -> bool {
+        self.count_different_pixels(other, tolerance) == Some(0)
+    }
+
+    /// Loads an image (usually a golden PNG) from a file and converts it to RGBA8
+    #[cfg(feature = "image_loading")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ImageError> {
+        let image = ::image::open(path)?.to_rgba();
+        let (width, height) = image.dimensions();
+        Ok(Self { pixels: image.into_raw(), width, height })
+    }
+
+    /// Saves the image to a file, the format is determined by the file extension
+    #[cfg(feature = "image_loading")]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), IoError> {
+        ::image::save_buffer(path, &self.pixels, self.width, self.height, ::image::RGBA(8))
+    }
+}
+
+/// Configuration for `render_to_image_with_options()`
+#[derive(Debug, Clone)]
+pub struct HeadlessOptions {
+    /// Replaces every builtin font (i.e. every font that is not loaded via `add_font`)
+    pub builtin_font: Rc<Vec<u8>>,
+    /// Extra fonts, referenced by `FontId::ExternalFont` in the CSS
+    pub fonts: Vec<(FontId, Vec<u8>)>,
+    /// Images, referenced by their ID in the CSS: BGRA8 pixels and the (width, height)
+    pub images: Vec<(String, Vec<u8>, (u32, u32))>,
+    /// Draw a magenta rectangle instead of invoking the `GlTextureCallback`s and
+    /// `IFrameCallback`s, for callbacks that don't render deterministically
+    pub callback_placeholders: bool,
+    /// Which OpenGL implementation to render with (`Software` = OSMesa)
+    pub renderer_type: RendererType,
+    /// Maximum time to wait for webrender to finish the frame
+    pub timeout: Duration,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            builtin_font: Rc::new(DEFAULT_TEST_FONT.to_vec()),
+            fonts: Vec::new(),
+            images: Vec::new(),
+            callback_placeholders: false,
+            renderer_type: RendererType::Default,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Error returned by `render_to_image()`
+#[derive(Debug)]
+pub enum HeadlessError {
+    /// Could not create the (hidden) window or the OpenGL context
+    WindowCreate(WindowCreateError),
+    /// One of the `HeadlessOptions::fonts` could not be loaded
+    Font(FontError),
+    /// Webrender did not finish the frame within `HeadlessOptions::timeout`
+    Timeout,
+}
+
+impl_display! {
+    HeadlessError,
+    {
+        WindowCreate(e) => format!("Could not create the headless window: {}", e),
+        Font(e) => format!("Could not load font: {}", e),
+        Timeout => "Timed out while waiting for webrender to render the frame",
+    }
+}
+
+impl_from!(WindowCreateError, HeadlessError::WindowCreate);
+impl_from!(FontError, HeadlessError::Font);
+
+/// Renders the DOM of `data.layout()`, styled with `css`, into an image of
+/// `size` physical pixels - the layout sees a window of `size / hidpi` logical pixels.
+///
+/// Uses the default `HeadlessOptions`, i.e. the bundled `DEFAULT_TEST_FONT`.
+pub fn render_to_image<T: Layout + Clone>(data: &T, css: Css, size: (u32, u32), hidpi: f32)
+-> Result<RawImage, HeadlessError>
+{
+    render_to_image_with_options(data, css, size, hidpi, &HeadlessOptions::default())
+}
+
+/// Same as `render_to_image`, but with custom fonts and placeholder settings
+pub fn render_to_image_with_options<T: Layout + Clone>(
+    data: &T,
+    css: Css,
+    size: (u32, u32),
+    hidpi: f32,
+    options: &HeadlessOptions)
+-> Result<RawImage, HeadlessError>
+{
+    use gleam::gl;
+
+    let (width, height) = size;
+    let hidpi = hidpi as f64;
+
+    let mut create_options = WindowCreateOptions::default();
+    create_options.state.is_visible = false;
+    create_options.state.has_decorations = false;
+    create_options.renderer_type = options.renderer_type;
+
+    let mut window = Window::new(create_options, css)?;
+
+    // The framebuffer has to have exactly `size` physical pixels, regardless
+    // of the DPI of the monitor, while the layout uses the requested DPI
+    let monitor_hidpi = window.display.gl_window().get_hidpi_factor();
+    window.display.gl_window().window().set_inner_size(LogicalSize::new(width as f64 / monitor_hidpi, height as f64 / monitor_hidpi));
+    window.state.size.dimensions = LogicalSize::new(width as f64 / hidpi, height as f64 / hidpi);
+    window.state.size.hidpi_factor = hidpi;
+
+    let mut app_resources = AppResources::default();
+    app_resources.builtin_font_override = Some(options.builtin_font.clone());
+    for (font_id, font_bytes) in &options.fonts {
+        app_resources.add_font(font_id.clone(), &mut &font_bytes[..])?;
+    }
+    for (image_id, pixels, image_size) in &options.images {
+        app_resources.add_image_raw(image_id.clone(), pixels.clone(), *image_size, RawImageFormat::BGRA8);
+    }
+
+    let mut fake_window = FakeWindow {
+        state: window.state.clone(),
+        default_callbacks: DefaultCallbackSystem::new(),
+        read_only_window: window.display.clone(),
+        tag_ids: TagIdGenerator::new(),
+        dom_allocator: DomAllocator::new(),
+        profiler: FrameProfiler::new(),
+        render_callback_placeholders: options.callback_placeholders,
+        layout_snapshot: LayoutSnapshot::default(),
+        menu: WindowMenu::default(),
+    };
+
+    let mut app_data = Arc::new(Mutex::new(data.clone()));
+
+    let dom: Dom<T> = {
+        #[cfg(test)]{
+            data.layout()
+        }
+
+        #[cfg(not(test))]{
+            data.layout(LayoutInfo {
+                window: &mut fake_window,
+                resources: &app_resources,
+            })
+        }
+    };
+
+    let mut ui_state = dom.into_ui_state(&mut fake_window.tag_ids);
+
+    let ui_description = UiDescription::match_css_to_dom(
+        &mut ui_state,
+        &window.css,
+        &window.css_index,
+        &mut window.state.focused_node,
+        &mut window.state.pending_focus_target,
+        &window.state.hovered_nodes,
+        false,
+        &mut fake_window.tag_ids,
+    );
+
+    render(&mut app_data, &ui_description, &ui_state, &mut window, &mut fake_window, &mut app_resources);
+
+    // The frame is built on the webrender backend thread - wait until webrender
+    // wakes up the events loop, then draw the finished frame into the back buffer
+    let start = Instant::now();
+    let mut frame_is_ready = false;
+    while !frame_is_ready {
+        window.events_loop.poll_events(|e| if let Event::Awakened = e { frame_is_ready = true; });
+        if !frame_is_ready {
+            if start.elapsed() > options.timeout {
+                return Err(HeadlessError::Timeout);
+            }
+            ::std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    let (_, framebuffer_size) = convert_window_size(&window.state.size);
+    window.renderer.as_mut().unwrap().update();
+    render_inner(&mut window, framebuffer_size);
+
+    let gl_context = get_gl_context(&window.display)?;
+    let pixels = gl_context.read_pixels(0, 0, width as i32, height as i32, gl::RGBA, gl::UNSIGNED_BYTE);
+
+    Ok(RawImage {
+        pixels: flip_rows(pixels, width as usize * 4),
+        width,
+        height,
+    })
+}
+
+/// OpenGL returns the rows bottom-up, `RawImage` stores them top-down
+fn flip_rows(pixels: Vec<u8>, row_len: usize) -> Vec<u8> {
+    if row_len == 0 {
+        return pixels;
+    }
+    pixels.chunks(row_len).rev().flat_map(|row| row.iter().cloned()).collect()
+}
+
+#[test]
+fn test_flip_rows() {
+    let pixels = vec![1, 1, 2, 2, 3, 3];
+    assert_eq!(flip_rows(pixels, 2), vec![3, 3, 2, 2, 1, 1]);
+}
+
+#[test]
+fn test_raw_image_tolerance() {
+    let a = RawImage { pixels: vec![10, 20, 30, 255, 0, 0, 0, 255], width: 2, height: 1 };
+    let b = RawImage { pixels: vec![12, 20, 30, 255, 0, 0, 9, 255], width: 2, height: 1 };
+    assert_eq!(a.count_different_pixels(&b, 2), Some(1));
+    assert!(!a.matches(&b, 2));
+    assert!(a.matches(&b, 9));
+    assert_eq!(a.count_different_pixels(&RawImage { pixels: vec![], width: 0, height: 0 }, 255), None);
+}
+
+/// Compares the `image` against the golden image `tests/golden/<name>`, allowing a few
+/// pixels of anti-aliasing differences between GPU drivers. A missing golden image is a
+/// failure - set `AZUL_UPDATE_GOLDEN=1` to (re-)generate it after an intentional change.
+#[cfg(all(test, feature = "image_loading"))]
+fn assert_matches_golden(image: &RawImage, name: &str, tolerance: u8) {
+
+    use std::{env, path::PathBuf};
+
+    let golden_path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name].iter().collect();
+
+    if env::var(::snapshot::UPDATE_SNAPSHOTS_ENV_VAR).is_ok() {
+        ::std::fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+        image.save(&golden_path).unwrap();
+        return;
+    }
+
+    let golden = RawImage::load(&golden_path).unwrap_or_else(|e| {
+        panic!("could not load the golden image {}: {} - set {}=1 to generate it",
+            golden_path.display(), e, ::snapshot::UPDATE_SNAPSHOTS_ENV_VAR)
+    });
+    let different_pixels = image.count_different_pixels(&golden, tolerance)
+        .expect("rendered image has a different size than the golden image");
+    assert!(different_pixels <= 16, "{} pixels differ from {}", different_pixels, golden_path.display());
+}
+
+/// Renders a small styled DOM and compares it against `tests/golden/styled_dom.png`.
+/// Only axis-aligned boxes, so that the golden image doesn't depend on the anti-aliasing
+/// of the GPU driver (text is checked by `test_render_text_is_deterministic`).
+///
+/// Needs an OpenGL context, so it is ignored by default - run it with
+/// `cargo test --features image_loading -- --ignored`. Set `AZUL_UPDATE_GOLDEN=1`
+/// to (re-)generate the golden image after an intentional change.
+#[cfg(all(feature = "image_loading", feature = "css-parser"))]
+#[test]
+#[ignore]
+fn test_render_to_image_matches_golden() {
+
+    use dom::NodeType;
+
+    #[derive(Clone)]
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div).with_id("root")
+                .with_child(Dom::new(NodeType::Div).with_class("box").with_id("red"))
+                .with_child(Dom::new(NodeType::Div).with_class("box").with_id("blue"))
+        }
+    }
+
+    // red: (0, 0) - (40, 40), blue: (60, 20) - (100, 60)
+    let css = ::css::from_str("
+        #root { background-color: #ffffff; flex-direction: row; align-items: flex-start; }
+        .box { width: 40px; height: 40px; }
+        #red { background-color: #ff0000; }
+        #blue { background-color: #0000ff; margin-left: 20px; margin-top: 20px; }
+    ").unwrap();
+
+    let image = render_to_image(&TestLayout { }, css, (160, 80), 1.0).unwrap();
+
+    const TOLERANCE: u8 = 8;
+    assert_matches_golden(&image, "styled_dom.png", TOLERANCE);
+}
+
+/// Renders a label twice with the bundled test font: the text has to be drawn
+/// and both images have to be identical.
+///
+/// Needs an OpenGL context, so it is ignored by default - run it with
+/// `cargo test --features css-parser -- --ignored`.
+#[cfg(feature = "css-parser")]
+#[test]
+#[ignore]
+fn test_render_text_is_deterministic() {
+
+    use dom::NodeType;
+
+    #[derive(Clone)]
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div).with_id("root")
+                .with_child(Dom::new(NodeType::Label("Hello".into())))
+        }
+    }
+
+    let css = || ::css::from_str("
+        #root { background-color: #ffffff; }
+        * { font-size: 16px; color: #000000; }
+    ").unwrap();
+
+    let first = render_to_image(&TestLayout { }, css(), (160, 80), 1.0).unwrap();
+    let second = render_to_image(&TestLayout { }, css(), (160, 80), 1.0).unwrap();
+
+    assert!(first.pixels.chunks(4).any(|pixel| pixel[0] < 128), "the label was not drawn");
+    assert_eq!(first, second);
+}
+
+/// Renders the corner cases of rounded clipping, offset box shadows and outlines
+/// and checks the pixels around their edges.
+///
+/// Needs an OpenGL context, so it is ignored by default - run it with
+/// `cargo test --features css-parser -- --ignored`.
+#[cfg(feature = "css-parser")]
+#[test]
+#[ignore]
+fn test_render_rounded_clip_shadow_and_outline() {
+
+    use dom::NodeType;
+
+    #[derive(Clone)]
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div).with_id("root")
+                .with_child(Dom::new(NodeType::Div).with_class("box").with_id("card")
+                    .with_child(Dom::new(NodeType::Div).with_id("card-content")))
+                .with_child(Dom::new(NodeType::Div).with_class("box").with_id("shadow"))
+                .with_child(Dom::new(NodeType::Div).with_class("box").with_id("outline"))
+        }
+    }
+
+    // Three 60x60 boxes at x = 20, 120 and 220, y = 20
+    let css = ::css::from_str("
+        #root { background-color: #ffffff; flex-direction: row; }
+        .box { width: 60px; height: 60px; margin: 20px; }
+        #card { border-radius: 20px; }
+        #card-content { width: 60px; height: 60px; background-color: #ff0000; }
+        #shadow { background-color: #000000; box-shadow: 10px 0px #0000ff; }
+        #outline { background-color: #000000; outline: 4px solid #ff00ff; }
+    ").unwrap();
+
+    let image = render_to_image(&TestLayout { }, css, (300, 100), 1.0).unwrap();
+
+    const TOLERANCE: u8 = 8;
+    let assert_pixel = |x: u32, y: u32, expected: [u8; 4]| {
+        let actual = image.get_pixel(x, y).unwrap();
+        let matches = actual.iter().zip(expected.iter()).all(|(a, e)| (*a as i32 - *e as i32).abs() <= TOLERANCE as i32);
+        assert!(matches, "pixel ({}, {}): expected {:?}, got {:?}", x, y, expected, actual);
+    };
+
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const MAGENTA: [u8; 4] = [255, 0, 255, 255];
+
+    // The child of the rounded card is clipped at the corners of the card
+    assert_pixel(50, 50, RED);
+    assert_pixel(22, 22, WHITE);
+    assert_pixel(77, 77, WHITE);
+
+    // The shadow is moved to the right by the offset and not clipped away
+    assert_pixel(150, 50, BLACK);
+    assert_pixel(185, 50, BLUE);
+    assert_pixel(115, 50, WHITE);
+
+    // The outline is drawn around the box without moving it
+    assert_pixel(218, 50, MAGENTA);
+    assert_pixel(221, 50, BLACK);
+    assert_pixel(215, 50, WHITE);
+}
+
+/// Renders a rotated button and checks that it is drawn rotated, while
+/// its (untransformed) layout doesn't move its siblings.
+///
+/// Needs an OpenGL context, so it is ignored by default - run it with
+/// `cargo test --features css-parser -- --ignored`.
+#[cfg(feature = "css-parser")]
+#[test]
+#[ignore]
+fn test_render_rotated_node() {
+
+    use dom::NodeType;
+
+    #[derive(Clone)]
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div).with_id("root")
+                .with_child(Dom::new(NodeType::Div).with_id("button"))
+                .with_child(Dom::new(NodeType::Div).with_id("sibling"))
+        }
+    }
+
+    // The button is laid out at (100, 100) - (200, 120), rotated around its
+    // center (150, 110) it covers (140, 60) - (160, 160)
+    let css = ::css::from_str("
+        #root { background-color: #ffffff; flex-direction: column; }
+        #button { width: 100px; height: 20px; margin-left: 100px; margin-top: 100px; background-color: #ff0000; transform: rotate(90deg); }
+        #sibling { width: 20px; height: 20px; background-color: #0000ff; }
+    ").unwrap();
+
+    let image = render_to_image(&TestLayout { }, css, (300, 200), 1.0).unwrap();
+
+    const TOLERANCE: u8 = 8;
+    let assert_pixel = |x: u32, y: u32, expected: [u8; 4]| {
+        let actual = image.get_pixel(x, y).unwrap();
+        let matches = actual.iter().zip(expected.iter()).all(|(a, e)| (*a as i32 - *e as i32).abs() <= TOLERANCE as i32);
+        assert!(matches, "pixel ({}, {}): expected {:?}, got {:?}", x, y, expected, actual);
+    };
+
+    assert_pixel(150, 70, [255, 0, 0, 255]);
+    assert_pixel(150, 150, [255, 0, 0, 255]);
+    assert_pixel(110, 110, [255, 255, 255, 255]);
+    assert_pixel(190, 110, [255, 255, 255, 255]);
+    // The sibling is placed below the untransformed button
+    assert_pixel(10, 130, [0, 0, 255, 255]);
+}
+
+/// Renders three overlapping siblings with the z-indices 2, 1 and 3, checks the
+/// pixels of the overlapping areas and compares the image against `tests/golden/z_index.png`.
+///
+/// Needs an OpenGL context, so it is ignored by default - run it with
+/// `cargo test --features image_loading -- --ignored`. Set `AZUL_UPDATE_GOLDEN=1`
+/// to (re-)generate the golden image after an intentional change.
+#[cfg(all(feature = "image_loading", feature = "css-parser"))]
+#[test]
+#[ignore]
+fn test_render_z_index() {
+
+    use dom::NodeType;
+
+    #[derive(Clone)]
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div).with_id("root")
+                .with_child(Dom::new(NodeType::Div).with_class("layer").with_id("red"))
+                .with_child(Dom::new(NodeType::Div).with_class("layer").with_id("green"))
+                .with_child(Dom::new(NodeType::Div).with_class("layer").with_id("blue"))
+        }
+    }
+
+    // red: (20, 20) - (80, 80), green: (40, 40) - (100, 100), blue: (60, 60) - (120, 120)
+    let css = ::css::from_str("
+        #root { background-color: #ffffff; }
+        .layer { position: absolute; width: 60px; height: 60px; }
+        #red { top: 20px; left: 20px; z-index: 2; background-color: #ff0000; }
+        #green { top: 40px; left: 40px; z-index: 1; background-color: #00ff00; }
+        #blue { top: 60px; left: 60px; z-index: 3; background-color: #0000ff; }
+    ").unwrap();
+
+    let image = render_to_image(&TestLayout { }, css, (140, 140), 1.0).unwrap();
+
+    const TOLERANCE: u8 = 8;
+    let assert_pixel = |x: u32, y: u32, expected: [u8; 4]| {
+        let actual = image.get_pixel(x, y).unwrap();
+        let matches = actual.iter().zip(expected.iter()).all(|(a, e)| (*a as i32 - *e as i32).abs() <= TOLERANCE as i32);
+        assert!(matches, "pixel ({}, {}): expected {:?}, got {:?}", x, y, expected, actual);
+    };
+
+    // Red (2) is drawn over green (1), blue (3) over both
+    assert_pixel(50, 50, [255, 0, 0, 255]);
+    assert_pixel(90, 50, [0, 255, 0, 255]);
+    assert_pixel(70, 70, [0, 0, 255, 255]);
+    assert_pixel(90, 90, [0, 0, 255, 255]);
+    assert_pixel(10, 10, [255, 255, 255, 255]);
+
+    assert_matches_golden(&image, "z_index.png", TOLERANCE);
+}
+
+/// Renders a box with a rounded gradient border and compares it against `tests/golden/border_gradient.png`.
+///
+/// Needs an OpenGL context, so it is ignored by default - run it with
+/// `cargo test --features image_loading -- --ignored`. Set `AZUL_UPDATE_GOLDEN=1`
+/// to (re-)generate the golden image after an intentional change.
+#[cfg(all(feature = "image_loading", feature = "css-parser"))]
+#[test]
+#[ignore]
+fn test_render_gradient_border_with_radius() {
+
+    use dom::NodeType;
+
+    #[derive(Clone)]
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div).with_id("root")
+                .with_child(Dom::new(NodeType::Div).with_id("card"))
+        }
+    }
+
+    // The card is at (20, 20) - (100, 100), the inner edge of the border at (30, 30) - (90, 90)
+    // is rounded with a 10px radius. The left half of the gradient is red, the right half blue.
+    let css = ::css::from_str("
+        #root { background-color: #ffffff; }
+        #card {
+            width: 80px; height: 80px; margin: 20px;
+            background-color: #00ff00;
+            border: 10px solid #000000;
+            border-radius: 20px;
+            border-image: linear-gradient(to right, #ff0000 50%, #0000ff 50%);
+        }
+    ").unwrap();
+
+    let image = render_to_image(&TestLayout { }, css, (120, 120), 1.0).unwrap();
+
+    const TOLERANCE: u8 = 8;
+    let assert_pixel = |x: u32, y: u32, expected: [u8; 4]| {
+        let actual = image.get_pixel(x, y).unwrap();
+        let matches = actual.iter().zip(expected.iter()).all(|(a, e)| (*a as i32 - *e as i32).abs() <= TOLERANCE as i32);
+        assert!(matches, "pixel ({}, {}): expected {:?}, got {:?}", x, y, expected, actual);
+    };
+
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    // The gradient fills the border instead of the border color
+    assert_pixel(25, 60, RED);
+    assert_pixel(95, 60, BLUE);
+    assert_pixel(60, 60, GREEN);
+    // The outer corner is rounded, the inner corner as well
+    assert_pixel(22, 22, WHITE);
+    assert_pixel(31, 31, RED);
+    assert_pixel(36, 36, GREEN);
+
+    assert_matches_golden(&image, "border_gradient.png", TOLERANCE);
+}
+
+/// Renders the same sliced border image on a small and a large box: the corners
+/// keep their size, the edges and the center are stretched. Compares the image
+/// against `tests/golden/border_image.png`.
+///
+/// Needs an OpenGL context, so it is ignored by default - run it with
+/// `cargo test --features image_loading -- --ignored`. Set `AZUL_UPDATE_GOLDEN=1`
+/// to (re-)generate the golden image after an intentional change.
+#[cfg(all(feature = "image_loading", feature = "css-parser"))]
+#[test]
+#[ignore]
+fn test_render_sliced_border_image() {
+
+    use dom::NodeType;
+
+    #[derive(Clone)]
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div).with_id("root")
+                .with_child(Dom::new(NodeType::Div).with_class("frame").with_id("small"))
+                .with_child(Dom::new(NodeType::Div).with_class("frame").with_id("large"))
+        }
+    }
+
+    // 6x6 image, sliced 2px from every edge: red corners, blue edges, green center (BGRA8)
+    let frame_pixels = (0..6).flat_map(|y| (0..6).map(move |x| (x, y))).flat_map(|(x, y)| {
+        let is_edge_x = x < 2 || x >= 4;
+        let is_edge_y = y < 2 || y >= 4;
+        match (is_edge_x, is_edge_y) {
+            (true, true) => vec![0, 0, 255, 255],
+            (false, false) => vec![0, 255, 0, 255],
+            _ => vec![255, 0, 0, 255],
+        }
+    }).collect::<Vec<u8>>();
+
+    let options = HeadlessOptions {
+        images: vec![(String::from("frame"), frame_pixels, (6, 6))],
+        .. HeadlessOptions::default()
+    };
+
+    // small: (20, 20) - (80, 80), large: (120, 20) - (220, 60)
+    let css = ::css::from_str("
+        #root { background-color: #ffffff; flex-direction: row; align-items: flex-start; }
+        .frame { margin: 20px; border: 10px solid #000000; border-image: image(\"frame\") 2 fill; }
+        #small { width: 60px; height: 60px; }
+        #large { width: 100px; height: 40px; }
+    ").unwrap();
+
+    let image = render_to_image_with_options(&TestLayout { }, css, (240, 100), 1.0, &options).unwrap();
+
+    const TOLERANCE: u8 = 8;
+    let assert_pixel = |x: u32, y: u32, expected: [u8; 4]| {
+        let actual = image.get_pixel(x, y).unwrap();
+        let matches = actual.iter().zip(expected.iter()).all(|(a, e)| (*a as i32 - *e as i32).abs() <= TOLERANCE as i32);
+        assert!(matches, "pixel ({}, {}): expected {:?}, got {:?}", x, y, expected, actual);
+    };
+
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    for &(left, top, width, height) in &[(20, 20, 60, 60), (120, 20, 100, 40)] {
+        // The corners are as large as the border at both sizes
+        assert_pixel(left + 5, top + 5, RED);
+        assert_pixel(left + width - 5, top + height - 5, RED);
+        // The edges are stretched along the sides
+        assert_pixel(left + width / 2, top + 5, BLUE);
+        assert_pixel(left + 5, top + height / 2, BLUE);
+        // `fill` draws the center
+        assert_pixel(left + width / 2, top + height / 2, GREEN);
+    }
+    assert_pixel(10, 10, WHITE);
+
+    assert_matches_golden(&image, "border_image.png", TOLERANCE);
+}
@@ -1,14 +1,26 @@
-use std::sync::atomic::{Ordering, AtomicUsize};
-use azul_css::{FontId, StyleFontSize};
+use std::{
+    rc::Rc,
+    hash::{Hash, Hasher},
+    collections::hash_map::DefaultHasher,
+    sync::atomic::{Ordering, AtomicUsize},
+};
+use azul_css::{FontId, StyleFontSize, StyleLetterSpacing};
 use {
     FastHashMap,
-    text_layout::Words,
+    text_layout::{Words, LineBreaks, TextSizePx},
     app_resources::AppResources,
 };
 
+/// Default for `TextCache::set_memory_limit`: 32 MB of shaped words and line breaks
+pub const DEFAULT_TEXT_CACHE_MEMORY_LIMIT: usize = 32 * 1024 * 1024;
+
+/// Line breaks are cached per width bucket of this many pixels, so that resizing
+/// a window doesn't fill the cache with one entry per pixel of width
+pub const LINE_BREAK_WIDTH_BUCKET: f32 = 16.0;
+
 static TEXT_ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
-fn new_text_id() -> TextId {
+pub(crate) fn new_text_id() -> TextId {
     let unique_id = TEXT_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
     TextId {
         inner: unique_id
@@ -21,6 +33,20 @@ pub struct TextId {
     inner: usize,
 }
 
+impl TextId {
+
+    /// Returns the number behind the ID, for serializing it
+    pub(crate) fn to_raw(&self) -> usize {
+        self.inner
+    }
+
+    /// Re-creates an ID from `to_raw()`. The ID only refers to the same text
+    /// in the text cache of the app that the ID was serialized in.
+    pub(crate) fn from_raw(inner: usize) -> Self {
+        Self { inner }
+    }
+}
+
 /// Cache for accessing large amounts of text
 #[derive(Debug, Default, Clone)]
 pub struct TextCache {
@@ -35,6 +61,8 @@ pub struct TextCache {
     /// FontId -> StyleFontSize (to categorize by size within a font)
     /// StyleFontSize -> layouted words (to cache the glyph widths on a per-font-size basis)
     pub layouted_strings_cache: FastHashMap<TextId, FastHashMap<FontId, FastHashMap<StyleFontSize, Words>>>,
+    /// Shaped words and line breaks, shared between all nodes that display the same text
+    pub(crate) shaped_text: ShapedTextCache,
 }
 
 impl TextCache {
@@ -65,6 +93,202 @@ impl TextCache {
     pub fn clear_all_texts(&mut self) {
         self.string_cache.clear();
         self.layouted_strings_cache.clear();
+        self.shaped_text.clear();
+    }
+
+    /// Sets how many bytes the shared shaped words and line breaks may take up.
+    /// Once the limit is reached, the least recently used entries are evicted.
+    ///
+    /// Does not affect the texts cached via `cache_text`, these are only removed via `delete_text`.
+    pub fn set_memory_limit(&mut self, bytes: usize) {
+        self.shaped_text.memory_limit = bytes;
+        self.shaped_text.make_room_for(0);
+    }
+
+    /// Returns how many bytes the shared shaped words and line breaks currently take up
+    pub fn get_memory_usage(&self) -> usize {
+        self.shaped_text.memory_used
+    }
+
+    /// Returns how often a text had to be shaped because it wasn't in the shared cache
+    pub fn get_shaping_count(&self) -> usize {
+        self.shaped_text.shaping_count
+    }
+}
+
+/// Hash of everything that influences the shaping of a text: the text content
+/// (or the `TextId` for cached texts), the font, the font size and the letter spacing
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ShapedTextKey(u64);
+
+impl ShapedTextKey {
+
+    pub(crate) fn new(text: &str, font_id: &FontId, font_size: &StyleFontSize, letter_spacing: Option<StyleLetterSpacing>) -> Self {
+        let mut hasher = DefaultHasher::default();
+        0_u8.hash(&mut hasher);
+        text.hash(&mut hasher);
+        Self::hash_shaping_features(hasher, font_id, font_size, letter_spacing)
+    }
+
+    /// The words of a `TextId` can be scaled from a different font size or be set via
+    /// `cache_text`, so they aren't shared with a text that happens to have the same content
+    pub(crate) fn for_text_id(text_id: &TextId, font_id: &FontId, font_size: &StyleFontSize, letter_spacing: Option<StyleLetterSpacing>) -> Self {
+        let mut hasher = DefaultHasher::default();
+        1_u8.hash(&mut hasher);
+        text_id.hash(&mut hasher);
+        Self::hash_shaping_features(hasher, font_id, font_size, letter_spacing)
+    }
+
+    fn hash_shaping_features(mut hasher: DefaultHasher, font_id: &FontId, font_size: &StyleFontSize, letter_spacing: Option<StyleLetterSpacing>) -> Self {
+        font_id.hash(&mut hasher);
+        font_size.hash(&mut hasher);
+        letter_spacing.hash(&mut hasher);
+        ShapedTextKey(hasher.finish())
+    }
+}
+
+/// Line breaks depend on the shaped words, the line height (via the vertical advance)
+/// and the width that the text is broken at (`None` if the text doesn't wrap)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct LineBreakKey {
+    text: ShapedTextKey,
+    vertical_advance: u32,
+    width_bucket: Option<i32>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum ShapedTextCacheKey {
+    Words(ShapedTextKey),
+    LineBreaks(LineBreakKey),
+}
+
+#[derive(Debug, Clone)]
+struct CachedItem<T> {
+    item: T,
+    size_in_bytes: usize,
+    last_used: usize,
+}
+
+/// Cache for the two stages of the text layout: The expensive shaping of the words
+/// (keyed by `ShapedTextKey`) and the cheaper line breaking of the shaped words
+/// (keyed by the `ShapedTextKey` and the width bucket).
+#[derive(Debug, Clone)]
+pub(crate) struct ShapedTextCache {
+    words: FastHashMap<ShapedTextKey, CachedItem<Rc<Words>>>,
+    line_breaks: FastHashMap<LineBreakKey, CachedItem<LineBreaks>>,
+    memory_limit: usize,
+    memory_used: usize,
+    /// Incremented on every access, for evicting the least recently used items
+    current_access: usize,
+    shaping_count: usize,
+}
+
+impl Default for ShapedTextCache {
+    fn default() -> Self {
+        Self {
+            words: FastHashMap::default(),
+            line_breaks: FastHashMap::default(),
+            memory_limit: DEFAULT_TEXT_CACHE_MEMORY_LIMIT,
+            memory_used: 0,
+            current_access: 0,
+            shaping_count: 0,
+        }
+    }
+}
+
+impl ShapedTextCache {
+
+    /// Returns the shaped words for the `key`, calls `shape_fn` if the text isn't in the cache yet
+    pub(crate) fn get_or_shape<F: FnOnce() -> Words>(&mut self, key: ShapedTextKey, shape_fn: F) -> Rc<Words> {
+        self.current_access += 1;
+
+        if !self.words.contains_key(&key) {
+            let words = shape_fn();
+            let size_in_bytes = words.get_heap_size();
+            self.shaping_count += 1;
+            self.make_room_for(size_in_bytes);
+            self.memory_used += size_in_bytes;
+            self.words.insert(key, CachedItem { item: Rc::new(words), size_in_bytes, last_used: 0 });
+        }
+
+        let cached = self.words.get_mut(&key).unwrap();
+        cached.last_used = self.current_access;
+        cached.item.clone()
+    }
+
+    /// Returns the line breaks for the shaped text `key` at the `max_horizontal_width`, calls
+    /// `break_fn` if there are no line breaks in the same width bucket that are valid for this width
+    pub(crate) fn get_or_break_lines<F: FnOnce() -> LineBreaks>(
+        &mut self,
+        key: ShapedTextKey,
+        vertical_advance: TextSizePx,
+        max_horizontal_width: Option<f32>,
+        break_fn: F)
+    -> &LineBreaks
+    {
+        self.current_access += 1;
+
+        let key = LineBreakKey {
+            text: key,
+            vertical_advance: vertical_advance.0.to_bits(),
+            width_bucket: max_horizontal_width.map(|width| (width / LINE_BREAK_WIDTH_BUCKET).floor() as i32),
+        };
+
+        let is_valid = self.line_breaks.get(&key)
+            .map(|cached| cached.item.is_valid_for(max_horizontal_width))
+            .unwrap_or(false);
+
+        if !is_valid {
+            let line_breaks = break_fn();
+            let size_in_bytes = line_breaks.get_heap_size();
+            if let Some(outdated) = self.line_breaks.remove(&key) {
+                self.memory_used -= outdated.size_in_bytes;
+            }
+            self.make_room_for(size_in_bytes);
+            self.memory_used += size_in_bytes;
+            self.line_breaks.insert(key, CachedItem { item: line_breaks, size_in_bytes, last_used: 0 });
+        }
+
+        let cached = self.line_breaks.get_mut(&key).unwrap();
+        cached.last_used = self.current_access;
+        &cached.item
+    }
+
+    /// Evicts the least recently used items until there is space for `size_in_bytes`.
+    ///
+    /// Evicts down to 3/4 of the memory limit, so that a full cache doesn't
+    /// have to be sorted again on every insertion.
+    fn make_room_for(&mut self, size_in_bytes: usize) {
+
+        if self.memory_used + size_in_bytes <= self.memory_limit {
+            return;
+        }
+
+        let target = (self.memory_limit / 4 * 3).saturating_sub(size_in_bytes);
+
+        let mut items_by_last_use = self.words.iter()
+            .map(|(key, cached)| (cached.last_used, ShapedTextCacheKey::Words(*key)))
+            .chain(self.line_breaks.iter().map(|(key, cached)| (cached.last_used, ShapedTextCacheKey::LineBreaks(*key))))
+            .collect::<Vec<_>>();
+
+        items_by_last_use.sort_by_key(|(last_used, _)| *last_used);
+
+        for (_, key) in items_by_last_use {
+            if self.memory_used <= target {
+                break;
+            }
+            let evicted_size = match key {
+                ShapedTextCacheKey::Words(key) => self.words.remove(&key).map(|cached| cached.size_in_bytes),
+                ShapedTextCacheKey::LineBreaks(key) => self.line_breaks.remove(&key).map(|cached| cached.size_in_bytes),
+            };
+            self.memory_used -= evicted_size.unwrap_or(0);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.words.clear();
+        self.line_breaks.clear();
+        self.memory_used = 0;
     }
 }
 
@@ -1,5 +1,6 @@
 #![allow(unused_variables, dead_code)]
 
+use std::rc::Rc;
 use webrender::api::LayoutPixel;
 use euclid::{TypedRect, TypedSize2D, TypedPoint2D};
 use rusttype::{Font, Scale, GlyphId};
@@ -10,7 +11,7 @@ use azul_css::{
 use {
     app_resources::AppResources,
     text_cache::TextInfo,
-    text_cache::{TextId, TextCache},
+    text_cache::{TextId, TextCache, ShapedTextKey},
 };
 
 pub use webrender::api::GlyphInstance;
@@ -132,6 +133,55 @@ impl Words {
             }
         }
     }
+
+    /// Returns the width of the longest line if the text isn't wrapped at all, i.e. the
+    /// `max-content` width of the text (the `min-content` width is the `longest_word_width`)
+    pub fn get_max_content_width(&self, font_metrics: &FontMetrics) -> f32 {
+
+        use self::SemanticWordItem::*;
+
+        let FontMetrics { space_width, tab_width, .. } = *font_metrics;
+
+        let mut max_line_width: f32 = 0.0;
+        let mut cur_line_cursor = 0.0;
+
+        for w in &self.items {
+            match w {
+                Word(w) => {
+                    // Same calculation as in `get_vertical_height`, but the trailing
+                    // space of the last word in a line doesn't count
+                    max_line_width = max_line_width.max(cur_line_cursor + w.total_width);
+                    cur_line_cursor += w.total_width + space_width.0;
+                },
+                Tab => {
+                    cur_line_cursor += tab_width.0;
+                    max_line_width = max_line_width.max(cur_line_cursor);
+                },
+                Return => cur_line_cursor = 0.0,
+            }
+        }
+
+        max_line_width
+    }
+
+    /// Approximate size of the words in bytes, for the memory limit of the `TextCache`
+    pub(crate) fn get_heap_size(&self) -> usize {
+        use std::mem::size_of;
+        let glyphs = self.items.iter().map(|item| match item {
+            SemanticWordItem::Word(w) => w.glyphs.capacity() * size_of::<GlyphInstance>(),
+            _ => 0,
+        }).sum::<usize>();
+        size_of::<Self>() + self.items.capacity() * size_of::<SemanticWordItem>() + glyphs
+    }
+}
+
+/// Output of the shaping stage, shared between all nodes that
+/// display the same text with the same font, font size and letter spacing
+#[derive(Debug, Clone)]
+pub struct ShapedWords {
+    /// Hash of the text and the shaping features, for looking up the line breaks
+    pub key: ShapedTextKey,
+    pub words: Rc<Words>,
 }
 
 /// A `Word` contains information about the layout of a single word
@@ -276,7 +326,7 @@ pub struct FontMetrics {
 ///   it is necessary for drawing the scrollbars later on, to determine the height of the bar. Contains
 ///   info about if the text has overflown the rectangle, and if yes, by how many pixels
 pub(crate) fn get_glyphs(
-    words: &Words,
+    shaped_words: &ShapedWords,
     app_resources: &mut AppResources,
     bounds: &TypedRect<f32, LayoutPixel>,
     target_font_id: &FontId,
@@ -302,6 +352,7 @@ pub(crate) fn get_glyphs(
     };
 
     let font_metrics = FontMetrics::new(&target_font.0, target_font_size, text_layout_options);
+    let words = &*shaped_words.words;
 
     // Prevent negative width / rect height or a too small rectangle -
     // the rect must be at least wide enough for the longest word
@@ -321,9 +372,16 @@ pub(crate) fn get_glyphs(
 
     let max_horizontal_text_width = if overflow.allows_horizontal_overflow() { None } else { Some(new_size.width) };
 
-    // (5) Align text to the left, initial layout of glyphs
-    let (mut positioned_glyphs, line_break_offsets, _, _) =
-        words_to_left_aligned_glyphs(words, &target_font.0, max_horizontal_text_width, &font_metrics);
+    // (5) Break the words into lines and align them to the left, initial layout of glyphs.
+    // The line breaks are shared with all nodes that show the same text at a similar width.
+    let (mut positioned_glyphs, line_break_offsets) = {
+        let line_breaks = app_resources.text_cache.shaped_text.get_or_break_lines(
+            shaped_words.key,
+            font_metrics.vertical_advance,
+            max_horizontal_text_width,
+            || break_lines(words, max_horizontal_text_width, &font_metrics));
+        (line_breaks.glyphs.clone(), line_breaks.get_line_break_offsets(max_horizontal_text_width))
+    };
 
     // (6) Add the harfbuzz adjustments to the positioned glyphs
     // apply_harfbuzz_adjustments(&mut positioned_glyphs, harfbuzz_adjustments);
@@ -436,6 +494,24 @@ pub(crate) fn get_words_cached<'a>(
     text_cache.layouted_strings_cache.get(&text_id).unwrap().get(&font_id).unwrap().get(&font_size).unwrap()
 }
 
+/// Shaping stage: Returns the words of the `text`, shared with all other nodes that
+/// display the same text with the same font, font size and letter spacing
+pub fn shape_text_cached<'a>(
+    text: &str,
+    font: &Font<'a>,
+    font_id: &FontId,
+    font_size: &StyleFontSize,
+    letter_spacing: Option<StyleLetterSpacing>,
+    text_cache: &mut TextCache)
+-> ShapedWords
+{
+    let key = ShapedTextKey::new(text, font_id, font_size, letter_spacing);
+    let words = text_cache.shaped_text.get_or_shape(key, || {
+        split_text_into_words(text, font, TextSizePx(font_size.0.to_pixels()), letter_spacing)
+    });
+    ShapedWords { key, words }
+}
+
 fn scale_words(words: &mut Words, scale_factor: f32) {
     // Scale the horizontal width of the words to match the new font size
     // Since each word has a local origin (i.e. the first character of each word
@@ -744,14 +820,63 @@ fn calculate_harfbuzz_adjustments<'a>(text: &str, font: &Font<'a>)
     Vec::new() // TODO
 }
 
+/// Output of the line-breaking stage: the glyphs of the shaped words, broken into lines
+/// and aligned to the left. Cached per width bucket in the `TextCache`.
+#[derive(Debug, Clone)]
+pub struct LineBreaks {
+    /// Left-aligned glyphs
+    pub glyphs: Vec<GlyphInstance>,
+    /// The index of the last glyph of each line (index into the `self.glyphs`)
+    /// and the position of the caret at the end of that line
+    pub line_ends: Vec<(IndexOfLineBreak, f32)>,
+    /// Width of the longest line
+    pub min_width: TextSizePx,
+    /// Height of all lines
+    pub min_height: TextSizePx,
+    /// Range of widths that break the words into the same lines (the end is exclusive):
+    /// Every word that didn't cause a line break still fits into the start of the range,
+    /// every word that caused a line break doesn't fit into the end of the range anymore.
+    valid_widths: (f32, f32),
+}
+
+impl LineBreaks {
+
+    /// Returns whether breaking the words at `max_horizontal_width` results in the same lines
+    pub fn is_valid_for(&self, max_horizontal_width: Option<f32>) -> bool {
+        let (min, max) = self.valid_widths;
+        match max_horizontal_width {
+            Some(width) => width >= min && width < max,
+            // Any line break that isn't caused by a return character depends on the width
+            None => max == ::std::f32::INFINITY,
+        }
+    }
+
+    /// Returns the index of the last glyph of each line and how much space each line
+    /// has to the right edge of the `max_horizontal_width` (or the longest line, if the
+    /// text doesn't wrap), for aligning the text horizontally
+    pub fn get_line_break_offsets(&self, max_horizontal_width: Option<f32>) -> Vec<(IndexOfLineBreak, RemainingSpaceToRight)> {
+        let line_width = max_horizontal_width.unwrap_or(self.min_width.0);
+        self.line_ends.iter().map(|(glyph_index, caret)| (*glyph_index, line_width - caret)).collect()
+    }
+
+    /// Approximate size of the line breaks in bytes, for the memory limit of the `TextCache`
+    pub(crate) fn get_heap_size(&self) -> usize {
+        use std::mem::size_of;
+        size_of::<Self>() +
+        self.glyphs.capacity() * size_of::<GlyphInstance>() +
+        self.line_ends.capacity() * size_of::<(IndexOfLineBreak, f32)>()
+    }
+}
+
+/// Line-breaking stage: Breaks the words into lines and positions the glyphs, left-aligned.
+///
 /// If `max_horizontal_width` is `None`, it means that the text is allowed to overflow
 /// the rectangle horizontally
-fn words_to_left_aligned_glyphs<'a>(
+pub(crate) fn break_lines(
     words: &Words,
-    font: &Font<'a>,
     max_horizontal_width: Option<f32>,
     font_metrics: &FontMetrics)
--> (Vec<GlyphInstance>, Vec<(usize, f32)>, TextSizePx, TextSizePx)
+-> LineBreaks
 {
     let words = &words.items;
 
@@ -760,21 +885,17 @@ fn words_to_left_aligned_glyphs<'a>(
     // left_aligned_glyphs stores the X and Y coordinates of the positioned glyphs
     let mut left_aligned_glyphs = Vec::<GlyphInstance>::new();
 
-    enum WordCaretMax {
-        SomeMaxWidth(f32),
-        NoMaxWidth(f32),
-    }
-
-    // The line break offsets (needed for center- / right-aligned text contains:
+    // The line ends (needed for center- / right-aligned text) contain:
     //
     // - The index of the glyph at which the line breaks
-    // - How much space each line has (to the right edge of the containing rectangle)
-    let mut line_break_offsets = Vec::<(usize, WordCaretMax)>::new();
+    // - The position of the caret at the end of the line
+    let mut line_ends = Vec::<(usize, f32)>::new();
 
     // word_caret is the current X position of the "pen" we are writing with
     let mut word_caret = 0.0;
     let mut current_line_num = 0;
     let mut max_word_caret = 0.0;
+    let mut valid_widths = (::std::f32::NEG_INFINITY, ::std::f32::INFINITY);
 
     let letter_spacing = letter_spacing.and_then(|p| Some(p.0.to_pixels())).unwrap_or(0.0);
 
@@ -782,28 +903,28 @@ fn words_to_left_aligned_glyphs<'a>(
         use self::SemanticWordItem::*;
         match word {
             Word(word) => {
+                let word_end = word_caret + word.total_width;
                 let text_overflows_rect = match max_horizontal_width {
-                    Some(max) => word_caret + word.total_width > max,
+                    Some(max) => word_end > max,
                     // If we don't have a maximum horizontal width, the text can overflow the
                     // bounding rectangle in the horizontal direction
                     None => false,
                 };
 
                 if text_overflows_rect {
-                    let space_until_horz_return = match max_horizontal_width {
-                        Some(s) => WordCaretMax::SomeMaxWidth(s - word_caret),
-                        None => WordCaretMax::NoMaxWidth(word_caret),
-                    };
+                    valid_widths.1 = valid_widths.1.min(word_end);
                     // TODO: This is monkey-patching. The following line crashed with an
                     // overflow, but I don't know the reason yet.
                     if left_aligned_glyphs.len() > 0 {
-                        line_break_offsets.push((left_aligned_glyphs.len() - 1, space_until_horz_return));
+                        line_ends.push((left_aligned_glyphs.len() - 1, word_caret));
                     }
                     if word_caret > max_word_caret {
                         max_word_caret = word_caret;
                     }
                     word_caret = 0.0;
                     current_line_num += 1;
+                } else {
+                    valid_widths.0 = valid_widths.0.max(word_end);
                 }
 
                 for glyph in &word.glyphs {
@@ -832,12 +953,8 @@ fn words_to_left_aligned_glyphs<'a>(
             },
             Return => {
                 // TODO: duplicated code
-                let space_until_horz_return = match max_horizontal_width {
-                    Some(s) => WordCaretMax::SomeMaxWidth(s - word_caret),
-                    None => WordCaretMax::NoMaxWidth(word_caret),
-                };
                 if left_aligned_glyphs.len() > 0 {
-                    line_break_offsets.push((left_aligned_glyphs.len() - 1, space_until_horz_return));
+                    line_ends.push((left_aligned_glyphs.len() - 1, word_caret));
                 }
                 if word_caret > max_word_caret {
                     max_word_caret = word_caret;
@@ -850,28 +967,32 @@ fn words_to_left_aligned_glyphs<'a>(
 
     // push the infos about the last line
     if !left_aligned_glyphs.is_empty() {
-        let space_until_horz_return = match max_horizontal_width {
-            Some(s) => WordCaretMax::SomeMaxWidth(s - word_caret),
-            None => WordCaretMax::NoMaxWidth(word_caret),
-        };
-        line_break_offsets.push((left_aligned_glyphs.len() - 1, space_until_horz_return));
+        line_ends.push((left_aligned_glyphs.len() - 1, word_caret));
         if word_caret > max_word_caret {
             max_word_caret = word_caret;
         }
     }
 
-    let min_enclosing_width = TextSizePx(max_word_caret);
-    let min_enclosing_height = (vertical_advance * current_line_num as f32) + font_size_no_line_height;
-
-    let line_break_offsets = line_break_offsets.into_iter().map(|(line, space_r)| {
-        let space_r = match space_r {
-            WordCaretMax::SomeMaxWidth(s) => s,
-            WordCaretMax::NoMaxWidth(word_caret) => max_word_caret - word_caret,
-        };
-        (line, space_r)
-    }).collect();
+    LineBreaks {
+        glyphs: left_aligned_glyphs,
+        line_ends,
+        min_width: TextSizePx(max_word_caret),
+        min_height: (vertical_advance * current_line_num as f32) + font_size_no_line_height,
+        valid_widths,
+    }
+}
 
-    (left_aligned_glyphs, line_break_offsets, min_enclosing_width, min_enclosing_height)
+/// If `max_horizontal_width` is `None`, it means that the text is allowed to overflow
+/// the rectangle horizontally
+fn words_to_left_aligned_glyphs(
+    words: &Words,
+    max_horizontal_width: Option<f32>,
+    font_metrics: &FontMetrics)
+-> (Vec<GlyphInstance>, Vec<(usize, f32)>, TextSizePx, TextSizePx)
+{
+    let line_breaks = break_lines(words, max_horizontal_width, font_metrics);
+    let line_break_offsets = line_breaks.get_line_break_offsets(max_horizontal_width);
+    (line_breaks.glyphs, line_break_offsets, line_breaks.min_width, line_breaks.min_height)
 }
 
 fn apply_harfbuzz_adjustments(positioned_glyphs: &mut [GlyphInstance], harfbuzz_adjustments: Vec<HarfbuzzAdjustment>)
@@ -1062,7 +1183,7 @@ pub fn layout_text<'a>(
     // This function does not calculate any overflow.
     let words = split_text_into_words(text, font, font_metrics.font_size_no_line_height, font_metrics.letter_spacing);
     let (mut layouted_glyphs, line_breaks, min_width, min_height) =
-        words_to_left_aligned_glyphs(&words, font, None, font_metrics);
+        words_to_left_aligned_glyphs(&words, None, font_metrics);
 
     align_text_horz(font_metrics.layout_options.horz_alignment, &mut layouted_glyphs, &line_breaks);
 
@@ -1091,3 +1212,155 @@ fn test_it_should_add_origin() {
     assert_eq!(instances[1].point.x as usize, 33);
     assert_eq!(instances[1].point.y as usize, 10);
 }
+
+#[test]
+fn test_get_max_content_width() {
+
+    let word = |total_width| SemanticWordItem::Word(Word { glyphs: Vec::new(), total_width });
+
+    let words = Words {
+        items: vec![word(30.0), word(50.0), SemanticWordItem::Return, word(20.0), SemanticWordItem::Tab, word(100.0)],
+        longest_word_width: 100.0,
+    };
+
+    let font_metrics = FontMetrics {
+        space_width: TextSizePx(5.0),
+        tab_width: TextSizePx(20.0),
+        vertical_advance: TextSizePx(10.0),
+        font_size_with_line_height: TextSizePx(10.0),
+        font_size_no_line_height: TextSizePx(10.0),
+        height_for_1px: 1.0,
+        letter_spacing: None,
+        layout_options: TextLayoutOptions::default(),
+    };
+
+    // Second line: "20" + space + tab + "100"
+    assert_eq!(words.get_max_content_width(&font_metrics), 145.0);
+
+    // The text doesn't wrap if it is layouted at its max-content width
+    let vertical_info = words.get_vertical_height(&LayoutOverflow::default(), &font_metrics, TextSizePx(145.0));
+    assert_eq!(vertical_info.vertical_height, TextSizePx(20.0));
+}
+
+#[cfg(test)]
+fn test_words_and_metrics() -> (Words, FontMetrics) {
+
+    let word = |total_width| SemanticWordItem::Word(Word {
+        glyphs: vec![GlyphInstance { index: 0, point: TypedPoint2D::new(0.0, 0.0) }],
+        total_width,
+    });
+
+    let words = Words {
+        items: vec![word(30.0), word(50.0), SemanticWordItem::Return, word(20.0), SemanticWordItem::Tab, word(100.0)],
+        longest_word_width: 100.0,
+    };
+
+    let font_metrics = FontMetrics {
+        space_width: TextSizePx(5.0),
+        tab_width: TextSizePx(20.0),
+        vertical_advance: TextSizePx(10.0),
+        font_size_with_line_height: TextSizePx(10.0),
+        font_size_no_line_height: TextSizePx(10.0),
+        height_for_1px: 1.0,
+        letter_spacing: None,
+        layout_options: TextLayoutOptions::default(),
+    };
+
+    (words, font_metrics)
+}
+
+#[test]
+fn test_break_lines() {
+
+    let (words, font_metrics) = test_words_and_metrics();
+
+    // "30 50", return, "20" + tab, "100" wraps because 20 + 5 + 20 + 100 > 100
+    let (glyphs, line_breaks, min_width, min_height) = words_to_left_aligned_glyphs(&words, Some(100.0), &font_metrics);
+    assert_eq!(glyphs.iter().map(|g| g.point.x).collect::<Vec<_>>(), vec![0.0, 35.0, 0.0, 0.0]);
+    assert_eq!(line_breaks, vec![(1, 10.0), (2, 55.0), (3, -5.0)]);
+    assert_eq!(min_width, TextSizePx(105.0));
+    assert_eq!(min_height, TextSizePx(30.0));
+
+    // Without a maximum width, only the return character breaks the line
+    let (glyphs, line_breaks, min_width, _) = words_to_left_aligned_glyphs(&words, None, &font_metrics);
+    assert_eq!(glyphs.iter().map(|g| g.point.x).collect::<Vec<_>>(), vec![0.0, 35.0, 0.0, 45.0]);
+    assert_eq!(line_breaks, vec![(1, 60.0), (3, 0.0)]);
+    assert_eq!(min_width, TextSizePx(150.0));
+
+    // The lines stay the same until "50" doesn't fit anymore or "100" fits
+    let line_breaks = break_lines(&words, Some(100.0), &font_metrics);
+    assert!(line_breaks.is_valid_for(Some(85.0)));
+    assert!(line_breaks.is_valid_for(Some(144.0)));
+    assert!(!line_breaks.is_valid_for(Some(84.0)));
+    assert!(!line_breaks.is_valid_for(Some(145.0)));
+    assert!(!line_breaks.is_valid_for(None));
+    assert!(break_lines(&words, None, &font_metrics).is_valid_for(Some(145.0)));
+}
+
+#[test]
+fn test_cached_line_breaks_are_identical_to_uncached_line_breaks() {
+
+    use text_cache::ShapedTextCache;
+
+    let (words, font_metrics) = test_words_and_metrics();
+    let key = ShapedTextKey::new("test", &FontId::ExternalFont("Test".into()), &StyleFontSize::px(10.0), None);
+    let mut cache = ShapedTextCache::default();
+
+    let widths = (0..800).map(|w| Some(w as f32 * 0.25)).chain(Some(None)).collect::<Vec<_>>();
+
+    // Widths in the same bucket share their line breaks as long as the lines don't change
+    for max_width in widths.iter().chain(widths.iter().rev()).cloned() {
+        let uncached = break_lines(&words, max_width, &font_metrics);
+        let cached = cache.get_or_break_lines(key, font_metrics.vertical_advance, max_width, || {
+            break_lines(&words, max_width, &font_metrics)
+        });
+        assert_eq!(cached.glyphs, uncached.glyphs);
+        assert_eq!(cached.get_line_break_offsets(max_width), uncached.get_line_break_offsets(max_width));
+        assert_eq!(cached.min_width, uncached.min_width);
+        assert_eq!(cached.min_height, uncached.min_height);
+    }
+}
+
+#[test]
+fn test_labels_with_the_same_text_are_shaped_once() {
+
+    use std::rc::Rc;
+    use azul_css::PixelValue;
+    use font::rusttype_load_font;
+    use testing::DEFAULT_TEST_FONT;
+
+    let (font, _) = rusttype_load_font(DEFAULT_TEST_FONT.to_vec(), None).unwrap();
+    let font_id = FontId::ExternalFont("KoHo".into());
+    let font_size = StyleFontSize::px(12.0);
+    let mut text_cache = TextCache::default();
+
+    let names = ["Today", "Yesterday", "alice", "bob"];
+    let labels = (0..400).map(|i| {
+        shape_text_cached(names[i % names.len()], &font, &font_id, &font_size, None, &mut text_cache)
+    }).collect::<Vec<_>>();
+
+    assert_eq!(text_cache.get_shaping_count(), names.len());
+    assert!(Rc::ptr_eq(&labels[0].words, &labels[4].words));
+    assert!(!Rc::ptr_eq(&labels[0].words, &labels[1].words));
+
+    // The shared words are the same as the words of a label that shaped its text by itself
+    let uncached = split_text_into_words("Today", &font, TextSizePx(12.0), None);
+    assert_eq!(format!("{:?}", labels[0].words), format!("{:?}", uncached));
+
+    // Different font sizes or letter spacings are shaped separately
+    shape_text_cached("Today", &font, &font_id, &StyleFontSize::px(13.0), None, &mut text_cache);
+    shape_text_cached("Today", &font, &font_id, &font_size, Some(StyleLetterSpacing(PixelValue::px(1.0))), &mut text_cache);
+    assert_eq!(text_cache.get_shaping_count(), names.len() + 2);
+
+    // Once the memory limit is reached, the least recently used words are evicted
+    let memory_limit = labels[0].words.get_heap_size() * 3;
+    text_cache.set_memory_limit(memory_limit);
+    assert!(text_cache.get_memory_usage() <= memory_limit);
+    let shaping_count = text_cache.get_shaping_count();
+    for i in 0..20 {
+        shape_text_cached(&format!("Today {}", i), &font, &font_id, &font_size, None, &mut text_cache);
+        assert!(text_cache.get_memory_usage() <= memory_limit);
+    }
+    shape_text_cached("Today", &font, &font_id, &font_size, None, &mut text_cache);
+    assert_eq!(text_cache.get_shaping_count(), shaping_count + 21);
+}
@@ -0,0 +1,517 @@
+//! Transient notifications ("File saved") on top of the window content,
+//! see `AppState::show_toast()`.
+//!
+//! The active toasts are stacked in a corner of the window (see `AppState::set_toast_corner()`).
+//! The stack isn't part of the DOM returned by `Layout::layout()` - it is appended as the last
+//! child of the root node of every window after the layout, so it is drawn on top of the
+//! window content. A toast with a `duration` is dismissed when the duration has passed,
+//! the timer is paused while the mouse is over the toast. Dismissed toasts fade out for
+//! `FADE_DURATION_MS` before they are removed. If more toasts than `max_visible` are shown
+//! at the same time, the rest is queued and shown as soon as the visible toasts are dismissed.
+//!
+//! The timers read the time from the `AnimationClock` of the `AppState`, so toasts
+//! dismiss deterministically with a manual clock. The DOM has the following classes:
+//!
+//! - `azul-toast-layer`: the stack, additionally `azul-toast-layer-top-left`, `-top-right`,
+//!   `-bottom-left` or `-bottom-right`, depending on the corner
+//! - `azul-toast`: a toast, additionally `azul-toast-info`, `-success`, `-warning` or
+//!   `-error` (depending on the severity) and `azul-toast-fading` while it fades out
+//! - `azul-toast-text`: the label of a toast that was created with `Toast::text()`
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+use {
+    dom::{Dom, Callback, On, UpdateScreen, Redraw, DontRedraw},
+    app_state::AppState,
+    traits::Layout,
+    window::CallbackInfo,
+};
+
+/// How long a toast is shown if the duration isn't set, in milliseconds
+const DEFAULT_DURATION_MS: u64 = 4000;
+
+/// How long a dismissed toast fades out before it is removed, in milliseconds
+pub const FADE_DURATION_MS: u64 = 200;
+
+/// How many toasts are visible at the same time by default
+const DEFAULT_MAX_VISIBLE: usize = 3;
+
+/// Marks the keys of the toast nodes, so that they don't collide with the keys of the user DOM
+const TOAST_KEY_PREFIX: u64 = 0x7a57_0000_0000_0000;
+
+/// ID of a toast, returned by `AppState::show_toast()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ToastId(usize);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ToastSeverity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Default for ToastSeverity {
+    fn default() -> Self {
+        ToastSeverity::Info
+    }
+}
+
+/// Corner of the window that the toasts are stacked in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ToastCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Default for ToastCorner {
+    fn default() -> Self {
+        ToastCorner::BottomRight
+    }
+}
+
+impl ToastCorner {
+    fn class(&self) -> &'static str {
+        use self::ToastCorner::*;
+        match self {
+            TopLeft => "azul-toast-layer-top-left",
+            TopRight => "azul-toast-layer-top-right",
+            BottomLeft => "azul-toast-layer-bottom-left",
+            BottomRight => "azul-toast-layer-bottom-right",
+        }
+    }
+}
+
+pub enum ToastContent<T: Layout> {
+    Text(String),
+    Dom(Dom<T>),
+}
+
+impl<T: Layout> Clone for ToastContent<T> {
+    fn clone(&self) -> Self {
+        match self {
+            ToastContent::Text(text) => ToastContent::Text(text.clone()),
+            ToastContent::Dom(dom) => ToastContent::Dom(dom.clone()),
+        }
+    }
+}
+
+/// A notification, see `AppState::show_toast()`
+pub struct Toast<T: Layout> {
+    pub content: ToastContent<T>,
+    /// How long the toast is shown, `None` if it is only dismissed by `AppState::dismiss_toast()`
+    pub duration: Option<Duration>,
+    pub severity: ToastSeverity,
+    /// Called when the toast is clicked
+    pub on_click: Option<Callback<T>>,
+}
+
+impl<T: Layout> Clone for Toast<T> {
+    fn clone(&self) -> Self {
+        Self {
+            content: self.content.clone(),
+            duration: self.duration,
+            severity: self.severity,
+            on_click: self.on_click.clone(),
+        }
+    }
+}
+
+impl<T: Layout> Toast<T> {
+
+    /// Creates an info toast that shows the `text` for 4 seconds
+    pub fn text<S: Into<String>>(text: S) -> Self {
+        Self::new(ToastContent::Text(text.into()))
+    }
+
+    /// Creates an info toast that shows the `dom` for 4 seconds
+    pub fn dom(dom: Dom<T>) -> Self {
+        Self::new(ToastContent::Dom(dom))
+    }
+
+    fn new(content: ToastContent<T>) -> Self {
+        Self {
+            content,
+            duration: Some(Duration::from_millis(DEFAULT_DURATION_MS)),
+            severity: ToastSeverity::default(),
+            on_click: None,
+        }
+    }
+
+    pub fn with_duration(self, duration: Option<Duration>) -> Self {
+        Self { duration, .. self }
+    }
+
+    pub fn with_severity(self, severity: ToastSeverity) -> Self {
+        Self { severity, .. self }
+    }
+
+    pub fn on_click(self, callback: Callback<T>) -> Self {
+        Self { on_click: Some(callback), .. self }
+    }
+}
+
+/// A toast that is currently visible
+struct ActiveToast<T: Layout> {
+    id: ToastId,
+    toast: Toast<T>,
+    /// How long the toast is still shown, `None` if it has no duration
+    remaining: Option<Duration>,
+    /// Time up to which `remaining` is counted down
+    last_update: Instant,
+    hovered: bool,
+    /// Time at which the toast started to fade out
+    fading_since: Option<Instant>,
+}
+
+/// The visible and queued toasts of the app, stored in the `AppState`
+pub(crate) struct ToastLayer<T: Layout> {
+    pub(crate) corner: ToastCorner,
+    pub(crate) max_visible: usize,
+    next_id: usize,
+    /// Visible toasts, in the order in which they were shown
+    visible: Vec<ActiveToast<T>>,
+    /// Toasts that are shown when there is space in the stack
+    queue: VecDeque<(ToastId, Toast<T>)>,
+}
+
+impl<T: Layout> Default for ToastLayer<T> {
+    fn default() -> Self {
+        Self {
+            corner: ToastCorner::default(),
+            max_visible: DEFAULT_MAX_VISIBLE,
+            next_id: 0,
+            visible: Vec::new(),
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl<T: Layout> ToastLayer<T> {
+
+    /// Shows the toast, or queues it if the stack is full
+    pub(crate) fn show(&mut self, toast: Toast<T>, now: Instant) -> ToastId {
+        let id = ToastId(self.next_id);
+        self.next_id += 1;
+        self.queue.push_back((id, toast));
+        self.show_queued(now);
+        id
+    }
+
+    /// Starts fading out the toast (or removes it from the queue),
+    /// returns `false` if the toast doesn't exist or was already dismissed
+    pub(crate) fn dismiss(&mut self, id: ToastId, now: Instant) -> bool {
+        if let Some(position) = self.queue.iter().position(|&(queued, _)| queued == id) {
+            self.queue.remove(position);
+            return true;
+        }
+        match self.visible.iter_mut().find(|active| active.id == id) {
+            Some(active) => {
+                let was_dismissed = active.fading_since.is_some();
+                active.fading_since.get_or_insert(now);
+                !was_dismissed
+            },
+            None => false,
+        }
+    }
+
+    /// Pauses (or resumes) the timer of the toast while the mouse is over it
+    pub(crate) fn set_hovered(&mut self, id: ToastId, hovered: bool, now: Instant) {
+        let active = match self.visible.iter_mut().find(|active| active.id == id) {
+            Some(active) => active,
+            None => return,
+        };
+        if active.hovered == hovered {
+            return;
+        }
+        if hovered {
+            active.count_down(now);
+        } else {
+            active.last_update = now;
+        }
+        active.hovered = hovered;
+    }
+
+    /// Counts down the timers, starts fading out the expired toasts, removes the faded
+    /// toasts and shows the queued toasts. Returns `Redraw` if a toast was shown, is
+    /// fading or was removed.
+    pub(crate) fn update(&mut self, now: Instant) -> UpdateScreen {
+
+        let fade_duration = Duration::from_millis(FADE_DURATION_MS);
+        let mut should_redraw = DontRedraw;
+
+        for active in self.visible.iter_mut() {
+            if active.fading_since.is_none() && !active.hovered {
+                active.count_down(now);
+                if active.remaining == Some(Duration::from_millis(0)) {
+                    active.fading_since = Some(now);
+                }
+            }
+            if active.fading_since.is_some() {
+                should_redraw = Redraw;
+            }
+        }
+
+        self.visible.retain(|active| match active.fading_since {
+            Some(fading_since) => now.duration_since(fading_since) < fade_duration,
+            None => true,
+        });
+
+        if self.show_queued(now) {
+            should_redraw = Redraw;
+        }
+
+        should_redraw
+    }
+
+    /// Moves queued toasts to the stack while there is space, returns whether a toast was shown
+    fn show_queued(&mut self, now: Instant) -> bool {
+        let mut shown = false;
+        while self.visible.len() < self.max_visible.max(1) {
+            let (id, toast) = match self.queue.pop_front() {
+                Some(queued) => queued,
+                None => break,
+            };
+            self.visible.push(ActiveToast {
+                id,
+                remaining: toast.duration,
+                toast,
+                last_update: now,
+                hovered: false,
+                fading_since: None,
+            });
+            shown = true;
+        }
+        shown
+    }
+
+    /// Returns the IDs of the visible toasts, in the order of the stack
+    pub(crate) fn visible_ids(&self) -> Vec<ToastId> {
+        self.visible.iter().map(|active| active.id).collect()
+    }
+
+    /// Returns the IDs of the queued toasts, in the order in which they will be shown
+    pub(crate) fn queued_ids(&self) -> Vec<ToastId> {
+        self.queue.iter().map(|&(id, _)| id).collect()
+    }
+
+    /// Builds the stack of the visible toasts, `None` if there are no visible toasts
+    pub(crate) fn dom(&self) -> Option<Dom<T>> {
+
+        if self.visible.is_empty() {
+            return None;
+        }
+
+        let mut layer = Dom::div()
+            .with_class("azul-toast-layer")
+            .with_class(self.corner.class());
+
+        for active in &self.visible {
+
+            let content = match active.toast.content {
+                ToastContent::Text(ref text) => Dom::label(text.clone()).with_class("azul-toast-text"),
+                ToastContent::Dom(ref dom) => dom.clone(),
+            };
+
+            // The key identifies the toast in the hover callbacks (the stack can
+            // change between the layout and the event) and keeps the node identity
+            // stable when the toasts before it are removed
+            let mut toast = Dom::div()
+                .with_class("azul-toast")
+                .with_class(severity_class(active.toast.severity))
+                .with_key(TOAST_KEY_PREFIX | active.id.0 as u64)
+                .with_callback(On::MouseEnter, Callback(on_toast_mouse_enter))
+                .with_callback(On::MouseLeave, Callback(on_toast_mouse_leave))
+                .with_child(content);
+
+            if active.fading_since.is_some() {
+                toast.add_class("azul-toast-fading");
+            }
+
+            if let Some(on_click) = active.toast.on_click {
+                toast.add_callback(On::LeftMouseUp, on_click);
+            }
+
+            layer.add_child(toast);
+        }
+
+        Some(layer)
+    }
+}
+
+impl<T: Layout> ActiveToast<T> {
+    /// Subtracts the time since the last update from the remaining duration
+    fn count_down(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.last_update);
+        self.remaining = self.remaining.map(|remaining| if elapsed >= remaining { Duration::from_millis(0) } else { remaining - elapsed });
+        self.last_update = now;
+    }
+}
+
+fn severity_class(severity: ToastSeverity) -> &'static str {
+    use self::ToastSeverity::*;
+    match severity {
+        Info => "azul-toast-info",
+        Success => "azul-toast-success",
+        Warning => "azul-toast-warning",
+        Error => "azul-toast-error",
+    }
+}
+
+/// Appends the toast stack to the root node of the `dom`
+pub(crate) fn append_toast_layer<T: Layout>(dom: &mut Dom<T>, toasts: &ToastLayer<T>) {
+    let layer = match toasts.dom() {
+        Some(layer) => layer,
+        None => return,
+    };
+    let root = dom.get_root();
+    // The root of a DOM always exists
+    let _ = dom.append_child_at(root, layer);
+}
+
+/// Returns the toast that the event was targeted at
+fn target_toast<T: Layout>(event: &CallbackInfo<T>) -> Option<ToastId> {
+    let key = event.ui_state.dom.arena.node_data[event.hit_dom_node].key?;
+    if key & TOAST_KEY_PREFIX != TOAST_KEY_PREFIX {
+        return None;
+    }
+    Some(ToastId((key & !TOAST_KEY_PREFIX) as usize))
+}
+
+fn on_toast_mouse_enter<T: Layout>(app_state: &mut AppState<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    let id = target_toast(event)?;
+    let now = app_state.clock.now();
+    app_state.toasts.set_hovered(id, true, now);
+    DontRedraw
+}
+
+fn on_toast_mouse_leave<T: Layout>(app_state: &mut AppState<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    let id = target_toast(event)?;
+    let now = app_state.clock.now();
+    app_state.toasts.set_hovered(id, false, now);
+    DontRedraw
+}
+
+#[cfg(test)]
+use deterministic::AnimationClock;
+
+#[cfg(test)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+#[cfg(test)]
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn test_toast_queueing() {
+
+    let mut clock = AnimationClock::manual();
+    let mut toasts = ToastLayer::<TestLayout>::default();
+    toasts.max_visible = 2;
+
+    let a = toasts.show(Toast::text("a").with_duration(Some(ms(1000))), clock.now());
+    let b = toasts.show(Toast::text("b").with_duration(Some(ms(3000))), clock.now());
+    let c = toasts.show(Toast::text("c"), clock.now());
+    assert_eq!(toasts.visible_ids(), vec![a, b]);
+    assert_eq!(toasts.queued_ids(), vec![c]);
+    assert_eq!(toasts.update(clock.now()), DontRedraw);
+
+    // `a` expires and fades out, `c` takes its place once `a` is removed
+    clock.advance(ms(1000));
+    assert_eq!(toasts.update(clock.now()), Redraw);
+    assert_eq!(toasts.visible_ids(), vec![a, b]);
+    assert!(toasts.dom().unwrap().find_all_by_class("azul-toast-fading").count() == 1);
+
+    clock.advance(ms(FADE_DURATION_MS));
+    assert_eq!(toasts.update(clock.now()), Redraw);
+    assert_eq!(toasts.visible_ids(), vec![b, c]);
+    assert!(toasts.queued_ids().is_empty());
+
+    // A queued toast can be dismissed before it is shown
+    let d = toasts.show(Toast::text("d"), clock.now());
+    assert_eq!(toasts.queued_ids(), vec![d]);
+    assert!(toasts.dismiss(d, clock.now()));
+    assert!(!toasts.dismiss(d, clock.now()));
+    assert!(toasts.queued_ids().is_empty());
+}
+
+#[test]
+fn test_toast_hover_pauses_timer() {
+
+    let mut clock = AnimationClock::manual();
+    let mut toasts = ToastLayer::<TestLayout>::default();
+
+    let a = toasts.show(Toast::text("a").with_duration(Some(ms(1000))), clock.now());
+
+    clock.advance(ms(600));
+    toasts.set_hovered(a, true, clock.now());
+
+    // While hovered, the timer doesn't run out
+    clock.advance(ms(5000));
+    assert_eq!(toasts.update(clock.now()), DontRedraw);
+    assert_eq!(toasts.visible_ids(), vec![a]);
+
+    // After the mouse leaves, the rest of the duration (400ms) runs
+    toasts.set_hovered(a, false, clock.now());
+    clock.advance(ms(399));
+    toasts.update(clock.now());
+    assert!(toasts.dom().unwrap().find_all_by_class("azul-toast-fading").next().is_none());
+    clock.advance(ms(1));
+    toasts.update(clock.now());
+    assert!(toasts.dom().unwrap().find_all_by_class("azul-toast-fading").next().is_some());
+
+    clock.advance(ms(FADE_DURATION_MS));
+    toasts.update(clock.now());
+    assert!(toasts.visible_ids().is_empty());
+    assert!(toasts.dom().is_none());
+}
+
+#[test]
+fn test_toast_dismissal_order() {
+
+    let mut clock = AnimationClock::manual();
+    let mut toasts = ToastLayer::<TestLayout>::default();
+    toasts.max_visible = 2;
+
+    let sticky = toasts.show(Toast::text("sticky").with_duration(None), clock.now());
+    let b = toasts.show(Toast::text("b").with_severity(ToastSeverity::Error), clock.now());
+    let c = toasts.show(Toast::text("c"), clock.now());
+    let d = toasts.show(Toast::text("d"), clock.now());
+
+    // Dismissing the second toast keeps the order of the others, queued toasts are shown in order
+    assert!(toasts.dismiss(b, clock.now()));
+    assert!(!toasts.dismiss(b, clock.now()));
+    clock.advance(ms(FADE_DURATION_MS));
+    toasts.update(clock.now());
+    assert_eq!(toasts.visible_ids(), vec![sticky, c]);
+    assert_eq!(toasts.queued_ids(), vec![d]);
+
+    // Toasts without a duration stay until they are dismissed
+    clock.advance(ms(DEFAULT_DURATION_MS));
+    toasts.update(clock.now());
+    clock.advance(ms(FADE_DURATION_MS));
+    toasts.update(clock.now());
+    assert_eq!(toasts.visible_ids(), vec![sticky, d]);
+
+    let dom = toasts.dom().unwrap();
+    assert_eq!(dom.find_all_by_class("azul-toast").count(), 2);
+    assert_eq!(dom.find_all_by_class("azul-toast-layer-bottom-right").next(), Some(dom.get_root()));
+
+    // The stack is appended to the root of the user DOM
+    let mut user_dom = Dom::<TestLayout>::div().with_child(Dom::div().with_child(Dom::label("content")));
+    append_toast_layer(&mut user_dom, &toasts);
+    let layer = user_dom.find_all_by_class("azul-toast-layer").next().unwrap();
+    assert_eq!(user_dom.children(user_dom.get_root()).last(), Some(layer));
+}
@@ -0,0 +1,132 @@
+//! 2D transforms of nodes (`transform` and `transform-origin`)
+//!
+//! Transforms don't affect the layout: the layout solver works on the untransformed
+//! rectangles and the transform is only applied when the node is drawn (by pushing
+//! a webrender reference frame) and when it is hit-tested (by mapping the cursor
+//! through the inverse transform). All transforms are in window coordinates, i.e.
+//! the transform-origin is already baked into the matrix, so that nested transforms
+//! can simply be multiplied together.
+
+use euclid::TypedTransform2D;
+use webrender::api::{LayoutPixel, LayoutRect, LayoutPoint, LayoutTransform};
+use azul_css::{StyleTransform, StyleTransformFunction, StyleTransformOrigin};
+
+/// 2D transform in window coordinates
+pub(crate) type LayoutTransform2D = TypedTransform2D<f32, LayoutPixel, LayoutPixel>;
+
+/// Returns the transform of a node that was laid out at `bounds`
+pub(crate) fn get_node_transform(
+    transform: &StyleTransform,
+    transform_origin: &StyleTransformOrigin,
+    bounds: &LayoutRect)
+-> LayoutTransform2D
+{
+    let origin_x = bounds.origin.x + transform_origin.x.to_pixels(bounds.size.width);
+    let origin_y = bounds.origin.y + transform_origin.y.to_pixels(bounds.size.height);
+
+    // Like in CSS, the rightmost function is applied first
+    let transform = transform.0.iter().rev().fold(LayoutTransform2D::identity(), |transform, function| {
+        transform.post_mul(&get_function_transform(function))
+    });
+
+    LayoutTransform2D::create_translation(-origin_x, -origin_y)
+        .post_mul(&transform)
+        .post_mul(&LayoutTransform2D::create_translation(origin_x, origin_y))
+}
+
+fn get_function_transform(function: &StyleTransformFunction) -> LayoutTransform2D {
+    use self::StyleTransformFunction::*;
+    match function {
+        Translate(x, y) => LayoutTransform2D::create_translation(x.to_pixels(), y.to_pixels()),
+        Scale(x, y) => LayoutTransform2D::create_scale(x.get(), y.get()),
+        Rotate(degrees) => {
+            // Since the y axis points down, a positive angle rotates clockwise:
+            // x' = x * cos - y * sin, y' = x * sin + y * cos
+            let (sin, cos) = degrees.get().to_radians().sin_cos();
+            LayoutTransform2D::row_major(cos, sin, -sin, cos, 0.0, 0.0)
+        },
+    }
+}
+
+/// Maps a point in window coordinates to the untransformed coordinates of the node.
+/// Returns `None` if the transform can't be inverted (i.e. `scale(0)`), in which
+/// case the node is not visible and can't be hit.
+pub(crate) fn untransform_point(transform: &LayoutTransform2D, point: &LayoutPoint) -> Option<LayoutPoint> {
+    transform.inverse().map(|inverse| inverse.transform_point(point))
+}
+
+/// Converts the transform to the transform of a webrender reference frame
+pub(crate) fn wr_translate_transform(transform: &LayoutTransform2D) -> LayoutTransform {
+    LayoutTransform::row_major_2d(
+        transform.m11, transform.m12,
+        transform.m21, transform.m22,
+        transform.m31, transform.m32,
+    )
+}
+
+#[cfg(test)]
+fn assert_point_eq(a: LayoutPoint, b: LayoutPoint) {
+    assert!((a.x - b.x).abs() < 0.001 && (a.y - b.y).abs() < 0.001, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn test_node_transform_functions() {
+
+    use webrender::api::LayoutSize;
+    use azul_css::{PixelValue, FloatValue};
+    use self::StyleTransformFunction::*;
+
+    let bounds = LayoutRect::new(LayoutPoint::new(100.0, 100.0), LayoutSize::new(40.0, 20.0));
+    let origin = StyleTransformOrigin::default();
+    let transform = |functions: Vec<StyleTransformFunction>, point: (f32, f32)| {
+        get_node_transform(&StyleTransform(functions), &origin, &bounds).transform_point(&LayoutPoint::new(point.0, point.1))
+    };
+
+    assert_point_eq(transform(vec![], (100.0, 100.0)), LayoutPoint::new(100.0, 100.0));
+    assert_point_eq(transform(vec![Translate(PixelValue::px(0.0), PixelValue::px(-8.0))], (100.0, 100.0)), LayoutPoint::new(100.0, 92.0));
+
+    // Scaled and rotated around the center of the node (120, 110)
+    assert_point_eq(transform(vec![Scale(FloatValue::new(2.0), FloatValue::new(2.0))], (100.0, 100.0)), LayoutPoint::new(80.0, 90.0));
+    assert_point_eq(transform(vec![Rotate(FloatValue::new(90.0))], (140.0, 110.0)), LayoutPoint::new(120.0, 130.0));
+
+    // The rightmost function is applied first: scale, then translate
+    assert_point_eq(
+        transform(vec![Translate(PixelValue::px(10.0), PixelValue::px(0.0)), Scale(FloatValue::new(2.0), FloatValue::new(1.0))], (140.0, 110.0)),
+        LayoutPoint::new(170.0, 110.0)
+    );
+    assert_point_eq(
+        transform(vec![Scale(FloatValue::new(2.0), FloatValue::new(1.0)), Translate(PixelValue::px(10.0), PixelValue::px(0.0))], (140.0, 110.0)),
+        LayoutPoint::new(180.0, 110.0)
+    );
+}
+
+#[test]
+fn test_node_transform_inverse_and_nesting() {
+
+    use webrender::api::LayoutSize;
+    use azul_css::{PixelValue, FloatValue, TransformOriginOffset};
+    use self::StyleTransformFunction::*;
+
+    let parent_bounds = LayoutRect::new(LayoutPoint::new(0.0, 0.0), LayoutSize::new(100.0, 100.0));
+    let child_bounds = LayoutRect::new(LayoutPoint::new(10.0, 10.0), LayoutSize::new(20.0, 20.0));
+
+    // Parent: rotated by 90 degrees around its top left corner
+    let top_left = StyleTransformOrigin {
+        x: TransformOriginOffset::Pixels(PixelValue::px(0.0)),
+        y: TransformOriginOffset::Pixels(PixelValue::px(0.0)),
+    };
+    let parent = get_node_transform(&StyleTransform(vec![Rotate(FloatValue::new(90.0))]), &top_left, &parent_bounds);
+    // Child: moved 5px to the right, in the rotated coordinate system of the parent
+    let child = get_node_transform(&StyleTransform(vec![Translate(PixelValue::px(5.0), PixelValue::px(0.0))]), &StyleTransformOrigin::default(), &child_bounds);
+
+    // The transform of the child is applied first, then the one of the parent
+    let child_in_window = child.post_mul(&parent);
+    assert_point_eq(child_in_window.transform_point(&LayoutPoint::new(10.0, 10.0)), LayoutPoint::new(-10.0, 15.0));
+
+    // Mapping the cursor back through the inverse yields the untransformed point
+    assert_point_eq(untransform_point(&child_in_window, &LayoutPoint::new(-10.0, 15.0)).unwrap(), LayoutPoint::new(10.0, 10.0));
+
+    // scale(0) can't be inverted
+    let invisible = get_node_transform(&StyleTransform(vec![Scale(FloatValue::new(0.0), FloatValue::new(0.0))]), &top_left, &parent_bounds);
+    assert_eq!(untransform_point(&invisible, &LayoutPoint::new(0.0, 0.0)), None);
+}
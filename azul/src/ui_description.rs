@@ -4,16 +4,14 @@ use std::{
     rc::Rc,
     collections::BTreeMap,
 };
-use azul_css::{ Css, CssDeclaration, CssProperty };
+use azul_css::{ Css, CssDeclaration };
 use webrender::api::HitTestItem;
 use {
-    FastHashMap,
     id_tree::{Arena, NodeId},
     traits::Layout,
-    dom::Dom,
-    dom::NodeData,
-    ui_state::UiState,
-    style::HoverGroup,
+    dom::{Dom, NodeData, TagIdGenerator},
+    ui_state::{UiState, CssOverrideList},
+    style::{HoverGroup, CssRuleIndex},
     focus::FocusTarget,
 };
 
@@ -30,7 +28,7 @@ pub struct UiDescription<T: Layout> {
     /// This is why we need this field here
     pub(crate) default_style_of_node: StyledNode,
     /// The style properties that should be overridden for this frame, cloned from the `Css`
-    pub(crate) dynamic_css_overrides: BTreeMap<NodeId, FastHashMap<String, CssProperty>>,
+    pub(crate) dynamic_css_overrides: BTreeMap<NodeId, CssOverrideList>,
     /// In order to hit-test :hover and :active selectors, need to insert tags for all rectangles
     /// that have a non-:hover path, for example if we have `#thing:hover`, then all nodes selected by `#thing`
     /// need to get a TagId, otherwise, they can't be hit-tested.
@@ -78,13 +76,17 @@ impl<T: Layout> Default for UiDescription<T> {
         let is_mouse_down = false;
         let mut focused_node = None;
         let mut focus_target = None;
+        let mut tag_ids = TagIdGenerator::new();
+        let css = Css::default();
         Self::match_css_to_dom(
-            &mut default_dom.into_ui_state(),
-            &Css::default(),
+            &mut default_dom.into_ui_state(&mut tag_ids),
+            &css,
+            &CssRuleIndex::new(&css),
             &mut focused_node,
             &mut focus_target,
             &hovered_nodes,
             is_mouse_down,
+            &mut tag_ids,
         )
     }
 }
@@ -96,15 +98,18 @@ impl<T: Layout> UiDescription<T> {
     pub fn match_css_to_dom(
         ui_state: &mut UiState<T>,
         style: &Css,
+        style_index: &CssRuleIndex,
         focused_node: &mut Option<NodeId>,
         pending_focus_target: &mut Option<FocusTarget>,
         hovered_nodes: &BTreeMap<NodeId, HitTestItem>,
         is_mouse_down: bool,
+        tag_ids: &mut TagIdGenerator,
     ) -> Self
     {
         let ui_description = ::style::match_dom_selectors(
             ui_state,
             &style,
+            style_index,
             focused_node,
             pending_focus_target,
             hovered_nodes,
@@ -112,13 +117,13 @@ impl<T: Layout> UiDescription<T> {
         );
 
         // Important: Create all the tags for the :hover and :active selectors
-        ui_state.create_tags_for_hover_nodes(&ui_description.selected_hover_nodes);
+        ui_state.create_tags_for_hover_nodes(&ui_description.selected_hover_nodes, tag_ids);
         ui_description
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq, Hash)]
 pub(crate) struct StyledNode {
     /// The CSS constraints, after the cascading step
     pub(crate) css_constraints: Vec<CssDeclaration>,
-}
\ No newline at end of file
+}
\ No newline at end of file
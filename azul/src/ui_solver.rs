@@ -1,14 +1,21 @@
 use std::f32;
 use glium::glutin::dpi::LogicalPosition;
 use azul_css::{
+    LayoutAxis,
     LayoutPosition,
     LayoutMargin,
     LayoutPadding,
+    LayoutIntrinsicSize,
+    PixelValue,
     RectLayout,
 };
 use {
     id_tree::{NodeId, NodeDataContainer, NodeHierarchy},
     display_list::DisplayRectangle,
+    grid_solver::{
+        GridArea, GridItemContribution, place_grid_items, solve_grid_tracks,
+        get_track_offsets, get_spanned_size, get_grid_content_size,
+    },
 };
 
 const DEFAULT_FLEX_GROW_FACTOR: f32 = 1.0;
@@ -55,23 +62,172 @@ impl WhConstraint {
             _ => false,
         }
     }
+
+    /// Clamps the `value` so that it satisfies the constraint
+    pub fn clamp(&self, value: f32) -> f32 {
+        use self::WhConstraint::*;
+        match self {
+            Between(min, max) => value.max(*min).min(*max),
+            EqualTo(exact) => *exact,
+            Unconstrained => value,
+        }
+    }
+}
+
+/// Size of the content of a node along one axis, used to resolve the
+/// `min-content`, `max-content` and `fit-content` keywords
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub(crate) struct ContentSize {
+    /// Size that the node should take up if no `width` / `height` is set,
+    /// for example the original width of an image
+    pub preferred: Option<f32>,
+    /// Smallest size the content can take up without overflowing,
+    /// for example the width of the longest word of a text
+    pub min_content: f32,
+    /// Size the content takes up if it isn't wrapped at all,
+    /// for example the width of the longest line of a text
+    pub max_content: f32,
+}
+
+impl ContentSize {
+    /// Content that can't be wrapped, i.e. an image or a text with a fixed width
+    pub fn from_preferred(preferred: Option<f32>) -> Self {
+        let size = preferred.unwrap_or(0.0);
+        Self { preferred, min_content: size, max_content: size }
+    }
+}
+
+/// Returns whether the node is taken out of the normal flow (`position: absolute` or
+/// `position: fixed`), i.e. it doesn't take away any space from its siblings
+fn is_out_of_flow(layout: &RectLayout) -> bool {
+    match layout.position.unwrap_or_default() {
+        LayoutPosition::Absolute | LayoutPosition::Fixed => true,
+        LayoutPosition::Static | LayoutPosition::Relative => false,
+    }
+}
+
+/// Returns the containing block of an out-of-flow node, i.e. the node whose padding box
+/// the `top`, `right`, `bottom` and `left` offsets are relative to:
+///
+/// - `position: absolute`: the nearest positioned (non-static) or transformed ancestor
+/// - `position: fixed`: the nearest transformed ancestor
+///
+/// If there is no such ancestor, the root node (the window viewport) is returned.
+fn get_containing_block(
+    node_id: NodeId,
+    node_hierarchy: &NodeHierarchy,
+    arena_data: &NodeDataContainer<RectLayout>,
+    transformed_nodes: &NodeDataContainer<bool>)
+-> NodeId
+{
+    let is_fixed = arena_data[node_id].position == Some(LayoutPosition::Fixed);
+    let mut current_node = node_hierarchy[node_id].parent;
+
+    while let Some(parent) = current_node {
+        let parent_is_positioned = arena_data[parent].position.unwrap_or_default() != LayoutPosition::Static;
+        if transformed_nodes[parent] || (parent_is_positioned && !is_fixed) {
+            return parent;
+        }
+        current_node = node_hierarchy[parent].parent;
+    }
+
+    NodeId::new(0)
+}
+
+/// Resolves the percentages (including `calc()` expressions with a percentage) of the
+/// properties along the `axis` against `basis`, the size of the containing block along
+/// that axis. Returns whether any property was resolved.
+///
+/// Percentages of the `padding` and `margin` always refer to the width of the containing
+/// block, so they are resolved together with the horizontal properties.
+fn resolve_percentages(layout: &mut RectLayout, axis: LayoutAxis, basis: f32) -> bool {
+
+    let mut resolved_any = false;
+
+    {
+        // Sizes and paddings can't be negative, margins and offsets can
+        let mut resolve = |value: &mut PixelValue, min: f32| {
+            if value.has_percentage() {
+                *value = PixelValue::px(value.to_pixels_with_basis(basis).max(min));
+                resolved_any = true;
+            }
+        };
+
+        match axis {
+            LayoutAxis::Horizontal => {
+                if let Some(width) = layout.width.as_mut() { resolve(&mut width.0, 0.0); }
+                if let Some(min_width) = layout.min_width.as_mut() { resolve(&mut min_width.0, 0.0); }
+                if let Some(max_width) = layout.max_width.as_mut() { resolve(&mut max_width.0, 0.0); }
+                if let Some(left) = layout.left.as_mut() { resolve(&mut left.0, f32::MIN); }
+                if let Some(right) = layout.right.as_mut() { resolve(&mut right.0, f32::MIN); }
+                if let Some(padding) = layout.padding.as_mut() {
+                    for side in [&mut padding.top, &mut padding.bottom, &mut padding.left, &mut padding.right].iter_mut() {
+                        if let Some(value) = side.as_mut() { resolve(value, 0.0); }
+                    }
+                }
+                if let Some(margin) = layout.margin.as_mut() {
+                    for side in [&mut margin.top, &mut margin.bottom, &mut margin.left, &mut margin.right].iter_mut() {
+                        if let Some(value) = side.as_mut() { resolve(value, f32::MIN); }
+                    }
+                }
+            },
+            LayoutAxis::Vertical => {
+                if let Some(height) = layout.height.as_mut() { resolve(&mut height.0, 0.0); }
+                if let Some(min_height) = layout.min_height.as_mut() { resolve(&mut min_height.0, 0.0); }
+                if let Some(max_height) = layout.max_height.as_mut() { resolve(&mut max_height.0, 0.0); }
+                if let Some(top) = layout.top.as_mut() { resolve(&mut top.0, f32::MIN); }
+                if let Some(bottom) = layout.bottom.as_mut() { resolve(&mut bottom.0, f32::MIN); }
+            },
+        }
+    }
+
+    resolved_any
+}
+
+/// Returns whether the node lays out its children in a grid (`grid-template-columns`
+/// or `grid-template-rows` is set) instead of in a flex row or column
+fn is_grid_container(layout: &RectLayout) -> bool {
+    layout.grid_template_columns.is_some() || layout.grid_template_rows.is_some()
+}
+
+/// Returns the in-flow children of a grid container, together with the cells they occupy
+fn get_grid_items(
+    node_id: NodeId,
+    node_hierarchy: &NodeHierarchy,
+    arena_data: &NodeDataContainer<RectLayout>)
+-> Vec<(NodeId, GridArea)>
+{
+    let children = node_id
+        .children(node_hierarchy)
+        .filter(|child_id| !is_out_of_flow(&arena_data[*child_id]))
+        .collect::<Vec<NodeId>>();
+
+    let placements = children.iter().map(|child_id| {
+        let child = &arena_data[*child_id];
+        (child.grid_column.map(|c| c.0), child.grid_row.map(|r| r.0))
+    }).collect::<Vec<_>>();
+
+    let explicit_columns = arena_data[node_id].grid_template_columns.as_ref().map(|c| c.0.len()).unwrap_or(0);
+
+    children.into_iter().zip(place_grid_items(explicit_columns, &placements)).collect()
 }
 
 macro_rules! determine_preferred {
-    ($fn_name:ident, $width:ident, $min_width:ident, $max_width:ident) => (
+    ($fn_name:ident, $width:ident, $intrinsic_width:ident, $min_width:ident, $max_width:ident) => (
 
-    /// - `preferred_inner_width` denotes the preferred width of the width or height got from the
-    /// from the rectangles content.
+    /// - `content_size` denotes the size of the rectangles content (see `ContentSize`).
     ///
-    /// For example, if you have an image, the `preferred_inner_width` is the images width,
-    /// if the node type is an text, the `preferred_inner_width` is the text height.
-    fn $fn_name(layout: &RectLayout, preferred_inner_width: Option<f32>) -> WhConstraint {
-
-        let mut width = layout.$width.and_then(|w| Some(w.0.to_pixels()));
-        let min_width = layout.$min_width.and_then(|w| Some(w.0.to_pixels()));
-        let max_width = layout.$max_width.and_then(|w| Some(w.0.to_pixels()));
+    /// For example, if you have an image, the `preferred` content width is the images width,
+    /// if the node type is an text, the `preferred` content width is the text height.
+    ///
+    /// The `min-content`, `max-content` and `fit-content` keywords only take effect if
+    /// no pixel width is set - the `min_width` and `max_width` constraints always win.
+    fn $fn_name(layout: &RectLayout, content_size: ContentSize) -> WhConstraint {
 
-        // TODO: correct for width / height less than 0 - "negative" width is impossible!
+        // Unresolved percentages count as 0 here, so `calc(100% - 20px)` can be negative
+        let mut width = layout.$width.and_then(|w| Some(w.0.to_pixels().max(0.0)));
+        let min_width = layout.$min_width.and_then(|w| Some(w.0.to_pixels().max(0.0)));
+        let max_width = layout.$max_width.and_then(|w| Some(w.0.to_pixels().max(0.0)));
 
         let (absolute_min, absolute_max) = {
             if let (Some(min), Some(max)) = (min_width, max_width) {
@@ -86,9 +242,28 @@ macro_rules! determine_preferred {
             }
         };
 
+        if width.is_none() {
+            match layout.$intrinsic_width {
+                Some(LayoutIntrinsicSize::MinContent) => width = Some(content_size.min_content),
+                Some(LayoutIntrinsicSize::MaxContent) => width = Some(content_size.max_content),
+                Some(LayoutIntrinsicSize::FitContent) => {
+                    // fit-content = min(max-content, max(min-content, available space)),
+                    // the available space is only known once the flex layout runs
+                    let min = content_size.min_content
+                        .max(absolute_min.unwrap_or(0.0))
+                        .min(absolute_max.unwrap_or(f32::MAX));
+                    let max = content_size.max_content
+                        .min(absolute_max.unwrap_or(f32::MAX))
+                        .max(min);
+                    return WhConstraint::Between(min, max);
+                },
+                None => { },
+            }
+        }
+
         // We only need to correct the width if the preferred width is in the range
         // between min & max and the width isn't already specified as a style
-        if let Some(preferred_width) = preferred_inner_width {
+        if let Some(preferred_width) = content_size.preferred {
             if width.is_none() &&
                preferred_width > absolute_min.unwrap_or(0.0) &&
                preferred_width < absolute_max.unwrap_or(f32::MAX)
@@ -100,8 +275,8 @@ macro_rules! determine_preferred {
         if let Some(width) = width {
             if let Some(max_width) = absolute_max {
                 if let Some(min_width) = absolute_min {
-                    if min_width < width && width < max_width {
-                        // normal: min_width < width < max_width
+                    if min_width <= width && width <= max_width {
+                        // normal: min_width <= width <= max_width
                         WhConstraint::EqualTo(width)
                     } else if width > max_width {
                         WhConstraint::EqualTo(max_width)
@@ -146,13 +321,13 @@ macro_rules! determine_preferred {
 /// or `None` if the height can't be determined from the node alone.
 ///
 // fn determine_preferred_width(layout: &RectLayout) -> Option<f32>
-determine_preferred!(determine_preferred_width, width, min_width, max_width);
+determine_preferred!(determine_preferred_width, width, intrinsic_width, min_width, max_width);
 
 /// Returns the preferred height, given [height, min_height, max_height] inside a RectLayout
 // or `None` if the height can't be determined from the node alone.
 ///
 // fn determine_preferred_height(layout: &RectLayout) -> Option<f32>
-determine_preferred!(determine_preferred_height, height, min_height, max_height);
+determine_preferred!(determine_preferred_height, height, intrinsic_height, min_height, max_height);
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 struct WidthCalculatedRect {
@@ -161,6 +336,10 @@ struct WidthCalculatedRect {
     pub padding: LayoutPadding,
     pub flex_grow_px: f32,
     pub min_inner_size_px: f32,
+    /// Content size of the node, including the padding
+    pub content_size: ContentSize,
+    /// Offset of a grid item relative to the content box of its grid container
+    pub grid_offset: Option<f32>,
 }
 
 impl WidthCalculatedRect {
@@ -195,6 +374,10 @@ struct HeightCalculatedRect {
     pub padding: LayoutPadding,
     pub flex_grow_px: f32,
     pub min_inner_size_px: f32,
+    /// Content size of the node, including the padding
+    pub content_size: ContentSize,
+    /// Offset of a grid item relative to the content box of its grid container
+    pub grid_offset: Option<f32>,
 }
 
 impl HeightCalculatedRect {
@@ -230,7 +413,13 @@ macro_rules! typed_arena {(
     $get_padding_fn:ident,
     $get_flex_basis:ident,
     $bubble_fn_name:ident,
-    $main_axis:ident
+    $main_axis:ident,
+    $start:ident,
+    $end:ident,
+    $grid_template:ident,
+    $grid_gap:ident,
+    $grid_start:ident,
+    $grid_span:ident
 ) => (
 
 impl NodeDataContainer<$struct_name> {
@@ -243,7 +432,7 @@ impl NodeDataContainer<$struct_name> {
     ///
     /// NOTE: Later on, this could maybe be a NodeDataContainer<&'a RectLayout>.
     #[must_use]
-    fn from_rect_layout_arena(node_data: &NodeDataContainer<RectLayout>, widths: NodeDataContainer<Option<f32>>) -> Self {
+    fn from_rect_layout_arena(node_data: &NodeDataContainer<RectLayout>, widths: NodeDataContainer<ContentSize>) -> Self {
         let new_nodes = node_data.internal.iter().enumerate().map(|(node_id, node_data)|{
             let id = NodeId::new(node_id);
             $struct_name {
@@ -253,11 +442,95 @@ impl NodeDataContainer<$struct_name> {
                 padding: node_data.padding.unwrap_or_default(),
                 flex_grow_px: 0.0,
                 min_inner_size_px: 0.0,
+                content_size: widths[id],
+                grid_offset: None,
             }
         }).collect();
         NodeDataContainer { internal: new_nodes }
     }
 
+    /// Resolves the `min_content` and `max_content` sizes of all nodes: The content sizes of
+    /// a leaf node are given by its content (text or image), the content of a parent node are
+    /// its children - summed up along the main axis, the largest child along the cross axis.
+    /// Out-of-flow children don't contribute to the size of their parent.
+    ///
+    /// The returned sizes include the padding of the node, i.e. they are border-box sizes.
+    #[must_use]
+    fn resolve_content_sizes(
+        node_hierarchy: &NodeHierarchy,
+        arena_data: &NodeDataContainer<RectLayout>,
+        mut content_sizes: NodeDataContainer<ContentSize>,
+        non_leaf_nodes: &[(usize, NodeId)])
+    -> NodeDataContainer<ContentSize>
+    {
+        use azul_css::LayoutAxis;
+
+        fn px(value: Option<::azul_css::PixelValue>) -> f32 {
+            value.and_then(|px| Some(px.to_pixels())).unwrap_or(0.0)
+        }
+
+        for (content_size, node_data) in content_sizes.internal.iter_mut().zip(arena_data.internal.iter()) {
+            let padding = node_data.padding.unwrap_or_default();
+            content_size.min_content += px(padding.$start) + px(padding.$end);
+            content_size.max_content += px(padding.$start) + px(padding.$end);
+        }
+
+        // Reverse, since we want to go from the inside out (depth 5 needs to be filled out first)
+        for (_node_depth, non_leaf_id) in non_leaf_nodes.iter().rev() {
+
+            if is_grid_container(&arena_data[*non_leaf_id]) {
+                // The content size of a grid is the size of its tracks, sized to the content of the items
+                let (min_contributions, max_contributions): (Vec<GridItemContribution>, Vec<GridItemContribution>) =
+                    get_grid_items(*non_leaf_id, node_hierarchy, arena_data).into_iter().map(|(child_id, area)| {
+                        let child_constraint = $determine_preferred_fn(&arena_data[child_id], content_sizes[child_id]);
+                        let child_margin = arena_data[child_id].margin.unwrap_or_default();
+                        let child_margin = px(child_margin.$start) + px(child_margin.$end);
+                        let min_content = child_constraint.clamp(content_sizes[child_id].min_content) + child_margin;
+                        let max_content = child_constraint.clamp(content_sizes[child_id].max_content) + child_margin;
+                        (
+                            GridItemContribution { start: area.$grid_start, span: area.$grid_span, min_content, max_content: min_content },
+                            GridItemContribution { start: area.$grid_start, span: area.$grid_span, min_content, max_content },
+                        )
+                    }).unzip();
+
+                let grid = &arena_data[*non_leaf_id];
+                let tracks = grid.$grid_template.as_ref().map(|t| &t.0[..]).unwrap_or(&[]);
+                let gap = grid.gap.and_then(|gap| Some(gap.$grid_gap.to_pixels())).unwrap_or(0.0);
+
+                content_sizes[*non_leaf_id].min_content += get_grid_content_size(tracks, &min_contributions, gap);
+                content_sizes[*non_leaf_id].max_content += get_grid_content_size(tracks, &max_contributions, gap);
+                continue;
+            }
+
+            let is_main_axis = arena_data[*non_leaf_id].direction.unwrap_or_default().get_axis() == LayoutAxis::$main_axis;
+            let mut children_min_content = 0.0;
+            let mut children_max_content = 0.0;
+
+            for child_id in non_leaf_id.children(node_hierarchy).filter(|id| !is_out_of_flow(&arena_data[*id])) {
+
+                // A child with a fixed width contributes its width, not the width of its content
+                let child_constraint = $determine_preferred_fn(&arena_data[child_id], content_sizes[child_id]);
+                let child_margin = arena_data[child_id].margin.unwrap_or_default();
+                let child_margin = px(child_margin.$start) + px(child_margin.$end);
+                let child_min_content = child_constraint.clamp(content_sizes[child_id].min_content) + child_margin;
+                let child_max_content = child_constraint.clamp(content_sizes[child_id].max_content) + child_margin;
+
+                if is_main_axis {
+                    children_min_content += child_min_content;
+                    children_max_content += child_max_content;
+                } else {
+                    children_min_content = child_min_content.max(children_min_content);
+                    children_max_content = child_max_content.max(children_max_content);
+                }
+            }
+
+            content_sizes[*non_leaf_id].min_content += children_min_content;
+            content_sizes[*non_leaf_id].max_content += children_max_content;
+        }
+
+        content_sizes
+    }
+
     /// Bubble the inner sizes to their parents -  on any parent nodes, fill out
     /// the width so that the `preferred_width` can contain the child nodes (if
     /// that doesn't violate the constraints of the parent)
@@ -275,7 +548,13 @@ impl NodeDataContainer<$struct_name> {
             use self::WhConstraint::*;
 
             // Sum of the direct children's flex-basis = the parents preferred width
-            let children_flex_basis = self.sum_children_flex_basis(*non_leaf_id, node_hierarchy, arena_data);
+            //
+            // The children of a grid are sized by the tracks, so a grid prefers the size of its content instead
+            let children_flex_basis = if is_grid_container(&arena_data[*non_leaf_id]) {
+                (self[*non_leaf_id].content_size.max_content - self[*non_leaf_id].$get_padding_fn()).max(0.0)
+            } else {
+                self.sum_children_flex_basis(*non_leaf_id, node_hierarchy, arena_data)
+            };
 
             // Calculate the new flex-basis width
             let parent_width_metrics = self[*non_leaf_id];
@@ -308,21 +587,110 @@ impl NodeDataContainer<$struct_name> {
 
     /// Go from the root down and flex_grow the children if needed - respects the `width`, `min_width` and `max_width` properties
     /// The layout step doesn't account for the min_width and max_width constraints, so we have to adjust them manually
+    ///
+    /// Percentages are resolved top-down, as soon as the width of the parent is known: the
+    /// resolved values are written back into `arena_data`, so that the following layout
+    /// steps only see pixel values.
     fn apply_flex_grow(
         &mut self,
         node_hierarchy: &NodeHierarchy,
-        arena_data: &NodeDataContainer<RectLayout>,
+        arena_data: &mut NodeDataContainer<RectLayout>,
+        transformed_nodes: &NodeDataContainer<bool>,
         parent_ids_sorted_by_depth: &[(usize, NodeId)],
         root_width: f32)
     {
+        /// Resolves the percentages of a node against `basis` and updates the
+        /// constraint, padding and margin of the node if anything changed
+        fn resolve_node_percentages(
+            node_id: NodeId,
+            basis: f32,
+            arena_data: &mut NodeDataContainer<RectLayout>,
+            width_calculated_arena: &mut NodeDataContainer<$struct_name>)
+        {
+            if !resolve_percentages(&mut arena_data[node_id], LayoutAxis::$main_axis, basis) {
+                return;
+            }
+
+            let layout = &arena_data[node_id];
+            let node = &mut width_calculated_arena[node_id];
+            node.$preferred_field = $determine_preferred_fn(layout, node.content_size);
+            node.margin = layout.margin.unwrap_or_default();
+            node.padding = layout.padding.unwrap_or_default();
+        }
+
+        /// Resolves the percentages of the children of `node_id`, relative to the inner width
+        /// of the node or, for out-of-flow children, to the width of their containing block
+        fn resolve_child_percentages(
+            node_id: NodeId,
+            node_hierarchy: &NodeHierarchy,
+            arena_data: &mut NodeDataContainer<RectLayout>,
+            transformed_nodes: &NodeDataContainer<bool>,
+            width_calculated_arena: &mut NodeDataContainer<$struct_name>)
+        {
+            let parent_node_inner_width = {
+                let parent_node = &width_calculated_arena[node_id];
+                parent_node.min_inner_size_px + parent_node.flex_grow_px - parent_node.$get_padding_fn()
+            };
+
+            for child_id in node_id.children(node_hierarchy) {
+                let basis = if is_out_of_flow(&arena_data[child_id]) {
+                    let containing_block_id = get_containing_block(child_id, node_hierarchy, arena_data, transformed_nodes);
+                    let containing_block = &width_calculated_arena[containing_block_id];
+                    containing_block.min_inner_size_px + containing_block.flex_grow_px
+                } else {
+                    parent_node_inner_width
+                };
+                resolve_node_percentages(child_id, basis, arena_data, width_calculated_arena);
+            }
+        }
+
+        /// Solves the width of an out-of-flow (`position: absolute` or `position: fixed`) node:
+        /// If both offsets (`left` and `right`) are set, the node stretches to fill its containing
+        /// block, otherwise it shrinks to fit its content. The `width`, `min-width` and `max-width`
+        /// constraints take precedence over the offsets.
+        fn solve_out_of_flow_width(
+            node_id: NodeId,
+            node_hierarchy: &NodeHierarchy,
+            arena_data: &NodeDataContainer<RectLayout>,
+            transformed_nodes: &NodeDataContainer<bool>,
+            width_calculated_arena: &mut NodeDataContainer<$struct_name>)
+        {
+            // The containing block is an ancestor, so its width is already solved
+            let containing_block_id = get_containing_block(node_id, node_hierarchy, arena_data, transformed_nodes);
+            let containing_block_width = {
+                let containing_block = &width_calculated_arena[containing_block_id];
+                containing_block.min_inner_size_px + containing_block.flex_grow_px
+            };
+
+            let node = width_calculated_arena[node_id];
+            let offset_start = arena_data[node_id].$start.and_then(|s| Some(s.0.to_pixels()));
+            let offset_end = arena_data[node_id].$end.and_then(|s| Some(s.0.to_pixels()));
+            let margin_start = node.margin.$start.and_then(|px| Some(px.to_pixels())).unwrap_or(0.0);
+            let margin_end = node.margin.$end.and_then(|px| Some(px.to_pixels())).unwrap_or(0.0);
+
+            let auto_width = match (offset_start, offset_end) {
+                (Some(start), Some(end)) => (containing_block_width - start - end - margin_start - margin_end).max(0.0),
+                _ => node.min_inner_size_px + node.$get_padding_fn(),
+            };
+
+            let width = match node.$preferred_field {
+                WhConstraint::EqualTo(exact) => exact,
+                WhConstraint::Between(min, max) => auto_width.max(min).min(max),
+                WhConstraint::Unconstrained => auto_width,
+            };
+
+            // so that node.min_inner_size_px + node.flex_grow_px = width
+            width_calculated_arena[node_id].flex_grow_px = width - node.min_inner_size_px;
+        }
+
         /// Does the actual width layout, respects the `width`, `min_width` and `max_width`
         /// properties as well as the `flex_grow` factor. `flex_shrink` currently does nothing.
         fn distribute_space_along_main_axis(
             node_id: &NodeId,
             node_hierarchy: &NodeHierarchy,
             arena_data: &NodeDataContainer<RectLayout>,
-            width_calculated_arena: &mut NodeDataContainer<$struct_name>,
-            positioned_node_stack: &[NodeId])
+            transformed_nodes: &NodeDataContainer<bool>,
+            width_calculated_arena: &mut NodeDataContainer<$struct_name>)
         {
             // The inner space of the parent node, without the padding
             let mut parent_node_inner_width = {
@@ -348,9 +716,9 @@ impl NodeDataContainer<$struct_name> {
 
                 for (exact_width_child_id, exact_width) in exact_width_childs {
 
-                    // If this child node is `position: absolute`, it doesn't take any space away from
-                    // its siblings, since it is taken out of the regular content flow
-                    if arena_data[exact_width_child_id].position.unwrap_or_default() != LayoutPosition::Absolute {
+                    // If this child node is `position: absolute` or `position: fixed`, it doesn't take any
+                    // space away from its siblings, since it is taken out of the regular content flow
+                    if !is_out_of_flow(&arena_data[exact_width_child_id]) {
                         horizontal_space_taken_up_by_fixed_width_items += exact_width;
                     }
 
@@ -380,7 +748,7 @@ impl NodeDataContainer<$struct_name> {
 
             for variable_child_id in &variable_width_childs {
 
-                if arena_data[*variable_child_id].position.unwrap_or_default() != LayoutPosition::Absolute {
+                if !is_out_of_flow(&arena_data[*variable_child_id]) {
 
                     let min_width = width_calculated_arena[*variable_child_id].$preferred_field.min_needed_space().unwrap_or(0.0);
 
@@ -392,40 +760,9 @@ impl NodeDataContainer<$struct_name> {
 
                 } else {
 
-                    // `position: absolute` items don't take space away from their siblings, rather
-                    // they take the minimum needed space by their content
-
-                    let root_id = NodeId::new(0);
-                    let nearest_relative_parent_node = positioned_node_stack.get(positioned_node_stack.len() - 1).unwrap_or(&root_id);
-                    let relative_parent_width = {
-                        let relative_parent_node = &width_calculated_arena[*nearest_relative_parent_node];
-                        relative_parent_node.flex_grow_px + relative_parent_node.min_inner_size_px
-                    };
-
-                    // By default, absolute positioned elements take the width of their content
-                    // let min_inner_width = width_calculated_arena[*variable_child_id].$preferred_field.min_needed_space().unwrap_or(0.0);
-
-                    // The absolute positioned node might have a max-width constraint, which has a
-                    // higher precedence than `top, bottom, left, right`.
-                    let max_space_current_node = match width_calculated_arena[*variable_child_id].$preferred_field {
-                        WhConstraint::EqualTo(e) => e,
-                        WhConstraint::Between(min, max) => {
-                            if relative_parent_width > min {
-                                if relative_parent_width < max {
-                                    relative_parent_width
-                                } else {
-                                    max
-                                }
-                            } else {
-                                min
-                            }
-                        },
-                        WhConstraint::Unconstrained => relative_parent_width,
-                    };
-
-                    // so that node.min_inner_size_px + node.flex_grow_px = max_space_current_node
-                    width_calculated_arena[*variable_child_id].flex_grow_px =
-                        max_space_current_node - width_calculated_arena[*variable_child_id].min_inner_size_px;
+                    // Out-of-flow items don't take space away from their siblings, rather
+                    // they are sized by their containing block or their content
+                    solve_out_of_flow_width(*variable_child_id, node_hierarchy, arena_data, transformed_nodes, width_calculated_arena);
 
                     absolute_variable_width_nodes.push(*variable_child_id);
                 }
@@ -523,8 +860,8 @@ impl NodeDataContainer<$struct_name> {
             node_id: &NodeId,
             node_hierarchy: &NodeHierarchy,
             arena_data: &NodeDataContainer<RectLayout>,
-            width_calculated_arena: &mut NodeDataContainer<$struct_name>,
-            positioned_node_stack: &[NodeId])
+            transformed_nodes: &NodeDataContainer<bool>,
+            width_calculated_arena: &mut NodeDataContainer<$struct_name>)
         {
             // The inner space of the parent node, without the padding
             let parent_node_inner_width = {
@@ -532,20 +869,12 @@ impl NodeDataContainer<$struct_name> {
                 parent_node.min_inner_size_px + parent_node.flex_grow_px - parent_node.$get_padding_fn()
             };
 
-            let last_relative_node_width = {
-                let zero_node = NodeId::new(0);
-                let last_relative_node_id = positioned_node_stack.get(positioned_node_stack.len() - 1).unwrap_or(&zero_node);
-                let last_relative_node = &width_calculated_arena[*last_relative_node_id];
-                last_relative_node.min_inner_size_px + last_relative_node.flex_grow_px - last_relative_node.$get_padding_fn()
-            };
-
             for child_id in node_id.children(node_hierarchy) {
 
-                let parent_node_inner_width = if arena_data[child_id].position.unwrap_or_default() != LayoutPosition::Absolute {
-                    parent_node_inner_width
-                } else {
-                    last_relative_node_width
-                };
+                if is_out_of_flow(&arena_data[child_id]) {
+                    solve_out_of_flow_width(child_id, node_hierarchy, arena_data, transformed_nodes, width_calculated_arena);
+                    continue;
+                }
 
                 let preferred_width = {
                     let min_width = width_calculated_arena[child_id].$preferred_field.min_needed_space().unwrap_or(0.0);
@@ -571,8 +900,67 @@ impl NodeDataContainer<$struct_name> {
             }
         }
 
+        /// Sizes the tracks of a grid container to its inner space and sizes the grid
+        /// items to the tracks they span. Items with a `width` (or `min-width` / `max-width`)
+        /// keep their size within their grid area.
+        fn distribute_space_in_grid(
+            node_id: &NodeId,
+            node_hierarchy: &NodeHierarchy,
+            arena_data: &NodeDataContainer<RectLayout>,
+            transformed_nodes: &NodeDataContainer<bool>,
+            width_calculated_arena: &mut NodeDataContainer<$struct_name>)
+        {
+            fn px(value: Option<::azul_css::PixelValue>) -> f32 {
+                value.and_then(|px| Some(px.to_pixels())).unwrap_or(0.0)
+            }
+
+            // The inner space of the parent node, without the padding
+            let parent_node_inner_width = {
+                let parent_node = &width_calculated_arena[*node_id];
+                parent_node.min_inner_size_px + parent_node.flex_grow_px - parent_node.$get_padding_fn()
+            };
+
+            let grid_items = get_grid_items(*node_id, node_hierarchy, arena_data);
+
+            let contributions = grid_items.iter().map(|(child_id, area)| {
+                let child = &width_calculated_arena[*child_id];
+                let child_margin = px(child.margin.$start) + px(child.margin.$end);
+                GridItemContribution {
+                    start: area.$grid_start,
+                    span: area.$grid_span,
+                    min_content: child.$preferred_field.clamp(child.content_size.min_content) + child_margin,
+                    max_content: child.$preferred_field.clamp(child.content_size.max_content) + child_margin,
+                }
+            }).collect::<Vec<GridItemContribution>>();
+
+            let grid = &arena_data[*node_id];
+            let tracks = grid.$grid_template.as_ref().map(|t| &t.0[..]).unwrap_or(&[]);
+            let gap = grid.gap.and_then(|gap| Some(gap.$grid_gap.to_pixels())).unwrap_or(0.0);
+
+            let track_sizes = solve_grid_tracks(tracks, &contributions, parent_node_inner_width, gap);
+            let track_offsets = get_track_offsets(&track_sizes, gap);
+
+            for (child_id, area) in grid_items {
+                let child = width_calculated_arena[child_id];
+                let child_margin = px(child.margin.$start) + px(child.margin.$end);
+                let spanned_size = get_spanned_size(&track_sizes, gap, area.$grid_start, area.$grid_span);
+                let width = child.$preferred_field.clamp(spanned_size - child_margin).max(0.0);
+
+                // so that node.min_inner_size_px + node.flex_grow_px = width
+                width_calculated_arena[child_id].flex_grow_px = width - child.min_inner_size_px;
+                width_calculated_arena[child_id].grid_offset = Some(track_offsets[area.$grid_start]);
+            }
+
+            for child_id in node_id.children(node_hierarchy).filter(|id| is_out_of_flow(&arena_data[*id])) {
+                solve_out_of_flow_width(child_id, node_hierarchy, arena_data, transformed_nodes, width_calculated_arena);
+            }
+        }
+
         debug_assert!(self[NodeId::new(0)].flex_grow_px == 0.0);
 
+        // The percentages of the root node refer to the window
+        resolve_node_percentages(NodeId::new(0), root_width, arena_data, self);
+
         // Set the window width on the root node (since there is only one root node, we can
         // calculate the `flex_grow_px` directly)
         //
@@ -589,26 +977,19 @@ impl NodeDataContainer<$struct_name> {
 
         self[NodeId::new(0)].flex_grow_px = root_preferred_width - top_level_flex_basis;
 
-        // Keep track of the nearest relative or absolute positioned element
-        let mut positioned_node_stack = vec![NodeId::new(0)];
-
         for (_node_depth, parent_id) in parent_ids_sorted_by_depth {
 
-            use azul_css::{LayoutAxis, LayoutPosition};
+            use azul_css::LayoutAxis;
 
-            let parent_is_positioned = arena_data[*parent_id].position.unwrap_or_default() != LayoutPosition::Static;
-            if parent_is_positioned {
-                positioned_node_stack.push(*parent_id);
-            }
+            resolve_child_percentages(*parent_id, node_hierarchy, arena_data, transformed_nodes, self);
+            let arena_data = &*arena_data;
 
-            if arena_data[*parent_id].direction.unwrap_or_default().get_axis() == LayoutAxis::$main_axis {
-                distribute_space_along_main_axis(parent_id, node_hierarchy, arena_data, self, &positioned_node_stack);
+            if is_grid_container(&arena_data[*parent_id]) {
+                distribute_space_in_grid(parent_id, node_hierarchy, arena_data, transformed_nodes, self);
+            } else if arena_data[*parent_id].direction.unwrap_or_default().get_axis() == LayoutAxis::$main_axis {
+                distribute_space_along_main_axis(parent_id, node_hierarchy, arena_data, transformed_nodes, self);
             } else {
-                distribute_space_along_cross_axis(parent_id, node_hierarchy, arena_data, self, &positioned_node_stack);
-            }
-
-            if parent_is_positioned {
-                positioned_node_stack.pop();
+                distribute_space_along_cross_axis(parent_id, node_hierarchy, arena_data, transformed_nodes, self);
             }
         }
     }
@@ -623,7 +1004,7 @@ impl NodeDataContainer<$struct_name> {
     {
         node_id
             .children(node_hierarchy)
-            .filter(|child_node_id| display_arena[*child_node_id].position != Some(LayoutPosition::Absolute))
+            .filter(|child_node_id| !is_out_of_flow(&display_arena[*child_node_id]))
             .map(|child_node_id| self[child_node_id].$get_flex_basis())
             .sum()
     }
@@ -638,7 +1019,13 @@ typed_arena!(
     get_horizontal_padding,
     get_flex_basis_horizontal,
     bubble_preferred_widths_to_parents,
-    Horizontal
+    Horizontal,
+    left,
+    right,
+    grid_template_columns,
+    column,
+    column,
+    column_span
 );
 
 typed_arena!(
@@ -648,7 +1035,13 @@ typed_arena!(
     get_vertical_padding,
     get_flex_basis_vertical,
     bubble_preferred_heights_to_parents,
-    Vertical
+    Vertical,
+    top,
+    bottom,
+    grid_template_rows,
+    row,
+    row,
+    row_span
 );
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -678,46 +1071,100 @@ impl HeightSolvedResult {
 #[derive(Debug, Clone)]
 pub(crate) struct SolvedWidthLayout {
     pub solved_widths: NodeDataContainer<WidthSolvedResult>,
+    /// Horizontal offset of grid items relative to the content box of their grid container
+    pub grid_offsets: NodeDataContainer<Option<f32>>,
     pub layout_only_arena: NodeDataContainer<RectLayout>,
+    /// Whether a node has a `transform` - transformed nodes are the containing block
+    /// of their `position: absolute` and `position: fixed` descendants
+    pub transformed_nodes: NodeDataContainer<bool>,
     pub non_leaf_nodes_sorted_by_depth: Vec<(usize, NodeId)>,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct SolvedHeightLayout {
     pub solved_heights: NodeDataContainer<HeightSolvedResult>,
+    /// Vertical offset of grid items relative to the content box of their grid container
+    pub grid_offsets: NodeDataContainer<Option<f32>>,
+    /// Same as `SolvedWidthLayout::layout_only_arena`, but with the vertical percentages resolved
+    pub layout_only_arena: NodeDataContainer<RectLayout>,
 }
 
 /// Returns the solved widths of the items in a BTree form
 pub(crate) fn solve_flex_layout_width<'a>(
     node_hierarchy: &NodeHierarchy,
     display_rectangles: &NodeDataContainer<DisplayRectangle<'a>>,
-    preferred_widths: NodeDataContainer<Option<f32>>,
+    content_widths: NodeDataContainer<ContentSize>,
+    window_width: f32)
+-> SolvedWidthLayout
+{
+    let layout_only_arena = display_rectangles.transform(|node, _| node.layout.clone());
+    let transformed_nodes = display_rectangles.transform(|node, _| node.style.transform.as_ref().map(|t| !t.0.is_empty()).unwrap_or(false));
+    solve_flex_layout_width_inner(node_hierarchy, layout_only_arena, transformed_nodes, content_widths, window_width)
+}
+
+fn solve_flex_layout_width_inner(
+    node_hierarchy: &NodeHierarchy,
+    mut layout_only_arena: NodeDataContainer<RectLayout>,
+    transformed_nodes: NodeDataContainer<bool>,
+    content_widths: NodeDataContainer<ContentSize>,
     window_width: f32)
 -> SolvedWidthLayout
 {
-    let layout_only_arena = display_rectangles.transform(|node, _| node.layout);
-    let mut width_calculated_arena = NodeDataContainer::<WidthCalculatedRect>::from_rect_layout_arena(&layout_only_arena, preferred_widths);
     let non_leaf_nodes_sorted_by_depth = node_hierarchy.get_parents_sorted_by_depth();
+    let content_widths = NodeDataContainer::<WidthCalculatedRect>::resolve_content_sizes(node_hierarchy, &layout_only_arena, content_widths, &non_leaf_nodes_sorted_by_depth);
+    let mut width_calculated_arena = NodeDataContainer::<WidthCalculatedRect>::from_rect_layout_arena(&layout_only_arena, content_widths);
+
+    // `aspect-ratio`: If only the height of a node is fixed, the width follows from the height
+    for (width_calculated_rect, layout) in width_calculated_arena.internal.iter_mut().zip(layout_only_arena.internal.iter()) {
+        let aspect_ratio = match layout.aspect_ratio {
+            Some(aspect_ratio) if layout.width.is_none() && layout.intrinsic_width.is_none() => aspect_ratio,
+            _ => continue,
+        };
+        if let (Some(_), WhConstraint::EqualTo(height)) = (layout.height, determine_preferred_height(layout, ContentSize::default())) {
+            let width_constraint = determine_preferred_width(layout, ContentSize::default());
+            width_calculated_rect.preferred_width = WhConstraint::EqualTo(width_constraint.clamp(height * aspect_ratio.get()));
+        }
+    }
+
     width_calculated_arena.bubble_preferred_widths_to_parents(node_hierarchy, &layout_only_arena, &non_leaf_nodes_sorted_by_depth);
-    width_calculated_arena.apply_flex_grow(node_hierarchy, &layout_only_arena, &non_leaf_nodes_sorted_by_depth, window_width);
+    width_calculated_arena.apply_flex_grow(node_hierarchy, &mut layout_only_arena, &transformed_nodes, &non_leaf_nodes_sorted_by_depth, window_width);
     let solved_widths = width_calculated_arena.transform(|node, _| node.solved_result());
-    SolvedWidthLayout { solved_widths , layout_only_arena, non_leaf_nodes_sorted_by_depth }
+    let grid_offsets = width_calculated_arena.transform(|node, _| node.grid_offset);
+    SolvedWidthLayout { solved_widths, grid_offsets, layout_only_arena, transformed_nodes, non_leaf_nodes_sorted_by_depth }
 }
 
 /// Returns the solved height of the items in a BTree form
 pub(crate) fn solve_flex_layout_height(
     node_hierarchy: &NodeHierarchy,
     solved_widths: &SolvedWidthLayout,
-    preferred_heights: NodeDataContainer<Option<f32>>,
+    content_heights: NodeDataContainer<ContentSize>,
     window_height: f32)
 -> SolvedHeightLayout
 {
-    let SolvedWidthLayout { layout_only_arena, .. } = solved_widths;
-    let mut height_calculated_arena = NodeDataContainer::<HeightCalculatedRect>::from_rect_layout_arena(&layout_only_arena, preferred_heights);
+    let SolvedWidthLayout { transformed_nodes, .. } = solved_widths;
+    // The horizontal percentages are already resolved, the vertical ones are resolved in `apply_flex_grow`
+    let mut layout_only_arena = solved_widths.layout_only_arena.clone();
+    let content_heights = NodeDataContainer::<HeightCalculatedRect>::resolve_content_sizes(node_hierarchy, &layout_only_arena, content_heights, &solved_widths.non_leaf_nodes_sorted_by_depth);
+    let mut height_calculated_arena = NodeDataContainer::<HeightCalculatedRect>::from_rect_layout_arena(&layout_only_arena, content_heights);
+
+    // `aspect-ratio`: If the height of a node isn't set, the height follows from the solved width
+    // (this includes the case where neither the width nor the height are set)
+    for (node_id, layout) in layout_only_arena.internal.iter().enumerate() {
+        let aspect_ratio = match layout.aspect_ratio {
+            Some(aspect_ratio) if layout.height.is_none() && layout.intrinsic_height.is_none() => aspect_ratio,
+            _ => continue,
+        };
+        let node_id = NodeId::new(node_id);
+        let height_constraint = determine_preferred_height(layout, ContentSize::default());
+        let width = solved_widths.solved_widths[node_id].total();
+        height_calculated_arena[node_id].preferred_height = WhConstraint::EqualTo(height_constraint.clamp(width / aspect_ratio.get()));
+    }
+
     height_calculated_arena.bubble_preferred_heights_to_parents(node_hierarchy, &layout_only_arena, &solved_widths.non_leaf_nodes_sorted_by_depth);
-    height_calculated_arena.apply_flex_grow(node_hierarchy, &layout_only_arena, &solved_widths.non_leaf_nodes_sorted_by_depth, window_height);
+    height_calculated_arena.apply_flex_grow(node_hierarchy, &mut layout_only_arena, &transformed_nodes, &solved_widths.non_leaf_nodes_sorted_by_depth, window_height);
     let solved_heights = height_calculated_arena.transform(|node, _| node.solved_result());
-    SolvedHeightLayout { solved_heights }
+    let grid_offsets = height_calculated_arena.transform(|node, _| node.grid_offset);
+    SolvedHeightLayout { solved_heights, grid_offsets, layout_only_arena }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
@@ -741,13 +1188,16 @@ macro_rules! get_position {
 fn $fn_name(
     node_hierarchy: &NodeHierarchy,
     node_data: &NodeDataContainer<RectLayout>,
+    transformed_nodes: &NodeDataContainer<bool>,
     non_leaf_nodes: &[(usize, NodeId)],
     solved_widths: &$width_layout)
 -> NodeDataContainer<$height_solved_position>
 {
+    /// Positions an out-of-flow node relative to the padding box of its containing block
     fn determine_child_x_absolute(
         child_id: NodeId,
-        positioned_node_stack: &[NodeId],
+        node_hierarchy: &NodeHierarchy,
+        transformed_nodes: &NodeDataContainer<bool>,
         arena_data: &NodeDataContainer<RectLayout>,
         arena_solved_data: &mut NodeDataContainer<$height_solved_position>,
         solved_widths: &$width_layout,
@@ -762,29 +1212,26 @@ fn $fn_name(
         let child_margin_left = child_margin.$left.and_then(|x| Some(x.to_pixels())).unwrap_or(0.0);
         let child_margin_right = child_margin.$right.and_then(|x| Some(x.to_pixels())).unwrap_or(0.0);
 
-        let zero_node = NodeId::new(0);
-        let last_relative_node_id = positioned_node_stack.get(positioned_node_stack.len() - 1).unwrap_or(&zero_node);
-
-        let last_relative_node = arena_data[*last_relative_node_id];
-        let last_relative_padding = last_relative_node.padding.unwrap_or_default();
-        let last_relative_padding_left = last_relative_padding.$left.and_then(|x| Some(x.to_pixels())).unwrap_or(0.0);
-        let last_relative_padding_right = last_relative_padding.$right.and_then(|x| Some(x.to_pixels())).unwrap_or(0.0);
-
-        let last_relative_node_x = arena_solved_data[*last_relative_node_id].0 + last_relative_padding_left;
-        let last_relative_node_inner_width = {
-            let last_relative_node = &solved_widths.$solved_widths_field[*last_relative_node_id];
-            last_relative_node.$min_width + last_relative_node.space_added - (last_relative_padding_left + last_relative_padding_right)
+        // The containing block is an ancestor, so its position is already solved
+        let containing_block_id = get_containing_block(child_id, node_hierarchy, arena_data, transformed_nodes);
+        let containing_block_x = arena_solved_data[containing_block_id].0;
+        let containing_block_width = {
+            let containing_block = &solved_widths.$solved_widths_field[containing_block_id];
+            containing_block.$min_width + containing_block.space_added
         };
 
-        let child_left = &arena_data[child_id].$left.and_then(|s| Some(s.0.to_pixels()));
-        let child_right = &arena_data[child_id].$right.and_then(|s| Some(s.0.to_pixels()));
-
-        if let Some(child_right) = child_right {
-            // align right / bottom of last relative parent
-            arena_solved_data[child_id].0 = (last_relative_node_x + last_relative_node_inner_width) - child_width_with_padding - child_margin_right - child_right;
-        } else {
-            // align left / top of last relative parent
-            arena_solved_data[child_id].0 = last_relative_node_x + child_margin_left + child_left.unwrap_or(0.0);
+        let child_left = arena_data[child_id].$left.and_then(|s| Some(s.0.to_pixels()));
+        let child_right = arena_data[child_id].$right.and_then(|s| Some(s.0.to_pixels()));
+
+        match (child_left, child_right) {
+            (None, Some(child_right)) => {
+                // align right / bottom of the containing block
+                arena_solved_data[child_id].0 = (containing_block_x + containing_block_width) - child_width_with_padding - child_margin_right - child_right;
+            },
+            (child_left, _) => {
+                // align left / top of the containing block
+                arena_solved_data[child_id].0 = containing_block_x + child_margin_left + child_left.unwrap_or(0.0);
+            },
         }
     }
 
@@ -797,7 +1244,8 @@ fn $fn_name(
         parent_x_position: f32,
         parent_inner_width: f32,
         sum_x_of_children_so_far: &mut f32,
-        positioned_node_stack: &[NodeId],
+        node_hierarchy: &NodeHierarchy,
+        transformed_nodes: &NodeDataContainer<bool>,
     ) {
         use azul_css::LayoutJustifyContent::*;
 
@@ -812,10 +1260,11 @@ fn $fn_name(
         let child_margin_left = child_margin.$left.and_then(|x| Some(x.to_pixels())).unwrap_or(0.0);
         let child_margin_right = child_margin.$right.and_then(|x| Some(x.to_pixels())).unwrap_or(0.0);
 
-        if child_node.position.unwrap_or_default() == LayoutPosition::Absolute {
+        if is_out_of_flow(child_node) {
             determine_child_x_absolute(
                 child_id,
-                positioned_node_stack,
+                node_hierarchy,
+                transformed_nodes,
                 arena_data,
                 arena_solved_data,
                 solved_widths
@@ -847,17 +1296,19 @@ fn $fn_name(
         arena_data: &NodeDataContainer<RectLayout>,
         solved_widths: &$width_layout,
         child_id: NodeId,
-        positioned_node_stack: &[NodeId],
+        node_hierarchy: &NodeHierarchy,
+        transformed_nodes: &NodeDataContainer<bool>,
         arena_solved_data: &mut NodeDataContainer<$height_solved_position>,
         parent_x_position: f32)
     {
         let child_node = &arena_data[child_id];
         let child_margin_left = child_node.margin.unwrap_or_default().$left.and_then(|x| Some(x.to_pixels())).unwrap_or(0.0);
 
-        if child_node.position.unwrap_or_default() == LayoutPosition::Absolute {
+        if is_out_of_flow(child_node) {
             determine_child_x_absolute(
                 child_id,
-                positioned_node_stack,
+                node_hierarchy,
+                transformed_nodes,
                 arena_data,
                 arena_solved_data,
                 solved_widths
@@ -871,30 +1322,40 @@ fn $fn_name(
 
     let mut arena_solved_data = NodeDataContainer::new(vec![$height_solved_position(0.0); node_data.len()]);
 
-    // Stack of the positioned nodes (nearest relative or absolute positioned node)
-    let mut positioned_node_stack = vec![NodeId::new(0)];
-
     for (_node_depth, parent_id) in non_leaf_nodes {
 
-        let parent_node = node_data[*parent_id];
+        let parent_node = &node_data[*parent_id];
         let parent_padding = parent_node.padding.unwrap_or_default();
         let parent_padding_left = parent_padding.$left.and_then(|x| Some(x.to_pixels())).unwrap_or(0.0);
         let parent_padding_right = parent_padding.$right.and_then(|x| Some(x.to_pixels())).unwrap_or(0.0);
         let parent_x_position = arena_solved_data[*parent_id].0 + parent_padding_left;
         let parent_direction = parent_node.direction.unwrap_or_default();
 
-        // Push nearest relative or absolute positioned element
-        let parent_is_positioned = parent_node.position.unwrap_or_default() != LayoutPosition::Static;
-        if parent_is_positioned {
-            positioned_node_stack.push(*parent_id);
-        }
-
         let parent_inner_width = {
             let parent_node = &solved_widths.$solved_widths_field[*parent_id];
             parent_node.$min_width + parent_node.space_added - (parent_padding_left + parent_padding_right)
         };
 
-        if parent_direction.get_axis() == LayoutAxis::$axis {
+        if is_grid_container(parent_node) {
+            // Grid items: The offset of the track the item starts in is already solved
+            for child_id in parent_id.children(node_hierarchy) {
+                let child_node = &node_data[child_id];
+                if is_out_of_flow(child_node) {
+                    determine_child_x_absolute(
+                        child_id,
+                        node_hierarchy,
+                        transformed_nodes,
+                        node_data,
+                        &mut arena_solved_data,
+                        solved_widths
+                    );
+                } else {
+                    let child_margin_left = child_node.margin.unwrap_or_default().$left.and_then(|x| Some(x.to_pixels())).unwrap_or(0.0);
+                    let grid_offset = solved_widths.grid_offsets[child_id].unwrap_or(0.0);
+                    arena_solved_data[child_id].0 = parent_x_position + grid_offset + child_margin_left;
+                }
+            }
+        } else if parent_direction.get_axis() == LayoutAxis::$axis {
             // Along main axis: Take X of parent
             let main_axis_alignment = node_data[*parent_id].justify_content.unwrap_or_default();
             let mut sum_x_of_children_so_far = 0.0;
@@ -910,7 +1371,8 @@ fn $fn_name(
                         parent_x_position,
                         parent_inner_width,
                         &mut sum_x_of_children_so_far,
-                        &positioned_node_stack,
+                        node_hierarchy,
+                        transformed_nodes,
                     );
                 }
             } else {
@@ -924,7 +1386,8 @@ fn $fn_name(
                         parent_x_position,
                         parent_inner_width,
                         &mut sum_x_of_children_so_far,
-                        &positioned_node_stack,
+                        node_hierarchy,
+                        transformed_nodes,
                     );
                 }
             }
@@ -937,7 +1400,7 @@ fn $fn_name(
 
             if should_align_towards_end {
                 let diff = parent_inner_width - sum_x_of_children_so_far;
-                for child_id in parent_id.children(node_hierarchy).filter(|ch| node_data[*ch].position.unwrap_or_default() != LayoutPosition::Absolute) {
+                for child_id in parent_id.children(node_hierarchy).filter(|ch| !is_out_of_flow(&node_data[*ch])) {
                     arena_solved_data[child_id].0 += diff;
                 }
             }
@@ -951,7 +1414,8 @@ fn $fn_name(
                         node_data,
                         solved_widths,
                         child_id,
-                        &positioned_node_stack,
+                        node_hierarchy,
+                        transformed_nodes,
                         &mut arena_solved_data,
                         parent_x_position,
                     );
@@ -962,18 +1426,14 @@ fn $fn_name(
                         node_data,
                         solved_widths,
                         child_id,
-                        &positioned_node_stack,
+                        node_hierarchy,
+                        transformed_nodes,
                         &mut arena_solved_data,
                         parent_x_position,
                     );
                 }
             }
         }
-
-        if parent_is_positioned {
-            positioned_node_stack.pop();
-        }
-
     }
 
     arena_solved_data
@@ -988,7 +1448,7 @@ pub(crate) fn get_x_positions(
 -> NodeDataContainer<HorizontalSolvedPosition>
 {
     get_position!(get_pos_x, SolvedWidthLayout, HorizontalSolvedPosition, solved_widths, min_width, left, right, Horizontal);
-    let mut arena = get_pos_x(node_hierarchy, &solved_widths.layout_only_arena, &solved_widths.non_leaf_nodes_sorted_by_depth, solved_widths);
+    let mut arena = get_pos_x(node_hierarchy, &solved_widths.layout_only_arena, &solved_widths.transformed_nodes, &solved_widths.non_leaf_nodes_sorted_by_depth, solved_widths);
 
     // Add the origin on top of the position
     let x = origin.x as f32;
@@ -1008,7 +1468,7 @@ pub(crate) fn get_y_positions(
 -> NodeDataContainer<VerticalSolvedPosition>
 {
     get_position!(get_pos_y, SolvedHeightLayout, VerticalSolvedPosition, solved_heights, min_height, top, bottom, Vertical);
-    let mut arena = get_pos_y(node_hierarchy, &solved_widths.layout_only_arena, &solved_widths.non_leaf_nodes_sorted_by_depth, solved_heights);
+    let mut arena = get_pos_y(node_hierarchy, &solved_heights.layout_only_arena, &solved_widths.transformed_nodes, &solved_widths.non_leaf_nodes_sorted_by_depth, solved_heights);
 
     // Add the origin on top of the position
     let y = origin.y as f32;
@@ -1099,7 +1559,7 @@ mod layout_tests {
         let arena = get_testing_hierarchy();
         let mut arena_data = vec![RectLayout::default(); arena.len()];
         for (id, rect) in constraints {
-            arena_data[*id] = *rect;
+            arena_data[*id] = rect.clone();
         }
         (arena, NodeDataContainer { internal: arena_data })
     }
@@ -1114,7 +1574,7 @@ mod layout_tests {
             max_width: None,
             .. Default::default()
         };
-        assert_eq!(determine_preferred_width(&layout, None), WhConstraint::Unconstrained);
+        assert_eq!(determine_preferred_width(&layout, ContentSize::default()), WhConstraint::Unconstrained);
 
         let layout = RectLayout {
             width: Some(LayoutWidth(PixelValue::px(500.0))),
@@ -1122,7 +1582,7 @@ mod layout_tests {
             max_width: None,
             .. Default::default()
         };
-        assert_eq!(determine_preferred_width(&layout, None), WhConstraint::EqualTo(500.0));
+        assert_eq!(determine_preferred_width(&layout, ContentSize::default()), WhConstraint::EqualTo(500.0));
 
         let layout = RectLayout {
             width: Some(LayoutWidth(PixelValue::px(500.0))),
@@ -1130,7 +1590,7 @@ mod layout_tests {
             max_width: None,
             .. Default::default()
         };
-        assert_eq!(determine_preferred_width(&layout, None), WhConstraint::EqualTo(600.0));
+        assert_eq!(determine_preferred_width(&layout, ContentSize::default()), WhConstraint::EqualTo(600.0));
 
         let layout = RectLayout {
             width: Some(LayoutWidth(PixelValue::px(10000.0))),
@@ -1138,7 +1598,7 @@ mod layout_tests {
             max_width: Some(LayoutMaxWidth(PixelValue::px(800.0))),
             .. Default::default()
         };
-        assert_eq!(determine_preferred_width(&layout, None), WhConstraint::EqualTo(800.0));
+        assert_eq!(determine_preferred_width(&layout, ContentSize::default()), WhConstraint::EqualTo(800.0));
 
         let layout = RectLayout {
             width: None,
@@ -1146,7 +1606,7 @@ mod layout_tests {
             max_width: Some(LayoutMaxWidth(PixelValue::px(800.0))),
             .. Default::default()
         };
-        assert_eq!(determine_preferred_width(&layout, None), WhConstraint::Between(600.0, 800.0));
+        assert_eq!(determine_preferred_width(&layout, ContentSize::default()), WhConstraint::Between(600.0, 800.0));
 
         let layout = RectLayout {
             width: None,
@@ -1154,7 +1614,7 @@ mod layout_tests {
             max_width: Some(LayoutMaxWidth(PixelValue::px(800.0))),
             .. Default::default()
         };
-        assert_eq!(determine_preferred_width(&layout, None), WhConstraint::Between(0.0, 800.0));
+        assert_eq!(determine_preferred_width(&layout, ContentSize::default()), WhConstraint::Between(0.0, 800.0));
 
         let layout = RectLayout {
             width: Some(LayoutWidth(PixelValue::px(1000.0))),
@@ -1162,7 +1622,7 @@ mod layout_tests {
             max_width: Some(LayoutMaxWidth(PixelValue::px(800.0))),
             .. Default::default()
         };
-        assert_eq!(determine_preferred_width(&layout, None), WhConstraint::EqualTo(800.0));
+        assert_eq!(determine_preferred_width(&layout, ContentSize::default()), WhConstraint::EqualTo(800.0));
 
         let layout = RectLayout {
             width: Some(LayoutWidth(PixelValue::px(1200.0))),
@@ -1170,7 +1630,7 @@ mod layout_tests {
             max_width: Some(LayoutMaxWidth(PixelValue::px(800.0))),
             .. Default::default()
         };
-        assert_eq!(determine_preferred_width(&layout, None), WhConstraint::EqualTo(800.0));
+        assert_eq!(determine_preferred_width(&layout, ContentSize::default()), WhConstraint::EqualTo(800.0));
 
         let layout = RectLayout {
             width: Some(LayoutWidth(PixelValue::px(1200.0))),
@@ -1178,7 +1638,7 @@ mod layout_tests {
             max_width: Some(LayoutMaxWidth(PixelValue::px(400.0))),
             .. Default::default()
         };
-        assert_eq!(determine_preferred_width(&layout, None), WhConstraint::EqualTo(400.0));
+        assert_eq!(determine_preferred_width(&layout, ContentSize::default()), WhConstraint::EqualTo(400.0));
     }
 
     /// Tests that the nodes get filled correctly
@@ -1187,7 +1647,7 @@ mod layout_tests {
 
         use azul_css::*;
 
-        let (node_hierarchy, node_data) = get_display_rectangle_arena(&[
+        let (node_hierarchy, mut node_data) = get_display_rectangle_arena(&[
             (0, RectLayout {
                 direction: Some(LayoutDirection::Row),
                 .. Default::default()
@@ -1204,7 +1664,7 @@ mod layout_tests {
             })
         ]);
 
-        let preferred_widths = node_data.transform(|_, _| None);
+        let preferred_widths = node_data.transform(|_, _| ContentSize::default());
         let mut width_filled_out_data = NodeDataContainer::<WidthCalculatedRect>::from_rect_layout_arena(&node_data, preferred_widths);
 
         // Test some basic stuff - test that `get_flex_basis` works
@@ -1285,7 +1745,8 @@ mod layout_tests {
         //    '   '-- 4     -- [] - expecting width to stretch to 80px (half of 160)
         //    '-- 5         -- [] - expecting width to stretch to 554px (754 - 200px max-width of earlier sibling)
 
-        width_filled_out_data.apply_flex_grow(&node_hierarchy, &node_data, &non_leaf_nodes_sorted_by_depth, window_width);
+        let transformed_nodes = node_data.transform(|_, _| false);
+        width_filled_out_data.apply_flex_grow(&node_hierarchy, &mut node_data, &transformed_nodes, &non_leaf_nodes_sorted_by_depth, window_width);
 
         assert_eq!(width_filled_out_data[NodeId::new(0)].solved_result(), WidthSolvedResult {
             min_width: 40.0,
@@ -1312,4 +1773,455 @@ mod layout_tests {
             space_added: window_width - 200.0,
         });
     }
+
+    /// Runs the whole layout (widths, heights and positions) and returns
+    /// the solved `(x, y, width, height)` of every node
+    fn get_solved_rects(
+        node_hierarchy: &NodeHierarchy,
+        node_data: NodeDataContainer<RectLayout>,
+        transformed_nodes: NodeDataContainer<bool>,
+        window_width: f32,
+        window_height: f32)
+    -> Vec<(f32, f32, f32, f32)>
+    {
+        let content_widths = node_data.transform(|_, _| ContentSize::default());
+        let content_heights = node_data.transform(|_, _| ContentSize::default());
+        get_solved_rects_with_content(node_hierarchy, node_data, transformed_nodes, content_widths, content_heights, window_width, window_height)
+    }
+
+    /// Same as `get_solved_rects`, but with the given content sizes (text or images) of the nodes
+    fn get_solved_rects_with_content(
+        node_hierarchy: &NodeHierarchy,
+        node_data: NodeDataContainer<RectLayout>,
+        transformed_nodes: NodeDataContainer<bool>,
+        content_widths: NodeDataContainer<ContentSize>,
+        content_heights: NodeDataContainer<ContentSize>,
+        window_width: f32,
+        window_height: f32)
+    -> Vec<(f32, f32, f32, f32)>
+    {
+        let solved_widths = solve_flex_layout_width_inner(node_hierarchy, node_data, transformed_nodes, content_widths, window_width);
+        let solved_heights = solve_flex_layout_height(node_hierarchy, &solved_widths, content_heights, window_height);
+        let x_positions = get_x_positions(&solved_widths, node_hierarchy, LogicalPosition::new(0.0, 0.0));
+        let y_positions = get_y_positions(&solved_heights, &solved_widths, node_hierarchy, LogicalPosition::new(0.0, 0.0));
+
+        (0..node_hierarchy.len()).map(|id| {
+            let id = NodeId::new(id);
+            (x_positions[id].0, y_positions[id].0, solved_widths.solved_widths[id].total(), solved_heights.solved_heights[id].total())
+        }).collect()
+    }
+
+    /// Tests that out-of-flow nodes are positioned relative to the padding box
+    /// of their containing block, not relative to their parent
+    #[test]
+    fn test_out_of_flow_positioning() {
+
+        use azul_css::*;
+
+        let padding = |px: f32| Some(LayoutPadding {
+            top: Some(PixelValue::px(px)),
+            bottom: Some(PixelValue::px(px)),
+            left: Some(PixelValue::px(px)),
+            right: Some(PixelValue::px(px)),
+        });
+
+        // - window: 400x300px
+        // 0                -- [padding: 20px]
+        // '- 1             -- [position: relative; padding: 10px]
+        //    '-- 2         -- []
+        //    '   '-- 3     -- [position: absolute / fixed; 10x10px]
+        //    '   '--- 4    -- []
+        //    '-- 5         -- [position: absolute; left: 0; right: 0; top: 0; height: 20px]
+        let layout = |node_3: RectLayout| get_display_rectangle_arena(&[
+            (0, RectLayout {
+                padding: padding(20.0),
+                .. Default::default()
+            }),
+            (1, RectLayout {
+                position: Some(LayoutPosition::Relative),
+                padding: padding(10.0),
+                .. Default::default()
+            }),
+            (3, RectLayout {
+                width: Some(LayoutWidth(PixelValue::px(10.0))),
+                height: Some(LayoutHeight(PixelValue::px(10.0))),
+                .. node_3
+            }),
+            (5, RectLayout {
+                position: Some(LayoutPosition::Absolute),
+                left: Some(LayoutLeft(PixelValue::px(0.0))),
+                right: Some(LayoutRight(PixelValue::px(0.0))),
+                top: Some(LayoutTop(PixelValue::px(0.0))),
+                height: Some(LayoutHeight(PixelValue::px(20.0))),
+                .. Default::default()
+            }),
+        ]);
+
+        // Absolute node, aligned to the bottom right of its positioned grandparent (1),
+        // even though its direct parent (2) is not positioned
+        let (node_hierarchy, node_data) = layout(RectLayout {
+            position: Some(LayoutPosition::Absolute),
+            right: Some(LayoutRight(PixelValue::px(5.0))),
+            bottom: Some(LayoutBottom(PixelValue::px(5.0))),
+            .. Default::default()
+        });
+        let transformed_nodes = node_data.transform(|_, _| false);
+
+        assert_eq!(get_solved_rects(&node_hierarchy, node_data, transformed_nodes, 400.0, 300.0), vec![
+            (0.0, 0.0, 400.0, 300.0),
+            (20.0, 20.0, 360.0, 260.0),
+            (30.0, 30.0, 340.0, 240.0),
+            // 20 + 360 - 10 - 5, 20 + 260 - 10 - 5
+            (365.0, 265.0, 10.0, 10.0),
+            (30.0, 30.0, 340.0, 240.0),
+            // stretched to the padding box of 1, out of flow, so 2 takes up the whole space
+            (20.0, 20.0, 360.0, 20.0),
+        ]);
+
+        // Fixed node: ignores the positioned grandparent, relative to the window
+        let fixed_node = RectLayout {
+            position: Some(LayoutPosition::Fixed),
+            left: Some(LayoutLeft(PixelValue::px(5.0))),
+            top: Some(LayoutTop(PixelValue::px(5.0))),
+            .. Default::default()
+        };
+        let (node_hierarchy, node_data) = layout(fixed_node.clone());
+        let transformed_nodes = node_data.transform(|_, _| false);
+        assert_eq!(get_solved_rects(&node_hierarchy, node_data, transformed_nodes, 400.0, 300.0)[3], (5.0, 5.0, 10.0, 10.0));
+
+        // ... unless one of its ancestors is transformed, which becomes the containing block
+        let (node_hierarchy, node_data) = layout(fixed_node.clone());
+        let transformed_nodes = node_data.transform(|_, node_id| node_id == NodeId::new(2));
+        assert_eq!(get_solved_rects(&node_hierarchy, node_data, transformed_nodes, 400.0, 300.0)[3], (35.0, 35.0, 10.0, 10.0));
+
+        // Without offsets on both sides, an out-of-flow node shrinks to its content
+        let (node_hierarchy, mut node_data) = layout(fixed_node);
+        node_data[NodeId::new(5)].right = None;
+        node_data[NodeId::new(5)].padding = padding(4.0);
+        let transformed_nodes = node_data.transform(|_, _| false);
+        assert_eq!(get_solved_rects(&node_hierarchy, node_data, transformed_nodes, 400.0, 300.0)[5], (20.0, 20.0, 8.0, 20.0));
+    }
+
+    /// Tests that percentages and `calc()` expressions resolve against the size of the parent,
+    /// and that the vertical padding resolves against the width, not the height
+    #[test]
+    fn test_percentage_and_calc_sizes() {
+
+        use azul_css::*;
+
+        // - window: 400x300px
+        // 0                -- [padding: 10%] - 40px on all sides, relative to the window width
+        // '- 1             -- [flex-direction: row; height: 50%] - 110px (half of 300 - 80)
+        //    '-- 2         -- [width: 25%; padding-top: 10%] - 80px wide, 32px padding (10% of 320)
+        //    '   '-- 3     -- []
+        //    '   '--- 4    -- []
+        //    '-- 5         -- [width: calc(100% - 80px)] - 240px, the rest of the row
+        let layout = || get_display_rectangle_arena(&[
+            (0, RectLayout {
+                padding: Some(LayoutPadding {
+                    top: Some(PixelValue::percent(10.0)),
+                    bottom: Some(PixelValue::percent(10.0)),
+                    left: Some(PixelValue::percent(10.0)),
+                    right: Some(PixelValue::percent(10.0)),
+                }),
+                .. Default::default()
+            }),
+            (1, RectLayout {
+                direction: Some(LayoutDirection::Row),
+                height: Some(LayoutHeight(PixelValue::percent(50.0))),
+                .. Default::default()
+            }),
+            (2, RectLayout {
+                width: Some(LayoutWidth(PixelValue::percent(25.0))),
+                padding: Some(LayoutPadding { top: Some(PixelValue::percent(10.0)), .. Default::default() }),
+                .. Default::default()
+            }),
+            (5, RectLayout {
+                width: Some(LayoutWidth(PixelValue::calc(-80.0, 0.0, 100.0))),
+                .. Default::default()
+            }),
+        ]);
+
+        let (node_hierarchy, node_data) = layout();
+        let transformed_nodes = node_data.transform(|_, _| false);
+        assert_eq!(get_solved_rects(&node_hierarchy, node_data, transformed_nodes, 400.0, 300.0), vec![
+            (0.0, 0.0, 400.0, 300.0),
+            (40.0, 40.0, 320.0, 110.0),
+            (40.0, 40.0, 80.0, 110.0),
+            (40.0, 72.0, 80.0, 39.0),
+            (40.0, 111.0, 80.0, 39.0),
+            (120.0, 40.0, 240.0, 110.0),
+        ]);
+
+        // The same layout in a smaller window: 20px padding, 160px for the row
+        let (node_hierarchy, node_data) = layout();
+        let transformed_nodes = node_data.transform(|_, _| false);
+        let rects = get_solved_rects(&node_hierarchy, node_data, transformed_nodes, 200.0, 300.0);
+        assert_eq!(rects[2], (20.0, 20.0, 40.0, 130.0));
+        assert_eq!(rects[5], (60.0, 20.0, 80.0, 130.0));
+    }
+
+    /// Content sizes for the testing hierarchy: node 3 is a text (longest word: 40px,
+    /// longest line: 120px, 20px high), node 4 an image (60x30px), all other nodes are divs
+    fn get_testing_content_sizes(node_data: &NodeDataContainer<RectLayout>) -> (NodeDataContainer<ContentSize>, NodeDataContainer<ContentSize>) {
+        let content_widths = node_data.transform(|_, node_id| match node_id.index() {
+            3 => ContentSize { preferred: None, min_content: 40.0, max_content: 120.0 },
+            4 => ContentSize::from_preferred(Some(60.0)),
+            _ => ContentSize::default(),
+        });
+        let content_heights = node_data.transform(|_, node_id| match node_id.index() {
+            3 => ContentSize::from_preferred(Some(20.0)),
+            4 => ContentSize::from_preferred(Some(30.0)),
+            _ => ContentSize::default(),
+        });
+        (content_widths, content_heights)
+    }
+
+    /// Tests `min-content`, `max-content` and `fit-content` on a text (3), an image (4) and an empty div (5)
+    #[test]
+    fn test_intrinsic_sizes() {
+
+        use azul_css::*;
+
+        let solve = |constraints: &[(usize, RectLayout)]| {
+            let (node_hierarchy, node_data) = get_display_rectangle_arena(constraints);
+            let (content_widths, content_heights) = get_testing_content_sizes(&node_data);
+            let transformed_nodes = node_data.transform(|_, _| false);
+            get_solved_rects_with_content(&node_hierarchy, node_data, transformed_nodes, content_widths, content_heights, 400.0, 300.0)
+        };
+
+        let intrinsic_width = |size: LayoutIntrinsicSize| RectLayout {
+            intrinsic_width: Some(size),
+            .. Default::default()
+        };
+
+        let leaf_widths = |size: LayoutIntrinsicSize, node_1: RectLayout| {
+            let rects = solve(&[(1, node_1), (3, intrinsic_width(size)), (4, intrinsic_width(size)), (5, intrinsic_width(size))]);
+            (rects[3].2, rects[4].2, rects[5].2)
+        };
+
+        assert_eq!(leaf_widths(LayoutIntrinsicSize::MinContent, RectLayout::default()), (40.0, 60.0, 0.0));
+        assert_eq!(leaf_widths(LayoutIntrinsicSize::MaxContent, RectLayout::default()), (120.0, 60.0, 0.0));
+        assert_eq!(leaf_widths(LayoutIntrinsicSize::FitContent, RectLayout::default()), (120.0, 60.0, 0.0));
+
+        // fit-content: fills the available space, but never shrinks below min-content
+        let narrow_parent = |width: f32| RectLayout {
+            width: Some(LayoutWidth(PixelValue::px(width))),
+            .. Default::default()
+        };
+        assert_eq!(leaf_widths(LayoutIntrinsicSize::FitContent, narrow_parent(80.0)).0, 80.0);
+        assert_eq!(leaf_widths(LayoutIntrinsicSize::FitContent, narrow_parent(30.0)).0, 40.0);
+
+        // The padding is added to the content size, min-width / max-width take precedence
+        let padded_text = RectLayout {
+            intrinsic_width: Some(LayoutIntrinsicSize::MinContent),
+            padding: Some(LayoutPadding {
+                left: Some(PixelValue::px(5.0)),
+                right: Some(PixelValue::px(5.0)),
+                .. Default::default()
+            }),
+            .. Default::default()
+        };
+        assert_eq!(solve(&[(3, padded_text)])[3].2, 50.0);
+        assert_eq!(solve(&[(3, RectLayout { max_width: Some(LayoutMaxWidth(PixelValue::px(100.0))), .. intrinsic_width(LayoutIntrinsicSize::MaxContent) })])[3].2, 100.0);
+
+        // Parent nodes get their content size from their children: 2 contains the text and the image
+        assert_eq!(solve(&[(1, intrinsic_width(LayoutIntrinsicSize::MinContent))])[1].2, 60.0);
+        assert_eq!(solve(&[(1, intrinsic_width(LayoutIntrinsicSize::MaxContent))])[1].2, 120.0);
+
+        // Column: the heights of the text (20px) and the image (30px) add up
+        let max_content_height = RectLayout {
+            intrinsic_height: Some(LayoutIntrinsicSize::MaxContent),
+            .. Default::default()
+        };
+        assert_eq!(solve(&[(2, max_content_height)])[2].3, 50.0);
+    }
+
+    /// Tests that the `aspect-ratio` resolves the missing axis
+    #[test]
+    fn test_aspect_ratio() {
+
+        use azul_css::*;
+
+        let aspect_ratio = |width: f32, height: f32| Some(LayoutAspectRatio {
+            width: FloatValue::new(width),
+            height: FloatValue::new(height),
+        });
+
+        let solve = |constraints: &[(usize, RectLayout)]| {
+            let (node_hierarchy, node_data) = get_display_rectangle_arena(constraints);
+            let transformed_nodes = node_data.transform(|_, _| false);
+            get_solved_rects(&node_hierarchy, node_data, transformed_nodes, 400.0, 300.0)
+        };
+
+        let rects = solve(&[
+            // width given: height follows the width
+            (3, RectLayout {
+                width: Some(LayoutWidth(PixelValue::px(150.0))),
+                aspect_ratio: aspect_ratio(3.0, 2.0),
+                .. Default::default()
+            }),
+            // height given: width follows the height
+            (4, RectLayout {
+                height: Some(LayoutHeight(PixelValue::px(100.0))),
+                aspect_ratio: aspect_ratio(3.0, 2.0),
+                .. Default::default()
+            }),
+            // nothing given: width is stretched by the flex layout, height follows the width
+            (5, RectLayout {
+                aspect_ratio: aspect_ratio(2.0, 1.0),
+                .. Default::default()
+            }),
+        ]);
+
+        assert_eq!((rects[3].2, rects[3].3), (150.0, 100.0));
+        assert_eq!((rects[4].2, rects[4].3), (150.0, 100.0));
+        assert_eq!((rects[5].2, rects[5].3), (400.0, 200.0));
+
+        // max-height is respected, even if it breaks the aspect ratio
+        let rects = solve(&[(5, RectLayout {
+            max_height: Some(LayoutMaxHeight(PixelValue::px(100.0))),
+            aspect_ratio: aspect_ratio(2.0, 1.0),
+            .. Default::default()
+        })]);
+        assert_eq!((rects[5].2, rects[5].3), (400.0, 100.0));
+
+        // width and height given: aspect-ratio is ignored
+        let rects = solve(&[(4, RectLayout {
+            width: Some(LayoutWidth(PixelValue::px(100.0))),
+            height: Some(LayoutHeight(PixelValue::px(100.0))),
+            aspect_ratio: aspect_ratio(2.0, 1.0),
+            .. Default::default()
+        })]);
+        assert_eq!((rects[4].2, rects[4].3), (100.0, 100.0));
+    }
+
+    /// Tests that the content sizes don't change the layout of nodes without
+    /// `min-content`, `max-content`, `fit-content` or `aspect-ratio`
+    #[test]
+    fn test_content_sizes_without_keywords() {
+
+        use azul_css::*;
+
+        // 0
+        // '- 1             -- [flex-direction: row]
+        //    '-- 2
+        //    '   '-- 3     -- text
+        //    '   '--- 4
+        //    '-- 5         -- [width: 100px]
+        let (node_hierarchy, node_data) = get_display_rectangle_arena(&[
+            (1, RectLayout {
+                direction: Some(LayoutDirection::Row),
+                .. Default::default()
+            }),
+            (5, RectLayout {
+                width: Some(LayoutWidth(PixelValue::px(100.0))),
+                .. Default::default()
+            }),
+        ]);
+
+        let expected = vec![
+            (0.0, 0.0, 400.0, 300.0),
+            (0.0, 0.0, 400.0, 300.0),
+            (0.0, 0.0, 300.0, 300.0),
+            (0.0, 0.0, 300.0, 150.0),
+            (0.0, 150.0, 300.0, 150.0),
+            (300.0, 0.0, 100.0, 300.0),
+        ];
+
+        let transformed_nodes = node_data.transform(|_, _| false);
+        assert_eq!(get_solved_rects(&node_hierarchy, node_data.clone(), transformed_nodes.clone(), 400.0, 300.0), expected);
+
+        let content_widths = node_data.transform(|_, node_id| match node_id.index() {
+            3 => ContentSize { preferred: None, min_content: 40.0, max_content: 120.0 },
+            _ => ContentSize::default(),
+        });
+        let content_heights = node_data.transform(|_, _| ContentSize::default());
+        assert_eq!(get_solved_rects_with_content(&node_hierarchy, node_data, transformed_nodes, content_widths, content_heights, 400.0, 300.0), expected);
+    }
+
+    /// Returns a hierarchy with a root (0), a single child (1) and
+    /// `item_count` children of that child (2, 3, ...)
+    fn get_flat_testing_hierarchy(item_count: usize) -> NodeHierarchy {
+        let mut nodes = vec![
+            Node {
+                parent: None,
+                previous_sibling: None,
+                next_sibling: None,
+                first_child: Some(NodeId::new(1)),
+                last_child: Some(NodeId::new(1)),
+            },
+            Node {
+                parent: Some(NodeId::new(0)),
+                previous_sibling: None,
+                next_sibling: None,
+                first_child: if item_count > 0 { Some(NodeId::new(2)) } else { None },
+                last_child: if item_count > 0 { Some(NodeId::new(item_count + 1)) } else { None },
+            },
+        ];
+        for item_id in 2..(item_count + 2) {
+            nodes.push(Node {
+                parent: Some(NodeId::new(1)),
+                previous_sibling: if item_id > 2 { Some(NodeId::new(item_id - 1)) } else { None },
+                next_sibling: if item_id < item_count + 1 { Some(NodeId::new(item_id + 1)) } else { None },
+                first_child: None,
+                last_child: None,
+            });
+        }
+        NodeHierarchy { internal: nodes }
+    }
+
+    /// Tests a form-like grid with a content-sized label column and a `1fr` field column
+    #[test]
+    fn test_grid_form_layout() {
+
+        use azul_css::*;
+
+        // - window: 400x300px
+        // 0
+        // '- 1     -- [grid-template-columns: auto 1fr; grid-template-rows: 30px 30px; gap: 10px]
+        //    '-- 2 -- label (80x20px)
+        //    '-- 3 -- field
+        //    '-- 4 -- label (60x20px)
+        //    '-- 5 -- field
+        //    '-- 6 -- [grid-column: span 2; height: 25px], placed in an implicit row
+        let node_hierarchy = get_flat_testing_hierarchy(5);
+        let mut node_data = NodeDataContainer::new(vec![RectLayout::default(); node_hierarchy.len()]);
+        node_data[NodeId::new(1)] = RectLayout {
+            grid_template_columns: Some(LayoutGridTemplateColumns(vec![GridTrackSize::Auto, GridTrackSize::Fraction(FloatValue::new(1.0))])),
+            grid_template_rows: Some(LayoutGridTemplateRows(vec![GridTrackSize::Fixed(PixelValue::px(30.0)); 2])),
+            gap: Some(LayoutGap { row: PixelValue::px(10.0), column: PixelValue::px(10.0) }),
+            .. Default::default()
+        };
+        node_data[NodeId::new(6)] = RectLayout {
+            grid_column: Some(LayoutGridColumn(GridPlacement { start: None, span: 2 })),
+            height: Some(LayoutHeight(PixelValue::px(25.0))),
+            .. Default::default()
+        };
+
+        let content_widths = node_data.transform(|_, node_id| match node_id.index() {
+            2 => ContentSize::from_preferred(Some(80.0)),
+            4 => ContentSize::from_preferred(Some(60.0)),
+            _ => ContentSize::default(),
+        });
+        let content_heights = node_data.transform(|_, node_id| match node_id.index() {
+            2 | 4 => ContentSize::from_preferred(Some(20.0)),
+            _ => ContentSize::default(),
+        });
+        let transformed_nodes = node_data.transform(|_, _| false);
+
+        assert_eq!(get_solved_rects_with_content(&node_hierarchy, node_data, transformed_nodes, content_widths, content_heights, 400.0, 300.0), vec![
+            (0.0, 0.0, 400.0, 300.0),
+            (0.0, 0.0, 400.0, 300.0),
+            // the label column is as wide as the widest label, the
+            // field column takes the rest: 400 - 80 - 10 = 310
+            (0.0, 0.0, 80.0, 20.0),
+            (90.0, 0.0, 310.0, 30.0),
+            // the second label keeps its fixed content width
+            (0.0, 40.0, 60.0, 20.0),
+            (90.0, 40.0, 310.0, 30.0),
+            // spans both columns, including the gap
+            (0.0, 80.0, 400.0, 25.0),
+        ]);
+    }
 }
@@ -1,5 +1,6 @@
 use std::{
-    fmt,
+    fmt, mem,
+    rc::Rc,
     collections::BTreeMap,
 };
 use azul_css::CssProperty;
@@ -8,7 +9,8 @@ use {
     FastHashMap,
     window::{LayoutInfo, WindowId},
     traits::Layout,
-    dom::{Callback, Dom, TagId, TabIndex,
+    dom::{Callback, Dom, NodeData, DomError, DomStatistics, TagId, TagIdGenerator, TabIndex,
+        dump_tree_with_overrides,
         HoverEventFilter, FocusEventFilter, NotEventFilter,
         WindowEventFilter, DesktopEventFilter
     },
@@ -16,24 +18,81 @@ use {
     id_tree::NodeId,
     style::HoverGroup,
     default_callbacks::DefaultCallbackId,
+    profiling::{FrameProfiler, FramePhase},
+    toast::append_toast_layer,
+    diagnostics::{AzulError, report},
 };
 
+/// The callbacks of a single node for one type of event filter. Nodes rarely have
+/// more than two or three callbacks, so a linear search through a flat `Vec` is
+/// faster than a lookup in a tree or hash map. Each filter appears at most once.
+pub type CallbackList<F, C> = Vec<(F, C)>;
+
+/// Maps the `NodeId` of every node that has at least one callback to its callbacks
+pub type CallbackMap<F, C> = FastHashMap<NodeId, CallbackList<F, C>>;
+
+/// Returns the callback that is registered for the `filter` in the `list`, if any
+#[inline]
+pub(crate) fn find_callback<F: PartialEq, C: Copy>(list: &[(F, C)], filter: &F) -> Option<C> {
+    list.iter().find(|(f, _)| f == filter).map(|(_, c)| *c)
+}
+
+/// Inserts the callback into the list - if the filter is already in the list,
+/// the old callback is replaced, so that the last registered callback wins.
+#[inline]
+pub(crate) fn insert_callback<F: PartialEq, C>(list: &mut CallbackList<F, C>, filter: F, callback: C) {
+    match list.iter_mut().find(|(f, _)| *f == filter) {
+        Some(existing) => existing.1 = callback,
+        None => list.push((filter, callback)),
+    }
+}
+
+/// The dynamic CSS overrides of a single node. The list is built once when the
+/// `UiState` is created and shared (not cloned) with the `UiDescription` of the same frame.
+///
+/// Every override ID appears at most once - if the user overrode the same ID twice,
+/// the last override wins.
+pub type CssOverrideList = Rc<[(String, CssProperty)]>;
+
+/// Deduplicates the overrides of a node (with last-wins semantics), while keeping
+/// the order in which the IDs were first added
+pub(crate) fn dedup_css_overrides(overrides: &[(String, CssProperty)]) -> CssOverrideList {
+    let mut deduplicated: Vec<(String, CssProperty)> = Vec::with_capacity(overrides.len());
+    for (override_id, property) in overrides {
+        match deduplicated.iter_mut().find(|(id, _)| id == override_id) {
+            Some(existing) => existing.1 = property.clone(),
+            None => deduplicated.push((override_id.clone(), property.clone())),
+        }
+    }
+    deduplicated.into()
+}
+
+/// Returns the property that the `override_id` is overridden with, if any
+#[inline]
+pub(crate) fn find_css_override<'a>(overrides: &'a [(String, CssProperty)], override_id: &str) -> Option<&'a CssProperty> {
+    overrides.iter().find(|(id, _)| id == override_id).map(|(_, property)| property)
+}
+
+/// The DOM of one frame, together with the hit-testing tags and callbacks of its nodes
+///
+/// Created from the DOM that `Layout::layout()` returns - the read-only getters
+/// are mostly useful to test which nodes got a tag and which callbacks were registered.
 pub struct UiState<T: Layout> {
     /// The actual DOM, rendered from the .layout() function
-    pub dom: Dom<T>,
+    pub(crate) dom: Dom<T>,
     /// The style properties that should be overridden for this frame, cloned from the `Css`
-    pub dynamic_css_overrides: BTreeMap<NodeId, FastHashMap<String, CssProperty>>,
+    pub(crate) dynamic_css_overrides: BTreeMap<NodeId, CssOverrideList>,
     /// Stores all tags for nodes that need to activate on a `:hover` or `:active` event.
-    pub tag_ids_to_hover_active_states: BTreeMap<TagId, (NodeId, HoverGroup)>,
+    pub(crate) tag_ids_to_hover_active_states: BTreeMap<TagId, (NodeId, HoverGroup)>,
 
     /// Tags -> Focusable nodes
-    pub tab_index_tags: BTreeMap<TagId, (NodeId, TabIndex)>,
+    pub(crate) tab_index_tags: BTreeMap<TagId, (NodeId, TabIndex)>,
     /// Tags -> Draggable nodes
-    pub draggable_tags: BTreeMap<TagId, NodeId>,
+    pub(crate) draggable_tags: BTreeMap<TagId, NodeId>,
     /// Tag IDs -> Node IDs
-    pub tag_ids_to_node_ids: BTreeMap<TagId, NodeId>,
+    pub(crate) tag_ids_to_node_ids: BTreeMap<TagId, NodeId>,
     /// Reverse of `tag_ids_to_node_ids`.
-    pub node_ids_to_tag_ids: BTreeMap<NodeId, TagId>,
+    pub(crate) node_ids_to_tag_ids: BTreeMap<NodeId, TagId>,
 
     // For hover, focus and not callbacks, there needs to be a tag generated
     // for hit-testing. Since window and desktop callbacks are not attached to
@@ -43,16 +102,16 @@ pub struct UiState<T: Layout> {
     // There are two maps per event, one for the regular callbacks and one for
     // the default callbacks. This is done for consistency, since otherwise the
     // event filtering logic gets much more complicated than it already is.
-    pub hover_callbacks:                BTreeMap<NodeId, BTreeMap<HoverEventFilter, Callback<T>>>,
-    pub hover_default_callbacks:        BTreeMap<NodeId, BTreeMap<HoverEventFilter, DefaultCallbackId>>,
-    pub focus_callbacks:                BTreeMap<NodeId, BTreeMap<FocusEventFilter, Callback<T>>>,
-    pub focus_default_callbacks:        BTreeMap<NodeId, BTreeMap<FocusEventFilter, DefaultCallbackId>>,
-    pub not_callbacks:                  BTreeMap<NodeId, BTreeMap<NotEventFilter, Callback<T>>>,
-    pub not_default_callbacks:          BTreeMap<NodeId, BTreeMap<NotEventFilter, DefaultCallbackId>>,
-    pub window_callbacks:               BTreeMap<NodeId, BTreeMap<WindowEventFilter, Callback<T>>>,
-    pub window_default_callbacks:       BTreeMap<NodeId, BTreeMap<WindowEventFilter, DefaultCallbackId>>,
-    pub desktop_callbacks:              BTreeMap<NodeId, BTreeMap<DesktopEventFilter, Callback<T>>>,
-    pub desktop_default_callbacks:      BTreeMap<NodeId, BTreeMap<DesktopEventFilter, DefaultCallbackId>>,
+    pub(crate) hover_callbacks:                CallbackMap<HoverEventFilter, Callback<T>>,
+    pub(crate) hover_default_callbacks:        CallbackMap<HoverEventFilter, DefaultCallbackId>,
+    pub(crate) focus_callbacks:                CallbackMap<FocusEventFilter, Callback<T>>,
+    pub(crate) focus_default_callbacks:        CallbackMap<FocusEventFilter, DefaultCallbackId>,
+    pub(crate) not_callbacks:                  CallbackMap<NotEventFilter, Callback<T>>,
+    pub(crate) not_default_callbacks:          CallbackMap<NotEventFilter, DefaultCallbackId>,
+    pub(crate) window_callbacks:               CallbackMap<WindowEventFilter, Callback<T>>,
+    pub(crate) window_default_callbacks:       CallbackMap<WindowEventFilter, DefaultCallbackId>,
+    pub(crate) desktop_callbacks:              CallbackMap<DesktopEventFilter, Callback<T>>,
+    pub(crate) desktop_default_callbacks:      CallbackMap<DesktopEventFilter, DefaultCallbackId>,
 }
 
 impl<T: Layout> fmt::Debug for UiState<T> {
@@ -100,8 +159,171 @@ impl<T: Layout> fmt::Debug for UiState<T> {
     }
 }
 
+/// Number of entries in the maps of a `UiState`, see `UiState::summary()`
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct UiStateSummary {
+    /// Number of nodes in the DOM
+    pub nodes: usize,
+    /// Number of nodes that got a hit-testing tag
+    pub tags: usize,
+    /// Number of focusable nodes (nodes with a `tab_index`)
+    pub tab_index_tags: usize,
+    /// Number of draggable nodes
+    pub draggable_tags: usize,
+    /// Number of nodes with at least one (regular or default) hover callback
+    pub nodes_with_hover_callbacks: usize,
+    /// Number of nodes with at least one (regular or default) focus callback
+    pub nodes_with_focus_callbacks: usize,
+    /// Number of nodes with at least one (regular or default) `Not` callback
+    pub nodes_with_not_callbacks: usize,
+    /// Number of nodes with at least one (regular or default) window callback
+    pub nodes_with_window_callbacks: usize,
+    /// Number of nodes with at least one (regular or default) desktop callback
+    pub nodes_with_desktop_callbacks: usize,
+    /// Number of nodes with dynamic CSS overrides
+    pub nodes_with_css_overrides: usize,
+}
+
+/// Returns the sorted, deduplicated `NodeId`s that have callbacks in either map
+fn nodes_with_callbacks<F, C, D>(callbacks: &CallbackMap<F, C>, default_callbacks: &CallbackMap<F, D>) -> Vec<NodeId> {
+    let mut nodes = callbacks.keys().chain(default_callbacks.keys()).cloned().collect::<Vec<_>>();
+    nodes.sort();
+    nodes.dedup();
+    nodes
+}
+
 impl<T: Layout> UiState<T> {
 
+    /// Creates the `UiState` of a DOM, with the tags starting at 1 - the same
+    /// tags that the first DOM of a window would get.
+    pub fn from_dom(dom: Dom<T>) -> Self {
+        dom.into_ui_state(&mut TagIdGenerator::new())
+    }
+
+    /// Same as `from_dom`, but checks the structure of the DOM first,
+    /// for DOMs that weren't built via `add_child` / `collect()`
+    pub fn try_from_dom(dom: Dom<T>) -> Result<Self, DomError> {
+        dom.try_into_ui_state(&mut TagIdGenerator::new())
+    }
+
+    /// The DOM that this `UiState` was created from
+    pub fn dom(&self) -> &Dom<T> {
+        &self.dom
+    }
+
+    /// Maps the hit-testing tags to the nodes they were assigned to
+    pub fn tag_ids_to_node_ids(&self) -> &BTreeMap<TagId, NodeId> {
+        &self.tag_ids_to_node_ids
+    }
+
+    /// Maps the nodes to their hit-testing tag - nodes without
+    /// callbacks, tab index or drag & drop don't get a tag
+    pub fn node_ids_to_tag_ids(&self) -> &BTreeMap<NodeId, TagId> {
+        &self.node_ids_to_tag_ids
+    }
+
+    /// The tags of all focusable nodes
+    pub fn tab_index_tags(&self) -> &BTreeMap<TagId, (NodeId, TabIndex)> {
+        &self.tab_index_tags
+    }
+
+    /// The tags of all draggable nodes
+    pub fn draggable_tags(&self) -> &BTreeMap<TagId, NodeId> {
+        &self.draggable_tags
+    }
+
+    /// The (deduplicated) dynamic CSS overrides of the nodes that have any
+    pub fn dynamic_css_overrides(&self) -> &BTreeMap<NodeId, CssOverrideList> {
+        &self.dynamic_css_overrides
+    }
+
+    /// Returns the ancestors of the node up to the root, see `Dom::ancestors`
+    pub fn ancestors<'a>(&'a self, node_id: NodeId) -> impl Iterator<Item = NodeId> + 'a {
+        self.dom.ancestors(node_id)
+    }
+
+    /// Returns the node itself or its nearest ancestor that matches, see `Dom::closest`
+    pub fn closest<F: Fn(&NodeData<T>) -> bool>(&self, node_id: NodeId, predicate: F) -> Option<NodeId> {
+        self.dom.closest(node_id, predicate)
+    }
+
+    /// Prints the DOM like `Dom::dump_with_overrides`, but with the CSS overrides
+    /// that were collected into this `UiState`, for debugging styling issues
+    pub fn dump_with_overrides(&self) -> String {
+        dump_tree_with_overrides(&self.dom, &self.dynamic_css_overrides)
+    }
+
+    /// Nodes with at least one (regular or default) hover callback, sorted
+    pub fn nodes_with_hover_callbacks(&self) -> Vec<NodeId> {
+        nodes_with_callbacks(&self.hover_callbacks, &self.hover_default_callbacks)
+    }
+
+    /// Nodes with at least one (regular or default) focus callback, sorted
+    pub fn nodes_with_focus_callbacks(&self) -> Vec<NodeId> {
+        nodes_with_callbacks(&self.focus_callbacks, &self.focus_default_callbacks)
+    }
+
+    /// Nodes with at least one (regular or default) `Not` callback, sorted
+    pub fn nodes_with_not_callbacks(&self) -> Vec<NodeId> {
+        nodes_with_callbacks(&self.not_callbacks, &self.not_default_callbacks)
+    }
+
+    /// Nodes with at least one (regular or default) window callback, sorted
+    pub fn nodes_with_window_callbacks(&self) -> Vec<NodeId> {
+        nodes_with_callbacks(&self.window_callbacks, &self.window_default_callbacks)
+    }
+
+    /// Nodes with at least one (regular or default) desktop callback, sorted
+    pub fn nodes_with_desktop_callbacks(&self) -> Vec<NodeId> {
+        nodes_with_callbacks(&self.desktop_callbacks, &self.desktop_default_callbacks)
+    }
+
+    /// The regular hover callbacks of a node (empty if the node has none)
+    pub fn hover_callbacks(&self, node_id: NodeId) -> &[(HoverEventFilter, Callback<T>)] {
+        self.hover_callbacks.get(&node_id).map(|c| &c[..]).unwrap_or(&[])
+    }
+
+    /// The regular focus callbacks of a node (empty if the node has none)
+    pub fn focus_callbacks(&self, node_id: NodeId) -> &[(FocusEventFilter, Callback<T>)] {
+        self.focus_callbacks.get(&node_id).map(|c| &c[..]).unwrap_or(&[])
+    }
+
+    /// The regular `Not` callbacks of a node (empty if the node has none)
+    pub fn not_callbacks(&self, node_id: NodeId) -> &[(NotEventFilter, Callback<T>)] {
+        self.not_callbacks.get(&node_id).map(|c| &c[..]).unwrap_or(&[])
+    }
+
+    /// The regular window callbacks of a node (empty if the node has none)
+    pub fn window_callbacks(&self, node_id: NodeId) -> &[(WindowEventFilter, Callback<T>)] {
+        self.window_callbacks.get(&node_id).map(|c| &c[..]).unwrap_or(&[])
+    }
+
+    /// The regular desktop callbacks of a node (empty if the node has none)
+    pub fn desktop_callbacks(&self, node_id: NodeId) -> &[(DesktopEventFilter, Callback<T>)] {
+        self.desktop_callbacks.get(&node_id).map(|c| &c[..]).unwrap_or(&[])
+    }
+
+    /// Returns the node, callback and CSS override counts of the DOM, see `Dom::statistics()`
+    pub fn statistics(&self) -> DomStatistics {
+        self.dom.statistics()
+    }
+
+    /// Returns the sizes of all maps, for quick sanity checks in tests
+    pub fn summary(&self) -> UiStateSummary {
+        UiStateSummary {
+            nodes: self.dom.len(),
+            tags: self.tag_ids_to_node_ids.len(),
+            tab_index_tags: self.tab_index_tags.len(),
+            draggable_tags: self.draggable_tags.len(),
+            nodes_with_hover_callbacks: self.nodes_with_hover_callbacks().len(),
+            nodes_with_focus_callbacks: self.nodes_with_focus_callbacks().len(),
+            nodes_with_not_callbacks: self.nodes_with_not_callbacks().len(),
+            nodes_with_window_callbacks: self.nodes_with_window_callbacks().len(),
+            nodes_with_desktop_callbacks: self.nodes_with_desktop_callbacks().len(),
+            nodes_with_css_overrides: self.dynamic_css_overrides.len(),
+        }
+    }
+
     #[allow(unused_imports, unused_variables)]
     pub(crate) fn from_app_state(app_state: &mut AppState<T>, window_id: &WindowId)
     -> Result<Self, RuntimeError<T>>
@@ -110,14 +332,23 @@ impl<T: Layout> UiState<T> {
         use std::sync::atomic::Ordering;
         use app::RuntimeError::*;
 
-        let mut fake_window = app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?;
-        let window_info = LayoutInfo {
-            window: &mut fake_window,
-            resources: &app_state.resources,
-        };
+        let fake_window = app_state.windows.get_mut(window_id).ok_or(WindowIndexError)?;
+
+        fake_window.profiler.begin_frame();
+        let layout_start = FrameProfiler::start();
+
+        // A hot-reloaded UI replaces the Layout::layout() function, the data is untouched
+        let hot_reloaded_dom = app_state.hot_reload_ui.as_ref().and_then(|ui| ui.get_dom());
 
         // Only shortly lock the data to get the dom out
-        let dom: Dom<T> = {
+        let mut dom: Dom<T> = if let Some(dom) = hot_reloaded_dom {
+            dom
+        } else {
+            let window_info = LayoutInfo {
+                window: &mut *fake_window,
+                resources: &app_state.resources,
+            };
+
             let dom_lock = app_state.data.lock().unwrap();
             #[cfg(test)]{
                 Dom::<T>::new(NodeType::Div)
@@ -128,15 +359,39 @@ impl<T: Layout> UiState<T> {
             }
         };
 
-        Ok(dom.into_ui_state())
+        // The toasts are drawn on top of the content, so they are added after the user DOM
+        append_toast_layer(&mut dom, &app_state.toasts);
+
+        fake_window.profiler.record(FramePhase::Layout, layout_start);
+        fake_window.profiler.set_node_count(dom.len());
+
+        fake_window.tag_ids.begin_frame();
+
+        let into_ui_state_start = FrameProfiler::start();
+        let ui_state = dom.try_into_ui_state(&mut fake_window.tag_ids);
+        fake_window.profiler.record(FramePhase::IntoUiState, into_ui_state_start);
+
+        match ui_state {
+            Ok(ui_state) => {
+                fake_window.state.dom_error = None;
+                Ok(ui_state)
+            },
+            Err(e) => {
+                // Only report the error once, not on every frame
+                if fake_window.state.dom_error != Some(e) {
+                    report(AzulError::InvalidDom(e));
+                }
+                fake_window.state.dom_error = Some(e);
+                Err(InvalidDom(e))
+            },
+        }
     }
 
-    pub(crate) fn create_tags_for_hover_nodes(&mut self, hover_nodes: &BTreeMap<NodeId, HoverGroup>) {
-        use dom::new_tag_id;
+    pub(crate) fn create_tags_for_hover_nodes(&mut self, hover_nodes: &BTreeMap<NodeId, HoverGroup>, tag_ids: &mut TagIdGenerator) {
         for (hover_node_id, hover_group) in hover_nodes {
             let hover_tag = match self.node_ids_to_tag_ids.get(hover_node_id) {
                 Some(tag_id) => *tag_id,
-                None => new_tag_id(),
+                None => tag_ids.new_tag_id(),
             };
 
             self.node_ids_to_tag_ids.insert(*hover_node_id, hover_tag);
@@ -144,4 +399,27 @@ impl<T: Layout> UiState<T> {
             self.tag_ids_to_hover_active_states.insert(hover_tag, (*hover_node_id, *hover_group));
         }
     }
+
+    /// Replaces the tags of this frame with the tags of a cached display list, so that the
+    /// hit-test results of the cached display list resolve to the nodes of this frame.
+    ///
+    /// `cached_tags` has to contain a tag for every tagged node of this frame, which
+    /// is the case if the `DisplayListCacheKey` of both frames is equal.
+    pub(crate) fn reuse_tags(&mut self, cached_tags: &BTreeMap<NodeId, TagId>) {
+
+        let tag_mapping: BTreeMap<TagId, TagId> = self.node_ids_to_tag_ids.iter()
+            .map(|(node_id, tag_id)| (*tag_id, cached_tags[node_id]))
+            .collect();
+
+        fn remap<V>(map: &mut BTreeMap<TagId, V>, tag_mapping: &BTreeMap<TagId, TagId>) {
+            let old_map = mem::replace(map, BTreeMap::new());
+            map.extend(old_map.into_iter().map(|(tag_id, v)| (tag_mapping[&tag_id], v)));
+        }
+
+        remap(&mut self.tag_ids_to_hover_active_states, &tag_mapping);
+        remap(&mut self.tab_index_tags, &tag_mapping);
+        remap(&mut self.draggable_tags, &tag_mapping);
+        remap(&mut self.tag_ids_to_node_ids, &tag_mapping);
+        self.node_ids_to_tag_ids = cached_tags.clone();
+    }
 }
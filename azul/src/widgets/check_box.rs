@@ -0,0 +1,238 @@
+//! Check box with a label, bound to a `CheckBoxState` in the application data
+//!
+//! ```rust,ignore
+//! CheckBox::new("Remember me")
+//!     .on_toggle(Callback(remember_me_toggled))
+//!     .bind(info.window, &self.remember_me, &self)
+//!     .dom(&self.remember_me)
+//! ```
+//!
+//! The check box is toggled by clicking it or by pressing Space or Enter while it is
+//! focused. The DOM has the following classes, styled by the native styles:
+//!
+//! - `azul-checkbox`: the focusable container, additionally `azul-checkbox-checked` if
+//!   the box is checked and `azul-checkbox-disabled` if it is disabled
+//! - `azul-checkbox-box`: the box, contains the `azul-checkbox-mark`
+//! - `azul-checkbox-label`: the label next to the box
+
+use {
+    traits::Layout,
+    dom::{
+        Dom, Callback, EventFilter, FocusEventFilter, HoverEventFilter,
+        UpdateScreen, Redraw, DontRedraw, TabIndex,
+    },
+    window::{FakeWindow, CallbackInfo},
+    prelude::VirtualKeyCode,
+    default_callbacks::{StackCheckedPointer, DefaultCallback, DefaultCallbackId},
+    app_state::AppStateNoData,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckBox<T: Layout> {
+    label: String,
+    disabled: bool,
+    on_toggle: Option<Callback<T>>,
+    callbacks: Option<ToggleCallbacks>,
+}
+
+/// IDs of the default callbacks of a `CheckBox` or `ToggleSwitch`
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub(crate) struct ToggleCallbacks {
+    click: DefaultCallbackId,
+    key_down: DefaultCallbackId,
+}
+
+/// State of a `CheckBox` or `ToggleSwitch`
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct CheckBoxState {
+    pub checked: bool,
+    toggled: bool,
+}
+
+impl CheckBoxState {
+
+    pub fn new(checked: bool) -> Self {
+        Self { checked, toggled: false }
+    }
+
+    /// Whether the last event that the widget handled toggled it. Use this in the
+    /// `on_toggle` callback, which is also called for clicks / keys that didn't toggle it.
+    pub fn was_toggled(&self) -> bool {
+        self.toggled
+    }
+
+    pub fn toggle(&mut self) {
+        self.checked = !self.checked;
+    }
+
+    fn on_click_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_click, app_state_no_data, window_event) }
+    }
+
+    fn on_virtual_key_down_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_virtual_key_down, app_state_no_data, window_event) }
+    }
+
+    pub fn on_click<T: Layout>(&mut self, _app_state_no_data: AppStateNoData<T>, _event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.handle_click()
+    }
+
+    pub fn on_virtual_key_down<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        let key = app_state_no_data.windows[event.window_id].get_keyboard_state().latest_virtual_keycode;
+        self.handle_key(key)
+    }
+
+    fn handle_click(&mut self) -> UpdateScreen {
+        self.toggle();
+        self.toggled = true;
+        Redraw
+    }
+
+    fn handle_key(&mut self, key: Option<VirtualKeyCode>) -> UpdateScreen {
+        if key.map(is_activation_key).unwrap_or(false) {
+            self.handle_click()
+        } else {
+            self.toggled = false;
+            DontRedraw
+        }
+    }
+}
+
+/// Whether the key activates a focused check box, toggle switch or radio button
+pub(crate) fn is_activation_key(key: VirtualKeyCode) -> bool {
+    key == VirtualKeyCode::Space || key == VirtualKeyCode::Return
+}
+
+impl ToggleCallbacks {
+
+    pub(crate) fn register<T: Layout>(window: &mut FakeWindow<T>, field: &CheckBoxState, data: &T) -> Option<Self> {
+        let ptr = StackCheckedPointer::new(data, field)?;
+        Some(ToggleCallbacks {
+            click: window.add_callback(ptr, DefaultCallback(CheckBoxState::on_click_private)),
+            key_down: window.add_callback(ptr, DefaultCallback(CheckBoxState::on_virtual_key_down_private)),
+        })
+    }
+
+    /// Makes the `dom` focusable and attaches the default callbacks and the `on_toggle` callback to it
+    pub(crate) fn attach<T: Layout>(callbacks: Option<Self>, on_toggle: Option<Callback<T>>, dom: &mut Dom<T>) {
+        dom.add_tab_index(TabIndex::Auto);
+
+        if let Some(callbacks) = callbacks {
+            dom.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseUp), callbacks.click);
+            dom.add_default_callback_id(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), callbacks.key_down);
+        }
+
+        if let Some(on_toggle) = on_toggle {
+            dom.add_callback(EventFilter::Hover(HoverEventFilter::LeftMouseUp), on_toggle);
+            dom.add_callback(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), on_toggle);
+        }
+    }
+}
+
+impl<T: Layout> CheckBox<T> {
+
+    pub fn new<S: Into<String>>(label: S) -> Self {
+        CheckBox {
+            label: label.into(),
+            disabled: false,
+            on_toggle: None,
+            callbacks: None,
+        }
+    }
+
+    /// A disabled check box can't be focused or toggled and has the `azul-checkbox-disabled` class
+    pub fn with_disabled(self, disabled: bool) -> Self {
+        Self { disabled, .. self }
+    }
+
+    /// Called after the check box handled a click or a key press - check
+    /// `CheckBoxState::was_toggled()`, the callback is attached to the events
+    /// and is also called for key presses that don't toggle the check box.
+    pub fn on_toggle(self, callback: Callback<T>) -> Self {
+        Self { on_toggle: Some(callback), .. self }
+    }
+
+    pub fn bind(self, window: &mut FakeWindow<T>, field: &CheckBoxState, data: &T) -> Self {
+        Self {
+            callbacks: ToggleCallbacks::register(window, field, data),
+            .. self
+        }
+    }
+
+    pub fn dom(&self, field: &CheckBoxState) -> Dom<T> {
+
+        let mut container = Dom::div().with_class("azul-checkbox");
+
+        if field.checked {
+            container.add_class("azul-checkbox-checked");
+        }
+
+        if self.disabled {
+            container.add_class("azul-checkbox-disabled");
+        } else {
+            ToggleCallbacks::attach(self.callbacks, self.on_toggle, &mut container);
+        }
+
+        container
+            .with_child(Dom::div().with_class("azul-checkbox-box")
+                .with_child(Dom::div().with_class("azul-checkbox-mark")))
+            .with_child(Dom::label(self.label.clone()).with_class("azul-checkbox-label"))
+    }
+}
+
+#[cfg(test)]
+fn on_toggle_test<T: Layout>(_: &mut ::app_state::AppState<T>, _: &mut CallbackInfo<T>) -> UpdateScreen {
+    Redraw
+}
+
+#[test]
+fn test_check_box_mouse_and_keyboard() {
+
+    let mut state = CheckBoxState::new(false);
+
+    assert_eq!(state.handle_click(), Redraw);
+    assert!(state.checked && state.was_toggled());
+    assert_eq!(state.handle_click(), Redraw);
+    assert!(!state.checked && state.was_toggled());
+
+    assert_eq!(state.handle_key(Some(VirtualKeyCode::Space)), Redraw);
+    assert!(state.checked && state.was_toggled());
+    assert_eq!(state.handle_key(Some(VirtualKeyCode::Return)), Redraw);
+    assert!(!state.checked);
+
+    // Other keys don't toggle it, but reset `was_toggled`
+    assert_eq!(state.handle_key(Some(VirtualKeyCode::A)), DontRedraw);
+    assert_eq!(state.handle_key(None), DontRedraw);
+    assert!(!state.checked && !state.was_toggled());
+}
+
+#[test]
+fn test_check_box_dom_and_disabled_state() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let checked = CheckBoxState::new(true);
+    let check_box = CheckBox::<TestLayout>::new("Remember me").on_toggle(Callback(on_toggle_test));
+
+    let dom = check_box.dom(&checked);
+    let root = dom.get_node_data(dom.get_root()).unwrap();
+    assert!(root.classes.contains(&"azul-checkbox".to_string()));
+    assert!(root.classes.contains(&"azul-checkbox-checked".to_string()));
+    assert_eq!(root.tab_index, Some(TabIndex::Auto));
+    assert_eq!(root.callbacks.len(), 2);
+    assert_eq!(dom.get_node_data(::id_tree::NodeId::new(3)).and_then(|n| n.get_label_text()), Some("Remember me"));
+
+    // Disabled: not focusable, no callbacks, but still shows whether it's checked
+    let dom = check_box.with_disabled(true).dom(&checked);
+    let root = dom.get_node_data(dom.get_root()).unwrap();
+    assert!(root.classes.contains(&"azul-checkbox-disabled".to_string()));
+    assert!(root.classes.contains(&"azul-checkbox-checked".to_string()));
+    assert_eq!(root.tab_index, None);
+    assert!(root.callbacks.is_empty() && root.default_callback_ids.is_empty());
+}
@@ -0,0 +1,111 @@
+//! Collapsible section with a header that shows / hides its content,
+//! bound to a `CheckBoxState` (checked = open) in the application data
+//!
+//! ```rust,ignore
+//! Collapsible::new("Advanced")
+//!     .bind(info.window, &self.advanced_open, &self)
+//!     .dom(&self.advanced_open, || advanced_settings(&self))
+//! ```
+//!
+//! The section is opened / closed by clicking the header or by pressing Space or Enter
+//! while the header is focused. The content is only built while the section is open.
+//! The DOM has the following classes:
+//!
+//! - `azul-collapsible`: the container, additionally `azul-collapsible-open` if it is open
+//! - `azul-collapsible-header`: the focusable header, contains the `azul-collapsible-chevron`
+//!   and the `azul-collapsible-title` label
+//! - `azul-collapsible-content`: contains the content, only exists while the section is open
+
+use {
+    traits::Layout,
+    dom::{Dom, Callback},
+    window::FakeWindow,
+    widgets::check_box::{CheckBoxState, ToggleCallbacks},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collapsible<T: Layout> {
+    title: String,
+    on_toggle: Option<Callback<T>>,
+    callbacks: Option<ToggleCallbacks>,
+}
+
+impl<T: Layout> Collapsible<T> {
+
+    pub fn new<S: Into<String>>(title: S) -> Self {
+        Collapsible {
+            title: title.into(),
+            on_toggle: None,
+            callbacks: None,
+        }
+    }
+
+    /// Called after the header handled a click or a key press - check
+    /// `CheckBoxState::was_toggled()`, see `CheckBox::on_toggle`
+    pub fn on_toggle(self, callback: Callback<T>) -> Self {
+        Self { on_toggle: Some(callback), .. self }
+    }
+
+    pub fn bind(self, window: &mut FakeWindow<T>, field: &CheckBoxState, data: &T) -> Self {
+        Self {
+            callbacks: ToggleCallbacks::register(window, field, data),
+            .. self
+        }
+    }
+
+    /// Builds the section, `content` is only called if the section is open
+    pub fn dom<F: FnOnce() -> Dom<T>>(&self, field: &CheckBoxState, content: F) -> Dom<T> {
+
+        let mut container = Dom::div().with_class("azul-collapsible");
+
+        // The class of the container changes when the section is toggled, the key keeps
+        // the focus on the header in the next frame (see `TabView`)
+        let mut header = Dom::div()
+            .with_class("azul-collapsible-header")
+            .with_key(field as *const CheckBoxState as u64)
+            .with_child(Dom::div().with_class("azul-collapsible-chevron"))
+            .with_child(Dom::label(self.title.clone()).with_class("azul-collapsible-title"));
+
+        ToggleCallbacks::attach(self.callbacks, self.on_toggle, &mut header);
+        container.add_child(header);
+
+        if field.checked {
+            container.add_class("azul-collapsible-open");
+            container.add_child(Dom::div().with_class("azul-collapsible-content").with_child(content()));
+        }
+
+        container
+    }
+}
+
+#[test]
+fn test_collapsible_builds_content_only_when_open() {
+
+    use std::cell::Cell;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let built = Cell::new(0);
+    let content = || {
+        built.set(built.get() + 1);
+        Dom::label("Content")
+    };
+
+    let section = Collapsible::<TestLayout>::new("Advanced");
+
+    let closed = section.dom(&CheckBoxState::new(false), &content);
+    assert_eq!(built.get(), 0);
+    assert_eq!(closed.find_all_by_class("azul-collapsible-open").count(), 0);
+    assert_eq!(closed.find_all_by_class("azul-collapsible-content").count(), 0);
+
+    let open = section.dom(&CheckBoxState::new(true), &content);
+    assert_eq!(built.get(), 1);
+    assert_eq!(open.find_all_by_class("azul-collapsible-open").next(), Some(open.get_root()));
+    assert_eq!(open.find_all_by_class("azul-collapsible-content").count(), 1);
+}
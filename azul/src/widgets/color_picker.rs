@@ -0,0 +1,802 @@
+//! Color picker with a saturation / value area, hue and alpha strips and text fields,
+//! bound to a `ColorPickerState` in the application data
+//!
+//! ```rust,ignore
+//! ColorPicker::new()
+//!     .on_change(Callback(brush_color_changed))
+//!     .bind(info.window, &self.brush_color, &self)
+//!     .dom(&self.brush_color)
+//! ```
+//!
+//! The color is changed by dragging in the saturation / value area or on the hue and alpha
+//! strips, or by typing into the R, G, B and hex fields (`TextInput`s that are driven by the
+//! color picker). Invalid input is highlighted and doesn't change the color. The color is
+//! stored as HSV, so the hue doesn't get lost while the color is gray. The DOM has the classes:
+//!
+//! - `azul-color-picker`: the container
+//! - `azul-color-picker-sv`: the saturation / value area, its `background-color` is the
+//!   current hue (dynamic property `__azul-color-picker-hue`). Contains the
+//!   `azul-color-picker-sv-white` and `azul-color-picker-sv-black` gradients.
+//! - `azul-color-picker-hue` / `azul-color-picker-alpha`: the strips, the `background` of the
+//!   alpha strip is a gradient to the current color (`__azul-color-picker-alpha-gradient`)
+//! - `azul-color-picker-marker`: the selection markers, positioned with `azul-color-picker-spacer`s
+//!   that are sized with the dynamic `flex-grow` property `__azul-color-picker-grow`
+//! - `azul-color-picker-preview`: shows the current color (`__azul-color-picker-preview`)
+//! - `azul-color-picker-fields`: contains one `azul-color-picker-field` (label and text input)
+//!   per text field, additionally `azul-color-picker-field-invalid` if the input is invalid
+
+use std::time::{Duration, Instant};
+use webrender::api::LayoutRect;
+use azul_css::{
+    CssProperty, ColorU, LayoutFlexGrow, StyleBackgroundColor, StyleBackground,
+    LinearGradient, GradientStopPre, Direction, DirectionCorner, ExtendMode,
+};
+use {
+    traits::Layout,
+    dom::{
+        Dom, Callback, EventFilter, FocusEventFilter, HoverEventFilter, WindowEventFilter,
+        UpdateScreen, Redraw, DontRedraw,
+    },
+    window::{FakeWindow, CallbackInfo},
+    default_callbacks::{StackCheckedPointer, DefaultCallback, DefaultCallbackId},
+    app_state::AppStateNoData,
+    widgets::text_input::{TextInput, TextInputState, TextInputEvent},
+};
+
+/// ID of the dynamic CSS property that sizes the spacers around the markers
+pub const GROW_CSS_ID: &str = "__azul-color-picker-grow";
+
+/// ID of the dynamic `background-color` of the saturation / value area
+pub const HUE_CSS_ID: &str = "__azul-color-picker-hue";
+
+/// ID of the dynamic `background` of the alpha strip
+pub const ALPHA_GRADIENT_CSS_ID: &str = "__azul-color-picker-alpha-gradient";
+
+/// ID of the dynamic `background-color` of the preview swatch
+pub const PREVIEW_CSS_ID: &str = "__azul-color-picker-preview";
+
+/// While dragging, `on_change` is notified at most once per frame (at 60 FPS)
+const CHANGE_INTERVAL_MS: u64 = 16;
+
+/// Indices of the text fields, in the order of the DOM
+const RED_FIELD: usize = 0;
+const GREEN_FIELD: usize = 1;
+const BLUE_FIELD: usize = 2;
+const HEX_FIELD: usize = 3;
+
+const FIELD_LABELS: [&str; 4] = ["R", "G", "B", "#"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorPicker<T: Layout> {
+    on_change: Option<Callback<T>>,
+    callbacks: Option<ColorPickerCallbacks>,
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+struct ColorPickerCallbacks {
+    press_sv: DefaultCallbackId,
+    press_hue: DefaultCallbackId,
+    press_alpha: DefaultCallbackId,
+    drag: DefaultCallbackId,
+    release: DefaultCallbackId,
+    field_text_input: DefaultCallbackId,
+    field_key_down: DefaultCallbackId,
+    field_mouse_down: DefaultCallbackId,
+    field_mouse_over: DefaultCallbackId,
+    field_mouse_up: DefaultCallbackId,
+}
+
+/// The part of the color picker that is dragged
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum DragArea {
+    SaturationValue,
+    Hue,
+    Alpha,
+}
+
+/// Color of a `ColorPicker` (as HSV) and the state of its text fields
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorPickerState {
+    /// Hue in degrees, from 0.0 to 360.0
+    pub hue: f32,
+    /// Saturation, from 0.0 to 1.0
+    pub saturation: f32,
+    /// Value (brightness), from 0.0 to 1.0
+    pub value: f32,
+    /// Alpha, from 0.0 (transparent) to 1.0
+    pub alpha: f32,
+    pub red_field: TextInputState,
+    pub green_field: TextInputState,
+    pub blue_field: TextInputState,
+    pub hex_field: TextInputState,
+    /// The text field whose input isn't a valid color
+    invalid_field: Option<usize>,
+    dragging: Option<(DragArea, LayoutRect)>,
+    /// The color changed, but `on_change` wasn't notified yet (because of the throttling)
+    change_pending: bool,
+    color_changed: bool,
+    last_change: Option<Instant>,
+}
+
+impl ColorPickerState {
+
+    pub fn new(color: ColorU) -> Self {
+        let mut state = Self {
+            hue: 0.0,
+            saturation: 0.0,
+            value: 0.0,
+            alpha: 1.0,
+            red_field: TextInputState::new("").with_max_length(3),
+            green_field: TextInputState::new("").with_max_length(3),
+            blue_field: TextInputState::new("").with_max_length(3),
+            hex_field: TextInputState::new("").with_max_length(9),
+            invalid_field: None,
+            dragging: None,
+            change_pending: false,
+            color_changed: false,
+            last_change: None,
+        };
+        state.set_color(color);
+        state
+    }
+
+    /// The current color, in RGBA
+    pub fn color(&self) -> ColorU {
+        let (r, g, b) = hsv_to_rgb(self.hue, self.saturation, self.value);
+        ColorU { r, g, b, a: unit_to_u8(self.alpha) }
+    }
+
+    /// Sets the color and updates the text fields. The hue (and saturation) are kept
+    /// if the color is gray (or black), since they can't be derived from the color.
+    pub fn set_color(&mut self, color: ColorU) {
+        self.set_color_except_field(color, None);
+    }
+
+    /// Whether the last event that the color picker handled changed the color. Use this
+    /// in the `on_change` callback, which is also called for events that didn't change
+    /// the color. While dragging, this is true at most once per frame - the final color
+    /// is always reported when the mouse button is released.
+    pub fn color_changed(&self) -> bool {
+        self.color_changed
+    }
+
+    /// Whether the input of the text field (0 to 3: R, G, B, hex) is not a valid color
+    pub fn is_field_invalid(&self, field: usize) -> bool {
+        self.invalid_field == Some(field)
+    }
+
+    fn set_color_except_field(&mut self, color: ColorU, except: Option<usize>) {
+        let (hue, saturation, value) = rgb_to_hsv(color.r, color.g, color.b);
+        if saturation > 0.0 && value > 0.0 {
+            self.hue = hue;
+        }
+        if value > 0.0 {
+            self.saturation = saturation;
+        }
+        self.value = value;
+        self.alpha = color.a as f32 / 255.0;
+        self.invalid_field = None;
+        self.update_fields(except);
+    }
+
+    /// Writes the current color into the text fields, except for the field that is being edited
+    fn update_fields(&mut self, except: Option<usize>) {
+        let color = self.color();
+        let texts = [color.r.to_string(), color.g.to_string(), color.b.to_string(), format_hex_color(color)];
+        for (index, text) in texts.iter().enumerate() {
+            if Some(index) == except {
+                continue;
+            }
+            let field = self.field_mut(index);
+            field.text = text.clone();
+            field.cursor = field.text.len();
+            field.selection_anchor = None;
+            field.scroll_x = 0.0;
+        }
+    }
+
+    fn field_mut(&mut self, index: usize) -> &mut TextInputState {
+        match index {
+            RED_FIELD => &mut self.red_field,
+            GREEN_FIELD => &mut self.green_field,
+            BLUE_FIELD => &mut self.blue_field,
+            _ => &mut self.hex_field,
+        }
+    }
+
+    fn field(&self, index: usize) -> &TextInputState {
+        match index {
+            RED_FIELD => &self.red_field,
+            GREEN_FIELD => &self.green_field,
+            BLUE_FIELD => &self.blue_field,
+            _ => &self.hex_field,
+        }
+    }
+
+    /// Starts dragging in the `area`, the position is relative to the area (0.0 to 1.0)
+    fn handle_press(&mut self, area: DragArea, rect: LayoutRect, x: f32, y: f32, now: Instant) -> UpdateScreen {
+        self.dragging = Some((area, rect));
+        self.handle_drag(x, y, now)
+    }
+
+    fn handle_drag(&mut self, x: f32, y: f32, now: Instant) -> UpdateScreen {
+
+        let area = match self.dragging {
+            Some((area, _)) => area,
+            None => return DontRedraw,
+        };
+
+        let (x, y) = (x.max(0.0).min(1.0), y.max(0.0).min(1.0));
+        let old = (self.hue, self.saturation, self.value, self.alpha);
+
+        match area {
+            DragArea::SaturationValue => { self.saturation = x; self.value = 1.0 - y; },
+            DragArea::Hue => self.hue = x * 360.0,
+            DragArea::Alpha => self.alpha = x,
+        }
+
+        if (self.hue, self.saturation, self.value, self.alpha) == old {
+            return DontRedraw;
+        }
+
+        self.invalid_field = None;
+        self.update_fields(None);
+        self.change_pending = true;
+        self.notify_change(now, false);
+        Redraw
+    }
+
+    fn handle_release(&mut self, now: Instant) -> UpdateScreen {
+        if self.dragging.take().is_none() {
+            return DontRedraw;
+        }
+        self.notify_change(now, true);
+        Redraw
+    }
+
+    /// Applies the text of the edited field to the color, if it is valid
+    fn handle_field_edit(&mut self, index: usize, now: Instant) {
+
+        let text = self.field(index).text.trim().to_string();
+        let mut color = self.color();
+
+        let parsed = match index {
+            RED_FIELD => text.parse::<u8>().ok().map(|r| ColorU { r, .. color }),
+            GREEN_FIELD => text.parse::<u8>().ok().map(|g| ColorU { g, .. color }),
+            BLUE_FIELD => text.parse::<u8>().ok().map(|b| ColorU { b, .. color }),
+            _ => parse_hex_color(&text),
+        };
+
+        match parsed {
+            Some(parsed) => color = parsed,
+            None => {
+                self.invalid_field = Some(index);
+                return;
+            },
+        }
+
+        self.set_color_except_field(color, Some(index));
+        self.change_pending = true;
+        self.notify_change(now, true);
+    }
+
+    /// Sets `color_changed` if a change is pending and `on_change` wasn't notified in
+    /// this frame (or if `force` is set, for the last change of a drag and text input)
+    fn notify_change(&mut self, now: Instant, force: bool) {
+        if !self.change_pending {
+            return;
+        }
+        let is_due = force || self.last_change.map(|last| now - last >= Duration::from_millis(CHANGE_INTERVAL_MS)).unwrap_or(true);
+        if is_due {
+            self.change_pending = false;
+            self.color_changed = true;
+            self.last_change = Some(now);
+        }
+    }
+
+    fn on_press_sv_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_press_sv, app_state_no_data, window_event) }
+    }
+
+    fn on_press_hue_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_press_hue, app_state_no_data, window_event) }
+    }
+
+    fn on_press_alpha_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_press_alpha, app_state_no_data, window_event) }
+    }
+
+    fn on_drag_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_drag, app_state_no_data, window_event) }
+    }
+
+    fn on_release_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_release, app_state_no_data, window_event) }
+    }
+
+    fn on_field_text_input_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_field_text_input, app_state_no_data, window_event) }
+    }
+
+    fn on_field_key_down_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_field_key_down, app_state_no_data, window_event) }
+    }
+
+    fn on_field_mouse_down_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_field_mouse_down, app_state_no_data, window_event) }
+    }
+
+    fn on_field_mouse_over_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_field_mouse_over, app_state_no_data, window_event) }
+    }
+
+    fn on_field_mouse_up_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_field_mouse_up, app_state_no_data, window_event) }
+    }
+
+    /// Called on the `azul-color-picker-sv` area
+    pub fn on_press_sv<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.on_press(DragArea::SaturationValue, &app_state_no_data, event)
+    }
+
+    /// Called on the `azul-color-picker-hue` strip
+    pub fn on_press_hue<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.on_press(DragArea::Hue, &app_state_no_data, event)
+    }
+
+    /// Called on the `azul-color-picker-alpha` strip
+    pub fn on_press_alpha<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.on_press(DragArea::Alpha, &app_state_no_data, event)
+    }
+
+    fn on_press<T: Layout>(&mut self, area: DragArea, app_state_no_data: &AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.color_changed = false;
+        let rect = app_state_no_data.windows[event.window_id].get_layout_snapshot().get_rect(event.hit_dom_node)?;
+        let (x, y) = event.cursor_relative_to_item?;
+        self.handle_press(area, rect, x / rect.size.width.max(1.0), y / rect.size.height.max(1.0), Instant::now())
+    }
+
+    /// Called on the container for every mouse movement, while an area is dragged
+    pub fn on_drag<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.color_changed = false;
+        let rect = match self.dragging {
+            Some((_, rect)) => rect,
+            None => return DontRedraw,
+        };
+        // The cursor can leave the area while dragging, so the rectangle from the press is used
+        let cursor = app_state_no_data.windows[event.window_id].get_mouse_state().cursor_pos?;
+        let x = (cursor.x as f32 - rect.origin.x) / rect.size.width.max(1.0);
+        let y = (cursor.y as f32 - rect.origin.y) / rect.size.height.max(1.0);
+        self.handle_drag(x, y, Instant::now())
+    }
+
+    pub fn on_release<T: Layout>(&mut self, _app_state_no_data: AppStateNoData<T>, _event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.color_changed = false;
+        self.handle_release(Instant::now())
+    }
+
+    /// Called on a text field, forwards the event to the `TextInputState` of the field
+    pub fn on_field_text_input<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.color_changed = false;
+        let index = field_index(event)?;
+        let update_screen = self.field_mut(index).on_text_input(app_state_no_data, event);
+        self.after_field_event(index);
+        update_screen
+    }
+
+    pub fn on_field_key_down<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.color_changed = false;
+        let index = field_index(event)?;
+        let update_screen = self.field_mut(index).on_virtual_key_down(app_state_no_data, event);
+        self.after_field_event(index);
+        update_screen
+    }
+
+    pub fn on_field_mouse_down<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        let index = field_index(event)?;
+        self.field_mut(index).on_mouse_down(app_state_no_data, event)
+    }
+
+    pub fn on_field_mouse_over<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        let index = field_index(event)?;
+        self.field_mut(index).on_mouse_over(app_state_no_data, event)
+    }
+
+    pub fn on_field_mouse_up<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        let index = field_index(event)?;
+        self.field_mut(index).on_mouse_up(app_state_no_data, event)
+    }
+
+    fn after_field_event(&mut self, index: usize) {
+        if self.field(index).last_event() == Some(TextInputEvent::Changed) {
+            self.handle_field_edit(index, Instant::now());
+        }
+    }
+}
+
+/// Index of the text field that the event was targeted at: the text input
+/// is inside of an `azul-color-picker-field`, inside of the `azul-color-picker-fields`
+fn field_index<T: Layout>(event: &CallbackInfo<T>) -> Option<usize> {
+    let (index, _) = event.get_index_in_parent(event.target_parent()?)?;
+    Some(index)
+}
+
+/// Converts HSV (hue in degrees, saturation and value from 0.0 to 1.0) to RGB
+pub fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> (u8, u8, u8) {
+
+    let saturation = saturation.max(0.0).min(1.0);
+    let value = value.max(0.0).min(1.0);
+    let hue = hue % 360.0;
+    let hue = if hue < 0.0 { hue + 360.0 } else { hue };
+
+    let chroma = value * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    let m = value - chroma;
+    (unit_to_u8(r + m), unit_to_u8(g + m), unit_to_u8(b + m))
+}
+
+/// Converts RGB to HSV (hue in degrees, saturation and value from 0.0 to 1.0).
+/// The hue of gray colors is 0.0.
+pub fn rgb_to_hsv(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * (((g - b) / delta) % 6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+
+    let hue = if hue < 0.0 { hue + 360.0 } else { hue };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+    (hue, saturation, max)
+}
+
+/// Parses a hex color with 3 (`#rgb`), 4 (`#rgba`), 6 (`#rrggbb`) or 8 (`#rrggbbaa`)
+/// digits, the `#` is optional. Colors without alpha are opaque.
+pub fn parse_hex_color(input: &str) -> Option<ColorU> {
+
+    let input = input.trim();
+    let digits = if input.starts_with('#') { &input[1..] } else { input };
+
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let digit = |index: usize| u8::from_str_radix(&digits[index..index + 1], 16).ok().map(|d| d * 17);
+    let byte = |index: usize| u8::from_str_radix(&digits[index * 2..index * 2 + 2], 16).ok();
+
+    match digits.len() {
+        3 => Some(ColorU { r: digit(0)?, g: digit(1)?, b: digit(2)?, a: 255 }),
+        4 => Some(ColorU { r: digit(0)?, g: digit(1)?, b: digit(2)?, a: digit(3)? }),
+        6 => Some(ColorU { r: byte(0)?, g: byte(1)?, b: byte(2)?, a: 255 }),
+        8 => Some(ColorU { r: byte(0)?, g: byte(1)?, b: byte(2)?, a: byte(3)? }),
+        _ => None,
+    }
+}
+
+/// Formats the color as `#rrggbb`, or as `#rrggbbaa` if it isn't opaque
+pub fn format_hex_color(color: ColorU) -> String {
+    if color.a == 255 {
+        format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", color.r, color.g, color.b, color.a)
+    }
+}
+
+fn unit_to_u8(value: f32) -> u8 {
+    (value.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+impl<T: Layout> ColorPicker<T> {
+
+    pub fn new() -> Self {
+        ColorPicker {
+            on_change: None,
+            callbacks: None,
+        }
+    }
+
+    /// Called after the color picker handled a mouse or keyboard event - check
+    /// `ColorPickerState::color_changed()` and read `ColorPickerState::color()`. The
+    /// callback is attached to the events and is also called for events that don't
+    /// change the color.
+    pub fn on_change(self, callback: Callback<T>) -> Self {
+        Self { on_change: Some(callback), .. self }
+    }
+
+    pub fn bind(self, window: &mut FakeWindow<T>, field: &ColorPickerState, data: &T) -> Self {
+        let ptr = StackCheckedPointer::new(data, field);
+        let callbacks = ptr.and_then(|ptr| {
+            Some(ColorPickerCallbacks {
+                press_sv: window.add_callback(ptr, DefaultCallback(ColorPickerState::on_press_sv_private)),
+                press_hue: window.add_callback(ptr, DefaultCallback(ColorPickerState::on_press_hue_private)),
+                press_alpha: window.add_callback(ptr, DefaultCallback(ColorPickerState::on_press_alpha_private)),
+                drag: window.add_callback(ptr, DefaultCallback(ColorPickerState::on_drag_private)),
+                release: window.add_callback(ptr, DefaultCallback(ColorPickerState::on_release_private)),
+                field_text_input: window.add_callback(ptr, DefaultCallback(ColorPickerState::on_field_text_input_private)),
+                field_key_down: window.add_callback(ptr, DefaultCallback(ColorPickerState::on_field_key_down_private)),
+                field_mouse_down: window.add_callback(ptr, DefaultCallback(ColorPickerState::on_field_mouse_down_private)),
+                field_mouse_over: window.add_callback(ptr, DefaultCallback(ColorPickerState::on_field_mouse_over_private)),
+                field_mouse_up: window.add_callback(ptr, DefaultCallback(ColorPickerState::on_field_mouse_up_private)),
+            })
+        });
+
+        Self {
+            callbacks,
+            .. self
+        }
+    }
+
+    pub fn dom(&self, field: &ColorPickerState) -> Dom<T> {
+
+        let color = field.color();
+        let (hue_r, hue_g, hue_b) = hsv_to_rgb(field.hue, 1.0, 1.0);
+        let opaque = ColorU { a: 255, .. color };
+        let transparent = ColorU { a: 0, .. color };
+
+        // The marker of the area is positioned with spacers in both directions
+        let sv_marker_row = Dom::div()
+            .with_class("azul-color-picker-sv-row")
+            .with_child(spacer(field.saturation))
+            .with_child(Dom::div().with_class("azul-color-picker-marker"))
+            .with_child(spacer(1.0 - field.saturation));
+
+        let sv_markers = Dom::div()
+            .with_class("azul-color-picker-sv-black")
+            .with_child(spacer(1.0 - field.value))
+            .with_child(sv_marker_row)
+            .with_child(spacer(field.value));
+
+        let mut sv = Dom::div()
+            .with_class("azul-color-picker-sv")
+            .with_css_override(HUE_CSS_ID, CssProperty::BackgroundColor(StyleBackgroundColor(ColorU { r: hue_r, g: hue_g, b: hue_b, a: 255 })))
+            .with_child(Dom::div().with_class("azul-color-picker-sv-white").with_child(sv_markers));
+
+        let mut hue = strip("azul-color-picker-hue", field.hue / 360.0);
+
+        let alpha_gradient = StyleBackground::LinearGradient(LinearGradient {
+            direction: Direction::FromTo(DirectionCorner::Left, DirectionCorner::Right),
+            extend_mode: ExtendMode::Clamp,
+            stops: vec![
+                GradientStopPre { offset: None, color: transparent },
+                GradientStopPre { offset: None, color: opaque },
+            ],
+        });
+        let mut alpha = strip("azul-color-picker-alpha", field.alpha)
+            .with_css_override(ALPHA_GRADIENT_CSS_ID, CssProperty::Background(alpha_gradient));
+
+        let mut container = Dom::div().with_class("azul-color-picker");
+
+        if let Some(callbacks) = self.callbacks {
+            sv.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseDown), callbacks.press_sv);
+            hue.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseDown), callbacks.press_hue);
+            alpha.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseDown), callbacks.press_alpha);
+            container.add_default_callback_id(EventFilter::Window(WindowEventFilter::MouseOver), callbacks.drag);
+            container.add_default_callback_id(EventFilter::Window(WindowEventFilter::LeftMouseUp), callbacks.release);
+        }
+
+        if let Some(on_change) = self.on_change {
+            sv.add_callback(EventFilter::Hover(HoverEventFilter::LeftMouseDown), on_change);
+            hue.add_callback(EventFilter::Hover(HoverEventFilter::LeftMouseDown), on_change);
+            alpha.add_callback(EventFilter::Hover(HoverEventFilter::LeftMouseDown), on_change);
+            container.add_callback(EventFilter::Window(WindowEventFilter::MouseOver), on_change);
+            container.add_callback(EventFilter::Window(WindowEventFilter::LeftMouseUp), on_change);
+        }
+
+        let preview = Dom::div()
+            .with_class("azul-color-picker-preview")
+            .with_css_override(PREVIEW_CSS_ID, CssProperty::BackgroundColor(StyleBackgroundColor(color)));
+
+        let fields = (0..FIELD_LABELS.len())
+            .map(|index| self.field_dom(field, index))
+            .collect::<Dom<T>>()
+            .with_class("azul-color-picker-fields");
+
+        let footer = Dom::div()
+            .with_class("azul-color-picker-footer")
+            .with_child(preview)
+            .with_child(fields);
+
+        container
+            .with_child(sv)
+            .with_child(hue)
+            .with_child(alpha)
+            .with_child(footer)
+    }
+
+    /// The label and `TextInput` of one text field. The text input isn't bound to its
+    /// state, the color picker forwards the events, so that it can parse the input.
+    fn field_dom(&self, field: &ColorPickerState, index: usize) -> Dom<T> {
+
+        let mut input = TextInput::<T>::new().dom(field.field(index));
+
+        if let Some(callbacks) = self.callbacks {
+            input.add_default_callback_id(EventFilter::Focus(FocusEventFilter::TextInput), callbacks.field_text_input);
+            input.add_default_callback_id(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), callbacks.field_key_down);
+            input.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseDown), callbacks.field_mouse_down);
+            input.add_default_callback_id(EventFilter::Hover(HoverEventFilter::MouseOver), callbacks.field_mouse_over);
+            input.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseUp), callbacks.field_mouse_up);
+        }
+
+        if let Some(on_change) = self.on_change {
+            input.add_callback(EventFilter::Focus(FocusEventFilter::TextInput), on_change);
+            input.add_callback(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), on_change);
+        }
+
+        let mut field_dom = Dom::div()
+            .with_class("azul-color-picker-field")
+            .with_child(Dom::label(FIELD_LABELS[index]).with_class("azul-color-picker-field-label"))
+            .with_child(input);
+
+        if field.is_field_invalid(index) {
+            field_dom.add_class("azul-color-picker-field-invalid");
+        }
+
+        field_dom
+    }
+}
+
+/// A strip with a marker at the `fraction` of its width
+fn strip<T: Layout>(class: &str, fraction: f32) -> Dom<T> {
+    Dom::div()
+        .with_class(class)
+        .with_child(spacer(fraction))
+        .with_child(Dom::div().with_class("azul-color-picker-marker"))
+        .with_child(spacer(1.0 - fraction))
+}
+
+fn spacer<T: Layout>(grow: f32) -> Dom<T> {
+    Dom::div()
+        .with_class("azul-color-picker-spacer")
+        .with_css_override(GROW_CSS_ID, CssProperty::FlexGrow(LayoutFlexGrow(grow.max(0.0).into())))
+}
+
+#[cfg(test)]
+fn test_rect() -> LayoutRect {
+    use webrender::api::{LayoutPoint, LayoutSize};
+    LayoutRect::new(LayoutPoint::new(10.0, 10.0), LayoutSize::new(200.0, 200.0))
+}
+
+#[test]
+fn test_color_picker_hsv_conversion() {
+
+    assert_eq!(hsv_to_rgb(0.0, 1.0, 1.0), (255, 0, 0));
+    assert_eq!(hsv_to_rgb(60.0, 1.0, 1.0), (255, 255, 0));
+    assert_eq!(hsv_to_rgb(120.0, 1.0, 1.0), (0, 255, 0));
+    assert_eq!(hsv_to_rgb(180.0, 1.0, 1.0), (0, 255, 255));
+    assert_eq!(hsv_to_rgb(240.0, 1.0, 1.0), (0, 0, 255));
+    assert_eq!(hsv_to_rgb(300.0, 1.0, 1.0), (255, 0, 255));
+    assert_eq!(hsv_to_rgb(0.0, 0.5, 0.5), (128, 64, 64));
+    assert_eq!(hsv_to_rgb(200.0, 0.0, 0.5), (128, 128, 128));
+    assert_eq!(hsv_to_rgb(123.0, 1.0, 0.0), (0, 0, 0));
+
+    // The hue wraps around, saturation and value are clamped
+    assert_eq!(hsv_to_rgb(360.0, 1.0, 1.0), (255, 0, 0));
+    assert_eq!(hsv_to_rgb(-120.0, 1.0, 1.0), (0, 0, 255));
+    assert_eq!(hsv_to_rgb(0.0, 2.0, 1.5), (255, 0, 0));
+
+    assert_eq!(rgb_to_hsv(255, 0, 0), (0.0, 1.0, 1.0));
+    assert_eq!(rgb_to_hsv(0, 255, 0), (120.0, 1.0, 1.0));
+    assert_eq!(rgb_to_hsv(0, 0, 255), (240.0, 1.0, 1.0));
+    assert_eq!(rgb_to_hsv(255, 0, 255), (300.0, 1.0, 1.0));
+    assert_eq!(rgb_to_hsv(0, 0, 0), (0.0, 0.0, 0.0));
+    assert_eq!(rgb_to_hsv(255, 255, 255), (0.0, 0.0, 1.0));
+
+    // RGB -> HSV -> RGB is lossless
+    for r in (0..256).step_by(15) {
+        for g in (0..256).step_by(15) {
+            for b in (0..256).step_by(15) {
+                let (r, g, b) = (r as u8, g as u8, b as u8);
+                let (h, s, v) = rgb_to_hsv(r, g, b);
+                assert_eq!(hsv_to_rgb(h, s, v), (r, g, b));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_color_picker_hex_colors() {
+
+    let color = |r, g, b, a| Some(ColorU { r, g, b, a });
+
+    assert_eq!(parse_hex_color("#f00"), color(255, 0, 0, 255));
+    assert_eq!(parse_hex_color("f00a"), color(255, 0, 0, 170));
+    assert_eq!(parse_hex_color("#FF8000"), color(255, 128, 0, 255));
+    assert_eq!(parse_hex_color("#ff800080"), color(255, 128, 0, 128));
+    assert_eq!(parse_hex_color("  #0a0B0c "), color(10, 11, 12, 255));
+
+    for invalid in &["", "#", "#f", "#ff", "#fffff", "#fffffff", "#fffffffff", "#ggg", "##fff", "+fff", "#ff 00 00"] {
+        assert_eq!(parse_hex_color(invalid), None, "{:?}", invalid);
+    }
+
+    assert_eq!(format_hex_color(ColorU { r: 255, g: 128, b: 0, a: 255 }), "#ff8000");
+    assert_eq!(format_hex_color(ColorU { r: 255, g: 128, b: 0, a: 128 }), "#ff800080");
+    assert_eq!(format_hex_color(ColorU { r: 0, g: 0, b: 0, a: 0 }), "#00000000");
+}
+
+#[test]
+fn test_color_picker_drag_updates_fields() {
+
+    let now = Instant::now();
+    let mut state = ColorPickerState::new(ColorU { r: 255, g: 0, b: 0, a: 255 });
+    assert_eq!(state.hex_field.text, "#ff0000");
+    assert_eq!(state.red_field.text, "255");
+
+    // Pressing in the middle of the area halves the saturation and value
+    assert_eq!(state.handle_press(DragArea::SaturationValue, test_rect(), 0.5, 0.5, now), Redraw);
+    assert!(state.color_changed());
+    assert_eq!(state.color(), ColorU { r: 128, g: 64, b: 64, a: 255 });
+    assert_eq!(state.hex_field.text, "#804040");
+    assert_eq!(state.green_field.text, "64");
+
+    // Within the same frame, the fields are updated, but `on_change` isn't notified
+    state.color_changed = false;
+    assert_eq!(state.handle_drag(1.5, -1.0, now + Duration::from_millis(5)), Redraw);
+    assert!(!state.color_changed());
+    assert_eq!(state.hex_field.text, "#ff0000");
+    assert_eq!(state.handle_drag(1.0, 0.0, now + Duration::from_millis(6)), DontRedraw);
+
+    // Releasing the mouse always reports the final color
+    assert_eq!(state.handle_release(now + Duration::from_millis(8)), Redraw);
+    assert!(state.color_changed());
+    assert_eq!(state.handle_drag(0.0, 0.0, now + Duration::from_millis(100)), DontRedraw);
+
+    state.handle_press(DragArea::Hue, test_rect(), 0.5, 0.0, now + Duration::from_millis(200));
+    assert_eq!(state.hex_field.text, "#00ffff");
+    state.handle_press(DragArea::Alpha, test_rect(), 0.5, 0.0, now + Duration::from_millis(300));
+    assert_eq!(state.hex_field.text, "#00ffff80");
+    assert_eq!(state.color().a, 128);
+}
+
+#[test]
+fn test_color_picker_field_input() {
+
+    let now = Instant::now();
+    let mut state = ColorPickerState::new(ColorU { r: 255, g: 0, b: 0, a: 255 });
+
+    // A valid hex color updates the other fields, but not the edited one
+    state.hex_field.text = "#00f".into();
+    state.handle_field_edit(HEX_FIELD, now);
+    assert!(state.color_changed());
+    assert_eq!(state.color(), ColorU { r: 0, g: 0, b: 255, a: 255 });
+    assert_eq!(state.hex_field.text, "#00f");
+    assert_eq!((state.red_field.text.as_str(), state.blue_field.text.as_str()), ("0", "255"));
+
+    // Invalid input is marked and doesn't change the color
+    state.red_field.text = "300".into();
+    state.handle_field_edit(RED_FIELD, now);
+    assert!(state.is_field_invalid(RED_FIELD));
+    assert_eq!(state.color(), ColorU { r: 0, g: 0, b: 255, a: 255 });
+
+    state.red_field.text = "255".into();
+    state.handle_field_edit(RED_FIELD, now);
+    assert!(!state.is_field_invalid(RED_FIELD));
+    assert_eq!(state.hex_field.text, "#ff00ff");
+
+    // Gray colors keep the hue
+    state.set_color(ColorU { r: 128, g: 128, b: 128, a: 255 });
+    assert_eq!(state.hue, 300.0);
+    state.set_color(ColorU { r: 0, g: 0, b: 0, a: 255 });
+    state.blue_field.text = "255".into();
+    state.handle_field_edit(BLUE_FIELD, now);
+    assert_eq!(state.color(), ColorU { r: 0, g: 0, b: 255, a: 255 });
+}
@@ -0,0 +1,538 @@
+//! Data table with a sticky header, resizable columns, sorting hooks, row selection
+//! and virtualized rows
+//!
+//! The application provides the rows by implementing `DataTableSource` on the struct
+//! that holds both the data and the `DataTableState`:
+//!
+//! ```rust,ignore
+//! impl DataTableSource<MyApp> for Inventory {
+//!     fn table_state(&self) -> &DataTableState { &self.table }
+//!     fn table_state_mut(&mut self) -> &mut DataTableState { &mut self.table }
+//!     fn row_count(&self) -> usize { self.items.len() }
+//!     fn render_cell(&self, row: usize, column: usize) -> Dom<MyApp> { ... }
+//!     fn sort_by_column(&mut self, column: usize, direction: SortDirection) { ... }
+//! }
+//!
+//! DataTable::new().dom(&self.inventory, &self, info.window)
+//! ```
+//!
+//! The rows are rendered in an IFrame, so only the rows that are visible are built,
+//! which keeps tables with hundreds of thousands of rows fast. Clicking a header
+//! calls `DataTableSource::sort_by_column` - the table doesn't sort the rows itself.
+//! The DOM has the following classes:
+//!
+//! - `azul-data-table`: the container
+//! - `azul-data-table-header`: the header row, contains the `azul-data-table-header-cell`s
+//!   (additionally `azul-data-table-sorted-ascending` / `-descending` on the sorted column),
+//!   which contain the `azul-data-table-header-title` and the `azul-data-table-resize-handle`
+//! - `azul-data-table-body`: the IFrame with the `azul-data-table-rows`, which contains the
+//!   `azul-data-table-row`s (additionally `azul-data-table-row-odd` / `azul-data-table-row-selected`),
+//!   which contain one `azul-data-table-cell` per column
+//!
+//! The widths of the header cells and cells are set with the dynamic `width` property
+//! `__azul-data-table-column-width`, the rows are scrolled with the dynamic `margin-top`
+//! property `__azul-data-table-scroll`.
+
+use std::{ops::Range, collections::BTreeSet};
+use azul_css::{CssProperty, LayoutWidth, LayoutMargin, PixelValue};
+use {
+    dom::{
+        Dom, NodeType, IFrameCallback, EventFilter, HoverEventFilter, WindowEventFilter,
+        UpdateScreen, Redraw, DontRedraw,
+    },
+    app_state::AppStateNoData,
+    traits::Layout,
+    default_callbacks::{StackCheckedPointer, DefaultCallback},
+    window::{LayoutInfo, HidpiAdjustedBounds, CallbackInfo, FakeWindow},
+};
+
+/// ID of the dynamic CSS property that sets the width of the cells of a column
+pub const COLUMN_WIDTH_CSS_ID: &str = "__azul-data-table-column-width";
+
+/// ID of the dynamic CSS property that scrolls the rows
+pub const SCROLL_CSS_ID: &str = "__azul-data-table-scroll";
+
+/// Provides the rows of a `DataTable`
+pub trait DataTableSource<T: Layout> {
+    /// Returns the column widths, selection, sorting and scroll position of the table
+    fn table_state(&self) -> &DataTableState;
+    /// Same as `table_state`, used by the table to update the state on user input
+    fn table_state_mut(&mut self) -> &mut DataTableState;
+    /// Returns how many rows the table has
+    fn row_count(&self) -> usize;
+    /// Returns the content of the cell, only called for the visible rows
+    fn render_cell(&self, row: usize, column: usize) -> Dom<T>;
+    /// Called when the header of the `column` is clicked, should reorder the rows
+    /// that `render_cell` returns. The selection is cleared before.
+    fn sort_by_column(&mut self, _column: usize, _direction: SortDirection) { }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DataTable {
+
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataTableColumn {
+    pub title: String,
+    /// Width of the column in pixels
+    pub width: f32,
+    /// The column can't be resized to be narrower than this
+    pub min_width: f32,
+}
+
+impl DataTableColumn {
+    pub fn new<S: Into<String>>(title: S, width: f32) -> Self {
+        Self {
+            title: title.into(),
+            width,
+            min_width: 20.0,
+        }
+    }
+
+    pub fn with_min_width(self, min_width: f32) -> Self {
+        Self { min_width, .. self }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum SelectionMode {
+    /// Clicking a row selects only this row, modifiers are ignored
+    Single,
+    /// Ctrl + click toggles a row, Shift + click selects the range from the last
+    /// clicked row, Ctrl + Shift + click adds that range to the selection
+    Multiple,
+}
+
+/// Selected rows of a `DataTable`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RowSelection {
+    pub rows: BTreeSet<usize>,
+    /// Row that Shift + click selects from (the last row that was clicked without Shift)
+    pub anchor: Option<usize>,
+}
+
+impl RowSelection {
+
+    pub fn contains(&self, row: usize) -> bool {
+        self.rows.contains(&row)
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+        self.anchor = None;
+    }
+
+    /// Updates the selection after a click on the `row` with the given modifiers
+    pub fn click(&mut self, row: usize, ctrl: bool, shift: bool, mode: SelectionMode) {
+
+        let (ctrl, shift) = match mode {
+            SelectionMode::Single => (false, false),
+            SelectionMode::Multiple => (ctrl, shift),
+        };
+
+        match (self.anchor, ctrl, shift) {
+            (Some(anchor), _, true) => {
+                let range = if anchor <= row { anchor..=row } else { row..=anchor };
+                if !ctrl {
+                    self.rows.clear();
+                }
+                self.rows.extend(range);
+            },
+            (_, true, _) => {
+                if !self.rows.remove(&row) {
+                    self.rows.insert(row);
+                }
+                self.anchor = Some(row);
+            },
+            _ => {
+                self.rows.clear();
+                self.rows.insert(row);
+                self.anchor = Some(row);
+            },
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct ColumnResize {
+    column: usize,
+    start_x: f32,
+    start_width: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataTableState {
+    pub columns: Vec<DataTableColumn>,
+    /// Height of a row in pixels, has to match the stylesheet
+    pub row_height: f32,
+    pub selection_mode: SelectionMode,
+    pub selection: RowSelection,
+    /// Column and direction that the rows are sorted by
+    pub sort: Option<(usize, SortDirection)>,
+    /// How far the rows are scrolled down, in pixels
+    pub scroll_offset: f32,
+    resize: Option<ColumnResize>,
+}
+
+impl DataTableState {
+
+    pub fn new(columns: Vec<DataTableColumn>) -> Self {
+        Self {
+            columns,
+            row_height: 20.0,
+            selection_mode: SelectionMode::Multiple,
+            selection: RowSelection::default(),
+            sort: None,
+            scroll_offset: 0.0,
+            resize: None,
+        }
+    }
+
+    pub fn with_row_height(self, row_height: f32) -> Self {
+        Self { row_height, .. self }
+    }
+
+    pub fn with_selection_mode(self, selection_mode: SelectionMode) -> Self {
+        Self { selection_mode, .. self }
+    }
+
+    /// Sorts by the `column`, ascending - or descending if the column was already sorted ascending
+    pub fn toggle_sort(&mut self, column: usize) -> SortDirection {
+        let direction = match self.sort {
+            Some((sorted, SortDirection::Ascending)) if sorted == column => SortDirection::Descending,
+            _ => SortDirection::Ascending,
+        };
+        self.sort = Some((column, direction));
+        direction
+    }
+}
+
+/// Width of a column that is resized by dragging from `start_x` to `x`
+fn resized_width(start_width: f32, start_x: f32, x: f32, min_width: f32) -> f32 {
+    (start_width + x - start_x).max(min_width)
+}
+
+/// Row at the `y` position (relative to the top of the table body)
+fn row_at(y: f32, scroll_offset: f32, row_height: f32) -> usize {
+    ((y + scroll_offset) / row_height).max(0.0).floor() as usize
+}
+
+/// Limits the scroll offset so that the last row is at the bottom of the viewport
+fn clamp_scroll_offset(scroll_offset: f32, row_count: usize, row_height: f32, viewport_height: f32) -> f32 {
+    let max_offset = (row_count as f32 * row_height - viewport_height).max(0.0);
+    scroll_offset.max(0.0).min(max_offset)
+}
+
+/// Rows that are (partially) visible in the viewport
+fn visible_row_range(scroll_offset: f32, row_height: f32, viewport_height: f32, row_count: usize) -> Range<usize> {
+    let first = row_at(0.0, scroll_offset, row_height).min(row_count);
+    let last = ((scroll_offset + viewport_height) / row_height).ceil().max(0.0) as usize;
+    first..last.min(row_count)
+}
+
+impl DataTable {
+
+    pub fn new() -> Self {
+        Self { }
+    }
+
+    pub fn dom<T: Layout, S: DataTableSource<T>>(&self, source: &S, data: &T, window: &mut FakeWindow<T>) -> Dom<T> {
+
+        let ptr = match StackCheckedPointer::new(data, source) {
+            Some(ptr) => ptr,
+            None => return Dom::label(
+                "Cannot create table from a heap-allocated DataTableSource, \
+                 please store it directly in the application data"),
+        };
+
+        let state = source.table_state();
+
+        let header_click = window.add_callback(ptr, DefaultCallback(on_header_click_private::<T, S>));
+        let resize_start = window.add_callback(ptr, DefaultCallback(on_resize_start_private::<T, S>));
+
+        let mut header = Dom::div().with_class("azul-data-table-header");
+
+        for (column_idx, column) in state.columns.iter().enumerate() {
+
+            let mut cell = Dom::div()
+                .with_class("azul-data-table-header-cell")
+                .with_css_override(COLUMN_WIDTH_CSS_ID, column_width(column.width));
+
+            match state.sort {
+                Some((sorted, SortDirection::Ascending)) if sorted == column_idx => cell.add_class("azul-data-table-sorted-ascending"),
+                Some((sorted, SortDirection::Descending)) if sorted == column_idx => cell.add_class("azul-data-table-sorted-descending"),
+                _ => { },
+            }
+
+            let mut title = Dom::label(column.title.clone()).with_class("azul-data-table-header-title");
+            title.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseUp), header_click);
+
+            let mut resize_handle = Dom::div().with_class("azul-data-table-resize-handle");
+            resize_handle.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseDown), resize_start);
+
+            header.add_child(cell.with_child(title).with_child(resize_handle));
+        }
+
+        // The body has to come after the header, so that its callbacks run after the
+        // callbacks of the header (which check whether a column is being resized)
+        let mut body = Dom::new(NodeType::IFrame((IFrameCallback(render_body_callback::<T, S>), ptr)))
+            .with_class("azul-data-table-body");
+
+        body.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseUp), window.add_callback(ptr, DefaultCallback(on_row_click_private::<T, S>)));
+        body.add_default_callback_id(EventFilter::Hover(HoverEventFilter::Scroll), window.add_callback(ptr, DefaultCallback(on_scroll_private::<T, S>)));
+        body.add_default_callback_id(EventFilter::Window(WindowEventFilter::MouseOver), window.add_callback(ptr, DefaultCallback(on_resize_move_private::<T, S>)));
+        body.add_default_callback_id(EventFilter::Window(WindowEventFilter::LeftMouseUp), window.add_callback(ptr, DefaultCallback(on_resize_end_private::<T, S>)));
+
+        Dom::div()
+            .with_class("azul-data-table")
+            .with_child(header)
+            .with_child(body)
+    }
+}
+
+fn column_width(width: f32) -> CssProperty {
+    CssProperty::Width(LayoutWidth(PixelValue::px(width)))
+}
+
+fn render_body_callback<T: Layout, S: DataTableSource<T>>(ptr: &StackCheckedPointer<T>, info: LayoutInfo<T>, dimensions: HidpiAdjustedBounds)
+-> Dom<T>
+{
+    unsafe { ptr.invoke_mut_iframe(render_body::<T, S>, info, dimensions) }
+}
+
+fn render_body<T: Layout, S: DataTableSource<T>>(source: &mut S, _info: LayoutInfo<T>, dimensions: HidpiAdjustedBounds)
+-> Dom<T>
+{
+    let row_count = source.row_count();
+    let state = source.table_state();
+    let viewport_height = dimensions.logical_size.height as f32;
+    let scroll_offset = clamp_scroll_offset(state.scroll_offset, row_count, state.row_height, viewport_height);
+    let visible_rows = visible_row_range(scroll_offset, state.row_height, viewport_height, row_count);
+
+    // Only the visible rows are rendered, the offset of the first row is the
+    // part of it that is scrolled out of the top of the viewport
+    let first_row_offset = scroll_offset - visible_rows.start as f32 * state.row_height;
+    let mut rows = Dom::div()
+        .with_class("azul-data-table-rows")
+        .with_css_override(SCROLL_CSS_ID, CssProperty::Margin(LayoutMargin { top: Some(PixelValue::px(-first_row_offset)), .. LayoutMargin::default() }));
+
+    for row_idx in visible_rows {
+
+        let mut row = Dom::div().with_class("azul-data-table-row");
+
+        if row_idx % 2 == 1 {
+            row.add_class("azul-data-table-row-odd");
+        }
+
+        if state.selection.contains(row_idx) {
+            row.add_class("azul-data-table-row-selected");
+        }
+
+        for (column_idx, column) in state.columns.iter().enumerate() {
+            row.add_child(Dom::div()
+                .with_class("azul-data-table-cell")
+                .with_css_override(COLUMN_WIDTH_CSS_ID, column_width(column.width))
+                .with_child(source.render_cell(row_idx, column_idx)));
+        }
+
+        rows.add_child(row);
+    }
+
+    rows
+}
+
+fn on_header_click_private<T: Layout, S: DataTableSource<T>>(ptr: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    unsafe { ptr.invoke_mut(on_header_click::<T, S>, app_state_no_data, event) }
+}
+
+fn on_resize_start_private<T: Layout, S: DataTableSource<T>>(ptr: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    unsafe { ptr.invoke_mut(on_resize_start::<T, S>, app_state_no_data, event) }
+}
+
+fn on_resize_move_private<T: Layout, S: DataTableSource<T>>(ptr: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    unsafe { ptr.invoke_mut(on_resize_move::<T, S>, app_state_no_data, event) }
+}
+
+fn on_resize_end_private<T: Layout, S: DataTableSource<T>>(ptr: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    unsafe { ptr.invoke_mut(on_resize_end::<T, S>, app_state_no_data, event) }
+}
+
+fn on_row_click_private<T: Layout, S: DataTableSource<T>>(ptr: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    unsafe { ptr.invoke_mut(on_row_click::<T, S>, app_state_no_data, event) }
+}
+
+fn on_scroll_private<T: Layout, S: DataTableSource<T>>(ptr: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    unsafe { ptr.invoke_mut(on_scroll::<T, S>, app_state_no_data, event) }
+}
+
+fn on_header_click<T: Layout, S: DataTableSource<T>>(source: &mut S, _app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+
+    // The mouse was released over the title after resizing a column
+    if source.table_state_mut().resize.take().is_some() {
+        return Redraw;
+    }
+
+    let header_cell = event.target_parent()?;
+    let (column, _) = event.get_index_in_parent(header_cell)?;
+
+    let direction = {
+        let state = source.table_state_mut();
+        state.selection.clear();
+        state.toggle_sort(column)
+    };
+
+    source.sort_by_column(column, direction);
+    Redraw
+}
+
+fn on_resize_start<T: Layout, S: DataTableSource<T>>(source: &mut S, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    let x = app_state_no_data.windows[event.window_id].get_mouse_state().cursor_pos?.x as f32;
+    let (column, _) = event.get_index_in_parent(event.target_parent()?)?;
+    let state = source.table_state_mut();
+    let start_width = state.columns.get(column)?.width;
+    state.resize = Some(ColumnResize { column, start_x: x, start_width });
+    DontRedraw
+}
+
+fn on_resize_move<T: Layout, S: DataTableSource<T>>(source: &mut S, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    let state = source.table_state_mut();
+    let resize = state.resize?;
+    let x = app_state_no_data.windows[event.window_id].get_mouse_state().cursor_pos?.x as f32;
+    let column = state.columns.get_mut(resize.column)?;
+    let width = resized_width(resize.start_width, resize.start_x, x, column.min_width);
+    if width == column.width {
+        return DontRedraw;
+    }
+    column.width = width;
+    Redraw
+}
+
+fn on_resize_end<T: Layout, S: DataTableSource<T>>(source: &mut S, _app_state_no_data: AppStateNoData<T>, _event: &mut CallbackInfo<T>) -> UpdateScreen {
+    source.table_state_mut().resize.take().and(Redraw)
+}
+
+fn on_row_click<T: Layout, S: DataTableSource<T>>(source: &mut S, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+
+    let row_count = source.row_count();
+    let state = source.table_state_mut();
+
+    // A resize that ends over the body doesn't select anything
+    if state.resize.is_some() {
+        return DontRedraw;
+    }
+
+    let (_, y) = event.cursor_relative_to_item?;
+    let row = row_at(y, state.scroll_offset, state.row_height);
+    if row >= row_count {
+        return DontRedraw;
+    }
+
+    let keyboard_state = app_state_no_data.windows[event.window_id].get_keyboard_state();
+    state.selection.click(row, keyboard_state.ctrl_down, keyboard_state.shift_down, state.selection_mode);
+    Redraw
+}
+
+fn on_scroll<T: Layout, S: DataTableSource<T>>(source: &mut S, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+
+    let window = &app_state_no_data.windows[event.window_id];
+    let scroll_y = window.get_mouse_state().scroll_y as f32;
+    let viewport_height = window.get_layout_snapshot().get_rect(event.hit_dom_node)?.size.height;
+
+    let row_count = source.row_count();
+    let state = source.table_state_mut();
+    let scroll_offset = clamp_scroll_offset(state.scroll_offset + scroll_y, row_count, state.row_height, viewport_height);
+
+    if scroll_offset == state.scroll_offset {
+        return DontRedraw;
+    }
+
+    state.scroll_offset = scroll_offset;
+    Redraw
+}
+
+#[test]
+fn test_column_resize_math() {
+    // Dragging to the right widens the column, to the left makes it narrower
+    assert_eq!(resized_width(100.0, 250.0, 280.0, 20.0), 130.0);
+    assert_eq!(resized_width(100.0, 250.0, 200.0, 20.0), 50.0);
+    // ... but not narrower than the minimum width
+    assert_eq!(resized_width(100.0, 250.0, 100.0, 20.0), 20.0);
+    assert_eq!(resized_width(100.0, 250.0, 250.0, 20.0), 100.0);
+}
+
+#[test]
+fn test_row_virtualization_math() {
+    assert_eq!(row_at(0.0, 0.0, 20.0), 0);
+    assert_eq!(row_at(19.9, 0.0, 20.0), 0);
+    assert_eq!(row_at(5.0, 30.0, 20.0), 1);
+
+    // 100 rows of 20px in a 200px viewport
+    assert_eq!(clamp_scroll_offset(-10.0, 100, 20.0, 200.0), 0.0);
+    assert_eq!(clamp_scroll_offset(5000.0, 100, 20.0, 200.0), 1800.0);
+    assert_eq!(clamp_scroll_offset(50.0, 5, 20.0, 200.0), 0.0);
+
+    assert_eq!(visible_row_range(0.0, 20.0, 200.0, 100), 0..10);
+    assert_eq!(visible_row_range(30.0, 20.0, 200.0, 100), 1..12);
+    assert_eq!(visible_row_range(1800.0, 20.0, 200.0, 100), 90..100);
+    assert_eq!(visible_row_range(0.0, 20.0, 200.0, 3), 0..3);
+    assert_eq!(visible_row_range(0.0, 20.0, 200.0, 0), 0..0);
+}
+
+#[test]
+fn test_row_selection_with_modifiers() {
+
+    use self::SelectionMode::*;
+
+    let rows = |selection: &RowSelection| selection.rows.iter().cloned().collect::<Vec<_>>();
+    let mut selection = RowSelection::default();
+
+    // Plain click: only the clicked row
+    selection.click(3, false, false, Multiple);
+    selection.click(5, false, false, Multiple);
+    assert_eq!(rows(&selection), vec![5]);
+
+    // Shift: range from the anchor, replacing the selection, in both directions
+    selection.click(8, false, true, Multiple);
+    assert_eq!(rows(&selection), vec![5, 6, 7, 8]);
+    selection.click(3, false, true, Multiple);
+    assert_eq!(rows(&selection), vec![3, 4, 5]);
+    assert_eq!(selection.anchor, Some(5));
+
+    // Ctrl: toggles a single row and moves the anchor
+    selection.click(10, true, false, Multiple);
+    assert_eq!(rows(&selection), vec![3, 4, 5, 10]);
+    selection.click(4, true, false, Multiple);
+    assert_eq!(rows(&selection), vec![3, 5, 10]);
+    assert_eq!(selection.anchor, Some(4));
+
+    // Ctrl + Shift: adds the range from the anchor
+    selection.click(12, true, false, Multiple);
+    selection.click(14, true, true, Multiple);
+    assert_eq!(rows(&selection), vec![3, 5, 10, 12, 13, 14]);
+
+    // Shift without an anchor behaves like a plain click
+    selection.clear();
+    selection.click(7, false, true, Multiple);
+    assert_eq!((rows(&selection), selection.anchor), (vec![7], Some(7)));
+
+    // Single selection ignores the modifiers
+    selection.click(2, true, true, Single);
+    assert_eq!(rows(&selection), vec![2]);
+}
+
+#[test]
+fn test_sort_toggling() {
+    let mut state = DataTableState::new(vec![DataTableColumn::new("A", 100.0), DataTableColumn::new("B", 100.0)]);
+    assert_eq!(state.toggle_sort(0), SortDirection::Ascending);
+    assert_eq!(state.toggle_sort(0), SortDirection::Descending);
+    assert_eq!(state.toggle_sort(0), SortDirection::Ascending);
+    assert_eq!(state.toggle_sort(1), SortDirection::Ascending);
+    assert_eq!(state.sort, Some((1, SortDirection::Ascending)));
+}
@@ -0,0 +1,699 @@
+//! Dropdown (select one of N options), bound to a `DropdownState` in the application data
+//!
+//! ```rust,ignore
+//! Dropdown::new()
+//!     .with_placeholder("Choose a size")
+//!     .on_select(Callback(size_selected))
+//!     .bind(info.window, &self.size, &self)
+//!     .dom(&self.size)
+//! ```
+//!
+//! The dropdown is a focusable button that shows the selected option. Clicking it or
+//! pressing Enter / Space / the arrow keys opens a popup list below the button (or above
+//! it, if there isn't enough space below it in the window). In the popup, the arrow keys,
+//! Home / End and Page Up / Page Down move the highlighted option, Enter / Space select
+//! it and Escape closes the popup. Typing the start of the label of an option highlights
+//! (or, if the popup is closed, selects) it. Clicking anywhere outside of the popup
+//! closes it.
+//!
+//! The popup shows at most `DropdownState::max_visible_options` options at once and
+//! scrolls with the mouse wheel and the highlighted option. The DOM has the classes:
+//!
+//! - `azul-dropdown`: the focusable container, additionally `azul-dropdown-open` if the
+//!   popup is open and `azul-dropdown-disabled` if the dropdown is disabled
+//! - `azul-dropdown-button`: the button, contains the `azul-dropdown-value` label
+//!   (additionally `azul-dropdown-placeholder` if nothing is selected) and the `azul-dropdown-arrow`
+//! - `azul-dropdown-popup`: the absolutely positioned popup (additionally
+//!   `azul-dropdown-popup-above` if it opens above the button), moved with the dynamic `top`
+//!   property `__azul-dropdown-popup-top`. Contains the `azul-dropdown-list` with the
+//!   `azul-dropdown-option` labels (additionally `azul-dropdown-option-selected` /
+//!   `azul-dropdown-option-highlighted`)
+
+use std::time::{Duration, Instant};
+use azul_css::{CssProperty, LayoutTop, PixelValue};
+use {
+    traits::Layout,
+    dom::{
+        Dom, Callback, EventFilter, FocusEventFilter, HoverEventFilter, NotEventFilter,
+        UpdateScreen, Redraw, DontRedraw, TabIndex,
+    },
+    window::{FakeWindow, CallbackInfo},
+    prelude::VirtualKeyCode,
+    default_callbacks::{StackCheckedPointer, DefaultCallback, DefaultCallbackId},
+    app_state::AppStateNoData,
+    focus::FocusTarget,
+    id_tree::NodeId,
+};
+
+/// ID of the dynamic CSS property that positions the popup relative to the button
+pub const POPUP_TOP_CSS_ID: &str = "__azul-dropdown-popup-top";
+
+/// Height of an option in the popup, unless set with `DropdownState::with_option_height`
+pub const DEFAULT_OPTION_HEIGHT: f32 = 20.0;
+
+/// Typed characters that are less than this many milliseconds apart are matched as one word
+const TYPE_AHEAD_TIMEOUT_MS: u64 = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dropdown<T: Layout> {
+    placeholder: Option<String>,
+    disabled: bool,
+    on_select: Option<Callback<T>>,
+    callbacks: Option<DropdownCallbacks>,
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+struct DropdownCallbacks {
+    button_click: DefaultCallbackId,
+    key_down: DefaultCallbackId,
+    text_input: DefaultCallbackId,
+    outside_click: DefaultCallbackId,
+    option_click: DefaultCallbackId,
+    option_hover: DefaultCallbackId,
+    scroll: DefaultCallbackId,
+}
+
+/// Whether the popup opens below or above the button
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum PopupPosition {
+    Below,
+    Above,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropdownState {
+    /// Labels of the options
+    pub options: Vec<String>,
+    /// Index of the selected option
+    pub selected: Option<usize>,
+    /// Whether the popup is open
+    pub is_open: bool,
+    /// Index of the option that is highlighted in the open popup (by hovering it or with the arrow keys)
+    pub highlighted: Option<usize>,
+    /// Index of the first option that is shown in the popup
+    pub first_visible: usize,
+    /// Maximum number of options that are shown in the popup at once
+    pub max_visible_options: usize,
+    /// Height of an option in the popup (in pixels), has to match the stylesheet
+    pub option_height: f32,
+    /// Where the popup was opened, see `popup_position()`
+    pub popup_position: PopupPosition,
+    popup_top: f32,
+    type_ahead: String,
+    last_type_ahead: Option<Instant>,
+    selection_changed: bool,
+}
+
+impl Default for DropdownState {
+    fn default() -> Self {
+        DropdownState {
+            options: Vec::new(),
+            selected: None,
+            is_open: false,
+            highlighted: None,
+            first_visible: 0,
+            max_visible_options: 8,
+            option_height: DEFAULT_OPTION_HEIGHT,
+            popup_position: PopupPosition::Below,
+            popup_top: DEFAULT_OPTION_HEIGHT,
+            type_ahead: String::new(),
+            last_type_ahead: None,
+            selection_changed: false,
+        }
+    }
+}
+
+/// Decides where a popup of the `popup_height` opens: below the anchor (spanning from
+/// `anchor_top` to `anchor_bottom`, in pixels relative to the window) if it fits into the
+/// window there, otherwise on the side that has more space.
+pub fn popup_position(anchor_top: f32, anchor_bottom: f32, popup_height: f32, window_height: f32) -> PopupPosition {
+    let space_below = window_height - anchor_bottom;
+    let space_above = anchor_top;
+    if popup_height <= space_below || space_below >= space_above {
+        PopupPosition::Below
+    } else {
+        PopupPosition::Above
+    }
+}
+
+impl DropdownState {
+
+    pub fn new<I, S>(options: I) -> Self where I: IntoIterator<Item=S>, S: Into<String> {
+        Self {
+            options: options.into_iter().map(|option| option.into()).collect(),
+            .. Default::default()
+        }
+    }
+
+    pub fn with_selected(self, selected: Option<usize>) -> Self {
+        Self { selected, .. self }
+    }
+
+    pub fn with_max_visible_options(self, max_visible_options: usize) -> Self {
+        Self { max_visible_options: max_visible_options.max(1), .. self }
+    }
+
+    pub fn with_option_height(self, option_height: f32) -> Self {
+        Self { option_height, .. self }
+    }
+
+    /// Label of the selected option
+    pub fn selected_option(&self) -> Option<&str> {
+        self.selected.and_then(|selected| self.options.get(selected)).map(|option| option.as_str())
+    }
+
+    /// Whether the last event that the dropdown handled selected a different option.
+    /// Use this in the `on_select` callback, which is also called for clicks / keys that
+    /// didn't change the selection.
+    pub fn selection_changed(&self) -> bool {
+        self.selection_changed
+    }
+
+    /// Selects the option, returns whether the selection changed
+    pub fn select(&mut self, index: usize) -> bool {
+        if index >= self.options.len() {
+            return false;
+        }
+        let changed = self.selected != Some(index);
+        self.selected = Some(index);
+        changed
+    }
+
+    /// Opens the popup below the button, highlighting the selected option
+    pub fn open(&mut self) {
+        self.is_open = true;
+        self.highlighted = self.selected.or(if self.options.is_empty() { None } else { Some(0) });
+        self.popup_position = PopupPosition::Below;
+        self.popup_top = self.option_height;
+        self.scroll_to_highlighted();
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.highlighted = None;
+    }
+
+    /// Height of the popup (in pixels), assuming that every option is `option_height` high
+    pub fn popup_height(&self) -> f32 {
+        self.options.len().min(self.max_visible_options) as f32 * self.option_height
+    }
+
+    /// Positions the popup next to the button, given its rectangle (top and bottom,
+    /// relative to the window) in the last frame
+    fn place_popup(&mut self, anchor_top: f32, anchor_bottom: f32, window_height: f32) {
+        let popup_height = self.popup_height();
+        self.popup_position = popup_position(anchor_top, anchor_bottom, popup_height, window_height);
+        self.popup_top = match self.popup_position {
+            PopupPosition::Below => anchor_bottom - anchor_top,
+            PopupPosition::Above => -popup_height,
+        };
+    }
+
+    /// Scrolls the popup by `rows` options (negative: upwards)
+    fn scroll_by(&mut self, rows: isize) {
+        let max_first_visible = self.options.len().saturating_sub(self.max_visible_options) as isize;
+        self.first_visible = (self.first_visible as isize + rows).max(0).min(max_first_visible) as usize;
+    }
+
+    fn scroll_to_highlighted(&mut self) {
+        let highlighted = match self.highlighted { Some(h) => h, None => return };
+        if highlighted < self.first_visible {
+            self.first_visible = highlighted;
+        } else if highlighted >= self.first_visible + self.max_visible_options {
+            self.first_visible = highlighted + 1 - self.max_visible_options;
+        }
+    }
+
+    fn highlight(&mut self, index: usize) {
+        if self.options.is_empty() {
+            return;
+        }
+        self.highlighted = Some(index.min(self.options.len() - 1));
+        self.scroll_to_highlighted();
+    }
+
+    fn type_ahead_is_active(&self, now: Instant) -> bool {
+        !self.type_ahead.is_empty() &&
+        self.last_type_ahead.map(|last| now.duration_since(last) < Duration::from_millis(TYPE_AHEAD_TIMEOUT_MS)).unwrap_or(false)
+    }
+
+    fn handle_key(&mut self, key: Option<VirtualKeyCode>, now: Instant) -> UpdateScreen {
+
+        use self::VirtualKeyCode::*;
+
+        let key = key?;
+
+        if !self.is_open {
+            return match key {
+                Space if self.type_ahead_is_active(now) => DontRedraw,
+                Return | Space | Up | Down => { self.open(); Redraw },
+                _ => DontRedraw,
+            };
+        }
+
+        let highlighted = self.highlighted.unwrap_or(0);
+        let last = self.options.len().saturating_sub(1);
+
+        match key {
+            Up => self.highlight(highlighted.saturating_sub(1)),
+            Down => self.highlight(highlighted + 1),
+            Home => self.highlight(0),
+            End => self.highlight(last),
+            PageUp => self.highlight(highlighted.saturating_sub(self.max_visible_options)),
+            PageDown => self.highlight(highlighted + self.max_visible_options),
+            Space if self.type_ahead_is_active(now) => return DontRedraw,
+            Return | Space => {
+                if let Some(highlighted) = self.highlighted {
+                    self.selection_changed = self.select(highlighted);
+                }
+                self.close();
+            },
+            Escape => self.close(),
+            _ => return DontRedraw,
+        }
+
+        Redraw
+    }
+
+    /// Appends the character to the typed prefix, highlights (or, if the popup is
+    /// closed, selects) the first option that starts with the prefix
+    fn handle_type_ahead(&mut self, c: char, now: Instant) -> UpdateScreen {
+
+        if c.is_control() || (c.is_whitespace() && !self.type_ahead_is_active(now)) {
+            return DontRedraw;
+        }
+
+        if !self.type_ahead_is_active(now) {
+            self.type_ahead.clear();
+        }
+        self.type_ahead.extend(c.to_lowercase());
+        self.last_type_ahead = Some(now);
+
+        let current = if self.is_open { self.highlighted } else { self.selected };
+        // A new prefix searches from the option after the current one, so that
+        // typing the same letter again cycles through the options with that letter
+        let start = match current {
+            Some(current) if self.type_ahead.chars().count() == 1 => current + 1,
+            Some(current) => current,
+            None => 0,
+        };
+
+        let option_count = self.options.len();
+        let found = (0..option_count)
+            .map(|offset| (start + offset) % option_count)
+            .find(|index| self.options[*index].to_lowercase().starts_with(&self.type_ahead))?;
+
+        if self.is_open {
+            self.highlight(found);
+        } else {
+            self.selection_changed = self.select(found);
+        }
+
+        Redraw
+    }
+
+    fn handle_option_click(&mut self, index_in_list: usize) -> UpdateScreen {
+        let index = self.first_visible + index_in_list;
+        self.selection_changed = self.select(index);
+        self.close();
+        Redraw
+    }
+
+    fn on_button_click_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_button_click, app_state_no_data, window_event) }
+    }
+
+    fn on_virtual_key_down_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_virtual_key_down, app_state_no_data, window_event) }
+    }
+
+    fn on_text_input_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_text_input, app_state_no_data, window_event) }
+    }
+
+    fn on_outside_click_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_outside_click, app_state_no_data, window_event) }
+    }
+
+    fn on_option_click_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_option_click, app_state_no_data, window_event) }
+    }
+
+    fn on_option_hover_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_option_hover, app_state_no_data, window_event) }
+    }
+
+    fn on_scroll_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_scroll, app_state_no_data, window_event) }
+    }
+
+    /// Positions the popup that was just opened next to the node (the button or the
+    /// container), using the layout of the last frame
+    fn place_popup_at_node<T: Layout>(&mut self, app_state_no_data: &AppStateNoData<T>, event: &CallbackInfo<T>, node_id: NodeId) {
+        let window = &app_state_no_data.windows[event.window_id];
+        if let Some(rect) = window.get_layout_snapshot().get_rect(node_id) {
+            let window_height = window.state.size.dimensions.height as f32;
+            self.place_popup(rect.origin.y, rect.origin.y + rect.size.height, window_height);
+        }
+    }
+
+    pub fn on_button_click<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        if self.is_open {
+            self.close();
+        } else {
+            self.open();
+            let button = event.hit_dom_node;
+            self.place_popup_at_node(&app_state_no_data, event, button);
+        }
+        Redraw
+    }
+
+    pub fn on_virtual_key_down<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.selection_changed = false;
+        let key = app_state_no_data.windows[event.window_id].get_keyboard_state().latest_virtual_keycode;
+        let was_open = self.is_open;
+        let update = self.handle_key(key, Instant::now());
+        if !was_open && self.is_open {
+            let container = event.hit_dom_node;
+            self.place_popup_at_node(&app_state_no_data, event, container);
+        }
+        update
+    }
+
+    pub fn on_text_input<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.selection_changed = false;
+        let c = app_state_no_data.windows[event.window_id].get_keyboard_state().current_char?;
+        self.handle_type_ahead(c, Instant::now())
+    }
+
+    /// Closes the popup when the mouse is pressed outside of the dropdown, i.e. neither
+    /// on the button (which is excluded by the `Not` filter) nor on the popup
+    pub fn on_outside_click<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+
+        if !self.is_open {
+            return DontRedraw;
+        }
+
+        let window = &app_state_no_data.windows[event.window_id];
+        let popup = event.hit_dom_node.children(event.get_node_hierarchy()).nth(1);
+        let popup_rect = popup.and_then(|popup| window.get_layout_snapshot().get_rect(popup));
+
+        if let (Some(rect), Some(cursor)) = (popup_rect, window.get_mouse_state().cursor_pos) {
+            let (x, y) = (cursor.x as f32, cursor.y as f32);
+            if x >= rect.origin.x && x < rect.origin.x + rect.size.width &&
+               y >= rect.origin.y && y < rect.origin.y + rect.size.height {
+                return DontRedraw;
+            }
+        }
+
+        self.close();
+        Redraw
+    }
+
+    pub fn on_option_click<T: Layout>(&mut self, _app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+
+        self.selection_changed = false;
+        let index_in_list = event.target_index_in_parent()?;
+
+        // option -> list -> popup -> container: keep the dropdown focused
+        let list = event.target_parent()?;
+        if let Some(container) = event.parent(list).and_then(|popup| event.parent(popup)) {
+            event.focus = Some(FocusTarget::Id(container));
+        }
+
+        self.handle_option_click(index_in_list)
+    }
+
+    pub fn on_option_hover<T: Layout>(&mut self, _app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        let index = self.first_visible + event.target_index_in_parent()?;
+        if self.highlighted == Some(index) {
+            return DontRedraw;
+        }
+        self.highlighted = Some(index);
+        Redraw
+    }
+
+    pub fn on_scroll<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        let scroll_y = app_state_no_data.windows[event.window_id].get_mouse_state().scroll_y as f32;
+        if scroll_y == 0.0 {
+            return DontRedraw;
+        }
+        let rows = (scroll_y / self.option_height).round() as isize;
+        let rows = if rows == 0 { scroll_y.signum() as isize } else { rows };
+        let first_visible = self.first_visible;
+        self.scroll_by(rows);
+        if self.first_visible == first_visible { DontRedraw } else { Redraw }
+    }
+}
+
+impl<T: Layout> Dropdown<T> {
+
+    pub fn new() -> Self {
+        Dropdown {
+            placeholder: None,
+            disabled: false,
+            on_select: None,
+            callbacks: None,
+        }
+    }
+
+    /// Text that is shown on the button (with the `azul-dropdown-placeholder` class) while nothing is selected
+    pub fn with_placeholder<S: Into<String>>(self, placeholder: S) -> Self {
+        Self { placeholder: Some(placeholder.into()), .. self }
+    }
+
+    /// A disabled dropdown can't be focused or opened and has the `azul-dropdown-disabled` class
+    pub fn with_disabled(self, disabled: bool) -> Self {
+        Self { disabled, .. self }
+    }
+
+    /// Called after the dropdown handled a click on an option, a key press or a typed
+    /// character - check `DropdownState::selection_changed()` and `DropdownState::selected`,
+    /// the callback is attached to the events and is also called if nothing was selected.
+    pub fn on_select(self, callback: Callback<T>) -> Self {
+        Self { on_select: Some(callback), .. self }
+    }
+
+    pub fn bind(self, window: &mut FakeWindow<T>, field: &DropdownState, data: &T) -> Self {
+        let ptr = StackCheckedPointer::new(data, field);
+        let callbacks = ptr.and_then(|ptr| {
+            Some(DropdownCallbacks {
+                button_click: window.add_callback(ptr, DefaultCallback(DropdownState::on_button_click_private)),
+                key_down: window.add_callback(ptr, DefaultCallback(DropdownState::on_virtual_key_down_private)),
+                text_input: window.add_callback(ptr, DefaultCallback(DropdownState::on_text_input_private)),
+                outside_click: window.add_callback(ptr, DefaultCallback(DropdownState::on_outside_click_private)),
+                option_click: window.add_callback(ptr, DefaultCallback(DropdownState::on_option_click_private)),
+                option_hover: window.add_callback(ptr, DefaultCallback(DropdownState::on_option_hover_private)),
+                scroll: window.add_callback(ptr, DefaultCallback(DropdownState::on_scroll_private)),
+            })
+        });
+
+        Self {
+            callbacks,
+            .. self
+        }
+    }
+
+    pub fn dom(&self, field: &DropdownState) -> Dom<T> {
+
+        let mut container = Dom::div().with_class("azul-dropdown");
+        let mut button = Dom::div().with_class("azul-dropdown-button");
+        let callbacks = if self.disabled { None } else { self.callbacks };
+
+        if self.disabled {
+            container.add_class("azul-dropdown-disabled");
+        } else {
+            container.add_tab_index(TabIndex::Auto);
+            if let Some(on_select) = self.on_select {
+                container.add_callback(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), on_select);
+                container.add_callback(EventFilter::Focus(FocusEventFilter::TextInput), on_select);
+            }
+        }
+
+        if let Some(callbacks) = callbacks {
+            container.add_default_callback_id(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), callbacks.key_down);
+            container.add_default_callback_id(EventFilter::Focus(FocusEventFilter::TextInput), callbacks.text_input);
+            button.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseUp), callbacks.button_click);
+        }
+
+        let value = match (field.selected_option(), &self.placeholder) {
+            (Some(selected), _) => Dom::label(selected.to_string()).with_class("azul-dropdown-value"),
+            (None, placeholder) => {
+                let placeholder = placeholder.clone().unwrap_or_default();
+                Dom::label(placeholder).with_class("azul-dropdown-value").with_class("azul-dropdown-placeholder")
+            },
+        };
+
+        container.add_child(button
+            .with_child(value)
+            .with_child(Dom::div().with_class("azul-dropdown-arrow")));
+
+        if !field.is_open || self.disabled {
+            return container;
+        }
+
+        container.add_class("azul-dropdown-open");
+
+        if let Some(callbacks) = callbacks {
+            container.add_default_callback_id(EventFilter::Not(NotEventFilter::Hover(HoverEventFilter::MouseDown)), callbacks.outside_click);
+        }
+
+        let mut popup = Dom::div().with_class("azul-dropdown-popup")
+            .with_css_override(POPUP_TOP_CSS_ID, CssProperty::Top(LayoutTop(PixelValue::px(field.popup_top))));
+
+        if field.popup_position == PopupPosition::Above {
+            popup.add_class("azul-dropdown-popup-above");
+        }
+
+        let mut list = Dom::div().with_class("azul-dropdown-list");
+
+        if let Some(callbacks) = callbacks {
+            list.add_default_callback_id(EventFilter::Hover(HoverEventFilter::Scroll), callbacks.scroll);
+        }
+
+        let visible_end = (field.first_visible + field.max_visible_options).min(field.options.len());
+
+        for index in field.first_visible..visible_end {
+
+            let mut option = Dom::label(field.options[index].clone()).with_class("azul-dropdown-option");
+
+            if field.selected == Some(index) {
+                option.add_class("azul-dropdown-option-selected");
+            }
+
+            if field.highlighted == Some(index) {
+                option.add_class("azul-dropdown-option-highlighted");
+            }
+
+            if let Some(callbacks) = callbacks {
+                option.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseUp), callbacks.option_click);
+                option.add_default_callback_id(EventFilter::Hover(HoverEventFilter::MouseOver), callbacks.option_hover);
+            }
+
+            if let Some(on_select) = self.on_select {
+                option.add_callback(EventFilter::Hover(HoverEventFilter::LeftMouseUp), on_select);
+            }
+
+            list.add_child(option);
+        }
+
+        container.with_child(popup.with_child(list))
+    }
+}
+
+#[test]
+fn test_popup_flips_above_near_the_bottom_of_the_window() {
+
+    // Enough space below
+    assert_eq!(popup_position(10.0, 30.0, 100.0, 500.0), PopupPosition::Below);
+    // Exactly enough space below
+    assert_eq!(popup_position(10.0, 30.0, 100.0, 130.0), PopupPosition::Below);
+    // Near the bottom: not enough space below, more space above
+    assert_eq!(popup_position(440.0, 460.0, 100.0, 500.0), PopupPosition::Above);
+    // Doesn't fit anywhere: the side with more space wins
+    assert_eq!(popup_position(100.0, 120.0, 300.0, 200.0), PopupPosition::Above);
+    assert_eq!(popup_position(50.0, 70.0, 300.0, 200.0), PopupPosition::Below);
+
+    let mut state = DropdownState::new(vec!["a", "b", "c", "d", "e"]).with_max_visible_options(4);
+    assert_eq!(state.popup_height(), 80.0);
+
+    state.open();
+    state.place_popup(440.0, 460.0, 500.0);
+    assert_eq!((state.popup_position, state.popup_top), (PopupPosition::Above, -80.0));
+    state.place_popup(10.0, 30.0, 500.0);
+    assert_eq!((state.popup_position, state.popup_top), (PopupPosition::Below, 20.0));
+}
+
+#[test]
+fn test_dropdown_open_select_close_with_keyboard() {
+
+    let now = Instant::now();
+    let mut state = DropdownState::new(vec!["Apple", "Banana", "Blueberry", "Cherry"]);
+
+    // Keys that don't open the popup
+    assert_eq!(state.handle_key(Some(VirtualKeyCode::Left), now), DontRedraw);
+    assert!(!state.is_open);
+
+    // Enter opens the popup, highlighting the first option
+    assert_eq!(state.handle_key(Some(VirtualKeyCode::Return), now), Redraw);
+    assert!(state.is_open);
+    assert_eq!(state.highlighted, Some(0));
+
+    state.handle_key(Some(VirtualKeyCode::Down), now);
+    state.handle_key(Some(VirtualKeyCode::Down), now);
+    assert_eq!(state.highlighted, Some(2));
+    state.handle_key(Some(VirtualKeyCode::End), now);
+    state.handle_key(Some(VirtualKeyCode::Down), now);
+    assert_eq!(state.highlighted, Some(3));
+    state.handle_key(Some(VirtualKeyCode::Up), now);
+
+    // Enter selects the highlighted option and closes the popup
+    assert_eq!(state.handle_key(Some(VirtualKeyCode::Return), now), Redraw);
+    assert_eq!((state.selected, state.is_open, state.selection_changed()), (Some(2), false, true));
+
+    // Escape closes without changing the selection, re-opening highlights the selection
+    state.handle_key(Some(VirtualKeyCode::Space), now);
+    assert_eq!(state.highlighted, Some(2));
+    state.handle_key(Some(VirtualKeyCode::Home), now);
+    state.handle_key(Some(VirtualKeyCode::Escape), now);
+    assert_eq!((state.selected, state.is_open), (Some(2), false));
+}
+
+#[test]
+fn test_dropdown_open_select_close_with_mouse_and_scrolling() {
+
+    let mut state = DropdownState::new((0..20).map(|i| format!("Option {}", i)))
+        .with_selected(Some(15))
+        .with_max_visible_options(5);
+
+    // Opening scrolls to the selected option
+    state.open();
+    assert_eq!((state.first_visible, state.highlighted), (11, Some(15)));
+
+    // Scrolling is clamped to the list
+    state.scroll_by(-3);
+    assert_eq!(state.first_visible, 8);
+    state.scroll_by(100);
+    assert_eq!(state.first_visible, 15);
+    state.scroll_by(-100);
+    assert_eq!(state.first_visible, 0);
+
+    // Clicking the third visible option selects it and closes the popup
+    state.scroll_by(4);
+    assert_eq!(state.handle_option_click(2), Redraw);
+    assert_eq!((state.selected, state.is_open, state.selection_changed()), (Some(6), false, true));
+    assert_eq!(state.highlighted, None);
+}
+
+#[test]
+fn test_dropdown_type_ahead() {
+
+    let now = Instant::now();
+    let mut state = DropdownState::new(vec!["Apple", "Banana", "Blueberry", "Cherry"]);
+
+    // Closed: typing selects the matching option directly
+    assert_eq!(state.handle_type_ahead('c', now), Redraw);
+    assert_eq!((state.selected, state.selection_changed()), (Some(3), true));
+
+    // Open: typing highlights, the same letter again cycles through the options
+    let later = now + Duration::from_millis(TYPE_AHEAD_TIMEOUT_MS * 2);
+    state.open();
+    state.handle_type_ahead('b', later);
+    assert_eq!(state.highlighted, Some(1));
+    let even_later = later + Duration::from_millis(TYPE_AHEAD_TIMEOUT_MS * 2);
+    state.handle_type_ahead('b', even_later);
+    assert_eq!(state.highlighted, Some(2));
+
+    // Typing quickly matches a prefix, Space is part of the prefix while typing
+    let much_later = even_later + Duration::from_millis(TYPE_AHEAD_TIMEOUT_MS * 2);
+    state.handle_type_ahead('B', much_later);
+    state.handle_type_ahead('a', much_later);
+    assert_eq!(state.highlighted, Some(1));
+    assert_eq!(state.handle_key(Some(VirtualKeyCode::Space), much_later), DontRedraw);
+    assert!(state.is_open);
+
+    // No match: nothing changes
+    assert_eq!(state.handle_type_ahead('x', much_later), DontRedraw);
+    assert_eq!(state.highlighted, Some(1));
+    assert_eq!(state.selected, Some(3));
+}
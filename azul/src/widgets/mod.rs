@@ -1,8 +1,21 @@
 #[cfg(feature = "svg")]
 pub mod svg;
 pub mod button;
+pub mod check_box;
+pub mod collapsible;
+pub mod color_picker;
+pub mod data_table;
+pub mod dropdown;
 pub mod label;
+pub mod modal;
+pub mod progress_bar;
 pub mod text_input;
+pub mod toggle_switch;
+pub mod radio_group;
+pub mod slider;
+pub mod split_pane;
+pub mod tab_view;
+pub mod tree_view;
 pub mod table_view;
 
 pub mod errors {
@@ -0,0 +1,419 @@
+//! Modal dialog over the rest of the UI, bound to a `ModalState` in the application data
+//!
+//! ```rust,ignore
+//! fn layout(&self, info: LayoutInfo<Self>) -> Dom<Self> {
+//!     Modal::new()
+//!         .on_dismiss(Callback(settings_dismissed))
+//!         .bind(info.window, &self.settings, &self)
+//!         .dom(&self.settings, main_ui(&self), || settings_dialog(&self))
+//! }
+//!
+//! fn open_settings(app_state: &mut AppState<MyApp>, event: &mut CallbackInfo<MyApp>) -> UpdateScreen {
+//!     let window = &app_state.windows[event.window_id];
+//!     app_state.data.modify(|data| data.settings.open(window, event));
+//!     Redraw
+//! }
+//! ```
+//!
+//! While the modal is open, the UI behind it is inert (see `Dom::make_inert`): it can't be
+//! clicked or focused, so the focus stays inside of the dialog. Opening the modal focuses the
+//! first focusable node of the dialog, closing it restores the focus to the node that was
+//! focused before (by `NodeId`, so the UI behind the modal has to be the same as before).
+//! Tab / Shift + Tab cycle through the focusable nodes of the dialog. Escape and clicking
+//! the backdrop dismiss the modal, unless this is disabled in the `ModalState`.
+//!
+//! Modals can be stacked by passing the DOM with the first modal as the background of the
+//! second one: only the topmost modal reacts to events. The DOM has the following classes:
+//!
+//! - `azul-modal`: the layer over the UI, centers the dialog
+//! - `azul-modal-backdrop`: covers the UI behind the dialog
+//! - `azul-modal-dialog`: the focusable dialog, contains the content
+
+use azul_css::{CssPath, CssPathSelector};
+use {
+    traits::Layout,
+    dom::{
+        Dom, Callback, EventFilter, HoverEventFilter, WindowEventFilter,
+        UpdateScreen, Redraw, TabIndex,
+    },
+    window::{FakeWindow, CallbackInfo},
+    prelude::VirtualKeyCode,
+    default_callbacks::{StackCheckedPointer, DefaultCallback, DefaultCallbackId},
+    app_state::AppStateNoData,
+    focus::FocusTarget,
+    id_tree::NodeId,
+};
+
+/// ID of the node that is focused when the modal opens
+pub const AUTOFOCUS_ID: &str = "__azul-modal-autofocus";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Modal<T: Layout> {
+    on_dismiss: Option<Callback<T>>,
+    callbacks: Option<ModalCallbacks>,
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+struct ModalCallbacks {
+    key_down: DefaultCallbackId,
+    backdrop_click: DefaultCallbackId,
+}
+
+/// Whether a modal is open and how it can be dismissed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModalState {
+    /// Whether pressing Escape dismisses the modal
+    pub dismiss_on_escape: bool,
+    /// Whether clicking the backdrop (outside of the dialog) dismisses the modal
+    pub dismiss_on_backdrop_click: bool,
+    is_open: bool,
+    previous_focus: Option<NodeId>,
+    dismissed: bool,
+}
+
+impl Default for ModalState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModalState {
+
+    pub fn new() -> Self {
+        Self {
+            dismiss_on_escape: true,
+            dismiss_on_backdrop_click: true,
+            is_open: false,
+            previous_focus: None,
+            dismissed: false,
+        }
+    }
+
+    pub fn with_dismiss_on_escape(self, dismiss_on_escape: bool) -> Self {
+        Self { dismiss_on_escape, .. self }
+    }
+
+    pub fn with_dismiss_on_backdrop_click(self, dismiss_on_backdrop_click: bool) -> Self {
+        Self { dismiss_on_backdrop_click, .. self }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    /// Whether the last event that the modal handled dismissed it. Use this in the
+    /// `on_dismiss` callback, which is also called for events that didn't dismiss it.
+    pub fn was_dismissed(&self) -> bool {
+        self.dismissed
+    }
+
+    /// Opens the modal and moves the focus to the first focusable node of the dialog
+    /// in the next frame. The node that is focused in the `window` gets the focus back
+    /// when the modal is closed.
+    pub fn open<T: Layout>(&mut self, window: &FakeWindow<T>, event: &mut CallbackInfo<T>) {
+        if let Some(focus) = self.open_with_focus(window.get_focused_node()) {
+            event.focus = Some(focus);
+        }
+    }
+
+    /// Closes the modal and restores the focus (without calling `on_dismiss`)
+    pub fn close<T: Layout>(&mut self, event: &mut CallbackInfo<T>) {
+        if let Some(focus) = self.close_with_focus() {
+            event.focus = Some(focus);
+        }
+    }
+
+    fn open_with_focus(&mut self, focused: Option<NodeId>) -> Option<FocusTarget> {
+        if self.is_open {
+            return None;
+        }
+        self.is_open = true;
+        self.previous_focus = focused;
+        Some(FocusTarget::Path(CssPath { selectors: vec![CssPathSelector::Id(AUTOFOCUS_ID.into())] }))
+    }
+
+    fn close_with_focus(&mut self) -> Option<FocusTarget> {
+        if !self.is_open {
+            return None;
+        }
+        self.is_open = false;
+        Some(self.previous_focus.take().map(FocusTarget::Id).unwrap_or(FocusTarget::NoFocus))
+    }
+
+    fn dismiss(&mut self) -> Option<FocusTarget> {
+        let focus = self.close_with_focus()?;
+        self.dismissed = true;
+        Some(focus)
+    }
+
+    /// Escape dismisses the modal, Tab / Shift + Tab move the focus to the next /
+    /// previous node in `focusable`, wrapping around. Returns the new focus.
+    fn handle_key(&mut self, key: VirtualKeyCode, shift: bool, focusable: &[NodeId], focused: Option<NodeId>) -> Option<FocusTarget> {
+        match key {
+            VirtualKeyCode::Escape if self.dismiss_on_escape => self.dismiss(),
+            VirtualKeyCode::Tab if self.is_open => next_focus(focusable, focused, shift).map(FocusTarget::Id),
+            _ => None,
+        }
+    }
+
+    /// Clicks inside of the dialog also hit the backdrop behind it, so they are ignored
+    fn handle_backdrop_click(&mut self, inside_dialog: bool) -> Option<FocusTarget> {
+        if inside_dialog || !self.dismiss_on_backdrop_click {
+            return None;
+        }
+        self.dismiss()
+    }
+
+    fn on_virtual_key_down_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_virtual_key_down, app_state_no_data, window_event) }
+    }
+
+    fn on_backdrop_click_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_backdrop_click, app_state_no_data, window_event) }
+    }
+
+    /// Called on the `azul-modal-dialog` for every key press in the window
+    pub fn on_virtual_key_down<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.dismissed = false;
+        let window = &app_state_no_data.windows[event.window_id];
+        let keyboard_state = window.get_keyboard_state();
+        let focusable = focusable_nodes(&event.ui_state.dom, event.hit_dom_node);
+        let focus = self.handle_key(keyboard_state.latest_virtual_keycode?, keyboard_state.shift_down, &focusable, window.get_focused_node())?;
+        event.focus = Some(focus);
+        Redraw
+    }
+
+    /// Called on the `azul-modal-backdrop`
+    pub fn on_backdrop_click<T: Layout>(&mut self, _app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.dismissed = false;
+        let layer = event.target_parent()?;
+        let dialog = layer.children(event.get_node_hierarchy()).nth(1)?;
+        let inside_dialog = event.hit_test_items.iter()
+            .any(|item| event.ui_state.tag_ids_to_node_ids.get(&item.tag.0) == Some(&dialog));
+        let focus = self.handle_backdrop_click(inside_dialog)?;
+        event.focus = Some(focus);
+        Redraw
+    }
+}
+
+/// The focusable nodes inside of the dialog in document order, or the dialog itself
+fn focusable_nodes<T: Layout>(dom: &Dom<T>, dialog: NodeId) -> Vec<NodeId> {
+    let focusable = dom.descendants(dialog)
+        .filter(|node_id| dom.get_node_data(*node_id).map(|node| node.tab_index.is_some()).unwrap_or(false))
+        .collect::<Vec<_>>();
+    if focusable.is_empty() { vec![dialog] } else { focusable }
+}
+
+/// The node after `focused` (or before it, if `backwards`), wrapping around
+fn next_focus(focusable: &[NodeId], focused: Option<NodeId>, backwards: bool) -> Option<NodeId> {
+    let count = focusable.len();
+    if count == 0 {
+        return None;
+    }
+    let next = match focusable.iter().position(|node_id| Some(*node_id) == focused) {
+        Some(index) if backwards => (index + count - 1) % count,
+        Some(index) => (index + 1) % count,
+        None if backwards => count - 1,
+        None => 0,
+    };
+    Some(focusable[next])
+}
+
+impl<T: Layout> Modal<T> {
+
+    pub fn new() -> Self {
+        Modal {
+            on_dismiss: None,
+            callbacks: None,
+        }
+    }
+
+    /// Called after the modal handled a key press or a click on the backdrop - check
+    /// `ModalState::was_dismissed()`
+    pub fn on_dismiss(self, callback: Callback<T>) -> Self {
+        Self { on_dismiss: Some(callback), .. self }
+    }
+
+    pub fn bind(self, window: &mut FakeWindow<T>, field: &ModalState, data: &T) -> Self {
+        let ptr = StackCheckedPointer::new(data, field);
+        let callbacks = ptr.and_then(|ptr| {
+            Some(ModalCallbacks {
+                key_down: window.add_callback(ptr, DefaultCallback(ModalState::on_virtual_key_down_private)),
+                backdrop_click: window.add_callback(ptr, DefaultCallback(ModalState::on_backdrop_click_private)),
+            })
+        });
+
+        Self {
+            callbacks,
+            .. self
+        }
+    }
+
+    /// Returns the `background` with the modal over it, `content` is only called if the modal is open
+    pub fn dom<F: FnOnce() -> Dom<T>>(&self, field: &ModalState, background: Dom<T>, content: F) -> Dom<T> {
+
+        if !field.is_open() {
+            return background;
+        }
+
+        // Only the topmost of several stacked modals gets the focus
+        let mut dom = background;
+        if let Some(node_id) = dom.get_node_by_id(AUTOFOCUS_ID) {
+            if let Some(node) = dom.get_node_data_mut(node_id) {
+                node.ids.retain(|id| id != AUTOFOCUS_ID);
+            }
+        }
+        dom.make_inert();
+
+        let mut content = content();
+        let content_root = content.get_root();
+        let autofocus = Some(content_root).into_iter().chain(content.descendants(content_root))
+            .find(|node_id| content.get_node_data(*node_id).map(|node| node.tab_index.is_some()).unwrap_or(false));
+
+        let mut backdrop = Dom::div().with_class("azul-modal-backdrop");
+        let mut dialog = Dom::div()
+            .with_class("azul-modal-dialog")
+            .with_key(field as *const ModalState as u64)
+            .with_tab_index(TabIndex::Auto);
+
+        match autofocus.and_then(|node_id| content.get_node_data_mut(node_id)) {
+            Some(node) => node.add_id(AUTOFOCUS_ID),
+            None => dialog.add_id(AUTOFOCUS_ID),
+        }
+
+        if let Some(callbacks) = self.callbacks {
+            dialog.add_default_callback_id(EventFilter::Window(WindowEventFilter::VirtualKeyDown), callbacks.key_down);
+            backdrop.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseUp), callbacks.backdrop_click);
+        }
+
+        if let Some(on_dismiss) = self.on_dismiss {
+            dialog.add_callback(EventFilter::Window(WindowEventFilter::VirtualKeyDown), on_dismiss);
+            backdrop.add_callback(EventFilter::Hover(HoverEventFilter::LeftMouseUp), on_dismiss);
+        }
+
+        dialog.add_child(content);
+
+        let layer = Dom::div()
+            .with_class("azul-modal")
+            .with_child(backdrop)
+            .with_child(dialog);
+
+        let root = dom.get_root();
+        // The root of a DOM always exists
+        let _ = dom.append_child_at(root, layer);
+        dom
+    }
+}
+
+#[test]
+fn test_modal_escape_and_focus_restoration() {
+
+    let mut state = ModalState::new();
+    let previous = NodeId::new(3);
+
+    // Opening moves the focus into the dialog, opening twice doesn't forget the previous focus
+    assert!(state.open_with_focus(Some(previous)).is_some());
+    assert_eq!(state.open_with_focus(Some(NodeId::new(7))), None);
+    assert!(state.is_open());
+
+    // Other keys don't dismiss the modal
+    assert_eq!(state.handle_key(VirtualKeyCode::A, false, &[], None), None);
+    assert!(state.is_open());
+
+    // Escape dismisses the modal and restores the focus
+    assert_eq!(state.handle_key(VirtualKeyCode::Escape, false, &[], None), Some(FocusTarget::Id(previous)));
+    assert!(!state.is_open());
+    assert!(state.was_dismissed());
+    assert_eq!(state.handle_key(VirtualKeyCode::Escape, false, &[], None), None);
+
+    // Without a previous focus, closing clears the focus
+    state.open_with_focus(None);
+    assert_eq!(state.close_with_focus(), Some(FocusTarget::NoFocus));
+
+    // Escape can be disabled
+    let mut state = ModalState::new().with_dismiss_on_escape(false);
+    state.open_with_focus(Some(previous));
+    assert_eq!(state.handle_key(VirtualKeyCode::Escape, false, &[], None), None);
+    assert!(state.is_open());
+}
+
+#[test]
+fn test_modal_backdrop_click() {
+
+    let mut state = ModalState::new();
+    state.open_with_focus(Some(NodeId::new(2)));
+
+    // Clicks inside of the dialog also hit the backdrop
+    assert_eq!(state.handle_backdrop_click(true), None);
+    assert!(state.is_open());
+
+    assert_eq!(state.handle_backdrop_click(false), Some(FocusTarget::Id(NodeId::new(2))));
+    assert!(!state.is_open());
+    assert!(state.was_dismissed());
+
+    let mut state = ModalState::new().with_dismiss_on_backdrop_click(false);
+    state.open_with_focus(None);
+    assert_eq!(state.handle_backdrop_click(false), None);
+    assert!(state.is_open());
+}
+
+#[test]
+fn test_modal_stacked_focus_trap() {
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    fn dialog_content(class: &str) -> Dom<TestLayout> {
+        Dom::div()
+            .with_child(Dom::label("Title"))
+            .with_child(Dom::div().with_class(class).with_tab_index(TabIndex::Auto))
+            .with_child(Dom::div().with_class(class).with_tab_index(TabIndex::Auto))
+    }
+
+    let background = Dom::<TestLayout>::div()
+        .with_child(Dom::div().with_class("button").with_tab_index(TabIndex::Auto));
+
+    let mut first = ModalState::new();
+    let mut second = ModalState::new();
+    first.open_with_focus(None);
+    second.open_with_focus(None);
+
+    let modal = Modal::<TestLayout>::new();
+    let dom = modal.dom(&first, background, || dialog_content("first"));
+    let dom = modal.dom(&second, dom, || dialog_content("second"));
+
+    let dialogs = dom.find_all_by_class("azul-modal-dialog").collect::<Vec<_>>();
+    let second_buttons = dom.find_all_by_class("second").collect::<Vec<_>>();
+    assert_eq!(dialogs.len(), 2);
+
+    // Only the topmost dialog and its content can be focused
+    let focusable = (0..dom.len())
+        .map(NodeId::new)
+        .filter(|node_id| dom.get_node_data(*node_id).unwrap().tab_index.is_some())
+        .collect::<Vec<_>>();
+    assert_eq!(focusable, vec![dialogs[1], second_buttons[0], second_buttons[1]]);
+    assert_eq!(dom.get_node_by_id(AUTOFOCUS_ID), Some(second_buttons[0]));
+
+    // Tab cycles through the content of the topmost dialog
+    let trap = focusable_nodes(&dom, dialogs[1]);
+    assert_eq!(trap, second_buttons);
+    assert_eq!(second.handle_key(VirtualKeyCode::Tab, false, &trap, Some(trap[0])), Some(FocusTarget::Id(trap[1])));
+    assert_eq!(second.handle_key(VirtualKeyCode::Tab, false, &trap, Some(trap[1])), Some(FocusTarget::Id(trap[0])));
+    assert_eq!(second.handle_key(VirtualKeyCode::Tab, true, &trap, Some(trap[0])), Some(FocusTarget::Id(trap[1])));
+    assert_eq!(second.handle_key(VirtualKeyCode::Tab, false, &trap, None), Some(FocusTarget::Id(trap[0])));
+
+    // A dialog without focusable content traps the focus on itself
+    let dom = modal.dom(&first, Dom::div(), || Dom::label("Saved"));
+    let dialog = dom.find_all_by_class("azul-modal-dialog").next().unwrap();
+    assert_eq!(focusable_nodes(&dom, dialog), vec![dialog]);
+    assert_eq!(dom.get_node_by_id(AUTOFOCUS_ID), Some(dialog));
+
+    // A closed modal returns the background unchanged
+    let closed = modal.dom(&ModalState::new(), Dom::div().with_class("app"), || Dom::label("Hidden"));
+    assert_eq!(closed.len(), 1);
+}
@@ -0,0 +1,160 @@
+//! Display-only progress bar, showing a `ProgressBarState` from the application data
+//!
+//! A determinate bar shows how much of a task is done. An indeterminate bar (for tasks
+//! that can't tell how long they'll take) shows a segment that moves back and forth -
+//! the animation has to be advanced by a daemon:
+//!
+//! ```rust,ignore
+//! const FRAME: Duration = Duration::from_millis(16);
+//!
+//! fn animate_download(app: &mut MyApp, _: &mut AppResources) -> (UpdateScreen, TerminateDaemon) {
+//!     (app.download.animate(FRAME), TerminateDaemon::Continue)
+//! }
+//!
+//! app_state.add_daemon(Daemon::unique(DaemonCallback(animate_download)).run_every(FRAME));
+//! ```
+//!
+//! The DOM has the following classes:
+//!
+//! - `azul-progress-bar`: the container, additionally `azul-progress-bar-indeterminate`
+//! - `azul-progress-bar-fill`: the done part of a determinate bar / the moving segment of an
+//!   indeterminate bar, sized with the dynamic `flex-grow` property `__azul-progress-bar-grow`
+//! - `azul-progress-bar-rest`: the empty space before (only for indeterminate bars) and after
+//!   the fill, also sized with `__azul-progress-bar-grow`
+
+use std::time::Duration;
+use azul_css::{CssProperty, LayoutFlexGrow};
+use {
+    traits::Layout,
+    dom::{Dom, UpdateScreen, Redraw, DontRedraw},
+};
+
+/// ID of the dynamic CSS property that sizes the fill and the empty space of the bar
+pub const GROW_CSS_ID: &str = "__azul-progress-bar-grow";
+
+/// How long the segment of an indeterminate bar takes to move from one end to the other
+const INDETERMINATE_PERIOD_MS: u64 = 1000;
+
+/// Width of the segment of an indeterminate bar, relative to the width of the bar
+const INDETERMINATE_SEGMENT: f32 = 0.3;
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ProgressBar {
+
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Progress {
+    /// The done fraction of the task, from 0.0 to 1.0
+    Determinate(f32),
+    /// The task doesn't know how long it will take
+    Indeterminate,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ProgressBarState {
+    pub progress: Progress,
+    /// Position of the segment of an indeterminate bar, from 0.0 to 2.0
+    /// (moving towards the end until 1.0, then back to the start)
+    phase: f32,
+}
+
+impl ProgressBarState {
+
+    pub fn determinate(fraction: f32) -> Self {
+        Self { progress: Progress::Determinate(clamp_fraction(fraction)), phase: 0.0 }
+    }
+
+    pub fn indeterminate() -> Self {
+        Self { progress: Progress::Indeterminate, phase: 0.0 }
+    }
+
+    /// Makes the bar determinate and sets the done fraction (clamped to 0.0 - 1.0)
+    pub fn set_fraction(&mut self, fraction: f32) {
+        self.progress = Progress::Determinate(clamp_fraction(fraction));
+    }
+
+    /// Returns the done fraction, or `None` if the bar is indeterminate
+    pub fn fraction(&self) -> Option<f32> {
+        match self.progress {
+            Progress::Determinate(fraction) => Some(fraction),
+            Progress::Indeterminate => None,
+        }
+    }
+
+    /// Advances the animation of an indeterminate bar by `elapsed`, call this
+    /// from a daemon. Returns `DontRedraw` if the bar is determinate.
+    pub fn animate(&mut self, elapsed: Duration) -> UpdateScreen {
+        if self.progress != Progress::Indeterminate {
+            return DontRedraw;
+        }
+        let elapsed_ms = elapsed.as_secs() as f32 * 1000.0 + elapsed.subsec_millis() as f32;
+        self.phase = (self.phase + elapsed_ms / INDETERMINATE_PERIOD_MS as f32) % 2.0;
+        Redraw
+    }
+
+    /// Returns the relative sizes of the empty space before the segment, the segment
+    /// and the empty space after it of an indeterminate bar
+    fn segment(&self) -> (f32, f32, f32) {
+        let position = if self.phase <= 1.0 { self.phase } else { 2.0 - self.phase };
+        let before = position * (1.0 - INDETERMINATE_SEGMENT);
+        (before, INDETERMINATE_SEGMENT, 1.0 - INDETERMINATE_SEGMENT - before)
+    }
+}
+
+fn clamp_fraction(fraction: f32) -> f32 {
+    fraction.max(0.0).min(1.0)
+}
+
+impl ProgressBar {
+
+    pub fn new() -> Self {
+        Self { }
+    }
+
+    pub fn dom<T: Layout>(&self, field: &ProgressBarState) -> Dom<T> {
+
+        let mut container = Dom::div().with_class("azul-progress-bar");
+
+        match field.progress {
+            Progress::Determinate(fraction) => {
+                container.add_child(Dom::div().with_class("azul-progress-bar-fill").with_css_override(GROW_CSS_ID, flex_grow(fraction)));
+                container.add_child(Dom::div().with_class("azul-progress-bar-rest").with_css_override(GROW_CSS_ID, flex_grow(1.0 - fraction)));
+            },
+            Progress::Indeterminate => {
+                let (before, segment, after) = field.segment();
+                container.add_class("azul-progress-bar-indeterminate");
+                container.add_child(Dom::div().with_class("azul-progress-bar-rest").with_css_override(GROW_CSS_ID, flex_grow(before)));
+                container.add_child(Dom::div().with_class("azul-progress-bar-fill").with_css_override(GROW_CSS_ID, flex_grow(segment)));
+                container.add_child(Dom::div().with_class("azul-progress-bar-rest").with_css_override(GROW_CSS_ID, flex_grow(after)));
+            },
+        }
+
+        container
+    }
+}
+
+fn flex_grow(value: f32) -> CssProperty {
+    CssProperty::FlexGrow(LayoutFlexGrow(value.into()))
+}
+
+#[test]
+fn test_progress_bar_determinate_and_indeterminate() {
+
+    let mut state = ProgressBarState::determinate(1.5);
+    assert_eq!(state.fraction(), Some(1.0));
+    state.set_fraction(0.25);
+    assert_eq!(state.fraction(), Some(0.25));
+    assert_eq!(state.animate(Duration::from_millis(100)), DontRedraw);
+
+    // The segment moves to the end and back
+    let mut state = ProgressBarState::indeterminate();
+    assert_eq!(state.fraction(), None);
+    assert_eq!(state.segment(), (0.0, 0.3, 0.7));
+    assert_eq!(state.animate(Duration::from_millis(1000)), Redraw);
+    assert_eq!(state.segment().0, 0.7);
+    state.animate(Duration::from_millis(500));
+    assert_eq!(state.segment().0, 0.35);
+    state.animate(Duration::from_millis(1500));
+    assert_eq!(state.segment().0, 0.7);
+}
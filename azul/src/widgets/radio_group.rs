@@ -0,0 +1,276 @@
+//! Group of radio buttons of which at most one is selected, bound to a
+//! `RadioGroupState` in the application data
+//!
+//! ```rust,ignore
+//! RadioGroup::new(vec!["Small", "Medium", "Large"])
+//!     .on_select(Callback(size_selected))
+//!     .bind(info.window, &self.size, &self)
+//!     .dom(&self.size)
+//! ```
+//!
+//! An option is selected by clicking it or by pressing Space or Enter while it is
+//! focused, the arrow keys select (and focus) the previous / next option. The DOM
+//! has the following classes:
+//!
+//! - `azul-radio-group`: the container, additionally `azul-radio-group-disabled` if
+//!   the group is disabled. The options have to stay its direct children.
+//! - `azul-radio`: a focusable option, additionally `azul-radio-checked` if it is selected
+//! - `azul-radio-circle`: the circle of an option, contains the `azul-radio-dot`
+//! - `azul-radio-label`: the label of an option
+
+use {
+    traits::Layout,
+    dom::{
+        Dom, Callback, EventFilter, FocusEventFilter, HoverEventFilter,
+        UpdateScreen, Redraw, DontRedraw, TabIndex,
+    },
+    window::{FakeWindow, CallbackInfo},
+    prelude::VirtualKeyCode,
+    default_callbacks::{StackCheckedPointer, DefaultCallback, DefaultCallbackId},
+    app_state::AppStateNoData,
+    focus::FocusTarget,
+    widgets::check_box::is_activation_key,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RadioGroup<T: Layout> {
+    options: Vec<String>,
+    disabled: bool,
+    on_select: Option<Callback<T>>,
+    callbacks: Option<RadioGroupCallbacks>,
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+struct RadioGroupCallbacks {
+    click: DefaultCallbackId,
+    key_down: DefaultCallbackId,
+}
+
+/// State of a `RadioGroup`: the index of the selected option
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct RadioGroupState {
+    pub selected: Option<usize>,
+    selection_changed: bool,
+}
+
+impl RadioGroupState {
+
+    pub fn new(selected: Option<usize>) -> Self {
+        Self { selected, selection_changed: false }
+    }
+
+    /// Whether the last event that the group handled selected a different option. Use
+    /// this in the `on_select` callback, which is also called for clicks / keys that
+    /// didn't change the selection.
+    pub fn selection_changed(&self) -> bool {
+        self.selection_changed
+    }
+
+    /// Selects the option, deselecting the previously selected option.
+    /// Returns whether the selection changed.
+    pub fn select(&mut self, index: usize) -> bool {
+        let changed = self.selected != Some(index);
+        self.selected = Some(index);
+        changed
+    }
+
+    fn on_click_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_click, app_state_no_data, window_event) }
+    }
+
+    fn on_virtual_key_down_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_virtual_key_down, app_state_no_data, window_event) }
+    }
+
+    pub fn on_click<T: Layout>(&mut self, _app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.selection_changed = false;
+        let index = event.target_index_in_parent()?;
+        self.handle_click(index)
+    }
+
+    pub fn on_virtual_key_down<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+
+        self.selection_changed = false;
+
+        let key = app_state_no_data.windows[event.window_id].get_keyboard_state().latest_virtual_keycode;
+        let focused = event.target_index_in_parent()?;
+        let group = event.target_parent()?;
+        let option_count = group.children(event.get_node_hierarchy()).count();
+
+        let selected = self.handle_key(focused, option_count, key)?;
+        if selected != focused {
+            let option = group.children(event.get_node_hierarchy()).nth(selected)?;
+            event.focus = Some(FocusTarget::Id(option));
+        }
+
+        Redraw
+    }
+
+    fn handle_click(&mut self, index: usize) -> UpdateScreen {
+        self.selection_changed = self.select(index);
+        if self.selection_changed { Redraw } else { DontRedraw }
+    }
+
+    /// Handles a key press while the option `focused` is focused,
+    /// returns the option that was selected by the key
+    fn handle_key(&mut self, focused: usize, option_count: usize, key: Option<VirtualKeyCode>) -> Option<usize> {
+
+        if option_count == 0 {
+            return None;
+        }
+
+        let selected = match key? {
+            key if is_activation_key(key) => focused,
+            VirtualKeyCode::Up | VirtualKeyCode::Left => (focused + option_count - 1) % option_count,
+            VirtualKeyCode::Down | VirtualKeyCode::Right => (focused + 1) % option_count,
+            _ => return None,
+        };
+
+        self.selection_changed = self.select(selected);
+        Some(selected)
+    }
+}
+
+impl<T: Layout> RadioGroup<T> {
+
+    pub fn new<I, S>(options: I) -> Self where I: IntoIterator<Item=S>, S: Into<String> {
+        RadioGroup {
+            options: options.into_iter().map(|option| option.into()).collect(),
+            disabled: false,
+            on_select: None,
+            callbacks: None,
+        }
+    }
+
+    /// The options of a disabled group can't be focused or selected,
+    /// the container has the `azul-radio-group-disabled` class
+    pub fn with_disabled(self, disabled: bool) -> Self {
+        Self { disabled, .. self }
+    }
+
+    /// Called after an option handled a click or a key press - check
+    /// `RadioGroupState::selection_changed()`, the callback is attached to the
+    /// events and is also called for clicks / keys that don't change the selection.
+    pub fn on_select(self, callback: Callback<T>) -> Self {
+        Self { on_select: Some(callback), .. self }
+    }
+
+    pub fn bind(self, window: &mut FakeWindow<T>, field: &RadioGroupState, data: &T) -> Self {
+        let ptr = StackCheckedPointer::new(data, field);
+        let callbacks = ptr.and_then(|ptr| {
+            Some(RadioGroupCallbacks {
+                click: window.add_callback(ptr, DefaultCallback(RadioGroupState::on_click_private)),
+                key_down: window.add_callback(ptr, DefaultCallback(RadioGroupState::on_virtual_key_down_private)),
+            })
+        });
+
+        Self {
+            callbacks,
+            .. self
+        }
+    }
+
+    pub fn dom(&self, field: &RadioGroupState) -> Dom<T> {
+
+        let mut group = Dom::div().with_class("azul-radio-group");
+
+        if self.disabled {
+            group.add_class("azul-radio-group-disabled");
+        }
+
+        for (index, option) in self.options.iter().enumerate() {
+
+            let mut option_dom = Dom::div().with_class("azul-radio");
+
+            if field.selected == Some(index) {
+                option_dom.add_class("azul-radio-checked");
+            }
+
+            if !self.disabled {
+                option_dom.add_tab_index(TabIndex::Auto);
+
+                if let Some(callbacks) = self.callbacks {
+                    option_dom.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseUp), callbacks.click);
+                    option_dom.add_default_callback_id(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), callbacks.key_down);
+                }
+
+                if let Some(on_select) = self.on_select {
+                    option_dom.add_callback(EventFilter::Hover(HoverEventFilter::LeftMouseUp), on_select);
+                    option_dom.add_callback(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), on_select);
+                }
+            }
+
+            group.add_child(option_dom
+                .with_child(Dom::div().with_class("azul-radio-circle")
+                    .with_child(Dom::div().with_class("azul-radio-dot")))
+                .with_child(Dom::label(option.clone()).with_class("azul-radio-label")));
+        }
+
+        group
+    }
+}
+
+#[test]
+fn test_radio_group_single_selection_with_mouse_and_keyboard() {
+
+    let mut state = RadioGroupState::new(None);
+
+    // Clicking selects the option, clicking it again doesn't change anything
+    assert_eq!(state.handle_click(1), Redraw);
+    assert_eq!((state.selected, state.selection_changed()), (Some(1), true));
+    assert_eq!(state.handle_click(1), DontRedraw);
+    assert_eq!((state.selected, state.selection_changed()), (Some(1), false));
+
+    // Selecting another option deselects the previous one
+    assert_eq!(state.handle_click(2), Redraw);
+    assert_eq!(state.selected, Some(2));
+
+    // Space / Enter select the focused option
+    assert_eq!(state.handle_key(0, 3, Some(VirtualKeyCode::Space)), Some(0));
+    assert_eq!((state.selected, state.selection_changed()), (Some(0), true));
+    assert_eq!(state.handle_key(0, 3, Some(VirtualKeyCode::Return)), Some(0));
+    assert!(!state.selection_changed());
+
+    // Arrow keys select the previous / next option and wrap around
+    assert_eq!(state.handle_key(0, 3, Some(VirtualKeyCode::Up)), Some(2));
+    assert_eq!(state.handle_key(2, 3, Some(VirtualKeyCode::Right)), Some(0));
+    assert_eq!(state.handle_key(0, 3, Some(VirtualKeyCode::Down)), Some(1));
+    assert_eq!(state.selected, Some(1));
+
+    // Other keys do nothing
+    assert_eq!(state.handle_key(1, 3, Some(VirtualKeyCode::A)), None);
+    assert_eq!(state.handle_key(1, 3, None), None);
+    assert_eq!(state.handle_key(0, 0, Some(VirtualKeyCode::Space)), None);
+    assert_eq!(state.selected, Some(1));
+}
+
+#[test]
+fn test_radio_group_dom_and_disabled_state() {
+
+    use id_tree::NodeId;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let state = RadioGroupState::new(Some(1));
+    let options = |dom: &Dom<TestLayout>| dom.get_root().children(dom.get_node_hierarchy())
+        .map(|option| dom.get_node_data(option).unwrap().clone())
+        .collect::<Vec<_>>();
+
+    let dom = RadioGroup::<TestLayout>::new(vec!["Small", "Medium", "Large"]).dom(&state);
+    let options_enabled = options(&dom);
+    assert_eq!(options_enabled.len(), 3);
+    let checked = options_enabled.iter().map(|o| o.classes.contains(&"azul-radio-checked".to_string())).collect::<Vec<_>>();
+    assert_eq!(checked, vec![false, true, false]);
+    assert!(options_enabled.iter().all(|o| o.tab_index == Some(TabIndex::Auto)));
+    assert_eq!(dom.get_node_data(NodeId::new(8)).and_then(|n| n.get_label_text()), Some("Medium"));
+
+    let dom = RadioGroup::<TestLayout>::new(vec!["Small", "Medium", "Large"]).with_disabled(true).dom(&state);
+    assert!(dom.get_node_data(dom.get_root()).unwrap().classes.contains(&"azul-radio-group-disabled".to_string()));
+    assert!(options(&dom).iter().all(|o| o.tab_index.is_none() && o.callbacks.is_empty() && o.default_callback_ids.is_empty()));
+}
@@ -0,0 +1,502 @@
+//! Horizontal or vertical slider that selects a value in a range,
+//! bound to a `SliderState` in the application data
+//!
+//! ```rust,ignore
+//! Slider::new()
+//!     .on_change(Callback(volume_changed))
+//!     .bind(info.window, &self.volume, &self)
+//!     .dom(&self.volume)
+//! ```
+//!
+//! The value is changed by dragging the thumb, by clicking the track (which moves the
+//! thumb to the cursor) or with the arrow keys (`SliderState::step`), Page Up / Page Down
+//! (`SliderState::page_step`) and Home / End while the slider is focused. If the state has
+//! ticks, the value snaps to the closest tick. The range can be inverted (`min > max`),
+//! the start of the track (left / bottom) is always `min`. The DOM has the classes:
+//!
+//! - `azul-slider`: the focusable container, additionally `azul-slider-horizontal` or
+//!   `azul-slider-vertical` and `azul-slider-disabled` if the slider is disabled
+//! - `azul-slider-track`: the track, contains the `azul-slider-fill` (the part of the track
+//!   before the thumb), the `azul-slider-thumb` and the `azul-slider-rest`. The fill and rest
+//!   are sized with the dynamic `flex-grow` property `__azul-slider-grow`.
+//! - `azul-slider-ticks`: only if the state has ticks, contains one `azul-slider-tick` per tick
+
+use std::time::{Duration, Instant};
+use azul_css::{CssProperty, LayoutFlexGrow};
+use {
+    traits::Layout,
+    dom::{
+        Dom, Callback, EventFilter, FocusEventFilter, HoverEventFilter, WindowEventFilter,
+        UpdateScreen, Redraw, DontRedraw, TabIndex,
+    },
+    window::{FakeWindow, CallbackInfo},
+    prelude::VirtualKeyCode,
+    default_callbacks::{StackCheckedPointer, DefaultCallback, DefaultCallbackId},
+    app_state::AppStateNoData,
+    focus::FocusTarget,
+};
+
+/// ID of the dynamic CSS property that sizes the fill and the rest of the track
+pub const GROW_CSS_ID: &str = "__azul-slider-grow";
+
+/// While dragging, `on_change` is notified at most once per frame (at 60 FPS)
+const CHANGE_INTERVAL_MS: u64 = 16;
+
+/// The slider draws at most this many ticks, even if the range has more
+const MAX_TICKS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slider<T: Layout> {
+    orientation: Orientation,
+    disabled: bool,
+    on_change: Option<Callback<T>>,
+    callbacks: Option<SliderCallbacks>,
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+struct SliderCallbacks {
+    press: DefaultCallbackId,
+    drag: DefaultCallbackId,
+    release: DefaultCallbackId,
+    key_down: DefaultCallbackId,
+}
+
+/// State of a `Slider`: the range, the value and the step sizes
+#[derive(Debug, Clone, PartialEq)]
+pub struct SliderState {
+    pub value: f32,
+    /// Value at the start of the track (left / bottom), can be larger than `max`
+    pub min: f32,
+    /// Value at the end of the track (right / top)
+    pub max: f32,
+    /// How much the arrow keys change the value
+    pub step: f32,
+    /// How much Page Up / Page Down change the value
+    pub page_step: f32,
+    /// Distance between two ticks, the value snaps to the ticks
+    pub ticks: Option<f32>,
+    dragging: bool,
+    /// The value changed, but `on_change` wasn't notified yet (because of the throttling)
+    change_pending: bool,
+    value_changed: bool,
+    last_change: Option<Instant>,
+}
+
+impl SliderState {
+
+    /// Creates a slider from `min` to `max`, the steps are 1% / 10% of the range
+    pub fn new(min: f32, max: f32, value: f32) -> Self {
+        let range = (max - min).abs();
+        let mut state = Self {
+            value,
+            min,
+            max,
+            step: range / 100.0,
+            page_step: range / 10.0,
+            ticks: None,
+            dragging: false,
+            change_pending: false,
+            value_changed: false,
+            last_change: None,
+        };
+        state.value = state.snap(value);
+        state
+    }
+
+    pub fn with_step(self, step: f32) -> Self {
+        Self { step: step.abs(), .. self }
+    }
+
+    pub fn with_page_step(self, page_step: f32) -> Self {
+        Self { page_step: page_step.abs(), .. self }
+    }
+
+    /// Snaps the value to multiples of `distance` (counted from `min`)
+    pub fn with_ticks(self, distance: f32) -> Self {
+        let mut state = Self { ticks: Some(distance.abs()), .. self };
+        state.value = state.snap(state.value);
+        state
+    }
+
+    /// Whether the last event that the slider handled changed the value. Use this in the
+    /// `on_change` callback, which is also called for events that didn't change the value.
+    /// While the thumb is dragged, this is true at most once per frame - the final value
+    /// is always reported when the mouse button is released.
+    pub fn value_changed(&self) -> bool {
+        self.value_changed
+    }
+
+    /// Whether the thumb is currently being dragged
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    /// Returns the position of the `value` on the track, from 0.0 (start) to 1.0 (end)
+    pub fn fraction_of(&self, value: f32) -> f32 {
+        if self.max == self.min {
+            return 0.0;
+        }
+        ((value - self.min) / (self.max - self.min)).max(0.0).min(1.0)
+    }
+
+    /// Returns the (snapped) value at the position on the track, from 0.0 (start) to 1.0 (end)
+    pub fn value_at(&self, fraction: f32) -> f32 {
+        let fraction = fraction.max(0.0).min(1.0);
+        self.snap(self.min + fraction * (self.max - self.min))
+    }
+
+    /// Sets the value (snapped to the ticks and clamped to the range),
+    /// returns whether the value changed
+    pub fn set_value(&mut self, value: f32) -> bool {
+        let value = self.snap(value);
+        let changed = value != self.value;
+        self.value = value;
+        changed
+    }
+
+    /// Rounds the value to the closest tick and clamps it to the range
+    fn snap(&self, value: f32) -> f32 {
+        let value = match self.ticks {
+            Some(distance) if distance > 0.0 => {
+                let distance = distance * self.direction();
+                self.min + ((value - self.min) / distance).round() * distance
+            },
+            _ => value,
+        };
+        value.max(self.min.min(self.max)).min(self.min.max(self.max))
+    }
+
+    /// 1.0 if the values increase along the track, -1.0 if the range is inverted
+    fn direction(&self) -> f32 {
+        if self.max < self.min { -1.0 } else { 1.0 }
+    }
+
+    fn on_press_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_press, app_state_no_data, window_event) }
+    }
+
+    fn on_drag_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_drag, app_state_no_data, window_event) }
+    }
+
+    fn on_release_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_release, app_state_no_data, window_event) }
+    }
+
+    fn on_virtual_key_down_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_virtual_key_down, app_state_no_data, window_event) }
+    }
+
+    /// Clicking the track moves the thumb to the cursor and starts dragging it
+    pub fn on_press<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.value_changed = false;
+        let fraction = self.cursor_fraction(&app_state_no_data, event)?;
+        event.focus = event.target_parent().map(FocusTarget::Id);
+        self.handle_press(fraction, Instant::now())
+    }
+
+    pub fn on_drag<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.value_changed = false;
+        if !self.dragging {
+            return DontRedraw;
+        }
+        let fraction = self.cursor_fraction(&app_state_no_data, event)?;
+        self.handle_drag(fraction, Instant::now())
+    }
+
+    pub fn on_release<T: Layout>(&mut self, _app_state_no_data: AppStateNoData<T>, _event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.value_changed = false;
+        self.handle_release(Instant::now())
+    }
+
+    pub fn on_virtual_key_down<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.value_changed = false;
+        let key = app_state_no_data.windows[event.window_id].get_keyboard_state().latest_virtual_keycode?;
+        self.handle_key(key, Instant::now())
+    }
+
+    /// Position of the cursor on the track that the callback is attached to, from 0.0 to 1.0
+    fn cursor_fraction<T: Layout>(&self, app_state_no_data: &AppStateNoData<T>, event: &CallbackInfo<T>) -> Option<f32> {
+        let window = &app_state_no_data.windows[event.window_id];
+        let cursor = window.get_mouse_state().cursor_pos?;
+        let track = window.get_layout_snapshot().get_rect(event.hit_dom_node)?;
+        // The track of a horizontal slider is wider than high
+        if track.size.width >= track.size.height {
+            Some((cursor.x as f32 - track.origin.x) / track.size.width.max(1.0))
+        } else {
+            Some(1.0 - (cursor.y as f32 - track.origin.y) / track.size.height.max(1.0))
+        }
+    }
+
+    fn handle_press(&mut self, fraction: f32, now: Instant) -> UpdateScreen {
+        self.dragging = true;
+        self.handle_drag(fraction, now)
+    }
+
+    fn handle_drag(&mut self, fraction: f32, now: Instant) -> UpdateScreen {
+        let value = self.value_at(fraction);
+        if !self.set_value(value) {
+            return DontRedraw;
+        }
+        self.change_pending = true;
+        self.notify_change(now, false);
+        Redraw
+    }
+
+    fn handle_release(&mut self, now: Instant) -> UpdateScreen {
+        if !self.dragging {
+            return DontRedraw;
+        }
+        self.dragging = false;
+        self.notify_change(now, true);
+        Redraw
+    }
+
+    fn handle_key(&mut self, key: VirtualKeyCode, now: Instant) -> UpdateScreen {
+
+        // With ticks, a step moves the value by at least one tick
+        let tick = self.ticks.unwrap_or(0.0);
+        let step = self.step.max(tick) * self.direction();
+        let page_step = self.page_step.max(tick) * self.direction();
+
+        let value = match key {
+            VirtualKeyCode::Left | VirtualKeyCode::Down => self.value - step,
+            VirtualKeyCode::Right | VirtualKeyCode::Up => self.value + step,
+            VirtualKeyCode::PageDown => self.value - page_step,
+            VirtualKeyCode::PageUp => self.value + page_step,
+            VirtualKeyCode::Home => self.min,
+            VirtualKeyCode::End => self.max,
+            _ => return DontRedraw,
+        };
+
+        if !self.set_value(value) {
+            return DontRedraw;
+        }
+
+        self.change_pending = true;
+        self.notify_change(now, true);
+        Redraw
+    }
+
+    /// Sets `value_changed` if a change is pending and `on_change` wasn't notified in
+    /// this frame (or if `force` is set, for the last change of a drag and key presses)
+    fn notify_change(&mut self, now: Instant, force: bool) {
+        if !self.change_pending {
+            return;
+        }
+        let is_due = force || self.last_change.map(|last| now - last >= Duration::from_millis(CHANGE_INTERVAL_MS)).unwrap_or(true);
+        if is_due {
+            self.change_pending = false;
+            self.value_changed = true;
+            self.last_change = Some(now);
+        }
+    }
+}
+
+impl<T: Layout> Slider<T> {
+
+    pub fn new() -> Self {
+        Slider {
+            orientation: Orientation::Horizontal,
+            disabled: false,
+            on_change: None,
+            callbacks: None,
+        }
+    }
+
+    pub fn with_orientation(self, orientation: Orientation) -> Self {
+        Self { orientation, .. self }
+    }
+
+    /// A disabled slider can't be focused or changed and has the `azul-slider-disabled` class
+    pub fn with_disabled(self, disabled: bool) -> Self {
+        Self { disabled, .. self }
+    }
+
+    /// Called after the slider handled a mouse or keyboard event - check
+    /// `SliderState::value_changed()`, the callback is attached to the events
+    /// and is also called for events that don't change the value.
+    pub fn on_change(self, callback: Callback<T>) -> Self {
+        Self { on_change: Some(callback), .. self }
+    }
+
+    pub fn bind(self, window: &mut FakeWindow<T>, field: &SliderState, data: &T) -> Self {
+        let ptr = StackCheckedPointer::new(data, field);
+        let callbacks = ptr.and_then(|ptr| {
+            Some(SliderCallbacks {
+                press: window.add_callback(ptr, DefaultCallback(SliderState::on_press_private)),
+                drag: window.add_callback(ptr, DefaultCallback(SliderState::on_drag_private)),
+                release: window.add_callback(ptr, DefaultCallback(SliderState::on_release_private)),
+                key_down: window.add_callback(ptr, DefaultCallback(SliderState::on_virtual_key_down_private)),
+            })
+        });
+
+        Self {
+            callbacks,
+            .. self
+        }
+    }
+
+    pub fn dom(&self, field: &SliderState) -> Dom<T> {
+
+        let mut container = Dom::div().with_class("azul-slider");
+        container.add_class(match self.orientation {
+            Orientation::Horizontal => "azul-slider-horizontal",
+            Orientation::Vertical => "azul-slider-vertical",
+        });
+
+        let fraction = field.fraction_of(field.value);
+        let fill = Dom::div().with_class("azul-slider-fill").with_css_override(GROW_CSS_ID, flex_grow(fraction));
+        let rest = Dom::div().with_class("azul-slider-rest").with_css_override(GROW_CSS_ID, flex_grow(1.0 - fraction));
+        let thumb = Dom::div().with_class("azul-slider-thumb");
+
+        // The start of a vertical track is at the bottom
+        let mut track = match self.orientation {
+            Orientation::Horizontal => Dom::div().with_class("azul-slider-track").with_child(fill).with_child(thumb).with_child(rest),
+            Orientation::Vertical => Dom::div().with_class("azul-slider-track").with_child(rest).with_child(thumb).with_child(fill),
+        };
+
+        if self.disabled {
+            container.add_class("azul-slider-disabled");
+        } else {
+            container.add_tab_index(TabIndex::Auto);
+
+            if let Some(callbacks) = self.callbacks {
+                container.add_default_callback_id(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), callbacks.key_down);
+                track.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseDown), callbacks.press);
+                track.add_default_callback_id(EventFilter::Window(WindowEventFilter::MouseOver), callbacks.drag);
+                track.add_default_callback_id(EventFilter::Window(WindowEventFilter::LeftMouseUp), callbacks.release);
+            }
+
+            if let Some(on_change) = self.on_change {
+                container.add_callback(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), on_change);
+                track.add_callback(EventFilter::Hover(HoverEventFilter::LeftMouseDown), on_change);
+                track.add_callback(EventFilter::Window(WindowEventFilter::MouseOver), on_change);
+                track.add_callback(EventFilter::Window(WindowEventFilter::LeftMouseUp), on_change);
+            }
+        }
+
+        container.add_child(track);
+
+        if let Some(distance) = field.ticks {
+            if distance > 0.0 {
+                let tick_count = (((field.max - field.min).abs() / distance).round() as usize + 1).min(MAX_TICKS);
+                container.add_child((0..tick_count)
+                    .map(|_| Dom::div().with_class("azul-slider-tick"))
+                    .collect::<Dom<T>>()
+                    .with_class("azul-slider-ticks"));
+            }
+        }
+
+        container
+    }
+}
+
+fn flex_grow(value: f32) -> CssProperty {
+    CssProperty::FlexGrow(LayoutFlexGrow(value.into()))
+}
+
+#[test]
+fn test_slider_value_position_mapping() {
+
+    let state = SliderState::new(0.0, 200.0, 50.0);
+    assert_eq!(state.fraction_of(50.0), 0.25);
+    assert_eq!(state.fraction_of(-10.0), 0.0);
+    assert_eq!(state.fraction_of(300.0), 1.0);
+    assert_eq!(state.value_at(0.5), 100.0);
+    assert_eq!(state.value_at(1.5), 200.0);
+
+    // Inverted range: the start of the track is the larger value
+    let inverted = SliderState::new(100.0, -100.0, 0.0);
+    assert_eq!(inverted.fraction_of(100.0), 0.0);
+    assert_eq!(inverted.fraction_of(50.0), 0.25);
+    assert_eq!(inverted.fraction_of(-100.0), 1.0);
+    assert_eq!(inverted.value_at(0.75), -50.0);
+    assert_eq!(inverted.value_at(-1.0), 100.0);
+
+    // Ticks: the value snaps to the closest tick, in both directions
+    let ticks = SliderState::new(0.0, 100.0, 0.0).with_ticks(25.0);
+    assert_eq!(ticks.value_at(0.3), 25.0);
+    assert_eq!(ticks.value_at(0.4), 50.0);
+    let inverted_ticks = SliderState::new(100.0, 0.0, 0.0).with_ticks(25.0);
+    assert_eq!(inverted_ticks.value_at(0.3), 75.0);
+    assert_eq!(inverted_ticks.value_at(0.4), 50.0);
+    assert_eq!(SliderState::new(0.0, 100.0, 40.0).with_ticks(25.0).value, 50.0);
+
+    // Empty range
+    let empty = SliderState::new(5.0, 5.0, 5.0);
+    assert_eq!(empty.fraction_of(5.0), 0.0);
+    assert_eq!(empty.value_at(0.5), 5.0);
+}
+
+#[test]
+fn test_slider_keyboard_stepping() {
+
+    let now = Instant::now();
+    let mut state = SliderState::new(0.0, 100.0, 50.0).with_step(5.0).with_page_step(20.0);
+
+    assert_eq!(state.handle_key(VirtualKeyCode::Right, now), Redraw);
+    assert_eq!((state.value, state.value_changed()), (55.0, true));
+    assert_eq!(state.handle_key(VirtualKeyCode::Down, now), Redraw);
+    assert_eq!(state.handle_key(VirtualKeyCode::Left, now), Redraw);
+    assert_eq!(state.value, 45.0);
+    assert_eq!(state.handle_key(VirtualKeyCode::PageUp, now), Redraw);
+    assert_eq!(state.value, 65.0);
+    assert_eq!(state.handle_key(VirtualKeyCode::PageDown, now), Redraw);
+    assert_eq!(state.value, 45.0);
+
+    // The value stays in the range
+    assert_eq!(state.handle_key(VirtualKeyCode::End, now), Redraw);
+    assert_eq!(state.handle_key(VirtualKeyCode::Up, now), DontRedraw);
+    assert_eq!(state.value, 100.0);
+    assert_eq!(state.handle_key(VirtualKeyCode::Home, now), Redraw);
+    assert_eq!(state.handle_key(VirtualKeyCode::PageDown, now), DontRedraw);
+    assert_eq!(state.value, 0.0);
+    assert_eq!(state.handle_key(VirtualKeyCode::A, now), DontRedraw);
+
+    // Right / Up move towards `max`, also if the range is inverted
+    let mut inverted = SliderState::new(10.0, 0.0, 5.0).with_step(1.0);
+    assert_eq!(inverted.handle_key(VirtualKeyCode::Right, now), Redraw);
+    assert_eq!(inverted.value, 4.0);
+    assert_eq!(inverted.handle_key(VirtualKeyCode::End, now), Redraw);
+    assert_eq!(inverted.value, 0.0);
+
+    // A step smaller than the ticks still moves to the next tick
+    let mut ticks = SliderState::new(0.0, 100.0, 50.0).with_step(1.0).with_ticks(10.0);
+    assert_eq!(ticks.handle_key(VirtualKeyCode::Right, now), Redraw);
+    assert_eq!(ticks.value, 60.0);
+}
+
+#[test]
+fn test_slider_drag_notifies_once_per_frame() {
+
+    let start = Instant::now();
+    let mut state = SliderState::new(0.0, 100.0, 0.0);
+
+    // Pressing the track jumps to the cursor
+    assert_eq!(state.handle_press(0.5, start), Redraw);
+    assert!(state.is_dragging());
+    assert_eq!((state.value, state.value_changed()), (50.0, true));
+
+    // A second move in the same frame changes the value, but isn't reported
+    state.value_changed = false;
+    assert_eq!(state.handle_drag(0.75, start + Duration::from_millis(5)), Redraw);
+    assert_eq!((state.value, state.value_changed()), (75.0, false));
+    state.value_changed = false;
+    assert_eq!(state.handle_drag(0.8, start + Duration::from_millis(20)), Redraw);
+    assert!(state.value_changed());
+
+    // Releasing reports the pending change immediately
+    state.value_changed = false;
+    state.handle_drag(0.875, start + Duration::from_millis(25));
+    assert!(!state.value_changed());
+    assert_eq!(state.handle_release(start + Duration::from_millis(26)), Redraw);
+    assert!(!state.is_dragging() && state.value_changed());
+    assert_eq!(state.value, 87.5);
+}
@@ -0,0 +1,726 @@
+//! Container that places panels next to each other (or below each other), separated by
+//! splitter handles that resize the panels, bound to a `SplitPaneState` in the application data
+//!
+//! ```rust,ignore
+//! SplitPane::new()
+//!     .on_resize(Callback(save_layout))
+//!     .bind(info.window, &self.editor_split, &self)
+//!     .dom(&self.editor_split, vec![file_tree(&self), editor(&self)])
+//! ```
+//!
+//! Dragging a handle moves the space between the two panels next to it, within the
+//! `min` / `max` sizes of the panels. A collapsible panel that is dragged below half of
+//! its minimum size collapses to zero, the handle next to it then shows a restore button.
+//! Double-clicking a handle resets all panels to their initial sizes.
+//!
+//! The current sizes (in pixels) are stored in `SplitPaneState::sizes`, so they survive
+//! rebuilding the DOM and can be saved and restored by the application. The panels are
+//! sized relative to each other, so they keep their proportions when the window is resized.
+//! The DOM has the following classes:
+//!
+//! - `azul-split-pane`: the container, additionally `azul-split-pane-horizontal` (panels
+//!   next to each other) or `azul-split-pane-vertical` (panels below each other)
+//! - `azul-split-pane-panel`: contains one of the panels, additionally `azul-split-pane-panel-fixed`
+//!   (for panels with a size in pixels, before the first resize) and `azul-split-pane-panel-collapsed`.
+//!   The panels are sized with the dynamic properties `__azul-split-pane-grow` (`flex-grow`)
+//!   and `__azul-split-pane-width` / `__azul-split-pane-height` (for fixed panels).
+//! - `azul-split-pane-handle`: the handle between two panels, additionally
+//!   `azul-split-pane-handle-collapsed` if one of the two panels is collapsed
+//! - `azul-split-pane-restore`: the restore button of a collapsed panel, inside the handle
+
+use std::time::{Duration, Instant};
+use azul_css::{CssProperty, LayoutFlexGrow, LayoutWidth, LayoutHeight, PixelValue};
+use glium::glutin::{MouseCursor, dpi::LogicalPosition};
+use {
+    traits::Layout,
+    dom::{
+        Dom, Callback, EventFilter, HoverEventFilter, WindowEventFilter,
+        UpdateScreen, Redraw, DontRedraw,
+    },
+    app_state::{AppState, AppStateNoData},
+    window::{FakeWindow, CallbackInfo},
+    default_callbacks::{StackCheckedPointer, DefaultCallback, DefaultCallbackId},
+    widgets::slider::Orientation,
+};
+
+/// ID of the dynamic CSS property that sizes the panels relative to each other
+pub const GROW_CSS_ID: &str = "__azul-split-pane-grow";
+
+/// ID of the dynamic CSS property that sets the width of a fixed panel
+pub const WIDTH_CSS_ID: &str = "__azul-split-pane-width";
+
+/// ID of the dynamic CSS property that sets the height of a fixed panel
+pub const HEIGHT_CSS_ID: &str = "__azul-split-pane-height";
+
+/// Two presses on the same handle that are less than this many milliseconds apart are a double click
+const DOUBLE_CLICK_MS: u64 = 500;
+
+/// Sizes that differ by less than this many pixels are equal
+const EPSILON: f32 = 0.01;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitPane<T: Layout> {
+    on_resize: Option<Callback<T>>,
+    callbacks: Option<SplitPaneCallbacks>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct SplitPaneCallbacks {
+    press: DefaultCallbackId,
+    drag: DefaultCallbackId,
+    release: DefaultCallbackId,
+    restore: DefaultCallbackId,
+}
+
+/// Initial size of a panel
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PanelSize {
+    /// Size in pixels
+    Pixels(f32),
+    /// Fraction of the size of the split pane, from 0.0 to 1.0
+    Fraction(f32),
+}
+
+/// Initial size and size constraints of a panel
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SplitPanel {
+    pub initial: PanelSize,
+    /// Minimum size in pixels
+    pub min: f32,
+    /// Maximum size in pixels
+    pub max: f32,
+    /// Whether the panel collapses to zero when it's dragged below half of its minimum size
+    pub collapsible: bool,
+}
+
+impl SplitPanel {
+
+    pub fn pixels(size: f32) -> Self {
+        Self::new(PanelSize::Pixels(size))
+    }
+
+    pub fn fraction(fraction: f32) -> Self {
+        Self::new(PanelSize::Fraction(fraction))
+    }
+
+    fn new(initial: PanelSize) -> Self {
+        Self { initial, min: 0.0, max: ::std::f32::INFINITY, collapsible: false }
+    }
+
+    pub fn with_min(self, min: f32) -> Self {
+        Self { min, .. self }
+    }
+
+    pub fn with_max(self, max: f32) -> Self {
+        Self { max, .. self }
+    }
+
+    pub fn with_collapsible(self, collapsible: bool) -> Self {
+        Self { collapsible, .. self }
+    }
+
+    fn clamp(&self, size: f32) -> f32 {
+        size.max(self.min).min(self.max.max(self.min))
+    }
+}
+
+/// A handle that is being dragged
+#[derive(Debug, Clone, PartialEq)]
+struct HandleDrag {
+    handle: usize,
+    start_position: f32,
+    start_sizes: Vec<f32>,
+}
+
+/// Panels and sizes of a `SplitPane`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitPaneState {
+    pub orientation: Orientation,
+    pub panels: Vec<SplitPanel>,
+    /// Current sizes of the panels in pixels (0.0 for collapsed panels),
+    /// empty until a handle is used for the first time
+    pub sizes: Vec<f32>,
+    /// Sizes of the collapsed panels before they were collapsed
+    restore_sizes: Vec<Option<f32>>,
+    drag: Option<HandleDrag>,
+    last_press: Option<(usize, Instant)>,
+    resized: bool,
+}
+
+impl SplitPaneState {
+
+    pub fn new(orientation: Orientation, panels: Vec<SplitPanel>) -> Self {
+        let restore_sizes = vec![None; panels.len()];
+        Self {
+            orientation,
+            panels,
+            sizes: Vec::new(),
+            restore_sizes,
+            drag: None,
+            last_press: None,
+            resized: false,
+        }
+    }
+
+    /// Whether the last event that the split pane handled changed the sizes. Use this in
+    /// the `on_resize` callback, which is also called for events that didn't change them.
+    pub fn was_resized(&self) -> bool {
+        self.resized
+    }
+
+    pub fn is_collapsed(&self, panel: usize) -> bool {
+        self.restore_sizes.get(panel).map(|size| size.is_some()).unwrap_or(false)
+    }
+
+    /// Collapses the panel to zero, the other panels grow to fill the space
+    pub fn collapse(&mut self, panel: usize) {
+        if panel >= self.panels.len() || self.sizes.len() != self.panels.len() || self.is_collapsed(panel) {
+            return;
+        }
+        let available = self.sizes.iter().sum();
+        self.restore_sizes[panel] = Some(self.sizes[panel]);
+        self.sizes[panel] = 0.0;
+        self.sizes = solve_sizes(&self.panels, &self.sizes, &self.collapsed(), available);
+        self.resized = true;
+    }
+
+    /// Restores a collapsed panel to the size that it had before it was collapsed
+    pub fn restore(&mut self, panel: usize) {
+        let restore_size = match self.restore_sizes.get_mut(panel).and_then(|size| size.take()) {
+            Some(size) => size,
+            None => return,
+        };
+        if self.sizes.len() != self.panels.len() {
+            return;
+        }
+        // The other panels make room for the restored panel
+        let available: f32 = self.sizes.iter().sum();
+        let mut others = self.collapsed();
+        others[panel] = true;
+        self.sizes = solve_sizes(&self.panels, &self.sizes, &others, (available - restore_size).max(0.0));
+        self.sizes[panel] = restore_size.min(available);
+        self.resized = true;
+    }
+
+    /// Resets the panels to their initial sizes, for a split pane of the `available` size
+    pub fn reset(&mut self, available: f32) {
+        self.restore_sizes = vec![None; self.panels.len()];
+        self.sizes = initial_sizes(&self.panels, available);
+        self.resized = true;
+    }
+
+    fn collapsed(&self) -> Vec<bool> {
+        (0..self.panels.len()).map(|panel| self.is_collapsed(panel)).collect()
+    }
+
+    /// Starts dragging the `handle` (between the panels `handle` and `handle + 1`),
+    /// `measured_sizes` are the sizes of the panels in the last frame.
+    /// A second press on the same handle within `DOUBLE_CLICK_MS` resets the sizes.
+    fn handle_press(&mut self, handle: usize, position: f32, measured_sizes: &[f32], now: Instant) -> UpdateScreen {
+
+        if handle + 1 >= self.panels.len() || measured_sizes.len() != self.panels.len() {
+            return DontRedraw;
+        }
+
+        let available = measured_sizes.iter().sum();
+
+        let is_double_click = match self.last_press {
+            Some((last_handle, last_time)) => last_handle == handle && now.duration_since(last_time) < Duration::from_millis(DOUBLE_CLICK_MS),
+            None => false,
+        };
+
+        if is_double_click {
+            self.last_press = None;
+            self.drag = None;
+            self.reset(available);
+            return Redraw;
+        }
+
+        self.last_press = Some((handle, now));
+        self.sizes = solve_sizes(&self.panels, measured_sizes, &self.collapsed(), available);
+        self.drag = Some(HandleDrag { handle, start_position: position, start_sizes: self.sizes.clone() });
+        DontRedraw
+    }
+
+    /// Moves the space between the two panels next to the dragged handle
+    fn handle_drag(&mut self, position: f32) -> UpdateScreen {
+
+        let (handle, delta, start_sizes) = match self.drag {
+            Some(ref drag) => (drag.handle, position - drag.start_position, drag.start_sizes.clone()),
+            None => return DontRedraw,
+        };
+
+        let (before, after) = (&self.panels[handle], &self.panels[handle + 1]);
+        let total = start_sizes[handle] + start_sizes[handle + 1];
+
+        // The size of the panel before the handle, so that both panels stay within their limits
+        let lower = before.min.max(total - after.max);
+        let upper = before.max.min(total - after.min);
+        let wanted = start_sizes[handle] + delta;
+        let mut size_before = if lower <= upper { wanted.max(lower).min(upper) } else { start_sizes[handle] };
+
+        let mut collapsed = (None, None);
+        if before.collapsible && wanted < before.min / 2.0 {
+            size_before = 0.0;
+            collapsed.0 = Some(start_sizes[handle].max(before.min));
+        } else if after.collapsible && total - wanted < after.min / 2.0 {
+            size_before = total;
+            collapsed.1 = Some(start_sizes[handle + 1].max(after.min));
+        }
+
+        let mut sizes = start_sizes;
+        sizes[handle] = size_before;
+        sizes[handle + 1] = total - size_before;
+
+        // Dragging a collapsed panel open again restores it
+        self.restore_sizes[handle] = if sizes[handle] > 0.0 { None } else { collapsed.0.or(self.restore_sizes[handle]) };
+        self.restore_sizes[handle + 1] = if sizes[handle + 1] > 0.0 { None } else { collapsed.1.or(self.restore_sizes[handle + 1]) };
+
+        if sizes == self.sizes {
+            return DontRedraw;
+        }
+
+        self.sizes = sizes;
+        self.resized = true;
+        Redraw
+    }
+
+    fn handle_release(&mut self) -> UpdateScreen {
+        self.drag.take().and(Redraw)
+    }
+
+    fn on_press_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_press, app_state_no_data, window_event) }
+    }
+
+    fn on_drag_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_drag, app_state_no_data, window_event) }
+    }
+
+    fn on_release_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_release, app_state_no_data, window_event) }
+    }
+
+    fn on_restore_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_restore, app_state_no_data, window_event) }
+    }
+
+    /// Pressing a handle starts dragging it (or resets the sizes on a double click)
+    pub fn on_press<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.resized = false;
+        let window = &app_state_no_data.windows[event.window_id];
+        let position = self.cursor_position(window.get_mouse_state().cursor_pos?);
+        let (index, container) = event.get_index_in_parent(event.hit_dom_node)?;
+
+        // The children of the container alternate between panels and handles
+        let snapshot = window.get_layout_snapshot();
+        let measured_sizes = container.children(event.get_node_hierarchy())
+            .step_by(2)
+            .map(|panel| snapshot.get_rect(panel).map(|rect| match self.orientation {
+                Orientation::Horizontal => rect.size.width,
+                Orientation::Vertical => rect.size.height,
+            }))
+            .collect::<Option<Vec<f32>>>()?;
+
+        self.handle_press(index / 2, position, &measured_sizes, Instant::now())
+    }
+
+    pub fn on_drag<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.resized = false;
+        if self.drag.is_none() {
+            return DontRedraw;
+        }
+        let cursor_pos = app_state_no_data.windows[event.window_id].get_mouse_state().cursor_pos?;
+        let position = self.cursor_position(cursor_pos);
+        self.handle_drag(position)
+    }
+
+    pub fn on_release<T: Layout>(&mut self, _app_state_no_data: AppStateNoData<T>, _event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.resized = false;
+        self.handle_release()
+    }
+
+    /// Clicking the restore button restores the collapsed panel next to the handle
+    pub fn on_restore<T: Layout>(&mut self, _app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.resized = false;
+        let (index, _) = event.get_index_in_parent(event.target_parent()?)?;
+        let handle = index / 2;
+        let panel = if self.is_collapsed(handle) { handle } else { handle + 1 };
+        if !self.is_collapsed(panel) {
+            return DontRedraw;
+        }
+        self.restore(panel);
+        Redraw
+    }
+
+    fn cursor_position(&self, cursor_pos: LogicalPosition) -> f32 {
+        match self.orientation {
+            Orientation::Horizontal => cursor_pos.x as f32,
+            Orientation::Vertical => cursor_pos.y as f32,
+        }
+    }
+}
+
+/// Sizes of the panels for a split pane of the `available` size, from the initial sizes
+/// of the panels. Fractions are fractions of the whole `available` size.
+pub fn initial_sizes(panels: &[SplitPanel], available: f32) -> Vec<f32> {
+    let sizes = panels.iter().map(|panel| match panel.initial {
+        PanelSize::Pixels(size) => size,
+        PanelSize::Fraction(fraction) => fraction * available,
+    }).collect::<Vec<_>>();
+    solve_sizes(panels, &sizes, &vec![false; panels.len()], available)
+}
+
+/// Fits the `sizes` of the panels into the `available` size: the difference is distributed
+/// over the panels that aren't collapsed, relative to their current sizes, within their
+/// minimum and maximum sizes.
+///
+/// If the available size is smaller than the sum of the minimum sizes, the panels shrink
+/// relative to their minimum sizes (so the minimum sizes are violated, but nothing overflows).
+/// If it's larger than the sum of the maximum sizes, the last panel gets the rest.
+pub fn solve_sizes(panels: &[SplitPanel], sizes: &[f32], collapsed: &[bool], available: f32) -> Vec<f32> {
+
+    let available = available.max(0.0);
+    let is_open = |panel: usize| !collapsed.get(panel).cloned().unwrap_or(false);
+    let open = (0..panels.len()).filter(|panel| is_open(*panel)).collect::<Vec<_>>();
+
+    let mut solved = vec![0.0; panels.len()];
+    if open.is_empty() {
+        return solved;
+    }
+
+    let min_sum: f32 = open.iter().map(|panel| panels[*panel].min).sum();
+    if available <= min_sum {
+        for panel in &open {
+            solved[*panel] = if min_sum > 0.0 { panels[*panel].min * available / min_sum } else { 0.0 };
+        }
+        return solved;
+    }
+
+    for panel in &open {
+        solved[*panel] = panels[*panel].clamp(sizes.get(*panel).cloned().unwrap_or(0.0).max(0.0));
+    }
+
+    // Every round clamps at least one panel to its limit, so this ends after one round per panel
+    for _ in 0..open.len() {
+
+        let delta = available - open.iter().map(|panel| solved[*panel]).sum::<f32>();
+        if delta.abs() < EPSILON {
+            break;
+        }
+
+        let flexible = open.iter().cloned().filter(|panel| {
+            let size = solved[*panel];
+            if delta > 0.0 { size < panels[*panel].max } else { size > panels[*panel].min }
+        }).collect::<Vec<_>>();
+
+        if flexible.is_empty() {
+            break;
+        }
+
+        let weight_sum: f32 = flexible.iter().map(|panel| solved[*panel]).sum();
+        for panel in &flexible {
+            let weight = if weight_sum > 0.0 { solved[*panel] / weight_sum } else { 1.0 / flexible.len() as f32 };
+            solved[*panel] = panels[*panel].clamp(solved[*panel] + delta * weight);
+        }
+    }
+
+    // All panels are at their maximum size
+    let rest = available - open.iter().map(|panel| solved[*panel]).sum::<f32>();
+    if rest > EPSILON {
+        solved[open[open.len() - 1]] += rest;
+    }
+
+    solved
+}
+
+impl<T: Layout> SplitPane<T> {
+
+    pub fn new() -> Self {
+        SplitPane {
+            on_resize: None,
+            callbacks: None,
+        }
+    }
+
+    /// Called after the split pane handled a mouse event - check `SplitPaneState::was_resized()`
+    /// and read the new `SplitPaneState::sizes`. The callback is attached to the events and
+    /// is also called for events that don't change the sizes.
+    pub fn on_resize(self, callback: Callback<T>) -> Self {
+        Self { on_resize: Some(callback), .. self }
+    }
+
+    pub fn bind(self, window: &mut FakeWindow<T>, field: &SplitPaneState, data: &T) -> Self {
+        let ptr = StackCheckedPointer::new(data, field);
+        let callbacks = ptr.and_then(|ptr| {
+            Some(SplitPaneCallbacks {
+                press: window.add_callback(ptr, DefaultCallback(SplitPaneState::on_press_private)),
+                drag: window.add_callback(ptr, DefaultCallback(SplitPaneState::on_drag_private)),
+                release: window.add_callback(ptr, DefaultCallback(SplitPaneState::on_release_private)),
+                restore: window.add_callback(ptr, DefaultCallback(SplitPaneState::on_restore_private)),
+            })
+        });
+
+        Self {
+            callbacks,
+            .. self
+        }
+    }
+
+    /// Builds the split pane with one panel per entry of `field.panels` - missing
+    /// panels are left empty, extra panels are ignored
+    pub fn dom(&self, field: &SplitPaneState, panels: Vec<Dom<T>>) -> Dom<T> {
+
+        let mut container = Dom::div().with_class("azul-split-pane");
+        let (orientation_class, size_css_id) = match field.orientation {
+            Orientation::Horizontal => ("azul-split-pane-horizontal", WIDTH_CSS_ID),
+            Orientation::Vertical => ("azul-split-pane-vertical", HEIGHT_CSS_ID),
+        };
+        container.add_class(orientation_class);
+
+        let is_measured = field.sizes.len() == field.panels.len();
+        let mut panel_doms = panels.into_iter();
+
+        for (index, panel) in field.panels.iter().enumerate() {
+
+            if index > 0 {
+                container.add_child(self.handle_dom(field, index - 1));
+            }
+
+            let mut panel_dom = Dom::div().with_class("azul-split-pane-panel");
+
+            // Before the first resize, the sizes in pixels aren't known
+            // yet, so the panels are sized with their initial sizes
+            let size = match (field.is_collapsed(index), is_measured, panel.initial) {
+                (true, _, _) => Some(0.0),
+                (false, true, _) => { panel_dom.add_css_override(GROW_CSS_ID, flex_grow(field.sizes[index])); None },
+                (false, false, PanelSize::Pixels(size)) => Some(size),
+                (false, false, PanelSize::Fraction(fraction)) => { panel_dom.add_css_override(GROW_CSS_ID, flex_grow(fraction)); None },
+            };
+
+            if let Some(size) = size {
+                let size_property = match field.orientation {
+                    Orientation::Horizontal => CssProperty::Width(LayoutWidth(PixelValue::px(size))),
+                    Orientation::Vertical => CssProperty::Height(LayoutHeight(PixelValue::px(size))),
+                };
+                panel_dom.add_class("azul-split-pane-panel-fixed");
+                panel_dom.add_css_override(size_css_id, size_property);
+            }
+
+            // The content of a collapsed panel isn't added, but still
+            // consumed, so that the next panels get the right content
+            let content = panel_doms.next();
+            if field.is_collapsed(index) {
+                panel_dom.add_class("azul-split-pane-panel-collapsed");
+            } else if let Some(content) = content {
+                panel_dom.add_child(content);
+            }
+
+            container.add_child(panel_dom);
+        }
+
+        if let Some(callbacks) = self.callbacks {
+            container.add_default_callback_id(EventFilter::Window(WindowEventFilter::MouseOver), callbacks.drag);
+            container.add_default_callback_id(EventFilter::Window(WindowEventFilter::LeftMouseUp), callbacks.release);
+        }
+
+        if let Some(on_resize) = self.on_resize {
+            container.add_callback(EventFilter::Window(WindowEventFilter::MouseOver), on_resize);
+            container.add_callback(EventFilter::Window(WindowEventFilter::LeftMouseUp), on_resize);
+        }
+
+        // Resets the resize cursor when a drag ends outside of the handle
+        container.add_callback(EventFilter::Window(WindowEventFilter::LeftMouseUp), Callback(reset_resize_cursor));
+
+        container
+    }
+
+    fn handle_dom(&self, field: &SplitPaneState, handle: usize) -> Dom<T> {
+
+        let mut handle_dom = Dom::div()
+            .with_class("azul-split-pane-handle")
+            .with_callback(EventFilter::Hover(HoverEventFilter::MouseLeave), Callback(reset_resize_cursor));
+
+        handle_dom.add_callback(EventFilter::Hover(HoverEventFilter::MouseEnter), match field.orientation {
+            Orientation::Horizontal => Callback(set_col_resize_cursor),
+            Orientation::Vertical => Callback(set_row_resize_cursor),
+        });
+
+        if let Some(callbacks) = self.callbacks {
+            handle_dom.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseDown), callbacks.press);
+        }
+        if let Some(on_resize) = self.on_resize {
+            handle_dom.add_callback(EventFilter::Hover(HoverEventFilter::LeftMouseDown), on_resize);
+        }
+
+        if field.is_collapsed(handle) || field.is_collapsed(handle + 1) {
+            handle_dom.add_class("azul-split-pane-handle-collapsed");
+            let mut restore = Dom::div().with_class("azul-split-pane-restore");
+            if let Some(callbacks) = self.callbacks {
+                restore.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseUp), callbacks.restore);
+            }
+            if let Some(on_resize) = self.on_resize {
+                restore.add_callback(EventFilter::Hover(HoverEventFilter::LeftMouseUp), on_resize);
+            }
+            handle_dom.add_child(restore);
+        }
+
+        handle_dom
+    }
+}
+
+fn flex_grow(value: f32) -> CssProperty {
+    CssProperty::FlexGrow(LayoutFlexGrow(value.into()))
+}
+
+fn set_mouse_cursor<T: Layout>(app_state: &mut AppState<T>, event: &CallbackInfo<T>, cursor: MouseCursor) -> UpdateScreen {
+    let window = app_state.windows.get_mut(event.window_id)?;
+    window.state.mouse_state.mouse_cursor_type = cursor;
+    DontRedraw
+}
+
+fn set_col_resize_cursor<T: Layout>(app_state: &mut AppState<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    set_mouse_cursor(app_state, event, MouseCursor::ColResize)
+}
+
+fn set_row_resize_cursor<T: Layout>(app_state: &mut AppState<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    set_mouse_cursor(app_state, event, MouseCursor::RowResize)
+}
+
+/// Resets the resize cursor, but not while a handle is dragged (the cursor can leave the handle)
+fn reset_resize_cursor<T: Layout>(app_state: &mut AppState<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    let mouse_state = *app_state.windows.get(event.window_id)?.get_mouse_state();
+    let is_resize_cursor = mouse_state.mouse_cursor_type == MouseCursor::ColResize || mouse_state.mouse_cursor_type == MouseCursor::RowResize;
+    if !is_resize_cursor || mouse_state.left_down {
+        return DontRedraw;
+    }
+    set_mouse_cursor(app_state, event, MouseCursor::Default)
+}
+
+#[test]
+fn test_split_pane_solve_sizes() {
+
+    let panels = vec![
+        SplitPanel::pixels(200.0).with_min(100.0).with_max(300.0),
+        SplitPanel::fraction(0.5).with_min(200.0),
+        SplitPanel::pixels(100.0).with_min(50.0).with_collapsible(true),
+    ];
+    let open = [false, false, false];
+
+    // Initial sizes: fractions are relative to the whole size, the rest is
+    // distributed relative to the sizes, within the limits
+    assert_eq!(initial_sizes(&panels, 1000.0), vec![250.0, 625.0, 125.0]);
+    assert_eq!(solve_sizes(&panels, &[200.0, 400.0, 100.0], &open, 700.0), vec![200.0, 400.0, 100.0]);
+
+    // Growing stops at the maximum size of the first panel
+    assert_eq!(solve_sizes(&panels, &[200.0, 300.0, 300.0], &open, 1400.0), vec![300.0, 550.0, 550.0]);
+
+    // Shrinking stops at the minimum sizes
+    assert_eq!(solve_sizes(&panels, &[200.0, 300.0, 300.0], &open, 400.0), vec![100.0, 200.0, 100.0]);
+
+    // Below the sum of the minimum sizes (350), the panels shrink relative to their minimums
+    assert_eq!(solve_sizes(&panels, &[200.0, 400.0, 100.0], &open, 175.0), vec![50.0, 100.0, 25.0]);
+    assert_eq!(solve_sizes(&panels, &[200.0, 400.0, 100.0], &open, -10.0), vec![0.0, 0.0, 0.0]);
+
+    // Collapsed panels stay at zero and don't count towards the minimum
+    assert_eq!(solve_sizes(&panels, &[200.0, 400.0, 100.0], &[false, false, true], 300.0), vec![100.0, 200.0, 0.0]);
+
+    // Above the sum of the maximum sizes, the last panel gets the rest
+    let limited = vec![SplitPanel::pixels(10.0).with_max(100.0), SplitPanel::pixels(10.0).with_max(100.0)];
+    assert_eq!(solve_sizes(&limited, &[10.0, 10.0], &[false, false], 300.0), vec![100.0, 200.0]);
+}
+
+#[test]
+fn test_split_pane_drag_collapse_and_reset() {
+
+    let now = Instant::now();
+    let mut state = SplitPaneState::new(Orientation::Horizontal, vec![
+        SplitPanel::fraction(0.25).with_min(100.0).with_collapsible(true),
+        SplitPanel::fraction(0.75).with_min(200.0),
+    ]);
+
+    // Press at x = 200 on the handle, the panels measured 200px and 600px in the last frame
+    assert_eq!(state.handle_press(0, 200.0, &[200.0, 600.0], now), DontRedraw);
+    assert_eq!(state.sizes, vec![200.0, 600.0]);
+
+    assert_eq!(state.handle_drag(250.0), Redraw);
+    assert!(state.was_resized());
+    assert_eq!(state.sizes, vec![250.0, 550.0]);
+
+    // The second panel doesn't shrink below its minimum size
+    state.handle_drag(900.0);
+    assert_eq!(state.sizes, vec![600.0, 200.0]);
+
+    // Between half of the minimum size and the minimum size, the first panel stays at the minimum
+    state.handle_drag(60.0);
+    assert_eq!(state.sizes, vec![100.0, 700.0]);
+    assert!(!state.is_collapsed(0));
+
+    // Below half of the minimum size, it collapses
+    state.handle_drag(40.0);
+    assert_eq!(state.sizes, vec![0.0, 800.0]);
+    assert!(state.is_collapsed(0));
+    assert_eq!(state.handle_release(), Redraw);
+    assert_eq!(state.handle_drag(500.0), DontRedraw);
+
+    // The restore button restores the size from before the drag
+    state.restore(0);
+    assert!(!state.is_collapsed(0));
+    assert_eq!(state.sizes, vec![200.0, 600.0]);
+
+    // A double click on the handle resets the panels to their initial sizes
+    state.handle_press(0, 200.0, &[200.0, 600.0], now + Duration::from_millis(1000));
+    state.handle_drag(300.0);
+    state.handle_release();
+    assert_eq!(state.sizes, vec![300.0, 500.0]);
+    assert_eq!(state.handle_press(0, 300.0, &[300.0, 500.0], now + Duration::from_millis(1200)), Redraw);
+    assert_eq!(state.sizes, vec![200.0, 600.0]);
+    assert_eq!(state.handle_drag(400.0), DontRedraw);
+
+    // Collapsing with the API, the other panels fill the space
+    state.collapse(0);
+    assert_eq!(state.sizes, vec![0.0, 800.0]);
+    state.restore(0);
+    assert_eq!(state.sizes, vec![200.0, 600.0]);
+}
+
+#[test]
+fn test_split_pane_dom() {
+
+    use dom::NodeType;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let mut state = SplitPaneState::new(Orientation::Vertical, vec![
+        SplitPanel::pixels(100.0).with_collapsible(true),
+        SplitPanel::fraction(1.0),
+        SplitPanel::fraction(1.0),
+    ]);
+    let panels = || vec![Dom::label("a"), Dom::label("b"), Dom::label("c")];
+
+    let dom = SplitPane::<TestLayout>::new().dom(&state, panels());
+    assert_eq!(dom.children(dom.get_root()).count(), 5);
+    assert_eq!(dom.find_all_by_class("azul-split-pane-handle").count(), 2);
+    assert_eq!(dom.find_all_by_class("azul-split-pane-panel-fixed").count(), 1);
+    assert_eq!(dom.find_all_by_class("azul-split-pane-restore").count(), 0);
+
+    state.sizes = vec![100.0, 300.0, 300.0];
+    state.collapse(0);
+    let dom = SplitPane::<TestLayout>::new().dom(&state, panels());
+    assert_eq!(dom.find_all_by_class("azul-split-pane-panel-collapsed").count(), 1);
+    assert_eq!(dom.find_all_by_class("azul-split-pane-handle-collapsed").count(), 1);
+    assert_eq!(dom.find_all_by_class("azul-split-pane-restore").count(), 1);
+    let labels = dom.descendants(dom.get_root())
+        .filter_map(|node| match dom.get_node_data(node)?.node_type {
+            NodeType::Label(ref text) => Some(text.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(labels, vec!["b".to_string(), "c".to_string()]);
+}
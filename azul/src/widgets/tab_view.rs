@@ -0,0 +1,409 @@
+//! Tab view: a strip of tabs and the content of the active tab,
+//! bound to a `TabViewState` in the application data
+//!
+//! ```rust,ignore
+//! TabView::new(vec!["General", "Network", "Advanced"])
+//!     .on_tab_change(Callback(tab_changed))
+//!     .bind(info.window, &self.settings_tabs, &self)
+//!     .dom(&self.settings_tabs, |tab| match tab {
+//!         0 => general_settings(&self),
+//!         1 => network_settings(&self),
+//!         _ => advanced_settings(&self),
+//!     })
+//! ```
+//!
+//! Only the content of the active tab is built: the closure is called once, for the active
+//! tab. Nodes of the previously active tab (including a focused text input) don't exist in
+//! the next frame, so they lose the focus and the hover state, as any other removed node.
+//!
+//! A tab is activated by clicking it. While a tab is focused, Left / Right activate the
+//! previous / next tab, Home / End the first / last tab and Ctrl + Tab / Ctrl + Shift + Tab
+//! the next / previous tab (all of these wrap around). The DOM has the following classes:
+//!
+//! - `azul-tab-view`: the container
+//! - `azul-tab-bar`: the strip of `azul-tab`s (additionally `azul-tab-active`), which contain
+//!   the focusable `azul-tab-button` with the `azul-tab-title` label and, if the tabs are
+//!   closable, the `azul-tab-close` button
+//! - `azul-tab-content`: contains the content of the active tab
+
+use {
+    traits::Layout,
+    dom::{
+        Dom, Callback, EventFilter, FocusEventFilter, HoverEventFilter,
+        UpdateScreen, Redraw, DontRedraw, TabIndex,
+    },
+    window::{FakeWindow, CallbackInfo},
+    prelude::VirtualKeyCode,
+    default_callbacks::{StackCheckedPointer, DefaultCallback, DefaultCallbackId},
+    app_state::AppStateNoData,
+    focus::FocusTarget,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabView<T: Layout> {
+    titles: Vec<String>,
+    closable: bool,
+    on_tab_change: Option<Callback<T>>,
+    on_tab_close: Option<Callback<T>>,
+    callbacks: Option<TabViewCallbacks>,
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+struct TabViewCallbacks {
+    click: DefaultCallbackId,
+    key_down: DefaultCallbackId,
+    close: DefaultCallbackId,
+}
+
+/// State of a `TabView`: the index of the active tab
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct TabViewState {
+    pub active: usize,
+    tab_changed: bool,
+    closed_tab: Option<usize>,
+}
+
+impl TabViewState {
+
+    pub fn new(active: usize) -> Self {
+        Self { active, tab_changed: false, closed_tab: None }
+    }
+
+    /// Whether the last event that the tab view handled activated a different tab. Use
+    /// this in the `on_tab_change` callback, which is also called for clicks / keys that
+    /// didn't change the active tab.
+    pub fn tab_changed(&self) -> bool {
+        self.tab_changed
+    }
+
+    /// The tab whose close button was clicked by the last event, use this in the
+    /// `on_tab_close` callback to remove the tab. `active` is already adjusted
+    /// for the removal of the tab.
+    pub fn closed_tab(&self) -> Option<usize> {
+        self.closed_tab
+    }
+
+    /// Activates the tab, returns whether the active tab changed
+    pub fn select(&mut self, index: usize) -> bool {
+        let changed = self.active != index;
+        self.active = index;
+        changed
+    }
+
+    fn on_click_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_click, app_state_no_data, window_event) }
+    }
+
+    fn on_virtual_key_down_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_virtual_key_down, app_state_no_data, window_event) }
+    }
+
+    fn on_close_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_close, app_state_no_data, window_event) }
+    }
+
+    /// Called on the `azul-tab-button`, activates its tab
+    pub fn on_click<T: Layout>(&mut self, _app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.reset_events();
+        let (index, _) = event.get_index_in_parent(event.target_parent()?)?;
+        self.handle_click(index)
+    }
+
+    /// Called on the focused `azul-tab-button`, switches the tab and moves the focus to it
+    pub fn on_virtual_key_down<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+
+        self.reset_events();
+
+        let keyboard_state = app_state_no_data.windows[event.window_id].get_keyboard_state();
+        let (focused, tab_bar) = event.get_index_in_parent(event.target_parent()?)?;
+        let tab_count = tab_bar.children(event.get_node_hierarchy()).count();
+
+        let active = self.handle_key(keyboard_state.latest_virtual_keycode?, keyboard_state.ctrl_down, keyboard_state.shift_down, focused, tab_count)?;
+        let tab = tab_bar.children(event.get_node_hierarchy()).nth(active)?;
+        let button = tab.children(event.get_node_hierarchy()).next()?;
+        event.focus = Some(FocusTarget::Id(button));
+
+        Redraw
+    }
+
+    /// Called on the `azul-tab-close` button
+    pub fn on_close<T: Layout>(&mut self, _app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.reset_events();
+        let (index, tab_bar) = event.get_index_in_parent(event.target_parent()?)?;
+        let tab_count = tab_bar.children(event.get_node_hierarchy()).count();
+        self.handle_close(index, tab_count)
+    }
+
+    fn reset_events(&mut self) {
+        self.tab_changed = false;
+        self.closed_tab = None;
+    }
+
+    fn handle_click(&mut self, index: usize) -> UpdateScreen {
+        self.tab_changed = self.select(index);
+        if self.tab_changed { Redraw } else { DontRedraw }
+    }
+
+    /// Handles a key press while the tab `focused` is focused, returns the activated tab
+    fn handle_key(&mut self, key: VirtualKeyCode, ctrl: bool, shift: bool, focused: usize, tab_count: usize) -> Option<usize> {
+
+        if tab_count == 0 {
+            return None;
+        }
+
+        let previous = (focused + tab_count - 1) % tab_count;
+        let next = (focused + 1) % tab_count;
+
+        let active = match key {
+            VirtualKeyCode::Tab if ctrl && shift => previous,
+            VirtualKeyCode::Tab if ctrl => next,
+            VirtualKeyCode::Left => previous,
+            VirtualKeyCode::Right => next,
+            VirtualKeyCode::Home => 0,
+            VirtualKeyCode::End => tab_count - 1,
+            _ => return None,
+        };
+
+        self.tab_changed = self.select(active);
+        Some(active)
+    }
+
+    /// Closes the tab `index` of `tab_count` tabs, the active tab stays active (or,
+    /// if it's the closed tab, the next tab / the previous tab if it was the last one)
+    fn handle_close(&mut self, index: usize, tab_count: usize) -> UpdateScreen {
+
+        self.closed_tab = Some(index);
+
+        if index < self.active {
+            self.active -= 1;
+        } else if index == self.active {
+            self.tab_changed = true;
+            if index + 1 >= tab_count {
+                self.active = self.active.saturating_sub(1);
+            }
+        }
+
+        Redraw
+    }
+}
+
+impl<T: Layout> TabView<T> {
+
+    pub fn new<I, S>(titles: I) -> Self where I: IntoIterator<Item=S>, S: Into<String> {
+        TabView {
+            titles: titles.into_iter().map(|title| title.into()).collect(),
+            closable: false,
+            on_tab_change: None,
+            on_tab_close: None,
+            callbacks: None,
+        }
+    }
+
+    /// Shows a close button on every tab, see `on_tab_close`
+    pub fn with_closable(self, closable: bool) -> Self {
+        Self { closable, .. self }
+    }
+
+    /// Called after a tab handled a click or a key press - check
+    /// `TabViewState::tab_changed()`, the callback is attached to the events
+    /// and is also called for clicks / keys that don't change the active tab.
+    pub fn on_tab_change(self, callback: Callback<T>) -> Self {
+        Self { on_tab_change: Some(callback), .. self }
+    }
+
+    /// Called after a close button was clicked - the tab view can't remove the tab
+    /// by itself, the callback has to remove the tab `TabViewState::closed_tab()`
+    pub fn on_tab_close(self, callback: Callback<T>) -> Self {
+        Self { on_tab_close: Some(callback), .. self }
+    }
+
+    pub fn bind(self, window: &mut FakeWindow<T>, field: &TabViewState, data: &T) -> Self {
+        let ptr = StackCheckedPointer::new(data, field);
+        let callbacks = ptr.and_then(|ptr| {
+            Some(TabViewCallbacks {
+                click: window.add_callback(ptr, DefaultCallback(TabViewState::on_click_private)),
+                key_down: window.add_callback(ptr, DefaultCallback(TabViewState::on_virtual_key_down_private)),
+                close: window.add_callback(ptr, DefaultCallback(TabViewState::on_close_private)),
+            })
+        });
+
+        Self {
+            callbacks,
+            .. self
+        }
+    }
+
+    /// Builds the tab view, `content` is called once with the index of the active tab
+    pub fn dom<F: FnOnce(usize) -> Dom<T>>(&self, field: &TabViewState, content: F) -> Dom<T> {
+
+        let mut tab_bar = Dom::div().with_class("azul-tab-bar");
+
+        for (index, title) in self.titles.iter().enumerate() {
+
+            let mut tab = Dom::div().with_class("azul-tab");
+
+            if index == field.active {
+                tab.add_class("azul-tab-active");
+            }
+
+            let mut button = Dom::div()
+                .with_class("azul-tab-button")
+                .with_tab_index(TabIndex::Auto)
+                .with_key(button_key(field, index));
+
+            if let Some(callbacks) = self.callbacks {
+                button.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseUp), callbacks.click);
+                button.add_default_callback_id(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), callbacks.key_down);
+            }
+
+            if let Some(on_tab_change) = self.on_tab_change {
+                button.add_callback(EventFilter::Hover(HoverEventFilter::LeftMouseUp), on_tab_change);
+                button.add_callback(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), on_tab_change);
+            }
+
+            tab.add_child(button.with_child(Dom::label(title.clone()).with_class("azul-tab-title")));
+
+            if self.closable {
+                let mut close = Dom::div().with_class("azul-tab-close");
+                if let Some(callbacks) = self.callbacks {
+                    close.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseUp), callbacks.close);
+                }
+                if let Some(on_tab_close) = self.on_tab_close {
+                    close.add_callback(EventFilter::Hover(HoverEventFilter::LeftMouseUp), on_tab_close);
+                }
+                tab.add_child(close);
+            }
+
+            tab_bar.add_child(tab);
+        }
+
+        let mut content_container = Dom::div().with_class("azul-tab-content");
+
+        if field.active < self.titles.len() {
+            content_container.add_child(content(field.active));
+        }
+
+        Dom::div()
+            .with_class("azul-tab-view")
+            .with_child(tab_bar)
+            .with_child(content_container)
+    }
+}
+
+/// Key of the button of a tab. The class of the tab changes when it's activated, so without
+/// a key, the focus (which moves to the activated tab) would be lost in the next frame.
+/// The address of the state makes the key unique if there are several tab views.
+fn button_key(field: &TabViewState, index: usize) -> u64 {
+    (field as *const TabViewState as u64).wrapping_mul(31).wrapping_add(index as u64)
+}
+
+#[cfg(test)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+#[test]
+fn test_tab_view_switching() {
+
+    let mut state = TabViewState::new(0);
+
+    assert_eq!(state.handle_click(2), Redraw);
+    assert_eq!((state.active, state.tab_changed()), (2, true));
+    assert_eq!(state.handle_click(2), DontRedraw);
+    assert!(!state.tab_changed());
+
+    // Arrows, Home / End and Ctrl + (Shift +) Tab, wrapping around
+    assert_eq!(state.handle_key(VirtualKeyCode::Right, false, false, 2, 3), Some(0));
+    assert_eq!(state.handle_key(VirtualKeyCode::Left, false, false, 0, 3), Some(2));
+    assert_eq!(state.handle_key(VirtualKeyCode::Tab, true, false, 2, 3), Some(0));
+    assert_eq!(state.handle_key(VirtualKeyCode::Tab, true, true, 0, 3), Some(2));
+    assert_eq!(state.handle_key(VirtualKeyCode::Home, false, false, 2, 3), Some(0));
+    assert_eq!(state.handle_key(VirtualKeyCode::End, false, false, 0, 3), Some(2));
+    assert_eq!(state.active, 2);
+
+    // Tab without Ctrl moves the focus instead
+    assert_eq!(state.handle_key(VirtualKeyCode::Tab, false, false, 2, 3), None);
+    assert_eq!(state.handle_key(VirtualKeyCode::A, true, false, 2, 3), None);
+    assert_eq!(state.handle_key(VirtualKeyCode::Right, false, false, 0, 0), None);
+    assert_eq!(state.active, 2);
+
+    // Closing a tab before the active tab keeps the active tab
+    let mut state = TabViewState::new(2);
+    state.handle_close(0, 4);
+    assert_eq!((state.active, state.tab_changed(), state.closed_tab()), (1, false, Some(0)));
+
+    // Closing the active tab activates the next one, or the previous one if it was the last
+    state.reset_events();
+    state.handle_close(1, 3);
+    assert_eq!((state.active, state.tab_changed()), (1, true));
+    state.handle_close(1, 2);
+    assert_eq!(state.active, 0);
+    state.handle_close(0, 1);
+    assert_eq!(state.active, 0);
+}
+
+#[test]
+fn test_tab_view_builds_only_the_active_tab() {
+
+    use std::cell::RefCell;
+
+    let built = RefCell::new(Vec::new());
+    let content = |tab: usize| {
+        built.borrow_mut().push(tab);
+        Dom::label(format!("Content {}", tab))
+    };
+
+    let tabs = TabView::<TestLayout>::new(vec!["General", "Network", "Advanced"]).with_closable(true);
+
+    let dom = tabs.dom(&TabViewState::new(1), &content);
+    assert_eq!(*built.borrow(), vec![1]);
+    assert_eq!(dom.find_all_by_class("azul-tab").count(), 3);
+    assert_eq!(dom.find_all_by_class("azul-tab-close").count(), 3);
+    let active = dom.find_all_by_class("azul-tab-active").collect::<Vec<_>>();
+    assert_eq!(active, vec![dom.children(dom.find_all_by_class("azul-tab-bar").next().unwrap()).nth(1).unwrap()]);
+
+    tabs.dom(&TabViewState::new(2), &content);
+    assert_eq!(*built.borrow(), vec![1, 2]);
+
+    // An active tab that doesn't exist (anymore) has no content
+    let dom = tabs.dom(&TabViewState::new(3), &content);
+    assert_eq!(*built.borrow(), vec![1, 2]);
+    let content_container = dom.find_all_by_class("azul-tab-content").next().unwrap();
+    assert_eq!(dom.children(content_container).count(), 0);
+}
+
+#[test]
+fn test_tab_view_releases_the_focus_of_removed_content() {
+
+    use node_identity::resolve_node_identities;
+    use window_state::WindowState;
+
+    let tabs = TabView::<TestLayout>::new(vec!["General", "Network"]);
+    let content = |tab: usize| match tab {
+        0 => Dom::div().with_class("general").with_tab_index(TabIndex::Auto),
+        _ => Dom::div().with_class("network").with_tab_index(TabIndex::Auto),
+    };
+
+    let mut tab_state = TabViewState::new(0);
+    let general = tabs.dom(&tab_state, content);
+    tab_state.active = 1;
+    let network = tabs.dom(&tab_state, content);
+
+    // The focused node only exists in the first tab
+    let mut state = WindowState::default();
+    state.focused_node = general.find_all_by_class("general").next();
+    assert!(state.focused_node.is_some());
+    state.migrate_node_ids(&resolve_node_identities(&general, &network));
+    assert_eq!(state.focused_node, None);
+
+    // The buttons keep their focus, even though the class of their tab changed
+    let second_button = general.find_all_by_class("azul-tab-button").nth(1);
+    state.focused_node = second_button;
+    state.migrate_node_ids(&resolve_node_identities(&general, &network));
+    assert_eq!(state.focused_node, network.find_all_by_class("azul-tab-button").nth(1));
+}
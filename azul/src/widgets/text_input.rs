@@ -1,75 +1,287 @@
-//! Text input (demonstrates two-way data binding)
+//! Single-line text input with a caret, selection, placeholder and password masking
+//!
+//! The state of the field lives in the application data as a `TextInputState`, which
+//! the widget updates via default callbacks (two-way data binding):
+//!
+//! ```rust,ignore
+//! TextInput::new()
+//!     .with_placeholder("Name")
+//!     .on_submit(Callback(submit_form))
+//!     .bind(info.window, &self.name, &self)
+//!     .dom(&self.name)
+//! ```
+//!
+//! Every grapheme of the text is rendered as its own label, so that the caret can be
+//! positioned with the mouse using the laid out rectangles of the last frame. The
+//! native styles come with styles for the `__azul-native-input-text-*` classes; the
+//! content is scrolled horizontally with the dynamic `margin-left` property
+//! `__azul-native-input-text-scroll`. IME composition isn't supported yet, composed
+//! characters arrive as regular `TextInput` events.
 
 use std::ops::Range;
+use webrender::api::LayoutRect;
+use azul_css::{CssProperty, LayoutMargin, PixelValue};
 use {
     traits::Layout,
     dom::{
-        Dom, EventFilter, FocusEventFilter,
+        Dom, Callback, EventFilter, FocusEventFilter, HoverEventFilter,
         UpdateScreen, Redraw, DontRedraw, TabIndex,
     },
     window::{FakeWindow, CallbackInfo},
     prelude::{VirtualKeyCode},
     default_callbacks::{StackCheckedPointer, DefaultCallback, DefaultCallbackId},
     app_state::AppStateNoData,
+    id_tree::NodeId,
 };
 
+/// Character that replaces every grapheme of a password field
+pub const PASSWORD_MASK: &str = "\u{2022}";
+
+/// ID of the dynamic CSS property that scrolls the content of the field
+pub const SCROLL_CSS_ID: &str = "__azul-native-input-text-scroll";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextInput<T: Layout> {
+    on_text_input_callback: Option<TextInputCallbacks>,
+    placeholder: Option<String>,
+    password: bool,
+    on_change: Option<Callback<T>>,
+    on_submit: Option<Callback<T>>,
+}
+
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
-pub struct TextInput {
-    on_text_input_callback: Option<(DefaultCallbackId, DefaultCallbackId)>,
+struct TextInputCallbacks {
+    text_input: DefaultCallbackId,
+    virtual_key_down: DefaultCallbackId,
+    mouse_down: DefaultCallbackId,
+    mouse_over: DefaultCallbackId,
+    mouse_up: DefaultCallbackId,
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+/// What the last event that the field handled did, see `TextInputState::last_event()`
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum TextInputEvent {
+    /// The text was edited
+    Changed,
+    /// Enter was pressed
+    Submitted,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct TextInputState {
     pub text: String,
-    pub selection: Option<Selection>,
+    /// Byte offset of the caret in the `text`, always at a grapheme boundary
     pub cursor: usize,
-}
-
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
-pub enum Selection {
-    All,
-    FromTo(Range<usize>),
+    /// The other end of the selection (byte offset), the selection spans from
+    /// the anchor to the `cursor`. `None` or equal to the cursor: nothing is selected
+    pub selection_anchor: Option<usize>,
+    /// Maximum number of graphemes that can be entered
+    pub max_length: Option<usize>,
+    /// How far the content is scrolled to the left (in pixels), so that the caret stays visible
+    pub scroll_x: f32,
+    last_event: Option<TextInputEvent>,
+    is_selecting_with_mouse: bool,
 }
 
 impl Default for TextInputState {
     fn default() -> Self {
         TextInputState {
             text: String::new(),
-            selection: None,
             cursor: 0,
+            selection_anchor: None,
+            max_length: None,
+            scroll_x: 0.0,
+            last_event: None,
+            is_selecting_with_mouse: false,
         }
     }
 }
 
 impl TextInputState {
+
     pub fn new<S: Into<String>>(input: S) -> Self {
-        let input_str: String = input.into();
-        let len = input_str.len();
+        let text: String = input.into();
         Self {
-            text: input_str,
-            selection: None,
-            cursor: len,
+            cursor: text.len(),
+            text,
+            .. Default::default()
         }
     }
-}
 
-struct TextInputCallback<'a> {
-    ptr: &'a TextInputState,
+    pub fn with_max_length(self, max_length: usize) -> Self {
+        Self { max_length: Some(max_length), .. self }
+    }
+
+    /// What the last key press that the field received did - `None` if it didn't
+    /// change the text (i.e. only moved the caret). Use this in the `on_change` and
+    /// `on_submit` callbacks of the `TextInput`.
+    pub fn last_event(&self) -> Option<TextInputEvent> {
+        self.last_event
+    }
+
+    /// Returns the selected byte range, `None` if nothing is selected
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.selection_anchor?;
+        if anchor == self.cursor {
+            None
+        } else {
+            Some(anchor.min(self.cursor)..anchor.max(self.cursor))
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selection().map(|range| &self.text[range])
+    }
+
+    pub fn select_all(&mut self) {
+        self.selection_anchor = Some(0);
+        self.cursor = self.text.len();
+    }
+
+    /// Moves the caret to the grapheme boundary at or before the byte offset
+    /// `cursor`, extending the selection from the current position if `select` is set
+    pub fn set_cursor(&mut self, cursor: usize, select: bool) {
+        let cursor = floor_grapheme_boundary(&self.text, cursor);
+        if select {
+            if self.selection_anchor.is_none() {
+                self.selection_anchor = Some(self.cursor);
+            }
+        } else {
+            self.selection_anchor = None;
+        }
+        self.cursor = cursor;
+    }
+
+    /// Moves the caret one grapheme (or word) to the left. Without `select`,
+    /// a selection is collapsed to its start instead.
+    pub fn move_left(&mut self, word: bool, select: bool) {
+        if let (Some(selection), false) = (self.selection(), select) {
+            return self.set_cursor(selection.start, false);
+        }
+        let target = if word { prev_word_boundary(&self.text, self.cursor) } else { prev_grapheme_boundary(&self.text, self.cursor) };
+        self.set_cursor(target, select);
+    }
+
+    /// Moves the caret one grapheme (or word) to the right. Without `select`,
+    /// a selection is collapsed to its end instead.
+    pub fn move_right(&mut self, word: bool, select: bool) {
+        if let (Some(selection), false) = (self.selection(), select) {
+            return self.set_cursor(selection.end, false);
+        }
+        let target = if word { next_word_boundary(&self.text, self.cursor) } else { next_grapheme_boundary(&self.text, self.cursor) };
+        self.set_cursor(target, select);
+    }
+
+    /// Replaces the selection (or inserts at the caret) with the `text`. Control
+    /// characters are skipped and the text is cut off at the `max_length`.
+    /// Returns whether the text changed.
+    pub fn insert_text(&mut self, text: &str) -> bool {
+
+        let text = text.chars().filter(|c| !c.is_control()).collect::<String>();
+        let selection = self.selection().unwrap_or(self.cursor..self.cursor);
+
+        let text = match self.max_length {
+            Some(max_length) => {
+                let remaining_graphemes = grapheme_count(&self.text[..selection.start]) + grapheme_count(&self.text[selection.end..]);
+                let allowed = max_length.saturating_sub(remaining_graphemes);
+                let end = grapheme_boundaries(&text).get(allowed).cloned().unwrap_or(text.len());
+                text[..end].to_string()
+            },
+            None => text,
+        };
+
+        if text.is_empty() && selection.start == selection.end {
+            return false;
+        }
+
+        self.text.replace_range(selection.clone(), &text);
+        self.cursor = selection.start + text.len();
+        self.selection_anchor = None;
+        true
+    }
+
+    /// Deletes the selection or the grapheme (or word) before the caret (Backspace).
+    /// Returns whether the text changed.
+    pub fn delete_backward(&mut self, word: bool) -> bool {
+        let range = match self.selection() {
+            Some(selection) => selection,
+            None => {
+                let start = if word { prev_word_boundary(&self.text, self.cursor) } else { prev_grapheme_boundary(&self.text, self.cursor) };
+                start..self.cursor
+            },
+        };
+        self.delete_range(range)
+    }
+
+    /// Deletes the selection or the grapheme (or word) after the caret (Delete).
+    /// Returns whether the text changed.
+    pub fn delete_forward(&mut self, word: bool) -> bool {
+        let range = match self.selection() {
+            Some(selection) => selection,
+            None => {
+                let end = if word { next_word_boundary(&self.text, self.cursor) } else { next_grapheme_boundary(&self.text, self.cursor) };
+                self.cursor..end
+            },
+        };
+        self.delete_range(range)
+    }
+
+    fn delete_range(&mut self, range: Range<usize>) -> bool {
+        self.selection_anchor = None;
+        if range.start == range.end {
+            return false;
+        }
+        self.text.replace_range(range.clone(), "");
+        self.cursor = range.start;
+        true
+    }
 }
 
-impl TextInput {
+impl<T: Layout> TextInput<T> {
 
     pub fn new() -> Self {
-        TextInput { on_text_input_callback: None }
+        TextInput {
+            on_text_input_callback: None,
+            placeholder: None,
+            password: false,
+            on_change: None,
+            on_submit: None,
+        }
     }
 
-    pub fn bind<T: Layout>(self, window: &mut FakeWindow<T>, field: &TextInputState, data: &T) -> Self {
+    /// Text that is shown (with the `__azul-native-input-text-placeholder` class) while the field is empty
+    pub fn with_placeholder<S: Into<String>>(self, placeholder: S) -> Self {
+        Self { placeholder: Some(placeholder.into()), .. self }
+    }
+
+    /// Shows a `PASSWORD_MASK` instead of every grapheme
+    pub fn with_password(self, password: bool) -> Self {
+        Self { password, .. self }
+    }
+
+    /// Called after the field handled a `TextInput` or `VirtualKeyDown` event - check
+    /// `TextInputState::last_event()` for `Some(TextInputEvent::Changed)`, since callbacks
+    /// are attached to events, not to the changes the field makes.
+    pub fn on_change(self, callback: Callback<T>) -> Self {
+        Self { on_change: Some(callback), .. self }
+    }
+
+    /// Called after the field handled a `VirtualKeyDown` event - check
+    /// `TextInputState::last_event()` for `Some(TextInputEvent::Submitted)`, i.e. Enter was pressed.
+    pub fn on_submit(self, callback: Callback<T>) -> Self {
+        Self { on_submit: Some(callback), .. self }
+    }
+
+    pub fn bind(self, window: &mut FakeWindow<T>, field: &TextInputState, data: &T) -> Self {
         let ptr = StackCheckedPointer::new(data, field);
         let on_text_input_callback = ptr.and_then(|ptr|{
-            Some((
-                window.add_callback(ptr, DefaultCallback(TextInputState::on_text_input_private)),
-                window.add_callback(ptr, DefaultCallback(TextInputState::on_virtual_key_down_private))
-            ))
+            Some(TextInputCallbacks {
+                text_input: window.add_callback(ptr, DefaultCallback(TextInputState::on_text_input_private)),
+                virtual_key_down: window.add_callback(ptr, DefaultCallback(TextInputState::on_virtual_key_down_private)),
+                mouse_down: window.add_callback(ptr, DefaultCallback(TextInputState::on_mouse_down_private)),
+                mouse_over: window.add_callback(ptr, DefaultCallback(TextInputState::on_mouse_over_private)),
+                mouse_up: window.add_callback(ptr, DefaultCallback(TextInputState::on_mouse_up_private)),
+            })
         });
 
         Self {
@@ -78,20 +290,64 @@ impl TextInput {
         }
     }
 
-    pub fn dom<T: Layout>(&self, field: &TextInputState) -> Dom<T> {
+    pub fn dom(&self, field: &TextInputState) -> Dom<T> {
 
         let mut parent_div =
             Dom::div()
             .with_class("__azul-native-input-text")
             .with_tab_index(TabIndex::Auto);
 
-        if let Some((text_input_callback, vk_callback)) = self.on_text_input_callback {
-            parent_div.add_default_callback_id(EventFilter::Focus(FocusEventFilter::TextInput), text_input_callback);
-            parent_div.add_default_callback_id(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), vk_callback);
+        if let Some(callbacks) = self.on_text_input_callback {
+            parent_div.add_default_callback_id(EventFilter::Focus(FocusEventFilter::TextInput), callbacks.text_input);
+            parent_div.add_default_callback_id(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), callbacks.virtual_key_down);
+            parent_div.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseDown), callbacks.mouse_down);
+            parent_div.add_default_callback_id(EventFilter::Hover(HoverEventFilter::MouseOver), callbacks.mouse_over);
+            parent_div.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseUp), callbacks.mouse_up);
+        }
+
+        if let Some(on_change) = self.on_change {
+            parent_div.add_callback(EventFilter::Focus(FocusEventFilter::TextInput), on_change);
+            parent_div.add_callback(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), on_change);
+        }
+
+        if let Some(on_submit) = self.on_submit {
+            parent_div.add_callback(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), on_submit);
+        }
+
+        let mut content = Dom::div()
+            .with_class("__azul-native-input-text-content")
+            .with_css_override(SCROLL_CSS_ID, CssProperty::Margin(LayoutMargin { left: Some(PixelValue::px(-field.scroll_x)), .. LayoutMargin::default() }));
+
+        let selection = field.selection();
+        let boundaries = grapheme_boundaries(&field.text);
+
+        for (idx, start) in boundaries.iter().enumerate() {
+            let end = boundaries.get(idx + 1).cloned().unwrap_or(field.text.len());
+            if *start == end {
+                continue; // end of the text
+            }
+            if *start == field.cursor {
+                content.add_child(Dom::div().with_class("__azul-native-input-text-cursor"));
+            }
+            let grapheme = if self.password { PASSWORD_MASK } else { &field.text[*start..end] };
+            let mut label = Dom::label(grapheme).with_class("__azul-native-input-text-char");
+            if selection.as_ref().map(|s| s.start <= *start && end <= s.end).unwrap_or(false) {
+                label.add_class("__azul-native-input-text-selected");
+            }
+            content.add_child(label);
+        }
+
+        if field.cursor >= field.text.len() {
+            content.add_child(Dom::div().with_class("__azul-native-input-text-cursor"));
         }
 
-        let label = Dom::label(field.text.clone()).with_class("__azul-native-input-text-label");
-        parent_div.with_child(label)
+        if field.text.is_empty() {
+            if let Some(placeholder) = &self.placeholder {
+                content.add_child(Dom::label(placeholder.clone()).with_class("__azul-native-input-text-placeholder"));
+            }
+        }
+
+        parent_div.with_child(content)
     }
 }
 
@@ -105,138 +361,417 @@ impl TextInputState {
         unsafe { data.invoke_mut(Self::on_text_input, app_state_no_data, window_event) }
     }
 
-    pub fn on_virtual_key_down<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    fn on_mouse_down_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_mouse_down, app_state_no_data, window_event) }
+    }
 
-        let keyboard_state = app_state_no_data.windows[event.window_id].get_keyboard_state();
+    fn on_mouse_over_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_mouse_over, app_state_no_data, window_event) }
+    }
 
-        match keyboard_state.latest_virtual_keycode {
-            Some(VirtualKeyCode::Back) => {
-                // TODO: shift + back = delete last word
-                let selection = self.selection.clone();
-                match selection {
-                    None => {
-                        if self.cursor == self.text.len() {
-                            self.text.pop();
-                        } else {
-                            let mut a = self.text.chars().take(self.cursor).collect::<String>();
-                            let new = self.text.len().min(self.cursor.saturating_add(1));
-                            a.extend(self.text.chars().skip(new));
-                            self.text = a;
-                        }
-                        self.cursor = self.cursor.saturating_sub(1);
-                    },
-                    Some(Selection::All) => {
-                        self.text.clear();
-                        self.cursor = 0;
-                        self.selection = None;
-                    },
-                    Some(Selection::FromTo(range)) => {
-                        delete_selection(self, range, None);
-                    },
-                }
+    fn on_mouse_up_private<T: Layout>(data: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, window_event: &mut CallbackInfo<T>) -> UpdateScreen {
+        unsafe { data.invoke_mut(Self::on_mouse_up, app_state_no_data, window_event) }
+    }
 
-                Redraw
-            },
+    pub fn on_virtual_key_down<T: Layout>(&mut self, mut app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+
+        let keyboard_state = app_state_no_data.windows[event.window_id].get_keyboard_state().clone();
+        let (ctrl, shift) = (keyboard_state.ctrl_down, keyboard_state.shift_down);
+        let mut changed = false;
+
+        self.last_event = None;
+
+        match keyboard_state.latest_virtual_keycode {
+            Some(VirtualKeyCode::Back) => changed = self.delete_backward(ctrl),
+            Some(VirtualKeyCode::Delete) => changed = self.delete_forward(ctrl),
+            Some(VirtualKeyCode::Left) => self.move_left(ctrl, shift),
+            Some(VirtualKeyCode::Right) => self.move_right(ctrl, shift),
+            Some(VirtualKeyCode::Home) => self.set_cursor(0, shift),
+            Some(VirtualKeyCode::End) => { let end = self.text.len(); self.set_cursor(end, shift) },
+            Some(VirtualKeyCode::Escape) => self.selection_anchor = None,
             Some(VirtualKeyCode::Return) => {
-                // TODO: selection!
-                self.text.push('\n');
-                self.cursor = self.cursor.saturating_add(1);
-                /*
-                match self.selection {
-                    None => {  },
-                }
-                */
-                Redraw
-            },
-            Some(VirtualKeyCode::Home) => {
-                self.cursor = 0;
-                self.selection = None;
-                Redraw
-            },
-            Some(VirtualKeyCode::End) => {
-                self.cursor = self.text.len();
-                self.selection = None;
-                Redraw
-            },
-            Some(VirtualKeyCode::A) if keyboard_state.ctrl_down => {
-                self.selection = Some(Selection::All);
-                Redraw
-            },
-            Some(VirtualKeyCode::Escape) => {
-                self.selection = None;
-                Redraw
+                self.last_event = Some(TextInputEvent::Submitted);
+                return Redraw;
             },
-            Some(VirtualKeyCode::Right) => {
-                self.cursor = self.text.len().min(self.cursor.saturating_add(1));
-                Redraw
-            },
-            Some(VirtualKeyCode::Left) => {
-                self.cursor = (0.max(self.cursor.saturating_sub(1))).min(self.cursor.saturating_add(1));
-                Redraw
+            Some(VirtualKeyCode::A) if ctrl => self.select_all(),
+            Some(VirtualKeyCode::C) if ctrl => {
+                if let Some(selected) = self.selected_text() {
+                    let _ = app_state_no_data.resources.set_clipboard_string(selected.to_string());
+                }
+                return DontRedraw;
             },
-            Some(VirtualKeyCode::C) => {
-                // TODO: copy
-                DontRedraw
+            Some(VirtualKeyCode::X) if ctrl => {
+                if let Some(selected) = self.selected_text().map(|s| s.to_string()) {
+                    let _ = app_state_no_data.resources.set_clipboard_string(selected);
+                    changed = self.delete_backward(false);
+                }
             },
-            Some(VirtualKeyCode::V) => {
-                // TODO: paste
-                DontRedraw
+            Some(VirtualKeyCode::V) if ctrl => {
+                if let Ok(contents) = app_state_no_data.resources.get_clipboard_string() {
+                    changed = self.insert_text(&contents);
+                }
             },
-            _ => DontRedraw,
+            _ => return DontRedraw,
+        }
+
+        if changed {
+            self.last_event = Some(TextInputEvent::Changed);
         }
+
+        self.scroll_to_cursor(&app_state_no_data, event);
+        Redraw
     }
 
     pub fn on_text_input<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
 
         let keyboard_state = app_state_no_data.windows[event.window_id].get_keyboard_state();
 
-        match keyboard_state.current_char {
-            Some(c) => {
-                let selection = self.selection.clone();
-                match selection {
-                    None => {
-                        if self.cursor == self.text.len() {
-                            self.text.push(c);
-                        } else {
-                            // TODO: insert character at the cursor location!
-                            self.text.push(c);
-                        }
-                        self.cursor = self.cursor.saturating_add(1);
-                    },
-                    Some(Selection::All) => {
-                        self.text = format!("{}", c);
-                        self.cursor = 1;
-                        self.selection = None;
-                    },
-                    Some(Selection::FromTo(range)) => {
-                        delete_selection(self, range, Some(c));
-                    },
-                }
-                Redraw
-            },
-            None => DontRedraw,
+        self.last_event = None;
+
+        // Ctrl+A etc. are handled in on_virtual_key_down, Backspace arrives as '\u{8}'
+        if keyboard_state.ctrl_down {
+            return DontRedraw;
+        }
+
+        let c = match keyboard_state.current_char {
+            Some(c) if !c.is_control() => c,
+            _ => return DontRedraw,
+        };
+
+        if !self.insert_text(&c.to_string()) {
+            return DontRedraw;
+        }
+
+        self.last_event = Some(TextInputEvent::Changed);
+        self.scroll_to_cursor(&app_state_no_data, event);
+        Redraw
+    }
+
+    pub fn on_mouse_down<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        let shift = app_state_no_data.windows[event.window_id].get_keyboard_state().shift_down;
+        let cursor = match self.cursor_at_mouse(&app_state_no_data, event) {
+            Some(cursor) => cursor,
+            None => return DontRedraw,
+        };
+        self.set_cursor(cursor, shift);
+        if !shift {
+            self.selection_anchor = Some(cursor);
+        }
+        self.is_selecting_with_mouse = true;
+        self.scroll_to_cursor(&app_state_no_data, event);
+        Redraw
+    }
+
+    pub fn on_mouse_over<T: Layout>(&mut self, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+        if !self.is_selecting_with_mouse {
+            return DontRedraw;
+        }
+        // The button was released outside of the field
+        if !app_state_no_data.windows[event.window_id].get_mouse_state().left_down {
+            self.is_selecting_with_mouse = false;
+            return DontRedraw;
         }
+        let cursor = match self.cursor_at_mouse(&app_state_no_data, event) {
+            Some(cursor) => cursor,
+            None => return DontRedraw,
+        };
+        if cursor == self.cursor {
+            return DontRedraw;
+        }
+        self.set_cursor(cursor, true);
+        self.scroll_to_cursor(&app_state_no_data, event);
+        Redraw
+    }
+
+    pub fn on_mouse_up<T: Layout>(&mut self, _app_state_no_data: AppStateNoData<T>, _event: &mut CallbackInfo<T>) -> UpdateScreen {
+        self.is_selecting_with_mouse = false;
+        DontRedraw
+    }
+
+    /// Returns the byte offset of the caret position under the mouse cursor
+    fn cursor_at_mouse<T: Layout>(&self, app_state_no_data: &AppStateNoData<T>, event: &CallbackInfo<T>) -> Option<usize> {
+        let (mouse_x, _) = event.cursor_in_viewport?;
+        let (content_rect, grapheme_rects) = get_grapheme_rects(app_state_no_data, event)?;
+        let grapheme_idx = grapheme_index_at_x(&grapheme_rects, mouse_x - content_rect.origin.x);
+        let boundaries = grapheme_boundaries(&self.text);
+        Some(boundaries.get(grapheme_idx).cloned().unwrap_or(self.text.len()))
+    }
+
+    /// Updates the `scroll_x`, so that the caret is visible. The positions of the
+    /// graphemes are taken from the last frame, graphemes that were just typed are
+    /// estimated with the average width of the other graphemes.
+    fn scroll_to_cursor<T: Layout>(&mut self, app_state_no_data: &AppStateNoData<T>, event: &CallbackInfo<T>) {
+        let field_rect = match app_state_no_data.windows[event.window_id].get_layout_snapshot().get_rect(event.hit_dom_node) {
+            Some(rect) => rect,
+            None => return,
+        };
+        let grapheme_rects = match get_grapheme_rects(app_state_no_data, event) {
+            Some((_, rects)) => rects,
+            None => return,
+        };
+        let cursor_grapheme = grapheme_count(&self.text[..self.cursor]);
+        let cursor_x = caret_x(&grapheme_rects, cursor_grapheme);
+        self.scroll_x = scroll_to_show(self.scroll_x, field_rect.size.width, cursor_x);
     }
 }
 
-fn delete_selection(state: &mut TextInputState, selection: Range<usize>, new_text: Option<char>) {
-    let Range { start, end } = selection;
-    let max = if end > state.text.len() { state.text.len() } else { end };
+/// Returns the rectangle of the content div of the field (the target of the callback) and
+/// the (x, width) of every grapheme label relative to it, from the layout of the last frame
+fn get_grapheme_rects<T: Layout>(app_state_no_data: &AppStateNoData<T>, event: &CallbackInfo<T>) -> Option<(LayoutRect, Vec<(f32, f32)>)> {
+    let layout = app_state_no_data.windows[event.window_id].get_layout_snapshot();
+    let node_hierarchy = event.get_node_hierarchy();
+    let content: NodeId = event.hit_dom_node.children(node_hierarchy).next()?;
+    let content_rect = layout.get_rect(content)?;
+    let rects = content.children(node_hierarchy)
+        .filter(|child| event.get_node_content(*child).map(|n| n.has_class("__azul-native-input-text-char")).unwrap_or(false))
+        .filter_map(|child| layout.get_rect(child))
+        .map(|rect| (rect.origin.x - content_rect.origin.x, rect.size.width))
+        .collect();
+    Some((content_rect, rects))
+}
 
-    let mut cur = start;
-    if max == state.text.len() {
-        state.text.truncate(start);
+/// Returns the index of the grapheme boundary closest to `x`, given the (x, width)
+/// of every grapheme: clicking on the right half of a grapheme puts the caret after it
+fn grapheme_index_at_x(grapheme_rects: &[(f32, f32)], x: f32) -> usize {
+    grapheme_rects.iter()
+        .position(|(start, width)| x < start + width / 2.0)
+        .unwrap_or(grapheme_rects.len())
+}
+
+/// Returns the x position of the caret in front of the grapheme `grapheme_idx`
+fn caret_x(grapheme_rects: &[(f32, f32)], grapheme_idx: usize) -> f32 {
+    if grapheme_idx == 0 {
+        return 0.0;
+    }
+    match grapheme_rects.get(grapheme_idx - 1) {
+        Some((start, width)) => start + width,
+        None => {
+            let known_width = grapheme_rects.last().map(|(start, width)| start + width).unwrap_or(0.0);
+            let average_width = if grapheme_rects.is_empty() { 0.0 } else { known_width / grapheme_rects.len() as f32 };
+            known_width + average_width * (grapheme_idx - grapheme_rects.len()) as f32
+        },
+    }
+}
+
+/// Returns the new scroll offset so that `x` is visible in a field of `width`
+fn scroll_to_show(scroll_x: f32, width: f32, x: f32) -> f32 {
+    if x < scroll_x {
+        x
+    } else if x > scroll_x + width {
+        x - width
     } else {
-        let mut a = state.text.chars().take(start).collect::<String>();
+        scroll_x
+    }
+}
+
+// Grapheme segmentation: approximates the extended grapheme clusters of UAX #29 -
+// combining marks, variation selectors, emoji modifiers, ZWJ sequences, flags and CRLF
+// stay together, which is enough for caret movement in a single-line field.
+
+fn is_grapheme_extend(c: char) -> bool {
+    match c as u32 {
+        0x0300..=0x036F | 0x0483..=0x0489 | 0x0591..=0x05BD | 0x0610..=0x061A | 0x064B..=0x065F |
+        0x0E31 | 0x0E34..=0x0E3A | 0x0E47..=0x0E4E |
+        0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x200C | 0x200D | 0x20D0..=0x20FF |
+        0xFE00..=0xFE0F | 0xFE20..=0xFE2F | 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F | 0xE0100..=0xE01EF => true,
+        _ => false,
+    }
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    match c as u32 {
+        0x1F1E6..=0x1F1FF => true,
+        _ => false,
+    }
+}
 
-        if let Some(new) = new_text {
-            a.push(new);
-            cur += 1;
+/// Returns the byte offset of the next grapheme boundary after `idx` (or the length of the text)
+fn next_grapheme_boundary(text: &str, idx: usize) -> usize {
+    let mut chars = match text.get(idx..) {
+        Some(rest) => rest.char_indices().map(|(i, c)| (idx + i, c)).peekable(),
+        None => return text.len(),
+    };
+    let first = match chars.next() {
+        Some((_, c)) => c,
+        None => return text.len(),
+    };
+    let mut previous = first;
+    let mut regional_indicators = if is_regional_indicator(first) { 1 } else { 0 };
+    while let Some(&(i, c)) = chars.peek() {
+        let joins = if first == '\r' {
+            c == '\n' && previous == '\r'
+        } else if is_regional_indicator(c) {
+            regional_indicators == 1 && previous == first
+        } else {
+            is_grapheme_extend(c) || previous == '\u{200D}'
+        };
+        if !joins {
+            return i;
+        }
+        if is_regional_indicator(c) {
+            regional_indicators += 1;
         }
+        previous = c;
+        chars.next();
+    }
+    text.len()
+}
+
+/// Returns the byte offsets where the graphemes of the text start
+fn grapheme_boundaries(text: &str) -> Vec<usize> {
+    let mut boundaries = Vec::new();
+    let mut idx = 0;
+    while idx < text.len() {
+        boundaries.push(idx);
+        idx = next_grapheme_boundary(text, idx);
+    }
+    boundaries
+}
+
+fn grapheme_count(text: &str) -> usize {
+    grapheme_boundaries(text).len()
+}
+
+/// Returns the grapheme boundary at or before `idx`
+fn floor_grapheme_boundary(text: &str, idx: usize) -> usize {
+    if idx >= text.len() {
+        return text.len();
+    }
+    grapheme_boundaries(text).into_iter().take_while(|b| *b <= idx).last().unwrap_or(0)
+}
+
+/// Returns the grapheme boundary before `idx`
+fn prev_grapheme_boundary(text: &str, idx: usize) -> usize {
+    grapheme_boundaries(text).into_iter().take_while(|b| *b < idx).last().unwrap_or(0)
+}
+
+/// Ctrl+Left: skips the whitespace before the caret, then the word before it
+fn prev_word_boundary(text: &str, idx: usize) -> usize {
+    let boundaries = grapheme_boundaries(text);
+    let mut graphemes = boundaries.iter().rev().filter(|b| **b < idx).peekable();
+    let mut position = idx;
+    while let Some(&&b) = graphemes.peek() {
+        if !text[b..].starts_with(char::is_whitespace) { break; }
+        position = b;
+        graphemes.next();
+    }
+    while let Some(&&b) = graphemes.peek() {
+        if text[b..].starts_with(char::is_whitespace) { break; }
+        position = b;
+        graphemes.next();
+    }
+    position
+}
+
+/// Ctrl+Right: skips the rest of the word after the caret, then the whitespace after it
+fn next_word_boundary(text: &str, idx: usize) -> usize {
+    let mut position = idx;
+    while position < text.len() && !text[position..].starts_with(char::is_whitespace) {
+        position = next_grapheme_boundary(text, position);
+    }
+    while position < text.len() && text[position..].starts_with(char::is_whitespace) {
+        position = next_grapheme_boundary(text, position);
+    }
+    position
+}
+
+#[test]
+fn test_caret_moves_over_whole_graphemes() {
 
-        a.extend(state.text.chars().skip(end));
-        state.text = a;
+    // "e" + combining acute, a family emoji (ZWJ sequence), a flag (two regional indicators), CRLF
+    let text = "ae\u{301}\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{1F1E9}\u{1F1EA}\r\nz";
+    let boundaries = grapheme_boundaries(text);
+    let graphemes = boundaries.iter().enumerate()
+        .map(|(i, start)| &text[*start..boundaries.get(i + 1).cloned().unwrap_or(text.len())])
+        .collect::<Vec<_>>();
+    assert_eq!(graphemes, vec![
+        "a", "e\u{301}", "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}", "\u{1F1E9}\u{1F1EA}", "\r\n", "z",
+    ]);
+
+    let mut state = TextInputState::new(text);
+    let mut positions = vec![state.cursor];
+    for _ in 0..7 {
+        state.move_left(false, false);
+        positions.push(state.cursor);
     }
+    let mut expected = boundaries.clone();
+    expected.push(text.len());
+    expected.reverse();
+    expected.push(0);
+    assert_eq!(positions, expected);
+
+    // Positions inside of a grapheme snap to its start
+    state.set_cursor(boundaries[2] + 1, false);
+    assert_eq!(state.cursor, boundaries[2]);
+
+    // Backspace deletes the whole emoji sequence, Delete the whole "e" + accent
+    state.set_cursor(boundaries[3], false);
+    assert!(state.delete_backward(false));
+    assert!(state.delete_backward(false));
+    assert_eq!(state.text, "a\u{1F1E9}\u{1F1EA}\r\nz");
+    assert_eq!(state.cursor, 1);
+    assert!(state.delete_forward(false));
+    assert_eq!(state.text, "a\r\nz");
+}
+
+#[test]
+fn test_select_all_type_delete_and_max_length() {
+
+    let mut state = TextInputState::new("hello world").with_max_length(9);
+
+    // Ctrl+Left / Ctrl+Shift+Left
+    state.move_left(true, false);
+    assert_eq!(state.cursor, 6);
+    state.move_left(true, true);
+    assert_eq!(state.selected_text(), Some("hello "));
+
+    // Typing replaces the selection
+    assert!(state.insert_text("\u{8}bye "));
+    assert_eq!(state.text, "bye world");
+    assert_eq!(state.cursor, 4);
+    assert_eq!(state.selection(), None);
 
-    state.cursor = cur;
-}
\ No newline at end of file
+    // The text is already longer than max_length, nothing can be inserted
+    assert!(!state.insert_text("x"));
+
+    state.select_all();
+    assert_eq!(state.selected_text(), Some("bye world"));
+    assert!(state.insert_text("0123456789"));
+    assert_eq!(state.text, "012345678");
+
+    state.select_all();
+    assert!(state.delete_backward(false));
+    assert_eq!(state.text, "");
+    assert!(!state.delete_backward(false));
+
+    // Ctrl+Backspace deletes the last word
+    let mut state = TextInputState::new("one two  ");
+    assert!(state.delete_backward(true));
+    assert_eq!(state.text, "one ");
+
+    // Left without shift collapses the selection to its start
+    let mut state = TextInputState::new("abc");
+    state.set_cursor(1, true);
+    state.move_left(false, false);
+    assert_eq!((state.cursor, state.selection()), (1, None));
+}
+
+#[test]
+fn test_mouse_positioning_and_scrolling() {
+
+    let rects = vec![(0.0, 10.0), (10.0, 6.0), (16.0, 10.0)];
+    assert_eq!(grapheme_index_at_x(&rects, -5.0), 0);
+    assert_eq!(grapheme_index_at_x(&rects, 4.0), 0);
+    assert_eq!(grapheme_index_at_x(&rects, 6.0), 1);
+    assert_eq!(grapheme_index_at_x(&rects, 14.0), 2);
+    assert_eq!(grapheme_index_at_x(&rects, 100.0), 3);
+
+    assert_eq!(caret_x(&rects, 0), 0.0);
+    assert_eq!(caret_x(&rects, 2), 16.0);
+    // One grapheme that wasn't laid out yet, estimated with the average width
+    assert!((caret_x(&rects, 4) - (26.0 + 26.0 / 3.0)).abs() < 0.001);
+
+    assert_eq!(scroll_to_show(0.0, 20.0, 16.0), 0.0);
+    assert_eq!(scroll_to_show(0.0, 20.0, 26.0), 6.0);
+    assert_eq!(scroll_to_show(6.0, 20.0, 0.0), 0.0);
+}
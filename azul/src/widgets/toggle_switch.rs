@@ -0,0 +1,103 @@
+//! On / off switch with a label, bound to a `CheckBoxState` in the application data
+//!
+//! Behaves like a `CheckBox` (click, Space or Enter toggles it), but is drawn as a
+//! track with a sliding thumb. The DOM has the following classes:
+//!
+//! - `azul-toggle-switch`: the focusable container, additionally `azul-toggle-switch-on`
+//!   if the switch is on and `azul-toggle-switch-disabled` if it is disabled
+//! - `azul-toggle-switch-track`: the track, contains the `azul-toggle-switch-thumb`
+//! - `azul-toggle-switch-label`: the label next to the switch
+
+use {
+    traits::Layout,
+    dom::{Dom, Callback},
+    window::FakeWindow,
+    widgets::check_box::{CheckBoxState, ToggleCallbacks},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToggleSwitch<T: Layout> {
+    label: String,
+    disabled: bool,
+    on_toggle: Option<Callback<T>>,
+    callbacks: Option<ToggleCallbacks>,
+}
+
+impl<T: Layout> ToggleSwitch<T> {
+
+    pub fn new<S: Into<String>>(label: S) -> Self {
+        ToggleSwitch {
+            label: label.into(),
+            disabled: false,
+            on_toggle: None,
+            callbacks: None,
+        }
+    }
+
+    /// A disabled switch can't be focused or toggled and has the `azul-toggle-switch-disabled` class
+    pub fn with_disabled(self, disabled: bool) -> Self {
+        Self { disabled, .. self }
+    }
+
+    /// Called after the switch handled a click or a key press - check
+    /// `CheckBoxState::was_toggled()`, see `CheckBox::on_toggle`
+    pub fn on_toggle(self, callback: Callback<T>) -> Self {
+        Self { on_toggle: Some(callback), .. self }
+    }
+
+    pub fn bind(self, window: &mut FakeWindow<T>, field: &CheckBoxState, data: &T) -> Self {
+        Self {
+            callbacks: ToggleCallbacks::register(window, field, data),
+            .. self
+        }
+    }
+
+    pub fn dom(&self, field: &CheckBoxState) -> Dom<T> {
+
+        let mut container = Dom::div().with_class("azul-toggle-switch");
+
+        if field.checked {
+            container.add_class("azul-toggle-switch-on");
+        }
+
+        if self.disabled {
+            container.add_class("azul-toggle-switch-disabled");
+        } else {
+            ToggleCallbacks::attach(self.callbacks, self.on_toggle, &mut container);
+        }
+
+        container
+            .with_child(Dom::div().with_class("azul-toggle-switch-track")
+                .with_child(Dom::div().with_class("azul-toggle-switch-thumb")))
+            .with_child(Dom::label(self.label.clone()).with_class("azul-toggle-switch-label"))
+    }
+}
+
+#[test]
+fn test_toggle_switch_dom_and_disabled_state() {
+
+    use dom::TabIndex;
+
+    struct TestLayout { }
+
+    impl Layout for TestLayout {
+        fn layout(&self) -> Dom<Self> {
+            Dom::div()
+        }
+    }
+
+    let off = CheckBoxState::new(false);
+    let on = CheckBoxState::new(true);
+
+    let dom = ToggleSwitch::<TestLayout>::new("Dark mode").dom(&off);
+    let root = dom.get_node_data(dom.get_root()).unwrap();
+    assert_eq!(root.classes, vec!["azul-toggle-switch".to_string()]);
+    assert_eq!(root.tab_index, Some(TabIndex::Auto));
+
+    let dom = ToggleSwitch::<TestLayout>::new("Dark mode").with_disabled(true).dom(&on);
+    let root = dom.get_node_data(dom.get_root()).unwrap();
+    assert!(root.classes.contains(&"azul-toggle-switch-on".to_string()));
+    assert!(root.classes.contains(&"azul-toggle-switch-disabled".to_string()));
+    assert_eq!(root.tab_index, None);
+    assert!(root.callbacks.is_empty() && root.default_callback_ids.is_empty());
+}
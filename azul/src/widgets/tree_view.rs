@@ -0,0 +1,767 @@
+//! Tree view with expandable nodes, selection, keyboard navigation and virtualized rows
+//!
+//! The application provides the nodes by implementing `TreeSource` on the struct that
+//! holds both the data and the `TreeViewState`:
+//!
+//! ```rust,ignore
+//! impl TreeSource<MyApp> for FileBrowser {
+//!     fn tree_state(&self) -> &TreeViewState { &self.tree }
+//!     fn tree_state_mut(&mut self) -> &mut TreeViewState { &mut self.tree }
+//!     fn children(&self, parent: Option<u64>) -> TreeChildren {
+//!         match self.directories.get(&parent) {
+//!             Some(entries) => TreeChildren::Loaded(entries.clone()),
+//!             None => TreeChildren::Loading,
+//!         }
+//!     }
+//! }
+//!
+//! TreeView::new()
+//!     .on_expand(Callback(load_directory))
+//!     .dom(&self.files, &self, info.window)
+//! ```
+//!
+//! Only the children of expanded nodes are requested. Children that aren't available yet
+//! (for example while a `Task` reads a directory) are shown as a loading row: start the
+//! loading in the `on_expand` callback (`TreeViewState::expanded_node()` is the node that
+//! was expanded) and return `TreeChildren::Loading` until it is done.
+//!
+//! Clicking a row selects it (Ctrl + click toggles it, Shift + click selects the range from
+//! the last clicked row), clicking the expander expands / collapses the node. While the tree
+//! view is focused, Up / Down / Home / End move the selection (Shift extends it, Ctrl only
+//! moves the cursor), Right expands the node or moves to its first child, Left collapses the
+//! node or moves to its parent, Enter expands / collapses the node and typing the start of a
+//! label selects the next node with that label.
+//!
+//! The rows are rendered in an IFrame, so only the visible rows are built. The DOM has
+//! the following classes:
+//!
+//! - `azul-tree-view`: the focusable container, contains the `azul-tree-body` IFrame
+//! - `azul-tree-rows`: the rows, scrolled with the dynamic `margin-top` property `__azul-tree-scroll`
+//! - `azul-tree-row`: a row, additionally `azul-tree-row-selected`, `azul-tree-row-cursor`
+//!   (the row that the keyboard moves from) and `azul-tree-row-loading` (the loading row)
+//! - `azul-tree-indent`: one per level of the row, for indentation and indent guides
+//! - `azul-tree-expander`: the arrow of a node with children, additionally `azul-tree-expander-expanded`
+//!   (or `azul-tree-expander-leaf` for nodes without children, which doesn't show an arrow)
+//! - `azul-tree-label`: the label of a node, `azul-tree-spinner` is the label of the loading row
+
+use std::{ops::Range, collections::BTreeSet, time::{Duration, Instant}};
+use azul_css::{CssProperty, LayoutMargin, PixelValue};
+use {
+    dom::{
+        Dom, Callback, NodeType, IFrameCallback, EventFilter, FocusEventFilter, HoverEventFilter,
+        UpdateScreen, Redraw, DontRedraw, TabIndex,
+    },
+    app_state::AppStateNoData,
+    traits::Layout,
+    prelude::VirtualKeyCode,
+    default_callbacks::{StackCheckedPointer, DefaultCallback},
+    window::{LayoutInfo, HidpiAdjustedBounds, CallbackInfo, FakeWindow},
+    focus::FocusTarget,
+};
+
+/// ID of the dynamic CSS property that scrolls the rows
+pub const SCROLL_CSS_ID: &str = "__azul-tree-scroll";
+
+/// Width of an `azul-tree-indent`, has to match the stylesheet
+pub const INDENT_WIDTH: f32 = 16.0;
+
+/// Width of an `azul-tree-expander`, has to match the stylesheet
+pub const EXPANDER_WIDTH: f32 = 16.0;
+
+/// Typed characters that are less than this many milliseconds apart are matched as one word
+const TYPE_AHEAD_TIMEOUT_MS: u64 = 1000;
+
+/// Provides the nodes of a `TreeView`
+pub trait TreeSource<T: Layout> {
+    /// Returns the expanded and selected nodes and the scroll position of the tree
+    fn tree_state(&self) -> &TreeViewState;
+    /// Same as `tree_state`, used by the tree view to update the state on user input
+    fn tree_state_mut(&mut self) -> &mut TreeViewState;
+    /// Returns the children of the node (`None` for the top-level nodes), only called for
+    /// expanded nodes. The IDs of the nodes have to be unique in the whole tree.
+    fn children(&self, parent: Option<u64>) -> TreeChildren;
+}
+
+/// Node of a `TreeView`, as returned by `TreeSource::children`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TreeNodeDesc {
+    pub id: u64,
+    pub label: String,
+    /// Whether the node can be expanded. The children are only requested when the node
+    /// is expanded, so this can be `true` for a directory that turns out to be empty.
+    pub has_children: bool,
+}
+
+impl TreeNodeDesc {
+
+    /// A node that can be expanded
+    pub fn branch<S: Into<String>>(id: u64, label: S) -> Self {
+        Self { id, label: label.into(), has_children: true }
+    }
+
+    /// A node without children
+    pub fn leaf<S: Into<String>>(id: u64, label: S) -> Self {
+        Self { id, label: label.into(), has_children: false }
+    }
+}
+
+/// Children of a node, see `TreeSource::children`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TreeChildren {
+    Loaded(Vec<TreeNodeDesc>),
+    /// The children are still being loaded, the tree shows a loading row
+    Loading,
+}
+
+/// Visible row of a `TreeView`, see `flatten_visible_rows`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TreeRow {
+    pub depth: usize,
+    pub parent: Option<u64>,
+    /// `None` for the loading row of a node whose children are still loading
+    pub node: Option<TreeNodeDesc>,
+    pub expanded: bool,
+}
+
+impl TreeRow {
+    /// Returns the ID of the node, `None` for a loading row
+    pub fn id(&self) -> Option<u64> {
+        self.node.as_ref().map(|node| node.id)
+    }
+}
+
+/// Returns the rows of the tree: the top-level nodes and the (recursive) children of the
+/// expanded nodes, in depth-first order. A node that is reached twice (because `children`
+/// returns a cycle) is only shown once.
+pub fn flatten_visible_rows<F: Fn(Option<u64>) -> TreeChildren>(children: F, expanded: &BTreeSet<u64>) -> Vec<TreeRow> {
+
+    let mut rows = Vec::new();
+    let mut visited = BTreeSet::new();
+    flatten_children(&children, expanded, None, 0, &mut visited, &mut rows);
+    rows
+}
+
+fn flatten_children<F: Fn(Option<u64>) -> TreeChildren>(
+    children: &F,
+    expanded: &BTreeSet<u64>,
+    parent: Option<u64>,
+    depth: usize,
+    visited: &mut BTreeSet<u64>,
+    rows: &mut Vec<TreeRow>)
+{
+    let nodes = match children(parent) {
+        TreeChildren::Loaded(nodes) => nodes,
+        TreeChildren::Loading => {
+            rows.push(TreeRow { depth, parent, node: None, expanded: false });
+            return;
+        },
+    };
+
+    for node in nodes {
+        if !visited.insert(node.id) {
+            continue;
+        }
+        let id = node.id;
+        let is_expanded = node.has_children && expanded.contains(&id);
+        rows.push(TreeRow { depth, parent, node: Some(node), expanded: is_expanded });
+        if is_expanded {
+            flatten_children(children, expanded, Some(id), depth + 1, visited, rows);
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TreeView<T: Layout> {
+    on_select: Option<Callback<T>>,
+    on_expand: Option<Callback<T>>,
+}
+
+/// Expanded and selected nodes of a `TreeView`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeViewState {
+    pub expanded: BTreeSet<u64>,
+    pub selection: BTreeSet<u64>,
+    /// Node that Up / Down move from (the last clicked / selected node)
+    pub cursor: Option<u64>,
+    /// Node that Shift + click / Shift + arrow keys select from
+    pub anchor: Option<u64>,
+    /// Height of a row in pixels, has to match the stylesheet
+    pub row_height: f32,
+    /// How far the rows are scrolled down, in pixels
+    pub scroll_offset: f32,
+    /// Height of the tree body in the last frame
+    viewport_height: f32,
+    type_ahead: String,
+    last_type_ahead: Option<Instant>,
+    selection_changed: bool,
+    expanded_node: Option<u64>,
+}
+
+impl Default for TreeViewState {
+    fn default() -> Self {
+        Self {
+            expanded: BTreeSet::new(),
+            selection: BTreeSet::new(),
+            cursor: None,
+            anchor: None,
+            row_height: 20.0,
+            scroll_offset: 0.0,
+            viewport_height: 0.0,
+            type_ahead: String::new(),
+            last_type_ahead: None,
+            selection_changed: false,
+            expanded_node: None,
+        }
+    }
+}
+
+impl TreeViewState {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_row_height(self, row_height: f32) -> Self {
+        Self { row_height, .. self }
+    }
+
+    /// Whether the last event that the tree view handled changed the selection. Use this
+    /// in the `on_select` callback, which is also called for events that didn't change it.
+    pub fn selection_changed(&self) -> bool {
+        self.selection_changed
+    }
+
+    /// The node that was expanded by the last event, use this in the `on_expand` callback
+    /// to start loading the children of the node
+    pub fn expanded_node(&self) -> Option<u64> {
+        self.expanded_node
+    }
+
+    /// Expands the node, returns whether it was collapsed
+    pub fn expand(&mut self, id: u64) -> bool {
+        let changed = self.expanded.insert(id);
+        if changed {
+            self.expanded_node = Some(id);
+        }
+        changed
+    }
+
+    /// Collapses the node, returns whether it was expanded
+    pub fn collapse(&mut self, id: u64) -> bool {
+        self.expanded.remove(&id)
+    }
+
+    fn reset_events(&mut self) {
+        self.selection_changed = false;
+        self.expanded_node = None;
+    }
+
+    /// Moves the cursor to the node: selects only this node, extends the selection from
+    /// the anchor (`shift`) or only moves the cursor (`ctrl`)
+    fn move_cursor(&mut self, id: u64, ctrl: bool, shift: bool, rows: &[TreeRow]) {
+        self.cursor = Some(id);
+        if shift {
+            self.select_range(id, false, rows);
+        } else if !ctrl {
+            self.select_only(id);
+        }
+    }
+
+    fn select_only(&mut self, id: u64) {
+        let previous = self.selection.clone();
+        self.selection.clear();
+        self.selection.insert(id);
+        self.anchor = Some(id);
+        self.selection_changed = self.selection != previous;
+    }
+
+    /// Selects the visible nodes from the anchor to the node
+    /// (the node only, if there is no anchor or it isn't visible)
+    fn select_range(&mut self, id: u64, add: bool, rows: &[TreeRow]) {
+
+        let anchor_index = self.anchor.and_then(|anchor| find_row(rows, anchor));
+        let (anchor_index, index) = match (anchor_index, find_row(rows, id)) {
+            (Some(anchor_index), Some(index)) => (anchor_index, index),
+            _ => return self.select_only(id),
+        };
+
+        let range = if anchor_index <= index { anchor_index..=index } else { index..=anchor_index };
+        let previous = self.selection.clone();
+        if !add {
+            self.selection.clear();
+        }
+        self.selection.extend(rows[range].iter().filter_map(|row| row.id()));
+        self.selection_changed = self.selection != previous;
+    }
+
+    /// Handles a click on the row `index`: on the expander, the node is expanded / collapsed,
+    /// anywhere else, it's selected (with Ctrl / Shift, see the module documentation)
+    fn handle_click(&mut self, index: usize, on_expander: bool, ctrl: bool, shift: bool, rows: &[TreeRow]) -> UpdateScreen {
+
+        let row = rows.get(index)?;
+        let node = row.node.as_ref()?;
+
+        if on_expander && node.has_children {
+            if !self.expand(node.id) {
+                self.collapse(node.id);
+            }
+            return Redraw;
+        }
+
+        self.cursor = Some(node.id);
+
+        if shift {
+            self.select_range(node.id, ctrl, rows);
+        } else if ctrl {
+            if !self.selection.remove(&node.id) {
+                self.selection.insert(node.id);
+            }
+            self.anchor = Some(node.id);
+            self.selection_changed = true;
+        } else {
+            self.select_only(node.id);
+        }
+
+        Redraw
+    }
+
+    fn handle_key(&mut self, key: VirtualKeyCode, ctrl: bool, shift: bool, rows: &[TreeRow]) -> UpdateScreen {
+
+        use self::VirtualKeyCode::*;
+
+        let node_rows = rows.iter().enumerate().filter(|&(_, row)| row.node.is_some()).map(|(index, _)| index).collect::<Vec<_>>();
+        if node_rows.is_empty() {
+            return DontRedraw;
+        }
+
+        let current = self.cursor.and_then(|cursor| find_row(rows, cursor));
+        let position = current.and_then(|current| node_rows.iter().position(|index| *index == current));
+
+        let target = match (key, position) {
+            (Up, Some(position)) => node_rows[position.saturating_sub(1)],
+            (Down, Some(position)) => node_rows[(position + 1).min(node_rows.len() - 1)],
+            (Up, None) | (Down, None) | (Home, _) => node_rows[0],
+            (End, _) => node_rows[node_rows.len() - 1],
+            (Right, Some(_)) | (Left, Some(_)) | (Return, Some(_)) => {
+                let index = current?;
+                let row = &rows[index];
+                let node = row.node.as_ref()?;
+                match key {
+                    Right if node.has_children && !row.expanded => { self.expand(node.id); return Redraw; },
+                    Right => match rows.get(index + 1) {
+                        Some(child) if child.parent == Some(node.id) && child.node.is_some() => index + 1,
+                        _ => return DontRedraw,
+                    },
+                    Left if row.expanded => { self.collapse(node.id); return Redraw; },
+                    Left => find_row(rows, row.parent?)?,
+                    _ if node.has_children => {
+                        if !self.expand(node.id) {
+                            self.collapse(node.id);
+                        }
+                        return Redraw;
+                    },
+                    _ => return DontRedraw,
+                }
+            },
+            _ => return DontRedraw,
+        };
+
+        let id = rows[target].id()?;
+        self.move_cursor(id, ctrl, shift, rows);
+        self.scroll_to_row(target);
+        Redraw
+    }
+
+    fn type_ahead_is_active(&self, now: Instant) -> bool {
+        !self.type_ahead.is_empty() &&
+        self.last_type_ahead.map(|last| now.duration_since(last) < Duration::from_millis(TYPE_AHEAD_TIMEOUT_MS)).unwrap_or(false)
+    }
+
+    /// Selects the next node whose label starts with the typed characters
+    fn handle_type_ahead(&mut self, c: char, now: Instant, rows: &[TreeRow]) -> UpdateScreen {
+
+        if c.is_control() || (c.is_whitespace() && !self.type_ahead_is_active(now)) || rows.is_empty() {
+            return DontRedraw;
+        }
+
+        if !self.type_ahead_is_active(now) {
+            self.type_ahead.clear();
+        }
+        self.type_ahead.extend(c.to_lowercase());
+        self.last_type_ahead = Some(now);
+
+        // A new prefix searches from the row after the cursor, so that typing
+        // the same letter again cycles through the nodes with that letter
+        let current = self.cursor.and_then(|cursor| find_row(rows, cursor));
+        let start = match current {
+            Some(current) if self.type_ahead.chars().count() == 1 => current + 1,
+            Some(current) => current,
+            None => 0,
+        };
+
+        let type_ahead = &self.type_ahead;
+        let found = (0..rows.len())
+            .map(|offset| (start + offset) % rows.len())
+            .find(|index| rows[*index].node.as_ref().map(|node| node.label.to_lowercase().starts_with(type_ahead.as_str())).unwrap_or(false))?;
+
+        let id = rows[found].id()?;
+        self.move_cursor(id, false, false, rows);
+        self.scroll_to_row(found);
+        Redraw
+    }
+
+    /// Scrolls the row into view, using the height of the tree body in the last frame
+    fn scroll_to_row(&mut self, index: usize) {
+        let top = index as f32 * self.row_height;
+        if top < self.scroll_offset {
+            self.scroll_offset = top;
+        } else if top + self.row_height > self.scroll_offset + self.viewport_height {
+            self.scroll_offset = (top + self.row_height - self.viewport_height).max(0.0);
+        }
+    }
+}
+
+fn find_row(rows: &[TreeRow], id: u64) -> Option<usize> {
+    rows.iter().position(|row| row.id() == Some(id))
+}
+
+/// Rows that are (partially) visible in the viewport
+fn visible_row_range(scroll_offset: f32, row_height: f32, viewport_height: f32, row_count: usize) -> Range<usize> {
+    let first = ((scroll_offset / row_height).floor().max(0.0) as usize).min(row_count);
+    let last = ((scroll_offset + viewport_height) / row_height).ceil().max(0.0) as usize;
+    first..last.min(row_count)
+}
+
+fn visible_rows<T: Layout, S: TreeSource<T>>(source: &S) -> Vec<TreeRow> {
+    flatten_visible_rows(|parent| source.children(parent), &source.tree_state().expanded)
+}
+
+impl<T: Layout> TreeView<T> {
+
+    pub fn new() -> Self {
+        Self { on_select: None, on_expand: None }
+    }
+
+    /// Called after the tree view handled a click or a key press - check
+    /// `TreeViewState::selection_changed()`, the callback is attached to the
+    /// events and is also called for events that don't change the selection.
+    pub fn on_select(self, callback: Callback<T>) -> Self {
+        Self { on_select: Some(callback), .. self }
+    }
+
+    /// Called after the tree view handled a click or a key press - check
+    /// `TreeViewState::expanded_node()`, which is `None` if no node was expanded
+    pub fn on_expand(self, callback: Callback<T>) -> Self {
+        Self { on_expand: Some(callback), .. self }
+    }
+
+    pub fn dom<S: TreeSource<T>>(&self, source: &S, data: &T, window: &mut FakeWindow<T>) -> Dom<T> {
+
+        let ptr = match StackCheckedPointer::new(data, source) {
+            Some(ptr) => ptr,
+            None => return Dom::label(
+                "Cannot create tree from a heap-allocated TreeSource, \
+                 please store it directly in the application data"),
+        };
+
+        let mut container = Dom::div().with_class("azul-tree-view").with_tab_index(TabIndex::Auto);
+        container.add_default_callback_id(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), window.add_callback(ptr, DefaultCallback(on_virtual_key_down_private::<T, S>)));
+        container.add_default_callback_id(EventFilter::Focus(FocusEventFilter::TextInput), window.add_callback(ptr, DefaultCallback(on_text_input_private::<T, S>)));
+
+        let mut body = Dom::new(NodeType::IFrame((IFrameCallback(render_body_callback::<T, S>), ptr)))
+            .with_class("azul-tree-body");
+        body.add_default_callback_id(EventFilter::Hover(HoverEventFilter::LeftMouseUp), window.add_callback(ptr, DefaultCallback(on_row_click_private::<T, S>)));
+        body.add_default_callback_id(EventFilter::Hover(HoverEventFilter::Scroll), window.add_callback(ptr, DefaultCallback(on_scroll_private::<T, S>)));
+
+        for callback in self.on_select.iter().chain(self.on_expand.iter()) {
+            container.add_callback(EventFilter::Focus(FocusEventFilter::VirtualKeyDown), *callback);
+            container.add_callback(EventFilter::Focus(FocusEventFilter::TextInput), *callback);
+            body.add_callback(EventFilter::Hover(HoverEventFilter::LeftMouseUp), *callback);
+        }
+
+        container.with_child(body)
+    }
+}
+
+fn render_body_callback<T: Layout, S: TreeSource<T>>(ptr: &StackCheckedPointer<T>, info: LayoutInfo<T>, dimensions: HidpiAdjustedBounds)
+-> Dom<T>
+{
+    unsafe { ptr.invoke_mut_iframe(render_body::<T, S>, info, dimensions) }
+}
+
+fn render_body<T: Layout, S: TreeSource<T>>(source: &mut S, _info: LayoutInfo<T>, dimensions: HidpiAdjustedBounds)
+-> Dom<T>
+{
+    let rows = visible_rows(source);
+
+    let state = source.tree_state_mut();
+    state.viewport_height = dimensions.logical_size.height as f32;
+    let max_offset = (rows.len() as f32 * state.row_height - state.viewport_height).max(0.0);
+    state.scroll_offset = state.scroll_offset.max(0.0).min(max_offset);
+
+    let visible = visible_row_range(state.scroll_offset, state.row_height, state.viewport_height, rows.len());
+    let first_row_offset = state.scroll_offset - visible.start as f32 * state.row_height;
+
+    let mut rows_dom = Dom::div()
+        .with_class("azul-tree-rows")
+        .with_css_override(SCROLL_CSS_ID, CssProperty::Margin(LayoutMargin { top: Some(PixelValue::px(-first_row_offset)), .. LayoutMargin::default() }));
+
+    for row in &rows[visible] {
+
+        let mut row_dom = Dom::div().with_class("azul-tree-row");
+
+        for _ in 0..row.depth {
+            row_dom.add_child(Dom::div().with_class("azul-tree-indent"));
+        }
+
+        match row.node {
+            Some(ref node) => {
+                if state.selection.contains(&node.id) {
+                    row_dom.add_class("azul-tree-row-selected");
+                }
+                if state.cursor == Some(node.id) {
+                    row_dom.add_class("azul-tree-row-cursor");
+                }
+                let mut expander = Dom::div().with_class("azul-tree-expander");
+                if !node.has_children {
+                    expander.add_class("azul-tree-expander-leaf");
+                } else if row.expanded {
+                    expander.add_class("azul-tree-expander-expanded");
+                }
+                row_dom.add_child(expander);
+                row_dom.add_child(Dom::label(node.label.clone()).with_class("azul-tree-label"));
+            },
+            None => {
+                row_dom.add_class("azul-tree-row-loading");
+                row_dom.add_child(Dom::div().with_class("azul-tree-expander").with_class("azul-tree-expander-leaf"));
+                row_dom.add_child(Dom::label("Loading...").with_class("azul-tree-spinner"));
+            },
+        }
+
+        rows_dom.add_child(row_dom);
+    }
+
+    rows_dom
+}
+
+fn on_virtual_key_down_private<T: Layout, S: TreeSource<T>>(ptr: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    unsafe { ptr.invoke_mut(on_virtual_key_down::<T, S>, app_state_no_data, event) }
+}
+
+fn on_text_input_private<T: Layout, S: TreeSource<T>>(ptr: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    unsafe { ptr.invoke_mut(on_text_input::<T, S>, app_state_no_data, event) }
+}
+
+fn on_row_click_private<T: Layout, S: TreeSource<T>>(ptr: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    unsafe { ptr.invoke_mut(on_row_click::<T, S>, app_state_no_data, event) }
+}
+
+fn on_scroll_private<T: Layout, S: TreeSource<T>>(ptr: &StackCheckedPointer<T>, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    unsafe { ptr.invoke_mut(on_scroll::<T, S>, app_state_no_data, event) }
+}
+
+fn on_virtual_key_down<T: Layout, S: TreeSource<T>>(source: &mut S, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    source.tree_state_mut().reset_events();
+    let keyboard_state = app_state_no_data.windows[event.window_id].get_keyboard_state();
+    let rows = visible_rows(source);
+    source.tree_state_mut().handle_key(keyboard_state.latest_virtual_keycode?, keyboard_state.ctrl_down, keyboard_state.shift_down, &rows)
+}
+
+fn on_text_input<T: Layout, S: TreeSource<T>>(source: &mut S, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+    source.tree_state_mut().reset_events();
+    let c = app_state_no_data.windows[event.window_id].get_keyboard_state().current_char?;
+    let rows = visible_rows(source);
+    source.tree_state_mut().handle_type_ahead(c, Instant::now(), &rows)
+}
+
+fn on_row_click<T: Layout, S: TreeSource<T>>(source: &mut S, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+
+    source.tree_state_mut().reset_events();
+
+    // Clicking the body focuses the tree view, so that it receives the keyboard input
+    event.focus = event.target_parent().map(FocusTarget::Id);
+
+    let (x, y) = event.cursor_relative_to_item?;
+    let rows = visible_rows(source);
+    let state = source.tree_state_mut();
+    let index = ((y + state.scroll_offset) / state.row_height).max(0.0).floor() as usize;
+    let depth = rows.get(index)?.depth;
+    let expander_x = x - depth as f32 * INDENT_WIDTH;
+    let on_expander = expander_x >= 0.0 && expander_x < EXPANDER_WIDTH;
+
+    let keyboard_state = app_state_no_data.windows[event.window_id].get_keyboard_state();
+    state.handle_click(index, on_expander, keyboard_state.ctrl_down, keyboard_state.shift_down, &rows)
+}
+
+fn on_scroll<T: Layout, S: TreeSource<T>>(source: &mut S, app_state_no_data: AppStateNoData<T>, event: &mut CallbackInfo<T>) -> UpdateScreen {
+
+    let scroll_y = app_state_no_data.windows[event.window_id].get_mouse_state().scroll_y as f32;
+    let row_count = visible_rows(source).len();
+    let state = source.tree_state_mut();
+
+    let max_offset = (row_count as f32 * state.row_height - state.viewport_height).max(0.0);
+    let scroll_offset = (state.scroll_offset + scroll_y).max(0.0).min(max_offset);
+
+    if scroll_offset == state.scroll_offset {
+        return DontRedraw;
+    }
+
+    state.scroll_offset = scroll_offset;
+    Redraw
+}
+
+/// 1 -> (2 -> 4, 5), 3 (loading)
+#[cfg(test)]
+fn test_children(parent: Option<u64>) -> TreeChildren {
+    match parent {
+        None => TreeChildren::Loaded(vec![TreeNodeDesc::branch(1, "src"), TreeNodeDesc::branch(3, "target")]),
+        Some(1) => TreeChildren::Loaded(vec![TreeNodeDesc::branch(2, "widgets"), TreeNodeDesc::leaf(5, "lib.rs")]),
+        Some(2) => TreeChildren::Loaded(vec![TreeNodeDesc::leaf(4, "button.rs")]),
+        Some(3) => TreeChildren::Loading,
+        Some(_) => TreeChildren::Loaded(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+fn summarize(rows: &[TreeRow]) -> Vec<(usize, Option<u64>, bool)> {
+    rows.iter().map(|row| (row.depth, row.id(), row.expanded)).collect()
+}
+
+#[test]
+fn test_flatten_visible_rows() {
+
+    let expanded = |ids: &[u64]| ids.iter().cloned().collect::<BTreeSet<_>>();
+
+    // Only the top-level nodes
+    let rows = flatten_visible_rows(test_children, &expanded(&[]));
+    assert_eq!(summarize(&rows), vec![(0, Some(1), false), (0, Some(3), false)]);
+
+    // Nested expansion, the children come right after their parent
+    let rows = flatten_visible_rows(test_children, &expanded(&[1, 2]));
+    assert_eq!(summarize(&rows), vec![
+        (0, Some(1), true), (1, Some(2), true), (2, Some(4), false), (1, Some(5), false), (0, Some(3), false),
+    ]);
+    assert_eq!(rows[2].parent, Some(2));
+    assert_eq!(rows[3].parent, Some(1));
+
+    // The children of a collapsed node are hidden, even if they are expanded themselves
+    let rows = flatten_visible_rows(test_children, &expanded(&[2]));
+    assert_eq!(summarize(&rows), vec![(0, Some(1), false), (0, Some(3), false)]);
+
+    // Children that are still loading are shown as a loading row
+    let rows = flatten_visible_rows(test_children, &expanded(&[3]));
+    assert_eq!(summarize(&rows), vec![(0, Some(1), false), (0, Some(3), true), (1, None, false)]);
+
+    // Leaves and unknown IDs in the expanded set are ignored
+    let rows = flatten_visible_rows(test_children, &expanded(&[5, 100]));
+    assert_eq!(rows.len(), 2);
+
+    // Cycles: a node that is its own child is only shown once
+    let cycle = |parent: Option<u64>| TreeChildren::Loaded(vec![TreeNodeDesc::branch(parent.unwrap_or(0) + 1, "a"), TreeNodeDesc::branch(1, "loop")]);
+    let rows = flatten_visible_rows(cycle, &expanded(&[1, 2]));
+    assert_eq!(summarize(&rows), vec![(0, Some(1), true), (1, Some(2), true), (2, Some(3), false)]);
+}
+
+#[test]
+fn test_tree_keyboard_navigation() {
+
+    use self::VirtualKeyCode::*;
+
+    let mut state = TreeViewState::new();
+    state.viewport_height = 100.0;
+    let rows = |state: &TreeViewState| flatten_visible_rows(test_children, &state.expanded);
+
+    // Without a cursor, Down selects the first node
+    let r = rows(&state);
+    assert_eq!(state.handle_key(Down, false, false, &r), Redraw);
+    assert_eq!((state.cursor, state.selection_changed()), (Some(1), true));
+
+    // Right expands, Right again moves to the first child
+    assert_eq!(state.handle_key(Right, false, false, &r), Redraw);
+    assert_eq!(state.expanded_node(), Some(1));
+    let r = rows(&state);
+    state.handle_key(Right, false, false, &r);
+    assert_eq!(state.cursor, Some(2));
+
+    // Down / Up move between the visible nodes and stop at the ends
+    state.handle_key(Down, false, false, &r);
+    assert_eq!(state.cursor, Some(5));
+    state.handle_key(Down, false, false, &r);
+    state.handle_key(Down, false, false, &r);
+    assert_eq!(state.cursor, Some(3));
+    state.handle_key(Home, false, false, &r);
+    state.handle_key(Up, false, false, &r);
+    assert_eq!(state.cursor, Some(1));
+
+    // Left on a collapsed node moves to the parent, Left on an expanded node collapses it
+    state.handle_key(End, false, false, &r);
+    state.handle_key(Up, false, false, &r);
+    assert_eq!(state.cursor, Some(5));
+    state.handle_key(Left, false, false, &r);
+    assert_eq!(state.cursor, Some(1));
+    assert_eq!(state.handle_key(Left, false, false, &r), Redraw);
+    assert!(state.expanded.is_empty());
+    let r = rows(&state);
+    assert_eq!(state.handle_key(Left, false, false, &r), DontRedraw);
+
+    // Right on a leaf does nothing, Enter toggles the expansion
+    assert_eq!(state.handle_key(Return, false, false, &r), Redraw);
+    assert!(state.expanded.contains(&1));
+    let r = rows(&state);
+    state.handle_key(Down, false, false, &r);
+    state.handle_key(Down, false, false, &r);
+    assert_eq!(state.cursor, Some(5));
+    assert_eq!(state.handle_key(Right, false, false, &r), DontRedraw);
+
+    // Shift extends the selection from the anchor, Ctrl only moves the cursor
+    state.handle_key(Up, false, true, &r);
+    assert_eq!(state.selection.iter().cloned().collect::<Vec<_>>(), vec![2, 5]);
+    state.handle_key(Up, true, false, &r);
+    assert_eq!(state.cursor, Some(1));
+    assert_eq!(state.selection.iter().cloned().collect::<Vec<_>>(), vec![2, 5]);
+
+    // The cursor row is scrolled into view
+    state.handle_key(End, false, false, &r);
+    state.viewport_height = 40.0;
+    state.handle_key(End, false, false, &r);
+    assert_eq!(state.scroll_offset, 40.0);
+    state.handle_key(Home, false, false, &r);
+    assert_eq!(state.scroll_offset, 0.0);
+}
+
+#[test]
+fn test_tree_mouse_selection_and_type_ahead() {
+
+    let mut state = TreeViewState::new();
+    state.expanded.insert(1);
+    let rows = flatten_visible_rows(test_children, &state.expanded);
+
+    // 1, 2, 5, 3
+    assert_eq!(state.handle_click(1, false, false, false, &rows), Redraw);
+    assert_eq!(state.handle_click(3, false, false, true, &rows), Redraw);
+    assert_eq!(state.selection.iter().cloned().collect::<Vec<_>>(), vec![2, 3, 5]);
+    state.handle_click(2, false, true, false, &rows);
+    assert_eq!(state.selection.iter().cloned().collect::<Vec<_>>(), vec![2, 3]);
+    state.handle_click(0, false, true, true, &rows);
+    assert_eq!(state.selection.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 5]);
+
+    // The expander toggles the node without changing the selection
+    state.handle_click(0, true, false, false, &rows);
+    assert!(!state.expanded.contains(&1));
+    assert_eq!(state.selection.len(), 4);
+    assert_eq!(state.handle_click(10, false, false, false, &rows), DontRedraw);
+
+    // Type-ahead cycles through the nodes starting with the typed letters
+    let now = Instant::now();
+    let mut state = TreeViewState::new();
+    state.expanded.insert(1);
+    assert_eq!(state.handle_type_ahead('t', now, &rows), Redraw);
+    assert_eq!(state.cursor, Some(3));
+    state.handle_type_ahead('w', now + Duration::from_millis(2000), &rows);
+    assert_eq!(state.cursor, Some(2));
+    state.handle_type_ahead('l', now + Duration::from_millis(4000), &rows);
+    state.handle_type_ahead('i', now + Duration::from_millis(4100), &rows);
+    assert_eq!(state.cursor, Some(5));
+    assert_eq!(state.selection.iter().cloned().collect::<Vec<_>>(), vec![5]);
+    assert_eq!(state.handle_type_ahead('x', now + Duration::from_millis(6000), &rows), DontRedraw);
+}
@@ -4,13 +4,14 @@ use std::{
     time::Duration,
     fmt,
     rc::Rc,
+    collections::BTreeMap,
     marker::PhantomData,
     io::Error as IoError,
     sync::atomic::{AtomicUsize, Ordering},
 };
 use webrender::{
     api::{
-        LayoutRect, PipelineId, Epoch, BuiltDisplayList, DocumentId,
+        LayoutRect, LayoutPoint, PipelineId, Epoch, BuiltDisplayList, DocumentId,
         RenderApi, ExternalScrollId, RenderNotifier, DeviceIntSize,
     },
     Renderer, RendererOptions, RendererKind, ShaderPrecacheFlags,
@@ -22,36 +23,49 @@ use glium::{
     glutin::{
         self, EventsLoop, AvailableMonitorsIter, GlContext, GlWindow, CreationError,
         MonitorId, EventsLoopProxy, ContextError, ContextBuilder, WindowBuilder, Icon,
-        dpi::{LogicalSize, PhysicalSize}
+        dpi::{LogicalPosition, LogicalSize, PhysicalSize}
     },
     backend::{Context, Facade, glutin::DisplayCreationError},
 };
 use gleam::gl::{self, Gl};
-use azul_css::{Css, ColorF};
+use azul_css::{Css, ColorF, RectStyle, RectLayout};
 #[cfg(debug_assertions)]
 use azul_css::HotReloadHandler;
+#[cfg(feature = "debug-inspector")]
+use glium::glutin::VirtualKeyCode;
+#[cfg(feature = "debug-inspector")]
+use inspector::{Inspector, DEFAULT_INSPECTOR_HOTKEY};
 use {
     FastHashMap,
-    dom::{Texture, Callback, NodeData, NodeType},
+    dom::{Dom, Texture, Callback, NodeData, NodeType, TagId, TagIdGenerator, DomAllocator},
     window_state::{WindowState, MouseState, KeyboardState, DebugState},
     traits::Layout,
     compositor::Compositor,
     app::FrameEventInfo,
     app_resources::AppResources,
     id_tree::NodeId,
+    layout_result::LayoutResult,
     default_callbacks::{
         DefaultCallbackSystem, StackCheckedPointer, DefaultCallback, DefaultCallbackId
     },
     ui_state::UiState,
-    display_list::ScrolledNodes,
+    display_list::{ScrolledNodes, DisplayListCacheKey},
     focus::FocusTarget,
     id_tree::{Node, NodeHierarchy},
+    profiling::FrameProfiler,
+    style::CssRuleIndex,
+    node_identity::NodeIdMapping,
+    menu::{MenuBar, MenuItemUpdate, MenuBackend, WindowMenu},
+    drag_drop::{DragData, DroppedData, DragDropBackend},
+    accessibility::AccessibilityAdapter,
+    diagnostics::{LogLevel, log},
 };
 pub use webrender::api::HitTestItem;
+pub use display_list::DisplayListCacheStats;
 
 static LAST_WINDOW_ID: AtomicUsize = AtomicUsize::new(0);
 
-fn new_window_id() -> WindowId {
+pub(crate) fn new_window_id() -> WindowId {
     WindowId { id: LAST_WINDOW_ID.fetch_add(1, Ordering::SeqCst) }
 }
 
@@ -74,6 +88,19 @@ pub struct FakeWindow<T: Layout> {
     /// but not change any window properties from underneath - this would
     /// lead to mismatch between the
     pub(crate) read_only_window: Rc<Display>,
+    /// Generates the hit-testing tags for the DOM (and all iframe DOMs) of this window
+    pub(crate) tag_ids: TagIdGenerator,
+    /// Buffers of the last frames DOM, re-used via `Dom::with_capacity_in()`
+    pub(crate) dom_allocator: DomAllocator<T>,
+    /// Measures the frame that is currently being built (zero-sized without the `profiling` feature)
+    pub(crate) profiler: FrameProfiler,
+    /// Headless rendering: draw a solid placeholder instead of calling
+    /// the `GlTextureCallback`s and `IFrameCallback`s of this window
+    pub(crate) render_callback_placeholders: bool,
+    /// Solved rectangles (and optionally styles) of the last frame that was laid out
+    pub(crate) layout_snapshot: LayoutSnapshot,
+    /// The menu bar of this window, see the `menu` module
+    pub(crate) menu: WindowMenu<T>,
 }
 
 impl<T: Layout> FakeWindow<T> {
@@ -94,6 +121,10 @@ impl<T: Layout> FakeWindow<T> {
         self.state.mouse_state = *mouse;
     }
 
+    pub(crate) fn set_dropped_data(&mut self, dropped_data: &Option<DroppedData>) {
+        self.state.dropped_data = dropped_data.clone();
+    }
+
     /// Returns the current keyboard keyboard state. We don't want the library
     /// user to be able to modify this state, only to read it.
     pub fn get_keyboard_state<'a>(&'a self) -> &'a KeyboardState {
@@ -106,6 +137,18 @@ impl<T: Layout> FakeWindow<T> {
         self.state.get_mouse_state()
     }
 
+    /// Returns the node that currently has the keyboard focus, if any
+    pub fn get_focused_node(&self) -> Option<NodeId> {
+        self.state.focused_node
+    }
+
+    /// Returns the solved layout of the last frame of this window - empty until
+    /// the first frame has been drawn. Note that during `layout()`, this is
+    /// still the layout of the previous frame.
+    pub fn get_layout_snapshot(&self) -> &LayoutSnapshot {
+        &self.layout_snapshot
+    }
+
     /// Adds a default callback to the window. The default callbacks are
     /// cleared after every frame, so two-way data binding widgets have to call this
     /// on every frame they want to insert a default callback.
@@ -124,6 +167,33 @@ impl<T: Layout> FakeWindow<T> {
         self.default_callbacks.add_callback(default_callback_id, callback_ptr, callback_fn);
         default_callback_id
     }
+
+    /// Sets (or removes) the menu bar of this window. The accelerators of the
+    /// menu items are active from the next event on. See the `menu` module for
+    /// why the menu is set here and not on the `WindowState`.
+    pub fn set_menu(&mut self, menu_bar: Option<MenuBar<T>>) {
+        self.menu.set_menu(menu_bar);
+    }
+
+    /// Returns the current menu bar of this window
+    pub fn get_menu(&self) -> Option<&MenuBar<T>> {
+        self.menu.menu_bar.as_ref()
+    }
+
+    /// Enables / disables, checks / unchecks or renames the menu item at the `path`.
+    /// Returns `None` if the window has no menu or there is no item at the `path`.
+    pub fn update_menu_item(&mut self, path: &[usize], update: MenuItemUpdate) -> Option<()> {
+        self.menu.update_item(path, update)
+    }
+
+    /// Renders the menu bar for windows without a `MenuBackend`, put this at the top
+    /// of the DOM returned from `Layout::layout()`. Empty if the window has no menu.
+    pub fn menu_bar_dom(&self) -> Dom<T> {
+        match &self.menu.menu_bar {
+            Some(menu_bar) => menu_bar.dom(self.menu.open_menu),
+            None => Dom::div(),
+        }
+    }
 }
 
 /// Read-only window which can be used to create / draw
@@ -196,6 +266,15 @@ pub struct LayoutInfo<'a, 'b, T: 'b + Layout> {
     pub resources: &'a AppResources,
 }
 
+impl<'a, 'b, T: 'b + Layout> LayoutInfo<'a, 'b, T> {
+    /// Returns the allocator that holds the memory of the last frames DOM, pass it to
+    /// `Dom::with_capacity_in()` and create the nodes with `DomAllocator::node()` to
+    /// re-use that memory.
+    pub fn get_dom_allocator(&mut self) -> &mut DomAllocator<T> {
+        &mut self.window.dom_allocator
+    }
+}
+
 impl<T: Layout> fmt::Debug for FakeWindow<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f,
@@ -225,6 +304,14 @@ pub struct CallbackInfo<'a, T: 'a + Layout> {
     pub cursor_relative_to_item: Option<(f32, f32)>,
     /// The (x, y) position of the mouse cursor, **relative to top left of the window**.
     pub cursor_in_viewport: Option<(f32, f32)>,
+    /// Size of the window, see `window_size()`
+    pub(crate) window_size: LogicalSize,
+    /// Position of the window, see `window_position()`
+    pub(crate) window_position: Option<LogicalPosition>,
+    /// Data to drag out of the window, set by `start_os_drag()`
+    pub(crate) os_drag: Option<DragData>,
+    /// Whether the window should stay open, set by `prevent_close()`
+    pub(crate) close_prevented: bool,
 }
 
 impl<'a, T: 'a + Layout> Clone for CallbackInfo<'a, T> {
@@ -237,6 +324,10 @@ impl<'a, T: 'a + Layout> Clone for CallbackInfo<'a, T> {
             hit_test_items: self.hit_test_items,
             cursor_relative_to_item: self.cursor_relative_to_item,
             cursor_in_viewport: self.cursor_in_viewport,
+            window_size: self.window_size,
+            window_position: self.window_position,
+            os_drag: self.os_drag.clone(),
+            close_prevented: self.close_prevented,
         }
     }
 }
@@ -251,6 +342,10 @@ impl<'a, T: 'a + Layout> fmt::Debug for CallbackInfo<'a, T> {
             hit_test_items: {:?}, \
             cursor_relative_to_item: {:?}, \
             cursor_in_viewport: {:?}, \
+            window_size: {:?}, \
+            window_position: {:?}, \
+            os_drag: {:?}, \
+            close_prevented: {:?}, \
         }}",
             self.focus,
             self.window_id,
@@ -259,6 +354,10 @@ impl<'a, T: 'a + Layout> fmt::Debug for CallbackInfo<'a, T> {
             self.hit_test_items,
             self.cursor_relative_to_item,
             self.cursor_in_viewport,
+            self.window_size,
+            self.window_position,
+            self.os_drag,
+            self.close_prevented,
         )
     }
 }
@@ -340,7 +439,7 @@ impl<'a, T: 'a + Layout> CallbackInfo<'a, T> {
 
     /// Returns the node content of a specific node
     pub fn get_node_content<'b>(&'b self, node_id: NodeId) -> Option<&'b NodeData<T>> {
-        self.ui_state.dom.arena.node_data.internal.get(node_id.index())
+        self.ui_state.dom.arena.node_data.get(node_id)
     }
 
     /// Returns the index of the target NodeId (the target that received the event)
@@ -358,11 +457,46 @@ impl<'a, T: 'a + Layout> CallbackInfo<'a, T> {
         self.get_node(node_id)?.parent
     }
 
+    /// Returns the (logical) size of the window. In a `WindowEventFilter::Resized`
+    /// callback, this is already the new size.
+    pub fn window_size(&self) -> LogicalSize {
+        self.window_size
+    }
+
+    /// Returns the position of the window, `None` if the window hasn't been moved and its
+    /// position wasn't set in the `WindowState`. In a `WindowEventFilter::Moved` callback,
+    /// this is already the new position.
+    pub fn window_position(&self) -> Option<LogicalPosition> {
+        self.window_position
+    }
+
     /// Returns the parent of the current target or None if the target is the root node.
     pub fn target_parent(&self) -> Option<NodeId> {
         self.parent(self.hit_dom_node)
     }
 
+    /// Returns the target itself or its nearest ancestor for which the `predicate` returns
+    /// `true`, i.e. the `.dropdown` that contains the clicked item, see `Dom::closest`
+    pub fn target_closest<F: Fn(&NodeData<T>) -> bool>(&self, predicate: F) -> Option<NodeId> {
+        self.ui_state.closest(self.hit_dom_node, predicate)
+    }
+
+    /// Starts dragging the `data` out of the window, to other applications, once the
+    /// callbacks of the current event have run. Call this from a mouse callback of a
+    /// draggable node (`LeftMouseDown` or `MouseOver` while the button is held down).
+    /// Does nothing if the window has no `DragDropBackend`, see the `drag_drop` module.
+    pub fn start_os_drag(&mut self, data: DragData) {
+        self.os_drag = Some(data);
+    }
+
+    /// Keeps the window open, i.e. to ask the user to save unsaved changes first.
+    /// Only has an effect in a `WindowEventFilter::CloseRequested` callback - the next
+    /// `CloseRequested` event closes the window again, unless a callback calls this again.
+    /// A window that was already destroyed (`WindowEventFilter::Closed`) can't be kept open.
+    pub fn prevent_close(&mut self) {
+        self.close_prevented = true;
+    }
+
     /// Checks whether the target of the CallbackInfo has a certain node type
     pub fn target_is_node_type(&self, node_type: NodeType<T>) -> bool {
         if let Some(self_node) = self.get_node_content(self.hit_dom_node) {
@@ -390,6 +524,11 @@ impl<'a, T: 'a + Layout> CallbackInfo<'a, T> {
         }
     }
 
+    /// Returns the value of the attribute `key` of the node that was hit, see `NodeData::attributes`
+    pub fn target_attribute(&self, key: &str) -> Option<&str> {
+        self.get_node_content(self.hit_dom_node)?.get_attribute(key)
+    }
+
     /// Traverses up the hierarchy, checks whether any parent has a certain ID,
     /// the returns that parent
     pub fn any_parent_has_id(&self, id: &str) -> Option<NodeId> {
@@ -445,6 +584,9 @@ pub struct WindowCreateOptions<T: Layout> {
     pub taskbar_icon: Option<Icon>,
     /// Windows only: Sets `WS_EX_NOREDIRECTIONBITMAP` on the window
     pub no_redirection_bitmap: bool,
+    /// Key that opens / closes the DOM inspector, `None` disables the inspector
+    #[cfg(feature = "debug-inspector")]
+    pub inspector_hotkey: Option<VirtualKeyCode>,
 }
 
 impl<T: Layout> Default for WindowCreateOptions<T> {
@@ -463,6 +605,8 @@ impl<T: Layout> Default for WindowCreateOptions<T> {
             window_icon: None,
             taskbar_icon: None,
             no_redirection_bitmap: false,
+            #[cfg(feature = "debug-inspector")]
+            inspector_hotkey: Some(DEFAULT_INSPECTOR_HOTKEY),
         }
     }
 }
@@ -610,9 +754,7 @@ impl RenderNotifier for Notifier {
     fn wake_up(&self) {
         #[cfg(not(target_os = "android"))]
         self.events_loop_proxy.wakeup().unwrap_or_else(|_| {
-            #[cfg(feature = "logging")] {
-                error!("couldn't wakeup event loop");
-            }
+            log(LogLevel::Error, "couldn't wakeup event loop");
         });
     }
 
@@ -685,10 +827,25 @@ pub struct Window<T: Layout> {
     // pub(crate) background_thread: Option<JoinHandle<()>>,
     /// The style applied to the current window
     pub(crate) css: Css,
+    /// Index of the rules in `css` for faster styling, has to be rebuilt when `css` changes
+    pub(crate) css_index: CssRuleIndex,
     /// An optional style hot-reloader for the current window, only available with debug_assertions
     /// enabled
     #[cfg(debug_assertions)]
     pub(crate) css_loader: Option<Box<dyn HotReloadHandler>>,
+    /// The DOM inspector of this window, see the `inspector` module
+    #[cfg(feature = "debug-inspector")]
+    pub(crate) inspector: Inspector,
+    /// Shows the menu of the window natively, without a backend the menu is drawn in the DOM
+    pub(crate) menu_backend: Option<Box<dyn MenuBackend<T>>>,
+    /// The `WindowMenu::generation` that the `menu_backend` was last updated with
+    pub(crate) synced_menu_generation: usize,
+    /// Drags data out of / drops data into the window, see the `drag_drop` module
+    pub(crate) drag_drop_backend: Option<Box<dyn DragDropBackend>>,
+    /// Exposes the UI to screen readers, see the `accessibility` module
+    pub(crate) accessibility_adapter: Option<Box<dyn AccessibilityAdapter>>,
+    /// The focused node that the `accessibility_adapter` was last told about
+    pub(crate) accessible_focused_node: Option<NodeId>,
     /// Purely a marker, so that `app.run()` can infer the type of `T: Layout`
     /// of the `WindowCreateOptions`, so that we can write:
     ///
@@ -734,6 +891,13 @@ impl ScrollStates {
     pub(crate) fn remove_unused_scroll_states(&mut self) {
         self.0.retain(|_, state| state.used_this_frame);
     }
+
+    /// Returns the current scroll offsets, for hit-testing without a webrender instance
+    pub(crate) fn get_scroll_offsets(&self) -> FastHashMap<ExternalScrollId, LayoutPoint> {
+        self.0.iter().map(|(scroll_id, state)| {
+            (*scroll_id, LayoutPoint::new(state.scroll_amount_x, state.scroll_amount_y))
+        }).collect()
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -784,6 +948,11 @@ impl Default for ScrollState {
 pub(crate) struct WindowInternal {
     pub(crate) last_display_list_builder: BuiltDisplayList,
     pub(crate) last_scrolled_nodes: ScrolledNodes,
+    /// Key of the `last_display_list_builder`, `None` if the last display list can't be re-used
+    pub(crate) last_display_list_cache_key: Option<DisplayListCacheKey>,
+    /// Hit-testing tags that are baked into the `last_display_list_builder`
+    pub(crate) last_display_list_tags: BTreeMap<NodeId, TagId>,
+    pub(crate) display_list_cache_stats: DisplayListCacheStats,
     pub(crate) api: RenderApi,
     pub(crate) epoch: Epoch,
     pub(crate) pipeline_id: PipelineId,
@@ -981,6 +1150,7 @@ impl<'a, T: Layout> Window<T> {
         set_webrender_debug_flags(&mut renderer, &DebugState::default(), &options.state.debug_state);
 
         css.sort_by_specificity();
+        let css_index = CssRuleIndex::new(&css);
 
         let window = Window {
             id: new_window_id(),
@@ -989,8 +1159,16 @@ impl<'a, T: Layout> Window<T> {
             renderer: Some(renderer),
             display: Rc::new(display),
             css,
+            css_index,
             #[cfg(debug_assertions)]
             css_loader: None,
+            #[cfg(feature = "debug-inspector")]
+            inspector: Inspector::new(options.inspector_hotkey),
+            menu_backend: None,
+            synced_menu_generation: 0,
+            drag_drop_backend: None,
+            accessibility_adapter: None,
+            accessible_focused_node: None,
             scroll_states: ScrollStates::new(),
             internal: WindowInternal {
                 api: api,
@@ -999,6 +1177,9 @@ impl<'a, T: Layout> Window<T> {
                 document_id: document_id,
                 last_display_list_builder: BuiltDisplayList::default(),
                 last_scrolled_nodes: ScrolledNodes::default(),
+                last_display_list_cache_key: None,
+                last_display_list_tags: BTreeMap::new(),
+                display_list_cache_stats: DisplayListCacheStats::default(),
             },
             marker: PhantomData,
         };
@@ -1015,6 +1196,29 @@ impl<'a, T: Layout> Window<T> {
         Ok(window)
     }
 
+    /// Shows the menu of the window (`FakeWindow::set_menu()`) with the given backend
+    /// instead of rendering it into the DOM, see the `menu` module
+    pub fn with_menu_backend(mut self, menu_backend: Box<dyn MenuBackend<T>>) -> Self {
+        self.menu_backend = Some(menu_backend);
+        self.synced_menu_generation = 0;
+        self
+    }
+
+    /// Enables dragging data to and dropping text / custom formats from other
+    /// applications with the given backend, see the `drag_drop` module
+    pub fn with_drag_drop_backend(mut self, drag_drop_backend: Box<dyn DragDropBackend>) -> Self {
+        self.drag_drop_backend = Some(drag_drop_backend);
+        self
+    }
+
+    /// Sends the accessibility tree of every frame to the given adapter and dispatches
+    /// the actions of the assistive technology, see the `accessibility` module
+    pub fn with_accessibility_adapter(mut self, accessibility_adapter: Box<dyn AccessibilityAdapter>) -> Self {
+        self.accessibility_adapter = Some(accessibility_adapter);
+        self.accessible_focused_node = None;
+        self
+    }
+
     /// Returns an iterator over all given monitors
     pub fn get_available_monitors() -> MonitorIter {
         MonitorIter {
@@ -1022,6 +1226,12 @@ impl<'a, T: Layout> Window<T> {
         }
     }
 
+    /// Returns how often the display list of this window could be re-used
+    /// from the previous frame instead of being rebuilt
+    pub fn get_display_list_cache_stats(&self) -> DisplayListCacheStats {
+        self.internal.display_list_cache_stats
+    }
+
     /// Updates the window state, diff the `self.state` with the `new_state`
     /// and updating the platform window to reflect the changes
     ///
@@ -1040,6 +1250,11 @@ impl<'a, T: Layout> Window<T> {
             set_webrender_debug_flags(r, &old_state.debug_state, &new_state.debug_state);
         }
 
+        old_state.debug_overlay = new_state.debug_overlay;
+        old_state.frame_hud = new_state.frame_hud;
+        old_state.callback_panic = new_state.callback_panic.clone();
+        old_state.ui_reload_error = new_state.ui_reload_error.clone();
+
         if old_state.title != new_state.title {
             window.set_title(&new_state.title);
             old_state.title = new_state.title;
@@ -1100,6 +1315,16 @@ impl<'a, T: Layout> Window<T> {
         }
     }
 
+    /// Migrates all per-node state of the window (focus, hover, the nodes of the
+    /// inspector) from the `NodeId`s of the last frame to the `NodeId`s of the new
+    /// frame. Every store of per-node state has to be migrated here.
+    pub(crate) fn migrate_node_ids(&mut self, mapping: &NodeIdMapping) {
+        self.state.migrate_node_ids(mapping);
+        #[cfg(feature = "debug-inspector")] {
+            self.inspector.migrate_node_ids(mapping);
+        }
+    }
+
     /// Resets the mouse states `scroll_x` and `scroll_y` to 0
     pub(crate) fn clear_scroll_state(&mut self) {
         self.state.mouse_state.scroll_x = 0.0;
@@ -1150,6 +1375,55 @@ impl HidpiAdjustedBounds {
     }
 }
 
+/// The solved layout of one frame of a window, indexed by the `NodeId`
+/// of the DOM that `layout()` returned (nodes of iframes are not included).
+///
+/// The layout is shared with `WindowState::get_layout_result()`, the computed styles
+/// are only recorded if they are requested (currently only by the inspector of the
+/// `debug-inspector` feature), since cloning the style of every node on every frame is not free.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LayoutSnapshot {
+    pub(crate) layout: Rc<LayoutResult>,
+    pub(crate) styles: Vec<(RectStyle, RectLayout)>,
+    pub(crate) capture_styles: bool,
+}
+
+impl LayoutSnapshot {
+
+    /// Number of nodes in the snapshot
+    pub fn len(&self) -> usize {
+        self.layout.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layout.is_empty()
+    }
+
+    /// Returns the box model, scroll size and drawing order of all nodes
+    pub fn get_layout_result(&self) -> &LayoutResult {
+        &self.layout
+    }
+
+    /// Returns the solved rectangle (the border box) of the node, in logical pixels relative to the window
+    pub fn get_rect(&self, node_id: NodeId) -> Option<LayoutRect> {
+        self.layout.get(node_id).map(|node| node.border_box)
+    }
+
+    /// Returns the computed (cascaded and parsed) style of the node, if styles were recorded
+    pub fn get_style(&self, node_id: NodeId) -> Option<&RectStyle> {
+        self.styles.get(node_id.index()).map(|(style, _)| style)
+    }
+
+    /// Returns the computed layout properties of the node, if styles were recorded
+    pub fn get_layout(&self, node_id: NodeId) -> Option<&RectLayout> {
+        self.styles.get(node_id.index()).map(|(_, layout)| layout)
+    }
+
+    /// Returns all nodes whose rectangle contains the point, in document order
+    pub fn nodes_at(&self, x: f32, y: f32) -> Vec<NodeId> {
+        self.layout.nodes_at(x, y)
+    }
+}
 
 fn set_webrender_debug_flags(r: &mut Renderer, old_flags: &DebugState, new_flags: &DebugState) {
 
@@ -2,30 +2,48 @@
 //! click was a mouseover, mouseout, and so on and calling the correct callbacks)
 
 use std::{
-    collections::{HashSet, BTreeMap},
+    collections::{HashSet, BTreeMap, BTreeSet},
     path::PathBuf,
+    rc::Rc,
     fmt,
+    time::{Duration, Instant},
 };
 use glium::glutin::{
     Window, Event, WindowEvent, KeyboardInput, ScanCode, ElementState,
-    MouseCursor, VirtualKeyCode, MouseScrollDelta, AxisId,
+    MouseCursor, VirtualKeyCode, MouseScrollDelta, AxisId, MouseButton,
     ModifiersState, dpi::{LogicalPosition, LogicalSize},
 };
-use webrender::api::HitTestItem;
+use webrender::api::{HitTestItem, ExternalScrollId, LayoutPoint};
 use {
+    FastHashMap,
     app::FrameEventInfo,
     dom::{
-        EventFilter, Callback, NotEventFilter, UpdateScreen,
+        EventFilter, Callback, NotEventFilter, UpdateScreen, DomError,
         HoverEventFilter, FocusEventFilter, WindowEventFilter, DesktopEventFilter,
     },
     default_callbacks::DefaultCallbackId,
     id_tree::NodeId,
-    ui_state::UiState,
+    ui_state::{UiState, find_callback},
     traits::Layout,
     focus::FocusTarget,
     app_state::AppState,
     window::CallbackInfo,
 };
+#[cfg(feature = "profiling")]
+use profiling::{FrameStats, FrameStatsHistory};
+#[cfg(feature = "profiling")]
+use frame_hud::FrameHudData;
+use dispatch_trace::{DispatchTrace, DispatchTraceEvent, SkipReason};
+use debug_overlay::DebugOverlayFlags;
+use hit_test::{HitRegion, hit_test_regions};
+use layout_result::LayoutResult;
+use node_identity::NodeIdMapping;
+use error_overlay::CallbackPanic;
+use drag_drop::{DroppedData, add_dropped_data};
+#[cfg(feature = "html-export")]
+use std::path::Path;
+#[cfg(feature = "html-export")]
+use html_export::{ExportedFrame, ExportError, write_frame_html};
 
 const DEFAULT_TITLE: &str = "Azul App";
 const DEFAULT_WIDTH: f64 = 800.0;
@@ -119,6 +137,48 @@ impl Default for MouseState {
     }
 }
 
+/// When two clicks on the same node count as a double click, see `On::DoubleClick`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DoubleClickSettings {
+    /// Maximum time between the releases of the mouse button, 500ms by default
+    pub interval: Duration,
+    /// Maximum distance (in logical pixels) the cursor may move between the two clicks, 4px by default
+    pub max_distance: f64,
+}
+
+impl Default for DoubleClickSettings {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(500),
+            max_distance: 4.0,
+        }
+    }
+}
+
+/// The last click, which may be the first click of a double click
+#[derive(Debug, Clone)]
+pub(crate) struct LastClick {
+    button: MouseButton,
+    time: Instant,
+    position: Option<LogicalPosition>,
+    /// Nodes that were hit by the click. Unlike the tags of the hit-test items, the
+    /// `NodeId`s stay valid if the UI is redrawn before the second click, since they
+    /// are migrated to the new frame by `WindowState::migrate_node_ids`
+    nodes: BTreeSet<NodeId>,
+}
+
+impl LastClick {
+    /// Returns whether a click with the `button` at the `position` makes this click a double click
+    fn is_double_click(&self, button: MouseButton, position: Option<LogicalPosition>, now: Instant, settings: &DoubleClickSettings) -> bool {
+        let is_close = match (self.position, position) {
+            (Some(a), Some(b)) => (a.x - b.x).hypot(a.y - b.y) <= settings.max_distance,
+            (None, None) => true,
+            _ => false,
+        };
+        self.button == button && is_close && now.duration_since(self.time) < settings.interval
+    }
+}
+
 /// Toggles webrender debug flags (will make stuff appear on
 /// the screen that you might not want to - used for debugging purposes)
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -180,6 +240,8 @@ pub struct WindowState {
     pub(crate) mouse_state: MouseState,
     /// Whether there is a file currently hovering over the window
     pub(crate) hovered_file: Option<PathBuf>,
+    /// Everything that was dropped onto the window in this frame
+    pub(crate) dropped_data: Option<DroppedData>,
     /// What node is currently hovered over, default to None. Only necessary internal
     /// to the crate, for emitting `On::FocusReceived` and `On::FocusLost` events,
     /// as well as styling `:focus` elements
@@ -189,6 +251,8 @@ pub struct WindowState {
     pub(crate) hovered_nodes: BTreeMap<NodeId, HitTestItem>,
     /// Previous window state, used for determining mouseout, etc. events
     pub(crate) previous_window_state: Option<Box<WindowState>>,
+    /// The last click, for detecting double clicks
+    pub(crate) last_click: Option<LastClick>,
     /// Whether there is a focus field overwrite from the last callback calls.
     pub(crate) pending_focus_target: Option<FocusTarget>,
     /// What the last motion was in case a controller was used.
@@ -196,6 +260,8 @@ pub struct WindowState {
     /// Mostly used for debugging, shows WebRender-builtin graphs on the screen.
     /// Used for performance monitoring and displaying frame times (rendering-only).
     pub debug_state: DebugState,
+    /// How fast and how close two clicks have to be to count as a double click
+    pub double_click: DoubleClickSettings,
     /// Size of the window + max width / max height: 800 x 600 by default
     pub size: WindowSize,
     /// Current title of the window
@@ -214,6 +280,30 @@ pub struct WindowState {
     pub is_transparent: bool,
     /// Is the window always on top?
     pub is_always_on_top: bool,
+    /// Timings and statistics of the last frames of this window
+    #[cfg(feature = "profiling")]
+    pub(crate) frame_stats: FrameStatsHistory,
+    /// Recorded event dispatch decisions, `None` if tracing is disabled
+    pub(crate) dispatch_trace: Option<DispatchTrace>,
+    /// Layout debugging information that is drawn on top of the window content
+    pub(crate) debug_overlay: DebugOverlayFlags,
+    /// Whether the FPS and frame-budget HUD is drawn on top of the window content
+    pub(crate) frame_hud: bool,
+    /// Tagged rectangles of the last frame in drawing order, see `hit_test()`
+    pub(crate) hit_regions: Vec<HitRegion>,
+    /// Solved layout of the last frame, see `get_layout_result()`
+    pub(crate) layout_result: Rc<LayoutResult>,
+    /// Current offsets of the scroll frames of the `hit_regions`, updated on every scroll
+    pub(crate) scroll_offsets: FastHashMap<ExternalScrollId, LayoutPoint>,
+    /// The first callback panic since the error overlay was last dismissed
+    pub(crate) callback_panic: Option<CallbackPanic>,
+    /// Why the last DOM returned by `Layout::layout()` couldn't be rendered, see `get_dom_error()`
+    pub(crate) dom_error: Option<DomError>,
+    /// Why the markup of `App::with_hot_reload_ui()` couldn't be loaded, see `get_ui_reload_error()`
+    pub(crate) ui_reload_error: Option<String>,
+    /// Rectangles, styles and content of the last frame, see `export_frame_html()`
+    #[cfg(feature = "html-export")]
+    pub(crate) exported_frame: Option<Rc<ExportedFrame>>,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -248,7 +338,9 @@ impl Default for WindowState {
             focused_node: None,
             hovered_nodes: BTreeMap::new(),
             hovered_file: None,
+            dropped_data: None,
             previous_window_state: None,
+            last_click: None,
             pending_focus_target: None,
             last_motion: None,
             title: DEFAULT_TITLE.into(),
@@ -261,6 +353,20 @@ impl Default for WindowState {
             is_transparent: false,
             is_always_on_top: false,
             debug_state: DebugState::default(),
+            double_click: DoubleClickSettings::default(),
+            #[cfg(feature = "profiling")]
+            frame_stats: FrameStatsHistory::default(),
+            dispatch_trace: None,
+            debug_overlay: DebugOverlayFlags::default(),
+            frame_hud: false,
+            hit_regions: Vec::new(),
+            layout_result: Rc::new(LayoutResult::default()),
+            scroll_offsets: FastHashMap::default(),
+            callback_panic: None,
+            dom_error: None,
+            ui_reload_error: None,
+            #[cfg(feature = "html-export")]
+            exported_frame: None,
         }
     }
 }
@@ -336,10 +442,138 @@ impl WindowState
         self.hovered_file.as_ref()
     }
 
+    /// Returns the files, text and custom formats that were dropped onto the window
+    /// in this frame - use this in `On::DroppedFile` and `On::DroppedData` callbacks,
+    /// see the `drag_drop` module
+    pub fn get_dropped_data(&self) -> Option<&DroppedData> {
+        self.dropped_data.as_ref()
+    }
+
     pub fn get_last_motion(&self) -> Option<(AxisId, f64)> {
         self.last_motion
     }
 
+    /// Returns the timings and statistics of the last frame that was drawn
+    /// (or an empty `FrameStats` if no frame has been drawn yet)
+    #[cfg(feature = "profiling")]
+    pub fn last_frame_stats(&self) -> FrameStats {
+        self.frame_stats.last().cloned().unwrap_or_default()
+    }
+
+    /// Returns the timings and statistics of the last `FRAME_STATS_HISTORY_LEN` frames
+    #[cfg(feature = "profiling")]
+    pub fn frame_stats_history(&self) -> &FrameStatsHistory {
+        &self.frame_stats
+    }
+
+    /// Starts or stops recording how the input events of this window are dispatched
+    /// to the callbacks, see `take_dispatch_trace()`. Disabling the tracing
+    /// throws away the events that haven't been taken yet.
+    pub fn set_dispatch_tracing(&mut self, enabled: bool) {
+        if enabled != self.dispatch_trace.is_some() {
+            self.dispatch_trace = if enabled { Some(DispatchTrace::default()) } else { None };
+        }
+    }
+
+    pub fn is_dispatch_tracing_enabled(&self) -> bool {
+        self.dispatch_trace.is_some()
+    }
+
+    /// Returns the events that were dispatched since the last call (oldest event first)
+    /// and clears the trace. Only the last `DISPATCH_TRACE_MAX_LEN` events are kept.
+    /// Always empty if tracing isn't enabled via `set_dispatch_tracing(true)`.
+    pub fn take_dispatch_trace(&mut self) -> Vec<DispatchTraceEvent> {
+        self.dispatch_trace.as_mut().map(|t| t.take()).unwrap_or_default()
+    }
+
+    /// Draws the selected layout debugging information (bounding boxes, padding,
+    /// baselines, ...) on top of the window content. The display list can't be
+    /// cached from one frame to the next while any overlay is enabled.
+    pub fn set_debug_overlay(&mut self, flags: DebugOverlayFlags) {
+        self.debug_overlay = flags;
+    }
+
+    pub fn get_debug_overlay(&self) -> DebugOverlayFlags {
+        self.debug_overlay
+    }
+
+    /// Shows or hides the FPS and frame-budget HUD in the top right corner of the
+    /// window, see the [`frame_hud`](../frame_hud/index.html) module. Set it on the
+    /// `state` of the `WindowCreateOptions` to show the HUD from the first frame on.
+    /// The HUD is only drawn with the `profiling` feature.
+    pub fn set_frame_hud(&mut self, visible: bool) {
+        self.frame_hud = visible;
+    }
+
+    pub fn get_frame_hud(&self) -> bool {
+        self.frame_hud
+    }
+
+    /// Returns the numbers that the frame HUD shows (or would show, if it is hidden)
+    /// in the next frame, computed from the `frame_stats_history()`
+    #[cfg(feature = "profiling")]
+    pub fn frame_hud_data(&self) -> FrameHudData {
+        FrameHudData::from_history(&self.frame_stats)
+    }
+
+    /// Writes the last frame that was drawn to `path` as a static HTML document: every
+    /// node becomes an absolutely positioned element with the solved rectangle and the
+    /// computed colors, borders and fonts of the node, images are embedded as PNGs.
+    /// See the [`html_export`](../html_export/index.html) module for what is approximated.
+    #[cfg(feature = "html-export")]
+    pub fn export_frame_html(&self, path: &Path) -> Result<(), ExportError> {
+        write_frame_html(self.exported_frame.as_ref().map(|frame| &**frame), &self.title, path)
+    }
+
+    /// Returns all nodes under the `point` (in logical pixels, relative to the window),
+    /// topmost first, as laid out in the last frame.
+    ///
+    /// Uses the same tagged rectangles that the event loop hit-tests against, so the
+    /// result matches the nodes that receive the mouse events at that point. Nodes without
+    /// a hit-testing tag (i.e. nodes without callbacks, not draggable, not focusable)
+    /// are not returned, see the [`hit_test`](../hit_test/index.html) module.
+    ///
+    /// Nodes (or parts of nodes) that are clipped away by a parent with `overflow: hidden`
+    /// or scrolled out of view are not returned.
+    pub fn hit_test(&self, point: LogicalPosition) -> Vec<::hit_test::HitTestItem> {
+        hit_test_regions(&self.hit_regions, &self.scroll_offsets, point)
+    }
+
+    /// Returns the solved layout of the last frame that was drawn: the margin, border,
+    /// padding and content box of every node, the scrollable size of scroll containers,
+    /// whether a node was clipped away entirely and the order in which the nodes were drawn.
+    ///
+    /// Empty until the first frame has been drawn, see the
+    /// [`layout_result`](../layout_result/index.html) module.
+    pub fn get_layout_result(&self) -> &LayoutResult {
+        &self.layout_result
+    }
+
+    /// Returns the panic that is currently shown in the error overlay of this window,
+    /// see the [`error_overlay`](../error_overlay/index.html) module.
+    pub fn get_callback_panic(&self) -> Option<&CallbackPanic> {
+        self.callback_panic.as_ref()
+    }
+
+    /// Hides the error overlay, same as pressing Escape or clicking on the window
+    pub fn dismiss_callback_panic(&mut self) {
+        self.callback_panic = None;
+    }
+
+    /// Returns why the last DOM returned by `Layout::layout()` is invalid (see
+    /// `Dom::validate()`), `None` if it was valid. While the DOM is invalid, the
+    /// window keeps showing the last valid frame.
+    pub fn get_dom_error(&self) -> Option<DomError> {
+        self.dom_error
+    }
+
+    /// Returns why the markup of `App::with_hot_reload_ui()` couldn't be loaded,
+    /// `None` if it is valid. The error is shown in the error overlay until the
+    /// markup is fixed, meanwhile the window keeps the last valid UI.
+    pub fn get_ui_reload_error(&self) -> Option<&str> {
+        self.ui_reload_error.as_ref().map(|e| e.as_str())
+    }
+
     /// Returns the window state of the previous frame, useful for calculating
     /// metrics for dragging motions. Note that you can't call this function
     /// recursively - calling `get_previous_window_state()` on the returned
@@ -348,6 +582,30 @@ impl WindowState
         self.previous_window_state.as_ref()
     }
 
+    /// Moves the focused and hovered nodes (and the focus target of the last callbacks)
+    /// to the `NodeId`s of the new frame, see `Window::migrate_node_ids`.
+    /// Nodes that were removed lose their focus / hover state.
+    pub(crate) fn migrate_node_ids(&mut self, mapping: &NodeIdMapping) {
+
+        self.focused_node = self.focused_node.and_then(|node_id| mapping.get(node_id));
+
+        self.hovered_nodes = self.hovered_nodes.iter()
+            .filter_map(|(node_id, item)| Some((mapping.get(*node_id)?, item.clone())))
+            .collect();
+
+        if let Some(FocusTarget::Id(node_id)) = self.pending_focus_target.clone() {
+            self.pending_focus_target = Some(mapping.get(node_id).map(FocusTarget::Id).unwrap_or(FocusTarget::NoFocus));
+        }
+
+        if let Some(last_click) = &mut self.last_click {
+            last_click.nodes = last_click.nodes.iter().filter_map(|node_id| mapping.get(*node_id)).collect();
+        }
+
+        if let Some(previous_window_state) = &mut self.previous_window_state {
+            previous_window_state.migrate_node_ids(mapping);
+        }
+    }
+
     /// Determine which event / which callback(s) should be called and in which order
     ///
     /// This function also updates / mutates the current window state, so that
@@ -356,7 +614,8 @@ impl WindowState
         &mut self,
         hit_test_items: &[HitTestItem],
         event: &Event,
-        ui_state: &UiState<T>
+        ui_state: &UiState<T>,
+        mut trace: Option<&mut DispatchTraceEvent>,
     ) -> CallbacksOfHitTest<T>
     {
         use std::collections::BTreeSet;
@@ -379,13 +638,6 @@ impl WindowState
         let current_window_events = get_window_events(self, event);
 
         let current_hover_events = get_hover_events(&current_window_events);
-        let current_focus_events = get_focus_events(&current_hover_events);
-
-        let event_was_mouse_down = if let WindowEvent::MouseInput { state: ElementState::Pressed, .. } = event { true } else { false };
-        let event_was_mouse_release = if let WindowEvent::MouseInput { state: ElementState::Released, .. } = event { true } else { false };
-
-        // TODO: If the current mouse is down, but the event
-        // wasn't a click, that means it was a drag
 
         // Figure out what the hovered NodeIds are
         let new_hit_node_ids: BTreeMap<NodeId, HitTestItem> = hit_test_items.iter().filter_map(|hit_test_item| {
@@ -394,6 +646,33 @@ impl WindowState
             .map(|node_id| (*node_id, hit_test_item.clone()))
         }).collect();
 
+        // A double click only reaches the nodes that were hit by both clicks, the
+        // `MouseUp` events of both clicks are delivered as usual
+        let (double_click_nodes, double_click_events) = match event {
+            WindowEvent::MouseInput { state: ElementState::Released, button, .. } => {
+                let hit_nodes = new_hit_node_ids.keys().cloned().collect();
+                let double_click_nodes = self.update_last_click(*button, hit_nodes, Instant::now());
+                let double_click_events = if double_click_nodes.is_empty() { HashSet::new() } else { get_double_click_events(*button) };
+                (double_click_nodes, double_click_events)
+            },
+            _ => (BTreeSet::new(), HashSet::new()),
+        };
+
+        let mut current_focus_events = get_focus_events(&current_hover_events);
+        current_focus_events.extend(get_focus_events(&double_click_events));
+
+        if let Some(trace) = trace.as_mut() {
+            let hover_events = current_hover_events.union(&double_click_events).cloned().collect();
+            trace.record_hit_test(hit_test_items, ui_state);
+            trace.record_filters(&current_window_events, &hover_events, &current_focus_events, &current_desktop_events);
+        }
+
+        let event_was_mouse_down = if let WindowEvent::MouseInput { state: ElementState::Pressed, .. } = event { true } else { false };
+        let event_was_mouse_release = if let WindowEvent::MouseInput { state: ElementState::Released, .. } = event { true } else { false };
+
+        // TODO: If the current mouse is down, but the event
+        // wasn't a click, that means it was a drag
+
         // Figure out what the current focused NodeId is
         if event_was_mouse_down || event_was_mouse_release {
 
@@ -437,8 +716,8 @@ impl WindowState
                 // Insert all normal Hover events
                 if let Some(ui_state_hover_event_filters) = ui_state.$hover_callbacks.get($node_id) {
                     for current_hover_event in &$current_hover_events {
-                        if let Some(callback) = ui_state_hover_event_filters.get(current_hover_event) {
-                            normal_hover_callbacks.insert(EventFilter::$event_filter(*current_hover_event), *callback);
+                        if let Some(callback) = find_callback(ui_state_hover_event_filters, current_hover_event) {
+                            normal_hover_callbacks.insert(EventFilter::$event_filter(*current_hover_event), callback);
                         }
                     }
                 }
@@ -449,8 +728,8 @@ impl WindowState
                 // Insert all default Hover events
                 if let Some(ui_state_hover_default_event_filters) = ui_state.$hover_default_callbacks.get($node_id) {
                     for current_hover_event in &$current_hover_events {
-                        if let Some(callback_id) = ui_state_hover_default_event_filters.get(current_hover_event) {
-                            default_hover_callbacks.insert(EventFilter::$event_filter(*current_hover_event), *callback_id);
+                        if let Some(callback_id) = find_callback(ui_state_hover_default_event_filters, current_hover_event) {
+                            default_hover_callbacks.insert(EventFilter::$event_filter(*current_hover_event), callback_id);
                         }
                     }
                 }
@@ -502,6 +781,9 @@ impl WindowState
         // Insert (normal + default) hover events
         for (hover_node_id, hit_test_item) in &new_hit_node_ids {
             insert_callbacks!(hover_node_id, Some(hit_test_item.clone()), hover_callbacks, hover_default_callbacks, current_hover_events, Hover);
+            if double_click_nodes.contains(hover_node_id) {
+                insert_callbacks!(hover_node_id, Some(hit_test_item.clone()), hover_callbacks, hover_default_callbacks, double_click_events, Hover);
+            }
         }
 
         // Insert (normal + default) focus events
@@ -553,16 +835,16 @@ impl WindowState
 
                 // Insert all normal Hover(MouseEnter) events
                 if let Some(ui_state_hover_event_filters) = ui_state.hover_callbacks.get(&$node_id) {
-                    if let Some(callback) = ui_state_hover_event_filters.get(&HoverEventFilter::$event_filter) {
-                        normal_callbacks.insert(EventFilter::Hover(HoverEventFilter::$event_filter), *callback);
+                    if let Some(callback) = find_callback(ui_state_hover_event_filters, &HoverEventFilter::$event_filter) {
+                        normal_callbacks.insert(EventFilter::Hover(HoverEventFilter::$event_filter), callback);
                     }
                 }
 
                 // Insert all normal Focus(MouseEnter) events
                 if node_is_focused {
                     if let Some(ui_state_focus_event_filters) = ui_state.focus_callbacks.get(&$node_id) {
-                        if let Some(callback) = ui_state_focus_event_filters.get(&FocusEventFilter::$event_filter) {
-                            normal_callbacks.insert(EventFilter::Focus(FocusEventFilter::$event_filter), *callback);
+                        if let Some(callback) = find_callback(ui_state_focus_event_filters, &FocusEventFilter::$event_filter) {
+                            normal_callbacks.insert(EventFilter::Focus(FocusEventFilter::$event_filter), callback);
                         }
                     }
                 }
@@ -572,16 +854,16 @@ impl WindowState
 
                 // Insert all default Hover(MouseEnter) events
                 if let Some(ui_state_hover_default_event_filters) = ui_state.hover_default_callbacks.get(&$node_id) {
-                    if let Some(callback_id) = ui_state_hover_default_event_filters.get(&HoverEventFilter::$event_filter) {
-                        default_callbacks.insert(EventFilter::Hover(HoverEventFilter::$event_filter), *callback_id);
+                    if let Some(callback_id) = find_callback(ui_state_hover_default_event_filters, &HoverEventFilter::$event_filter) {
+                        default_callbacks.insert(EventFilter::Hover(HoverEventFilter::$event_filter), callback_id);
                     }
                 }
 
                 // Insert all default Focus(MouseEnter) events
                 if node_is_focused {
                     if let Some(ui_state_focus_default_event_filters) = ui_state.focus_default_callbacks.get(&$node_id) {
-                        if let Some(callback_id) = ui_state_focus_default_event_filters.get(&FocusEventFilter::$event_filter) {
-                            default_callbacks.insert(EventFilter::Focus(FocusEventFilter::$event_filter), *callback_id);
+                        if let Some(callback_id) = find_callback(ui_state_focus_default_event_filters, &FocusEventFilter::$event_filter) {
+                            default_callbacks.insert(EventFilter::Focus(FocusEventFilter::$event_filter), callback_id);
                         }
                     }
                 }
@@ -677,17 +959,66 @@ impl WindowState
                                 nodes_with_callbacks.entry(*node_id)
                                 .or_insert_with(|| DetermineCallbackResult::default())
                                 .normal_callbacks.insert(EventFilter::Not(*event_filter), *event_callback);
+                            } else if let Some(trace) = trace.as_mut() {
+                                trace.skip(*node_id, EventFilter::Not(*event_filter), SkipReason::NotFilterExcluded);
                             }
                         }
-                        // TODO: Same thing for default callbacks here
                     },
                     NotEventFilter::Focus(f) => {
                         // TODO: Same thing for focus
-                    }
+                    },
+                    NotEventFilter::Window(w) => {
+                        if !current_window_events.contains(w) {
+                            continue;
+                        }
+                        // The window event didn't happen "on" the node if it is neither hovered nor focused
+                        if !new_hit_node_ids.contains_key(node_id) && self.focused_node != Some(*node_id) {
+                            nodes_with_callbacks.entry(*node_id)
+                            .or_insert_with(|| DetermineCallbackResult::default())
+                            .normal_callbacks.insert(EventFilter::Not(*event_filter), *event_callback);
+                        } else if let Some(trace) = trace.as_mut() {
+                            trace.skip(*node_id, EventFilter::Not(*event_filter), SkipReason::NotFilterExcluded);
+                        }
+                    },
                 }
             }
         }
 
+        // Insert default NotEventFilter callbacks - these fire whenever the event happened
+        // and the node wasn't hit (or for window events, wasn't hit or focused), even if no
+        // other node has a callback for the event (so that widgets can close their popups
+        // when clicking on an empty area)
+        for (node_id, not_event_filter_callback_list) in &ui_state.not_default_callbacks {
+            for (event_filter, callback_id) in not_event_filter_callback_list {
+                let is_excluded = match event_filter {
+                    NotEventFilter::Hover(h) => {
+                        if !current_hover_events.contains(h) {
+                            continue;
+                        }
+                        new_hit_node_ids.contains_key(node_id)
+                    },
+                    NotEventFilter::Focus(_) => continue, // TODO: Same thing for focus
+                    NotEventFilter::Window(w) => {
+                        if !current_window_events.contains(w) {
+                            continue;
+                        }
+                        new_hit_node_ids.contains_key(node_id) || self.focused_node == Some(*node_id)
+                    },
+                };
+                if !is_excluded {
+                    nodes_with_callbacks.entry(*node_id)
+                    .or_insert_with(|| DetermineCallbackResult::default())
+                    .default_callbacks.insert(EventFilter::Not(*event_filter), *callback_id);
+                } else if let Some(trace) = trace.as_mut() {
+                    trace.skip(*node_id, EventFilter::Not(*event_filter), SkipReason::NotFilterExcluded);
+                }
+            }
+        }
+
+        if let Some(trace) = trace.as_mut() {
+            trace.record_skipped_callbacks(ui_state, &new_hit_node_ids, self.focused_node, &current_focus_events, &nodes_with_callbacks);
+        }
+
         self.hovered_nodes = new_hit_node_ids;
         self.previous_window_state = Some(previous_state);
 
@@ -698,10 +1029,31 @@ impl WindowState
         }
     }
 
+    /// Remembers the click for detecting double clicks. If the click completes a double click,
+    /// returns the nodes that were hit by both clicks and forgets the click, so that a third
+    /// click starts a new double click.
+    fn update_last_click(&mut self, button: MouseButton, hit_nodes: BTreeSet<NodeId>, now: Instant) -> BTreeSet<NodeId> {
+
+        let position = self.mouse_state.cursor_pos;
+        let is_double_click = self.last_click.as_ref()
+            .map(|last_click| last_click.is_double_click(button, position, now, &self.double_click))
+            .unwrap_or(false);
+
+        if is_double_click {
+            let last_click = self.last_click.take().unwrap();
+            last_click.nodes.intersection(&hit_nodes).cloned().collect()
+        } else {
+            self.last_click = Some(LastClick { button, time: now, position, nodes: hit_nodes });
+            BTreeSet::new()
+        }
+    }
+
     pub(crate) fn update_window_state(&mut self, events: &[Event], awakened_task: bool) -> (FrameEventInfo, bool) {
         let mut frame_event_info = FrameEventInfo::default();
         let mut should_window_close = false;
 
+        self.dropped_data = None;
+
         for event in events {
             if window_should_close(event, &mut frame_event_info, awakened_task) {
                 should_window_close = true;
@@ -829,6 +1181,7 @@ impl WindowState
                     },
                     WindowEvent::DroppedFile(path) => {
                         self.hovered_file = Some(path.clone());
+                        add_dropped_data(&mut self.dropped_data, DroppedData::from_file(path.clone()));
                     },
                     WindowEvent::HoveredFileCancelled => {
                         self.hovered_file = None;
@@ -910,8 +1263,21 @@ fn get_window_events(window_state: &mut WindowState, event: &WindowEvent) -> Has
                 _ => { }
             }
         },
-        WindowEvent::MouseWheel { .. } => {
+        WindowEvent::MouseWheel { delta, .. } => {
             events_vec.insert(WindowEventFilter::Scroll);
+            let (delta_x, delta_y) = match delta {
+                MouseScrollDelta::PixelDelta(LogicalPosition { x, y }) => (*x, *y),
+                MouseScrollDelta::LineDelta(x, y) => (*x as f64, *y as f64),
+            };
+            // A diagonal scroll on a touchpad is both a vertical and a horizontal scroll
+            if delta_y > 0.0 {
+                events_vec.insert(WindowEventFilter::ScrollUp);
+            } else if delta_y < 0.0 {
+                events_vec.insert(WindowEventFilter::ScrollDown);
+            }
+            if delta_x != 0.0 {
+                events_vec.insert(WindowEventFilter::ScrollHorizontal);
+            }
         },
         WindowEvent::KeyboardInput {
             input: KeyboardInput { state: ElementState::Pressed, virtual_keycode: Some(_), .. }, ..
@@ -946,6 +1312,25 @@ fn get_window_events(window_state: &mut WindowState, event: &WindowEvent) -> Has
         WindowEvent::CursorLeft { .. } => {
             events_vec.insert(WindowEventFilter::MouseLeave);
         },
+        WindowEvent::Resized(size) => {
+            // Updated here (and not after the callbacks) so that the
+            // callbacks can read the new size from the `CallbackInfo`
+            window_state.size.dimensions = *size;
+            events_vec.insert(WindowEventFilter::Resized);
+            if size.width <= 0.0 || size.height <= 0.0 {
+                events_vec.insert(WindowEventFilter::Minimized);
+            }
+        },
+        WindowEvent::Moved(position) => {
+            window_state.position = Some(*position);
+            events_vec.insert(WindowEventFilter::Moved);
+        },
+        WindowEvent::CloseRequested => {
+            events_vec.insert(WindowEventFilter::CloseRequested);
+        },
+        WindowEvent::Destroyed => {
+            events_vec.insert(WindowEventFilter::Closed);
+        },
         _ => { }
     }
     events_vec
@@ -959,6 +1344,18 @@ fn get_focus_events(input: &HashSet<HoverEventFilter>) -> HashSet<FocusEventFilt
     input.iter().filter_map(|hover_event| hover_event.to_focus_event_filter()).collect()
 }
 
+fn get_double_click_events(button: MouseButton) -> HashSet<HoverEventFilter> {
+    let mut events = HashSet::new();
+    events.insert(HoverEventFilter::DoubleClick);
+    match button {
+        MouseButton::Left => { events.insert(HoverEventFilter::LeftDoubleClick); },
+        MouseButton::Right => { events.insert(HoverEventFilter::RightDoubleClick); },
+        MouseButton::Middle => { events.insert(HoverEventFilter::MiddleDoubleClick); },
+        MouseButton::Other(_) => { },
+    }
+    events
+}
+
 /// Pre-filters any events that are not handled by the framework yet, since it would be wasteful
 /// to process them. Modifies the `frame_event_info` so that the
 ///
@@ -980,6 +1377,11 @@ pub(crate) fn window_should_close(event: &Event, frame_event_info: &mut FrameEve
                     frame_event_info.new_window_size = Some(*wh);
                     frame_event_info.is_resize_event = true;
                     frame_event_info.should_redraw_window = true;
+                    // For the `WindowEventFilter::Resized` callbacks
+                    frame_event_info.should_hittest = true;
+                },
+                WindowEvent::Moved(_) => {
+                    frame_event_info.should_hittest = true;
                 },
                 WindowEvent::Refresh => {
                     frame_event_info.should_redraw_window = true;
@@ -989,8 +1391,8 @@ pub(crate) fn window_should_close(event: &Event, frame_event_info: &mut FrameEve
                     frame_event_info.should_redraw_window = true;
                 },
                 WindowEvent::CloseRequested | WindowEvent::Destroyed => {
-                    // TODO: Callback the windows onclose method
-                    // (ex. for implementing a "do you really want to close" dialog)
+                    // The `CloseRequested` and `Closed` callbacks are
+                    // called before the window is closed, see `is_close_event`
                     return true;
                 },
                 WindowEvent::KeyboardInput { .. } |
@@ -1015,6 +1417,83 @@ pub(crate) fn window_should_close(event: &Event, frame_event_info: &mut FrameEve
     false
 }
 
+/// Returns the callbacks for the data that the `DragDropBackend` of the window received:
+/// the `On::DroppedData` callbacks of the nodes under the cursor and the
+/// `WindowEventFilter::DroppedData` callbacks
+pub(crate) fn determine_drop_callbacks<T: Layout>(hit_test_items: &[HitTestItem], ui_state: &UiState<T>) -> CallbacksOfHitTest<T> {
+
+    let mut callbacks = CallbacksOfHitTest::default();
+
+    for (window_node_id, window_callbacks) in &ui_state.window_callbacks {
+        if let Some(callback) = find_callback(window_callbacks, &WindowEventFilter::DroppedData) {
+            callbacks.nodes_with_callbacks.entry(*window_node_id)
+                .or_insert_with(DetermineCallbackResult::default)
+                .normal_callbacks.insert(EventFilter::Window(WindowEventFilter::DroppedData), callback);
+        }
+    }
+
+    for hit_test_item in hit_test_items {
+        let hover_node_id = match ui_state.tag_ids_to_node_ids.get(&hit_test_item.tag.0) {
+            Some(node_id) => *node_id,
+            None => continue,
+        };
+        let callback = ui_state.hover_callbacks.get(&hover_node_id)
+            .and_then(|hover_callbacks| find_callback(hover_callbacks, &HoverEventFilter::DroppedData));
+        if let Some(callback) = callback {
+            let callback_result = callbacks.nodes_with_callbacks.entry(hover_node_id)
+                .or_insert_with(DetermineCallbackResult::default);
+            callback_result.hit_test_item = Some(hit_test_item.clone());
+            callback_result.normal_callbacks.insert(EventFilter::Hover(HoverEventFilter::DroppedData), callback);
+        }
+    }
+
+    callbacks
+}
+
+/// Returns whether the event closes the window, see `window_should_close`
+pub(crate) fn is_close_event(event: &Event) -> bool {
+    match event {
+        Event::WindowEvent { event: WindowEvent::CloseRequested, .. } |
+        Event::WindowEvent { event: WindowEvent::Destroyed, .. } => true,
+        _ => false,
+    }
+}
+
+/// Calls the callbacks of the close events in the `events` and returns whether the window
+/// should close. `call_close_callbacks` calls the callbacks of one event and returns whether
+/// one of them called `CallbackInfo::prevent_close()`.
+///
+/// The window stays open if any callback of a `CloseRequested` event prevented closing
+/// (a veto during any other event is ignored), unless the window was already destroyed.
+/// Each call only judges the given `events`, so a veto never carries over to the next frame.
+/// If the window stays open, the close events are removed from the `events`, since their
+/// callbacks have already been called.
+pub(crate) fn handle_close_events<E, F>(events: &mut Vec<Event>, mut call_close_callbacks: F) -> Result<bool, E>
+    where F: FnMut(&Event) -> Result<bool, E>
+{
+    let mut close_prevented = false;
+    let mut window_destroyed = false;
+
+    for event in events.iter().filter(|event| is_close_event(event)) {
+        let callbacks_prevent_close = call_close_callbacks(event)?;
+        match event {
+            Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
+                close_prevented = close_prevented || callbacks_prevent_close;
+            },
+            // A window that was already destroyed can't be kept open
+            _ => window_destroyed = true,
+        }
+    }
+
+    if !close_prevented || window_destroyed {
+        return Ok(true);
+    }
+
+    // The close callbacks have already been called, handle the rest of the events as usual
+    events.retain(|event| !is_close_event(event));
+    Ok(false)
+}
+
 fn update_mouse_cursor(window: &Window, old: &MouseCursor, new: &MouseCursor) {
     if *old != *new {
         window.set_cursor(*new);
@@ -1079,4 +1558,435 @@ pub fn keymap<T: Layout>(
         })
         .next()
         .and_then(|(_, callback)| (callback)(app_state, event))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+use {
+    dom::{Dom, NodeType, On, TabIndex, Redraw, DontRedraw},
+    dispatch_trace::{hit_test_item, window_event},
+};
+
+#[cfg(test)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::new(NodeType::Div)
+    }
+}
+
+#[cfg(test)]
+fn redraw(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+    Redraw
+}
+
+#[cfg(test)]
+fn dont_redraw(_: &mut AppState<TestLayout>, _: &mut CallbackInfo<TestLayout>) -> UpdateScreen {
+    DontRedraw
+}
+
+#[cfg(test)]
+fn mouse_wheel(delta: ::glium::glutin::MouseScrollDelta) -> Event {
+    use glium::glutin::{WindowId, DeviceId, WindowEvent, TouchPhase, ModifiersState};
+    Event::WindowEvent {
+        window_id: unsafe { WindowId::dummy() },
+        event: WindowEvent::MouseWheel {
+            device_id: unsafe { DeviceId::dummy() },
+            delta,
+            phase: TouchPhase::Moved,
+            modifiers: ModifiersState::default(),
+        },
+    }
+}
+
+#[cfg(test)]
+fn mouse_up(button: MouseButton) -> Event {
+    use glium::glutin::DeviceId;
+    window_event(WindowEvent::MouseInput {
+        device_id: unsafe { DeviceId::dummy() },
+        state: ElementState::Released,
+        button,
+        modifiers: ModifiersState::default(),
+    })
+}
+
+/// Dispatches the `event` with the cursor over the `hit_nodes`, returns the filters
+/// of the callbacks of the `node_id` that `determine_callbacks` selected
+#[cfg(test)]
+fn called_filters(
+    window_state: &mut WindowState,
+    ui_state: &UiState<TestLayout>,
+    event: &Event,
+    hit_nodes: &[NodeId],
+    node_id: NodeId)
+-> Vec<EventFilter>
+{
+    let tags = ui_state.node_ids_to_tag_ids();
+    let hit_test_items = hit_nodes.iter().map(|hit_node| hit_test_item(tags[hit_node])).collect::<Vec<_>>();
+    let callbacks = window_state.determine_callbacks(&hit_test_items, event, ui_state, None);
+    callbacks.nodes_with_callbacks.get(&node_id)
+        .map(|result| result.normal_callbacks.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_double_click() {
+
+    use std::time::Duration;
+    use dom::HoverEventFilter::*;
+
+    let ui_state = UiState::from_dom(Dom::new(NodeType::Div)
+        .with_child(Dom::new(NodeType::Div)
+            .with_callback(On::MouseUp, Callback(dont_redraw))
+            .with_callback(On::DoubleClick, Callback(redraw))
+            .with_callback(On::LeftDoubleClick, Callback(redraw))
+            .with_callback(On::RightDoubleClick, Callback(redraw))
+            .with_callback(On::MiddleDoubleClick, Callback(redraw)))
+        .with_child(Dom::new(NodeType::Div)
+            .with_callback(On::DoubleClick, Callback(redraw))));
+
+    let item = NodeId::new(1);
+    let other = NodeId::new(2);
+
+    // Returns the filters of the callbacks of the clicked node that are going to be called
+    let click = |window_state: &mut WindowState, node_id: NodeId, button: MouseButton| {
+        called_filters(window_state, &ui_state, &mouse_up(button), &[node_id], node_id)
+    };
+
+    // The MouseUp callback is called for both clicks, the double click is
+    // delivered on the second click, a third click starts a new double click
+    let mut window_state = WindowState::default();
+    assert_eq!(click(&mut window_state, item, MouseButton::Left), vec![EventFilter::Hover(MouseUp)]);
+    assert_eq!(click(&mut window_state, item, MouseButton::Left), vec![
+        EventFilter::Hover(MouseUp),
+        EventFilter::Hover(DoubleClick),
+        EventFilter::Hover(LeftDoubleClick),
+    ]);
+    assert_eq!(click(&mut window_state, item, MouseButton::Left), vec![EventFilter::Hover(MouseUp)]);
+
+    // The right and the middle button have their own double click filters
+    for &(button, double_click) in &[(MouseButton::Right, RightDoubleClick), (MouseButton::Middle, MiddleDoubleClick)] {
+        let mut window_state = WindowState::default();
+        assert_eq!(click(&mut window_state, item, button), vec![EventFilter::Hover(MouseUp)]);
+        assert_eq!(click(&mut window_state, item, button), vec![
+            EventFilter::Hover(MouseUp),
+            EventFilter::Hover(DoubleClick),
+            EventFilter::Hover(double_click),
+        ]);
+    }
+
+    // Clicks with two different buttons aren't a double click
+    let mut window_state = WindowState::default();
+    click(&mut window_state, item, MouseButton::Left);
+    assert_eq!(click(&mut window_state, item, MouseButton::Right), vec![EventFilter::Hover(MouseUp)]);
+
+    // Clicks on two different nodes aren't a double click
+    let mut window_state = WindowState::default();
+    click(&mut window_state, item, MouseButton::Left);
+    assert_eq!(click(&mut window_state, other, MouseButton::Left), vec![]);
+
+    // Clicks that are too far apart in time aren't a double click
+    let mut window_state = WindowState::default();
+    window_state.double_click.interval = Duration::from_millis(0);
+    click(&mut window_state, item, MouseButton::Left);
+    assert_eq!(click(&mut window_state, item, MouseButton::Left), vec![EventFilter::Hover(MouseUp)]);
+
+    // Clicks that are too far apart on the screen aren't a double click, the second
+    // click starts a new double click at its own position
+    let mut window_state = WindowState::default();
+    window_state.mouse_state.cursor_pos = Some(LogicalPosition::new(10.0, 10.0));
+    click(&mut window_state, item, MouseButton::Left);
+    window_state.mouse_state.cursor_pos = Some(LogicalPosition::new(15.0, 10.0));
+    assert_eq!(click(&mut window_state, item, MouseButton::Left), vec![EventFilter::Hover(MouseUp)]);
+    window_state.mouse_state.cursor_pos = Some(LogicalPosition::new(18.0, 10.0));
+    assert_eq!(click(&mut window_state, item, MouseButton::Left), vec![
+        EventFilter::Hover(MouseUp),
+        EventFilter::Hover(DoubleClick),
+        EventFilter::Hover(LeftDoubleClick),
+    ]);
+}
+
+#[test]
+fn test_double_click_with_redraw_between_the_clicks() {
+
+    use dom::{TagIdGenerator, HoverEventFilter::*};
+    use node_identity::resolve_node_identities;
+
+    // The second frame optionally inserts a node in front of the item, so that the item moves
+    let dom = |insert_node: bool| {
+        let mut dom = Dom::new(NodeType::Div);
+        if insert_node {
+            dom.add_child(Dom::new(NodeType::Div).with_callback(On::MouseUp, Callback(dont_redraw)));
+        }
+        dom.with_child(Dom::new(NodeType::Div)
+            .with_key(1)
+            .with_callback(On::MouseUp, Callback(redraw))
+            .with_callback(On::DoubleClick, Callback(redraw)))
+    };
+
+    // Clicks the item, redraws the UI (i.e. because the MouseUp callback returned `Redraw`)
+    // and clicks the item again, which is the node `item` in the second frame
+    let click_redraw_click = |insert_node: bool, item: NodeId| {
+        let mut tag_ids = TagIdGenerator::new();
+        let first_frame = dom(false).into_ui_state(&mut tag_ids);
+        let mut window_state = WindowState::default();
+        called_filters(&mut window_state, &first_frame, &mouse_up(MouseButton::Left), &[NodeId::new(1)], NodeId::new(1));
+
+        tag_ids.begin_frame();
+        let second_frame = dom(insert_node).into_ui_state(&mut tag_ids);
+        window_state.migrate_node_ids(&resolve_node_identities(&first_frame.dom, &second_frame.dom));
+
+        // The tags aren't restarted, so the item is hit with a different tag than before
+        assert!(second_frame.node_ids_to_tag_ids()[&item] != first_frame.node_ids_to_tag_ids()[&NodeId::new(1)]);
+        called_filters(&mut window_state, &second_frame, &mouse_up(MouseButton::Left), &[item], item)
+    };
+
+    let double_click = vec![EventFilter::Hover(MouseUp), EventFilter::Hover(DoubleClick)];
+    assert_eq!(click_redraw_click(false, NodeId::new(1)), double_click);
+    assert_eq!(click_redraw_click(true, NodeId::new(2)), double_click);
+}
+
+#[test]
+fn test_scroll_direction_filters() {
+
+    use glium::glutin::{MouseScrollDelta, dpi::LogicalPosition};
+    use dom::HoverEventFilter::*;
+
+    let ui_state = UiState::from_dom(Dom::new(NodeType::Div)
+        .with_child(Dom::new(NodeType::Div)
+            .with_callback(On::Scroll, Callback(dont_redraw))
+            .with_callback(On::ScrollUp, Callback(redraw))
+            .with_callback(On::ScrollDown, Callback(redraw))
+            .with_callback(On::ScrollHorizontal, Callback(redraw))));
+
+    let canvas = NodeId::new(1);
+
+    // Returns the filters of the callbacks of the canvas that are going to be called
+    let scroll = |delta: MouseScrollDelta| {
+        called_filters(&mut WindowState::default(), &ui_state, &mouse_wheel(delta), &[canvas], canvas)
+    };
+
+    assert_eq!(scroll(MouseScrollDelta::LineDelta(0.0, 1.0)), vec![EventFilter::Hover(Scroll), EventFilter::Hover(ScrollUp)]);
+    assert_eq!(scroll(MouseScrollDelta::LineDelta(0.0, -1.0)), vec![EventFilter::Hover(Scroll), EventFilter::Hover(ScrollDown)]);
+    assert_eq!(scroll(MouseScrollDelta::PixelDelta(LogicalPosition::new(-12.0, 0.0))), vec![EventFilter::Hover(Scroll), EventFilter::Hover(ScrollHorizontal)]);
+    assert_eq!(scroll(MouseScrollDelta::PixelDelta(LogicalPosition::new(3.0, -5.0))), vec![
+        EventFilter::Hover(Scroll),
+        EventFilter::Hover(ScrollDown),
+        EventFilter::Hover(ScrollHorizontal),
+    ]);
+}
+
+#[test]
+fn test_window_lifecycle_filters() {
+
+    use glium::glutin::{WindowEvent, dpi::{LogicalSize, LogicalPosition}};
+    use dom::WindowEventFilter::*;
+
+    let ui_state = UiState::from_dom(Dom::new(NodeType::Div)
+        .with_callback(EventFilter::Window(Resized), Callback(redraw))
+        .with_callback(EventFilter::Window(Moved), Callback(redraw))
+        .with_callback(EventFilter::Window(Minimized), Callback(redraw))
+        .with_callback(EventFilter::Window(CloseRequested), Callback(redraw))
+        .with_callback(EventFilter::Window(Closed), Callback(redraw)));
+
+    let root = NodeId::new(0);
+
+    // Returns the filters of the window callbacks that are going to be called
+    let dispatch = |window_state: &mut WindowState, event: WindowEvent| {
+        called_filters(window_state, &ui_state, &window_event(event), &[], root)
+    };
+
+    let mut window_state = WindowState::default();
+
+    assert_eq!(dispatch(&mut window_state, WindowEvent::Resized(LogicalSize::new(300.0, 200.0))), vec![EventFilter::Window(Resized)]);
+    assert_eq!(window_state.size.dimensions, LogicalSize::new(300.0, 200.0));
+
+    assert_eq!(dispatch(&mut window_state, WindowEvent::Resized(LogicalSize::new(0.0, 0.0))), vec![
+        EventFilter::Window(Resized),
+        EventFilter::Window(Minimized),
+    ]);
+
+    assert_eq!(dispatch(&mut window_state, WindowEvent::Moved(LogicalPosition::new(40.0, 60.0))), vec![EventFilter::Window(Moved)]);
+    assert_eq!(window_state.position, Some(LogicalPosition::new(40.0, 60.0)));
+
+    assert_eq!(dispatch(&mut window_state, WindowEvent::CloseRequested), vec![EventFilter::Window(CloseRequested)]);
+    assert_eq!(dispatch(&mut window_state, WindowEvent::Destroyed), vec![EventFilter::Window(Closed)]);
+}
+
+#[test]
+fn test_not_window_event_filter() {
+
+    use glium::glutin::{WindowEvent, KeyboardInput, ElementState, VirtualKeyCode, DeviceId, ModifiersState};
+
+    // The shortcut is registered on the text field, so that it doesn't fire while typing
+    let shortcut = EventFilter::Not(NotEventFilter::Window(WindowEventFilter::VirtualKeyDown));
+    let ui_state = UiState::from_dom(Dom::new(NodeType::Div)
+        .with_child(Dom::new(NodeType::Div)
+            .with_tab_index(TabIndex::Auto)
+            .with_callback(shortcut, Callback(redraw))));
+
+    let text_field = NodeId::new(1);
+
+    let key_down = window_event(WindowEvent::KeyboardInput {
+        device_id: unsafe { DeviceId::dummy() },
+        input: KeyboardInput {
+            scancode: 0,
+            state: ElementState::Pressed,
+            virtual_keycode: Some(VirtualKeyCode::S),
+            modifiers: ModifiersState::default(),
+        },
+    });
+
+    // Returns whether the shortcut fires, given the focused and hovered state of the text field
+    let shortcut_fires = |focused: bool, hovered: bool| {
+        let mut window_state = WindowState::default();
+        if focused {
+            window_state.focused_node = Some(text_field);
+        }
+        let hit_nodes = if hovered { vec![text_field] } else { Vec::new() };
+        called_filters(&mut window_state, &ui_state, &key_down, &hit_nodes, text_field).contains(&shortcut)
+    };
+
+    assert!(shortcut_fires(false, false));
+    assert!(!shortcut_fires(true, false));
+    assert!(!shortcut_fires(false, true));
+}
+
+#[test]
+fn test_close_requested_can_be_prevented() {
+
+    use glium::glutin::DeviceId;
+
+    let close_requested = || window_event(WindowEvent::CloseRequested);
+    let destroyed = || window_event(WindowEvent::Destroyed);
+    let cursor_moved = || window_event(WindowEvent::CursorMoved {
+        device_id: unsafe { DeviceId::dummy() },
+        position: LogicalPosition::new(10.0, 10.0),
+        modifiers: ModifiersState::default(),
+    });
+
+    // Runs the close handling of one frame, the `vetoes` are the results of the close
+    // callbacks, one per close event. Returns whether the window closes and how many
+    // events are left for the rest of the frame.
+    let frame = |mut events: Vec<Event>, vetoes: &[bool]| {
+        let mut vetoes = vetoes.iter();
+        let closes = handle_close_events(&mut events, |event| -> Result<bool, ()> {
+            assert!(is_close_event(event));
+            Ok(*vetoes.next().expect("callbacks of a close event were called too often"))
+        }).unwrap();
+        assert_eq!(vetoes.next(), None, "callbacks of a close event weren't called");
+        (closes, events.len())
+    };
+
+    // Nobody objects, the window closes
+    assert!(frame(vec![close_requested()], &[false]).0);
+
+    // A veto keeps the window open, the other events of the frame are still handled,
+    // but the close events aren't handled a second time
+    assert_eq!(frame(vec![cursor_moved(), close_requested()], &[true]), (false, 1));
+
+    // One veto is enough, even if the callbacks of another close event don't object
+    assert_eq!(frame(vec![close_requested(), close_requested()], &[true, false]), (false, 0));
+
+    // The veto of the last frame doesn't keep the window open on the next `CloseRequested`
+    assert!(frame(vec![close_requested()], &[false]).0);
+
+    // A destroyed window can't be kept open
+    assert!(frame(vec![close_requested(), destroyed()], &[true, false]).0);
+
+    // `prevent_close()` only has an effect on `CloseRequested`
+    assert!(frame(vec![destroyed()], &[true]).0);
+}
+
+#[test]
+fn test_close_requested_callback_can_keep_the_window_open() {
+
+    use app::{CallbackEvent, CallCallbackReturn, call_normal_callbacks};
+    use window::new_window_id;
+
+    struct Editor {
+        dirty: bool,
+    }
+
+    impl Layout for Editor {
+        fn layout(&self) -> Dom<Self> {
+            Dom::new(NodeType::Div)
+        }
+    }
+
+    // Keeps the window open while the document has unsaved changes
+    fn keep_open_if_dirty(app_state: &mut AppState<Editor>, event: &mut CallbackInfo<Editor>) -> UpdateScreen {
+        if app_state.data.lock().ok()?.dirty {
+            event.prevent_close();
+            Redraw
+        } else {
+            DontRedraw
+        }
+    }
+
+    let ui_state = UiState::from_dom(Dom::new(NodeType::Div)
+        .with_callback(EventFilter::Window(WindowEventFilter::CloseRequested), Callback(keep_open_if_dirty)));
+    let window_id = new_window_id();
+
+    // Dispatches a `CloseRequested` event to the callbacks of the `ui_state`, the same way
+    // the frame loop does, returns whether the window closes
+    let window_closes = |dirty: bool| {
+        let mut app_state = AppState::new(Editor { dirty });
+        let mut window_state = WindowState::default();
+        let mut events = vec![window_event(WindowEvent::CloseRequested)];
+        handle_close_events(&mut events, |event| -> Result<bool, ()> {
+            let callbacks = window_state.determine_callbacks(&[], event, &ui_state, None);
+            let callback_event = CallbackEvent {
+                window_id: &window_id,
+                ui_state: &ui_state,
+                hit_test_items: &[],
+                window_size: window_state.size.dimensions,
+                window_position: window_state.position,
+            };
+            let mut call_result = CallCallbackReturn::default();
+            call_normal_callbacks(&callbacks, &callback_event, &mut app_state, None, &mut None, &mut call_result);
+            Ok(call_result.close_prevented)
+        }).unwrap()
+    };
+
+    assert!(!window_closes(true));
+    assert!(window_closes(false));
+}
+
+#[test]
+fn test_dropped_data_callbacks() {
+
+    let ui_state = UiState::from_dom(Dom::new(NodeType::Div)
+        .with_callback(EventFilter::Window(WindowEventFilter::DroppedData), Callback(redraw))
+        .with_child(Dom::new(NodeType::Div)
+            .with_callback(On::DroppedData, Callback(redraw))
+            .with_callback(On::DroppedFile, Callback(dont_redraw)))
+        .with_child(Dom::new(NodeType::Div)
+            .with_callback(On::DroppedFile, Callback(dont_redraw))));
+
+    let root = NodeId::new(0);
+    let drop_target = NodeId::new(1);
+    let file_target = NodeId::new(2);
+    let tags = ui_state.node_ids_to_tag_ids();
+
+    // Returns the filters of the callbacks that a drop over the `hit_nodes` calls, per node
+    let dropped_on = |hit_nodes: &[NodeId]| {
+        let hit_test_items = hit_nodes.iter().map(|hit_node| hit_test_item(tags[hit_node])).collect::<Vec<_>>();
+        determine_drop_callbacks(&hit_test_items, &ui_state).nodes_with_callbacks.into_iter()
+            .map(|(node_id, result)| (node_id, result.normal_callbacks.keys().cloned().collect::<Vec<_>>()))
+            .collect::<Vec<_>>()
+    };
+
+    // The window callback is always called, the node under the cursor gets `On::DroppedData`,
+    // but not `On::DroppedFile`
+    assert_eq!(dropped_on(&[drop_target]), vec![
+        (root, vec![EventFilter::Window(WindowEventFilter::DroppedData)]),
+        (drop_target, vec![EventFilter::Hover(HoverEventFilter::DroppedData)]),
+    ]);
+
+    // A node that only accepts files doesn't get the data
+    assert_eq!(dropped_on(&[file_target]), vec![
+        (root, vec![EventFilter::Window(WindowEventFilter::DroppedData)]),
+    ]);
+}
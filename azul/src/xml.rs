@@ -1,11 +1,34 @@
 //! Module for parsing and loading a `Dom<T>` from a XML file
+//!
+//! `dom_from_xml()` converts markup to a `Dom`:
+//!
+//! - `<div>` is a `NodeType::Div`, `<p>Text</p>` is a `NodeType::Label` (whitespace
+//!   in the text is collapsed, like in HTML)
+//! - `<img src-id="logo"/>` is a `NodeType::Image` of the image that is registered
+//!   as `"logo"` in the `CallbackRegistry`
+//! - the `id` and `class` attributes are split at whitespace and become the IDs and classes
+//! - `onmouseup="save"`, `onleftmouseup="save"`, `onfocus="save"`, ... attach the callback
+//!   that is registered as `"save"` in the `CallbackRegistry`, for the `On` event with the
+//!   same name (lowercase, `onfocus` / `onblur` for `FocusReceived` / `FocusLost`)
+//! - other attributes are ignored
+//!
+//! `App::with_hot_reload_ui()` uses this to load the layout of the windows from a file
+//! that is re-parsed whenever it changes, see `XmlReloadHandler`.
 
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
 use {
-    dom::{Dom, Callback},
+    dom::{Dom, Callback, CallbackRegistry, On},
     traits::Layout,
+    diagnostics::{AzulError, report},
 };
 
+/// How often `XmlFileWatcher` checks the file for changes by default
+pub const DEFAULT_RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+
 /// Error that can happen during hot-reload -
 /// stringified, since it is only used for printing and is not exposed in the public API
 pub type SyntaxError = String;
@@ -21,6 +44,9 @@ pub type XmlNodeContent = String;
 pub type XmlAttributeKey = String;
 /// Value of an attribute, such as the "blue" in `<button color="blue">Hello</button>`.
 pub type XmlAttributeValue = String;
+/// Resolves the callback names of the `on...` attributes and the image names
+/// of the `src-id` attributes, see `Dom::from_xml`
+pub type XmlCallbackMap<T> = CallbackRegistry<T>;
 
 /// Represents one tag
 #[derive(Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -29,12 +55,29 @@ pub struct XmlNode {
     pub attributes: BTreeMap<XmlAttributeKey, XmlAttributeValue>,
     pub children: Vec<XmlNode>,
     pub content: Option<XmlNodeContent>,
+    /// Line of the opening tag (starting at 1), for error messages
+    pub line: usize,
 }
 
 /// Parses an XML style sheet and returns the root XML nodes
 /// (which, recursively, contain all children in a tree-like fashion)
-fn parse_tree(_input: &str) -> Result<XmlNode, XmlParseError> {
-    Ok(XmlNode::default())
+pub fn parse_tree(input: &str) -> Result<XmlNode, XmlParseError> {
+
+    let mut reader = XmlReader { chars: input.chars().collect(), pos: 0, line: 1 };
+
+    reader.skip_misc()?;
+    if reader.is_at_end() {
+        return Err(XmlParseError::NoRootComponent);
+    }
+
+    let root = reader.parse_element()?;
+
+    reader.skip_misc()?;
+    if !reader.is_at_end() {
+        return Err(XmlParseError::MultipleRootComponents);
+    }
+
+    Ok(root)
 }
 
 /// Trait that has to be implemented by all types
@@ -66,17 +109,635 @@ pub struct XmlComponentMap<T: Layout> {
     callbacks: BTreeMap<String, Callback<T>>,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum XmlParseError {
     /// The DOM can only have one root component, not multiple.
     MultipleRootComponents,
+    /// The document doesn't contain any element
+    NoRootComponent,
+    /// The markup is malformed (unclosed quotes, missing `>`, unknown entities, ...)
+    Syntax { line: usize, message: String },
+    /// `</b>` closes a `<a>`, the `line` is the line of the closing tag
+    MismatchedClosingTag { line: usize, expected: XmlTagName, found: XmlTagName },
+    /// The document ends before the tag opened at `line` is closed
+    UnclosedTag { line: usize, tag: XmlTagName },
+    /// The tag can't be converted to a DOM node
+    UnknownElement { line: usize, tag: XmlTagName },
+    /// The `on...` attribute doesn't name an `On` event
+    UnknownEvent { line: usize, attribute: XmlAttributeKey },
+    /// No callback is registered under the name
+    CallbackNotFound { line: usize, name: XmlAttributeValue },
+    /// The element requires an attribute that it doesn't have, such as the `src-id` of an `<img>`
+    MissingAttribute { line: usize, tag: XmlTagName, attribute: XmlAttributeKey },
+    /// No image is registered under the name
+    ImageNotFound { line: usize, name: XmlAttributeValue },
 }
 
-/*
-pub fn dom_from_xml<T: Layout>(
-    xml: &str,
-    component_map: &XmlComponentMap<T>
-) -> Result<Dom<T>, XmlParseError> {
+impl_display! {
+    XmlParseError,
+    {
+        MultipleRootComponents => "The document has more than one root element",
+        NoRootComponent => "The document has no root element",
+        Syntax { line, message } => format!("Line {}: {}", line, message),
+        MismatchedClosingTag { line, expected, found } => format!("Line {}: </{}> closes <{}>", line, found, expected),
+        UnclosedTag { line, tag } => format!("Line {}: <{}> is never closed", line, tag),
+        UnknownElement { line, tag } => format!("Line {}: unknown element <{}>", line, tag),
+        UnknownEvent { line, attribute } => format!("Line {}: unknown event attribute \"{}\"", line, attribute),
+        CallbackNotFound { line, name } => format!("Line {}: no callback is registered as \"{}\"", line, name),
+        MissingAttribute { line, tag, attribute } => format!("Line {}: <{}> requires the \"{}\" attribute", line, tag, attribute),
+        ImageNotFound { line, name } => format!("Line {}: no image is registered as \"{}\"", line, name),
+    }
+}
+
+/// Parses the `xml` and converts it to a `Dom`, see the module documentation
+pub fn dom_from_xml<T: Layout>(xml: &str, callbacks: &CallbackRegistry<T>) -> Result<Dom<T>, XmlParseError> {
+    dom_from_xml_node(&parse_tree(xml)?, callbacks)
+}
+
+/// Converts a parsed XML tree to a `Dom`, see the module documentation
+pub fn dom_from_xml_node<T: Layout>(node: &XmlNode, callbacks: &CallbackRegistry<T>) -> Result<Dom<T>, XmlParseError> {
+
+    let mut dom = match node.tag_name.as_str() {
+        "div" => {
+            if node.content.is_some() {
+                return Err(XmlParseError::Syntax {
+                    line: node.line,
+                    message: "<div> can't contain text, use a <p>".into(),
+                });
+            }
+            Dom::div()
+        },
+        "p" => Dom::label(node.content.clone().unwrap_or_default()),
+        "img" => {
+            if node.content.is_some() {
+                return Err(XmlParseError::Syntax {
+                    line: node.line,
+                    message: "<img> can't contain text, use a <p>".into(),
+                });
+            }
+            let name = node.attributes.get("src-id").ok_or_else(|| XmlParseError::MissingAttribute {
+                line: node.line,
+                tag: node.tag_name.clone(),
+                attribute: "src-id".into(),
+            })?;
+            let image_id = callbacks.get_image(name).ok_or_else(|| XmlParseError::ImageNotFound { line: node.line, name: name.clone() })?;
+            Dom::image(image_id)
+        },
+        _ => return Err(XmlParseError::UnknownElement { line: node.line, tag: node.tag_name.clone() }),
+    };
+
+    for (key, value) in &node.attributes {
+        match key.as_str() {
+            "id" => for id in value.split_whitespace() { dom.add_id(id); },
+            "class" => for class in value.split_whitespace() { dom.add_class(class); },
+            _ if key.starts_with("on") => {
+                let event = event_from_attribute(key).ok_or_else(|| XmlParseError::UnknownEvent { line: node.line, attribute: key.clone() })?;
+                let callback = callbacks.get_callback(value).ok_or_else(|| XmlParseError::CallbackNotFound { line: node.line, name: value.clone() })?;
+                dom.add_callback(event, callback);
+            },
+            _ => { },
+        }
+    }
+
+    for child in &node.children {
+        dom.add_child(dom_from_xml_node(child, callbacks)?);
+    }
+
+    Ok(dom)
+}
+
+/// Maps `onmouseup`, `onleftmouseup`, ... to the `On` event
+fn event_from_attribute(attribute: &str) -> Option<On> {
+    use self::On::*;
+    let event = match attribute {
+        "onmouseover" => MouseOver,
+        "onmousedown" => MouseDown,
+        "onleftmousedown" => LeftMouseDown,
+        "onmiddlemousedown" => MiddleMouseDown,
+        "onrightmousedown" => RightMouseDown,
+        "onmouseup" => MouseUp,
+        "onleftmouseup" => LeftMouseUp,
+        "onmiddlemouseup" => MiddleMouseUp,
+        "onrightmouseup" => RightMouseUp,
+        "ondoubleclick" => DoubleClick,
+        "onleftdoubleclick" => LeftDoubleClick,
+        "onmiddledoubleclick" => MiddleDoubleClick,
+        "onrightdoubleclick" => RightDoubleClick,
+        "onmouseenter" => MouseEnter,
+        "onmouseleave" => MouseLeave,
+        "onscroll" => Scroll,
+        "onscrollup" => ScrollUp,
+        "onscrolldown" => ScrollDown,
+        "onscrollhorizontal" => ScrollHorizontal,
+        "ontextinput" => TextInput,
+        "onvirtualkeydown" => VirtualKeyDown,
+        "onvirtualkeyup" => VirtualKeyUp,
+        "onhoveredfile" => HoveredFile,
+        "ondroppedfile" => DroppedFile,
+        "ondroppeddata" => DroppedData,
+        "onhoveredfilecancelled" => HoveredFileCancelled,
+        "onfocus" => FocusReceived,
+        "onblur" => FocusLost,
+        _ => return None,
+    };
+    Some(event)
+}
+
+/// Cursor over the characters of an XML document, counting the lines
+struct XmlReader {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl XmlReader {
+
+    fn is_at_end(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        if c == '\n' {
+            self.line += 1;
+        }
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn syntax_error<S: Into<String>>(&self, message: S) -> XmlParseError {
+        XmlParseError::Syntax { line: self.line, message: message.into() }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map(|c| c.is_whitespace()).unwrap_or(false) {
+            self.advance();
+        }
+    }
+
+    /// Skips everything up to and including the `end`
+    fn skip_until(&mut self, end: &str) -> Result<(), XmlParseError> {
+        let line = self.line;
+        while !self.starts_with(end) {
+            if self.advance().is_none() {
+                return Err(XmlParseError::Syntax { line, message: format!("missing \"{}\"", end) });
+            }
+        }
+        self.pos += end.len();
+        Ok(())
+    }
+
+    /// Skips whitespace, comments, the `<?xml ... ?>` declaration and `<!DOCTYPE ...>`
+    fn skip_misc(&mut self) -> Result<(), XmlParseError> {
+        loop {
+            self.skip_whitespace();
+            if self.starts_with("<!--") {
+                self.skip_until("-->")?;
+            } else if self.starts_with("<?") {
+                self.skip_until("?>")?;
+            } else if self.starts_with("<!") {
+                self.skip_until(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, XmlParseError> {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == ':' || c == '.' {
+                name.push(c);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        if name.is_empty() {
+            Err(self.syntax_error(match self.peek() {
+                Some(c) => format!("expected a name, found '{}'", c),
+                None => "expected a name, found the end of the document".to_string(),
+            }))
+        } else {
+            Ok(name)
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), XmlParseError> {
+        match self.peek() {
+            Some(c) if c == expected => { self.advance(); Ok(()) },
+            Some(c) => Err(self.syntax_error(format!("expected '{}', found '{}'", expected, c))),
+            None => Err(self.syntax_error(format!("expected '{}', found the end of the document", expected))),
+        }
+    }
+
+    /// Parses `&amp;`, `&#65;`, ... after the `&`
+    fn parse_entity(&mut self) -> Result<char, XmlParseError> {
+        let mut name = String::new();
+        loop {
+            match self.advance() {
+                Some(';') => break,
+                Some(c) if name.len() < 10 => name.push(c),
+                _ => return Err(self.syntax_error(format!("unterminated entity \"&{}\"", name))),
+            }
+        }
+        let c = match name.as_str() {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if name.starts_with("#x") => u32::from_str_radix(&name[2..], 16).ok().and_then(::std::char::from_u32),
+            _ if name.starts_with('#') => name[1..].parse::<u32>().ok().and_then(::std::char::from_u32),
+            _ => None,
+        };
+        c.ok_or_else(|| self.syntax_error(format!("unknown entity \"&{};\"", name)))
+    }
+
+    fn parse_attribute_value(&mut self) -> Result<String, XmlParseError> {
+        let quote = match self.peek() {
+            Some(c) if c == '"' || c == '\'' => c,
+            _ => return Err(self.syntax_error("expected a quoted attribute value")),
+        };
+        let line = self.line;
+        self.advance();
+        let mut value = String::new();
+        loop {
+            match self.advance() {
+                Some(c) if c == quote => return Ok(value),
+                Some('&') => value.push(self.parse_entity()?),
+                Some(c) => value.push(c),
+                None => return Err(XmlParseError::Syntax { line, message: "unclosed attribute value".into() }),
+            }
+        }
+    }
+
+    /// Parses an element, starting at its `<`
+    fn parse_element(&mut self) -> Result<XmlNode, XmlParseError> {
+
+        let line = self.line;
+        self.expect('<')?;
+        let tag_name = self.parse_name()?;
+        let mut attributes = BTreeMap::new();
+
+        // Attributes
+        loop {
+            self.skip_whitespace();
+            if self.starts_with("/>") {
+                self.pos += 2;
+                return Ok(XmlNode { tag_name, attributes, children: Vec::new(), content: None, line });
+            } else if self.peek() == Some('>') {
+                self.advance();
+                break;
+            } else if self.is_at_end() {
+                return Err(XmlParseError::UnclosedTag { line, tag: tag_name });
+            }
+            let key = self.parse_name()?;
+            self.skip_whitespace();
+            self.expect('=')?;
+            self.skip_whitespace();
+            let value = self.parse_attribute_value()?;
+            attributes.insert(key, value);
+        }
+
+        // Content and children
+        let mut children = Vec::new();
+        let mut text = String::new();
+
+        loop {
+            if self.is_at_end() {
+                return Err(XmlParseError::UnclosedTag { line, tag: tag_name });
+            } else if self.starts_with("</") {
+                self.pos += 2;
+                let closing_line = self.line;
+                let closing_name = self.parse_name()?;
+                self.skip_whitespace();
+                self.expect('>')?;
+                if closing_name != tag_name {
+                    return Err(XmlParseError::MismatchedClosingTag { line: closing_line, expected: tag_name, found: closing_name });
+                }
+                break;
+            } else if self.starts_with("<!--") {
+                self.skip_until("-->")?;
+            } else if self.peek() == Some('<') {
+                children.push(self.parse_element()?);
+            } else if self.peek() == Some('&') {
+                self.advance();
+                text.push(self.parse_entity()?);
+            } else if let Some(c) = self.advance() {
+                text.push(c);
+            }
+        }
+
+        let content = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let content = if content.is_empty() { None } else { Some(content) };
+
+        Ok(XmlNode { tag_name, attributes, children, content, line })
+    }
+}
+
+/// Source of the markup of a hot-reloaded UI, see `App::with_hot_reload_ui()`
+pub trait XmlReloadHandler {
+    /// Returns the markup if it changed since the last call (always on the first call),
+    /// `Ok(None)` if it didn't change and a human-readable error if it couldn't be read
+    fn reload_xml(&mut self) -> Result<Option<String>, String>;
+    /// Returns how often the source should be checked for changes
+    fn get_reload_interval(&self) -> Duration;
+}
+
+/// Loads the markup from a file and reloads it whenever the modification time of the file changes
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct XmlFileWatcher {
+    file_path: PathBuf,
+    reload_interval: Duration,
+    last_modified: Option<SystemTime>,
+}
+
+impl XmlFileWatcher {
+    /// Creates a watcher for the XML file at the given path
+    pub fn new<P: Into<PathBuf>>(file_path: P) -> Self {
+        Self { file_path: file_path.into(), reload_interval: DEFAULT_RELOAD_INTERVAL, last_modified: None }
+    }
+
+    pub fn with_reload_interval(self, reload_interval: Duration) -> Self {
+        Self { reload_interval, .. self }
+    }
+}
+
+impl XmlReloadHandler for XmlFileWatcher {
+    fn reload_xml(&mut self) -> Result<Option<String>, String> {
+        use std::fs;
+
+        let file_path = self.file_path.clone();
+        let io_error = |e: ::std::io::Error| format!("Io error: \"{}\" when loading file \"{}\"", e, file_path.display());
+
+        let modified = fs::metadata(&self.file_path).and_then(|m| m.modified()).map_err(&io_error)?;
+        if self.last_modified == Some(modified) {
+            return Ok(None);
+        }
+
+        let xml = fs::read_to_string(&self.file_path).map_err(&io_error)?;
+        self.last_modified = Some(modified);
+        Ok(Some(xml))
+    }
+
+    fn get_reload_interval(&self) -> Duration {
+        self.reload_interval
+    }
+}
+
+/// The UI that `App::with_hot_reload_ui()` loads instead of calling `Layout::layout()`
+pub(crate) struct HotReloadUi<T: Layout> {
+    handler: Box<dyn XmlReloadHandler>,
+    callbacks: CallbackRegistry<T>,
+    /// The last markup that could be converted to a `Dom`, `None` until the source is valid once
+    last_valid_xml: Option<XmlNode>,
+    /// Why the current markup can't be loaded, shown in the error overlay
+    pub(crate) error: Option<String>,
+    last_reload: Option<Instant>,
+}
+
+impl<T: Layout> HotReloadUi<T> {
+
+    pub(crate) fn new(handler: Box<dyn XmlReloadHandler>, callbacks: CallbackRegistry<T>) -> Self {
+        Self {
+            handler,
+            callbacks,
+            last_valid_xml: None,
+            error: None,
+            last_reload: None,
+        }
+    }
+
+    /// Checks the source for changes (if the reload interval has passed since the last
+    /// check) and returns whether the UI or the error changed. If the new markup can't be
+    /// loaded, the last valid markup stays in place.
+    pub(crate) fn reload(&mut self, now: Instant) -> bool {
+
+        if let Some(last_reload) = self.last_reload {
+            if now - last_reload < self.handler.get_reload_interval() {
+                return false;
+            }
+        }
+        self.last_reload = Some(now);
+
+        let result = self.handler.reload_xml().and_then(|xml| match xml {
+            Some(xml) => {
+                let node = parse_tree(&xml).map_err(|e| e.to_string())?;
+                // Resolve the callbacks once, so that building the DOM on every frame can't fail
+                dom_from_xml_node(&node, &self.callbacks).map_err(|e| e.to_string())?;
+                Ok(Some(node))
+            },
+            None => Ok(None),
+        });
+
+        match result {
+            Ok(None) => false,
+            Ok(Some(node)) => {
+                self.last_valid_xml = Some(node);
+                self.error = None;
+                true
+            },
+            Err(e) => {
+                if self.error.as_ref() == Some(&e) {
+                    return false;
+                }
+                report(AzulError::UiReload(e.to_string()));
+                self.error = Some(e);
+                true
+            },
+        }
+    }
+
+    /// Builds the DOM from the last valid markup, `None` if the markup was never valid
+    pub(crate) fn get_dom(&self) -> Option<Dom<T>> {
+        let node = self.last_valid_xml.as_ref()?;
+        dom_from_xml_node(node, &self.callbacks).ok()
+    }
+}
+#[cfg(test)]
+struct TestLayout { }
+
+#[cfg(test)]
+impl Layout for TestLayout {
+    fn layout(&self) -> Dom<Self> {
+        Dom::div()
+    }
+}
+
+#[cfg(test)]
+fn on_save(_: &mut ::app_state::AppState<TestLayout>, _: &mut ::window::CallbackInfo<TestLayout>) -> ::dom::UpdateScreen {
+    ::dom::Redraw
+}
+
+#[cfg(test)]
+fn on_select(_: &mut ::app_state::AppState<TestLayout>, _: &mut ::window::CallbackInfo<TestLayout>) -> ::dom::UpdateScreen {
+    ::dom::DontRedraw
+}
+
+#[cfg(test)]
+fn test_callbacks() -> CallbackRegistry<TestLayout> {
+    let mut callbacks = CallbackRegistry::new();
+    callbacks.register_callback("save", Callback(on_save));
+    callbacks
+}
+
+#[test]
+fn test_dom_from_xml() {
+
+    let xml = r#"<?xml version="1.0"?>
+        <!-- Settings page -->
+        <div id="main" class="page dark">
+            <p class="title">Settings &amp; more</p>
+            <div class="row">
+                <p onleftmouseup="save">
+                    Save
+                    changes
+                </p>
+                <div class="spacer"/>
+            </div>
+        </div>
+    "#;
+
+    let expected = Dom::<TestLayout>::div().with_id("main").with_class("page").with_class("dark")
+        .with_child(Dom::label("Settings & more").with_class("title"))
+        .with_child(Dom::div().with_class("row")
+            .with_child(Dom::label("Save changes").with_callback(On::LeftMouseUp, Callback(on_save)))
+            .with_child(Dom::div().with_class("spacer")));
+
+    assert_eq!(dom_from_xml(xml, &test_callbacks()), Ok(expected));
+}
+
+#[test]
+fn test_xml_errors_have_line_numbers() {
+
+    let callbacks = test_callbacks();
+
+    assert_eq!(dom_from_xml("<div>\n  <p>a</p>\n</p>", &callbacks).unwrap_err(),
+        XmlParseError::MismatchedClosingTag { line: 3, expected: "div".into(), found: "p".into() });
+    assert_eq!(dom_from_xml("<div>\n  <p>a</p>\n", &callbacks).unwrap_err(),
+        XmlParseError::UnclosedTag { line: 1, tag: "div".into() });
+    assert_eq!(dom_from_xml("<div>\n  <button/>\n</div>", &callbacks).unwrap_err(),
+        XmlParseError::UnknownElement { line: 2, tag: "button".into() });
+    assert_eq!(dom_from_xml("<div>\n\n  <p onmouseup=\"delete\">x</p>\n</div>", &callbacks).unwrap_err(),
+        XmlParseError::CallbackNotFound { line: 3, name: "delete".into() });
+    assert_eq!(dom_from_xml("<div onclick=\"save\"/>", &callbacks).unwrap_err(),
+        XmlParseError::UnknownEvent { line: 1, attribute: "onclick".into() });
+    assert_eq!(dom_from_xml("<div/><div/>", &callbacks).unwrap_err(), XmlParseError::MultipleRootComponents);
+    assert_eq!(dom_from_xml("<!-- empty -->", &callbacks).unwrap_err(), XmlParseError::NoRootComponent);
+
+    let error = dom_from_xml("<div>\n<p class=\"a>x</p>", &callbacks).unwrap_err();
+    assert_eq!(error.to_string(), "Line 2: unclosed attribute value");
+}
+
+/// Replays scripted file contents, like a file that is edited while the app is running
+#[cfg(test)]
+struct MockFileWatcher {
+    changes: ::std::rc::Rc<::std::cell::RefCell<Vec<Result<Option<String>, String>>>>,
+}
+
+#[cfg(test)]
+impl XmlReloadHandler for MockFileWatcher {
+    fn reload_xml(&mut self) -> Result<Option<String>, String> {
+        let mut changes = self.changes.borrow_mut();
+        if changes.is_empty() { Ok(None) } else { changes.remove(0) }
+    }
+    fn get_reload_interval(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+}
+
+#[test]
+fn test_hot_reload_ui_keeps_the_last_valid_ui_on_errors() {
+
+    use std::{rc::Rc, cell::RefCell};
+
+    let changes = Rc::new(RefCell::new(vec![Ok(Some("<div><p class=\"v1\">Version 1</p></div>".to_string()))]));
+    let mut ui = HotReloadUi::new(Box::new(MockFileWatcher { changes: changes.clone() }), test_callbacks());
+    let start = Instant::now();
+    let later = |ms: u64| start + Duration::from_millis(ms);
+
+    assert!(ui.get_dom().is_none());
+    assert!(ui.reload(start));
+    let version_1 = ui.get_dom().unwrap();
+    assert_eq!(version_1, Dom::div().with_child(Dom::label("Version 1").with_class("v1")));
+
+    // Unchanged file
+    assert!(!ui.reload(later(600)));
+
+    // The file is saved with a syntax error: the error is shown, the old UI stays
+    changes.borrow_mut().push(Ok(Some("<div><p>Version 2</div>".to_string())));
+    assert!(ui.reload(later(1200)));
+    assert_eq!(ui.error, Some("Line 1: </div> closes <p>".to_string()));
+    assert_eq!(ui.get_dom(), Some(version_1.clone()));
+
+    // The same error again doesn't trigger a redraw, neither does polling before the interval passed
+    changes.borrow_mut().push(Ok(Some("<div><p>Version 2</div>".to_string())));
+    assert!(!ui.reload(later(1800)));
+    changes.borrow_mut().push(Ok(Some("<div><p onmouseup=\"save\">Version 2</p></div>".to_string())));
+    assert!(!ui.reload(later(1900)));
+
+    // The fixed file replaces the UI and clears the error
+    assert!(ui.reload(later(2400)));
+    assert_eq!(ui.error, None);
+    assert_eq!(ui.get_dom(), Some(Dom::div().with_child(Dom::label("Version 2").with_callback(On::MouseUp, Callback(on_save)))));
+
+    // The file can't be read (e.g. it is deleted while saving), the UI stays
+    changes.borrow_mut().push(Err("Io error: not found".to_string()));
+    assert!(ui.reload(later(3000)));
+    assert_eq!(ui.error, Some("Io error: not found".to_string()));
+    assert!(ui.get_dom().is_some());
+}
+
+#[test]
+fn test_dom_from_xml_round_trip() {
+
+    use images::new_image_id;
+
+    let logo = new_image_id();
+    let mut callbacks = test_callbacks();
+    callbacks.register_callback("select", Callback(on_select));
+    callbacks.register_image("logo", logo);
+
+    let xml = r#"
+        <div id="app">
+            <div id="toolbar" class="row">
+                <img src-id="logo" class="icon"/>
+                <p class="button" onmouseup="save">Save</p>
+            </div>
+            <div class="list">
+                <p onmouseup="select" onfocus="select">First</p>
+                <p>Second</p>
+            </div>
+        </div>
+    "#;
+
+    let hand_built = Dom::<TestLayout>::div().with_id("app")
+        .with_child(Dom::div().with_id("toolbar").with_class("row")
+            .with_child(Dom::image(logo).with_class("icon"))
+            .with_child(Dom::label("Save").with_class("button").with_callback(On::MouseUp, Callback(on_save))))
+        .with_child(Dom::div().with_class("list")
+            .with_child(Dom::label("First").with_callback(On::FocusReceived, Callback(on_select)).with_callback(On::MouseUp, Callback(on_select)))
+            .with_child(Dom::label("Second")));
+
+    let dom = Dom::from_xml(xml, &callbacks).unwrap();
+    assert_eq!(dom, hand_built);
+
+    // Same arena shape: the nodes are in the same order and have the same parents / siblings
+    assert_eq!(dom.len(), hand_built.len());
+    assert_eq!(dom.traverse().collect::<Vec<_>>(), hand_built.traverse().collect::<Vec<_>>());
+    assert_eq!(dom.to_html_string(), hand_built.to_html_string());
 
+    assert_eq!(Dom::from_xml("<div>\n  <img/>\n</div>", &callbacks).unwrap_err(),
+        XmlParseError::MissingAttribute { line: 2, tag: "img".into(), attribute: "src-id".into() });
+    assert_eq!(Dom::from_xml("<div>\n  <img src-id=\"avatar\"/>\n</div>", &callbacks).unwrap_err(),
+        XmlParseError::ImageNotFound { line: 2, name: "avatar".into() });
 }
-*/
\ No newline at end of file
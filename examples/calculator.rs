@@ -235,6 +235,17 @@ fn handle_text_input(app_state: &mut AppState<Calculator>, event: &mut CallbackI
 }
 
 fn handle_virtual_key_input(app_state: &mut AppState<Calculator>, event: &mut CallbackInfo<Calculator>) -> UpdateScreen {
+    use azul::prelude::AcceleratorKey::*;
+
+    // Ctrl + D toggles the layout debugging overlay, Ctrl + F the frame HUD
+    // (the HUD needs `--features profiling`)
+    if keymap(app_state, event, &[
+        (vec![Ctrl, Key(VirtualKeyCode::D)], toggle_debug_overlay),
+        (vec![Ctrl, Key(VirtualKeyCode::F)], toggle_frame_hud),
+    ]) == Redraw {
+        return Redraw;
+    }
+
     let current_key = app_state.windows[event.window_id].state.get_keyboard_state().latest_virtual_keycode?;
     let event = match current_key {
         VirtualKeyCode::Return => Event::EqualSign,
@@ -244,6 +255,20 @@ fn handle_virtual_key_input(app_state: &mut AppState<Calculator>, event: &mut Ca
     process_event(app_state, event)
 }
 
+fn toggle_debug_overlay(app_state: &mut AppState<Calculator>, event: &mut CallbackInfo<Calculator>) -> UpdateScreen {
+    let window_state = &mut app_state.windows.get_mut(event.window_id)?.state;
+    let overlay = if window_state.get_debug_overlay().is_empty() { DebugOverlayFlags::all() } else { DebugOverlayFlags::default() };
+    window_state.set_debug_overlay(overlay);
+    Redraw
+}
+
+fn toggle_frame_hud(app_state: &mut AppState<Calculator>, event: &mut CallbackInfo<Calculator>) -> UpdateScreen {
+    let window_state = &mut app_state.windows.get_mut(event.window_id)?.state;
+    let visible = window_state.get_frame_hud();
+    window_state.set_frame_hud(!visible);
+    Redraw
+}
+
 fn process_event(app_state: &mut AppState<Calculator>, event: Event) -> UpdateScreen {
 
     // Act on the event accordingly
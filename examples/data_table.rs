@@ -0,0 +1,84 @@
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+extern crate azul;
+
+use azul::{prelude::*, widgets::data_table::*};
+
+const ROW_COUNT: usize = 50_000;
+
+struct Item {
+    id: usize,
+    name: String,
+    price: u32,
+}
+
+struct Inventory {
+    items: Vec<Item>,
+    table: DataTableState,
+}
+
+struct DataTableDemo {
+    inventory: Inventory,
+}
+
+impl DataTableSource<DataTableDemo> for Inventory {
+
+    fn table_state(&self) -> &DataTableState {
+        &self.table
+    }
+
+    fn table_state_mut(&mut self) -> &mut DataTableState {
+        &mut self.table
+    }
+
+    fn row_count(&self) -> usize {
+        self.items.len()
+    }
+
+    fn render_cell(&self, row: usize, column: usize) -> Dom<DataTableDemo> {
+        let item = &self.items[row];
+        match column {
+            0 => Dom::label(format!("{}", item.id)),
+            1 => Dom::label(item.name.clone()),
+            _ => Dom::label(format!("{}.{:02} €", item.price / 100, item.price % 100)),
+        }
+    }
+
+    fn sort_by_column(&mut self, column: usize, direction: SortDirection) {
+        match column {
+            0 => self.items.sort_by_key(|item| item.id),
+            1 => self.items.sort_by(|a, b| a.name.cmp(&b.name)),
+            _ => self.items.sort_by_key(|item| item.price),
+        }
+        if direction == SortDirection::Descending {
+            self.items.reverse();
+        }
+    }
+}
+
+impl Layout for DataTableDemo {
+    fn layout(&self, info: LayoutInfo<Self>) -> Dom<Self> {
+        DataTable::new().dom(&self.inventory, &self, info.window)
+    }
+}
+
+fn main() {
+
+    let items = (0..ROW_COUNT).map(|id| Item {
+        id,
+        name: format!("Item {}", (id * 7919) % ROW_COUNT),
+        price: ((id * 104_729) % 100_000) as u32,
+    }).collect();
+
+    let table = DataTableState::new(vec![
+        DataTableColumn::new("ID", 80.0),
+        DataTableColumn::new("Name", 200.0).with_min_width(60.0),
+        DataTableColumn::new("Price", 120.0),
+    ]);
+
+    let app = App::new(DataTableDemo {
+        inventory: Inventory { items, table },
+    }, AppConfig::default());
+
+    app.run(Window::new(WindowCreateOptions::default(), css::native()).unwrap()).unwrap();
+}
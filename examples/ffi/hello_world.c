@@ -0,0 +1,57 @@
+/*
+ * Counter app driven through the C ABI of azul (`azul/include/azul.h`).
+ *
+ * Build azul with the `ffi` feature as a static library, then:
+ * cc -I azul/include examples/ffi/hello_world.c -L <dir of libazul.a> -lazul -o hello_world
+ */
+#include <stdio.h>
+#include "azul.h"
+
+typedef struct {
+    int counter;
+} AppData;
+
+static uint8_t on_increment(AzulCallbackContext *context) {
+    AppData *data = (AppData *)context->user_data;
+    data->counter += 1;
+    return 1; /* redraw */
+}
+
+static AzulDom *layout(void *user_data) {
+    AppData *data = (AppData *)user_data;
+    char text[64];
+    snprintf(text, sizeof(text), "%d", data->counter);
+
+    AzulDom *root = azul_dom_new_div();
+
+    AzulDom *label = azul_dom_new_label(text);
+    azul_dom_add_id(label, "counter");
+
+    AzulDom *button = azul_dom_new_div();
+    azul_dom_add_class(button, "__azul-native-button");
+    azul_dom_add_callback(button, AZUL_ON_LEFT_MOUSE_UP, on_increment);
+    azul_dom_add_child(button, azul_dom_new_label("Increment counter"));
+
+    /* The children are owned by the root from here on */
+    azul_dom_add_child(root, label);
+    azul_dom_add_child(root, button);
+
+    /* The returned DOM is owned by azul */
+    return root;
+}
+
+int main(void) {
+    AppData data = { 5 };
+    AzulApp *app = azul_app_new(layout, &data);
+    if (app == NULL) {
+        return 1;
+    }
+    /* NULL = native style, azul_app_run takes ownership of the app */
+    int32_t result = azul_app_run(app, NULL);
+    if (result != AZUL_OK) {
+        fprintf(stderr, "azul_app_run failed with %d\n", (int)result);
+        return 1;
+    }
+    printf("final counter: %d\n", data.counter);
+    return 0;
+}
@@ -0,0 +1,48 @@
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+extern crate azul;
+
+use azul::prelude::*;
+use std::time::Duration;
+
+struct Settings {
+    entries: Vec<(&'static str, String)>,
+}
+
+impl Layout for Settings {
+    fn layout(&self, _: LayoutInfo<Self>) -> Dom<Self> {
+
+        // Labels and fields alternate: the grid places them into the two columns,
+        // so that all fields start at the same X position, regardless of the label width
+        let form = self.entries.iter().flat_map(|(label, value)| vec![
+            Dom::label(label.to_string()).with_class("label"),
+            Dom::label(value.clone()).with_class("field"),
+        ]).collect::<Dom<Self>>();
+
+        Dom::div().with_id("form").with_child(form)
+            .with_child(Dom::label("Save").with_id("save"))
+    }
+}
+
+fn main() {
+    let data = Settings {
+        entries: vec![
+            ("Name", "Jane Doe".into()),
+            ("E-Mail address", "jane@example.com".into()),
+            ("Language", "English".into()),
+            ("Time zone", "UTC+01:00".into()),
+        ],
+    };
+
+    macro_rules! CSS_PATH { () => (concat!(env!("CARGO_MANIFEST_DIR"), "/../examples/grid_form.css")) }
+
+    let app = App::new(data, AppConfig::default());
+
+    #[cfg(debug_assertions)]
+    let window = Window::new_hot_reload(WindowCreateOptions::default(), css::hot_reload(CSS_PATH!(), Duration::from_millis(500))).unwrap();
+
+    #[cfg(not(debug_assertions))]
+    let window = Window::new(WindowCreateOptions::default(), css::override_native(include_str!(CSS_PATH!())).unwrap()).unwrap();
+
+    app.run(window).unwrap();
+}
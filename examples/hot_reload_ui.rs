@@ -0,0 +1,45 @@
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+extern crate azul;
+
+use azul::prelude::*;
+
+struct Counter {
+    count: usize,
+}
+
+impl Layout for Counter {
+    // Only used until the markup can be loaded
+    fn layout(&self, _info: LayoutInfo<Self>) -> Dom<Self> {
+        Dom::label("Loading hot_reload_ui.xml...")
+    }
+}
+
+fn increment(app_state: &mut AppState<Counter>, event: &mut CallbackInfo<Counter>) -> UpdateScreen {
+    let count = app_state.data.modify_clone(|state| { state.count += 1; state.count })?;
+    app_state.windows.get_mut(event.window_id)?.state.title = format!("Count: {}", count);
+    Redraw
+}
+
+fn reset(app_state: &mut AppState<Counter>, event: &mut CallbackInfo<Counter>) -> UpdateScreen {
+    app_state.data.modify(|state| state.count = 0)?;
+    app_state.windows.get_mut(event.window_id)?.state.title = "Count: 0".to_string();
+    Redraw
+}
+
+fn main() {
+
+    macro_rules! XML_PATH { () => (concat!(env!("CARGO_MANIFEST_DIR"), "/../examples/hot_reload_ui.xml")) }
+
+    let mut callbacks = CallbackRegistry::new();
+    callbacks.register_callback("increment", Callback(increment));
+    callbacks.register_callback("reset", Callback(reset));
+
+    // Edit examples/hot_reload_ui.xml while the app is running, the counter
+    // keeps its value, since only the DOM is reloaded
+    let app = App::new(Counter { count: 0 }, AppConfig::default())
+        .with_hot_reload_ui(XML_PATH!(), callbacks);
+
+    let window = Window::new(WindowCreateOptions::default(), css::native()).unwrap();
+    app.run(window).unwrap();
+}
@@ -14,12 +14,9 @@ const CUSTOM_CSS: &str = ".selected { background-color: black; color: white; }";
 impl Layout for List {
     fn layout(&self, _: LayoutInfo<Self>) -> Dom<Self> {
         self.items.iter().enumerate().map(|(idx, item)| {
-            NodeData {
-                node_type: NodeType::Label(item.to_string()),
-                classes: if self.selected == Some(idx) { vec!["selected".into()] } else { vec![] },
-                callbacks: vec![(On::MouseDown.into(), Callback(print_which_item_was_selected))],
-                .. Default::default()
-            }
+            let node = NodeData::label(*item)
+                .with_callback(On::MouseDown, Callback(print_which_item_was_selected));
+            if self.selected == Some(idx) { node.with_class("selected") } else { node }
         }).collect::<Dom<Self>>()
     }
 }
@@ -0,0 +1,75 @@
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+extern crate azul;
+
+use azul::prelude::*;
+
+struct Counter {
+    count: usize,
+    history: Vec<usize>,
+}
+
+// File (0): New (0)
+// Edit (1): Increment (0), Undo (1), --- (2), Show count in title (3)
+const UNDO: &[usize] = &[1, 1];
+const SHOW_IN_TITLE: &[usize] = &[1, 3];
+
+impl Layout for Counter {
+    fn layout(&self, mut info: LayoutInfo<Self>) -> Dom<Self> {
+        if info.window.get_menu().is_none() {
+            info.window.set_menu(Some(menu_bar()));
+        }
+        // Without a native `MenuBackend`, the menu is drawn as part of the DOM
+        Dom::div()
+            .with_child(info.window.menu_bar_dom())
+            .with_child(Dom::label(format!("{}", self.count)))
+    }
+}
+
+fn menu_bar() -> MenuBar<Counter> {
+    MenuBar::new()
+        .with_menu(Menu::new("&File")
+            .with_item(MenuItem::new("&New", MenuCallback(new_counter)).with_accelerator(KeyCombo::new(VirtualKeyCode::N).ctrl())))
+        .with_menu(Menu::new("&Edit")
+            .with_item(MenuItem::new("&Increment", MenuCallback(increment)).with_accelerator(KeyCombo::new(VirtualKeyCode::Up).ctrl()))
+            .with_item(MenuItem::new("&Undo", MenuCallback(undo)).with_accelerator(KeyCombo::new(VirtualKeyCode::Z).ctrl()).with_enabled(false))
+            .with_separator()
+            .with_item(MenuItem::new("Show count in &title", MenuCallback(toggle_title))))
+}
+
+fn new_counter(app_state: &mut AppState<Counter>, info: &mut MenuCallbackInfo) -> UpdateScreen {
+    app_state.data.modify(|state| { state.count = 0; state.history.clear(); })?;
+    update_window(app_state, info.window_id)
+}
+
+fn increment(app_state: &mut AppState<Counter>, info: &mut MenuCallbackInfo) -> UpdateScreen {
+    app_state.data.modify(|state| { state.history.push(state.count); state.count += 1; })?;
+    update_window(app_state, info.window_id)
+}
+
+fn undo(app_state: &mut AppState<Counter>, info: &mut MenuCallbackInfo) -> UpdateScreen {
+    app_state.data.modify(|state| if let Some(count) = state.history.pop() { state.count = count; })?;
+    update_window(app_state, info.window_id)
+}
+
+fn toggle_title(app_state: &mut AppState<Counter>, info: &mut MenuCallbackInfo) -> UpdateScreen {
+    let show = !info.checked;
+    app_state.windows.get_mut(&info.window_id)?.update_menu_item(SHOW_IN_TITLE, MenuItemUpdate { checked: Some(show), .. MenuItemUpdate::default() })?;
+    update_window(app_state, info.window_id)
+}
+
+/// Enables "Undo" only if there is something to undo and updates the window title
+fn update_window(app_state: &mut AppState<Counter>, window_id: WindowId) -> UpdateScreen {
+    let (count, can_undo) = app_state.data.lock().ok().map(|state| (state.count, !state.history.is_empty()))?;
+    let window = app_state.windows.get_mut(&window_id)?;
+    window.update_menu_item(UNDO, MenuItemUpdate { enabled: Some(can_undo), .. MenuItemUpdate::default() })?;
+    let show_in_title = window.get_menu()?.get_item(SHOW_IN_TITLE)?.checked;
+    window.state.title = if show_in_title { format!("Count: {}", count) } else { "Menu example".to_string() };
+    Redraw
+}
+
+fn main() {
+    let app = App::new(Counter { count: 0, history: Vec::new() }, AppConfig::default());
+    let window = Window::new(WindowCreateOptions::default(), css::native()).unwrap();
+    app.run(window).unwrap();
+}
@@ -14,13 +14,10 @@ impl Layout for List {
     fn layout(&self, _: LayoutInfo<Self>) -> Dom<Self> {
 
         let child_nodes = self.items.iter().enumerate().map(|(idx, item)| {
-            NodeData {
-                node_type: NodeType::Label(item.to_string()),
-                classes: vec!["item".into()],
-                ids: if self.selected == Some(idx) { vec!["selected".into()] } else { vec![] },
-                callbacks: vec![(On::MouseDown.into(), Callback(print_which_item_was_selected))],
-                .. Default::default()
-            }
+            let node = NodeData::label(*item)
+                .with_class("item")
+                .with_callback(On::MouseDown, Callback(print_which_item_was_selected));
+            if self.selected == Some(idx) { node.with_id("selected") } else { node }
         }).collect::<Dom<Self>>();
 
         Dom::new(NodeType::Div).with_id("container").with_child(child_nodes)
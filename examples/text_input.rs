@@ -3,51 +3,97 @@
 extern crate azul;
 
 use azul::prelude::*;
-use azul::widgets::text_input::*;
+use azul::widgets::{text_input::*, button::Button};
 
 const CSS: &str = "
-#text_input_1 {
-    border-radius: 2px;
-    width: 200px;
-    height: 20px;
-    border: 2px solid transparent;
-    background-color: #ccc;
-    position: absolute;
-    top: 40px;
-    left: 40px;
+#form {
+    flex-direction: column;
+    padding: 20px;
 }
 
-#text_input_1 p {
-    font-size: 10px;
+.__azul-native-input-text {
+    width: 200px;
+    margin-bottom: 10px;
 }
 
-#text_input_1:focus {
-    border: 2px solid  #80ff80;
+#status {
+    margin-top: 10px;
 }
 ";
 
-struct TestCrudApp {
-    text_input: TextInputState,
+struct LoginForm {
+    user_name: TextInputState,
+    password: TextInputState,
+    status: String,
 }
 
-impl Default for TestCrudApp {
+impl Default for LoginForm {
     fn default() -> Self {
         Self {
-            text_input: TextInputState::new("Hover mouse over rectangle and press keys")
+            user_name: TextInputState::default().with_max_length(32),
+            password: TextInputState::default(),
+            status: String::new(),
         }
     }
 }
 
-impl Layout for TestCrudApp {
-    fn layout(&self, info: LayoutInfo<Self>) -> Dom<Self> {
-        TextInput::new()
-        .bind(info.window, &self.text_input, &self)
-        .dom(&self.text_input)
-        .with_id("text_input_1")
+impl Layout for LoginForm {
+    fn layout(&self, mut info: LayoutInfo<Self>) -> Dom<Self> {
+
+        let user_name = TextInput::new()
+            .with_placeholder("User name")
+            .on_change(Callback(clear_status))
+            .on_submit(Callback(submit_on_enter))
+            .bind(&mut *info.window, &self.user_name, &self)
+            .dom(&self.user_name);
+
+        let password = TextInput::new()
+            .with_placeholder("Password")
+            .with_password(true)
+            .on_change(Callback(clear_status))
+            .on_submit(Callback(submit_on_enter))
+            .bind(&mut *info.window, &self.password, &self)
+            .dom(&self.password);
+
+        Dom::div().with_id("form")
+            .with_child(user_name)
+            .with_child(password)
+            .with_child(Button::with_label("Log in").dom().with_callback(On::LeftMouseUp, Callback(submit)))
+            .with_child(Dom::label(self.status.clone()).with_id("status"))
     }
 }
 
+fn clear_status(app_state: &mut AppState<LoginForm>, _event: &mut CallbackInfo<LoginForm>) -> UpdateScreen {
+    app_state.data.modify(|state| {
+        let changed = state.user_name.last_event() == Some(TextInputEvent::Changed) ||
+                      state.password.last_event() == Some(TextInputEvent::Changed);
+        if changed {
+            state.status.clear();
+        }
+    })?;
+    Redraw
+}
+
+fn submit_on_enter(app_state: &mut AppState<LoginForm>, event: &mut CallbackInfo<LoginForm>) -> UpdateScreen {
+    let submitted = app_state.data.lock().ok().map(|state| {
+        state.user_name.last_event() == Some(TextInputEvent::Submitted) ||
+        state.password.last_event() == Some(TextInputEvent::Submitted)
+    })?;
+    if submitted { submit(app_state, event) } else { DontRedraw }
+}
+
+fn submit(app_state: &mut AppState<LoginForm>, _event: &mut CallbackInfo<LoginForm>) -> UpdateScreen {
+    app_state.data.modify(|state| {
+        state.status = if state.user_name.text.is_empty() || state.password.text.is_empty() {
+            "Please enter a user name and a password".to_string()
+        } else {
+            format!("Logged in as {}", state.user_name.text)
+        };
+    })?;
+    Redraw
+}
+
 fn main() {
-    let app = App::new(TestCrudApp::default(), AppConfig::default());
+    let app = App::new(LoginForm::default(), AppConfig::default());
     app.run(Window::new(WindowCreateOptions::default(), css::override_native(CSS).unwrap()).unwrap()).unwrap();
 }
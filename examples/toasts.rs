@@ -0,0 +1,55 @@
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+extern crate azul;
+
+use std::time::Duration;
+use azul::{prelude::*, widgets::button::Button};
+
+struct ToastDemo {
+    saved_count: usize,
+    error_toast: Option<ToastId>,
+}
+
+impl Layout for ToastDemo {
+    fn layout(&self, _: LayoutInfo<Self>) -> Dom<Self> {
+        Dom::div()
+            .with_child(Button::with_label("Save").dom().with_callback(On::MouseUp, Callback(save)))
+            .with_child(Button::with_label("Fail").dom().with_callback(On::MouseUp, Callback(fail)))
+    }
+}
+
+fn save(app_state: &mut AppState<ToastDemo>, _: &mut CallbackInfo<ToastDemo>) -> UpdateScreen {
+    let saved_count = {
+        let mut data = app_state.data.lock().ok()?;
+        data.saved_count += 1;
+        data.saved_count
+    };
+    app_state.show_toast(
+        Toast::text(format!("File saved ({})", saved_count))
+            .with_severity(ToastSeverity::Success)
+    );
+    Redraw
+}
+
+fn fail(app_state: &mut AppState<ToastDemo>, _: &mut CallbackInfo<ToastDemo>) -> UpdateScreen {
+    let id = app_state.show_toast(
+        Toast::text("Could not connect to the server - click to dismiss")
+            .with_severity(ToastSeverity::Error)
+            .with_duration(Some(Duration::from_secs(10)))
+            .on_click(Callback(dismiss_error))
+    );
+    app_state.data.lock().ok()?.error_toast = Some(id);
+    Redraw
+}
+
+fn dismiss_error(app_state: &mut AppState<ToastDemo>, _: &mut CallbackInfo<ToastDemo>) -> UpdateScreen {
+    let id = app_state.data.lock().ok()?.error_toast.take()?;
+    app_state.dismiss_toast(id);
+    Redraw
+}
+
+fn main() {
+    let mut app = App::new(ToastDemo { saved_count: 0, error_toast: None }, AppConfig::default());
+    app.app_state.set_toast_corner(ToastCorner::TopRight);
+    app.run(Window::new(WindowCreateOptions::default(), css::native()).unwrap()).unwrap();
+}